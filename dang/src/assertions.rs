@@ -0,0 +1,247 @@
+//! Invariants checked after every step, e.g. "sp stays above 0x20000000" or "the PC never
+//! enters 0x0-0xff". A violated assertion stops the target like a breakpoint would.
+//!
+//! There are only ever a handful of these, but they're checked on every step of a continue, so
+//! everything is parsed up front and a check is a couple of u32 comparisons
+
+use std::fmt;
+
+use wellen::TimeTableIdx;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl CompareOp {
+    pub fn parse(op: &str) -> Option<Self> {
+        Some(match op {
+            "<" => CompareOp::Lt,
+            "<=" => CompareOp::Le,
+            ">" => CompareOp::Gt,
+            ">=" => CompareOp::Ge,
+            "==" => CompareOp::Eq,
+            "!=" => CompareOp::Ne,
+            _ => return None,
+        })
+    }
+
+    /// Whether `lhs <op> rhs` holds, comparing as unsigned
+    pub fn holds(self, lhs: u32, rhs: u32) -> bool {
+        match self {
+            CompareOp::Lt => lhs < rhs,
+            CompareOp::Le => lhs <= rhs,
+            CompareOp::Gt => lhs > rhs,
+            CompareOp::Ge => lhs >= rhs,
+            CompareOp::Eq => lhs == rhs,
+            CompareOp::Ne => lhs != rhs,
+        }
+    }
+}
+
+impl fmt::Display for CompareOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+            CompareOp::Eq => "==",
+            CompareOp::Ne => "!=",
+        };
+        write!(f, "{op}")
+    }
+}
+
+/// What has to hold after every step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    /// `x<gpr> <op> value`
+    Reg {
+        gpr: usize,
+        op: CompareOp,
+        value: u32,
+    },
+    /// The PC stays inside (or outside) `[lo, hi]`
+    PcRange { lo: u32, hi: u32, inside: bool },
+}
+
+impl Check {
+    /// The value that was checked when this fails, `None` if it holds
+    fn violation(&self, pc: u32, gpr: impl Fn(usize) -> u32) -> Option<u32> {
+        match *self {
+            Check::Reg {
+                gpr: idx,
+                op,
+                value,
+            } => {
+                let observed = gpr(idx);
+                (!op.holds(observed, value)).then_some(observed)
+            }
+            Check::PcRange { lo, hi, inside } => ((lo..=hi).contains(&pc) != inside).then_some(pc),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Check::Reg { gpr, op, value } => write!(f, "x{gpr} {op} 0x{value:x}"),
+            Check::PcRange { lo, hi, inside } => {
+                let side = if *inside { "inside" } else { "outside" };
+                write!(f, "pc {side} 0x{lo:x}-0x{hi:x}")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Assertion {
+    pub id: u32,
+    pub check: Check,
+}
+
+/// Which assertion tripped, and where
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Violation {
+    pub id: u32,
+    pub check: Check,
+    /// The register or PC value that broke the check
+    pub observed: u32,
+    pub time_idx: TimeTableIdx,
+}
+
+impl fmt::Display for Violation {
+    /// `<id> <time_idx> <observed in hex> <check>`, the monitor reply format
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {:x} {}",
+            self.id, self.time_idx, self.observed, self.check
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Assertions {
+    list: Vec<Assertion>,
+    next_id: u32,
+    /// The most recent violation, kept around for `last_assert`
+    pub last_violation: Option<Violation>,
+}
+
+impl Assertions {
+    pub fn add(&mut self, check: Check) -> Assertion {
+        self.next_id += 1;
+        let assertion = Assertion {
+            id: self.next_id,
+            check,
+        };
+        self.list.push(assertion);
+        assertion
+    }
+
+    pub fn remove(&mut self, id: u32) -> Option<Assertion> {
+        let pos = self.list.iter().position(|a| a.id == id)?;
+        Some(self.list.remove(pos))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Assertion> {
+        self.list.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    /// The first assertion that doesn't hold with the PC at `pc`. `gpr` reads a register at the
+    /// current position, and is only called for registers an assertion looks at
+    pub fn check(
+        &self,
+        pc: u32,
+        time_idx: TimeTableIdx,
+        gpr: impl Fn(usize) -> u32,
+    ) -> Option<Violation> {
+        self.list.iter().find_map(|assertion| {
+            let observed = assertion.check.violation(pc, &gpr)?;
+            Some(Violation {
+                id: assertion.id,
+                check: assertion.check,
+                observed,
+                time_idx,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reg_check() {
+        let mut assertions = Assertions::default();
+        let sp_floor = assertions.add(Check::Reg {
+            gpr: 2,
+            op: CompareOp::Gt,
+            value: 0x2000_0000,
+        });
+        let regs = |sp: u32| move |gpr: usize| if gpr == 2 { sp } else { 0 };
+
+        assert_eq!(assertions.check(0x100, 5, regs(0x2000_1000)), None);
+        let violation = assertions.check(0x100, 6, regs(0x1000_0000)).unwrap();
+        assert_eq!(violation.id, sp_floor.id);
+        assert_eq!(violation.observed, 0x1000_0000);
+        assert_eq!(violation.to_string(), "1 6 10000000 x2 > 0x20000000");
+    }
+
+    #[test]
+    fn test_pc_range_check() {
+        let mut assertions = Assertions::default();
+        assertions.add(Check::PcRange {
+            lo: 0,
+            hi: 0xff,
+            inside: false,
+        });
+        let no_regs = |_: usize| -> u32 { unreachable!("pc checks don't read registers") };
+        assert_eq!(assertions.check(0x100, 0, no_regs), None);
+        assert_eq!(assertions.check(0xff, 0, no_regs).unwrap().observed, 0xff);
+
+        let mut inside = Assertions::default();
+        inside.add(Check::PcRange {
+            lo: 0x100000,
+            hi: 0x100fff,
+            inside: true,
+        });
+        assert_eq!(inside.check(0x100000, 0, no_regs), None);
+        assert!(inside.check(0x101000, 0, no_regs).is_some());
+    }
+
+    #[test]
+    fn test_ids_are_not_reused() {
+        let mut assertions = Assertions::default();
+        let check = Check::PcRange {
+            lo: 0,
+            hi: 0,
+            inside: false,
+        };
+        let first = assertions.add(check);
+        assert_eq!(assertions.remove(first.id), Some(first));
+        assert_eq!(assertions.remove(first.id), None);
+        assert!(assertions.is_empty());
+        assert_eq!(assertions.add(check).id, first.id + 1);
+    }
+
+    #[test]
+    fn test_parse_op_round_trips() {
+        for op in ["<", "<=", ">", ">=", "==", "!="] {
+            assert_eq!(CompareOp::parse(op).unwrap().to_string(), op);
+        }
+        assert_eq!(CompareOp::parse("=>"), None);
+    }
+}
@@ -0,0 +1,111 @@
+//! A cap on how much one monitor command can print. Everything goes back hex encoded in console
+//! output packets, and a client that shows it all at once chokes on a few megabytes. Past the
+//! cap the rest is dropped and [`TRUNCATED`] goes on the end.
+//!
+//! `monitor limit=<bytes> <cmd>` raises or lowers the cap for one command
+
+use std::fmt::{self, Write};
+
+/// Bytes of output a monitor command gets unless it says otherwise
+pub const DEFAULT_LIMIT: usize = 256 * 1024;
+
+/// The line added after output that was cut short
+pub const TRUNCATED: &str = "... output truncated (use limit= or paginate)";
+
+/// Split a leading `limit=<bytes>` off `cmd`. `Err` if it's there but isn't a number
+pub fn parse_limit(cmd: &str) -> Result<(usize, &str), String> {
+    let Some(rest) = cmd.strip_prefix("limit=") else {
+        return Ok((DEFAULT_LIMIT, cmd));
+    };
+    let (limit, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+    match limit.parse::<usize>() {
+        Ok(limit) => Ok((limit, rest.trim_start())),
+        Err(_) => Err(format!("invalid limit '{limit}', expected a byte count")),
+    }
+}
+
+/// Passes writes through to `inner` until `limit` bytes have gone by, then drops the rest
+pub struct BoundedOutput<'a, W: fmt::Write> {
+    inner: &'a mut W,
+    remaining: usize,
+    truncated: bool,
+    /// Whether what got through ended a line, so the marker starts on its own
+    at_line_start: bool,
+}
+
+impl<'a, W: fmt::Write> BoundedOutput<'a, W> {
+    pub fn new(inner: &'a mut W, limit: usize) -> Self {
+        Self {
+            inner,
+            remaining: limit,
+            truncated: false,
+            at_line_start: true,
+        }
+    }
+
+    /// Add the [`TRUNCATED`] line if anything was dropped. Returns whether it was
+    pub fn finish(self) -> Result<bool, fmt::Error> {
+        if self.truncated {
+            if !self.at_line_start {
+                self.inner.write_char('\n')?;
+            }
+            writeln!(self.inner, "{TRUNCATED}")?;
+        }
+        Ok(self.truncated)
+    }
+}
+
+impl<W: Write> Write for BoundedOutput<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.truncated {
+            return Ok(());
+        }
+        let mut fits = s.len().min(self.remaining);
+        while !s.is_char_boundary(fits) {
+            fits -= 1;
+        }
+        if fits < s.len() {
+            self.truncated = true;
+        }
+        let (kept, _) = s.split_at(fits);
+        if let Some(last) = kept.chars().last() {
+            self.at_line_start = last == '\n';
+        }
+        self.remaining -= fits;
+        self.inner.write_str(kept)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_limit() {
+        assert_eq!(parse_limit("time_idx"), Ok((DEFAULT_LIMIT, "time_idx")));
+        assert_eq!(
+            parse_limit("limit=10 coverage 0 ff"),
+            Ok((10, "coverage 0 ff"))
+        );
+        assert_eq!(parse_limit("limit=10"), Ok((10, "")));
+        assert!(parse_limit("limit=lots time_idx").is_err());
+    }
+
+    #[test]
+    fn test_truncates_at_the_limit() {
+        let mut out = String::new();
+        let mut bounded = BoundedOutput::new(&mut out, 10);
+        write!(bounded, "12345").unwrap();
+        writeln!(bounded, "6789").unwrap();
+        assert!(!bounded.finish().unwrap());
+        assert_eq!(out, "123456789\n");
+
+        let mut out = String::new();
+        let mut bounded = BoundedOutput::new(&mut out, 6);
+        // cut partway through a write, and never in the middle of a character
+        write!(bounded, "abcdé").unwrap();
+        write!(bounded, "dropped").unwrap();
+        assert!(bounded.finish().unwrap());
+        assert_eq!(out, format!("abcd\n{TRUNCATED}\n"));
+    }
+}
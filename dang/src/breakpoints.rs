@@ -0,0 +1,319 @@
+//! Software breakpoints as the target keeps them. Each one counts its hits and can be told to
+//! let the next few go by with `monitor bp_ignore <addr> <n>`, so skipping the first hundred
+//! trips through a loop costs one continue instead of a hundred round trips.
+//!
+//! `monitor breakpoints` lists them one per line as `<addr> <hits> <ignore_remaining>`, the
+//! address in hex. GDB itself takes its breakpoints out at every stop and puts them back on
+//! resume, which starts both counts over, so they're for clients that leave theirs in
+//!
+//! A PC has to land on a breakpoint's address to hit it. Some cores put a 4 byte instruction's
+//! address in the trace as the address of its second halfword, so a breakpoint there never
+//! fires. `monitor set_bp_match loose` also counts a PC 2 bytes past a breakpoint on a 4 byte
+//! instruction, GDB's Z0 kind says which those are, as a hit. A kind that gets the length wrong
+//! then stops on the 2 byte instruction that really starts there, so `exact` stays the default.
+//! A PC within 2 bytes of a breakpoint that it doesn't hit is logged at debug level either way
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Z0 kind, the instruction length in bytes, for breakpoints set without one
+pub const DEFAULT_KIND: usize = 4;
+
+/// How close the PC has to get to a breakpoint to hit it, `monitor set_bp_match <exact|loose>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BpMatch {
+    /// Only the breakpoint's address
+    #[default]
+    Exact,
+    /// Also 2 bytes past a breakpoint on a 4 byte instruction
+    Loose,
+}
+
+impl fmt::Display for BpMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BpMatch::Exact => write!(f, "exact"),
+            BpMatch::Loose => write!(f, "loose"),
+        }
+    }
+}
+
+impl FromStr for BpMatch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(BpMatch::Exact),
+            "loose" => Ok(BpMatch::Loose),
+            _ => Err(format!(
+                "Unknown breakpoint match '{s}', expected exact or loose"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakpointEntry {
+    pub addr: u32,
+    /// Times the PC has arrived here, ignored arrivals included
+    pub hits: u64,
+    /// Arrivals still to go by before this one stops the target
+    pub ignore_remaining: u32,
+    /// Length of the instruction it's on, from GDB's Z0 kind. Not in the listing, so a parsed
+    /// entry has [`DEFAULT_KIND`]
+    pub kind: usize,
+}
+
+impl BreakpointEntry {
+    fn new(addr: u32) -> Self {
+        BreakpointEntry {
+            addr,
+            hits: 0,
+            ignore_remaining: 0,
+            kind: DEFAULT_KIND,
+        }
+    }
+}
+
+impl fmt::Display for BreakpointEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:x} {} {}", self.addr, self.hits, self.ignore_remaining)
+    }
+}
+
+impl FromStr for BreakpointEntry {
+    type Err = String;
+
+    /// Also takes a bare address, what the listing was before it had counts
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split_whitespace();
+        let addr = fields
+            .next()
+            .ok_or_else(|| "Empty breakpoint entry".to_string())?;
+        let addr = u32::from_str_radix(addr, 16)
+            .map_err(|e| format!("Bad breakpoint address '{addr}': {e}"))?;
+        let mut entry = BreakpointEntry::new(addr);
+        if let Some(hits) = fields.next() {
+            entry.hits = hits
+                .parse()
+                .map_err(|e| format!("Bad hit count '{hits}': {e}"))?;
+            let ignore = fields
+                .next()
+                .ok_or_else(|| format!("Breakpoint entry without an ignore count: {s}"))?;
+            entry.ignore_remaining = ignore
+                .parse()
+                .map_err(|e| format!("Bad ignore count '{ignore}': {e}"))?;
+        }
+        Ok(entry)
+    }
+}
+
+/// Every software breakpoint the target has, by address
+#[derive(Debug, Default)]
+pub struct BreakpointSet {
+    entries: BTreeMap<u32, BreakpointEntry>,
+    pub match_mode: BpMatch,
+    /// The PC [`BreakpointSet::hit`] was last asked about, so a loose match doesn't hit the
+    /// same instruction again when the trace has both of its halfwords
+    last_pc: Option<u32>,
+}
+
+impl BreakpointSet {
+    /// Add a breakpoint at `addr`. False if there already was one, which keeps its counts
+    pub fn insert(&mut self, addr: u32) -> bool {
+        self.insert_with_kind(addr, DEFAULT_KIND)
+    }
+
+    /// Add a breakpoint at `addr` on an instruction `kind` bytes long. False if there already
+    /// was one, which keeps its counts and takes the new kind
+    pub fn insert_with_kind(&mut self, addr: u32, kind: usize) -> bool {
+        if let Some(entry) = self.entries.get_mut(&addr) {
+            entry.kind = kind;
+            return false;
+        }
+        self.entries.insert(
+            addr,
+            BreakpointEntry {
+                kind,
+                ..BreakpointEntry::new(addr)
+            },
+        );
+        true
+    }
+
+    pub fn remove(&mut self, addr: &u32) -> bool {
+        self.entries.remove(addr).is_some()
+    }
+
+    pub fn contains(&self, addr: &u32) -> bool {
+        self.entries.contains_key(addr)
+    }
+
+    /// Addresses, ascending
+    pub fn iter(&self) -> impl Iterator<Item = &u32> {
+        self.entries.keys()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &BreakpointEntry> {
+        self.entries.values()
+    }
+
+    pub fn get(&self, addr: u32) -> Option<&BreakpointEntry> {
+        self.entries.get(&addr)
+    }
+
+    /// Let the next `count` arrivals at `addr` go by. False if there's no breakpoint there
+    pub fn set_ignore(&mut self, addr: u32, count: u32) -> bool {
+        match self.entries.get_mut(&addr) {
+            Some(entry) => {
+                entry.ignore_remaining = count;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Address of the breakpoint a PC at `pc` hits under [`BreakpointSet::match_mode`]
+    pub fn matching(&self, pc: u32) -> Option<u32> {
+        if self.entries.contains_key(&pc) {
+            return Some(pc);
+        }
+        let second_half = pc.checked_sub(2)?;
+        match self.entries.get(&second_half) {
+            Some(entry) if self.match_mode == BpMatch::Loose && entry.kind == 4 => {
+                Some(second_half)
+            }
+            _ => None,
+        }
+    }
+
+    /// What's logged for each breakpoint within 2 bytes of `pc` that a PC there doesn't hit
+    pub fn near_misses(&self, pc: u32) -> Vec<String> {
+        if self.matching(pc).is_some() {
+            return Vec::new();
+        }
+        self.entries
+            .range(pc.saturating_sub(2)..=pc.saturating_add(2))
+            .map(|(addr, _)| {
+                format!(
+                    "PC 0x{pc:x} is near the breakpoint at 0x{addr:x} but doesn't hit it with {} \
+                     matching",
+                    self.match_mode
+                )
+            })
+            .collect()
+    }
+
+    /// The PC arrived at `pc`. Counts the hit on the breakpoint it matches and says whether to
+    /// stop, which a breakpoint with arrivals left to ignore doesn't, using one of them up
+    /// instead
+    pub fn hit(&mut self, pc: u32) -> bool {
+        let last_pc = self.last_pc.replace(pc);
+        let addr = match self.matching(pc) {
+            // the first halfword of this instruction already hit
+            Some(addr) if addr != pc && last_pc == Some(addr) => return false,
+            Some(addr) => addr,
+            None => {
+                if log::log_enabled!(log::Level::Debug) {
+                    for near_miss in self.near_misses(pc) {
+                        log::debug!("{near_miss}");
+                    }
+                }
+                return false;
+            }
+        };
+        let Some(entry) = self.entries.get_mut(&addr) else {
+            return false;
+        };
+        entry.hits += 1;
+        if entry.ignore_remaining > 0 {
+            entry.ignore_remaining -= 1;
+            false
+        } else {
+            true
+        }
+    }
+}
+
+impl Extend<u32> for BreakpointSet {
+    fn extend<T: IntoIterator<Item = u32>>(&mut self, iter: T) {
+        for addr in iter {
+            self.insert(addr);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a BreakpointSet {
+    type Item = &'a u32;
+    type IntoIter = std::collections::btree_map::Keys<'a, u32, BreakpointEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignored_hits_still_count() {
+        let mut set = BreakpointSet::default();
+        assert!(set.insert(0x100));
+        assert!(set.set_ignore(0x100, 2));
+        assert!(!set.set_ignore(0x104, 2));
+
+        assert!(!set.hit(0x100));
+        assert!(!set.hit(0x100));
+        assert!(set.hit(0x100));
+        assert!(set.hit(0x100));
+        assert!(!set.hit(0x104));
+
+        let entry = set.get(0x100).unwrap();
+        assert_eq!(entry.hits, 4);
+        assert_eq!(entry.ignore_remaining, 0);
+
+        // re-adding leaves the counts alone
+        assert!(!set.insert(0x100));
+        assert_eq!(set.get(0x100).unwrap().hits, 4);
+    }
+
+    #[test]
+    fn test_loose_match_takes_the_second_halfword() {
+        let mut set = BreakpointSet::default();
+        set.insert_with_kind(0x100, 4);
+        set.insert_with_kind(0x200, 2);
+
+        assert!(!set.hit(0x102));
+        assert_eq!(set.get(0x100).unwrap().hits, 0);
+
+        set.match_mode = BpMatch::Loose;
+        assert!(set.hit(0x102));
+        assert_eq!(set.get(0x100).unwrap().hits, 1);
+        // a compressed instruction has no second halfword
+        assert!(!set.hit(0x202));
+        // nor does the same instruction hit twice
+        assert!(set.hit(0x100));
+        assert!(!set.hit(0x102));
+        assert_eq!(set.get(0x100).unwrap().hits, 2);
+    }
+
+    #[test]
+    fn test_entry_round_trip() {
+        let entry = BreakpointEntry {
+            addr: 0x1000a4,
+            hits: 7,
+            ignore_remaining: 3,
+            kind: DEFAULT_KIND,
+        };
+        assert_eq!(entry.to_string(), "1000a4 7 3");
+        assert_eq!("1000a4 7 3".parse::<BreakpointEntry>(), Ok(entry));
+        assert_eq!(
+            "1000a4".parse::<BreakpointEntry>(),
+            Ok(BreakpointEntry::new(0x1000a4))
+        );
+        assert!("1000a4 7".parse::<BreakpointEntry>().is_err());
+        assert!("zz".parse::<BreakpointEntry>().is_err());
+    }
+}
@@ -2,7 +2,13 @@
 //! `arm-none-eabi-cc -march=armv4t`. It's not modeled after any real-world
 //! system.
 
-use crate::runtime;
+use crate::error::StartupError;
+use crate::export::{self, ExportFormat};
+use crate::observer::{self, lock_waver};
+use crate::runtime::{self, KillPolicy, ScratchRegion};
+use crate::thread_check::ThreadCheck;
+use crate::tidx_log;
+use crate::waveloader::{self, MappingArgs, MappingScript, WaveLoadOptions};
 
 use super::runtime::Waver;
 use argh::FromArgs;
@@ -10,12 +16,37 @@ use gdbstub::common::Signal;
 use gdbstub::conn::Connection;
 use gdbstub::conn::ConnectionExt;
 use gdbstub::stub::run_blocking;
+use gdbstub::stub::run_blocking::BlockingEventLoop;
+use gdbstub::stub::state_machine::GdbStubStateMachine;
 use gdbstub::stub::DisconnectReason;
 use gdbstub::stub::GdbStub;
+use gdbstub::stub::GdbStubBuilder;
+use gdbstub::stub::GdbStubError;
 use gdbstub::stub::SingleThreadStopReason;
+use gdbstub::target::ext::base::reverse_exec::ReplayLogPosition;
 use gdbstub::target::Target;
+use std::fs::File;
+use std::io::{BufWriter, Read};
 use std::net::TcpStream;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use std::{net::TcpListener, path::PathBuf};
+use wellen::TimeTableIdx;
+
+/// Size of the packet buffer every GDB session gets, and so the `PacketSize` advertised in
+/// `qSupported`. Big enough for a `g` reply of all 33 registers with room to spare
+pub const PACKET_BUFFER_SIZE: usize = 4096;
+
+/// A stub over `connection` with a [`PACKET_BUFFER_SIZE`] packet buffer. Primary and observer
+/// sessions both go through here so they advertise the same size
+pub(crate) fn new_stub<'a, T: Target, C: Connection>(connection: C) -> GdbStub<'a, T, C> {
+    GdbStubBuilder::new(connection)
+        .packet_buffer_size(PACKET_BUFFER_SIZE)
+        .build()
+        .expect("a sized packet buffer is allocated by the builder")
+}
 
 #[derive(FromArgs, Debug, Clone)]
 /// CLI to dang Dang
@@ -26,16 +57,316 @@ struct DangArgs {
 
     #[argh(option)]
     /// path to a signal mapping file
-    mapping_path: PathBuf,
+    mapping_path: Option<PathBuf>,
+
+    #[argh(option)]
+    /// the signal mapping script's python source, instead of --mapping-path
+    mapping_inline: Option<String>,
+
+    #[argh(option)]
+    /// "-" to read the signal mapping script from stdin, instead of --mapping-path
+    mapping: Option<String>,
 
     #[argh(option)]
     /// path to a signal mapping file
     elf: PathBuf,
+
+    #[argh(option, from_str_fn(parse_mapping_arg))]
+    /// key=value parameter handed to the mapping script, can be repeated
+    mapping_arg: Vec<(String, String)>,
+
+    #[argh(option, from_str_fn(parse_scratch))]
+    /// writable scratch memory region, as <addr>:<size> in hex
+    scratch: Option<ScratchRegion>,
+
+    #[argh(option, default = "0")]
+    /// number of extra read-only observer connections to accept after the primary one
+    observers: usize,
+
+    #[argh(switch)]
+    /// check the mapping's register signals against the trace and exit instead of waiting for gdb
+    check: bool,
+
+    #[argh(option)]
+    /// write every retired instruction with its time, PC and GPRs to a .csv or .json
+    /// (newline-delimited) file and exit instead of waiting for gdb
+    export_trace: Option<PathBuf>,
+
+    #[argh(option)]
+    /// first time index --export-trace writes, the start of the trace if not given
+    from: Option<TimeTableIdx>,
+
+    #[argh(option)]
+    /// last time index --export-trace writes, the end of the trace if not given
+    to: Option<TimeTableIdx>,
+
+    #[argh(switch)]
+    /// exit once the first gdb session ends, instead of waiting for the next connection
+    once: bool,
+
+    #[argh(option, default = "true")]
+    /// whether a kill from gdb stops the stub (the default), or only starts the trace over
+    exit_on_kill: bool,
+
+    #[argh(option, from_str_fn(waveloader::parse_load_threads))]
+    /// threads to load the waveform with, 1 for the smallest memory footprint. One a core if
+    /// not given
+    wave_load_threads: Option<usize>,
+
+    #[argh(option, default = "true")]
+    /// whether scopes with no name stay in the hierarchy (the default), or their contents are
+    /// folded into the parent scope
+    wave_keep_scopes_with_empty_name: bool,
+}
+
+impl DangArgs {
+    fn wave_load(&self) -> WaveLoadOptions {
+        WaveLoadOptions {
+            threads: self.wave_load_threads,
+            keep_scopes_with_empty_name: self.wave_keep_scopes_with_empty_name,
+        }
+    }
+}
+
+/// Everything optional about starting dang, on top of the wave, mapping and ELF paths
+#[derive(Debug, Clone, Default)]
+pub struct DangOptions {
+    pub mapping_args: MappingArgs,
+    /// The mapping script's source, from `--mapping-inline` or stdin. When set, the mapping
+    /// path isn't read or checked
+    pub mapping_script: Option<MappingScript>,
+    pub scratch: Option<ScratchRegion>,
+    /// How many read-only observer connections to accept after the primary one
+    pub observers: usize,
+    /// Wait for the next primary connection when one detaches or drops, instead of returning.
+    /// The CLI does this unless it's given `--once`
+    pub reaccept: bool,
+    pub kill_policy: KillPolicy,
+    /// How the waveform is loaded, `--wave-load-threads` and `--wave-keep-scopes-with-empty-name`
+    pub wave_load: WaveLoadOptions,
+    /// The program embedding dang has its own logger: don't try to install one. Whatever level
+    /// an entry point would have set is then up to that logger, see [`tidx_log::SILENT_FILTERS`]
+    pub embedded_logging: bool,
+    /// Fail the first resume with this target error
+    #[cfg(test)]
+    pub(crate) fail_on_resume: Option<&'static str>,
+}
+
+impl DangOptions {
+    /// Install dang's own logger, `default_filter` unless `RUST_LOG` says otherwise, when it
+    /// isn't embedded
+    fn init_logging(&self, default_filter: &str) {
+        if !self.embedded_logging {
+            tidx_log::try_init(default_filter);
+        }
+    }
+
+    /// [`validate_paths`], leaving out the mapping path if there's a [`Self::mapping_script`]
+    fn validate_paths(&self, wave_path: &Path, mapping_path: &Path, elf: &Path) -> DynResult<()> {
+        let mapping_path = self.mapping_script.is_none().then_some(mapping_path);
+        validate_input_paths(wave_path, mapping_path, elf)?;
+        Ok(())
+    }
+
+    fn create_waver(
+        &self,
+        wave_path: PathBuf,
+        mapping_path: PathBuf,
+        elf: PathBuf,
+    ) -> DynResult<Waver> {
+        let load = |script: &MappingScript| {
+            Waver::new_with_mapping_script(
+                wave_path,
+                script,
+                elf,
+                &self.mapping_args,
+                &self.wave_load,
+            )
+        };
+        let emu = match &self.mapping_script {
+            Some(script) => load(script),
+            None => MappingScript::read(&mapping_path).and_then(|script| load(&script)),
+        };
+        let mut emu = emu.map_err(StartupError::flatten)?;
+        emu.mem.set_scratch(self.scratch);
+        emu.kill_policy = self.kill_policy;
+        #[cfg(test)]
+        {
+            emu.fail_on_resume = self.fail_on_resume;
+        }
+        Ok(emu)
+    }
+}
+
+/// Parse a `--scratch <addr>:<size>` flag, both in hex
+pub fn parse_scratch(value: &str) -> Result<ScratchRegion, String> {
+    let parse_hex = |v: &str| u32::from_str_radix(v.trim_start_matches("0x"), 16);
+    let err = || format!("expected <addr>:<size> in hex, got '{value}'");
+    let (start, size) = value.split_once(':').ok_or_else(err)?;
+    let start = parse_hex(start).map_err(|_| err())?;
+    let size = parse_hex(size).map_err(|_| err())?;
+    if size == 0 || start.checked_add(size - 1).is_none() {
+        return Err(format!("scratch region '{value}' is empty or wraps around"));
+    }
+    Ok(ScratchRegion { start, size })
+}
+
+/// Parse a `--mapping-arg key=value` flag
+pub fn parse_mapping_arg(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected key=value, got '{value}'")),
+    }
+}
+
+/// Work out where the mapping script comes from: `--mapping-path`, `--mapping-inline` or
+/// `--mapping -`, which reads `stdin` to the end. Exactly one has to be given. The path is a
+/// placeholder when the script isn't a file
+fn mapping_source(
+    path: Option<PathBuf>,
+    inline: Option<String>,
+    from: Option<String>,
+    stdin: impl Read,
+) -> Result<(PathBuf, Option<MappingScript>), String> {
+    let placeholder = || PathBuf::from(waveloader::INLINE_MAPPING_NAME);
+    match (path, inline, from.as_deref()) {
+        (Some(path), None, None) => Ok((path, None)),
+        (None, Some(source), None) => Ok((placeholder(), Some(MappingScript::inline(source)?))),
+        (None, None, Some("-")) => {
+            // one byte past the cap is enough to know it's too big
+            let mut source = String::new();
+            stdin
+                .take(waveloader::MAX_INLINE_MAPPING_BYTES as u64 + 1)
+                .read_to_string(&mut source)
+                .map_err(|e| format!("could not read the mapping script from stdin: {e}"))?;
+            Ok((placeholder(), Some(MappingScript::inline(source)?)))
+        }
+        (None, None, Some(other)) => Err(format!(
+            "--mapping only takes -, to read stdin, use --mapping-path {other} for a file"
+        )),
+        (None, None, None) => {
+            Err("one of --mapping-path, --mapping-inline or --mapping - is needed".to_string())
+        }
+        _ => Err(
+            "only one of --mapping-path, --mapping-inline or --mapping - can be given".to_string(),
+        ),
+    }
 }
 
 type DynResult<T> = Result<T, Box<dyn std::error::Error>>;
 
-fn wait_for_tcp(port: u16) -> DynResult<TcpStream> {
+/// Something is wrong with one of the paths we were handed on the command line
+#[derive(Debug)]
+pub struct PathError {
+    /// The command line flag the path came in on, e.g. `--wave-path`
+    pub flag: &'static str,
+    pub path: PathBuf,
+    pub problem: String,
+    pub expected: &'static str,
+}
+
+impl std::fmt::Display for PathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}: {} (expected {})",
+            self.flag,
+            self.path.display(),
+            self.problem,
+            self.expected
+        )
+    }
+}
+
+impl std::error::Error for PathError {}
+
+fn check_readable_file(
+    flag: &'static str,
+    path: &Path,
+    expected: &'static str,
+) -> Result<std::fs::File, PathError> {
+    let err = |problem: String| PathError {
+        flag,
+        path: path.to_path_buf(),
+        problem,
+        expected,
+    };
+
+    if !path.exists() {
+        return Err(err("file does not exist".to_string()));
+    }
+    if !path.is_file() {
+        return Err(err("not a regular file".to_string()));
+    }
+    std::fs::File::open(path).map_err(|e| err(format!("file cannot be read ({e})")))
+}
+
+fn check_extension(
+    flag: &'static str,
+    path: &Path,
+    extensions: &[&str],
+    expected: &'static str,
+) -> Result<(), PathError> {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    match ext {
+        Some(ext) if extensions.contains(&ext.as_str()) => Ok(()),
+        _ => Err(PathError {
+            flag,
+            path: path.to_path_buf(),
+            problem: "unexpected file extension".to_string(),
+            expected,
+        }),
+    }
+}
+
+/// Check that the waveform, mapping script and ELF all exist, can be read and look like what
+/// they claim to be. This is cheap, so do it before we spend any time loading waves
+pub fn validate_paths(wave_path: &Path, mapping_path: &Path, elf: &Path) -> Result<(), PathError> {
+    validate_input_paths(wave_path, Some(mapping_path), elf)
+}
+
+/// [`validate_paths`], skipping the mapping script when it doesn't come from a file
+fn validate_input_paths(
+    wave_path: &Path,
+    mapping_path: Option<&Path>,
+    elf: &Path,
+) -> Result<(), PathError> {
+    const WAVE_EXPECTED: &str = "a .vcd, .fst or .ghw waveform";
+    const MAPPING_EXPECTED: &str = "a python mapping script (.py)";
+    const ELF_EXPECTED: &str = "a RISC-V ELF binary";
+
+    check_readable_file("--wave-path", wave_path, WAVE_EXPECTED)?;
+    check_extension(
+        "--wave-path",
+        wave_path,
+        &["vcd", "fst", "ghw"],
+        WAVE_EXPECTED,
+    )?;
+
+    if let Some(mapping_path) = mapping_path {
+        check_readable_file("--mapping-path", mapping_path, MAPPING_EXPECTED)?;
+        check_extension("--mapping-path", mapping_path, &["py"], MAPPING_EXPECTED)?;
+    }
+
+    // ELFs don't usually have an extension, so sniff the magic instead
+    let mut elf_file = check_readable_file("--elf", elf, ELF_EXPECTED)?;
+    let mut magic = [0u8; 4];
+    if elf_file.read_exact(&mut magic).is_err() || &magic != b"\x7fELF" {
+        return Err(PathError {
+            flag: "--elf",
+            path: elf.to_path_buf(),
+            problem: "file is not an ELF".to_string(),
+            expected: ELF_EXPECTED,
+        });
+    }
+
+    Ok(())
+}
+
+fn wait_for_tcp(port: u16) -> DynResult<(TcpStream, TcpListener)> {
     let sockaddr = format!("127.0.0.1:{port}");
     log::warn!("Waiting for a GDB connection on {sockaddr:?}...");
 
@@ -46,7 +377,7 @@ fn wait_for_tcp(port: u16) -> DynResult<TcpStream> {
     let (stream, addr) = sock.accept()?;
     log::warn!("Debugger connected from {addr}");
 
-    Ok(stream)
+    Ok((stream, sock))
 }
 
 pub fn wait_for_tcp_with_port(port: u16) -> DynResult<(TcpStream, u16)> {
@@ -99,6 +430,11 @@ impl run_blocking::BlockingEventLoop for DangGdbEventLoop {
             conn.peek().map(|b| b.is_some()).unwrap_or(true)
         };
 
+        #[cfg(test)]
+        if let Some(e) = target.fail_on_resume.take() {
+            return Err(run_blocking::WaitForStopReasonError::Target(e));
+        }
+
         match target.run(poll_incoming_data) {
             runtime::RunEvent::IncomingData => {
                 let byte = conn
@@ -112,7 +448,18 @@ impl run_blocking::BlockingEventLoop for DangGdbEventLoop {
                 let stop_reason = match event {
                     runtime::Event::DoneStep => SingleThreadStopReason::DoneStep,
                     runtime::Event::Halted => SingleThreadStopReason::Terminated(Signal::SIGSTOP),
+                    // `T05` with `swbreak:`, gdbstub advertising `swbreak+` since there are
+                    // software breakpoints. Without it gdb may try to back the PC up over a trap
+                    // instruction that was never written
                     runtime::Event::Break => SingleThreadStopReason::SwBreak(()),
+                    // not a real signal, but lets the client tell this apart from a breakpoint
+                    runtime::Event::Livelock => SingleThreadStopReason::Signal(Signal::SIGXCPU),
+                    // same trick, `last_assert` has the details
+                    runtime::Event::Assertion => SingleThreadStopReason::Signal(Signal::SIGABRT),
+                    runtime::Event::TraceStart => SingleThreadStopReason::ReplayLog {
+                        tid: None,
+                        pos: ReplayLogPosition::Begin,
+                    },
                 };
 
                 Ok(run_blocking::Event::TargetStopped(stop_reason))
@@ -131,154 +478,1038 @@ impl run_blocking::BlockingEventLoop for DangGdbEventLoop {
     }
 }
 
+/// Why a primary GDB session ended early
+#[derive(Debug)]
+pub enum SessionError {
+    Stub(GdbStubError<&'static str, std::io::Error>),
+    Target(&'static str),
+    Connection(std::io::Error),
+}
+
+impl From<GdbStubError<&'static str, std::io::Error>> for SessionError {
+    fn from(e: GdbStubError<&'static str, std::io::Error>) -> Self {
+        Self::Stub(e)
+    }
+}
+
+/// Drive the primary GDB session against the shared waver.
+///
+/// This is `GdbStub::run_blocking` with the waver locked for each packet or run rather than for
+/// the whole session, so observer connections get a look in between. A continue holds the lock
+/// until the target stops, so observers wait that out
+fn run_primary(
+    shared: &Mutex<Waver>,
+    connection: Box<dyn ConnectionExt<Error = std::io::Error>>,
+) -> Result<DisconnectReason, SessionError> {
+    let mut gdb = new_stub(connection).run_state_machine(&mut *lock_waver(shared))?;
+    loop {
+        gdb = match gdb {
+            GdbStubStateMachine::Idle(mut gdb) => {
+                // block on the connection without holding the lock
+                let byte = gdb.borrow_conn().read().map_err(SessionError::Connection)?;
+                gdb.incoming_data(&mut *lock_waver(shared), byte)?
+            }
+            GdbStubStateMachine::Disconnected(gdb) => break Ok(gdb.get_reason()),
+            GdbStubStateMachine::CtrlCInterrupt(gdb) => {
+                let mut target = lock_waver(shared);
+                let stop_reason =
+                    DangGdbEventLoop::on_interrupt(&mut *target).map_err(SessionError::Target)?;
+                gdb.interrupt_handled(&mut *target, stop_reason)?
+            }
+            GdbStubStateMachine::Running(mut gdb) => {
+                let mut target = lock_waver(shared);
+                match DangGdbEventLoop::wait_for_stop_reason(&mut *target, gdb.borrow_conn()) {
+                    Ok(run_blocking::Event::TargetStopped(stop_reason)) => {
+                        gdb.report_stop(&mut *target, stop_reason)?
+                    }
+                    Ok(run_blocking::Event::IncomingData(byte)) => {
+                        gdb.incoming_data(&mut *target, byte)?
+                    }
+                    Err(run_blocking::WaitForStopReasonError::Target(e)) => {
+                        break Err(SessionError::Target(e));
+                    }
+                    Err(run_blocking::WaitForStopReasonError::Connection(e)) => {
+                        break Err(SessionError::Connection(e));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Serve primary GDB sessions on `listener`, starting with the one already accepted on `stream`.
+///
+/// A session that detaches or drops its connection sends the stub back to waiting for the next
+/// one when `reaccept` is on, with the cursor and breakpoints where that session left them.
+/// Anything else stops it: a kill the [`KillPolicy`] lets through, the target exiting, or an
+/// error. So does any end at all with `reaccept` off. The outcome of the last session is
+/// handed back, and the caller dropping `listener` stops anyone else connecting.
+///
+/// While observer slots are still open, a reconnecting client may be taken as an observer
+fn serve_primary(
+    shared: &Mutex<Waver>,
+    listener: &TcpListener,
+    mut stream: TcpStream,
+    reaccept: bool,
+) -> DynResult<SessionOutcome> {
+    loop {
+        // whoever connects finds the trace already loaded
+        lock_waver(shared).launched = false;
+        let outcome = session_outcome(run_primary(shared, Box::new(ThreadCheck::new(stream))));
+        let reconnectable = matches!(
+            outcome,
+            SessionOutcome::CleanDisconnect | SessionOutcome::ConnectionError(_)
+        );
+        if !reaccept || !reconnectable {
+            return Ok(outcome);
+        }
+        log::info!("{outcome}, waiting for the next GDB connection");
+        stream = wait_for_tcp_with_listener(listener.try_clone()?)?;
+    }
+}
+
+/// How the primary GDB session ended, handed back to whoever started dang
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionOutcome {
+    /// The client detached
+    CleanDisconnect,
+    /// The client sent a kill
+    Killed,
+    TargetExited(u8),
+    TargetTerminated(Signal),
+    /// The target gave up with a fatal error
+    TargetError(String),
+    /// Reading from or writing to the client failed
+    ConnectionError(std::io::ErrorKind),
+    /// gdbstub itself gave up, e.g. on a malformed packet. Neither the target's nor the
+    /// connection's fault
+    ProtocolError(String),
+}
+
+impl SessionOutcome {
+    /// Whether the session ended the way a session is supposed to end
+    pub fn is_clean(&self) -> bool {
+        matches!(
+            self,
+            SessionOutcome::CleanDisconnect
+                | SessionOutcome::Killed
+                | SessionOutcome::TargetExited(_)
+                | SessionOutcome::TargetTerminated(_)
+        )
+    }
+}
+
+impl std::fmt::Display for SessionOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionOutcome::CleanDisconnect => write!(f, "GDB client disconnected"),
+            SessionOutcome::Killed => write!(f, "GDB client killed the target"),
+            SessionOutcome::TargetExited(code) => write!(f, "target exited with code {code}"),
+            SessionOutcome::TargetTerminated(sig) => {
+                write!(f, "target terminated with signal {sig}")
+            }
+            SessionOutcome::TargetError(e) => write!(f, "target encountered a fatal error: {e}"),
+            SessionOutcome::ConnectionError(kind) => write!(f, "connection error: {kind}"),
+            SessionOutcome::ProtocolError(e) => write!(f, "gdbstub protocol error: {e}"),
+        }
+    }
+}
+
+/// Sort out how a session ended, logging it along the way
+fn session_outcome(result: Result<DisconnectReason, SessionError>) -> SessionOutcome {
+    let outcome = match result {
+        Ok(disconnect_reason) => match disconnect_reason {
+            DisconnectReason::Disconnect => SessionOutcome::CleanDisconnect,
+            DisconnectReason::TargetExited(code) => SessionOutcome::TargetExited(code),
+            DisconnectReason::TargetTerminated(sig) => SessionOutcome::TargetTerminated(sig),
+            DisconnectReason::Kill => SessionOutcome::Killed,
+        },
+        Err(SessionError::Stub(e)) => {
+            if e.is_target_error() {
+                SessionOutcome::TargetError(e.into_target_error().unwrap().to_string())
+            } else if e.is_connection_error() {
+                let (e, kind) = e.into_connection_error().unwrap();
+                log::debug!("connection error while {kind:?}: {e}");
+                SessionOutcome::ConnectionError(e.kind())
+            } else {
+                SessionOutcome::ProtocolError(e.to_string())
+            }
+        }
+        Err(SessionError::Target(e)) => SessionOutcome::TargetError(e.to_string()),
+        Err(SessionError::Connection(e)) => SessionOutcome::ConnectionError(e.kind()),
+    };
+    log::debug!("session ended: {outcome}");
+    outcome
+}
+
 pub fn start() -> DynResult<()> {
+    let args: DangArgs = argh::from_env();
+    let wave_load = args.wave_load();
     let DangArgs {
         wave_path,
         mapping_path,
+        mapping_inline,
+        mapping,
         elf,
-    } = argh::from_env();
+        mapping_arg,
+        scratch,
+        observers,
+        check,
+        export_trace,
+        from,
+        to,
+        once,
+        exit_on_kill,
+        ..
+    } = args;
+
+    let (mapping_path, mapping_script) =
+        mapping_source(mapping_path, mapping_inline, mapping, std::io::stdin())?;
+    let options = DangOptions {
+        mapping_args: mapping_arg.into_iter().collect(),
+        mapping_script,
+        scratch,
+        observers,
+        reaccept: !once,
+        kill_policy: if exit_on_kill {
+            KillPolicy::Exit
+        } else {
+            KillPolicy::Stay
+        },
+        wave_load,
+        ..DangOptions::default()
+    };
+    if check {
+        return check_mapping(wave_path, mapping_path, elf, options);
+    }
+    if let Some(out_path) = export_trace {
+        let range = from.unwrap_or(0)..=to.unwrap_or(TimeTableIdx::MAX);
+        return export_trace_file(wave_path, mapping_path, elf, options, &out_path, range);
+    }
+    let outcome = start_with_options(wave_path, mapping_path, elf, options)?;
+    if outcome.is_clean() {
+        Ok(())
+    } else {
+        Err(outcome.to_string().into())
+    }
+}
+
+/// `--check`: load everything, print what [`Waver::check_gprs`] makes of the mapping and exit.
+/// A mapping with a hard error doesn't load, so that comes back as the startup error
+pub fn check_mapping(
+    wave_path: PathBuf,
+    mapping_path: PathBuf,
+    elf: PathBuf,
+    options: DangOptions,
+) -> DynResult<()> {
+    options.init_logging("error");
 
-    start_with_args(wave_path, mapping_path, elf)
+    options.validate_paths(&wave_path, &mapping_path, &elf)?;
+    let emu = options.create_waver(wave_path, mapping_path, elf)?;
+
+    let report = emu.check_gprs();
+    if report.is_clean() {
+        println!("register signals look consistent with the trace");
+    }
+    for finding in &report.findings {
+        println!("{finding}");
+    }
+    Ok(())
 }
 
+/// `--export-trace`: load everything, write the retired instructions in `range` to `out_path`
+/// with [`export::export`] and exit. Nothing retiring in `range` is an error, after the summary
+pub fn export_trace_file(
+    wave_path: PathBuf,
+    mapping_path: PathBuf,
+    elf: PathBuf,
+    options: DangOptions,
+    out_path: &Path,
+    range: RangeInclusive<TimeTableIdx>,
+) -> DynResult<()> {
+    options.init_logging("error");
+
+    let format = ExportFormat::from_path(out_path).ok_or_else(|| {
+        format!(
+            "--export-trace {} should end in .csv, or .json for newline-delimited JSON",
+            out_path.display()
+        )
+    })?;
+    if range.is_empty() {
+        return Err(format!("--from {} is after --to {}", range.start(), range.end()).into());
+    }
+    options.validate_paths(&wave_path, &mapping_path, &elf)?;
+    let emu = options.create_waver(wave_path, mapping_path, elf)?;
+    // a rewrite mid-load leaves a trace that's neither the old run nor the new one
+    emu.check_wave_file().map_err(|e| format!("{e}, run the export again"))?;
+
+    let started = Instant::now();
+    let out = BufWriter::new(File::create(out_path)?);
+    let rows = export::export(&emu, range.clone(), format, out, |rows| {
+        eprintln!("{rows} rows written")
+    })?;
+    println!(
+        "wrote {rows} rows to {} in {:.2}s",
+        out_path.display(),
+        started.elapsed().as_secs_f64()
+    );
+    if rows == 0 {
+        return Err(format!(
+            "no instructions retire between time index {} and {}",
+            range.start(),
+            range.end()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+#[deprecated(note = "use start_with_options, which takes DangOptions and says how the session ended")]
 pub fn start_with_args(wave_path: PathBuf, mapping_path: PathBuf, elf: PathBuf) -> DynResult<()> {
-    start_with_args_and_port(wave_path, mapping_path, elf, 9001)
+    start_with_options(wave_path, mapping_path, elf, DangOptions::default()).map(drop)
 }
 
+#[deprecated(
+    note = "use start_with_options_and_port, which takes DangOptions and says how the session ended"
+)]
 pub fn start_with_args_and_port(
     wave_path: PathBuf,
     mapping_path: PathBuf,
     elf: PathBuf,
     port: u16,
 ) -> DynResult<()> {
-    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-        .try_init();
+    start_with_options_and_port(wave_path, mapping_path, elf, DangOptions::default(), port)
+        .map(drop)
+}
 
-    log::debug!("starting logger to stdout");
+#[deprecated(
+    note = "use start_with_options_and_listener, which takes DangOptions and says how the session ended"
+)]
+pub fn start_with_args_and_listener(
+    wave_path: PathBuf,
+    mapping_path: PathBuf,
+    elf: PathBuf,
+    listener: TcpListener,
+) -> DynResult<()> {
+    start_with_options_and_listener(wave_path, mapping_path, elf, DangOptions::default(), listener)
+        .map(drop)
+}
+
+#[deprecated(
+    note = "use start_with_options_and_listener_silent, which takes DangOptions and says how the session ended"
+)]
+pub fn start_with_args_and_listener_silent(
+    wave_path: PathBuf,
+    mapping_path: PathBuf,
+    elf: PathBuf,
+    listener: TcpListener,
+) -> DynResult<()> {
+    start_with_options_and_listener_silent(
+        wave_path,
+        mapping_path,
+        elf,
+        DangOptions::default(),
+        listener,
+    )
+    .map(drop)
+}
 
-    let mut emu = Waver::new(wave_path, mapping_path, elf).expect("Could not create wave runtime");
+/// Serve GDB on port 9001 until the sessions are over, see [`start_with_options_and_port`]
+pub fn start_with_options(
+    wave_path: PathBuf,
+    mapping_path: PathBuf,
+    elf: PathBuf,
+    options: DangOptions,
+) -> DynResult<SessionOutcome> {
+    start_with_options_and_port(wave_path, mapping_path, elf, options, 9001)
+}
 
-    let connection: Box<dyn ConnectionExt<Error = std::io::Error>> =
-        { Box::new(wait_for_tcp(port)?) };
+/// Load everything, then serve GDB on `port` until the sessions are over. Startup failures are
+/// the error, how the last session ended is the [`SessionOutcome`]
+pub fn start_with_options_and_port(
+    wave_path: PathBuf,
+    mapping_path: PathBuf,
+    elf: PathBuf,
+    options: DangOptions,
+    port: u16,
+) -> DynResult<SessionOutcome> {
+    options.init_logging("info");
 
-    let gdb = GdbStub::new(connection);
+    log::debug!("starting logger to stdout");
 
-    match gdb.run_blocking::<DangGdbEventLoop>(&mut emu) {
-        Ok(disconnect_reason) => match disconnect_reason {
-            DisconnectReason::Disconnect => {
-                log::debug!("GDB client has disconnected. Running to completion...");
-            }
-            DisconnectReason::TargetExited(code) => {
-                log::debug!("Target exited with code {code}!")
-            }
-            DisconnectReason::TargetTerminated(sig) => {
-                log::debug!("Target terminated with signal {sig}!")
-            }
-            DisconnectReason::Kill => log::debug!("GDB sent a kill command!"),
-        },
-        Err(e) => {
-            if e.is_target_error() {
-                log::debug!(
-                    "target encountered a fatal error: {}",
-                    e.into_target_error().unwrap()
-                )
-            } else if e.is_connection_error() {
-                let (e, kind) = e.into_connection_error().unwrap();
-                log::debug!("connection error: {kind:?} - {e}",)
-            } else {
-                log::debug!("gdbstub encountered a fatal error: {e}")
-            }
-        }
-    }
+    options.validate_paths(&wave_path, &mapping_path, &elf)?;
+    let emu = options.create_waver(wave_path, mapping_path, elf)?;
+
+    let (stream, listener) = wait_for_tcp(port)?;
+    let shared = Arc::new(Mutex::new(emu));
+    observer::spawn_observers(listener.try_clone()?, shared.clone(), options.observers);
+
+    let outcome = serve_primary(&shared, &listener, stream, options.reaccept)?;
 
     log::debug!("Program completed");
 
-    Ok(())
+    Ok(outcome)
 }
 
-pub fn start_with_args_and_listener(
+/// [`start_with_options_and_port`] on a listener the caller already bound
+pub fn start_with_options_and_listener(
     wave_path: PathBuf,
     mapping_path: PathBuf,
     elf: PathBuf,
+    options: DangOptions,
     listener: TcpListener,
-) -> DynResult<()> {
-    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug"))
-        .try_init();
+) -> DynResult<SessionOutcome> {
+    options.init_logging("debug");
     log::debug!("started");
 
-    let mut emu = Waver::new(wave_path, mapping_path, elf).expect("Could not create wave runtime");
+    options.validate_paths(&wave_path, &mapping_path, &elf)?;
+    let emu = options.create_waver(wave_path, mapping_path, elf)?;
 
     log::debug!("emulator made");
 
-    let connection: Box<dyn ConnectionExt<Error = std::io::Error>> =
-        { Box::new(wait_for_tcp_with_listener(listener)?) };
+    let stream = wait_for_tcp_with_listener(listener.try_clone()?)?;
+    let shared = Arc::new(Mutex::new(emu));
+    observer::spawn_observers(listener.try_clone()?, shared.clone(), options.observers);
 
     log::debug!("connection made");
 
-    let gdb = GdbStub::new(connection);
-
-    log::debug!("gdb stub made");
-
-    match gdb.run_blocking::<DangGdbEventLoop>(&mut emu) {
-        Ok(disconnect_reason) => match disconnect_reason {
-            DisconnectReason::Disconnect => {
-                log::debug!("GDB client has disconnected. Running to completion...");
-            }
-            DisconnectReason::TargetExited(code) => {
-                log::debug!("Target exited with code {code}!")
-            }
-            DisconnectReason::TargetTerminated(sig) => {
-                log::debug!("Target terminated with signal {sig}!")
-            }
-            DisconnectReason::Kill => log::debug!("GDB sent a kill command!"),
-        },
-        Err(e) => {
-            if e.is_target_error() {
-                log::debug!(
-                    "target encountered a fatal error: {}",
-                    e.into_target_error().unwrap()
-                )
-            } else if e.is_connection_error() {
-                let (e, kind) = e.into_connection_error().unwrap();
-                log::debug!("connection error: {kind:?} - {e}",)
-            } else {
-                log::debug!("gdbstub encountered a fatal error: {e}")
-            }
-        }
-    }
+    let outcome = serve_primary(&shared, &listener, stream, options.reaccept)?;
 
     log::debug!("Program completed");
 
-    Ok(())
+    Ok(outcome)
 }
 
-pub fn start_with_args_and_listener_silent(
+/// [`start_with_options_and_listener`] logging errors only
+pub fn start_with_options_and_listener_silent(
     wave_path: PathBuf,
     mapping_path: PathBuf,
     elf: PathBuf,
+    options: DangOptions,
     listener: TcpListener,
-) -> DynResult<()> {
-    // Initialize logger with error level only to suppress most output
-    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("error"))
-        .try_init();
+) -> DynResult<SessionOutcome> {
+    // Errors only, to suppress most output. An embedder's own logger gets the same from
+    // tidx_log::SILENT_FILTERS
+    options.init_logging("error");
+
+    options.validate_paths(&wave_path, &mapping_path, &elf)?;
+    let emu = options.create_waver(wave_path, mapping_path, elf)?;
+
+    let stream = wait_for_tcp_with_listener(listener.try_clone()?)?;
+    let shared = Arc::new(Mutex::new(emu));
+    observer::spawn_observers(listener.try_clone()?, shared.clone(), options.observers);
+
+    // Nothing is logged past errors, the caller decides what to say about how it ended
+    serve_primary(&shared, &listener, stream, options.reaccept)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    fn test_data(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../test_data/ibex")
+            .join(name)
+    }
+
+    fn assert_names(err: PathError, flag: &str, path: &Path) {
+        let msg = err.to_string();
+        assert_eq!(err.flag, flag);
+        assert!(msg.contains(flag), "{msg} should name {flag}");
+        assert!(
+            msg.contains(&path.display().to_string()),
+            "{msg} should name {}",
+            path.display()
+        );
+    }
 
-    let mut emu = Waver::new(wave_path, mapping_path, elf).expect("Could not create wave runtime");
+    #[test]
+    fn test_validate_paths_ok() {
+        validate_paths(
+            &test_data("sim.fst"),
+            &test_data("signal_get.py"),
+            &test_data("hello_test.elf"),
+        )
+        .expect("test data should validate");
+    }
 
-    let connection: Box<dyn ConnectionExt<Error = std::io::Error>> =
-        { Box::new(wait_for_tcp_with_listener(listener)?) };
+    #[test]
+    fn test_validate_missing_wave() {
+        let wave = test_data("nope.fst");
+        let err = validate_paths(
+            &wave,
+            &test_data("signal_get.py"),
+            &test_data("hello_test.elf"),
+        )
+        .unwrap_err();
+        assert_names(err, "--wave-path", &wave);
+    }
 
-    let gdb = GdbStub::new(connection);
+    #[test]
+    fn test_validate_missing_mapping() {
+        let mapping = test_data("nope.py");
+        let err = validate_paths(
+            &test_data("sim.fst"),
+            &mapping,
+            &test_data("hello_test.elf"),
+        )
+        .unwrap_err();
+        assert_names(err, "--mapping-path", &mapping);
+    }
 
-    match gdb.run_blocking::<DangGdbEventLoop>(&mut emu) {
-        Ok(_disconnect_reason) => {
-            // Suppressed all disconnect reason output
+    #[test]
+    fn test_validate_missing_elf() {
+        let elf = test_data("nope.elf");
+        let err =
+            validate_paths(&test_data("sim.fst"), &test_data("signal_get.py"), &elf).unwrap_err();
+        assert_names(err, "--elf", &elf);
+    }
+
+    #[test]
+    fn test_validate_implausible_files() {
+        // swapped arguments are the classic typo
+        let wave = test_data("signal_get.py");
+        let err =
+            validate_paths(&wave, &test_data("sim.fst"), &test_data("hello_test.elf")).unwrap_err();
+        assert_names(err, "--wave-path", &wave);
+
+        let elf = test_data("signal_get.py");
+        let err =
+            validate_paths(&test_data("sim.fst"), &test_data("signal_get.py"), &elf).unwrap_err();
+        assert_names(err, "--elf", &elf);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_validate_unreadable_wave() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let wave = std::env::temp_dir().join(format!("dang_unreadable_{}.fst", std::process::id()));
+        std::fs::write(&wave, b"not really a waveform").unwrap();
+        std::fs::set_permissions(&wave, std::fs::Permissions::from_mode(0o000)).unwrap();
+
+        // root can read anything, nothing to check in that case
+        if std::fs::File::open(&wave).is_err() {
+            let err = validate_paths(
+                &wave,
+                &test_data("signal_get.py"),
+                &test_data("hello_test.elf"),
+            )
+            .unwrap_err();
+            assert!(err.problem.contains("cannot be read"));
+            assert_names(err, "--wave-path", &wave);
         }
-        Err(_e) => {
-            // Suppress all error output
+
+        let _ = std::fs::remove_file(&wave);
+    }
+
+    #[test]
+    fn test_log_time_idx_follows_cursor() {
+        let waver = DangOptions::default()
+            .create_waver(
+                test_data("sim.fst"),
+                test_data("signal_get.py"),
+                test_data("hello_test.elf"),
+            )
+            .expect("test data should load");
+        let shared = Mutex::new(waver);
+        assert_eq!(tidx_log::current_time_idx(), None);
+
+        {
+            let mut target = lock_waver(&shared);
+            let start = target.cursor.time_idx;
+            assert_eq!(tidx_log::prefix(), format!("[tidx={start}]"));
+
+            target.step();
+            assert_ne!(target.cursor.time_idx, start);
+            assert_eq!(
+                tidx_log::prefix(),
+                format!("[tidx={}]", target.cursor.time_idx)
+            );
         }
+        assert_eq!(tidx_log::prefix(), "[tidx=-]");
     }
 
-    // Suppress "Program completed" message
+    fn temp_file(name: &str, contents: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("dang_{}_{name}", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
 
-    Ok(())
+    fn startup_error(wave: &Path, mapping: &Path, elf: &Path) -> StartupError {
+        let err = match DangOptions::default().create_waver(
+            wave.to_path_buf(),
+            mapping.to_path_buf(),
+            elf.to_path_buf(),
+        ) {
+            Ok(_) => panic!("startup should have failed"),
+            Err(err) => err,
+        };
+        *err.downcast::<StartupError>()
+            .expect("startup failures should be categorized")
+    }
+
+    #[test]
+    fn test_startup_error_bad_elf() {
+        // right magic, nothing behind it
+        let elf = temp_file("truncated.elf", b"\x7fELF\x01\x01");
+        let err = startup_error(&test_data("sim.fst"), &test_data("signal_get.py"), &elf);
+        let _ = std::fs::remove_file(&elf);
+
+        assert_eq!(err.kind, ErrorKind::Elf);
+        let msg = err.to_string();
+        assert!(msg.starts_with("ElfError: "), "{msg}");
+        assert!(msg.contains(&elf.display().to_string()), "{msg}");
+    }
+
+    #[test]
+    fn test_startup_error_bad_waveform() {
+        let wave = temp_file("garbage.fst", b"not really a waveform");
+        let err = startup_error(
+            &wave,
+            &test_data("signal_get.py"),
+            &test_data("hello_test.elf"),
+        );
+        let _ = std::fs::remove_file(&wave);
+
+        assert_eq!(err.kind, ErrorKind::Waveform);
+        let msg = err.to_string();
+        assert!(msg.starts_with("WaveformError: "), "{msg}");
+        assert!(msg.contains(&wave.display().to_string()), "{msg}");
+    }
+
+    #[test]
+    fn test_startup_error_missing_signal_suggests_name() {
+        // pc_wb_o with the _o dropped
+        let mapping = temp_file(
+            "typo.py",
+            br#"
+def get_gdb_signals(wave):
+    pc = wave.get_signal_from_path(
+        "TOP.ibex_simple_system.u_top.u_ibex_top.u_ibex_core.wb_stage_i.pc_wb"
+    )
+    return {"pc": pc}
+"#,
+        );
+        let wave = test_data("sim.fst");
+        let err = startup_error(&wave, &mapping, &test_data("hello_test.elf"));
+        let _ = std::fs::remove_file(&mapping);
+
+        assert_eq!(err.kind, ErrorKind::Mapping);
+        let msg = err.to_string();
+        assert!(
+            msg.starts_with("MappingError: signal 'TOP.ibex_simple_system"),
+            "{msg}"
+        );
+        assert!(msg.contains(&wave.display().to_string()), "{msg}");
+        let suggestion = msg
+            .split("(did you mean ")
+            .nth(1)
+            .expect("should suggest a name");
+        assert!(suggestion.contains("wb_stage_i.pc_wb"), "{msg}");
+    }
+
+    #[test]
+    fn test_startup_error_missing_gpr() {
+        let mapping = temp_file(
+            "no_gprs.py",
+            br#"
+def get_gdb_signals(wave):
+    pc = wave.get_signal_from_path(
+        "TOP.ibex_simple_system.u_top.u_ibex_top.u_ibex_core.wb_stage_i.pc_wb_o"
+    )
+    return {"pc": pc}
+"#,
+        );
+        let err = startup_error(
+            &test_data("sim.fst"),
+            &mapping,
+            &test_data("hello_test.elf"),
+        );
+        let _ = std::fs::remove_file(&mapping);
+
+        assert_eq!(err.kind, ErrorKind::Mapping);
+        let msg = err.to_string();
+        assert!(msg.contains("'x0'"), "{msg}");
+        assert!(msg.contains(&mapping.display().to_string()), "{msg}");
+    }
+
+    #[test]
+    fn test_stock_mapping_gprs_check_clean() {
+        let emu = DangOptions::default()
+            .create_waver(
+                test_data("sim.fst"),
+                test_data("signal_get.py"),
+                test_data("hello_test.elf"),
+            )
+            .unwrap();
+        let report = emu.check_gprs();
+        assert!(report.is_clean(), "{:?}", report.findings);
+    }
+
+    #[test]
+    fn test_export_trace_file() {
+        let out = std::env::temp_dir().join(format!("dang_export_{}.csv", std::process::id()));
+        let export = |range| {
+            export_trace_file(
+                test_data("sim.fst"),
+                test_data("signal_get.py"),
+                test_data("hello_test.elf"),
+                DangOptions::default(),
+                &out,
+                range,
+            )
+        };
+
+        export(0..=TimeTableIdx::MAX).unwrap();
+        let csv = std::fs::read_to_string(&out).unwrap();
+        assert!(csv.starts_with("time,time_idx,pc,x0,"));
+        assert!(csv.lines().count() > 1);
+
+        // the file is written either way, but with nothing in it the export fails
+        let err = export(TimeTableIdx::MAX - 1..=TimeTableIdx::MAX).unwrap_err();
+        assert!(err.to_string().contains("no instructions retire"), "{err}");
+        assert_eq!(std::fs::read_to_string(&out).unwrap().lines().count(), 1);
+        std::fs::remove_file(&out).unwrap();
+    }
+
+    #[test]
+    fn test_startup_error_swapped_x0() {
+        let mapping = test_data("signal_get_swapped.py");
+        let err = startup_error(&test_data("sim.fst"), &mapping, &test_data("hello_test.elf"));
+
+        assert_eq!(err.kind, ErrorKind::Mapping);
+        let msg = err.to_string();
+        assert!(msg.contains("the signal mapped to x0 holds"), "{msg}");
+        assert!(msg.contains("x5"), "{msg}");
+        assert!(msg.contains(&mapping.display().to_string()), "{msg}");
+    }
+
+    #[test]
+    fn test_parse_mapping_arg() {
+        assert_eq!(
+            parse_mapping_arg("prefix=TOP.soc.core0").unwrap(),
+            ("prefix".to_string(), "TOP.soc.core0".to_string())
+        );
+        assert_eq!(
+            parse_mapping_arg("core=").unwrap(),
+            ("core".to_string(), String::new())
+        );
+        assert!(parse_mapping_arg("prefix").is_err());
+        assert!(parse_mapping_arg("=1").is_err());
+    }
+
+    #[test]
+    fn test_wave_load_flags() {
+        let required = ["--wave-path", "sim.fst", "--elf", "hello_test.elf"];
+        let parse = |extra: &[&str]| {
+            let args: Vec<&str> = required.iter().chain(extra).copied().collect();
+            DangArgs::from_args(&["dang"], &args)
+        };
+
+        let args = parse(&[]).unwrap();
+        assert_eq!(args.wave_load(), WaveLoadOptions::default());
+
+        let args = parse(&[
+            "--wave-load-threads",
+            "1",
+            "--wave-keep-scopes-with-empty-name",
+            "false",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.wave_load(),
+            WaveLoadOptions {
+                threads: Some(1),
+                keep_scopes_with_empty_name: false,
+            }
+        );
+        assert_eq!(
+            parse(&["--wave-load-threads", "6"])
+                .unwrap()
+                .wave_load()
+                .threads,
+            Some(6)
+        );
+        assert!(parse(&["--wave-load-threads", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_mapping_source() {
+        let source = "def get_gdb_signals(wave):\n    return {}\n";
+        let none = || std::io::empty();
+
+        let (path, script) =
+            mapping_source(Some(test_data("signal_get.py")), None, None, none()).unwrap();
+        assert_eq!(path, test_data("signal_get.py"));
+        assert_eq!(script, None);
+
+        let (_, inline) = mapping_source(None, Some(source.into()), None, none()).unwrap();
+        let (_, stdin) = mapping_source(None, None, Some("-".into()), source.as_bytes()).unwrap();
+        assert_eq!(inline, stdin);
+        let script = stdin.unwrap();
+        assert_eq!(script.name, waveloader::INLINE_MAPPING_NAME);
+        assert_eq!(script.source, source);
+
+        let huge = vec![b'#'; waveloader::MAX_INLINE_MAPPING_BYTES + 10];
+        assert!(mapping_source(None, None, Some("-".into()), &huge[..]).is_err());
+        assert!(mapping_source(None, None, Some("map.py".into()), none()).is_err());
+        assert!(mapping_source(None, None, None, none()).is_err());
+        assert!(mapping_source(
+            Some(test_data("signal_get.py")),
+            Some(source.into()),
+            None,
+            none()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_scratch() {
+        assert_eq!(
+            parse_scratch("0x80000000:100").unwrap(),
+            ScratchRegion {
+                start: 0x8000_0000,
+                size: 0x100
+            }
+        );
+        assert!(parse_scratch("80000000").is_err());
+        assert!(parse_scratch("80000000:0").is_err());
+        assert!(parse_scratch("ffffff00:200").is_err());
+    }
+
+    /// A silent session over the fixture, with a raw connection to it. The handle gives back how
+    /// the session ended
+    fn spawn_session(
+        options: DangOptions,
+    ) -> (TcpStream, std::thread::JoinHandle<SessionOutcome>) {
+        let (port, handle) = spawn_stub(options);
+        (TcpStream::connect(("127.0.0.1", port)).unwrap(), handle)
+    }
+
+    /// The stub behind [`spawn_session`], listening on the port handed back
+    fn spawn_stub(options: DangOptions) -> (u16, std::thread::JoinHandle<SessionOutcome>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = std::thread::spawn(move || {
+            start_with_options_and_listener_silent(
+                test_data("sim.fst"),
+                test_data("signal_get.py"),
+                test_data("hello_test.elf"),
+                options,
+                listener,
+            )
+            .map_err(|e| e.to_string())
+            .expect("fixture should start")
+        });
+        (port, handle)
+    }
+
+    fn send_packet(stream: &mut TcpStream, body: &str) {
+        use std::io::Write;
+        let checksum = body.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        write!(stream, "${body}#{checksum:02x}").unwrap();
+    }
+
+    #[test]
+    fn test_detach_is_clean_disconnect() {
+        let (mut stream, handle) = spawn_session(DangOptions::default());
+        send_packet(&mut stream, "D");
+        let outcome = handle.join().unwrap();
+        assert_eq!(outcome, SessionOutcome::CleanDisconnect);
+        assert!(outcome.is_clean());
+    }
+
+    /// The body of the next reply on `stream`, skipping acks
+    fn read_reply(stream: &mut TcpStream) -> String {
+        let mut bytes = stream.bytes().map(|b| b.unwrap());
+        bytes.by_ref().find(|&b| b == b'$');
+        let body: Vec<u8> = bytes.by_ref().take_while(|&b| b != b'#').collect();
+        // the checksum
+        bytes.nth(1);
+        String::from_utf8(body).unwrap()
+    }
+
+    #[test]
+    fn test_kill_stops_the_stub() {
+        let options = DangOptions {
+            reaccept: true,
+            ..Default::default()
+        };
+        let (mut stream, handle) = spawn_session(options);
+        send_packet(&mut stream, "!");
+        assert_eq!(read_reply(&mut stream), "OK");
+        send_packet(&mut stream, "vKill;1");
+        assert_eq!(read_reply(&mut stream), "OK");
+        // however the client leaves after that, nothing waits for it to come back
+        drop(stream);
+        assert_eq!(handle.join().unwrap(), SessionOutcome::Killed);
+    }
+
+    #[test]
+    fn test_kill_under_stay_keeps_the_session() {
+        let options = DangOptions {
+            kill_policy: KillPolicy::Stay,
+            ..Default::default()
+        };
+        let (mut stream, handle) = spawn_session(options);
+        send_packet(&mut stream, "!");
+        assert_eq!(read_reply(&mut stream), "OK");
+        send_packet(&mut stream, "vRun;");
+        read_reply(&mut stream);
+        send_packet(&mut stream, "qAttached:1");
+        assert_eq!(read_reply(&mut stream), "0");
+        send_packet(&mut stream, "vKill;1");
+        assert_eq!(read_reply(&mut stream), "OK");
+        // still there, waiting for the next run, and nothing's running now
+        send_packet(&mut stream, "qAttached:1");
+        assert_eq!(read_reply(&mut stream), "1");
+        send_packet(&mut stream, "D");
+        assert!(handle.join().unwrap().is_clean());
+    }
+
+    #[test]
+    fn test_reconnect_after_disconnect() {
+        let options = DangOptions {
+            reaccept: true,
+            ..Default::default()
+        };
+        let (port, handle) = spawn_stub(options);
+        let mut first = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        send_packet(&mut first, "!");
+        assert_eq!(read_reply(&mut first), "OK");
+        send_packet(&mut first, "vRun;");
+        read_reply(&mut first);
+        send_packet(&mut first, "qAttached:1");
+        assert_eq!(read_reply(&mut first), "0");
+        // dropped without detaching
+        drop(first);
+
+        let mut second = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        // a new client didn't start the program itself
+        send_packet(&mut second, "qAttached:1");
+        assert_eq!(read_reply(&mut second), "1");
+        send_packet(&mut second, "D");
+        assert_eq!(read_reply(&mut second), "OK");
+
+        let mut third = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        send_packet(&mut third, "k");
+        assert_eq!(handle.join().unwrap(), SessionOutcome::Killed);
+    }
+
+    #[test]
+    fn test_once_ends_on_disconnect() {
+        let (port, handle) = spawn_stub(DangOptions::default());
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        send_packet(&mut stream, "D");
+        assert_eq!(handle.join().unwrap(), SessionOutcome::CleanDisconnect);
+        assert!(TcpStream::connect(("127.0.0.1", port)).is_err());
+    }
+
+    /// The PC out of a `g` reply, the 33rd register after x0 to x31
+    fn pc_from_registers(registers: &str) -> u32 {
+        let hex = &registers[32 * 8..33 * 8];
+        let bytes: Vec<u8> = (0..4)
+            .map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap())
+            .collect();
+        u32::from_le_bytes(bytes.try_into().unwrap())
+    }
+
+    #[test]
+    fn test_reverse_step_goes_back_an_instruction() {
+        let (mut stream, handle) = spawn_session(DangOptions::default());
+        let pc = |stream: &mut TcpStream| {
+            send_packet(stream, "g");
+            pc_from_registers(&read_reply(stream))
+        };
+        send_packet(&mut stream, "s");
+        read_reply(&mut stream);
+        let before = pc(&mut stream);
+        send_packet(&mut stream, "s");
+        read_reply(&mut stream);
+        assert_ne!(pc(&mut stream), before);
+
+        send_packet(&mut stream, "bs");
+        read_reply(&mut stream);
+        assert_eq!(pc(&mut stream), before);
+
+        send_packet(&mut stream, "D");
+        assert!(handle.join().unwrap().is_clean());
+    }
+
+    #[test]
+    fn test_h_for_a_missing_thread_is_an_error() {
+        let (mut stream, handle) = spawn_session(DangOptions::default());
+        for packet in ["Hg2", "Hc2", "Hgp1.2"] {
+            send_packet(&mut stream, packet);
+            assert_eq!(read_reply(&mut stream), "E01", "{packet}");
+        }
+        for packet in ["Hg1", "Hc1", "Hg0", "Hc-1"] {
+            send_packet(&mut stream, packet);
+            assert_eq!(read_reply(&mut stream), "OK", "{packet}");
+        }
+        // and without acks the same
+        send_packet(&mut stream, "QStartNoAckMode");
+        assert_eq!(read_reply(&mut stream), "OK");
+        send_packet(&mut stream, "Hg2");
+        assert_eq!(read_reply(&mut stream), "E01");
+        send_packet(&mut stream, "Hg1");
+        assert_eq!(read_reply(&mut stream), "OK");
+
+        send_packet(&mut stream, "D");
+        assert!(handle.join().unwrap().is_clean());
+    }
+
+    #[test]
+    fn test_reverse_continue_returns_to_breakpoint() {
+        let (mut stream, handle) = spawn_session(DangOptions::default());
+        let pc = |stream: &mut TcpStream| {
+            send_packet(stream, "g");
+            pc_from_registers(&read_reply(stream))
+        };
+        // putchar
+        send_packet(&mut stream, "Z0,100084,4");
+        assert_eq!(read_reply(&mut stream), "OK");
+        send_packet(&mut stream, "c");
+        read_reply(&mut stream);
+        assert_eq!(pc(&mut stream), 0x100084);
+        send_packet(&mut stream, "s");
+        read_reply(&mut stream);
+        send_packet(&mut stream, "s");
+        read_reply(&mut stream);
+        assert_ne!(pc(&mut stream), 0x100084);
+
+        send_packet(&mut stream, "bc");
+        read_reply(&mut stream);
+        assert_eq!(pc(&mut stream), 0x100084);
+
+        // nothing before the first hit, so back to the start of the trace
+        send_packet(&mut stream, "bc");
+        assert!(read_reply(&mut stream).contains("replaylog:begin"));
+
+        send_packet(&mut stream, "D");
+        assert!(handle.join().unwrap().is_clean());
+    }
+
+    #[test]
+    fn test_target_error_keeps_message() {
+        let options = DangOptions {
+            fail_on_resume: Some("injected failure"),
+            ..Default::default()
+        };
+        let (mut stream, handle) = spawn_session(options);
+        send_packet(&mut stream, "c");
+        let outcome = handle.join().unwrap();
+        assert_eq!(
+            outcome,
+            SessionOutcome::TargetError("injected failure".to_string())
+        );
+        assert!(!outcome.is_clean());
+        assert_eq!(
+            outcome.to_string(),
+            "target encountered a fatal error: injected failure"
+        );
+    }
 }
@@ -1,28 +1,51 @@
-use anyhow::Context;
 use num_bigint::BigUint;
 use wellen::SignalValue;
 
+/// The value of a bit vector, `None` if any bit is x, z or another non-binary state, or if it
+/// isn't a bit vector at all (reals, strings)
+fn defined_value(signal_value: &SignalValue<'_>) -> Option<(BigUint, u32)> {
+    match signal_value {
+        SignalValue::Binary(val, bits) => Some((BigUint::from_bytes_be(val), *bits)),
+        SignalValue::FourValue(_, bits) | SignalValue::NineValue(_, bits) => {
+            // only a 4 or 9 state encoding, the value may still be all 0s and 1s
+            let bit_string = signal_value.to_bit_string()?;
+            if !bit_string.bytes().all(|b| b == b'0' || b == b'1') {
+                return None;
+            }
+            Some((BigUint::parse_bytes(bit_string.as_bytes(), 2)?, *bits))
+        }
+        // reals, strings, anything else that isn't made of bits
+        _ => None,
+    }
+}
+
 impl Mappable for BigUint {
     fn try_from_signal(signal_value: SignalValue<'_>) -> Option<Self> {
-        match signal_value {
-            SignalValue::Binary(val, _bits) => Some(BigUint::from_bytes_be(val)),
-            _ => None,
-        }
+        defined_value(&signal_value).map(|(value, _bits)| value)
+    }
+
+    fn sentinel() -> Self {
+        BigUint::default()
     }
 }
 
 /// Trait to easily convert between existing data types
 pub trait Mappable: Sized + PartialEq {
+    /// The value if it's a fully defined bit vector, `None` for x/z bits, reals and strings.
+    /// Values wider than `Self` keep their low bits
     fn try_from_signal(signal_value: SignalValue<'_>) -> Option<Self>;
+
+    /// What [`Mappable::from_signal`] gives for a value that doesn't convert: all ones for the
+    /// fixed width types, which no real PC or pointer looks like, and zero for `BigUint`
+    fn sentinel() -> Self;
+
+    /// [`Mappable::try_from_signal`], with [`Mappable::sentinel`] for values that don't convert.
+    /// Use the fallible form wherever a missing value means something
     fn from_signal(signal_value: SignalValue<'_>) -> Self {
-        Self::try_from_signal(signal_value)
-            .with_context(|| {
-                format!(
-                    "Failed to convert signal value to {:?}",
-                    signal_value.to_bit_string()
-                )
-            })
-            .expect("Failed to convert signal value to Mappable")
+        Self::try_from_signal(signal_value).unwrap_or_else(|| {
+            log::warn!("Signal value {signal_value:?} has no defined value, using a sentinel");
+            Self::sentinel()
+        })
     }
 
     fn bit_width(&self) -> u32 {
@@ -34,17 +57,24 @@ macro_rules! impl_mappable_basic {
     ($t:ty) => {
         impl Mappable for $t {
             fn try_from_signal(signal_value: SignalValue<'_>) -> Option<Self> {
-                match signal_value {
-                    SignalValue::Binary(val, bits) => {
-                        if bits <= std::mem::size_of::<Self>() as u32 * 8 {
-                            let val = val.try_into().ok().map(|val| <$t>::from_be_bytes(val));
-                            val
-                        } else {
-                            None
-                        }
-                    }
-                    _ => None,
+                const WIDTH: usize = std::mem::size_of::<$t>();
+                let (value, bits) = defined_value(&signal_value)?;
+                if bits as usize > WIDTH * 8 {
+                    log::debug!(
+                        "Truncating a {bits} bit value to {} bits for {}",
+                        WIDTH * 8,
+                        stringify!($t)
+                    );
                 }
+                // the low 64 bits, then the low bytes of those, same bits reinterpreted
+                let low = value.iter_u64_digits().next().unwrap_or(0).to_be_bytes();
+                let mut bytes = [0u8; WIDTH];
+                bytes.copy_from_slice(&low[low.len() - WIDTH..]);
+                Some(<$t>::from_be_bytes(bytes))
+            }
+
+            fn sentinel() -> Self {
+                <$t>::from_be_bytes([0xff; std::mem::size_of::<$t>()])
             }
         }
     };
@@ -61,3 +91,105 @@ impl_mappable_basic!(i64);
 //NOTE: we should also cover reals here
 impl_mappable_basic!(f32);
 impl_mappable_basic!(f64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// xorshift, enough randomness to cover widths and bit patterns without a dependency
+    struct Rng(u64);
+
+    impl Rng {
+        fn next_u64(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next_u64() % n
+        }
+    }
+
+    /// `bit_string` packed the way wellen stores it, `bits_per_state` bits per character,
+    /// right aligned in big-endian bytes
+    fn pack(bit_string: &str, bits_per_state: usize) -> Vec<u8> {
+        let mut packed = BigUint::default();
+        for c in bit_string.chars() {
+            let state = match c {
+                '0' => 0u8,
+                '1' => 1,
+                'x' => 2,
+                'z' => 3,
+                other => panic!("no encoding for {other}"),
+            };
+            packed = (packed << bits_per_state) + state;
+        }
+        let len = (bit_string.len() * bits_per_state).div_ceil(8);
+        let bytes = packed.to_bytes_be();
+        let mut out = vec![0u8; len - bytes.len().min(len)];
+        out.extend(bytes);
+        out
+    }
+
+    #[test]
+    fn test_random_bit_strings() {
+        let mut rng = Rng(0x9e3779b97f4a7c15);
+        for _ in 0..2000 {
+            let width = 1 + rng.below(128) as usize;
+            let mut bit_string: String = (0..width)
+                .map(|_| if rng.below(2) == 0 { '0' } else { '1' })
+                .collect();
+            let undefined = rng.below(8) == 0;
+            if undefined {
+                let at = rng.below(width as u64) as usize;
+                let state = if rng.below(2) == 0 { "x" } else { "z" };
+                bit_string.replace_range(at..at + 1, state);
+            }
+
+            let four = pack(&bit_string, 2);
+            let four_state = SignalValue::FourValue(&four, width as u32);
+            if undefined {
+                assert_eq!(u32::try_from_signal(four_state), None, "{bit_string}");
+                assert_eq!(u64::try_from_signal(four_state), None, "{bit_string}");
+                assert_eq!(BigUint::try_from_signal(four_state), None, "{bit_string}");
+                assert_eq!(u32::from_signal(four_state), u32::sentinel());
+                continue;
+            }
+
+            let expected = BigUint::parse_bytes(bit_string.as_bytes(), 2).unwrap();
+            let low = expected.iter_u64_digits().next().unwrap_or(0);
+            let two = pack(&bit_string, 1);
+            let two_state = SignalValue::Binary(&two, width as u32);
+            for value in [two_state, four_state] {
+                assert_eq!(BigUint::try_from_signal(value), Some(expected.clone()));
+                assert_eq!(u64::try_from_signal(value), Some(low), "{bit_string}");
+                assert_eq!(
+                    u32::try_from_signal(value),
+                    Some(low as u32),
+                    "{bit_string}"
+                );
+                assert_eq!(u8::try_from_signal(value), Some(low as u8), "{bit_string}");
+                i16::try_from_signal(value).unwrap();
+                f64::try_from_signal(value).unwrap();
+            }
+            if width <= 32 {
+                // narrow enough to round trip
+                let value = u32::from_signal(two_state);
+                assert_eq!(format!("{value:0width$b}"), bit_string);
+            }
+        }
+    }
+
+    #[test]
+    fn test_non_bit_vectors() {
+        for value in [SignalValue::Real(1.5), SignalValue::String("idle")] {
+            assert_eq!(u32::try_from_signal(value), None);
+            assert_eq!(BigUint::try_from_signal(value), None);
+            assert_eq!(u32::from_signal(value), 0xffff_ffff);
+            assert!(f32::from_signal(value).is_nan());
+            assert_eq!(BigUint::from_signal(value), BigUint::default());
+        }
+    }
+}
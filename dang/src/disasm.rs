@@ -0,0 +1,131 @@
+use raki::{Decode, Isa};
+
+use crate::runtime::DummyMem;
+
+/// A single line of target-side disassembly
+pub struct DisasmLine {
+    pub addr: u32,
+    pub bytes: Vec<u8>,
+    /// `None` if the bytes at `addr` didn't decode to anything we know about
+    pub text: Option<String>,
+}
+
+/// Render raw instruction bytes as a fixed width hex column, e.g. `13 05 05 00`.
+/// Compressed instructions are padded so the mnemonic column lines up.
+pub fn format_bytes_column(bytes: &[u8]) -> String {
+    let hex: Vec<String> = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    format!("{:<11}", hex.join(" "))
+}
+
+impl std::fmt::Display for DisasmLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bytes = format_bytes_column(&self.bytes);
+        match &self.text {
+            Some(text) => write!(f, "0x{:x}  {bytes}   {text}", self.addr),
+            None => {
+                let half = u16::from_le_bytes([self.bytes[0], self.bytes[1]]);
+                write!(
+                    f,
+                    "0x{:x}  {bytes}   .word 0x{half:04x}  <undecodable>",
+                    self.addr
+                )
+            }
+        }
+    }
+}
+
+/// Most instructions one [`disassemble`] call gives back, asking for more gets this many
+pub const MAX_COUNT: usize = 256;
+
+/// Disassemble `count` instructions starting at `start`, at most [`MAX_COUNT`].
+///
+/// The instruction length comes from the low two bits of the first halfword, as per the
+/// RISC-V spec. Anything that fails to decode is emitted as a 2 byte placeholder and we move on
+pub fn disassemble(mem: &DummyMem, start: u32, count: usize) -> Vec<DisasmLine> {
+    let count = count.min(MAX_COUNT);
+    let mut rv = Vec::with_capacity(count);
+    let mut addr = start;
+    for _ in 0..count {
+        let lo = [mem.r8(addr), mem.r8(addr.wrapping_add(1))];
+        let is_compressed = lo[0] & 0b11 != 0b11;
+
+        let decoded = if is_compressed {
+            u16::from_le_bytes(lo).decode(Isa::Rv32).ok()
+        } else {
+            mem.r32(addr).decode(Isa::Rv32).ok()
+        };
+
+        let line = match decoded {
+            Some(inst) => {
+                let len = if is_compressed { 2 } else { 4 };
+                DisasmLine {
+                    addr,
+                    bytes: (0..len).map(|i| mem.r8(addr.wrapping_add(i))).collect(),
+                    text: Some(inst.to_string()),
+                }
+            }
+            None => DisasmLine {
+                addr,
+                bytes: lo.to_vec(),
+                text: None,
+            },
+        };
+        addr = addr.wrapping_add(line.bytes.len() as u32);
+        rv.push(line);
+    }
+    rv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mem_with(base: u32, bytes: &[u8]) -> DummyMem {
+        let mut mem = DummyMem::default();
+        for (i, b) in bytes.iter().enumerate() {
+            mem.w8(base + i as u32, *b);
+        }
+        mem
+    }
+
+    #[test]
+    fn test_bytes_column_width() {
+        assert_eq!(
+            format_bytes_column(&[0x13, 0x05, 0x05, 0x00]),
+            "13 05 05 00"
+        );
+        assert_eq!(format_bytes_column(&[0x01, 0x00]), "01 00      ");
+    }
+
+    #[test]
+    fn test_disassemble_mixed_lengths() {
+        // addi a0, a0, 0 followed by c.nop
+        let mem = mem_with(0x100200, &[0x13, 0x05, 0x05, 0x00, 0x01, 0x00]);
+        let lines = disassemble(&mem, 0x100200, 2);
+        assert_eq!(lines[0].bytes, vec![0x13, 0x05, 0x05, 0x00]);
+        assert!(lines[0].to_string().starts_with("0x100200  13 05 05 00   "));
+        assert_eq!(lines[1].addr, 0x100204);
+        assert_eq!(lines[1].bytes, vec![0x01, 0x00]);
+    }
+
+    #[test]
+    fn test_disassemble_undecodable_keeps_going() {
+        // 0x7f is a reserved major opcode, then addi a0, a0, 0
+        let mem = mem_with(0x0, &[0xff, 0xff, 0x13, 0x05, 0x05, 0x00]);
+        let lines = disassemble(&mem, 0x0, 2);
+        assert!(lines[0].text.is_none());
+        assert_eq!(
+            lines[0].to_string(),
+            "0x0  ff ff         .word 0xffff  <undecodable>"
+        );
+        assert_eq!(lines[1].addr, 0x2);
+        assert!(lines[1].text.is_some());
+    }
+
+    #[test]
+    fn test_disassemble_count_is_capped() {
+        let mem = DummyMem::default();
+        assert_eq!(disassemble(&mem, 0x0, usize::MAX).len(), MAX_COUNT);
+        assert_eq!(disassemble(&mem, 0x0, 3).len(), 3);
+    }
+}
@@ -0,0 +1,88 @@
+//! Error kinds for everything that can go wrong while bringing up a [`Waver`](crate::runtime::Waver)
+
+use std::fmt;
+
+/// Which of the three inputs a startup failure is blamed on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Waveform,
+    Mapping,
+    Elf,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Waveform => write!(f, "WaveformError"),
+            Self::Mapping => write!(f, "MappingError"),
+            Self::Elf => write!(f, "ElfError"),
+        }
+    }
+}
+
+/// A startup failure, tagged with the input it came from.
+///
+/// Attached as anyhow context on the way up, so `downcast_ref::<StartupError>()` on the final
+/// error tells callers how to categorize it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StartupError {
+    pub kind: ErrorKind,
+    pub detail: String,
+}
+
+impl StartupError {
+    pub fn waveform(detail: impl Into<String>) -> Self {
+        Self {
+            kind: ErrorKind::Waveform,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn mapping(detail: impl Into<String>) -> Self {
+        Self {
+            kind: ErrorKind::Mapping,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn elf(detail: impl Into<String>) -> Self {
+        Self {
+            kind: ErrorKind::Elf,
+            detail: detail.into(),
+        }
+    }
+
+    /// anyhow context doesn't survive being boxed into a `dyn Error`, so fold the rest of the
+    /// chain into the detail of the tagged layer. Untagged errors are boxed as they are
+    pub fn flatten(err: anyhow::Error) -> Box<dyn std::error::Error> {
+        let Some(tagged) = err.downcast_ref::<StartupError>() else {
+            return err.into();
+        };
+        // Context layers show up in the chain as anyhow's wrapper type, so match on the text
+        let tagged_text = tagged.to_string();
+        let detail = err
+            .chain()
+            .map(|layer| {
+                let text = layer.to_string();
+                if text == tagged_text {
+                    tagged.detail.clone()
+                } else {
+                    text
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(": ");
+        Box::new(StartupError {
+            kind: tagged.kind,
+            detail,
+        })
+    }
+}
+
+impl fmt::Display for StartupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.detail)
+    }
+}
+
+impl std::error::Error for StartupError {}
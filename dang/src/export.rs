@@ -0,0 +1,263 @@
+//! `--export-trace`: the whole architectural trace as data, for analysis outside of a debugger.
+//!
+//! One row per retired instruction, an entry of the PC's signal that changes its value, with the
+//! simulation time, time index, PC and all 32 GPRs there. Rows are read off the signals and
+//! written one at a time, so memory use doesn't grow with the length of the trace. CSV gets a
+//! header and hex values, JSON is newline-delimited with one object a row and plain numbers, so
+//! either can be streamed by whatever reads it. x or z bits come out as `x` and `null`
+
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use wellen::TimeTableIdx;
+
+use crate::convert::Mappable;
+use crate::runtime::Waver;
+use crate::waveloader::WellenSignalExt;
+
+/// Rows between progress reports
+pub const PROGRESS_EVERY: u64 = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    /// Newline-delimited JSON
+    Ndjson,
+}
+
+impl ExportFormat {
+    /// Going by the output's extension, `.csv`, or `.json` or `.ndjson`
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "csv" => Some(ExportFormat::Csv),
+            "json" | "ndjson" => Some(ExportFormat::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+/// One retired instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceRow {
+    /// Simulation time at `time_idx`, in the waveform's timescale
+    pub time: u64,
+    pub time_idx: TimeTableIdx,
+    pub pc: u32,
+    /// `None` where the trace has x or z bits
+    pub gprs: [Option<u32>; 32],
+}
+
+impl TraceRow {
+    fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "{},{},0x{:08x}", self.time, self.time_idx, self.pc)?;
+        for value in self.gprs {
+            match value {
+                Some(value) => write!(out, ",0x{value:08x}")?,
+                None => write!(out, ",x")?,
+            }
+        }
+        writeln!(out)
+    }
+
+    fn write_json(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(
+            out,
+            "{{\"time\":{},\"time_idx\":{},\"pc\":{},\"gprs\":[",
+            self.time, self.time_idx, self.pc
+        )?;
+        for (gpr, value) in self.gprs.iter().enumerate() {
+            if gpr > 0 {
+                write!(out, ",")?;
+            }
+            match value {
+                Some(value) => write!(out, "{value}")?,
+                None => write!(out, "null")?,
+            }
+        }
+        writeln!(out, "]}}")
+    }
+}
+
+fn write_csv_header(out: &mut impl Write) -> io::Result<()> {
+    write!(out, "time,time_idx,pc")?;
+    for gpr in 0..32 {
+        write!(out, ",x{gpr}")?;
+    }
+    writeln!(out)
+}
+
+/// The instructions retired at time indices in `range`, oldest first. Entries that rewrite the
+/// PC with the value it already had are stalls and left out, like [`Waver::pc_trace`] does, as
+/// are ones where the PC has x or z bits
+pub fn retired_rows(
+    waver: &Waver,
+    range: RangeInclusive<TimeTableIdx>,
+) -> impl Iterator<Item = TraceRow> + '_ {
+    let pc = &waver.waves.pc;
+    let changes = pc.time_indices();
+    let value_at = move |pos: usize| u32::try_from_signal(pc.get_val(changes[pos]));
+    let first = changes.partition_point(|&idx| idx < *range.start());
+    let end = *range.end();
+
+    (first..changes.len())
+        .take_while(move |&pos| changes[pos] <= end)
+        .filter(move |&pos| pos == 0 || value_at(pos) != value_at(pos - 1))
+        .filter_map(move |pos| {
+            let time_idx = changes[pos];
+            Some(TraceRow {
+                time: waver.cursor.all_times[time_idx as usize],
+                time_idx,
+                pc: value_at(pos)?,
+                gprs: std::array::from_fn(|gpr| {
+                    waver
+                        .waves
+                        .gprs
+                        .get(gpr)?
+                        .try_get_val(time_idx)
+                        .and_then(u32::try_from_signal)
+                }),
+            })
+        })
+}
+
+/// Write the rows of [`retired_rows`] to `out` as `format`, calling `progress` with the count
+/// so far every [`PROGRESS_EVERY`] rows. How many rows there were
+pub fn export(
+    waver: &Waver,
+    range: RangeInclusive<TimeTableIdx>,
+    format: ExportFormat,
+    mut out: impl Write,
+    mut progress: impl FnMut(u64),
+) -> io::Result<u64> {
+    if format == ExportFormat::Csv {
+        write_csv_header(&mut out)?;
+    }
+    let mut rows = 0;
+    for row in retired_rows(waver, range) {
+        match format {
+            ExportFormat::Csv => row.write_csv(&mut out)?,
+            ExportFormat::Ndjson => row.write_json(&mut out)?,
+        }
+        rows += 1;
+        if rows % PROGRESS_EVERY == 0 {
+            progress(rows);
+        }
+    }
+    out.flush()?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::tests::fixture_waver;
+    use gdbstub::target::ext::base::singlethread::SingleThreadBase;
+    use gdbstub_arch::riscv::reg::RiscvCoreRegs;
+
+    /// A range a few hundred entries into the trace, past the startup code
+    fn small_range(waver: &Waver) -> RangeInclusive<TimeTableIdx> {
+        let changes = &waver.cursor.all_changes;
+        changes[200]..=changes[400]
+    }
+
+    /// What GDB would see there, moving the cursor with `goto_idx` and reading `g`
+    fn stub_registers(waver: &mut Waver, time_idx: TimeTableIdx) -> (u32, [u32; 32]) {
+        let mut out = String::new();
+        waver
+            .monitor_cmd(&format!("goto_idx {time_idx}"), &mut out, |_| None)
+            .unwrap();
+        assert_eq!(out.trim(), time_idx.to_string());
+        let mut regs = RiscvCoreRegs::<u32>::default();
+        assert!(waver.read_registers(&mut regs).is_ok());
+        (regs.pc, regs.x)
+    }
+
+    #[test]
+    fn test_csv_rows_match_the_stub() {
+        let mut waver = fixture_waver();
+        let range = small_range(&waver);
+
+        let mut csv = Vec::new();
+        let rows = export(&waver, range.clone(), ExportFormat::Csv, &mut csv, |_| {}).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(lines[0].starts_with("time,time_idx,pc,x0,x1,"));
+        assert!(lines[0].ends_with(",x31"));
+        assert_eq!(lines.len() as u64, rows + 1);
+
+        // one row for each change of the PC's value in the range
+        let pc = &waver.waves.pc;
+        let changes = pc.time_indices();
+        let retired = (1..changes.len())
+            .filter(|&pos| range.contains(&changes[pos]))
+            .filter(|&pos| {
+                let value = |pos: usize| u32::try_from_signal(pc.get_val(changes[pos]));
+                value(pos).is_some() && value(pos) != value(pos - 1)
+            })
+            .count();
+        assert!(retired > 10);
+        assert_eq!(rows, retired as u64);
+
+        for line in [lines[1], lines[lines.len() / 2]] {
+            let fields: Vec<&str> = line.split(',').collect();
+            assert_eq!(fields.len(), 3 + 32);
+            let time_idx: TimeTableIdx = fields[1].parse().unwrap();
+            let (pc, gprs) = stub_registers(&mut waver, time_idx);
+            assert_eq!(fields[0], waver.cursor_time().to_string());
+            assert_eq!(fields[2], format!("0x{pc:08x}"));
+            for (gpr, value) in gprs.iter().enumerate() {
+                assert_eq!(
+                    fields[3 + gpr],
+                    format!("0x{value:08x}"),
+                    "x{gpr} in {line}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let waver = fixture_waver();
+        let range = small_range(&waver);
+        let first = retired_rows(&waver, range.clone()).next().unwrap();
+
+        let mut json = Vec::new();
+        let rows = export(&waver, range, ExportFormat::Ndjson, &mut json, |_| {}).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert_eq!(json.lines().count() as u64, rows);
+        let line = json.lines().next().unwrap();
+        assert!(line.starts_with(&format!(
+            "{{\"time\":{},\"time_idx\":{},\"pc\":{},\"gprs\":[0,",
+            first.time, first.time_idx, first.pc
+        )));
+        assert!(line.ends_with("]}"));
+        assert_eq!(line.matches(',').count(), 3 + 31);
+    }
+
+    #[test]
+    fn test_empty_range() {
+        let waver = fixture_waver();
+        let past = waver.cursor.all_times.len() as TimeTableIdx;
+        let mut out = Vec::new();
+        let rows = export(
+            &waver,
+            past..=past + 10,
+            ExportFormat::Ndjson,
+            &mut out,
+            |_| {},
+        );
+        assert_eq!(rows.unwrap(), 0);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        let format = |path: &str| ExportFormat::from_path(Path::new(path));
+        assert_eq!(format("trace.csv"), Some(ExportFormat::Csv));
+        assert_eq!(format("out/trace.json"), Some(ExportFormat::Ndjson));
+        assert_eq!(format("trace.ndjson"), Some(ExportFormat::Ndjson));
+        assert_eq!(format("trace.txt"), None);
+        assert_eq!(format("trace"), None);
+    }
+}
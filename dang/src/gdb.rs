@@ -1,7 +1,14 @@
+use std::fmt;
 use std::io::Write;
 
+use crate::assertions::{Check, CompareOp};
+use crate::bounded_output::{self, BoundedOutput};
+use crate::breakpoints::BpMatch;
 use crate::convert::Mappable;
-use crate::runtime::{ExecMode, Waver};
+use crate::disasm;
+use crate::monitor_batch;
+use crate::runtime::{ExecMode, KillPolicy, Waver};
+use crate::stop_context;
 use crate::waveloader;
 use gdbstub::common::Pid;
 use gdbstub::target::ext::base::singlethread::SingleThreadResume;
@@ -27,6 +34,7 @@ use gdbstub::{
 };
 use gdbstub_arch::riscv::{reg::id::RiscvRegId, Riscv32};
 use waveloader::WellenSignalExt;
+use wellen::TimeTableIdx;
 
 impl Breakpoints for Waver {
     #[inline(always)]
@@ -48,9 +56,12 @@ impl target::ext::breakpoints::SwBreakpoint for Waver {
     fn add_sw_breakpoint(
         &mut self,
         addr: u32,
-        _kind: <Riscv32 as Arch>::BreakpointKind,
+        kind: <Riscv32 as Arch>::BreakpointKind,
     ) -> TargetResult<bool, Self> {
-        self.breakpoints.push(addr);
+        // GDB resends Z0 for breakpoints it already has, so a second add is still OK
+        if !self.breakpoints.insert_with_kind(addr, kind) {
+            log::debug!("Breakpoint at 0x{addr:x} was already set");
+        }
         Ok(true)
     }
 
@@ -59,15 +70,22 @@ impl target::ext::breakpoints::SwBreakpoint for Waver {
         addr: u32,
         _kind: <Riscv32 as Arch>::BreakpointKind,
     ) -> TargetResult<bool, Self> {
-        match self.breakpoints.iter().position(|x| *x == addr) {
-            None => return Ok(false),
-            Some(pos) => self.breakpoints.remove(pos),
-        };
-
-        Ok(true)
+        // false goes back as E22, which tells the client there was nothing to remove
+        Ok(self.breakpoints.remove(&addr))
     }
 }
 
+/// errno handed back for writes outside of the scratch region
+pub const EROFS: u8 = 30;
+
+/// What the reply to a monitor command dang doesn't have starts with
+pub const UNKNOWN_COMMAND: &str = "I don't know how to handle";
+
+/// Addresses handed to monitor commands are always hex, with or without the 0x
+fn parse_monitor_addr(addr: &str) -> Option<u32> {
+    u32::from_str_radix(addr.trim_start_matches("0x"), 16).ok()
+}
+
 impl MonitorCmd for Waver {
     fn handle_monitor_cmd(
         &mut self,
@@ -82,9 +100,75 @@ impl MonitorCmd for Waver {
                 return Ok(());
             }
         };
+        self.monitor_cmd(cmd, &mut out, |_| None)
+    }
+}
+
+impl Waver {
+    /// A whole monitor command, `limit=` and `batch` included, with its output held to the
+    /// [`bounded_output`] cap. `refuse` gives the reason to turn a command down, given the whole
+    /// command so sub-commands can be told apart
+    pub fn monitor_cmd(
+        &mut self,
+        cmd: &str,
+        out: &mut impl fmt::Write,
+        refuse: impl Fn(&str) -> Option<&'static str>,
+    ) -> Result<(), <Self as Target>::Error> {
+        let (limit, cmd) = match bounded_output::parse_limit(cmd) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                outputln!(out, "{}", e);
+                return Ok(());
+            }
+        };
+        let mut out = BoundedOutput::new(out, limit);
+        let (name, args) = cmd.split_once(' ').unwrap_or((cmd, ""));
+        let result = if name == "batch" {
+            self.monitor_batch(args, &mut out, &refuse)
+        } else if let Some(reason) = refuse(cmd) {
+            outputln!(out, "{}", reason);
+            Ok(())
+        } else {
+            self.run_monitor_cmd(cmd, &mut out)
+        };
+        if let Ok(true) = out.finish() {
+            log::debug!("DANG SERVER: Output of '{cmd}' cut off at {limit} bytes");
+        }
+        result
+    }
+
+    /// `batch`: run each sub-command under its [`monitor_batch::header`], without stopping at
+    /// one that fails. `refuse` gives the reason to turn a sub-command down instead
+    pub fn monitor_batch(
+        &mut self,
+        args: &str,
+        out: &mut impl fmt::Write,
+        refuse: impl Fn(&str) -> Option<&'static str>,
+    ) -> Result<(), <Self as Target>::Error> {
+        for cmd in monitor_batch::split(args) {
+            outputln!(out, "{}", monitor_batch::header(&cmd));
+            let name = cmd.split_once(' ').map_or(cmd.as_str(), |(name, _)| name);
+            if name == "batch" {
+                outputln!(out, "{}batch can't be nested", monitor_batch::FAILED);
+            } else if let Some(reason) = refuse(&cmd) {
+                outputln!(out, "{}{}", monitor_batch::FAILED, reason);
+            } else if let Err(e) = self.run_monitor_cmd(&cmd, out) {
+                outputln!(out, "{}{}", monitor_batch::FAILED, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Any monitor command but `batch`
+    fn run_monitor_cmd(
+        &mut self,
+        cmd: &str,
+        out: &mut impl fmt::Write,
+    ) -> Result<(), <Self as Target>::Error> {
         log::debug!("DANG SERVER: Processing monitor command: '{cmd}'");
 
-        match cmd {
+        let (name, args) = cmd.split_once(' ').unwrap_or((cmd, ""));
+        match name {
             "" => outputln!(out,
                 "WHAT DID YOU SAY?! SPEAK UP! I WILL CRAWL THROUGH THE TERMINAL :)! I AM JUST BEING SILLY!"
             ),
@@ -93,7 +177,334 @@ impl MonitorCmd for Waver {
                 log::debug!("DANG SERVER: time_idx command returning: {time_idx}");
                 outputln!(out, "{}", time_idx)
             },
-            _ => outputln!(out, "I don't know how to handle '{}'", cmd),
+            // `<time> <unit>` at the cursor, the unit left off when the waveform doesn't say
+            "time" => match self.cursor.all_times.get(self.cursor.time_idx as usize) {
+                Some(time) => match self.time_unit() {
+                    Some(unit) => outputln!(out, "{} {}", time, unit),
+                    None => outputln!(out, "{}", time),
+                },
+                None => outputln!(out, "time index {} is past the end of the trace", self.cursor.time_idx),
+            },
+            // `ok`, or that the waveform was rewritten since it was loaded and dang needs a restart
+            "wavecheck" => match self.check_wave_file() {
+                Ok(()) => outputln!(out, "ok"),
+                Err(e) => outputln!(out, "{}", e),
+            },
+            "stop_context" => {
+                let n_inst = match args.trim() {
+                    "" => Some(stop_context::DEFAULT_INSTRUCTIONS),
+                    n_inst => n_inst.parse::<usize>().ok(),
+                };
+                match n_inst {
+                    Some(n_inst) => {
+                        let context = self.stop_context(n_inst.min(stop_context::MAX_INSTRUCTIONS));
+                        outputln!(out, "{}", context.to_string().trim_end())
+                    }
+                    None => outputln!(out, "usage: stop_context [n_inst]"),
+                }
+            },
+            "disasm" => {
+                let mut args = args.split_whitespace();
+                let start = match args.next().map(parse_monitor_addr) {
+                    Some(Some(addr)) => addr,
+                    Some(None) => {
+                        outputln!(out, "usage: disasm [addr] [count]");
+                        return Ok(());
+                    }
+                    None => self.get_current_pc(),
+                };
+                // anything past disasm::MAX_COUNT gets that many
+                let count = args.next().and_then(|c| c.parse().ok()).unwrap_or(8);
+                for line in disasm::disassemble(&self.mem, start, count) {
+                    outputln!(out, "{}", line);
+                }
+            },
+            "coverage" => {
+                let mut args = args.split_whitespace();
+                let start = args.next().and_then(parse_monitor_addr);
+                let end = args.next().and_then(parse_monitor_addr);
+                // optionally a page of the addresses, `offset` in and `count` long
+                let page = match (args.next().map(str::parse), args.next().map(str::parse)) {
+                    (None, None) => Some((0, usize::MAX)),
+                    (Some(Ok(offset)), Some(Ok(count))) => Some((offset, count)),
+                    _ => None,
+                };
+                match (start, end, page) {
+                    (Some(start), Some(end), Some((offset, count))) => {
+                        match self.pc_visit_counts(start, end) {
+                            Ok(counts) => {
+                                for (pc, visits) in counts.into_iter().skip(offset).take(count) {
+                                    outputln!(out, "{:x} {}", pc, visits);
+                                }
+                            }
+                            Err(progress) => outputln!(out, "{}", progress),
+                        }
+                    }
+                    _ => outputln!(out, "usage: coverage <start> <end> [offset count]"),
+                }
+            },
+            "pc_trace" => {
+                let mut args = args.split_whitespace();
+                let from = args.next().map(str::parse::<TimeTableIdx>);
+                let count = args.next().map(str::parse::<usize>);
+                let backward = match args.next() {
+                    None => Some(false),
+                    Some("back") => Some(true),
+                    Some(_) => None,
+                };
+                match (from, count, backward) {
+                    (Some(Ok(from)), Some(Ok(count)), Some(backward)) => {
+                        for (time_idx, pc) in self.pc_trace(from, count, backward) {
+                            outputln!(out, "{} {:x}", time_idx, pc);
+                        }
+                    }
+                    _ => outputln!(out, "usage: pc_trace <time_idx> <count> [back]"),
+                }
+            },
+            "goto_idx" => match args.trim().parse::<TimeTableIdx>() {
+                Ok(time_idx) if self.goto_time_idx(time_idx) => outputln!(out, "{}", time_idx),
+                Ok(time_idx) => outputln!(out, "time index {} is past the end of the trace", time_idx),
+                Err(_) => outputln!(out, "usage: goto_idx <time_idx>"),
+            },
+            // parsed wider than a time index, so one past any trace is out of range rather than
+            // not a number
+            "goto_time" => match args.trim().parse::<u64>() {
+                // `<time_idx> <time> <pc in hex>` of where the cursor landed
+                Ok(time_idx) => match TimeTableIdx::try_from(time_idx).ok().and_then(|time_idx| self.goto_time(time_idx)) {
+                    Some(landed) => outputln!(
+                        out,
+                        "{} {} {:x}",
+                        landed,
+                        self.cursor_time(),
+                        self.get_current_pc::<u32>()
+                    ),
+                    None => outputln!(
+                        out,
+                        "time index {} is out of range, nothing changes at or after it in {} entries",
+                        time_idx,
+                        self.cursor.all_times.len()
+                    ),
+                },
+                Err(_) => outputln!(out, "usage: goto_time <time_idx>"),
+            },
+            "reg_last_change" => match args.trim().parse::<usize>() {
+                Ok(gpr) if gpr < 32 => match self.gpr_last_change(gpr) {
+                    Some(change) => outputln!(out, "{} {}", gpr, change),
+                    None => outputln!(out, "{} none", gpr),
+                },
+                _ => outputln!(out, "usage: reg_last_change <0-31>"),
+            },
+            "reg_history" => {
+                let mut args = args.split_whitespace();
+                let gpr = args.next().map(str::parse::<usize>);
+                let from = args.next().map(str::parse::<TimeTableIdx>);
+                let count = args.next().map(str::parse::<usize>);
+                let stride = args.next().map_or(Ok(1), str::parse::<usize>);
+                match (gpr, from, count, stride) {
+                    (Some(Ok(gpr)), Some(Ok(from)), Some(Ok(count)), Ok(stride))
+                        if gpr < 32 && stride > 0 =>
+                    {
+                        // where the next page starts, then one `<time_idx> <value in hex>` per
+                        // change, x for a value with x or z bits
+                        let (page, next) = self.reg_history(gpr, from, count, stride);
+                        match next {
+                            Some(next) => outputln!(out, "{}", next),
+                            None => outputln!(out, "end"),
+                        }
+                        for (time_idx, value) in page {
+                            match value {
+                                Some(value) => outputln!(out, "{} {:x}", time_idx, value),
+                                None => outputln!(out, "{} x", time_idx),
+                            }
+                        }
+                    }
+                    _ => outputln!(out, "usage: reg_history <0-31> <from_idx> <count> [stride]"),
+                }
+            },
+            "regs_last_change" => {
+                for gpr in 0..32 {
+                    match self.gpr_last_change(gpr) {
+                        Some(change) => outputln!(out, "{} {}", gpr, change),
+                        None => outputln!(out, "{} none", gpr),
+                    }
+                }
+            },
+            "check_gprs" => {
+                let report = self.check_gprs();
+                if report.is_clean() {
+                    outputln!(out, "ok");
+                }
+                for finding in &report.findings {
+                    outputln!(out, "{}", finding);
+                }
+            },
+            "assert_reg" => {
+                let parts: Vec<&str> = args.split_whitespace().collect();
+                let check = match parts[..] {
+                    [gpr, op, value] => match (gpr.parse::<usize>(), CompareOp::parse(op), parse_monitor_addr(value)) {
+                        (Ok(gpr), Some(op), Some(value)) if gpr < 32 => Some(Check::Reg { gpr, op, value }),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                match check {
+                    Some(check) => {
+                        let assertion = self.assertions.add(check);
+                        outputln!(out, "{} {}", assertion.id, assertion.check)
+                    }
+                    None => outputln!(out, "usage: assert_reg <0-31> <op> <value>, op is one of < <= > >= == !="),
+                }
+            },
+            "assert_pc_range" => {
+                let parts: Vec<&str> = args.split_whitespace().collect();
+                let check = match parts[..] {
+                    [lo, hi, side] => match (parse_monitor_addr(lo), parse_monitor_addr(hi), side) {
+                        (Some(lo), Some(hi), "inside" | "outside") if lo <= hi => Some(Check::PcRange { lo, hi, inside: side == "inside" }),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                match check {
+                    Some(check) => {
+                        let assertion = self.assertions.add(check);
+                        outputln!(out, "{} {}", assertion.id, assertion.check)
+                    }
+                    None => outputln!(out, "usage: assert_pc_range <lo> <hi> <inside|outside>"),
+                }
+            },
+            "assert_list" => {
+                for assertion in self.assertions.iter() {
+                    outputln!(out, "{} {}", assertion.id, assertion.check);
+                }
+            },
+            "assert_del" => match args.trim().parse::<u32>() {
+                Ok(id) => match self.assertions.remove(id) {
+                    Some(assertion) => outputln!(out, "deleted {}", assertion.id),
+                    None => outputln!(out, "no assertion {}", id),
+                },
+                Err(_) => outputln!(out, "usage: assert_del <id>"),
+            },
+            "last_assert" => match self.assertions.last_violation {
+                Some(violation) => outputln!(out, "{}", violation),
+                None => outputln!(out, "none"),
+            },
+            "skip_range" => {
+                let parts: Vec<&str> = args.split_whitespace().collect();
+                match parts[..] {
+                    ["add", lo, hi] => match (parse_monitor_addr(lo), parse_monitor_addr(hi)) {
+                        (Some(lo), Some(hi)) if lo <= hi => {
+                            let range = self.skip_ranges.add(lo, hi);
+                            outputln!(out, "{}", range)
+                        }
+                        _ => outputln!(out, "usage: skip_range add <lo> <hi>"),
+                    },
+                    ["list"] => {
+                        for range in self.skip_ranges.iter() {
+                            outputln!(out, "{}", range);
+                        }
+                    }
+                    ["del", id] => match id.parse::<u32>() {
+                        Ok(id) => match self.skip_ranges.remove(id) {
+                            Some(range) => outputln!(out, "deleted {}", range.id),
+                            None => outputln!(out, "no skip range {}", id),
+                        },
+                        Err(_) => outputln!(out, "usage: skip_range del <id>"),
+                    },
+                    _ => outputln!(out, "usage: skip_range <add <lo> <hi>|list|del <id>>"),
+                }
+            },
+            "fastforward_ranges" => match args.trim() {
+                "on" => {
+                    self.skip_ranges.fast_forward = true;
+                    outputln!(out, "will fast-forward through skip ranges")
+                }
+                "off" => {
+                    self.skip_ranges.fast_forward = false;
+                    outputln!(out, "will step through skip ranges")
+                }
+                _ => outputln!(out, "usage: fastforward_ranges <on|off>"),
+            },
+            "list_vars" => {
+                let mut args = args.split_whitespace();
+                let offset = args.next().map(str::parse::<usize>);
+                let count = args.next().map(str::parse::<usize>);
+                let filter = args.next().unwrap_or("");
+                match (offset, count) {
+                    (Some(Ok(offset)), Some(Ok(count))) => {
+                        let (total, names) = self.vars.page(filter, offset, count);
+                        outputln!(out, "{}", total);
+                        for name in names {
+                            outputln!(out, "{}", name);
+                        }
+                    }
+                    _ => outputln!(out, "usage: list_vars <offset> <count> [filter]"),
+                }
+            },
+            "breakpoints" => {
+                for entry in self.breakpoints.entries() {
+                    outputln!(out, "{}", entry);
+                }
+            },
+            "bp_ignore" => {
+                let parts: Vec<&str> = args.split_whitespace().collect();
+                match parts[..] {
+                    [addr, count] => match (parse_monitor_addr(addr), count.parse::<u32>()) {
+                        (Some(addr), Ok(count)) => {
+                            if self.breakpoints.set_ignore(addr, count) {
+                                outputln!(out, "will ignore the next {} hits of {:x}", count, addr)
+                            } else {
+                                outputln!(out, "no breakpoint at {:x}", addr)
+                            }
+                        }
+                        _ => outputln!(out, "usage: bp_ignore <addr> <count>"),
+                    },
+                    _ => outputln!(out, "usage: bp_ignore <addr> <count>"),
+                }
+            },
+            "set_bp_match" => match args.trim().parse::<BpMatch>() {
+                Ok(match_mode) => {
+                    self.breakpoints.match_mode = match_mode;
+                    match match_mode {
+                        BpMatch::Exact => outputln!(out, "breakpoints hit at their address only"),
+                        BpMatch::Loose => outputln!(out, "breakpoints on 4 byte instructions also hit 2 bytes in, a wrong Z0 kind makes that stop on the instruction after a 2 byte one"),
+                    }
+                }
+                Err(_) => outputln!(out, "usage: set_bp_match <exact|loose>, loose also counts a PC 2 bytes into a 4 byte instruction's breakpoint, for cores that trace its second halfword"),
+            },
+            "resume_stats" => outputln!(out, "{}", self.resume_stats),
+            "meminfo" => outputln!(out, "pc_index {}", self.pc_index.describe()),
+            "index_status" => outputln!(out, "{}", self.pc_index.status()),
+            "scratch_info" => match self.mem.scratch() {
+                Some(scratch) => outputln!(out, "{:x} {:x}", scratch.start, scratch.size),
+                None => outputln!(out, "none"),
+            },
+            "set_livelock_threshold" => match args.trim().parse::<u64>() {
+                Ok(threshold) if threshold > 0 => {
+                    self.livelock.threshold = threshold;
+                    outputln!(out, "livelock threshold is {} steps", threshold)
+                }
+                _ => outputln!(out, "usage: set_livelock_threshold <steps>"),
+            },
+            "stop_on_livelock" => match args.trim() {
+                "on" => {
+                    self.livelock.stop_on_livelock = true;
+                    outputln!(out, "will stop on livelock")
+                }
+                "off" => {
+                    self.livelock.stop_on_livelock = false;
+                    outputln!(out, "will warn on livelock")
+                }
+                _ => outputln!(out, "usage: stop_on_livelock <on|off>"),
+            },
+            // stands in for a command with far more to say than fits
+            #[cfg(test)]
+            "spew" => {
+                let line = "x".repeat(63);
+                for _ in 0..args.trim().parse::<usize>().unwrap_or(0) / 64 {
+                    outputln!(out, "{}", line);
+                }
+            },
+            _ => outputln!(out, "{} '{}'", UNKNOWN_COMMAND, cmd),
         };
 
         Ok(())
@@ -119,6 +530,9 @@ impl Target for Waver {
     // Without this annotation, LLVM might fail to dead-code-eliminate nested IDET
     // implementations, resulting in unnecessary binary bloat.
 
+    // One hart, which gdbstub numbers thread 1 throughout: `qC` and `qfThreadInfo` name it,
+    // stop replies carry `thread:01`, and `T` errors for any other id. `H` for another id is
+    // turned away before it gets here, see `thread_check`
     #[inline(always)]
     fn base_ops(&mut self) -> target::ext::base::BaseOps<'_, Self::Arch, Self::Error> {
         target::ext::base::BaseOps::SingleThread(self)
@@ -135,7 +549,7 @@ impl Target for Waver {
     fn support_extended_mode(
         &mut self,
     ) -> Option<target::ext::extended_mode::ExtendedModeOps<'_, Self>> {
-        None
+        Some(self)
     }
 
     #[inline(always)]
@@ -279,9 +693,13 @@ impl SingleThreadBase for Waver {
         Ok(data.len())
     }
 
-    fn write_addrs(&mut self, _start_addr: u32, _data: &[u8]) -> TargetResult<(), Self> {
-        // We do not support writing registers because we have read only signals
-        Err(TargetError::NonFatal)
+    fn write_addrs(&mut self, start_addr: u32, data: &[u8]) -> TargetResult<(), Self> {
+        // Memory comes from the ELF and is read only, except for the scratch region
+        if self.mem.write_scratch(start_addr, data) {
+            Ok(())
+        } else {
+            Err(TargetError::Errno(EROFS))
+        }
     }
 
     #[inline(always)]
@@ -297,7 +715,11 @@ impl SingleThreadResume for Waver {
         if signal.is_some() {
             return Err("no support for continuing with signal");
         }
-        self.exec_mode = ExecMode::Continue;
+        // the continue still runs over what was loaded, which is at least self-consistent
+        if let Err(e) = self.check_wave_file() {
+            log::warn!("{e}");
+        }
+        self.begin_resume(ExecMode::Continue);
 
         Ok(())
     }
@@ -336,7 +758,7 @@ impl target::ext::base::singlethread::SingleThreadSingleStep for Waver {
         if signal.is_some() {
             return Err("no support for stepping with signal");
         }
-        self.exec_mode = ExecMode::Step;
+        self.begin_resume(ExecMode::Step);
 
         Ok(())
     }
@@ -351,25 +773,21 @@ impl target::ext::base::single_register_access::SingleRegisterAccess<()> for Wav
     ) -> TargetResult<usize, Self> {
         let idx = self.cursor.time_idx;
 
-        let rv = match reg_id {
-            RiscvRegId::Pc => {
-                let val = self.waves.pc.get_val(idx);
-                let rv = u32::from_signal(val).to_be_bytes();
-                match buf.write(&rv) {
-                    Ok(bytes_written) => Ok(bytes_written), // Return the number of bytes written
-                    Err(_) => Err(TargetError::NonFatal),
-                }
-            }
-            RiscvRegId::Gpr(grp_id) => {
-                let val = self.waves.gprs[grp_id as usize].get_val(idx);
-                let val = u32::from_signal(val).to_be_bytes();
-                // Use the write method directly on buf
-                match buf.write(&val) {
+        let signal = match reg_id {
+            RiscvRegId::Pc => Some(self.waves.pc.as_ref()),
+            RiscvRegId::Gpr(grp_id) => self.waves.gprs.get(grp_id as usize),
+            _ => None,
+        };
+        let rv = match signal {
+            // x or z bits read as unavailable rather than as some made up value
+            Some(signal) => match u32::try_from_signal(signal.get_val(idx)) {
+                Some(val) => match buf.write(&val.to_be_bytes()) {
                     Ok(bytes_written) => Ok(bytes_written), // Return the number of bytes written
                     Err(_) => Err(TargetError::NonFatal),
-                }
-            }
-            _ => Err(TargetError::NonFatal),
+                },
+                None => Ok(0),
+            },
+            None => Err(TargetError::NonFatal),
         };
         if let Ok(ref inner) = rv {
             log::debug!("read reg {reg_id:?}, {inner:?} bytes at idx {idx:?}");
@@ -391,50 +809,60 @@ impl target::ext::base::single_register_access::SingleRegisterAccess<()> for Wav
 
 impl target::ext::base::reverse_exec::ReverseCont<()> for Waver {
     fn reverse_cont(&mut self) -> Result<(), Self::Error> {
-        // FIXME: actually implement reverse step
-        log::debug!(
-            "FIXME: Not actually reverse-continuing. Performing forwards continue instead..."
-        );
-        self.exec_mode = ExecMode::Continue;
+        self.begin_resume(ExecMode::ReverseContinue);
         Ok(())
     }
 }
 
 impl target::ext::base::reverse_exec::ReverseStep<()> for Waver {
     fn reverse_step(&mut self, _tid: ()) -> Result<(), Self::Error> {
-        // FIXME: actually implement reverse step
-
-        log::debug!(
-            "FIXME: Not actually reverse-stepping. Performing single forwards step instead..."
-        );
-        self.exec_mode = ExecMode::Step;
+        self.begin_resume(ExecMode::ReverseStep);
         Ok(())
     }
 }
 
 impl target::ext::base::singlethread::SingleThreadRangeStepping for Waver {
     fn resume_range_step(&mut self, start: u32, end: u32) -> Result<(), Self::Error> {
-        self.exec_mode = ExecMode::RangeStep(start, end);
+        self.begin_resume(ExecMode::RangeStep(start, end));
         Ok(())
     }
 }
 
+/// The trace is the one "process", and it's always there. What extended mode tracks is how the
+/// current session's GDB came by it:
+///
+/// - a new connection finds it already loaded, so it's attached (`qAttached` says 1), and a
+///   quitting GDB detaches from it
+/// - `run` (`vRun`) or a restart (`R`) puts the cursor back at the start. GDB then started it
+///   itself (`qAttached` says 0), and a quitting GDB kills it
+/// - a kill ends the stub under [`KillPolicy::Exit`]. Under [`KillPolicy::Stay`] the cursor goes
+///   back to the start, and the session waits for the next `run` or `attach`
+///
+/// Breakpoints, assertions and skip ranges survive all of these
 impl target::ext::extended_mode::ExtendedMode for Waver {
     fn kill(&mut self, pid: Option<Pid>) -> TargetResult<ShouldTerminate, Self> {
         log::debug!("GDB sent a kill request for pid {pid:?}");
-        Ok(ShouldTerminate::No)
+        match self.kill_policy {
+            KillPolicy::Exit => Ok(ShouldTerminate::Yes),
+            KillPolicy::Stay => {
+                self.reset();
+                self.launched = false;
+                Ok(ShouldTerminate::No)
+            }
+        }
     }
 
     fn restart(&mut self) -> Result<(), Self::Error> {
         log::debug!("GDB sent a restart request");
+        self.reset();
+        self.launched = true;
         Ok(())
     }
 
     fn attach(&mut self, pid: Pid) -> TargetResult<(), Self> {
         log::debug!("GDB attached to a process with PID {pid}");
-        // stub implementation: just report the same code, but running under a
-        // different pid.
-
+        // there's only the one trace, whatever the pid, and attaching leaves the cursor be
+        self.launched = false;
         Ok(())
     }
 
@@ -456,6 +884,7 @@ impl target::ext::extended_mode::ExtendedMode for Waver {
         log::debug!("GDB tried to run a new process with filename {filename:?}, and args {args:?}");
 
         self.reset();
+        self.launched = true;
 
         // when running in single-threaded mode, this PID can be anything
         Ok(Pid::new(1).unwrap())
@@ -463,7 +892,11 @@ impl target::ext::extended_mode::ExtendedMode for Waver {
 
     fn query_if_attached(&mut self, pid: Pid) -> TargetResult<AttachKind, Self> {
         log::debug!("GDB queried if it was attached to a process with PID {pid}");
-        Ok(AttachKind::Attach)
+        Ok(if self.launched {
+            AttachKind::Run
+        } else {
+            AttachKind::Attach
+        })
     }
 
     #[inline(always)]
@@ -622,4 +1055,341 @@ mod tests {
             "Host I/O should be disabled to prevent arbitrary file access"
         );
     }
+
+    #[test]
+    fn test_breakpoints_are_a_set() {
+        use target::ext::breakpoints::SwBreakpoint;
+        let mut waver = crate::runtime::tests::fixture_waver();
+
+        assert!(matches!(waver.add_sw_breakpoint(0x1003d4, 4), Ok(true)));
+        assert!(matches!(waver.add_sw_breakpoint(0x1003d0, 4), Ok(true)));
+        // a second add is accepted without adding anything
+        assert!(matches!(waver.add_sw_breakpoint(0x1003d0, 4), Ok(true)));
+        assert_eq!(
+            waver.breakpoints.iter().copied().collect::<Vec<_>>(),
+            [0x1003d0, 0x1003d4]
+        );
+
+        assert!(matches!(waver.remove_sw_breakpoint(0x1003d0, 4), Ok(true)));
+        // nothing left to remove, which goes back to the client as an error
+        assert!(matches!(waver.remove_sw_breakpoint(0x1003d0, 4), Ok(false)));
+        assert_eq!(
+            waver.breakpoints.iter().copied().collect::<Vec<_>>(),
+            [0x1003d4]
+        );
+    }
+
+    #[test]
+    fn test_breakpoints_leave_memory_alone() {
+        use target::ext::breakpoints::SwBreakpoint;
+        let mut waver = crate::runtime::tests::fixture_waver();
+        let read = |waver: &mut Waver| {
+            let mut data = [0; 8];
+            assert!(matches!(waver.read_addrs(0x1000a4, &mut data), Ok(8)));
+            data
+        };
+
+        // no trap instruction goes in, gdb compares what it reads back against its own copy
+        let original = read(&mut waver);
+        assert!(matches!(waver.add_sw_breakpoint(0x1000a4, 2), Ok(true)));
+        assert!(matches!(waver.add_sw_breakpoint(0x1000a8, 4), Ok(true)));
+        assert_eq!(read(&mut waver), original);
+        assert!(matches!(waver.remove_sw_breakpoint(0x1000a4, 2), Ok(true)));
+        assert_eq!(read(&mut waver), original);
+    }
+
+    #[test]
+    fn test_restart_resets_cursor_and_keeps_breakpoints() {
+        use target::ext::extended_mode::ExtendedMode;
+        let mut waver = crate::runtime::tests::fixture_waver();
+        let pid = Pid::new(1).unwrap();
+        let start = waver.cursor.time_idx;
+        assert_eq!(waver.start_time_idx, start);
+        assert!(matches!(waver.query_if_attached(pid), Ok(AttachKind::Attach)));
+
+        waver.breakpoints.insert(0x1003d0);
+        for _ in 0..20 {
+            waver.step();
+        }
+        assert!(waver.cursor.time_idx > start);
+
+        waver.restart().unwrap();
+        assert_eq!(waver.cursor.time_idx, start);
+        assert_eq!(waver.resume_stats, Default::default());
+        assert!(waver.breakpoints.contains(&0x1003d0));
+        assert!(matches!(waver.query_if_attached(pid), Ok(AttachKind::Run)));
+
+        // staying on a kill starts over and waits for the next run
+        waver.step();
+        waver.kill_policy = KillPolicy::Stay;
+        assert!(matches!(waver.kill(None), Ok(ShouldTerminate::No)));
+        assert_eq!(waver.cursor.time_idx, start);
+        assert!(matches!(waver.query_if_attached(pid), Ok(AttachKind::Attach)));
+
+        waver.kill_policy = KillPolicy::Exit;
+        assert!(matches!(waver.kill(None), Ok(ShouldTerminate::Yes)));
+    }
+
+    #[test]
+    fn test_reg_history_pages_stitch() {
+        const SP: usize = 2;
+        let mut waver = crate::runtime::tests::fixture_waver();
+        let start = waver.cursor.time_idx;
+        let run = |waver: &mut Waver, cmd: &str| {
+            let mut out = String::new();
+            waver.monitor_cmd(cmd, &mut out, |_| None).unwrap();
+            out
+        };
+
+        let (all, end) = waver.reg_history(SP, 0, usize::MAX, 1);
+        assert_eq!(end, None);
+        assert!(all.len() > 20, "sp should change a fair bit over the trace");
+        let (thirds, _) = waver.reg_history(SP, 0, usize::MAX, 3);
+        assert_eq!(thirds, all.iter().copied().step_by(3).collect::<Vec<_>>());
+
+        for (stride, whole) in [(1, &all), (3, &thirds)] {
+            // 7 at a time through the monitor command, each page starting where the last said
+            let mut paged = Vec::new();
+            let mut from = Some(0);
+            while let Some(page_from) = from {
+                let out = run(&mut waver, &format!("reg_history {SP} {page_from} 7 {stride}"));
+                let mut lines = out.lines();
+                from = match lines.next().unwrap() {
+                    "end" => None,
+                    next => Some(next.parse::<TimeTableIdx>().unwrap()),
+                };
+                for line in lines {
+                    let (time_idx, value) = line.split_once(' ').unwrap();
+                    let value = u32::from_str_radix(value, 16).ok();
+                    paged.push((time_idx.parse::<TimeTableIdx>().unwrap(), value));
+                }
+            }
+            assert_eq!(&paged, whole, "stride {stride}");
+        }
+        assert_eq!(waver.cursor.time_idx, start);
+
+        assert!(run(&mut waver, "reg_history 32 0 8").starts_with("usage"));
+        assert!(run(&mut waver, "reg_history 2 0 8 0").starts_with("usage"));
+    }
+
+    #[test]
+    fn test_stop_context_matches_piecewise() {
+        use crate::stop_context::{StopContext, DEFAULT_INSTRUCTIONS};
+        let mut waver = crate::runtime::tests::fixture_waver();
+        let run = |waver: &mut Waver, cmd: &str| {
+            let mut out = String::new();
+            waver.monitor_cmd(cmd, &mut out, |_| None).unwrap();
+            out
+        };
+
+        assert!(waver.goto_time_idx(waver.cursor.time_idx + 10));
+        let context: StopContext = run(&mut waver, "stop_context 3").parse().unwrap();
+        let time_idx: u64 = run(&mut waver, "time_idx").trim().parse().unwrap();
+        assert_eq!(context.time_idx, time_idx);
+        assert_eq!(context.pc, waver.get_current_pc::<u32>());
+        for (gpr, &value) in context.gprs.iter().enumerate() {
+            assert_eq!(value, waver.get_current_gpr(gpr), "x{gpr}");
+        }
+        let code: Vec<u8> = (context.pc..context.pc + 12)
+            .map(|addr| waver.mem.r8(addr))
+            .collect();
+        assert_eq!(context.code, code);
+
+        let context: StopContext = run(&mut waver, "stop_context").parse().unwrap();
+        assert_eq!(context.code.len(), DEFAULT_INSTRUCTIONS * 4);
+        assert!(run(&mut waver, "stop_context lots").starts_with("usage"));
+    }
+
+    #[test]
+    fn test_bp_ignore_shows_in_listing() {
+        use crate::breakpoints::{BreakpointEntry, DEFAULT_KIND};
+        let mut waver = crate::runtime::tests::fixture_waver();
+        let run = |waver: &mut Waver, cmd: &str| {
+            let mut out = String::new();
+            waver.monitor_cmd(cmd, &mut out, |_| None).unwrap();
+            out
+        };
+
+        assert!(run(&mut waver, "bp_ignore 1003d0 3").starts_with("no breakpoint"));
+        waver.breakpoints.extend([0x1003d0, 0x1003d4]);
+        assert!(run(&mut waver, "bp_ignore 1003d0 3").starts_with("will ignore"));
+        assert!(run(&mut waver, "bp_ignore 1003d0").starts_with("usage"));
+        assert!(run(&mut waver, "bp_ignore 1003d0 -1").starts_with("usage"));
+
+        let listing: Vec<BreakpointEntry> = run(&mut waver, "breakpoints")
+            .lines()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        assert_eq!(
+            listing,
+            [
+                BreakpointEntry {
+                    addr: 0x1003d0,
+                    hits: 0,
+                    ignore_remaining: 3,
+                    kind: DEFAULT_KIND,
+                },
+                BreakpointEntry {
+                    addr: 0x1003d4,
+                    hits: 0,
+                    ignore_remaining: 0,
+                    kind: DEFAULT_KIND,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_set_bp_match() {
+        let mut waver = crate::runtime::tests::fixture_waver();
+        let run = |waver: &mut Waver, cmd: &str| {
+            let mut out = String::new();
+            waver.monitor_cmd(cmd, &mut out, |_| None).unwrap();
+            out
+        };
+
+        assert_eq!(waver.breakpoints.match_mode, BpMatch::Exact);
+        assert!(run(&mut waver, "set_bp_match loose").starts_with("breakpoints on 4 byte"));
+        assert_eq!(waver.breakpoints.match_mode, BpMatch::Loose);
+        assert!(run(&mut waver, "set_bp_match sloppy").starts_with("usage"));
+        assert_eq!(waver.breakpoints.match_mode, BpMatch::Loose);
+        assert!(run(&mut waver, "set_bp_match exact").starts_with("breakpoints hit"));
+        assert_eq!(waver.breakpoints.match_mode, BpMatch::Exact);
+    }
+
+    #[test]
+    fn test_wavecheck() {
+        let data = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../test_data/ibex");
+        let wave_path =
+            std::env::temp_dir().join(format!("dang_{}_wavecheck.fst", std::process::id()));
+        std::fs::copy(data.join("sim.fst"), &wave_path).unwrap();
+        let mut waver = Waver::new(
+            wave_path.clone(),
+            data.join("signal_get.py"),
+            data.join("hello_test.elf"),
+        )
+        .unwrap();
+        let run = |waver: &mut Waver, cmd: &str| {
+            let mut out = String::new();
+            waver.monitor_cmd(cmd, &mut out, |_| None).unwrap();
+            out
+        };
+        assert_eq!(run(&mut waver, "wavecheck"), "ok\n");
+
+        // the simulation ran again and wrote a new one
+        let mut rewritten = std::fs::read(&wave_path).unwrap();
+        rewritten.extend_from_slice(b"more");
+        std::fs::write(&wave_path, rewritten).unwrap();
+        let reply = run(&mut waver, "wavecheck");
+        assert!(reply.contains("changed on disk"), "{reply}");
+        assert!(reply.contains("restart dang"), "{reply}");
+
+        // a continue still goes ahead on what was loaded
+        assert!(waver.resume(None).is_ok());
+        let _ = std::fs::remove_file(&wave_path);
+    }
+
+    #[test]
+    fn test_time() {
+        let mut waver = crate::runtime::tests::fixture_waver();
+        let run = |waver: &mut Waver, cmd: &str| {
+            let mut out = String::new();
+            waver.monitor_cmd(cmd, &mut out, |_| None).unwrap();
+            out
+        };
+
+        let unit = waver.time_unit().expect("the fixture has a timescale");
+        assert!(waver.goto_time_idx(waver.cursor.all_changes[30]));
+        let time = waver.cursor.all_times[waver.cursor.time_idx as usize];
+        assert_eq!(run(&mut waver, "time"), format!("{time} {unit}\n"));
+
+        waver.timescale = None;
+        assert_eq!(run(&mut waver, "time"), format!("{time}\n"));
+
+        // only reachable by setting the cursor by hand, but it shouldn't panic
+        waver.cursor.time_idx = waver.cursor.all_times.len() as TimeTableIdx;
+        assert!(run(&mut waver, "time").contains("past the end of the trace"));
+    }
+
+    #[test]
+    fn test_goto_time() {
+        let mut waver = crate::runtime::tests::fixture_waver();
+        let run = |waver: &mut Waver, cmd: &str| {
+            let mut out = String::new();
+            waver.monitor_cmd(cmd, &mut out, |_| None).unwrap();
+            out
+        };
+
+        let target = waver.cursor.all_changes[20] + 1;
+        let reply = run(&mut waver, &format!("goto_time {target}"));
+        let fields: Vec<&str> = reply.split_whitespace().collect();
+        assert_eq!(fields.len(), 3, "{reply}");
+        assert_eq!(fields[0], waver.cursor.all_changes[21].to_string());
+        assert_eq!(fields[0], waver.cursor.time_idx.to_string());
+        assert_eq!(fields[1], waver.cursor_time().to_string());
+        assert_eq!(fields[2], format!("{:x}", waver.get_current_pc::<u32>()));
+
+        let past = waver.cursor.all_times.len();
+        let landed = waver.cursor.time_idx;
+        assert!(run(&mut waver, &format!("goto_time {past}")).contains("out of range"));
+        assert!(run(&mut waver, &format!("goto_time {}", u64::MAX)).contains("out of range"));
+        assert_eq!(waver.cursor.time_idx, landed);
+        assert!(run(&mut waver, "goto_time soon").starts_with("usage"));
+    }
+
+    #[test]
+    fn test_index_status() {
+        let mut waver = crate::runtime::tests::fixture_waver();
+        // no sidecar, so the build below is this session's own
+        waver.pc_index = crate::pc_index::LazyPcIndex::default();
+        let run = |waver: &mut Waver, cmd: &str| {
+            let mut out = String::new();
+            waver.monitor_cmd(cmd, &mut out, |_| None).unwrap();
+            out
+        };
+
+        assert_eq!(run(&mut waver, "index_status"), "not built\n");
+        while run(&mut waver, "coverage 1003d0 1003d0").starts_with(crate::pc_index::BUILDING) {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        let status = run(&mut waver, "index_status");
+        assert!(status.starts_with("built, "), "{status}");
+        assert!(!status.contains("cache"));
+    }
+
+    #[test]
+    fn test_monitor_output_is_capped() {
+        use crate::bounded_output::{DEFAULT_LIMIT, TRUNCATED};
+        let mut waver = crate::runtime::tests::fixture_waver();
+        let run = |waver: &mut Waver, cmd: &str| {
+            let mut out = String::new();
+            waver.monitor_cmd(cmd, &mut out, |_| None).unwrap();
+            out
+        };
+
+        // a megabyte stops at the cap
+        let out = run(&mut waver, "spew 1048576");
+        let (kept, marker) = out.rsplit_once('\n').unwrap().0.rsplit_once('\n').unwrap();
+        assert_eq!(kept.len() + 1, DEFAULT_LIMIT);
+        assert_eq!(marker, TRUNCATED);
+
+        // limit= raises it for one command
+        let out = run(&mut waver, "limit=2000000 spew 1048576");
+        assert_eq!(out.len(), 1048576);
+        assert!(!out.contains(TRUNCATED));
+        assert!(run(&mut waver, "spew 1048576").ends_with(&format!("{TRUNCATED}\n")));
+
+        // short output is left alone
+        let time_idx = run(&mut waver, "time_idx");
+        assert_eq!(time_idx, format!("{}\n", waver.cursor.time_idx));
+
+        // and lowers it, a batch sharing the one cap
+        let first = format!("== time_idx ==\n{time_idx}");
+        let out = run(
+            &mut waver,
+            &format!("limit={} batch time_idx; spew 640", first.len() + 3),
+        );
+        assert_eq!(out, format!("{first}== \n{TRUNCATED}\n"));
+        assert!(run(&mut waver, "limit=many time_idx").starts_with("invalid limit"));
+    }
 }
@@ -0,0 +1,265 @@
+//! Sanity checks on the GPR and PC signals a mapping script hands back.
+//!
+//! Swapping two register paths in a mapping gives a debugger that mostly works but shows the
+//! wrong values, so the trace is checked against what the ABI says each register should look
+//! like. x0 not being zero is a hard error; everything else is a heuristic and only warns
+
+use std::fmt;
+use std::ops::Range;
+
+use wellen::{Signal, TimeTableIdx};
+
+use crate::convert::Mappable;
+use crate::runtime::{DummyMem, RequiredWaves};
+use crate::waveloader::WellenSignalExt;
+
+/// How many retired instructions from the start of the trace the heuristics look at
+pub const SAMPLE_LEN: usize = 10_000;
+
+/// Below this many samples the heuristics don't say anything
+const MIN_SAMPLES: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    /// The mapping is wrong, dang won't start with it
+    Error(String),
+    /// Implausible for the register's ABI role, but could be legitimate
+    Warning(String),
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::Error(msg) => write!(f, "error: {msg}"),
+            Finding::Warning(msg) => write!(f, "warning: {msg}"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GprReport {
+    pub findings: Vec<Finding>,
+}
+
+impl GprReport {
+    /// The hard error, if the mapping has one
+    pub fn error(&self) -> Option<&str> {
+        self.findings.iter().find_map(|finding| match finding {
+            Finding::Error(msg) => Some(msg.as_str()),
+            Finding::Warning(_) => None,
+        })
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// Send everything to the log, errors as errors and warnings as warnings
+    pub fn log(&self) {
+        for finding in &self.findings {
+            match finding {
+                Finding::Error(msg) => log::error!("{msg}"),
+                Finding::Warning(msg) => log::warn!("{msg}"),
+            }
+        }
+    }
+}
+
+/// Run every check against the trace. `text` is the executable address ranges of the ELF, and
+/// `mem` holds its contents for decoding the instructions that were retired
+pub fn check_gprs(waves: &RequiredWaves, mem: &DummyMem, text: &[Range<u32>]) -> GprReport {
+    let mut report = GprReport::default();
+    if let Some(error) = check_x0(&waves.gprs) {
+        report.findings.push(error);
+    }
+    if let Some(warning) = check_pc_moves(&waves.pc) {
+        report.findings.push(warning);
+    }
+
+    let samples = sample_retires(waves, SAMPLE_LEN);
+    let sp: Vec<u32> = samples
+        .iter()
+        .filter_map(|s| gpr_at(waves, 2, s.0))
+        .collect();
+    if let Some(warning) = check_sp(&sp) {
+        report.findings.push(warning);
+    }
+
+    let calls = samples
+        .iter()
+        .filter(|(_, pc)| is_call(mem.r32(*pc)))
+        .count();
+    let ra: Vec<u32> = samples
+        .iter()
+        .filter_map(|s| gpr_at(waves, 1, s.0))
+        .collect();
+    if let Some(warning) = check_ra(&ra, calls, text) {
+        report.findings.push(warning);
+    }
+    report
+}
+
+fn gpr_at(waves: &RequiredWaves, gpr: usize, idx: TimeTableIdx) -> Option<u32> {
+    u32::try_from_signal(waves.gprs.get(gpr)?.try_get_val(idx)?)
+}
+
+/// The first nonzero value `signal` takes, and when
+fn first_nonzero(signal: &Signal) -> Option<(TimeTableIdx, u32)> {
+    signal.time_indices().iter().find_map(|&idx| {
+        let value = u32::try_from_signal(signal.get_val(idx))?;
+        (value != 0).then_some((idx, value))
+    })
+}
+
+/// x0 is hardwired to zero, so a signal mapped to it that isn't can only be a mis-mapping.
+/// Checked over the whole trace, it only costs a walk over x0's changes
+fn check_x0(gprs: &[Signal]) -> Option<Finding> {
+    let (idx, value) = first_nonzero(gprs.first()?)?;
+    // x0 was probably swapped with one of the registers that's always zero instead
+    let always_zero: Vec<String> = gprs
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, signal)| first_nonzero(signal).is_none())
+        .map(|(gpr, _)| format!("x{gpr}"))
+        .collect();
+    let problem = format!(
+        "the signal mapped to x0 holds 0x{value:x} at time index {idx}, but x0 is hardwired to \
+         zero"
+    );
+    let msg = match always_zero.as_slice() {
+        [] => format!("{problem}, and no other register signal is always zero"),
+        [gpr] => format!(
+            "{problem}. The signal mapped to {gpr} is always zero, are x0 and {gpr} swapped?"
+        ),
+        gprs => format!(
+            "{problem}. The signals mapped to {} are always zero, x0 was probably swapped with \
+             one of them",
+            gprs.join(", ")
+        ),
+    };
+    Some(Finding::Error(msg))
+}
+
+/// A trace cut short, by a simulation that died straight away or a dump filtered down to
+/// almost nothing, leaves the PC without anywhere to go. Everything works, but every resume
+/// ends up at the end of the trace
+fn check_pc_moves(pc: &Signal) -> Option<Finding> {
+    (pc.time_indices().len() < 2).then(|| {
+        Finding::Warning(
+            "the pc signal holds a single value over the whole trace, every resume will stop at \
+             the end of it"
+                .to_string(),
+        )
+    })
+}
+
+/// `(time_idx, pc)` for the first `count` instructions retired in the trace
+fn sample_retires(waves: &RequiredWaves, count: usize) -> Vec<(TimeTableIdx, u32)> {
+    let mut samples: Vec<(TimeTableIdx, u32)> = Vec::with_capacity(count);
+    for &idx in waves.pc.time_indices() {
+        if samples.len() == count {
+            break;
+        }
+        let Some(pc) = u32::try_from_signal(waves.pc.get_val(idx)) else {
+            continue;
+        };
+        // the pc signal can rewrite the same value while the pipeline stalls
+        if samples.last().map(|(_, last)| *last) != Some(pc) {
+            samples.push((idx, pc));
+        }
+    }
+    samples
+}
+
+/// The stack pointer moves on function entry and exit, not every other instruction
+fn check_sp(sp: &[u32]) -> Option<Finding> {
+    if sp.len() < MIN_SAMPLES {
+        return None;
+    }
+    let deltas = sp.len() - 1;
+    let changes = sp.windows(2).filter(|pair| pair[0] != pair[1]).count();
+    (changes * 2 > deltas).then(|| {
+        Finding::Warning(format!(
+            "the signal mapped to sp (x2) changes on {changes} of {deltas} sampled instructions, \
+             more than a stack pointer should"
+        ))
+    })
+}
+
+/// Calls leave a return address in ra, so if there were calls ra should point into .text
+fn check_ra(ra: &[u32], calls: usize, text: &[Range<u32>]) -> Option<Finding> {
+    if ra.len() < MIN_SAMPLES || calls == 0 || text.is_empty() {
+        return None;
+    }
+    let in_text = |value: &u32| text.iter().any(|range| range.contains(value));
+    (!ra.iter().any(in_text)).then(|| {
+        Finding::Warning(format!(
+            "the signal mapped to ra (x1) never holds a .text address over {} sampled \
+             instructions, despite {calls} calls",
+            ra.len()
+        ))
+    })
+}
+
+/// Whether `insn` is a jal/jalr that links into ra, compressed or not
+fn is_call(insn: u32) -> bool {
+    if insn & 0b11 != 0b11 {
+        let half = insn & 0xffff;
+        // c.jal (RV32 only)
+        let c_jal = half & 0xe003 == 0x2001;
+        // c.jalr, rs1 can't be zero
+        let c_jalr = half & 0xf07f == 0x9002 && half & 0x0f80 != 0;
+        return c_jal || c_jalr;
+    }
+    let opcode = insn & 0x7f;
+    let rd = (insn >> 7) & 0x1f;
+    (opcode == 0x6f || opcode == 0x67) && rd == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_call() {
+        // jal ra, 0x10
+        assert!(is_call(0x010000ef));
+        // jalr ra, 0(a5)
+        assert!(is_call(0x000780e7));
+        // j 0x10, no link
+        assert!(!is_call(0x0100006f));
+        // ret
+        assert!(!is_call(0x00008067));
+        // c.jalr a5
+        assert!(is_call(0x9782));
+        // c.jr ra
+        assert!(!is_call(0x8082));
+        // addi sp, sp, -16
+        assert!(!is_call(0xff010113));
+    }
+
+    #[test]
+    fn test_sp_heuristic() {
+        let steady: Vec<u32> = (0..100u32).map(|i| 0x1000 - (i / 10) * 16).collect();
+        assert_eq!(check_sp(&steady), None);
+
+        let busy: Vec<u32> = (0..100).collect();
+        let warning = check_sp(&busy).unwrap();
+        assert!(warning.to_string().contains("99 of 99"), "{warning}");
+
+        // not enough to go on
+        assert_eq!(check_sp(&[1, 2, 3]), None);
+    }
+
+    #[test]
+    fn test_ra_heuristic() {
+        let text = [0x100000..0x101000];
+        let in_text = vec![0x100abc; 32];
+        let data = vec![0x2000_0000; 32];
+        assert_eq!(check_ra(&in_text, 3, &text), None);
+        assert!(check_ra(&data, 3, &text).is_some());
+        // no calls, ra never had a reason to be set
+        assert_eq!(check_ra(&data, 0, &text), None);
+    }
+}
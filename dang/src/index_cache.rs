@@ -0,0 +1,345 @@
+//! Keeping a built [`PcIndex`] in a `<wave>.dangidx` file next to the waveform, so the next
+//! session over the same trace reads it back instead of walking the PC signal again.
+//!
+//! The file is keyed by the waveform's size, modification time and a hash of its first MiB, and
+//! by the PC signal the index was built from, its change count and a hash of its time indices.
+//! Anything that doesn't match, or doesn't read back cleanly, is ignored with a warning and the
+//! index is built again. The format is little endian throughout:
+//!
+//! ```text
+//! magic        b"DANGIDX\0"
+//! version      u32
+//! key          wave_len u64, mtime_secs u64, mtime_nanos u32, head_hash u64,
+//!              pc_changes u64, pc_hash u64
+//! entries      u64, then per PC ascending: pc u32, count u32, count time indices u32
+//! checksum     u64, FNV-1a over everything before it
+//! ```
+
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::UNIX_EPOCH;
+
+use wellen::{Signal, TimeTableIdx};
+
+use crate::pc_index::PcIndex;
+
+const MAGIC: &[u8; 8] = b"DANGIDX\0";
+
+/// Bumped whenever the layout changes, older files are rebuilt over
+const VERSION: u32 = 1;
+
+/// How much of the waveform goes into the key's hash
+const HEAD_BYTES: u64 = 1 << 20;
+
+/// Told apart from other writers of the same sidecar, so none of them sees a half written file
+static TMP_SUFFIX: AtomicUsize = AtomicUsize::new(0);
+
+/// 64 bit FNV-1a, cheap and stable across builds, which `DefaultHasher` isn't
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// What a sidecar has to have been written for to be read back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheKey {
+    pub wave_len: u64,
+    pub mtime_secs: u64,
+    pub mtime_nanos: u32,
+    pub head_hash: u64,
+    pub pc_changes: u64,
+    pub pc_hash: u64,
+}
+
+impl CacheKey {
+    pub fn new(wave: &Path, pc: &Signal) -> io::Result<Self> {
+        let metadata = std::fs::metadata(wave)?;
+        let mtime = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_err(io::Error::other)?;
+
+        let mut head = Vec::new();
+        File::open(wave)?.take(HEAD_BYTES).read_to_end(&mut head)?;
+        let mut head_hash = Fnv::new();
+        head_hash.write(&head);
+
+        let mut pc_hash = Fnv::new();
+        for idx in pc.time_indices() {
+            pc_hash.write(&idx.to_le_bytes());
+        }
+
+        Ok(CacheKey {
+            wave_len: metadata.len(),
+            mtime_secs: mtime.as_secs(),
+            mtime_nanos: mtime.subsec_nanos(),
+            head_hash: head_hash.0,
+            pc_changes: pc.time_indices().len() as u64,
+            pc_hash: pc_hash.0,
+        })
+    }
+}
+
+/// The bytes of a sidecar holding `index`
+pub fn encode(key: &CacheKey, index: &PcIndex) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.extend_from_slice(&key.wave_len.to_le_bytes());
+    out.extend_from_slice(&key.mtime_secs.to_le_bytes());
+    out.extend_from_slice(&key.mtime_nanos.to_le_bytes());
+    out.extend_from_slice(&key.head_hash.to_le_bytes());
+    out.extend_from_slice(&key.pc_changes.to_le_bytes());
+    out.extend_from_slice(&key.pc_hash.to_le_bytes());
+
+    out.extend_from_slice(&(index.visited_count() as u64).to_le_bytes());
+    for (pc, occurrences) in index.entries() {
+        out.extend_from_slice(&pc.to_le_bytes());
+        out.extend_from_slice(&(occurrences.len() as u32).to_le_bytes());
+        for idx in occurrences {
+            out.extend_from_slice(&idx.to_le_bytes());
+        }
+    }
+
+    let mut checksum = Fnv::new();
+    checksum.write(&out);
+    out.extend_from_slice(&checksum.0.to_le_bytes());
+    out
+}
+
+/// Reads through a sidecar, every read failing on running out of bytes
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        if self.bytes.len() < N {
+            return Err("truncated".to_string());
+        }
+        let (head, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        Ok(head.try_into().unwrap())
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        self.take().map(u64::from_le_bytes)
+    }
+}
+
+/// The key and index a sidecar holds. Checks everything it can, so a damaged file is an error
+/// rather than a wrong index
+pub fn decode(bytes: &[u8]) -> Result<(CacheKey, PcIndex), String> {
+    if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+        return Err("not a dang index file".to_string());
+    }
+    let Some(body_len) = bytes.len().checked_sub(8) else {
+        return Err("truncated".to_string());
+    };
+    let (body, checksum) = bytes.split_at(body_len);
+    let mut expected = Fnv::new();
+    expected.write(body);
+    if expected.0.to_le_bytes() != checksum {
+        return Err("checksum mismatch".to_string());
+    }
+
+    let mut reader = Reader {
+        bytes: &body[MAGIC.len()..],
+    };
+    let version = reader.u32()?;
+    if version != VERSION {
+        return Err(format!("version {version}, expected {VERSION}"));
+    }
+    let key = CacheKey {
+        wave_len: reader.u64()?,
+        mtime_secs: reader.u64()?,
+        mtime_nanos: reader.u32()?,
+        head_hash: reader.u64()?,
+        pc_changes: reader.u64()?,
+        pc_hash: reader.u64()?,
+    };
+
+    let count = reader.u64()?;
+    let mut entries = Vec::new();
+    let mut last_pc = None;
+    for _ in 0..count {
+        let pc = reader.u32()?;
+        if last_pc.is_some_and(|last| last >= pc) {
+            return Err(format!("pc {pc:x} out of order"));
+        }
+        last_pc = Some(pc);
+
+        let len = reader.u32()? as usize;
+        // checked against what's left before anything is allocated for it
+        if len == 0 || len > reader.bytes.len() / 4 {
+            return Err(format!("bad occurrence count {len} for pc {pc:x}"));
+        }
+        let occurrences = (0..len)
+            .map(|_| reader.u32())
+            .collect::<Result<Vec<TimeTableIdx>, String>>()?;
+        if occurrences.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(format!("time indices of pc {pc:x} out of order"));
+        }
+        entries.push((pc, occurrences));
+    }
+    if !reader.bytes.is_empty() {
+        return Err(format!("{} trailing bytes", reader.bytes.len()));
+    }
+    Ok((key, PcIndex::from_entries(entries)))
+}
+
+/// Where a built index is saved, and which waveform it's checked against
+#[derive(Debug, Clone)]
+pub struct IndexCache {
+    wave: PathBuf,
+    sidecar: PathBuf,
+}
+
+impl IndexCache {
+    pub fn new(wave: PathBuf, sidecar: PathBuf) -> Self {
+        IndexCache { wave, sidecar }
+    }
+
+    /// `<wave>.dangidx`, next to the waveform
+    pub fn beside(wave: &Path) -> Self {
+        let mut sidecar = OsString::from(wave.as_os_str());
+        sidecar.push(".dangidx");
+        IndexCache::new(wave.to_path_buf(), PathBuf::from(sidecar))
+    }
+
+    pub fn sidecar(&self) -> &Path {
+        &self.sidecar
+    }
+
+    /// The index saved for this waveform and `pc`, if there's one that still matches. A missing
+    /// file is the usual first run, anything else that stops it being used gets a warning
+    pub fn load(&self, pc: &Signal) -> Option<PcIndex> {
+        let bytes = match std::fs::read(&self.sidecar) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                log::warn!("Could not read {}: {e}", self.sidecar.display());
+                return None;
+            }
+        };
+        let key = match CacheKey::new(&self.wave, pc) {
+            Ok(key) => key,
+            Err(e) => {
+                log::warn!("Could not key {}: {e}", self.wave.display());
+                return None;
+            }
+        };
+        match decode(&bytes) {
+            Ok((saved, index)) if saved == key => {
+                log::debug!("PC index loaded from {}", self.sidecar.display());
+                Some(index)
+            }
+            Ok(_) => {
+                log::info!(
+                    "{} was written for another trace, rebuilding",
+                    self.sidecar.display()
+                );
+                None
+            }
+            Err(e) => {
+                log::warn!("Ignoring {}: {e}, rebuilding", self.sidecar.display());
+                None
+            }
+        }
+    }
+
+    /// Save `index`, built from `pc`. Written aside and renamed over the sidecar, so readers
+    /// only ever see a whole file. Failing to is only worth a warning, the index is still good
+    pub fn store(&self, pc: &Signal, index: &PcIndex) {
+        let result = CacheKey::new(&self.wave, pc).and_then(|key| {
+            let mut tmp = OsString::from(self.sidecar.as_os_str());
+            tmp.push(format!(
+                ".{}.{}.tmp",
+                std::process::id(),
+                TMP_SUFFIX.fetch_add(1, Ordering::Relaxed)
+            ));
+            std::fs::write(&tmp, encode(&key, index))?;
+            std::fs::rename(&tmp, &self.sidecar).inspect_err(|_| {
+                let _ = std::fs::remove_file(&tmp);
+            })
+        });
+        match result {
+            Ok(()) => log::debug!("PC index saved to {}", self.sidecar.display()),
+            Err(e) => log::warn!("Could not save {}: {e}", self.sidecar.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> CacheKey {
+        CacheKey {
+            wave_len: 1313339,
+            mtime_secs: 1_700_000_000,
+            mtime_nanos: 42,
+            head_hash: 0xdead_beef,
+            pc_changes: 5,
+            pc_hash: 0xfeed,
+        }
+    }
+
+    fn index() -> PcIndex {
+        PcIndex::from_entries([(0x100, vec![0, 4]), (0x104, vec![1]), (0x108, vec![2, 3])])
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let bytes = encode(&key(), &index());
+        let (saved, loaded) = decode(&bytes).unwrap();
+        assert_eq!(saved, key());
+        assert_eq!(loaded, index());
+        assert_eq!(loaded.occurrences(0x100), [0, 4]);
+
+        let (_, empty) = decode(&encode(&key(), &PcIndex::default())).unwrap();
+        assert_eq!(empty.visited_count(), 0);
+    }
+
+    #[test]
+    fn test_damage_is_an_error() {
+        let bytes = encode(&key(), &index());
+        assert!(decode(&[]).is_err());
+        assert!(decode(b"DANGIDX\0").is_err());
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode(&bytes[..bytes.len() / 2]).is_err());
+
+        let mut flipped = bytes.clone();
+        flipped[40] ^= 1;
+        assert_eq!(decode(&flipped).unwrap_err(), "checksum mismatch");
+
+        let mut magic = bytes.clone();
+        magic[0] = b'X';
+        assert_eq!(decode(&magic).unwrap_err(), "not a dang index file");
+
+        // a future version, with the checksum redone so only the version is off
+        let mut newer = bytes[..bytes.len() - 8].to_vec();
+        newer[8..12].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        let mut checksum = Fnv::new();
+        checksum.write(&newer);
+        newer.extend_from_slice(&checksum.0.to_le_bytes());
+        assert!(decode(&newer).unwrap_err().starts_with("version"));
+    }
+}
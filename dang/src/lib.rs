@@ -1,8 +1,27 @@
+pub mod assertions;
+pub mod bounded_output;
+pub mod breakpoints;
 pub mod cli;
 pub mod convert;
+pub mod disasm;
+pub mod error;
+pub mod export;
 pub mod gdb;
+pub mod gpr_check;
+pub mod index_cache;
+pub mod monitor_batch;
+pub mod observer;
+pub mod pc_index;
 pub mod runtime;
+pub mod skip_ranges;
+pub mod stop_context;
+pub mod thread_check;
+pub mod tidx_log;
+pub mod var_list;
+pub mod wave_watch;
 pub mod waveloader;
 
-pub use cli::{start, start_with_args, start_with_args_and_port, start_with_args_and_listener, start_with_args_and_listener_silent};
+#[allow(deprecated)]
+pub use cli::{start_with_args, start_with_args_and_port, start_with_args_and_listener, start_with_args_and_listener_silent};
+pub use cli::{DangOptions, SessionOutcome, start, start_with_options, start_with_options_and_port, start_with_options_and_listener, start_with_options_and_listener_silent};
 pub use runtime::Waver;
\ No newline at end of file
@@ -1,12 +1,20 @@
+mod assertions;
 pub mod cli;
 pub(crate) mod convert;
+mod disasm;
+mod error;
 mod gdb;
+mod gpr_check;
+mod observer;
 pub mod runtime;
+mod tidx_log;
+mod var_list;
 mod waveloader;
 
 fn main() {
     let app_err = cli::start();
     if let Err(err) = app_err {
-        panic!("Failed to run dang with error {err}")
+        eprintln!("error: dang could not start\n\n  {err}");
+        std::process::exit(2);
     }
 }
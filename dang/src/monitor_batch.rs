@@ -0,0 +1,102 @@
+//! `monitor batch <cmd>; <cmd>; ...`, several monitor commands in one round trip. Each
+//! sub-command's output comes back under a `== <cmd> ==` header line. A `;` inside a
+//! sub-command is written `\;`
+
+/// What a sub-command that couldn't run has in place of its output
+pub const FAILED: &str = "error: ";
+
+/// The line each sub-command's output starts with
+pub fn header(cmd: &str) -> String {
+    format!("== {cmd} ==")
+}
+
+/// The sub-commands of a `batch` argument, trimmed, with `\;` turned back into `;`. Empty ones
+/// are dropped
+pub fn split(args: &str) -> Vec<String> {
+    let mut cmds = Vec::new();
+    let mut current = String::new();
+    let mut chars = args.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&';') => {
+                current.push(';');
+                chars.next();
+            }
+            ';' => cmds.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    cmds.push(current);
+    cmds.into_iter()
+        .map(|cmd| cmd.trim().to_string())
+        .filter(|cmd| !cmd.is_empty())
+        .collect()
+}
+
+/// The `batch` argument running `cmds` in order, the inverse of [`split`]
+pub fn join(cmds: &[&str]) -> String {
+    cmds.iter()
+        .map(|cmd| cmd.trim().replace(';', "\\;"))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// A `batch` reply cut back into each sub-command and its output, in order. Anything before
+/// the first header is dropped
+pub fn split_output(output: &str) -> Vec<(String, String)> {
+    let mut sections: Vec<(String, String)> = Vec::new();
+    for line in output.lines() {
+        let cmd = line
+            .strip_prefix("== ")
+            .and_then(|rest| rest.strip_suffix(" =="));
+        match (cmd, sections.last_mut()) {
+            (Some(cmd), _) => sections.push((cmd.to_string(), String::new())),
+            (None, Some((_, body))) => {
+                body.push_str(line);
+                body.push('\n');
+            }
+            (None, None) => {}
+        }
+    }
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_and_join() {
+        assert_eq!(
+            split("time_idx; resume_stats ;meminfo"),
+            ["time_idx", "resume_stats", "meminfo"]
+        );
+        assert_eq!(split(" ; ;"), Vec::<String>::new());
+        assert_eq!(split(r"echo a\;b; time_idx"), ["echo a;b", "time_idx"]);
+        // a lone backslash stays
+        assert_eq!(split(r"a\b"), [r"a\b"]);
+
+        let cmds = ["goto_idx 5", "echo x;y", "time_idx"];
+        assert_eq!(join(&cmds), r"goto_idx 5; echo x\;y; time_idx");
+        assert_eq!(split(&join(&cmds)), cmds);
+    }
+
+    #[test]
+    fn test_split_output() {
+        let output = format!(
+            "{}\n42\n{}\n{}\n1 2 3 4\n",
+            header("time_idx"),
+            header("breakpoints"),
+            header("resume_stats")
+        );
+        assert_eq!(
+            split_output(&output),
+            [
+                ("time_idx".to_string(), "42\n".to_string()),
+                ("breakpoints".to_string(), String::new()),
+                ("resume_stats".to_string(), "1 2 3 4\n".to_string()),
+            ]
+        );
+        assert!(split_output("stray\n").is_empty());
+    }
+}
@@ -0,0 +1,331 @@
+//! Read-only observer connections.
+//!
+//! The primary connection drives the [`Waver`]. Observers share the same state through an
+//! `Arc<Mutex<..>>` and can read registers and memory or run monitor queries, but anything that
+//! would move the cursor or change the target is refused
+
+use std::net::TcpListener;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+use gdbstub::common::{Pid, Signal};
+use gdbstub::conn::{Connection, ConnectionExt};
+use gdbstub::outputln;
+use gdbstub::stub::{run_blocking, SingleThreadStopReason};
+use gdbstub::target::ext::base::singlethread::SingleThreadBase;
+use gdbstub::target::ext::breakpoints::{Breakpoints, SwBreakpoint};
+use gdbstub::target::ext::exec_file::ExecFile;
+use gdbstub::target::ext::monitor_cmd::{ConsoleOutput, MonitorCmd};
+use gdbstub::target::{self, Target, TargetError, TargetResult};
+use gdbstub::{arch::Arch, target::ext::base::BaseOps};
+use gdbstub_arch::riscv::Riscv32;
+
+use crate::cli;
+use crate::runtime::Waver;
+use crate::thread_check::ThreadCheck;
+use crate::tidx_log::TargetScope;
+
+pub type SharedWaver = Arc<Mutex<Waver>>;
+
+/// errno handed back to observers for anything that would change the target
+pub const EPERM: u8 = 1;
+
+const READ_ONLY: &str = "observer connection is read-only";
+
+/// Monitor commands that change target state, refused for observers
+const MUTATING_MONITOR_CMDS: &[&str] = &[
+    "set_livelock_threshold",
+    "stop_on_livelock",
+    "goto_idx",
+    "goto_time",
+    "fastforward_ranges",
+    "bp_ignore",
+    "set_bp_match",
+    "assert_reg",
+    "assert_pc_range",
+    "assert_del",
+];
+
+/// `skip_range` sub-commands that change the ranges, `list` is fine
+const MUTATING_SKIP_RANGE_CMDS: &[&str] = &["add", "del"];
+
+/// Why an observer can't run the monitor command `cmd`, if it can't
+fn refusal(cmd: &str) -> Option<&'static str> {
+    let mut words = cmd.split_whitespace();
+    let name = words.next().unwrap_or("");
+    let mutating = MUTATING_MONITOR_CMDS.contains(&name)
+        || (name == "skip_range"
+            && words
+                .next()
+                .is_some_and(|sub| MUTATING_SKIP_RANGE_CMDS.contains(&sub)));
+    mutating.then_some(READ_ONLY)
+}
+
+/// Lock the shared waver. A panic on another connection shouldn't take every observer down with
+/// it, so a poisoned lock is used as is
+pub fn lock_waver(shared: &Mutex<Waver>) -> WaverGuard<'_> {
+    let guard = shared
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    // drop order is declaration order, so the scope outlives the lock
+    WaverGuard {
+        _scope: TargetScope::enter(guard.cursor.time_idx.into()),
+        guard,
+    }
+}
+
+/// The locked waver. While it's held, log records from this thread carry the cursor's time index
+pub struct WaverGuard<'a> {
+    guard: MutexGuard<'a, Waver>,
+    _scope: TargetScope,
+}
+
+impl Deref for WaverGuard<'_> {
+    type Target = Waver;
+
+    fn deref(&self) -> &Waver {
+        &self.guard
+    }
+}
+
+impl DerefMut for WaverGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Waver {
+        &mut self.guard
+    }
+}
+
+/// Accept up to `count` observer connections on `listener`, each served on its own thread
+pub fn spawn_observers(listener: TcpListener, shared: SharedWaver, count: usize) {
+    if count == 0 {
+        return;
+    }
+    thread::spawn(move || {
+        for _ in 0..count {
+            let (stream, addr) = match listener.accept() {
+                Ok(conn) => conn,
+                Err(e) => {
+                    log::warn!("failed to accept observer connection: {e}");
+                    return;
+                }
+            };
+            log::info!("Observer connected from {addr}");
+
+            let mut observer = Observer {
+                waver: shared.clone(),
+            };
+            thread::spawn(move || {
+                let connection: Box<dyn ConnectionExt<Error = std::io::Error>> = Box::new(ThreadCheck::new(stream));
+                match cli::new_stub(connection).run_blocking::<ObserverEventLoop>(&mut observer) {
+                    Ok(reason) => log::info!("Observer {addr} disconnected: {reason:?}"),
+                    Err(e) => log::warn!("Observer {addr} session ended: {e}"),
+                }
+            });
+        }
+    });
+}
+
+/// A read-only view of the shared waver, one per observer connection
+pub struct Observer {
+    waver: SharedWaver,
+}
+
+impl Observer {
+    fn waver(&self) -> WaverGuard<'_> {
+        lock_waver(&self.waver)
+    }
+}
+
+enum ObserverEventLoop {}
+
+impl run_blocking::BlockingEventLoop for ObserverEventLoop {
+    type Target = Observer;
+    type Connection = Box<dyn ConnectionExt<Error = std::io::Error>>;
+    type StopReason = SingleThreadStopReason<u32>;
+
+    #[allow(clippy::type_complexity)]
+    fn wait_for_stop_reason(
+        _target: &mut Observer,
+        conn: &mut Self::Connection,
+    ) -> Result<
+        run_blocking::Event<SingleThreadStopReason<u32>>,
+        run_blocking::WaitForStopReasonError<
+            <Self::Target as Target>::Error,
+            <Self::Connection as Connection>::Error,
+        >,
+    > {
+        // Observers can't resume, so there is never anything to wait on besides the client
+        let byte = conn
+            .read()
+            .map_err(run_blocking::WaitForStopReasonError::Connection)?;
+        Ok(run_blocking::Event::IncomingData(byte))
+    }
+
+    fn on_interrupt(
+        _target: &mut Observer,
+    ) -> Result<Option<SingleThreadStopReason<u32>>, <Observer as Target>::Error> {
+        Ok(Some(SingleThreadStopReason::Signal(Signal::SIGINT)))
+    }
+}
+
+impl Target for Observer {
+    type Error = &'static str;
+    type Arch = Riscv32;
+
+    #[inline(always)]
+    fn base_ops(&mut self) -> BaseOps<'_, Self::Arch, Self::Error> {
+        BaseOps::SingleThread(self)
+    }
+
+    #[inline(always)]
+    fn support_breakpoints(
+        &mut self,
+    ) -> Option<target::ext::breakpoints::BreakpointsOps<'_, Self>> {
+        Some(self)
+    }
+
+    #[inline(always)]
+    fn support_monitor_cmd(&mut self) -> Option<target::ext::monitor_cmd::MonitorCmdOps<'_, Self>> {
+        Some(self)
+    }
+
+    #[inline(always)]
+    fn support_exec_file(&mut self) -> Option<target::ext::exec_file::ExecFileOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SingleThreadBase for Observer {
+    fn read_registers(
+        &mut self,
+        regs: &mut <Riscv32 as Arch>::Registers,
+    ) -> TargetResult<(), Self> {
+        self.waver().read_registers(regs)
+    }
+
+    fn write_registers(&mut self, _regs: &<Riscv32 as Arch>::Registers) -> TargetResult<(), Self> {
+        log::warn!("{READ_ONLY}, refusing register write");
+        Err(TargetError::Errno(EPERM))
+    }
+
+    fn read_addrs(&mut self, start_addr: u32, data: &mut [u8]) -> TargetResult<usize, Self> {
+        self.waver().read_addrs(start_addr, data)
+    }
+
+    fn write_addrs(&mut self, start_addr: u32, _data: &[u8]) -> TargetResult<(), Self> {
+        log::warn!("{READ_ONLY}, refusing memory write at 0x{start_addr:x}");
+        Err(TargetError::Errno(EPERM))
+    }
+
+    // No resume support at all, so `c`/`s`/`vCont` get an empty (unsupported) reply. Failing
+    // them from here would be a fatal error and end the observer's session
+}
+
+impl Breakpoints for Observer {
+    #[inline(always)]
+    fn support_sw_breakpoint(
+        &mut self,
+    ) -> Option<target::ext::breakpoints::SwBreakpointOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SwBreakpoint for Observer {
+    fn add_sw_breakpoint(
+        &mut self,
+        addr: u32,
+        _kind: <Riscv32 as Arch>::BreakpointKind,
+    ) -> TargetResult<bool, Self> {
+        log::warn!("{READ_ONLY}, refusing breakpoint at 0x{addr:x}");
+        Err(TargetError::Errno(EPERM))
+    }
+
+    fn remove_sw_breakpoint(
+        &mut self,
+        addr: u32,
+        _kind: <Riscv32 as Arch>::BreakpointKind,
+    ) -> TargetResult<bool, Self> {
+        log::warn!("{READ_ONLY}, refusing to remove breakpoint at 0x{addr:x}");
+        Err(TargetError::Errno(EPERM))
+    }
+}
+
+impl MonitorCmd for Observer {
+    fn handle_monitor_cmd(
+        &mut self,
+        cmd: &[u8],
+        mut out: ConsoleOutput<'_>,
+    ) -> Result<(), Self::Error> {
+        let Ok(cmd) = std::str::from_utf8(cmd) else {
+            outputln!(out, "command must be valid UTF-8");
+            return Ok(());
+        };
+        // a batch is checked one sub-command at a time, the rest of it still runs
+        self.waver().monitor_cmd(cmd, &mut out, refusal)
+    }
+}
+
+impl ExecFile for Observer {
+    fn get_exec_file(
+        &self,
+        pid: Option<Pid>,
+        offset: u64,
+        length: usize,
+        buf: &mut [u8],
+    ) -> TargetResult<usize, Self> {
+        self.waver().get_exec_file(pid, offset, length, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::tests::fixture_waver;
+
+    fn run(waver: &mut Waver, cmd: &str) -> String {
+        let mut out = String::new();
+        waver.monitor_cmd(cmd, &mut out, refusal).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_observer_cant_touch_assertions() {
+        let mut waver = fixture_waver();
+        let mut primary = String::new();
+        waver
+            .monitor_cmd("assert_reg 2 == 0", &mut primary, |_| None)
+            .unwrap();
+        let id = waver.assertions.iter().next().unwrap().id;
+
+        for cmd in [
+            "assert_reg 2 == 0".to_string(),
+            "assert_pc_range 0x100000 0x100100 inside".to_string(),
+            format!("assert_del {id}"),
+        ] {
+            assert_eq!(run(&mut waver, &cmd), format!("{READ_ONLY}\n"), "{cmd}");
+        }
+        assert_eq!(waver.assertions.iter().count(), 1);
+        // listing them is fine
+        assert!(run(&mut waver, "assert_list").starts_with(&format!("{id} ")));
+    }
+
+    #[test]
+    fn test_observer_cant_touch_skip_ranges() {
+        let mut waver = fixture_waver();
+        let mut primary = String::new();
+        waver
+            .monitor_cmd("skip_range add 0x100000 0x100100", &mut primary, |_| None)
+            .unwrap();
+        let id = waver.skip_ranges.iter().next().unwrap().id;
+
+        for cmd in [
+            "skip_range add 0x100200 0x100300".to_string(),
+            format!("skip_range del {id}"),
+            format!("batch skip_range del {id}"),
+        ] {
+            assert!(run(&mut waver, &cmd).contains(READ_ONLY), "{cmd}");
+        }
+        assert_eq!(waver.skip_ranges.iter().count(), 1);
+        assert_eq!(run(&mut waver, "skip_range list"), primary);
+    }
+}
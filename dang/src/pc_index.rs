@@ -0,0 +1,524 @@
+//! Every time index the PC takes each of its values at, for the monitor commands that look a PC
+//! up across the whole trace (`coverage` and friends) instead of walking the PC signal each time.
+//!
+//! The trace never changes under a session, so the index is built once, on a background thread
+//! the first time something asks for it. Until it's done, those commands answer with
+//! [`BuildProgress`] and the client retries. A built index is saved next to the waveform, see
+//! [`crate::index_cache`], and the next session over the same trace reads it back instead.
+//! `monitor index_status` says which of those happened
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use smallvec::SmallVec;
+use wellen::{Signal, TimeTableIdx};
+
+use crate::convert::Mappable;
+use crate::index_cache::IndexCache;
+use crate::waveloader::WellenSignalExt;
+
+/// What a not yet built index answers with, ahead of the progress
+pub const BUILDING: &str = "index building";
+
+/// How many PC entries the build gets through between progress updates and checks for being
+/// cancelled
+const PROGRESS_STRIDE: usize = 4096;
+
+/// Most PCs show up a handful of times, loops are the ones that spill
+type Occurrences = SmallVec<[TimeTableIdx; 4]>;
+
+/// PC value to every time index the PC signal holds it at, sorted
+#[derive(Debug, Default, PartialEq)]
+pub struct PcIndex {
+    occurrences: BTreeMap<u32, Occurrences>,
+}
+
+impl PcIndex {
+    /// One pass over the changes of `pc`, counting them off in `done` as it goes. Entries with
+    /// x or z bits aren't any PC and are left out. `None` if `cancel` gets set along the way
+    pub fn build(pc: &Signal, done: &AtomicUsize, cancel: &AtomicBool) -> Option<Self> {
+        let mut occurrences: BTreeMap<u32, Occurrences> = BTreeMap::new();
+        for (n, &idx) in pc.time_indices().iter().enumerate() {
+            if let Some(value) = u32::try_from_signal(pc.get_val(idx)) {
+                // the changes come in order, so each list stays sorted
+                occurrences.entry(value).or_default().push(idx);
+            }
+            if n % PROGRESS_STRIDE == 0 {
+                if cancel.load(Ordering::Relaxed) {
+                    log::debug!("PC index build cancelled {n} entries in");
+                    return None;
+                }
+                done.store(n, Ordering::Relaxed);
+            }
+        }
+        done.store(pc.time_indices().len(), Ordering::Relaxed);
+        Some(Self { occurrences })
+    }
+
+    /// An index from each PC and its time indices, which have to be sorted. For reading one
+    /// back from [`crate::index_cache`]
+    pub(crate) fn from_entries(
+        entries: impl IntoIterator<Item = (u32, Vec<TimeTableIdx>)>,
+    ) -> Self {
+        Self {
+            occurrences: entries
+                .into_iter()
+                .map(|(pc, occurrences)| (pc, Occurrences::from_vec(occurrences)))
+                .collect(),
+        }
+    }
+
+    /// Every PC with its time indices, ascending, for writing the index out
+    pub(crate) fn entries(&self) -> impl Iterator<Item = (u32, &[TimeTableIdx])> {
+        self.occurrences
+            .iter()
+            .map(|(pc, occurrences)| (*pc, occurrences.as_slice()))
+    }
+
+    /// Every time index the PC is `pc` at, oldest first
+    pub fn occurrences(&self, pc: u32) -> &[TimeTableIdx] {
+        self.occurrences.get(&pc).map_or(&[], |o| o.as_slice())
+    }
+
+    /// The first time the PC is `pc` at or after `idx`
+    pub fn first_at_or_after(&self, pc: u32, idx: TimeTableIdx) -> Option<TimeTableIdx> {
+        let occurrences = self.occurrences(pc);
+        occurrences
+            .get(occurrences.partition_point(|&i| i < idx))
+            .copied()
+    }
+
+    /// How many distinct PCs the trace visits
+    pub fn visited_count(&self) -> usize {
+        self.occurrences.len()
+    }
+
+    /// How many times the PC is at each address in `range` it visits at all. A backwards
+    /// range has nothing in it
+    pub fn visit_counts(
+        &self,
+        range: RangeInclusive<u32>,
+    ) -> impl Iterator<Item = (u32, u64)> + '_ {
+        let (start, end) = range.into_inner();
+        self.occurrences
+            .range(start..=end.max(start))
+            .filter(move |(pc, _)| **pc <= end)
+            .map(|(pc, occurrences)| (*pc, occurrences.len() as u64))
+    }
+
+    /// Rough heap footprint, for `meminfo`. Counts the entries and spilled lists, not the
+    /// tree's own node overhead
+    pub fn heap_bytes(&self) -> usize {
+        let entry = std::mem::size_of::<u32>() + std::mem::size_of::<Occurrences>();
+        self.occurrences
+            .values()
+            .map(|o| {
+                let spilled = if o.spilled() {
+                    o.capacity() * std::mem::size_of::<TimeTableIdx>()
+                } else {
+                    0
+                };
+                entry + spilled
+            })
+            .sum()
+    }
+}
+
+/// How far along a [`PcIndex`] build is. Displays as the monitor reply for "not yet"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuildProgress {
+    pub done: usize,
+    pub total: usize,
+}
+
+impl BuildProgress {
+    pub fn percent(&self) -> usize {
+        (self.done * 100).checked_div(self.total).unwrap_or(0)
+    }
+}
+
+impl fmt::Display for BuildProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{BUILDING}, {}% done, retry shortly", self.percent())
+    }
+}
+
+/// Where a ready index came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexSource {
+    /// Walked the PC signal this session
+    Built,
+    /// Read back from the sidecar an earlier session saved
+    Cache,
+}
+
+/// Reads the index back from `cache` if it has a good one, otherwise builds it and saves it
+/// there. `None` if the build was cancelled
+fn load_or_build(
+    pc: &Signal,
+    cache: Option<&IndexCache>,
+    done: &AtomicUsize,
+    cancel: &AtomicBool,
+) -> Option<(PcIndex, IndexSource)> {
+    if let Some(index) = cache.and_then(|cache| cache.load(pc)) {
+        done.store(pc.time_indices().len(), Ordering::Relaxed);
+        return Some((index, IndexSource::Cache));
+    }
+    let index = PcIndex::build(pc, done, cancel)?;
+    if let Some(cache) = cache {
+        cache.store(pc, &index);
+    }
+    Some((index, IndexSource::Built))
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    NotStarted,
+    Building {
+        done: Arc<AtomicUsize>,
+        total: usize,
+        handle: JoinHandle<Option<(PcIndex, IndexSource)>>,
+    },
+    Ready(PcIndex, IndexSource),
+}
+
+/// A [`PcIndex`] that starts building the first time it's asked for. Dropping it mid build
+/// cancels the build and waits for the thread to give up
+#[derive(Default)]
+pub struct LazyPcIndex {
+    state: State,
+    /// Where the index is looked for before building it, and saved after
+    cache: Option<IndexCache>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl LazyPcIndex {
+    /// An index that's read from and saved to `cache`
+    pub fn with_cache(cache: IndexCache) -> Self {
+        LazyPcIndex {
+            state: State::NotStarted,
+            cache: Some(cache),
+            cancel: Arc::default(),
+        }
+    }
+
+    /// Whether the build has been started, or is already done
+    pub fn is_started(&self) -> bool {
+        !matches!(self.state, State::NotStarted)
+    }
+
+    /// Neither read the index from the sidecar nor save it there, for a build that hasn't
+    /// started yet
+    pub fn forget_cache(&mut self) {
+        self.cache = None;
+    }
+
+    /// The index once it's built. Until then this starts the build if it hasn't yet, and says
+    /// how far along it is
+    pub fn get(&mut self, pc: &Arc<Signal>) -> Result<&PcIndex, BuildProgress> {
+        if let State::NotStarted = self.state {
+            let done = Arc::new(AtomicUsize::new(0));
+            let total = pc.time_indices().len();
+            let handle = {
+                let (pc, done, cancel) =
+                    (Arc::clone(pc), Arc::clone(&done), Arc::clone(&self.cancel));
+                let cache = self.cache.clone();
+                thread::spawn(move || load_or_build(&pc, cache.as_ref(), &done, &cancel))
+            };
+            log::debug!("Building the PC index over {total} entries");
+            self.state = State::Building {
+                done,
+                total,
+                handle,
+            };
+        }
+        if matches!(&self.state, State::Building { handle, .. } if handle.is_finished()) {
+            let State::Building { handle, .. } = std::mem::take(&mut self.state) else {
+                unreachable!()
+            };
+            // only a drop cancels, and that doesn't come back here
+            let (index, source) = handle
+                .join()
+                .expect("building the PC index panicked")
+                .expect("the PC index build was cancelled");
+            log::debug!("PC index ready, {} PCs", index.visited_count());
+            self.state = State::Ready(index, source);
+        }
+
+        match &self.state {
+            State::Ready(index, _) => Ok(index),
+            State::Building { done, total, .. } => Err(BuildProgress {
+                done: done.load(Ordering::Relaxed),
+                total: *total,
+            }),
+            State::NotStarted => unreachable!("the build was just started"),
+        }
+    }
+
+    /// The index if it's already built, without starting anything
+    pub fn ready(&self) -> Option<&PcIndex> {
+        match &self.state {
+            State::Ready(index, _) => Some(index),
+            _ => None,
+        }
+    }
+
+    /// Where the index came from once it's ready
+    pub fn source(&self) -> Option<IndexSource> {
+        match &self.state {
+            State::Ready(_, source) => Some(*source),
+            _ => None,
+        }
+    }
+
+    /// For `index_status`: not built, building with how far along, or built and where from
+    pub fn status(&self) -> String {
+        match &self.state {
+            State::NotStarted => "not built".to_string(),
+            State::Building { done, total, .. } => {
+                let progress = BuildProgress {
+                    done: done.load(Ordering::Relaxed),
+                    total: *total,
+                };
+                format!("building, {}% done", progress.percent())
+            }
+            State::Ready(index, IndexSource::Built) => {
+                format!("built, {} PCs", index.visited_count())
+            }
+            State::Ready(index, IndexSource::Cache) => {
+                format!("built, {} PCs, loaded from cache", index.visited_count())
+            }
+        }
+    }
+
+    /// For `meminfo`
+    pub fn describe(&self) -> String {
+        match &self.state {
+            State::NotStarted => "not built".to_string(),
+            State::Building { done, total, .. } => BuildProgress {
+                done: done.load(Ordering::Relaxed),
+                total: *total,
+            }
+            .to_string(),
+            State::Ready(index, _) => format!(
+                "{} PCs, {} bytes",
+                index.visited_count(),
+                index.heap_bytes()
+            ),
+        }
+    }
+}
+
+impl Drop for LazyPcIndex {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        if let State::Building { handle, .. } = std::mem::take(&mut self.state) {
+            // gives up at its next chunk, and saves nothing
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::tests::fixture_waver;
+    use crate::wave_watch::WaveFingerprint;
+    use crate::Waver;
+    use std::path::PathBuf;
+    use std::sync::mpsc;
+
+    /// Spins until the background build finishes
+    fn wait(lazy: &mut LazyPcIndex, pc: &Arc<Signal>) {
+        while lazy.get(pc).is_err() {
+            thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_matches_linear_scan() {
+        let waver = fixture_waver();
+        let pc = &waver.waves.pc;
+        let mut lazy = LazyPcIndex::default();
+        wait(&mut lazy, pc);
+        let index = lazy.ready().unwrap();
+
+        let scan: Vec<(TimeTableIdx, u32)> = pc
+            .time_indices()
+            .iter()
+            .filter_map(|&idx| u32::try_from_signal(pc.get_val(idx)).map(|value| (idx, value)))
+            .collect();
+        let mut distinct: Vec<u32> = scan.iter().map(|(_, value)| *value).collect();
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert_eq!(index.visited_count(), distinct.len());
+
+        for &value in &distinct {
+            let expected: Vec<TimeTableIdx> = scan
+                .iter()
+                .filter(|(_, v)| *v == value)
+                .map(|(idx, _)| *idx)
+                .collect();
+            assert_eq!(index.occurrences(value), expected, "pc 0x{value:x}");
+        }
+        assert!(index.occurrences(0x1).is_empty());
+
+        // the first instruction of main
+        let main = index.occurrences(0x1003d0);
+        assert!(!main.is_empty());
+        assert_eq!(index.first_at_or_after(0x1003d0, 0), Some(main[0]));
+        assert_eq!(index.first_at_or_after(0x1003d0, main[0]), Some(main[0]));
+        assert_eq!(
+            index.first_at_or_after(0x1003d0, main[main.len() - 1] + 1),
+            None
+        );
+
+        let counts: Vec<(u32, u64)> = index.visit_counts(0x1003d0..=0x1003ff).collect();
+        assert_eq!(counts, waver_counts(&scan, 0x1003d0, 0x1003ff));
+        assert!(index.heap_bytes() > 0);
+    }
+
+    fn waver_counts(scan: &[(TimeTableIdx, u32)], start: u32, end: u32) -> Vec<(u32, u64)> {
+        let mut counts = BTreeMap::new();
+        for (_, value) in scan.iter().filter(|(_, v)| (start..=end).contains(v)) {
+            *counts.entry(*value).or_insert(0) += 1;
+        }
+        counts.into_iter().collect()
+    }
+
+    #[test]
+    fn test_not_ready_until_built() {
+        let waver = fixture_waver();
+        let (release, gate) = mpsc::channel::<()>();
+        let done = Arc::new(AtomicUsize::new(0));
+        let handle = {
+            let (pc, done) = (Arc::clone(&waver.waves.pc), Arc::clone(&done));
+            thread::spawn(move || {
+                done.store(1, Ordering::Relaxed);
+                gate.recv().unwrap();
+                PcIndex::build(&pc, &done, &AtomicBool::new(false))
+                    .map(|index| (index, IndexSource::Built))
+            })
+        };
+        let mut lazy = LazyPcIndex {
+            state: State::Building {
+                done: Arc::clone(&done),
+                total: 4,
+                handle,
+            },
+            cache: None,
+            cancel: Arc::default(),
+        };
+        while done.load(Ordering::Relaxed) == 0 {
+            thread::yield_now();
+        }
+
+        let progress = lazy.get(&waver.waves.pc).err().unwrap();
+        assert_eq!(progress.percent(), 25);
+        assert_eq!(
+            progress.to_string(),
+            "index building, 25% done, retry shortly"
+        );
+        assert!(lazy.ready().is_none());
+        assert!(lazy.describe().starts_with(BUILDING));
+        assert_eq!(lazy.status(), "building, 25% done");
+
+        release.send(()).unwrap();
+        wait(&mut lazy, &waver.waves.pc);
+        assert!(lazy.ready().unwrap().visited_count() > 0);
+        assert!(lazy.describe().ends_with("bytes"));
+        assert_eq!(lazy.source(), Some(IndexSource::Built));
+    }
+
+    #[test]
+    fn test_cancelled_build_gives_up() {
+        let waver = fixture_waver();
+        let done = AtomicUsize::new(0);
+        assert!(PcIndex::build(&waver.waves.pc, &done, &AtomicBool::new(true)).is_none());
+        assert!(done.load(Ordering::Relaxed) < waver.waves.pc.time_indices().len());
+    }
+
+    /// The fixture's waveform, with its sidecar somewhere of the test's own
+    fn temp_cache(name: &str) -> IndexCache {
+        let wave = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../test_data/ibex/sim.fst");
+        let sidecar =
+            std::env::temp_dir().join(format!("dang_{}_{name}.dangidx", std::process::id()));
+        let _ = std::fs::remove_file(&sidecar);
+        IndexCache::new(wave, sidecar)
+    }
+
+    /// A fresh fixture waver over `cache`, once its index is ready
+    fn cached_waver(cache: &IndexCache) -> Waver {
+        let mut waver = fixture_waver();
+        waver.pc_index = LazyPcIndex::with_cache(cache.clone());
+        wait(&mut waver.pc_index, &waver.waves.pc);
+        waver
+    }
+
+    #[test]
+    fn test_reloads_from_sidecar() {
+        let cache = temp_cache("reload");
+        let mut built = cached_waver(&cache);
+        assert_eq!(built.pc_index.source(), Some(IndexSource::Built));
+        assert!(cache.sidecar().exists());
+
+        let mut loaded = cached_waver(&cache);
+        assert_eq!(loaded.pc_index.source(), Some(IndexSource::Cache));
+        assert!(loaded.pc_index.status().ends_with("loaded from cache"));
+        assert_eq!(built.pc_index.ready(), loaded.pc_index.ready());
+        assert_eq!(
+            built.pc_visit_counts(0, u32::MAX),
+            loaded.pc_visit_counts(0, u32::MAX)
+        );
+        let main = built.pc_index.ready().unwrap().occurrences(0x1003d0);
+        assert_eq!(loaded.pc_index.ready().unwrap().occurrences(0x1003d0), main);
+
+        std::fs::remove_file(cache.sidecar()).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_sidecar_rebuilds() {
+        let cache = temp_cache("corrupt");
+        let built = cached_waver(&cache);
+        let mut bytes = std::fs::read(cache.sidecar()).unwrap();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 0xff;
+        std::fs::write(cache.sidecar(), &bytes).unwrap();
+
+        let rebuilt = cached_waver(&cache);
+        assert_eq!(rebuilt.pc_index.source(), Some(IndexSource::Built));
+        assert_eq!(built.pc_index.ready(), rebuilt.pc_index.ready());
+
+        // and so is a file that isn't an index at all, which the rebuild then replaces
+        std::fs::write(cache.sidecar(), b"not an index").unwrap();
+        let rebuilt = cached_waver(&cache);
+        assert_eq!(rebuilt.pc_index.source(), Some(IndexSource::Built));
+        let reloaded = cached_waver(&cache);
+        assert_eq!(reloaded.pc_index.source(), Some(IndexSource::Cache));
+
+        std::fs::remove_file(cache.sidecar()).unwrap();
+    }
+
+    #[test]
+    fn test_rewritten_waveform_isnt_saved() {
+        let cache = temp_cache("rewritten");
+        let mut waver = fixture_waver();
+        waver.pc_index = LazyPcIndex::with_cache(cache.clone());
+        // a waveform that's gone since it was loaded counts as rewritten
+        waver.wave_file = Some(WaveFingerprint {
+            path: cache.sidecar().with_extension("fst"),
+            size: 0,
+            mtime: None,
+        });
+        while waver.pc_visit_counts(0, u32::MAX).is_err() {
+            thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(waver.pc_index.source(), Some(IndexSource::Built));
+        assert!(!cache.sidecar().exists());
+    }
+}
@@ -1,46 +1,237 @@
 use std::collections::BTreeMap;
+use std::ops::Range;
 use std::path::PathBuf;
+use std::sync::Arc;
 
-use crate::waveloader::{self, WellenSignalExt};
+use crate::assertions::Assertions;
+use crate::breakpoints::BreakpointSet;
+use crate::error::StartupError;
+use crate::gpr_check::{self, GprReport};
+use crate::index_cache::IndexCache;
+use crate::pc_index::{BuildProgress, LazyPcIndex};
+use crate::skip_ranges::{FastForward, SkipRanges};
+use crate::stop_context::StopContext;
+use crate::tidx_log;
+use crate::var_list::VarList;
+use crate::wave_watch::WaveFingerprint;
+use crate::waveloader::{self, MappingArgs, MappingScript, WaveLoadOptions, WellenSignalExt};
 use crate::{convert::Mappable, waveloader::Loaded};
 
-use wellen::{TimeTable, TimeTableIdx};
+use anyhow::Context;
+use wellen::{TimeTable, TimeTableIdx, Timescale};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Event {
     DoneStep,
     Halted,
     Break,
+    /// The PC has been stuck in a small loop for longer than the livelock threshold
+    Livelock,
+    /// One of the [`Assertions`] stopped holding, see [`Assertions::last_violation`]
+    Assertion,
+    /// A reverse step or continue from the PC's first change, there's nothing earlier in the
+    /// trace
+    TraceStart,
     //TODO -- add this in
     //WatchWrite(u32),
     //WatchRead(u32),
 }
 
+/// When a register last changed, see [`Waver::gpr_last_change`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegChange {
+    pub time_idx: TimeTableIdx,
+    /// Simulation time at `time_idx`, in the waveform's timescale
+    pub time: u64,
+    /// The value before the change, `None` for the register's first value
+    pub previous: Option<u32>,
+}
+
+impl std::fmt::Display for RegChange {
+    /// `<time_idx> <time> <previous in hex, or ->`, the monitor reply format
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} ", self.time_idx, self.time)?;
+        match self.previous {
+            Some(previous) => write!(f, "{previous:x}"),
+            None => write!(f, "-"),
+        }
+    }
+}
+
+/// What the most recent resume did, reset by [`Waver::begin_resume`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResumeStats {
+    /// How many times the cursor moved on to the PC's next change
+    pub pc_advances: u64,
+    /// Entries of the merged PC/GPR change list between the start and end of the resume
+    pub changes_crossed: u64,
+    pub start_time_idx: TimeTableIdx,
+    pub end_time_idx: TimeTableIdx,
+}
+
+impl std::fmt::Display for ResumeStats {
+    /// `<pc_advances> <changes_crossed> <start_time_idx> <end_time_idx>`, the monitor reply
+    /// format
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.pc_advances, self.changes_crossed, self.start_time_idx, self.end_time_idx
+        )
+    }
+}
+
 pub struct WaveCursor {
     pub time_idx: TimeTableIdx,
     pub all_changes: Vec<TimeTableIdx>,
     pub all_times: TimeTable,
 }
 
+/// What a kill from GDB does, `--exit-on-kill`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KillPolicy {
+    /// End the session and stop the stub, however it was started
+    #[default]
+    Exit,
+    /// Put the cursor back at the start and keep the session, so a `run` can pick it up again
+    Stay,
+}
+
 #[derive(Debug)]
 pub enum ExecMode {
     Step,
     Continue,
     RangeStep(u32, u32),
+    /// Back to the previous instruction, see [`Waver::prev_pc`]
+    ReverseStep,
+    /// Back through the trace until the PC arrives at a breakpoint, or the trace starts
+    ReverseContinue,
 }
 
 pub struct Waver {
     pub waves: RequiredWaves,
     pub cursor: WaveCursor,
     pub mem: DummyMem,
-    pub breakpoints: Vec<u32>,
+    /// Software breakpoints, each at most once however often it's set, with their hit and
+    /// ignore counts
+    pub breakpoints: BreakpointSet,
     pub exec_mode: ExecMode,
     pub elf_path: PathBuf,
+    pub livelock: LivelockDetector,
+    pub assertions: Assertions,
+    pub vars: VarList,
+    /// What [`WaveCursor::all_times`] counts in, `None` if the waveform doesn't say
+    pub timescale: Option<Timescale>,
+    /// The waveform file as it was when it was loaded, `None` for waves built in memory
+    pub wave_file: Option<WaveFingerprint>,
+    pub resume_stats: ResumeStats,
+    /// Address ranges of the ELF's executable sections
+    pub text: Vec<Range<u32>>,
+    /// Where each PC shows up in the trace, built the first time a monitor command needs it, or
+    /// read back from the waveform's `.dangidx` sidecar
+    pub pc_index: LazyPcIndex,
+    /// PC ranges a continue can jump across, see [`Waver::fast_forward`]
+    pub skip_ranges: SkipRanges,
+    /// Where the cursor starts, and goes back to on [`Waver::reset`]
+    pub start_time_idx: TimeTableIdx,
+    pub kill_policy: KillPolicy,
+    /// Whether the current session's GDB started the program itself with `run` or a restart,
+    /// rather than attaching to it. Answers `qAttached`
+    pub launched: bool,
+    /// Target error the next resume fails with, to exercise the session's error path
+    #[cfg(test)]
+    pub(crate) fail_on_resume: Option<&'static str>,
+}
+
+/// How many distinct PCs a loop can touch and still count as a livelock
+const LIVELOCK_WINDOW: usize = 16;
+
+/// Spots the core spinning in a tight loop while continuing.
+///
+/// Keeps a ring of the last few distinct PCs. Any PC outside of the ring resets the count, so
+/// this only fires once nothing new has been executed for `threshold` steps
+pub struct LivelockDetector {
+    recent: [u32; LIVELOCK_WINDOW],
+    len: usize,
+    next: usize,
+    steps_since_new: u64,
+    since_time_idx: TimeTableIdx,
+    pub threshold: u64,
+    pub stop_on_livelock: bool,
+}
+
+impl Default for LivelockDetector {
+    fn default() -> Self {
+        Self {
+            recent: [0; LIVELOCK_WINDOW],
+            len: 0,
+            next: 0,
+            steps_since_new: 0,
+            since_time_idx: 0,
+            threshold: 1_000_000,
+            stop_on_livelock: false,
+        }
+    }
+}
+
+impl LivelockDetector {
+    /// Record a step to `pc`. Returns true on the step the loop crosses the threshold
+    pub fn observe(&mut self, pc: u32, time_idx: TimeTableIdx) -> bool {
+        if self.recent[..self.len].contains(&pc) {
+            self.steps_since_new += 1;
+            return self.steps_since_new == self.threshold;
+        }
+
+        self.recent[self.next] = pc;
+        self.next = (self.next + 1) % LIVELOCK_WINDOW;
+        self.len = (self.len + 1).min(LIVELOCK_WINDOW);
+        self.steps_since_new = 0;
+        self.since_time_idx = time_idx;
+        false
+    }
+
+    /// Number of distinct addresses the loop is spinning over
+    pub fn loop_len(&self) -> usize {
+        self.len
+    }
+
+    /// Time index of the last never-before-seen PC
+    pub fn since_time_idx(&self) -> TimeTableIdx {
+        self.since_time_idx
+    }
+
+    /// Forget the loop seen so far, keeping the threshold and whether to stop
+    pub fn clear(&mut self) {
+        *self = Self {
+            threshold: self.threshold,
+            stop_on_livelock: self.stop_on_livelock,
+            ..Self::default()
+        };
+    }
+}
+
+/// A range of memory the debugger is allowed to write to.
+/// Everything else is backed by the ELF/waveform and is read only
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScratchRegion {
+    pub start: u32,
+    pub size: u32,
+}
+
+impl ScratchRegion {
+    /// True if all of `[addr, addr + len)` is inside the region
+    pub fn contains(&self, addr: u32, len: usize) -> bool {
+        let end = addr as u64 + len as u64;
+        addr >= self.start && end <= self.start as u64 + self.size as u64
+    }
 }
 
 #[derive(Default)]
 pub struct DummyMem {
     mem: BTreeMap<u32, u8>,
+    scratch: Option<ScratchRegion>,
+    /// Writes into the scratch region, kept apart so the loaded ELF is never clobbered
+    overlay: BTreeMap<u32, u8>,
 }
 
 impl DummyMem {
@@ -49,7 +240,34 @@ impl DummyMem {
     }
 
     pub fn r8(&self, addr: u32) -> u8 {
-        self.mem.get(&addr).copied().unwrap_or(0)
+        self.overlay
+            .get(&addr)
+            .or_else(|| self.mem.get(&addr))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn set_scratch(&mut self, scratch: Option<ScratchRegion>) {
+        self.scratch = scratch;
+        self.overlay.clear();
+    }
+
+    pub fn scratch(&self) -> Option<ScratchRegion> {
+        self.scratch
+    }
+
+    /// Write `data` into the scratch region. Returns false, writing nothing, if any of it
+    /// falls outside of the region
+    pub fn write_scratch(&mut self, addr: u32, data: &[u8]) -> bool {
+        match self.scratch {
+            Some(scratch) if scratch.contains(addr, data.len()) => {
+                for (addr, val) in (addr..).zip(data) {
+                    self.overlay.insert(addr, *val);
+                }
+                true
+            }
+            _ => false,
+        }
     }
 
     pub fn r32(&self, addr: u32) -> u32 {
@@ -63,21 +281,68 @@ impl DummyMem {
 }
 
 impl Waver {
+    /// Put the cursor back where it started, as if the program had just been loaded.
+    /// Breakpoints, assertions, skip ranges and settings are kept, what the last resume left
+    /// behind isn't
     pub fn reset(&mut self) {
-        log::debug!("resetting cursor! actually doing nothing");
+        log::debug!("resetting the cursor to time idx {}", self.start_time_idx);
+        self.goto_time_idx(self.start_time_idx);
+        self.exec_mode = ExecMode::Step;
+        self.resume_stats = ResumeStats::default();
+        self.assertions.last_violation = None;
+        self.livelock.clear();
     }
 
     pub fn new(
         wave_path: PathBuf,
         py_file_path: PathBuf,
         elf_path: PathBuf,
+    ) -> anyhow::Result<Self> {
+        Self::new_with_mapping_args(wave_path, py_file_path, elf_path, &MappingArgs::new())
+    }
+
+    /// Same as [`Waver::new`], but hands `mapping_args` to the mapping script
+    pub fn new_with_mapping_args(
+        wave_path: PathBuf,
+        py_file_path: PathBuf,
+        elf_path: PathBuf,
+        mapping_args: &MappingArgs,
+    ) -> anyhow::Result<Self> {
+        let script = MappingScript::read(&py_file_path)?;
+        Self::new_with_mapping_script(
+            wave_path,
+            &script,
+            elf_path,
+            mapping_args,
+            &WaveLoadOptions::default(),
+        )
+    }
+
+    /// Same as [`Waver::new_with_mapping_args`], with a mapping script that isn't necessarily a
+    /// file, like one from `--mapping-inline`, and the waveform loaded with `load_options`
+    pub fn new_with_mapping_script(
+        wave_path: PathBuf,
+        script: &MappingScript,
+        elf_path: PathBuf,
+        mapping_args: &MappingArgs,
+        load_options: &WaveLoadOptions,
     ) -> anyhow::Result<Self> {
         // load ELF
-        let program_elf = std::fs::read(&elf_path)?;
-        let elf_header = goblin::elf::Elf::parse(&program_elf)?;
+        let program_elf = std::fs::read(&elf_path)
+            .with_context(|| StartupError::elf(format!("could not read {}", elf_path.display())))?;
+        let elf_header = goblin::elf::Elf::parse(&program_elf).with_context(|| {
+            StartupError::elf(format!("could not parse {} as an ELF", elf_path.display()))
+        })?;
 
         let mut mem = DummyMem::default();
 
+        let text = elf_header
+            .section_headers
+            .iter()
+            .filter(|h| h.is_executable())
+            .map(|h| h.sh_addr as u32..(h.sh_addr + h.sh_size) as u32)
+            .collect();
+
         // copy all in-memory sections from the ELF file into system RAM
         let sections = elf_header
             .section_headers
@@ -85,17 +350,27 @@ impl Waver {
             .filter(|h| h.is_alloc() && h.sh_type != goblin::elf::section_header::SHT_NOBITS);
 
         for h in sections {
+            let name = elf_header
+                .shdr_strtab
+                .get_at(h.sh_name)
+                .unwrap_or("<no name>");
             log::debug!(
                 "loading section {:?} into memory from [{:#010x?}..{:#010x?}]",
-                elf_header
-                    .shdr_strtab
-                    .get_at(h.sh_name)
-                    .unwrap_or("<no name>"),
+                name,
                 h.sh_addr,
                 h.sh_addr + h.sh_size,
             );
 
-            for (i, b) in program_elf[h.file_range().unwrap()].iter().enumerate() {
+            let data = h
+                .file_range()
+                .and_then(|range| program_elf.get(range))
+                .ok_or_else(|| {
+                    anyhow::anyhow!(StartupError::elf(format!(
+                        "section {name} runs past the end of {}",
+                        elf_path.display()
+                    )))
+                })?;
+            for (i, b) in data.iter().enumerate() {
                 mem.w8(h.sh_addr as u32 + i as u32, *b);
             }
         }
@@ -122,24 +397,265 @@ impl Waver {
             elf_header.entry
         );
 
-        let Loaded { cursor, waves } =
-            waveloader::Loaded::create_loaded_waves(wave_path, py_file_path, first_pc as u32)?;
+        let index_cache = IndexCache::beside(&wave_path);
+        // before reading it, so a rewrite that lands while it's loading still shows
+        let wave_file = WaveFingerprint::of(&wave_path);
+        let Loaded {
+            cursor,
+            waves,
+            var_names,
+            timescale,
+        } = waveloader::Loaded::create_loaded_waves(
+            wave_path,
+            script,
+            first_pc as u32,
+            mapping_args,
+            load_options,
+        )?;
 
-        Ok(Waver {
+        let waver = Waver {
             waves,
+            start_time_idx: cursor.time_idx,
             cursor,
             mem,
-            breakpoints: Vec::new(),
+            breakpoints: BreakpointSet::default(),
             exec_mode: ExecMode::Step,
             elf_path: elf_path.clone(),
-        })
+            livelock: LivelockDetector::default(),
+            assertions: Assertions::default(),
+            vars: VarList::new(var_names),
+            timescale,
+            wave_file,
+            resume_stats: ResumeStats::default(),
+            text,
+            pc_index: LazyPcIndex::with_cache(index_cache),
+            skip_ranges: SkipRanges::default(),
+            kill_policy: KillPolicy::default(),
+            launched: false,
+            #[cfg(test)]
+            fail_on_resume: None,
+        };
+
+        let report = waver.check_gprs();
+        report.log();
+        if let Some(error) = report.error() {
+            return Err(anyhow::anyhow!(StartupError::mapping(format!(
+                "{error} (in {})",
+                script.name
+            ))));
+        }
+        Ok(waver)
+    }
+
+    /// A waver over waves and memory that are already loaded, with nothing read from disk and
+    /// no mapping script run. For tests, which build their waves in memory, see
+    /// [`waveloader::tests::synthetic_waves`]. `elf_path` is only kept, never read
+    #[cfg(test)]
+    pub(crate) fn from_parts(
+        waves: RequiredWaves,
+        cursor: WaveCursor,
+        mem: DummyMem,
+        elf_path: PathBuf,
+    ) -> Self {
+        Waver {
+            waves,
+            start_time_idx: cursor.time_idx,
+            cursor,
+            mem,
+            breakpoints: BreakpointSet::default(),
+            exec_mode: ExecMode::Step,
+            elf_path,
+            livelock: LivelockDetector::default(),
+            assertions: Assertions::default(),
+            vars: VarList::new(Vec::new()),
+            timescale: None,
+            wave_file: None,
+            resume_stats: ResumeStats::default(),
+            text: Vec::new(),
+            pc_index: LazyPcIndex::default(),
+            skip_ranges: SkipRanges::default(),
+            kill_policy: KillPolicy::default(),
+            launched: false,
+            fail_on_resume: None,
+        }
+    }
+
+    /// Check the GPR signals against what the ABI says they should look like, see
+    /// [`gpr_check`]
+    pub fn check_gprs(&self) -> GprReport {
+        gpr_check::check_gprs(&self.waves, &self.mem, &self.text)
     }
+    /// The PC at the cursor, the [`Mappable::sentinel`] if the trace has x or z bits there or
+    /// no value yet
     pub fn get_current_pc<T: Mappable>(&self) -> T {
-        T::from_signal(self.waves.pc.get_val(self.cursor.time_idx))
+        self.waves
+            .pc
+            .try_get_val(self.cursor.time_idx)
+            .map_or_else(T::sentinel, T::from_signal)
     }
 
+    /// `x{idx}` at the cursor, the [`Mappable::sentinel`] if the trace has x or z bits there or
+    /// no value yet
     pub fn get_current_gpr(&self, idx: usize) -> u32 {
-        u32::from_signal(self.waves.gprs[idx].get_val(self.cursor.time_idx))
+        self.waves.gprs[idx]
+            .try_get_val(self.cursor.time_idx)
+            .map_or_else(u32::sentinel, u32::from_signal)
+    }
+
+    /// The cursor's time index, the PC and GPRs there and `n_inst * 4` bytes of memory from the
+    /// PC on, see [`stop_context`](crate::stop_context)
+    pub fn stop_context(&self, n_inst: usize) -> StopContext {
+        let pc: u32 = self.get_current_pc();
+        StopContext {
+            time_idx: u64::from(self.cursor.time_idx),
+            pc,
+            gprs: std::array::from_fn(|gpr| self.get_current_gpr(gpr)),
+            code: (0..n_inst as u32 * 4)
+                .map(|offset| self.mem.r8(pc.wrapping_add(offset)))
+                .collect(),
+        }
+    }
+
+    /// Every PC the trace visits in `[start, end]`, along with how many times it gets there.
+    /// The trace never changes so this is the same answer no matter where the cursor is.
+    /// Answered from the PC index, so this is how far along it is until that's built
+    pub fn pc_visit_counts(
+        &mut self,
+        start: u32,
+        end: u32,
+    ) -> Result<BTreeMap<u32, u64>, BuildProgress> {
+        if !self.pc_index.is_started() {
+            // the index would come from the old trace, so it isn't filed under the new file
+            if let Err(e) = self.check_wave_file() {
+                log::warn!("{e}, building the PC index from the loaded trace without saving it");
+                self.pc_index.forget_cache();
+            }
+        }
+        let index = self.pc_index.get(&self.waves.pc)?;
+        Ok(index.visit_counts(start..=end).collect())
+    }
+
+    /// The most recent change of GPR `gpr` at or before the cursor, `None` if it has no value
+    /// yet. Entries in the trace that rewrite the same value don't count as changes
+    pub fn gpr_last_change(&self, gpr: usize) -> Option<RegChange> {
+        let signal = self.waves.gprs.get(gpr)?;
+        let changes = signal.time_indices();
+        // sorted, so everything up to the cursor is a prefix
+        let mut pos = changes
+            .partition_point(|&idx| idx <= self.cursor.time_idx)
+            .checked_sub(1)?;
+        let value_at = |pos: usize| u32::try_from_signal(signal.get_val(changes[pos]));
+        while pos > 0 && value_at(pos - 1) == value_at(pos) {
+            pos -= 1;
+        }
+
+        let time_idx = changes[pos];
+        Some(RegChange {
+            time_idx,
+            time: self.cursor.all_times[time_idx as usize],
+            previous: pos.checked_sub(1).and_then(value_at),
+        })
+    }
+
+    /// Up to `count` values of GPR `gpr`, taken at every `stride`-th change of its signal from
+    /// the first one at or after `from`, as `(time_idx, value)`. The value is `None` where the
+    /// trace has x or z bits. Also where the next page starts, `None` once the signal runs out.
+    /// Read straight off the signal, the cursor doesn't move
+    pub fn reg_history(
+        &self,
+        gpr: usize,
+        from: TimeTableIdx,
+        count: usize,
+        stride: usize,
+    ) -> (Vec<(TimeTableIdx, Option<u32>)>, Option<TimeTableIdx>) {
+        let Some(signal) = self.waves.gprs.get(gpr) else {
+            return (Vec::new(), None);
+        };
+        let changes = signal.time_indices();
+        let first = changes.partition_point(|&idx| idx < from);
+        let mut positions = (first..changes.len()).step_by(stride.max(1));
+        let page = positions
+            .by_ref()
+            .take(count)
+            .map(|pos| {
+                (
+                    changes[pos],
+                    u32::try_from_signal(signal.get_val(changes[pos])),
+                )
+            })
+            .collect();
+        (page, positions.next().map(|pos| changes[pos]))
+    }
+
+    /// Up to `count` instructions retired after the one at `from`, or before it with
+    /// `backward`, as `(time_idx, pc)` nearest first. Entries that rewrite the PC with the value
+    /// it already had are stalls, not new instructions, and are skipped
+    pub fn pc_trace(
+        &self,
+        from: TimeTableIdx,
+        count: usize,
+        backward: bool,
+    ) -> Vec<(TimeTableIdx, u32)> {
+        let pc = &self.waves.pc;
+        let changes = pc.time_indices();
+        let value_at = |pos: usize| u32::try_from_signal(pc.get_val(changes[pos]));
+        let retires = |pos: &usize| *pos == 0 || value_at(*pos) != value_at(*pos - 1);
+        let entry = |pos: usize| value_at(pos).map(|value| (changes[pos], value));
+
+        let after_from = changes.partition_point(|&idx| idx <= from);
+        if !backward {
+            return (after_from..changes.len())
+                .filter(retires)
+                .filter_map(entry)
+                .take(count)
+                .collect();
+        }
+        // `from` may sit on a stall, the search starts before the instruction it belongs to
+        let Some(mut current) = after_from.checked_sub(1) else {
+            return Vec::new();
+        };
+        while !retires(&current) {
+            current -= 1;
+        }
+        (0..current)
+            .rev()
+            .filter(retires)
+            .filter_map(entry)
+            .take(count)
+            .collect()
+    }
+
+    /// Move the cursor straight to `time_idx`. `false` if that's past the end of the trace
+    pub fn goto_time_idx(&mut self, time_idx: TimeTableIdx) -> bool {
+        if time_idx as usize >= self.cursor.all_times.len() {
+            return false;
+        }
+        self.cursor.time_idx = time_idx;
+        tidx_log::set_time_idx(Some(time_idx.into()));
+        true
+    }
+
+    /// Move the cursor to the first entry of the merged change list at or after `time_idx`, so
+    /// it lands on a PC or GPR change rather than between them. Where it landed, `None` with the
+    /// cursor left where it is if `time_idx` is past the end of the trace or nothing changes
+    /// from there on
+    pub fn goto_time(&mut self, time_idx: TimeTableIdx) -> Option<TimeTableIdx> {
+        if time_idx as usize >= self.cursor.all_times.len() {
+            return None;
+        }
+        let changes = &self.cursor.all_changes;
+        let landed = *changes.get(changes.partition_point(|&idx| idx < time_idx))?;
+        self.goto_time_idx(landed).then_some(landed)
+    }
+
+    /// Simulation time at the cursor, in the waveform's timescale
+    pub fn cursor_time(&self) -> u64 {
+        self.cursor.all_times[self.cursor.time_idx as usize]
+    }
+
+    /// The waveform's timescale, `ps` or `10ns`, see [`waveloader::timescale_unit`]
+    pub fn time_unit(&self) -> Option<String> {
+        self.timescale.as_ref().and_then(waveloader::timescale_unit)
     }
 
     pub fn next_pc(&mut self) -> Option<u32> {
@@ -150,6 +666,8 @@ impl Waver {
             .try_get_next_val(self.cursor.time_idx)
             .map(|(sig, _idx)| (u32::try_from_signal(sig), _idx))?;
         self.cursor.time_idx = idx;
+        self.resume_stats.pc_advances += 1;
+        tidx_log::set_time_idx(Some(idx.into()));
         if Some(prev_pc) == new_pc {
             None
         } else {
@@ -157,6 +675,63 @@ impl Waver {
         }
     }
 
+    /// Move the cursor back to the previous instruction: the PC's last change to a value other
+    /// than the one it has now, and back over any stalls that rewrote that value, the way
+    /// [`Waver::pc_trace`] counts them. `None`, with the cursor left where it is, if there's no
+    /// earlier value to go back to
+    pub fn prev_pc(&mut self) -> Option<u32> {
+        let current_pc: u32 = self.get_current_pc();
+        let pc = &self.waves.pc;
+        let changes = pc.time_indices();
+        let value_at = |pos: usize| {
+            pc.try_get_val(changes[pos])
+                .map_or_else(u32::sentinel, u32::from_signal)
+        };
+        let current = changes.partition_point(|&idx| idx <= self.cursor.time_idx);
+        let mut prev = (0..current.saturating_sub(1))
+            .rev()
+            .find(|&pos| value_at(pos) != current_pc)?;
+        let prev_pc = value_at(prev);
+        while prev > 0 && value_at(prev - 1) == prev_pc {
+            prev -= 1;
+        }
+        let idx = changes[prev];
+        self.goto_time_idx(idx);
+        Some(prev_pc)
+    }
+
+    /// An error if the waveform on disk isn't the one that was loaded. dang can't swap it out
+    /// under a session, so the fix is a restart
+    pub fn check_wave_file(&self) -> Result<(), String> {
+        match &self.wave_file {
+            Some(wave_file) if wave_file.is_stale() => {
+                Err(wave_file.changed_message("restart dang to pick up the new one"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Start a resume in `mode`, resetting [`Waver::resume_stats`] and the livelock count. The
+    /// stub may call [`Waver::run`] several times for one resume when data comes in
+    /// mid-continue, the stats cover all of them
+    pub fn begin_resume(&mut self, mode: ExecMode) {
+        self.exec_mode = mode;
+        self.livelock.clear();
+        self.resume_stats = ResumeStats {
+            start_time_idx: self.cursor.time_idx,
+            end_time_idx: self.cursor.time_idx,
+            ..ResumeStats::default()
+        };
+    }
+
+    /// Merged change list entries in `(start, end]`
+    fn changes_between(&self, start: TimeTableIdx, end: TimeTableIdx) -> u64 {
+        let changes = &self.cursor.all_changes;
+        let before_start = changes.partition_point(|&idx| idx <= start);
+        let through_end = changes.partition_point(|&idx| idx <= end);
+        through_end.saturating_sub(before_start) as u64
+    }
+
     /// single-step the interpreter
     pub fn step(&mut self) -> Option<Event> {
         let next_pc = self.next_pc();
@@ -164,9 +739,39 @@ impl Waver {
             log::debug!("pc is {pc:?}");
             log::debug!("mem is {:?}", self.mem.r32(pc));
 
-            if self.breakpoints.contains(&pc) {
+            if self.breakpoints.hit(pc) {
                 return Some(Event::Break);
             }
+
+            if !self.assertions.is_empty() {
+                let violation = self
+                    .assertions
+                    .check(pc, self.cursor.time_idx, |gpr| self.get_current_gpr(gpr));
+                if let Some(violation) = violation {
+                    log::info!("assertion {} failed: {}", violation.id, violation.check);
+                    self.assertions.last_violation = Some(violation);
+                    return Some(Event::Assertion);
+                }
+            }
+
+            if matches!(self.exec_mode, ExecMode::Continue) {
+                if let Some(event) = self.fast_forward(pc) {
+                    return Some(event);
+                }
+            }
+
+            if matches!(self.exec_mode, ExecMode::Continue)
+                && self.livelock.observe(pc, self.cursor.time_idx)
+            {
+                log::warn!(
+                    "PC appears to be looping over {} addresses since time idx {}",
+                    self.livelock.loop_len(),
+                    self.livelock.since_time_idx()
+                );
+                if self.livelock.stop_on_livelock {
+                    return Some(Event::Livelock);
+                }
+            }
             None
         } else {
             let current_pc: u32 = self.get_current_pc();
@@ -175,6 +780,42 @@ impl Waver {
         }
     }
 
+    /// With fast-forwarding on and `pc` in a skip range, move the cursor to the last change
+    /// before the PC leaves the range, so the next step is the one out of it. A breakpoint inside
+    /// the range stops the jump on it instead. Assertions need checking on every step, so
+    /// nothing is skipped while there are any
+    fn fast_forward(&mut self, pc: u32) -> Option<Event> {
+        if !self.skip_ranges.fast_forward || !self.assertions.is_empty() {
+            return None;
+        }
+        let range = self.skip_ranges.containing(pc)?;
+        let breakpoints = &mut self.breakpoints;
+        let jump = SkipRanges::fast_forward(range, &self.waves.pc, self.cursor.time_idx, |pc| {
+            breakpoints.hit(pc)
+        })?;
+        match jump {
+            FastForward::Exit(idx) => {
+                log::debug!("Skipping range {} to time idx {idx}", range.id);
+                self.goto_time_idx(idx);
+                None
+            }
+            FastForward::Break(idx) => {
+                self.goto_time_idx(idx);
+                Some(Event::Break)
+            }
+        }
+    }
+
+    /// Go back an instruction, stopping on a breakpoint there. Going backwards isn't an arrival,
+    /// so hit and ignore counts are left alone
+    fn reverse_step(&mut self) -> Option<Event> {
+        match self.prev_pc() {
+            Some(pc) if self.breakpoints.matching(pc).is_some() => Some(Event::Break),
+            Some(_) => None,
+            None => Some(Event::TraceStart),
+        }
+    }
+
     /// run the emulator in accordance with the currently set `ExecutionMode`.
     ///
     /// since the emulator runs in the same thread as the GDB loop, the emulator
@@ -183,6 +824,26 @@ impl Waver {
     pub fn run(&mut self, mut poll_incoming_data: impl FnMut() -> bool) -> RunEvent {
         let run_event = match self.exec_mode {
             ExecMode::Step => RunEvent::Event(self.step().unwrap_or(Event::DoneStep)),
+            ExecMode::ReverseStep => RunEvent::Event(match self.prev_pc() {
+                Some(_) => Event::DoneStep,
+                None => Event::TraceStart,
+            }),
+            ExecMode::ReverseContinue => {
+                let mut cycles = 0;
+                loop {
+                    if cycles % 1024 == 0 {
+                        // poll for incoming data
+                        if poll_incoming_data() {
+                            break RunEvent::IncomingData;
+                        }
+                    }
+                    cycles += 1;
+
+                    if let Some(event) = self.reverse_step() {
+                        break RunEvent::Event(event);
+                    }
+                }
+            }
             ExecMode::Continue => {
                 let mut cycles = 0;
                 loop {
@@ -223,6 +884,9 @@ impl Waver {
                 }
             }
         };
+        self.resume_stats.end_time_idx = self.cursor.time_idx;
+        self.resume_stats.changes_crossed =
+            self.changes_between(self.resume_stats.start_time_idx, self.cursor.time_idx);
         log::debug!("run_event is {run_event:?}");
         run_event
     }
@@ -235,8 +899,466 @@ pub enum RunEvent {
 }
 
 pub struct RequiredWaves {
-    pub pc: wellen::Signal,
+    /// Shared with the thread that builds the [`crate::pc_index::PcIndex`]
+    pub pc: Arc<wellen::Signal>,
     pub gprs: Vec<wellen::Signal>,
     //fprs: Option<[wellen::Signal; 32]>,
     //csrs: HashMap<u32, wellen::Signal>,
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scratch_writes() {
+        let mut mem = DummyMem::default();
+        mem.w8(0x1000, 0xaa);
+        assert!(!mem.write_scratch(0x1000, &[1]));
+
+        mem.set_scratch(Some(ScratchRegion {
+            start: 0x1000,
+            size: 4,
+        }));
+        assert!(mem.write_scratch(0x1000, &[1, 2, 3, 4]));
+        assert_eq!(mem.r32(0x1000), 0x04030201);
+
+        // straddling the end of the region writes nothing
+        assert!(!mem.write_scratch(0x1002, &[9, 9, 9]));
+        assert_eq!(mem.r8(0x1002), 3);
+    }
+
+    #[test]
+    fn test_livelock_detector_small_loop() {
+        let mut detector = LivelockDetector {
+            threshold: 8,
+            ..Default::default()
+        };
+        let looping = [0x100, 0x104, 0x108];
+
+        let fired: Vec<bool> = looping
+            .iter()
+            .cycle()
+            .take(3 + 8)
+            .enumerate()
+            .map(|(i, pc)| detector.observe(*pc, i as TimeTableIdx))
+            .collect();
+        assert_eq!(fired.iter().filter(|f| **f).count(), 1);
+        assert!(*fired.last().unwrap());
+        assert_eq!(detector.loop_len(), 3);
+        assert_eq!(detector.since_time_idx(), 2);
+
+        // something new resets the count
+        assert!(!detector.observe(0x200, 100));
+        assert_eq!(detector.since_time_idx(), 100);
+    }
+
+    #[test]
+    fn test_livelock_detector_straight_line_code() {
+        let mut detector = LivelockDetector {
+            threshold: 4,
+            ..Default::default()
+        };
+        for pc in (0..1000).map(|i| i * 4) {
+            assert!(!detector.observe(pc, pc));
+        }
+    }
+
+    /// A waver over a trace where the PC takes each of `pc` in turn, one time index apart
+    fn synthetic_waver(pc: &[u32]) -> Waver {
+        let changes: Vec<(TimeTableIdx, u32)> = (0..).zip(pc.iter().copied()).collect();
+        let (waves, cursor) = waveloader::tests::synthetic_waves(&changes);
+        Waver::from_parts(waves, cursor, DummyMem::default(), PathBuf::new())
+    }
+
+    #[test]
+    fn test_synthetic_halts_at_end_of_trace() {
+        let mut waver = synthetic_waver(&[0x100, 0x104, 0x108]);
+        assert_eq!(waver.cursor.all_times.len(), 3);
+        waver.begin_resume(ExecMode::Continue);
+        assert!(matches!(
+            waver.run(|| false),
+            RunEvent::Event(Event::Halted)
+        ));
+        assert_eq!(waver.cursor.time_idx, 2);
+        assert_eq!(waver.get_current_pc::<u32>(), 0x108);
+        // and stays halted
+        assert!(matches!(waver.step(), Some(Event::Halted)));
+
+        // a PC that never changes has nowhere to go
+        let mut waver = synthetic_waver(&[0x100]);
+        assert!(matches!(waver.step(), Some(Event::Halted)));
+        assert_eq!(waver.cursor.time_idx, 0);
+    }
+
+    #[test]
+    fn test_synthetic_single_step_trace() {
+        // one time step, every resume is at the end of the trace straight away
+        for mode in [
+            ExecMode::Step,
+            ExecMode::Continue,
+            ExecMode::RangeStep(0x100, 0x104),
+        ] {
+            let mut waver = synthetic_waver(&[0x100]);
+            assert_eq!(waver.cursor.all_times.len(), 1);
+            waver.begin_resume(mode);
+            assert!(matches!(
+                waver.run(|| false),
+                RunEvent::Event(Event::Halted)
+            ));
+            assert_eq!(waver.cursor.time_idx, 0);
+            assert_eq!(waver.resume_stats.changes_crossed, 0);
+        }
+
+        let waver = synthetic_waver(&[0x100]);
+        let context = waver.stop_context(2);
+        assert_eq!((context.time_idx, context.pc), (0, 0x100));
+        assert!(waver.pc_trace(0, 4, false).is_empty());
+        assert!(waver.pc_trace(0, 4, true).is_empty());
+        assert!(waver
+            .check_gprs()
+            .findings
+            .iter()
+            .any(|finding| finding.to_string().contains("single value")));
+    }
+
+    #[test]
+    fn test_synthetic_register_without_a_value_yet() {
+        let changes: Vec<(TimeTableIdx, u32)> = vec![(0, 0x100), (1, 0x104)];
+        let (mut waves, cursor) = waveloader::tests::synthetic_waves(&changes);
+        let (mut late, _) = waveloader::tests::synthetic_signals(&[&[(1, 0x20)]]);
+        waves.gprs[5] = late.pop().unwrap();
+        let mut waver = Waver::from_parts(waves, cursor, DummyMem::default(), PathBuf::new());
+
+        assert_eq!(waver.get_current_gpr(5), u32::sentinel());
+        assert!(waver.goto_time_idx(1));
+        assert_eq!(waver.get_current_gpr(5), 0x20);
+    }
+
+    #[test]
+    fn test_synthetic_breakpoint_on_next_change() {
+        let mut waver = synthetic_waver(&[0x100, 0x104, 0x108, 0x10c]);
+        waver.breakpoints.extend([0x104, 0x108]);
+
+        // the very first step lands on one, the nearer of the two stops first
+        waver.begin_resume(ExecMode::Continue);
+        assert!(matches!(waver.run(|| false), RunEvent::Event(Event::Break)));
+        assert_eq!(waver.cursor.time_idx, 1);
+        assert_eq!(waver.get_current_pc::<u32>(), 0x104);
+        assert_eq!(waver.resume_stats.pc_advances, 1);
+
+        waver.begin_resume(ExecMode::Continue);
+        assert!(matches!(waver.run(|| false), RunEvent::Event(Event::Break)));
+        assert_eq!(waver.get_current_pc::<u32>(), 0x108);
+
+        // a step onto one reports the breakpoint too
+        waver.breakpoints.insert(0x10c);
+        waver.begin_resume(ExecMode::Step);
+        assert!(matches!(waver.run(|| false), RunEvent::Event(Event::Break)));
+        assert_eq!(waver.cursor.time_idx, 3);
+    }
+
+    #[test]
+    fn test_synthetic_range_step_exits_range() {
+        let mut waver = synthetic_waver(&[0x100, 0x104, 0x108, 0x200, 0x204]);
+        waver.begin_resume(ExecMode::RangeStep(0x100, 0x10c));
+        assert!(matches!(
+            waver.run(|| false),
+            RunEvent::Event(Event::DoneStep)
+        ));
+        // stopped on the first PC outside of the range, not after it
+        assert_eq!(waver.get_current_pc::<u32>(), 0x200);
+        assert_eq!(waver.cursor.time_idx, 3);
+        assert_eq!(waver.resume_stats.pc_advances, 3);
+    }
+
+    #[test]
+    fn test_synthetic_reverse_step() {
+        // the repeated 0x104 is a stall, the instruction started at the first one
+        let mut waver = synthetic_waver(&[0x100, 0x104, 0x104, 0x108]);
+        assert!(waver.goto_time_idx(3));
+
+        waver.begin_resume(ExecMode::ReverseStep);
+        assert!(matches!(
+            waver.run(|| false),
+            RunEvent::Event(Event::DoneStep)
+        ));
+        assert_eq!(waver.get_current_pc::<u32>(), 0x104);
+        assert_eq!(waver.cursor.time_idx, 1);
+        assert_eq!(waver.resume_stats.end_time_idx, 1);
+
+        assert_eq!(waver.prev_pc(), Some(0x100));
+        assert_eq!(waver.cursor.time_idx, 0);
+
+        // the first change is as far back as the trace goes
+        waver.begin_resume(ExecMode::ReverseStep);
+        assert!(matches!(
+            waver.run(|| false),
+            RunEvent::Event(Event::TraceStart)
+        ));
+        assert_eq!(waver.cursor.time_idx, 0);
+        assert_eq!(waver.get_current_pc::<u32>(), 0x100);
+    }
+
+    #[test]
+    fn test_synthetic_reverse_continue() {
+        let mut waver = synthetic_waver(&[0x100, 0x104, 0x108, 0x104, 0x108, 0x10c]);
+        waver.breakpoints.insert(0x104);
+        assert!(waver.goto_time_idx(5));
+
+        // the latest arrival first, without counting it as a hit
+        waver.begin_resume(ExecMode::ReverseContinue);
+        assert!(matches!(waver.run(|| false), RunEvent::Event(Event::Break)));
+        assert_eq!(waver.cursor.time_idx, 3);
+        assert_eq!(waver.breakpoints.get(0x104).unwrap().hits, 0);
+
+        waver.begin_resume(ExecMode::ReverseContinue);
+        assert!(matches!(waver.run(|| false), RunEvent::Event(Event::Break)));
+        assert_eq!(waver.cursor.time_idx, 1);
+
+        waver.begin_resume(ExecMode::ReverseContinue);
+        assert!(matches!(
+            waver.run(|| false),
+            RunEvent::Event(Event::TraceStart)
+        ));
+        assert_eq!(waver.cursor.time_idx, 0);
+        assert_eq!(waver.resume_stats.end_time_idx, 0);
+    }
+
+    #[test]
+    fn test_bp_match_on_the_second_halfword() {
+        use crate::breakpoints::BpMatch;
+
+        // the core traces the 4 byte instruction at 0x104 as 0x106
+        let trace = [0x100, 0x106, 0x10a];
+        let mut waver = synthetic_waver(&trace);
+        waver.breakpoints.insert_with_kind(0x104, 4);
+        waver.begin_resume(ExecMode::Continue);
+        let logged = crate::tidx_log::tests::capture_logs(|| {
+            assert!(matches!(
+                waver.run(|| false),
+                RunEvent::Event(Event::Halted)
+            ));
+        });
+        assert_eq!(waver.breakpoints.get(0x104).unwrap().hits, 0);
+        let near_miss =
+            "PC 0x106 is near the breakpoint at 0x104 but doesn't hit it with exact matching";
+        assert_eq!(waver.breakpoints.near_misses(0x106), [near_miss]);
+        // and it's in the debug log, not only there for the asking
+        assert!(logged.iter().any(|line| line == near_miss), "{logged:?}");
+
+        let mut waver = synthetic_waver(&trace);
+        waver.breakpoints.insert_with_kind(0x104, 4);
+        waver.breakpoints.match_mode = BpMatch::Loose;
+        waver.begin_resume(ExecMode::Continue);
+        assert!(matches!(waver.run(|| false), RunEvent::Event(Event::Break)));
+        assert_eq!(waver.get_current_pc::<u32>(), 0x106);
+        assert_eq!(waver.breakpoints.get(0x104).unwrap().hits, 1);
+        assert!(waver.breakpoints.near_misses(0x106).is_empty());
+    }
+
+    #[test]
+    fn test_reverse_continue_polls() {
+        let mut waver = synthetic_waver(&[0x100, 0x104, 0x108]);
+        assert!(waver.goto_time_idx(2));
+        waver.begin_resume(ExecMode::ReverseContinue);
+        assert!(matches!(waver.run(|| true), RunEvent::IncomingData));
+        assert_eq!(waver.cursor.time_idx, 2);
+    }
+
+    #[test]
+    fn test_reverse_step_retraces_steps() {
+        let mut waver = fixture_waver();
+        let start = waver.cursor.time_idx;
+        let ahead = waver.pc_trace(start, 4, false);
+        let (last_idx, _) = ahead[3];
+        assert!(waver.goto_time_idx(last_idx));
+
+        // back over the same instructions the trace went through
+        for &(time_idx, pc) in ahead[..3].iter().rev() {
+            assert_eq!(waver.prev_pc(), Some(pc));
+            assert_eq!(waver.cursor.time_idx, time_idx);
+        }
+    }
+
+    #[test]
+    fn test_goto_time_lands_on_a_change() {
+        let mut waver = fixture_waver();
+        let changes = waver.cursor.all_changes.clone();
+        // on a change it stays there, between two it moves on to the later one
+        assert_eq!(waver.goto_time(changes[5]), Some(changes[5]));
+        assert_eq!(waver.goto_time(changes[5] + 1), Some(changes[6]));
+        assert_eq!(waver.cursor.time_idx, changes[6]);
+
+        let last = *changes.last().unwrap();
+        assert_eq!(waver.goto_time(last + 1), None);
+        assert_eq!(waver.goto_time(TimeTableIdx::MAX), None);
+        assert_eq!(waver.cursor.time_idx, changes[6]);
+    }
+
+    pub(crate) fn fixture_waver() -> Waver {
+        let cargo_manifest_dir = env!("CARGO_MANIFEST_DIR");
+        let elf_path = PathBuf::from(cargo_manifest_dir).join("../test_data/ibex/hello_test.elf");
+        let wave_path = PathBuf::from(cargo_manifest_dir).join("../test_data/ibex/sim.fst");
+        let script_path = PathBuf::from(cargo_manifest_dir).join("../test_data/ibex/signal_get.py");
+        Waver::new(wave_path, script_path, elf_path).unwrap()
+    }
+
+    #[test]
+    fn test_pc_trace_both_ways() {
+        let mut waver = fixture_waver();
+        let start = waver.cursor.time_idx;
+        let ahead = waver.pc_trace(start, 8, false);
+        assert_eq!(ahead.len(), 8);
+        assert!(ahead
+            .windows(2)
+            .all(|pair| pair[0].0 < pair[1].0 && pair[0].1 != pair[1].1));
+
+        // walking back from the last one retraces the same instructions
+        let (last_idx, last_pc) = ahead[7];
+        let mut retraced = waver.pc_trace(last_idx, 7, true);
+        retraced.reverse();
+        assert_eq!(retraced, ahead[..7]);
+
+        assert!(waver.goto_time_idx(last_idx));
+        assert_eq!(waver.get_current_pc::<u32>(), last_pc);
+        assert!(!waver.goto_time_idx(TimeTableIdx::MAX));
+    }
+
+    #[test]
+    fn test_continue_stops_on_livelock() {
+        let mut waver = fixture_waver();
+        // the uart loops in puts spin well past this
+        waver.livelock.threshold = 32;
+        waver.livelock.stop_on_livelock = true;
+        waver.exec_mode = ExecMode::Continue;
+
+        match waver.run(|| false) {
+            RunEvent::Event(Event::Livelock) => {}
+            other => panic!("expected a livelock stop, got {other:?}"),
+        }
+        assert!(waver.livelock.loop_len() <= LIVELOCK_WINDOW);
+    }
+
+    #[test]
+    fn test_every_continue_counts_livelock_afresh() {
+        let mut waver = fixture_waver();
+        waver.livelock.threshold = 32;
+        waver.livelock.stop_on_livelock = true;
+
+        for _ in 0..2 {
+            waver.begin_resume(ExecMode::Continue);
+            assert_eq!(waver.livelock.loop_len(), 0);
+            match waver.run(|| false) {
+                RunEvent::Event(Event::Livelock) => {}
+                other => panic!("expected a livelock stop, got {other:?}"),
+            }
+            // the whole threshold over again, not what was left of the last one
+            assert!(waver.resume_stats.pc_advances >= 32);
+        }
+    }
+
+    #[test]
+    fn test_resume_stats_step() {
+        let mut waver = fixture_waver();
+        let start = waver.cursor.time_idx;
+        waver.begin_resume(ExecMode::Step);
+        assert!(matches!(
+            waver.run(|| false),
+            RunEvent::Event(Event::DoneStep)
+        ));
+
+        let stats = waver.resume_stats;
+        assert_eq!(stats.start_time_idx, start);
+        assert_eq!(stats.end_time_idx, waver.cursor.time_idx);
+        assert_eq!(stats.pc_advances, 1);
+        // the PC's next change is in the merged list, along with any GPR changes before it
+        assert!(stats.changes_crossed >= 1);
+        assert_eq!(
+            stats.changes_crossed,
+            waver
+                .cursor
+                .all_changes
+                .iter()
+                .filter(|&&idx| start < idx && idx <= stats.end_time_idx)
+                .count() as u64
+        );
+
+        // the next resume starts counting from scratch
+        waver.begin_resume(ExecMode::Step);
+        assert_eq!(waver.resume_stats.pc_advances, 0);
+        assert_eq!(waver.resume_stats.start_time_idx, stats.end_time_idx);
+    }
+
+    #[test]
+    fn test_resume_stats_continue_to_breakpoint() {
+        let mut waver = fixture_waver();
+        let start = waver.cursor.time_idx;
+        // puthex, a few calls in
+        waver.breakpoints.insert(0x1000a4);
+        waver.begin_resume(ExecMode::Continue);
+        assert!(matches!(waver.run(|| false), RunEvent::Event(Event::Break)));
+        assert_eq!(waver.get_current_pc::<u32>(), 0x1000a4);
+
+        let stats = waver.resume_stats;
+        let pc_changes = waver
+            .waves
+            .pc
+            .time_indices()
+            .iter()
+            .filter(|&&idx| start < idx && idx <= stats.end_time_idx)
+            .count() as u64;
+        assert_eq!(stats.pc_advances, pc_changes);
+        assert!(stats.changes_crossed >= stats.pc_advances);
+        assert_eq!(stats.end_time_idx, waver.cursor.time_idx);
+    }
+
+    #[test]
+    fn test_ignored_breakpoint_stops_on_later_hit() {
+        let mut waver = fixture_waver();
+        // the first PC the trace comes back to a fourth time, and when it does
+        let mut arrivals = BTreeMap::new();
+        let (fourth, pc) = waver
+            .pc_trace(waver.cursor.time_idx, usize::MAX, false)
+            .into_iter()
+            .find(|&(_, pc)| {
+                let seen = arrivals.entry(pc).or_insert(0);
+                *seen += 1;
+                *seen == 4
+            })
+            .expect("the uart loops come back to their PCs");
+
+        waver.breakpoints.insert(pc);
+        assert!(waver.breakpoints.set_ignore(pc, 3));
+        waver.begin_resume(ExecMode::Continue);
+        assert!(matches!(waver.run(|| false), RunEvent::Event(Event::Break)));
+        assert_eq!(waver.cursor.time_idx, fourth);
+
+        let entry = waver.breakpoints.get(pc).unwrap();
+        assert_eq!(entry.hits, 4);
+        assert_eq!(entry.ignore_remaining, 0);
+    }
+
+    #[test]
+    fn test_resume_stats_end_of_trace() {
+        let mut waver = fixture_waver();
+        let start = waver.cursor.time_idx;
+        waver.begin_resume(ExecMode::Continue);
+        assert!(matches!(
+            waver.run(|| false),
+            RunEvent::Event(Event::Halted)
+        ));
+
+        let stats = waver.resume_stats;
+        let last_pc_change = *waver.waves.pc.time_indices().last().unwrap();
+        assert_eq!(stats.end_time_idx, last_pc_change);
+        let changes_after_start = waver
+            .cursor
+            .all_changes
+            .iter()
+            .filter(|&&idx| start < idx && idx <= last_pc_change)
+            .count() as u64;
+        assert_eq!(stats.changes_crossed, changes_after_start);
+        assert!(stats.pc_advances > 0);
+    }
+}
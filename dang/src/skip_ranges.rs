@@ -0,0 +1,200 @@
+//! PC ranges a continue doesn't need to step through, like delay loops or crt0. With
+//! fast-forwarding on, a continue that lands in one jumps straight to where the PC next leaves
+//! it instead of going round the loop change by change.
+//!
+//! Breakpoints inside a range still stop the continue, see [`SkipRanges::fast_forward`]
+
+use std::fmt;
+use std::str::FromStr;
+
+use wellen::{Signal, TimeTableIdx};
+
+use crate::convert::Mappable;
+use crate::waveloader::WellenSignalExt;
+
+/// `[lo, hi]`, both ends included like `assert_pc_range`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkipRange {
+    pub id: u32,
+    pub lo: u32,
+    pub hi: u32,
+}
+
+impl SkipRange {
+    pub fn contains(&self, pc: u32) -> bool {
+        (self.lo..=self.hi).contains(&pc)
+    }
+}
+
+impl fmt::Display for SkipRange {
+    /// `<id> <lo in hex> <hi in hex>`, the monitor reply format
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:x} {:x}", self.id, self.lo, self.hi)
+    }
+}
+
+impl FromStr for SkipRange {
+    type Err = String;
+
+    /// The [`fmt::Display`] form back
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let hex = |value: &str| u32::from_str_radix(value, 16).ok();
+        match parts[..] {
+            [id, lo, hi] => match (id.parse(), hex(lo), hex(hi)) {
+                (Ok(id), Some(lo), Some(hi)) => Ok(SkipRange { id, lo, hi }),
+                _ => Err(format!("Malformed skip range: {line}")),
+            },
+            _ => Err(format!("Malformed skip range: {line}")),
+        }
+    }
+}
+
+/// Where a fast-forward left the cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FastForward {
+    /// On the last change inside the range, the next step leaves it
+    Exit(TimeTableIdx),
+    /// On a change that hits a breakpoint inside the range
+    Break(TimeTableIdx),
+}
+
+#[derive(Debug, Default)]
+pub struct SkipRanges {
+    list: Vec<SkipRange>,
+    next_id: u32,
+    /// Jump across ranges during continue, `fastforward_ranges on|off`
+    pub fast_forward: bool,
+}
+
+impl SkipRanges {
+    pub fn add(&mut self, lo: u32, hi: u32) -> SkipRange {
+        self.next_id += 1;
+        let range = SkipRange {
+            id: self.next_id,
+            lo,
+            hi,
+        };
+        self.list.push(range);
+        range
+    }
+
+    pub fn remove(&mut self, id: u32) -> Option<SkipRange> {
+        let pos = self.list.iter().position(|r| r.id == id)?;
+        Some(self.list.remove(pos))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SkipRange> {
+        self.list.iter()
+    }
+
+    /// The range `pc` is in, if any
+    pub fn containing(&self, pc: u32) -> Option<SkipRange> {
+        self.list.iter().find(|r| r.contains(pc)).copied()
+    }
+
+    /// Scan the changes of `pc_signal` after `from`, inside `range`, for the last one before the
+    /// PC leaves the range or the first one at an address `is_breakpoint` says to stop at.
+    /// Entries with x or z bits count as leaving. `None` if the PC leaves with the very next
+    /// change, and there's nothing to skip
+    pub fn fast_forward(
+        range: SkipRange,
+        pc_signal: &Signal,
+        from: TimeTableIdx,
+        mut is_breakpoint: impl FnMut(u32) -> bool,
+    ) -> Option<FastForward> {
+        let changes = pc_signal.time_indices();
+        let after_from = changes.partition_point(|&idx| idx <= from);
+        let mut last_inside = None;
+        for &idx in &changes[after_from..] {
+            match u32::try_from_signal(pc_signal.get_val(idx)) {
+                Some(pc) if range.contains(pc) => {
+                    if is_breakpoint(pc) {
+                        return Some(FastForward::Break(idx));
+                    }
+                    last_inside = Some(idx);
+                }
+                _ => break,
+            }
+        }
+        last_inside.map(FastForward::Exit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::tests::fixture_waver;
+
+    #[test]
+    fn test_ids_are_not_reused() {
+        let mut ranges = SkipRanges::default();
+        let first = ranges.add(0x100, 0x1ff);
+        assert_eq!(ranges.containing(0x1ff), Some(first));
+        assert_eq!(ranges.containing(0x200), None);
+        assert_eq!(first.to_string(), "1 100 1ff");
+        assert_eq!("1 100 1ff".parse(), Ok(first));
+        assert!("1 100".parse::<SkipRange>().is_err());
+        assert!("x 100 1ff".parse::<SkipRange>().is_err());
+
+        assert_eq!(ranges.remove(first.id), Some(first));
+        assert_eq!(ranges.remove(first.id), None);
+        assert_eq!(ranges.iter().count(), 0);
+        assert_eq!(ranges.add(0, 0).id, first.id + 1);
+    }
+
+    #[test]
+    fn test_fast_forward_matches_stepping() {
+        let waver = fixture_waver();
+        let pc = &waver.waves.pc;
+        let values: Vec<(TimeTableIdx, Option<u32>)> = pc
+            .time_indices()
+            .iter()
+            .map(|&idx| (idx, u32::try_from_signal(pc.get_val(idx))))
+            .collect();
+
+        // the start of main, up to its first call, entered from the first change there
+        let range = SkipRange {
+            id: 1,
+            lo: 0x1003d0,
+            hi: 0x1003ff,
+        };
+        let enter = values
+            .iter()
+            .position(|(_, v)| v.is_some_and(|pc| range.contains(pc)))
+            .expect("the trace runs main");
+        let exit = enter
+            + values[enter..]
+                .iter()
+                .position(|(_, v)| !v.is_some_and(|pc| range.contains(pc)))
+                .unwrap();
+        assert!(exit - enter > 1, "main should take more than one change");
+
+        let (from, _) = values[enter];
+        assert_eq!(
+            SkipRanges::fast_forward(range, pc, from, |_| false),
+            Some(FastForward::Exit(values[exit - 1].0))
+        );
+
+        // a breakpoint partway through stops the jump there
+        let mid = (enter + exit) / 2;
+        let (mid_idx, mid_pc) = values[mid];
+        let first_hit = values[enter + 1..]
+            .iter()
+            .find(|(_, v)| *v == mid_pc)
+            .unwrap()
+            .0;
+        assert!(first_hit <= mid_idx);
+        assert_eq!(
+            SkipRanges::fast_forward(range, pc, from, |pc| Some(pc) == mid_pc),
+            Some(FastForward::Break(first_hit))
+        );
+
+        // leaving straight away, nothing to skip
+        let (last_inside, _) = values[exit - 1];
+        assert_eq!(
+            SkipRanges::fast_forward(range, pc, last_inside, |_| false),
+            None
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! `monitor stop_context [n_inst]`, what a debugger redraws after a stop in one reply: the time
+//! index, the PC, x0 through x31 and the bytes of the next `n_inst` instructions at the PC.
+//! That's one round trip in place of a `time_idx`, a `g` and an `m`, which adds up over a slow
+//! link.
+//!
+//! The reply is three lines: `<time_idx> <pc>`, the 32 GPRs, then the code bytes run together.
+//! Everything but the time index is hex
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Instructions' worth of code the reply carries without an `n_inst`
+pub const DEFAULT_INSTRUCTIONS: usize = 4;
+
+/// Most instructions' worth one reply carries, asking for more gets this many
+pub const MAX_INSTRUCTIONS: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StopContext {
+    pub time_idx: u64,
+    pub pc: u32,
+    pub gprs: [u32; 32],
+    /// `n_inst * 4` bytes of memory from the PC on
+    pub code: Vec<u8>,
+}
+
+impl fmt::Display for StopContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {:x}", self.time_idx, self.pc)?;
+        let gprs: Vec<String> = self.gprs.iter().map(|gpr| format!("{gpr:x}")).collect();
+        writeln!(f, "{}", gprs.join(" "))?;
+        for byte in &self.code {
+            write!(f, "{byte:02x}")?;
+        }
+        writeln!(f)
+    }
+}
+
+impl FromStr for StopContext {
+    type Err = String;
+
+    /// The [`fmt::Display`] form back
+    fn from_str(reply: &str) -> Result<Self, Self::Err> {
+        let malformed = || format!("Malformed stop context: {}", reply.trim());
+        let mut lines = reply.lines();
+        let (time_idx, pc) = lines
+            .next()
+            .and_then(|line| line.split_once(' '))
+            .ok_or_else(malformed)?;
+        let time_idx = time_idx.parse().map_err(|_| malformed())?;
+        let pc = u32::from_str_radix(pc, 16).map_err(|_| malformed())?;
+
+        let gprs: Vec<u32> = lines
+            .next()
+            .ok_or_else(malformed)?
+            .split_whitespace()
+            .map(|gpr| u32::from_str_radix(gpr, 16))
+            .collect::<Result<_, _>>()
+            .map_err(|_| malformed())?;
+        let gprs: [u32; 32] = gprs.try_into().map_err(|_| malformed())?;
+
+        let code = lines.next().unwrap_or("").trim();
+        if code.len() % 2 != 0 || !code.is_ascii() {
+            return Err(malformed());
+        }
+        let code = (0..code.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&code[i..i + 2], 16))
+            .collect::<Result<_, _>>()
+            .map_err(|_| malformed())?;
+
+        Ok(StopContext {
+            time_idx,
+            pc,
+            gprs,
+            code,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut gprs = [0; 32];
+        gprs[1] = 0x1003e4;
+        gprs[2] = 0x7fff_fff0;
+        let context = StopContext {
+            time_idx: 42,
+            pc: 0x1003d0,
+            gprs,
+            code: vec![0x13, 0x01, 0x01, 0xff, 0x97],
+        };
+        let reply = context.to_string();
+        assert!(reply.starts_with("42 1003d0\n0 1003e4 7ffffff0 0 "));
+        assert!(reply.ends_with("\n130101ff97\n"));
+        assert_eq!(reply.parse(), Ok(context.clone()));
+
+        // no code asked for
+        let bare = StopContext {
+            code: Vec::new(),
+            ..context
+        };
+        assert_eq!(bare.to_string().parse(), Ok(bare));
+
+        assert!("42 1003d0\n0 1 2\n".parse::<StopContext>().is_err());
+        assert!("42\n".parse::<StopContext>().is_err());
+        let odd = reply.replace("130101ff97", "13010");
+        assert!(odd.parse::<StopContext>().is_err());
+    }
+}
@@ -0,0 +1,160 @@
+//! `H` packets checked against the one hart dang has. gdbstub acks `Hg` and `Hc` for any
+//! thread id and only finds out the thread isn't there on the next register read, so a client
+//! selecting hart 2 would be told it worked. [`ThreadCheck`] sits between the socket and the
+//! stub and answers those packets with an error itself, the way gdbstub answers a `T` for a
+//! thread that isn't alive
+
+use gdbstub::conn::{Connection, ConnectionExt};
+use std::collections::VecDeque;
+
+/// The thread id gdbstub gives the hart of a single-threaded target
+const HART_TID: u64 = 1;
+
+/// Whether the body of an `H` packet names a specific thread other than the hart. `0` (any) and
+/// `-1` (all) are left to gdbstub, as is anything that doesn't parse
+fn selects_missing_thread(body: &[u8]) -> bool {
+    let Some(thread) = body
+        .strip_prefix(b"Hg")
+        .or_else(|| body.strip_prefix(b"Hc"))
+    else {
+        return false;
+    };
+    let Ok(thread) = std::str::from_utf8(thread) else {
+        return false;
+    };
+    // the multiprocess `p<pid>.<tid>` form
+    let tid = match thread.strip_prefix('p') {
+        Some(thread) => match thread.split_once('.') {
+            Some((_, tid)) => tid,
+            None => return false,
+        },
+        None => thread,
+    };
+    match u64::from_str_radix(tid, 16) {
+        Ok(tid) => tid != 0 && tid != HART_TID,
+        Err(_) => false,
+    }
+}
+
+/// A connection that answers `H` packets for threads other than the hart with `E01` instead of
+/// passing them on. Everything else goes through to the stub untouched
+pub struct ThreadCheck<C> {
+    inner: C,
+    /// Bytes of a packet already read off `inner` that the stub hasn't had yet
+    pending: VecDeque<u8>,
+    /// Set once `QStartNoAckMode` has gone through, after which packets aren't acked
+    no_ack: bool,
+}
+
+impl<C: ConnectionExt> ThreadCheck<C> {
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            pending: VecDeque::new(),
+            no_ack: false,
+        }
+    }
+
+    /// Read the rest of a packet whose `$` and first byte `first` have been read, up to and
+    /// including its two checksum bytes
+    fn read_packet(&mut self, first: u8) -> Result<(Vec<u8>, [u8; 2]), C::Error> {
+        let mut body = vec![first];
+        while *body.last().expect("starts with a byte") != b'#' {
+            body.push(self.inner.read()?);
+        }
+        body.pop();
+        Ok((body, [self.inner.read()?, self.inner.read()?]))
+    }
+
+    fn reply_error(&mut self) -> Result<(), C::Error> {
+        if !self.no_ack {
+            self.inner.write(b'+')?;
+        }
+        let body = b"E01";
+        let checksum = body.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        self.inner.write(b'$')?;
+        self.inner.write_all(body)?;
+        self.inner
+            .write_all(format!("#{checksum:02x}").as_bytes())?;
+        self.inner.flush()
+    }
+}
+
+impl<C: ConnectionExt> Connection for ThreadCheck<C> {
+    type Error = C::Error;
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::Error> {
+        self.inner.write(byte)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.inner.write_all(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+
+    fn on_session_start(&mut self) -> Result<(), Self::Error> {
+        self.inner.on_session_start()
+    }
+}
+
+impl<C: ConnectionExt> ConnectionExt for ThreadCheck<C> {
+    fn read(&mut self) -> Result<u8, Self::Error> {
+        loop {
+            if let Some(byte) = self.pending.pop_front() {
+                return Ok(byte);
+            }
+            let byte = self.inner.read()?;
+            if byte != b'$' {
+                return Ok(byte);
+            }
+            // only `H` and `Q` packets are held back until they're whole, everything else
+            // streams through
+            let first = self.inner.read()?;
+            if first != b'H' && first != b'Q' {
+                self.pending.push_back(first);
+                return Ok(byte);
+            }
+            let (body, checksum) = self.read_packet(first)?;
+            if selects_missing_thread(&body) {
+                self.reply_error()?;
+                continue;
+            }
+            if body == b"QStartNoAckMode" {
+                // gdbstub still acks this one, then stops
+                self.no_ack = true;
+            }
+            self.pending.extend(body);
+            self.pending.push_back(b'#');
+            self.pending.extend(checksum);
+            return Ok(byte);
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>, Self::Error> {
+        match self.pending.front() {
+            Some(&byte) => Ok(Some(byte)),
+            None => self.inner.peek(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selects_missing_thread() {
+        assert!(selects_missing_thread(b"Hg2"));
+        assert!(selects_missing_thread(b"Hc2"));
+        assert!(selects_missing_thread(b"Hgp1.a"));
+        assert!(!selects_missing_thread(b"Hg1"));
+        assert!(!selects_missing_thread(b"Hcp1.1"));
+        assert!(!selects_missing_thread(b"Hg0"));
+        assert!(!selects_missing_thread(b"Hc-1"));
+        assert!(!selects_missing_thread(b"Hgzz"));
+        assert!(!selects_missing_thread(b"QStartNoAckMode"));
+    }
+}
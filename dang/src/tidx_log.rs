@@ -0,0 +1,215 @@
+//! Stamp log records with the waveform time index they were emitted at.
+//!
+//! Wall clock timestamps don't help when lining dang's logs up with a simulator's, the waveform
+//! position does. Whoever holds the [`Waver`](crate::Waver) publishes the cursor's time index
+//! for its thread, and [`TimeIdxLogger`] prefixes every record with it: `[tidx=4242] ...`.
+//! Records from outside any target operation (startup, connection handling) get `[tidx=-]`
+
+use std::cell::Cell;
+
+use log::{Log, Metadata, Record};
+
+thread_local! {
+    static CURRENT_TIME_IDX: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Publish the time index for log records from this thread, `None` outside a target operation
+pub fn set_time_idx(time_idx: Option<u64>) {
+    CURRENT_TIME_IDX.with(|current| current.set(time_idx));
+}
+
+pub fn current_time_idx() -> Option<u64> {
+    CURRENT_TIME_IDX.with(Cell::get)
+}
+
+/// The `[tidx=..]` prefix for this thread right now
+pub fn prefix() -> String {
+    match current_time_idx() {
+        Some(time_idx) => format!("[tidx={time_idx}]"),
+        None => "[tidx=-]".to_string(),
+    }
+}
+
+/// Marks this thread as working on the target at `time_idx` until dropped
+pub struct TargetScope {
+    previous: Option<u64>,
+}
+
+impl TargetScope {
+    pub fn enter(time_idx: u64) -> Self {
+        let previous = current_time_idx();
+        set_time_idx(Some(time_idx));
+        Self { previous }
+    }
+}
+
+impl Drop for TargetScope {
+    fn drop(&mut self) {
+        set_time_idx(self.previous);
+    }
+}
+
+/// Wraps another logger, prefixing every record with the current time index
+pub struct TimeIdxLogger<L> {
+    inner: L,
+}
+
+impl<L: Log> TimeIdxLogger<L> {
+    pub fn new(inner: L) -> Self {
+        Self { inner }
+    }
+}
+
+impl<L: Log> Log for TimeIdxLogger<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.inner.enabled(record.metadata()) {
+            return;
+        }
+        self.inner.log(
+            &Record::builder()
+                .args(format_args!("{} {}", prefix(), record.args()))
+                .metadata(record.metadata().clone())
+                .module_path(record.module_path())
+                .file(record.file())
+                .line(record.line())
+                .build(),
+        );
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// What [`start_with_options_and_listener_silent`](crate::start_with_options_and_listener_silent)
+/// lets through, per module path: errors only, from dang and the protocol stub it runs. It only
+/// applies them itself when it installs its own logger, a program embedding dang with a logger
+/// of its own applies them there
+pub const SILENT_FILTERS: &[(&str, log::LevelFilter)] = &[
+    ("dang", log::LevelFilter::Error),
+    ("gdbstub", log::LevelFilter::Error),
+];
+
+/// Install env_logger behind [`TimeIdxLogger`], unless a logger is already set up
+pub fn try_init(default_filter: &str) {
+    // dang's own tests all share the one capturing logger, whichever of them starts first
+    #[cfg(test)]
+    if tests::install_capture() {
+        return;
+    }
+    let logger =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_filter))
+            .build();
+    let max_level = logger.filter();
+    match log::set_boxed_logger(Box::new(TimeIdxLogger::new(logger))) {
+        Ok(()) => log::set_max_level(max_level),
+        Err(_) => log::debug!("A logger is already installed, leaving it in place"),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::sync::{Mutex, OnceLock};
+
+    thread_local! {
+        static CAPTURED: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+    }
+
+    /// The test binary's logger, keeping records only from threads inside [`capture_logs`]
+    struct Capture;
+
+    impl Log for Capture {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            CAPTURED.with(|captured| captured.borrow().is_some())
+        }
+
+        fn log(&self, record: &Record) {
+            CAPTURED.with(|captured| {
+                if let Some(lines) = captured.borrow_mut().as_mut() {
+                    lines.push(record.args().to_string());
+                }
+            });
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// Make [`Capture`] the global logger, which [`try_init`] does in place of its own for
+    /// every test. False if some other logger got there first
+    pub(crate) fn install_capture() -> bool {
+        static INSTALLED: OnceLock<bool> = OnceLock::new();
+        *INSTALLED.get_or_init(|| {
+            let installed = log::set_logger(&Capture).is_ok();
+            if installed {
+                log::set_max_level(log::LevelFilter::Trace);
+            }
+            installed
+        })
+    }
+
+    /// Every record logged on this thread while `f` runs, at any level
+    pub(crate) fn capture_logs(f: impl FnOnce()) -> Vec<String> {
+        assert!(
+            install_capture(),
+            "another logger was installed ahead of the test one"
+        );
+        CAPTURED.with(|captured| *captured.borrow_mut() = Some(Vec::new()));
+        f();
+        CAPTURED.with(|captured| captured.borrow_mut().take().unwrap_or_default())
+    }
+
+    #[derive(Default)]
+    struct Sink(Mutex<Vec<String>>);
+
+    impl Log for Sink {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn log_line(logger: &TimeIdxLogger<Sink>, msg: &str) -> String {
+        logger.log(
+            &Record::builder()
+                .args(format_args!("{msg}"))
+                .level(log::Level::Debug)
+                .build(),
+        );
+        logger.inner.0.lock().unwrap().last().unwrap().clone()
+    }
+
+    #[test]
+    fn test_prefix_follows_time_idx() {
+        let logger = TimeIdxLogger::new(Sink::default());
+        assert_eq!(log_line(&logger, "starting"), "[tidx=-] starting");
+
+        {
+            let _scope = TargetScope::enter(4242);
+            assert_eq!(log_line(&logger, "read reg Pc"), "[tidx=4242] read reg Pc");
+
+            // the waver moving the cursor mid-operation
+            set_time_idx(Some(4250));
+            assert_eq!(log_line(&logger, "stepped"), "[tidx=4250] stepped");
+        }
+        assert_eq!(log_line(&logger, "disconnected"), "[tidx=-] disconnected");
+    }
+
+    #[test]
+    fn test_time_idx_is_per_thread() {
+        let _scope = TargetScope::enter(7);
+        let other = std::thread::spawn(current_time_idx).join().unwrap();
+        assert_eq!(other, None);
+        assert_eq!(current_time_idx(), Some(7));
+    }
+}
@@ -0,0 +1,99 @@
+//! Every variable in the waveform's hierarchy, served a page at a time over `list_vars` so a
+//! client can fuzzy match against dang's copy instead of loading the waveform itself
+
+/// Whether `name` contains the characters of `filter` in order, ignoring ASCII case.
+///
+/// This is the cheapest check every fuzzy match of `filter` also passes, so filtering on the
+/// target never drops something the client's matcher would have kept
+pub fn filter_matches(filter: &str, name: &str) -> bool {
+    let mut name = name.chars();
+    filter
+        .chars()
+        .all(|f| name.any(|n| n.eq_ignore_ascii_case(&f)))
+}
+
+pub struct VarList {
+    names: Vec<String>,
+    /// Indices into `names` matching the last filter asked for. Clients page through one filter
+    /// at a time, so this saves rescanning every name for each page
+    filtered: Option<(String, Vec<usize>)>,
+}
+
+impl VarList {
+    pub fn new(names: Vec<String>) -> Self {
+        Self {
+            names,
+            filtered: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// How many names match `filter`, and up to `count` of them starting from the `offset`th
+    /// match. An empty filter matches everything
+    pub fn page(&mut self, filter: &str, offset: usize, count: usize) -> (usize, Vec<&str>) {
+        if filter.is_empty() {
+            let page = self.names.iter().skip(offset).take(count);
+            return (self.names.len(), page.map(String::as_str).collect());
+        }
+
+        if self.filtered.as_ref().map(|(f, _)| f.as_str()) != Some(filter) {
+            let matching = self
+                .names
+                .iter()
+                .enumerate()
+                .filter(|(_, name)| filter_matches(filter, name))
+                .map(|(idx, _)| idx)
+                .collect();
+            self.filtered = Some((filter.to_string(), matching));
+        }
+        let Some((_, matching)) = &self.filtered else {
+            unreachable!("filtered was just set");
+        };
+        let page = matching.iter().skip(offset).take(count);
+        (
+            matching.len(),
+            page.map(|&idx| self.names[idx].as_str()).collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_matches() {
+        assert!(filter_matches("", "top.cpu.pc"));
+        assert!(filter_matches("cpu", "top.cpu.pc"));
+        assert!(filter_matches("CPU", "top.cpu.pc"));
+        // in order, not necessarily next to each other
+        assert!(filter_matches("tpc", "top.cpu.pc"));
+        assert!(!filter_matches("cput", "top.cpu.pc"));
+        assert!(!filter_matches("pcc", "top.cpu.pc"));
+    }
+
+    #[test]
+    fn test_pages() {
+        let mut vars = VarList::new(
+            ["top.clk", "top.cpu.pc", "top.cpu.rf_x1", "top.uart.tx"]
+                .map(String::from)
+                .to_vec(),
+        );
+        assert_eq!(
+            vars.page("", 1, 2),
+            (4, vec!["top.cpu.pc", "top.cpu.rf_x1"])
+        );
+        assert_eq!(vars.page("", 3, 10), (4, vec!["top.uart.tx"]));
+        assert_eq!(vars.page("cpu", 0, 1), (2, vec!["top.cpu.pc"]));
+        assert_eq!(vars.page("cpu", 1, 1), (2, vec!["top.cpu.rf_x1"]));
+        assert_eq!(vars.page("cpu", 2, 1), (2, vec![]));
+        assert_eq!(vars.page("nope", 0, 10), (0, vec![]));
+    }
+}
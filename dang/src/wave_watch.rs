@@ -0,0 +1,95 @@
+//! Noticing the waveform being rewritten while a session is open.
+//!
+//! dang and jpdb's signal tracker both read the waveform once at startup. Rerunning the
+//! simulation writes a new one over it, and from then on they'd be answering from the old trace,
+//! or reading signals out of a file that's no longer the one they parsed. Unlike the ELF's, a
+//! waveform's contents are far too big to hash, so the size and mtime are all that's compared:
+//! a stat, cheap enough to do before every continue, PC index build and trace export
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// What the waveform on disk looked like when it was loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WaveFingerprint {
+    pub path: PathBuf,
+    pub size: u64,
+    pub mtime: Option<SystemTime>,
+}
+
+impl WaveFingerprint {
+    /// How `path` looks now, `None` if it can't be stat'd
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            mtime: metadata.modified().ok(),
+        })
+    }
+
+    /// Whether the file at `path` has a different size or mtime than it did. A file that's gone
+    /// counts as changed
+    pub fn is_stale(&self) -> bool {
+        Self::of(&self.path).map_or(true, |now| now != *self)
+    }
+
+    /// Says the file changed, and what `fix` would pick the new one up
+    pub fn changed_message(&self, fix: &str) -> String {
+        format!(
+            "waveform {} changed on disk since it was loaded, {fix}",
+            self.path.display()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::time::Duration;
+
+    fn temp_wave(name: &str, data: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("dang_{}_{name}", std::process::id()));
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_mtime_size_and_removal() {
+        let path = temp_wave("watched.fst", &[0; 64]);
+        let fingerprint = WaveFingerprint::of(&path).unwrap();
+        assert!(!fingerprint.is_stale());
+
+        // same size, rewritten later
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        assert!(fingerprint.is_stale());
+
+        let rewritten = WaveFingerprint::of(&path).unwrap();
+        fs::write(&path, [0; 65]).unwrap();
+        assert!(rewritten.is_stale());
+
+        fs::remove_file(&path).unwrap();
+        assert!(WaveFingerprint::of(&path).is_none());
+        assert!(fingerprint.is_stale());
+    }
+
+    #[test]
+    fn test_changed_message() {
+        let fingerprint = WaveFingerprint {
+            path: PathBuf::from("/sim/run.fst"),
+            size: 0,
+            mtime: None,
+        };
+        assert_eq!(
+            fingerprint.changed_message("restart dang"),
+            "waveform /sim/run.fst changed on disk since it was loaded, restart dang"
+        );
+    }
+}
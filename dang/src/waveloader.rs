@@ -1,22 +1,180 @@
 use crate::convert::Mappable;
+use crate::error::StartupError;
 use crate::runtime::{RequiredWaves, WaveCursor};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use nucleo_matcher::{
+    pattern::{AtomKind, CaseMatching, Normalization, Pattern},
+    Config, Matcher,
+};
+use pyo3::exceptions::PyKeyError;
 use pyo3::prelude::*;
+use pyo3::types::PyDict;
 use pywellen::{self, pywellen as doggy};
-use wellen::{self, LoadOptions, Signal, SignalValue, TimeTableIdx};
+use wellen::{self, LoadOptions, Signal, SignalValue, TimeTableIdx, Timescale, TimescaleUnit};
 
 use std::{cmp::Ordering, collections::HashMap, fs, path::Path};
-use std::{cmp::Reverse, sync::Once};
+use std::{
+    cmp::Reverse,
+    sync::{Arc, Once},
+    time::{Duration, Instant},
+};
 use std::{collections::BinaryHeap, path::PathBuf};
+
+/// Extra `key=value` parameters handed to the mapping script, from `--mapping-arg`
+pub type MappingArgs = HashMap<String, String>;
+
+/// What a mapping script given as source rather than a file is called in errors and tracebacks
+pub const INLINE_MAPPING_NAME: &str = "<inline-mapping>";
+
+/// Largest mapping script taken inline or from stdin
+pub const MAX_INLINE_MAPPING_BYTES: usize = 1024 * 1024;
+
+/// The mapping script's source, and the name errors and python tracebacks call it by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappingScript {
+    pub name: String,
+    pub source: String,
+}
+
+impl MappingScript {
+    /// Read the script at `path`, which is also its name
+    pub fn read(path: &Path) -> Result<Self> {
+        let source = fs::read_to_string(path)
+            .with_context(|| StartupError::mapping(format!("could not read {}", path.display())))?;
+        Ok(Self {
+            name: path.display().to_string(),
+            source,
+        })
+    }
+
+    /// A script given as source, from `--mapping-inline` or `--mapping -`. `Err` past
+    /// [`MAX_INLINE_MAPPING_BYTES`]
+    pub fn inline(source: String) -> std::result::Result<Self, String> {
+        if source.len() > MAX_INLINE_MAPPING_BYTES {
+            return Err(format!(
+                "inline mapping script is {} bytes, the most is {MAX_INLINE_MAPPING_BYTES}, \
+                 use --mapping-path for anything bigger",
+                source.len()
+            ));
+        }
+        Ok(Self {
+            name: INLINE_MAPPING_NAME.to_string(),
+            source,
+        })
+    }
+}
+
 pub struct Loaded {
     pub(crate) waves: RequiredWaves,
     pub(crate) cursor: WaveCursor,
+    /// Full names of every variable in the hierarchy, in hierarchy order
+    pub(crate) var_names: Vec<String>,
+    /// What the time table counts in, `None` if the waveform doesn't say
+    pub(crate) timescale: Option<Timescale>,
+}
+
+/// `timescale` the way a waveform header writes it, `ps` or `10ns`. `None` for a unit wellen
+/// doesn't know
+pub fn timescale_unit(timescale: &Timescale) -> Option<String> {
+    let unit = match timescale.unit {
+        TimescaleUnit::ZeptoSeconds => "zs",
+        TimescaleUnit::AttoSeconds => "as",
+        TimescaleUnit::FemtoSeconds => "fs",
+        TimescaleUnit::PicoSeconds => "ps",
+        TimescaleUnit::NanoSeconds => "ns",
+        TimescaleUnit::MicroSeconds => "us",
+        TimescaleUnit::MilliSeconds => "ms",
+        TimescaleUnit::Seconds => "s",
+        TimescaleUnit::Unknown => return None,
+    };
+    Some(match timescale.factor {
+        1 => unit.to_string(),
+        factor => format!("{factor}{unit}"),
+    })
+}
+
+/// How a waveform is loaded, from `--wave-load-threads` and `--wave-keep-scopes-with-empty-name`.
+///
+/// Loading an FST body on every core is quickest but peaks highest in memory, one thread trades
+/// load time for footprint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaveLoadOptions {
+    /// Threads to load with, 1 for the loading thread alone. `None` leaves it to rayon, which
+    /// starts one a core
+    pub threads: Option<usize>,
+    /// Keep scopes with no name in the hierarchy, rather than folding their contents into the
+    /// parent
+    pub keep_scopes_with_empty_name: bool,
+}
+
+impl Default for WaveLoadOptions {
+    fn default() -> Self {
+        Self {
+            threads: None,
+            keep_scopes_with_empty_name: true,
+        }
+    }
+}
+
+impl WaveLoadOptions {
+    /// What wellen is handed
+    pub fn wellen(&self) -> LoadOptions {
+        LoadOptions {
+            multi_thread: self.threads != Some(1),
+            remove_scopes_with_empty_name: !self.keep_scopes_with_empty_name,
+        }
+    }
+
+    /// Run `load` on as many threads as asked for: a pool of its own for a thread count past
+    /// one, wherever it's called from otherwise
+    pub fn run<T: Send>(&self, load: impl FnOnce() -> T + Send) -> Result<T> {
+        match self.threads {
+            Some(threads) if threads > 1 => {
+                let pool = rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .context("could not start the waveform load threads")?;
+                Ok(pool.install(load))
+            }
+            _ => Ok(load()),
+        }
+    }
+
+    /// How the load is spread over threads, for the load summary
+    pub fn mode(&self) -> String {
+        match self.threads {
+            Some(1) => "single-threaded".to_string(),
+            Some(threads) => format!("{threads} threads"),
+            None => "multi-threaded".to_string(),
+        }
+    }
+}
+
+/// Parse a `--wave-load-threads` flag, a thread count of at least one
+pub fn parse_load_threads(value: &str) -> std::result::Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(threads) if threads > 0 => Ok(threads),
+        _ => Err(format!(
+            "expected a thread count of 1 or more, got '{value}'"
+        )),
+    }
+}
+
+/// The one line logged once a waveform has loaded
+pub fn load_summary(
+    path: &Path,
+    elapsed: Duration,
+    signals: usize,
+    time_table_len: usize,
+    options: &WaveLoadOptions,
+) -> String {
+    format!(
+        "loaded {} in {elapsed:.2?} ({}): {signals} signals, {time_table_len} time table entries",
+        path.display(),
+        options.mode()
+    )
 }
-const LOAD_OPTS: LoadOptions = LoadOptions {
-    multi_thread: true,
-    remove_scopes_with_empty_name: false,
-};
 
 pub trait WellenSignalExt {
     /// Trivially maps idx to the first value available
@@ -120,58 +278,222 @@ fn merge_changes(arrays: Vec<&[TimeTableIdx]>) -> Vec<TimeTableIdx> {
     sorted
 }
 
+/// The cursor over `pc` and `gprs`, on the first change of the PC to `first_pc`.
+///
+/// A trace without a single change of those signals has nothing to debug and is an error. One
+/// with a single time step is fine, it's at its end from the start
+fn start_cursor(
+    pc: &Signal,
+    gprs: &[Signal],
+    all_times: wellen::TimeTable,
+    first_pc: u32,
+    script_name: &str,
+    file_name: &Path,
+) -> std::result::Result<WaveCursor, StartupError> {
+    let all_changes = merge_changes(
+        std::iter::once(pc)
+            .chain(gprs)
+            .map(|signal| signal.time_indices())
+            .collect(),
+    );
+    if all_times.is_empty() || all_changes.is_empty() {
+        return Err(StartupError::waveform(format!(
+            "waveform {} contains no value changes for the mapped signals",
+            file_name.display()
+        )));
+    }
+    let first_pc_idx = pc.find_idx(first_pc).ok_or_else(|| {
+        StartupError::mapping(format!(
+            "the pc signal from {script_name} never takes the value 0x{first_pc:x} (the first pc of the ELF) in {}",
+            file_name.display()
+        ))
+    })?;
+    log::debug!("found first PC index: {first_pc_idx}");
+    Ok(WaveCursor {
+        time_idx: first_pc_idx,
+        all_changes,
+        all_times,
+    })
+}
+
+/// Checks that `signal` could have come from the waveform that was loaded, with
+/// `time_table_len` entries. A mapping script that opens some other file and returns its signals
+/// gets time indices that mean nothing here, and would otherwise fail much later, or worse, not
+/// at all
+fn check_same_waveform(
+    name: &str,
+    signal: &Signal,
+    time_table_len: usize,
+    script_name: &str,
+    file_name: &Path,
+) -> std::result::Result<(), StartupError> {
+    let indices = signal.time_indices();
+    let out_of_range = indices
+        .last()
+        .filter(|&&last| last as usize >= time_table_len);
+    if out_of_range.is_none() && indices.len() <= time_table_len {
+        return Ok(());
+    }
+    let found = match out_of_range {
+        Some(last) => format!("changes at time index {last}"),
+        None => format!("has {} changes", indices.len()),
+    };
+    Err(StartupError::mapping(format!(
+        "mapping script appears to have loaded a different waveform: signal '{name}' from \
+         {script_name} {found}, but {} has only {time_table_len} time table entries. \
+         get_gdb_signals(wave) has to return signals from the Waveform it's passed",
+        file_name.display()
+    )))
+}
+
 impl Loaded {
     pub fn create_loaded_waves(
         file_name: PathBuf,
-        signal_py_file: PathBuf,
+        script: &MappingScript,
         first_pc: u32,
+        mapping_args: &MappingArgs,
+        load_options: &WaveLoadOptions,
     ) -> Result<Self> {
-        let header = wellen::viewers::read_header(file_name.as_path(), &LOAD_OPTS)?;
-        let hierarchy = header.hierarchy;
+        let started = Instant::now();
+        let (hierarchy, body) = load_options.run(|| -> Result<_> {
+            let header = wellen::viewers::read_header(file_name.as_path(), &load_options.wellen())
+                .with_context(|| {
+                    StartupError::waveform(format!(
+                        "could not read the header of {}",
+                        file_name.display()
+                    ))
+                })?;
+            let hierarchy = header.hierarchy;
 
-        let body = wellen::viewers::read_body(header.body, &hierarchy, None)?;
+            let body =
+                wellen::viewers::read_body(header.body, &hierarchy, None).with_context(|| {
+                    StartupError::waveform(format!(
+                        "could not read the body of {}",
+                        file_name.display()
+                    ))
+                })?;
+            Ok((hierarchy, body))
+        })??;
+        log::info!(
+            "{}",
+            load_summary(
+                &file_name,
+                started.elapsed(),
+                hierarchy.iter_vars().count(),
+                body.time_table.len(),
+                load_options
+            )
+        );
 
         let script_name = "get_gdb_signals";
         let script_output =
-            validate_get_signals(signal_py_file.as_path(), script_name, file_name.as_path());
-        if script_output.signals.is_none() {
-            return Err(anyhow::anyhow!("Failed to validate get_gdb_signals"));
-        }
-        let mut py_signals = script_output.signals.unwrap();
+            validate_get_signals(script, script_name, file_name.as_path(), mapping_args);
+        let Some(mut py_signals) = script_output.signals else {
+            let script_error = script_output
+                .error
+                .unwrap_or_else(|| "unknown error".to_string());
+            let missing = script_output
+                .last_signal_path
+                .and_then(|path| missing_signal(&hierarchy, path));
+            let detail = match missing {
+                Some((path, Some(suggestion))) => format!(
+                    "signal '{path}' not found in hierarchy of {} (did you mean {suggestion}?)",
+                    file_name.display()
+                ),
+                Some((path, None)) => format!(
+                    "signal '{path}' not found in hierarchy of {}",
+                    file_name.display()
+                ),
+                None => format!(
+                    "{script_name}() in {} failed against {}",
+                    script.name,
+                    file_name.display()
+                ),
+            };
+            return Err(anyhow::anyhow!(script_error).context(StartupError::mapping(detail)));
+        };
 
-        let pc = py_signals
-            .remove("pc")
-            .expect("No signal provided named pc!");
+        let mut take_signal = |name: &str| {
+            py_signals.remove(name).ok_or_else(|| {
+                anyhow::anyhow!(StartupError::mapping(format!(
+                    "{script_name}() in {} returned no signal named '{name}'",
+                    script.name
+                )))
+            })
+        };
 
+        let pc = take_signal("pc")?;
         let gprs: Vec<Signal> = (0..32)
-            .map(|val| {
-                py_signals
-                    .remove(format!("x{val}").as_str())
-                    .expect("No signal named x{val} provided")
-            })
-            .collect();
+            .map(|val| take_signal(format!("x{val}").as_str()))
+            .collect::<Result<_>>()?;
 
-        let mut all_changes_together = vec![];
-        all_changes_together.push(pc.time_indices());
-        for gpr in gprs.iter() {
-            all_changes_together.push(gpr.time_indices());
+        let time_table_len = body.time_table.len();
+        let named = std::iter::once(("pc".to_string(), &pc))
+            .chain((0..32).map(|i| format!("x{i}")).zip(&gprs));
+        for (name, signal) in named {
+            check_same_waveform(&name, signal, time_table_len, &script.name, &file_name)
+                .map_err(|error| anyhow::anyhow!(error))?;
         }
-        let all_changes = merge_changes(all_changes_together);
-        let first_pc_idx = pc.find_idx(first_pc).unwrap();
-        log::debug!("found first PC index: {first_pc_idx}");
-        let cursor = WaveCursor {
-            time_idx: first_pc_idx,
-            all_changes,
-            all_times: body.time_table,
-        };
+
+        let cursor = start_cursor(
+            &pc,
+            &gprs,
+            body.time_table,
+            first_pc,
+            &script.name,
+            &file_name,
+        )
+        .map_err(|error| anyhow::anyhow!(error))?;
+
+        let var_names = hierarchy
+            .iter_vars()
+            .map(|var| var.full_name(&hierarchy))
+            .collect();
 
         Ok(Loaded {
-            waves: RequiredWaves { pc, gprs },
+            waves: RequiredWaves {
+                pc: Arc::new(pc),
+                gprs,
+            },
             cursor,
+            var_names,
+            timescale: hierarchy.timescale(),
         })
     }
 }
 
+/// If `path` doesn't exist in the waveform, returns it along with the nearest real signal name,
+/// if anything comes close
+fn missing_signal(hierarchy: &wellen::Hierarchy, path: String) -> Option<(String, Option<String>)> {
+    let names: Vec<String> = hierarchy
+        .iter_vars()
+        .map(|var| var.full_name(hierarchy))
+        .collect();
+    if names.contains(&path) {
+        return None;
+    }
+    let suggestion = nearest_name(&names, &path);
+    Some((path, suggestion))
+}
+
+/// Closest signal name to `query`, going by the fuzzy matcher. Falls back to only the leaf name
+/// if the whole path doesn't match anything
+fn nearest_name(names: &[String], query: &str) -> Option<String> {
+    let mut matcher = Matcher::new(Config::DEFAULT);
+    let leaf = query.rsplit('.').next().unwrap_or(query);
+    [query, leaf].into_iter().find_map(|needle| {
+        Pattern::new(
+            needle,
+            CaseMatching::Ignore,
+            Normalization::Smart,
+            AtomKind::Fuzzy,
+        )
+        .match_list(names, &mut matcher)
+        .first()
+        .map(|(name, _)| name.to_string())
+    })
+}
+
 static INIT: Once = std::sync::Once::new();
 
 fn initialize() {
@@ -192,32 +514,56 @@ pub enum MappingParsedEvents {
 pub struct ValidationResult {
     /// If None, it failed
     pub signals: Option<HashMap<String, wellen::Signal>>,
+    /// Why it failed, if it did
+    pub error: Option<String>,
+    /// Last hierarchy path the script looked up, the likely culprit if it failed
+    pub last_signal_path: Option<String>,
 }
 
 impl ValidationResult {
     pub fn from_events(_all_events: Vec<MappingParsedEvents>) -> Self {
         Self {
             signals: None,
+            error: None,
+            last_signal_path: None,
         }
     }
 }
 
-pub fn validate_get_signals(script: &Path, fn_name: &str, wave_path: &Path) -> ValidationResult {
-    initialize();
-    let mut events = vec![];
+/// Stands in for the waveform handed to the mapping script, remembering the last path it was
+/// asked for so a failed lookup can be named
+const TRACKED_WAVE: &str = r#"
+class TrackedWave:
+    def __init__(self, wave):
+        self._wave = wave
+        self.last_path = None
 
-    let script_content = fs::read_to_string(script);
-    if let Err(_) = script_content {
-        events.push(MappingParsedEvents::FileStatus);
-        return ValidationResult::from_events(events);
-    }
-    events.push(MappingParsedEvents::FileStatus);
-    let script_content = script_content.unwrap();
+    def get_signal_from_path(self, path):
+        self.last_path = path
+        return self._wave.get_signal_from_path(path)
+
+    def __getattr__(self, name):
+        return getattr(self._wave, name)
+"#;
+
+/// Run `fn_name` from the mapping script against the waveform. Python reports problems in the
+/// script under its [`MappingScript::name`].
+///
+/// The function is called as `fn_name(wave)`, or as `fn_name(wave, args)` if it takes a second
+/// parameter, where `args` is a dict built from the `--mapping-arg key=value` flags
+pub fn validate_get_signals(
+    script: &MappingScript,
+    fn_name: &str,
+    wave_path: &Path,
+    mapping_args: &MappingArgs,
+) -> ValidationResult {
+    initialize();
+    let mut events = vec![MappingParsedEvents::FileStatus];
 
     pyo3::prepare_freethreaded_python();
+    let mut last_signal_path = None;
     let py_result = Python::with_gil(|py| {
-        let activators =
-            PyModule::from_code_bound(py, script_content.as_str(), "signal_get.py", "signal_get");
+        let activators = PyModule::from_code_bound(py, &script.source, &script.name, "signal_get");
 
         let activators = match activators {
             Ok(module) => module,
@@ -237,12 +583,15 @@ pub fn validate_get_signals(script: &Path, fn_name: &str, wave_path: &Path) -> V
             Err(e) => {
                 events.push(MappingParsedEvents::WaveCreationStatus);
                 return Err(pyo3::PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
-                    e.to_string(),
+                    format!("could not open {}: {e}", wave_path.display()),
                 ));
             }
         };
 
-        let wave_bound = Bound::new(py, wave)?;
+        let wave_bound =
+            PyModule::from_code_bound(py, TRACKED_WAVE, "tracked_wave.py", "tracked_wave")?
+                .getattr("TrackedWave")?
+                .call1((Bound::new(py, wave)?,))?;
 
         let function_result = activators.getattr(fn_name);
         let function = match function_result {
@@ -257,7 +606,24 @@ pub fn validate_get_signals(script: &Path, fn_name: &str, wave_path: &Path) -> V
             }
         };
 
-        let call_result = function.call1((wave_bound,));
+        // Only hand over the args if the function asks for them
+        let arg_count: usize = function
+            .getattr("__code__")
+            .and_then(|code| code.getattr("co_argcount"))
+            .and_then(|count| count.extract())
+            .unwrap_or(1);
+        let call_result = if arg_count >= 2 {
+            let args = PyDict::new_bound(py);
+            for (key, value) in mapping_args {
+                args.set_item(key, value)?;
+            }
+            function.call1((&wave_bound, args))
+        } else {
+            if !mapping_args.is_empty() {
+                log::warn!("{fn_name}(wave) takes no args, ignoring --mapping-arg");
+            }
+            function.call1((&wave_bound,))
+        };
         let all_waves: HashMap<String, pywellen::Signal> = match call_result {
             Ok(result) => {
                 events.push(MappingParsedEvents::GetFnCall);
@@ -265,6 +631,11 @@ pub fn validate_get_signals(script: &Path, fn_name: &str, wave_path: &Path) -> V
             }
             Err(e) => {
                 events.push(MappingParsedEvents::GetFnCall);
+                last_signal_path = wave_bound
+                    .getattr("last_path")
+                    .and_then(|path| path.extract())
+                    .ok()
+                    .flatten();
                 return Err(e);
             }
         };
@@ -275,40 +646,188 @@ pub fn validate_get_signals(script: &Path, fn_name: &str, wave_path: &Path) -> V
     // Check for required GDB signals regardless of Python execution result
     let mut missing_signals = vec![];
 
-    let signals = if let Ok(py_signals) = py_result {
-        // Convert to wellen signals
-        let wellen_signals: HashMap<String, wellen::Signal> = py_signals
-            .into_iter()
-            .filter_map(|(name, signal)| signal.to_wellen_signal().map(|s| (name, s)))
-            .collect();
+    let mut error = None;
+    let signals = match py_result {
+        Ok(py_signals) => {
+            // Convert to wellen signals
+            let wellen_signals: HashMap<String, wellen::Signal> = py_signals
+                .into_iter()
+                .filter_map(|(name, signal)| signal.to_wellen_signal().map(|s| (name, s)))
+                .collect();
 
-        // Check for required signals
-        if !wellen_signals.contains_key("pc") {
-            missing_signals.push("pc".to_string());
-        }
+            // Check for required signals
+            if !wellen_signals.contains_key("pc") {
+                missing_signals.push("pc".to_string());
+            }
 
-        for i in 0..32 {
-            let signal_name = format!("x{}", i);
-            if !wellen_signals.contains_key(&signal_name) {
-                missing_signals.push(signal_name);
+            for i in 0..32 {
+                let signal_name = format!("x{}", i);
+                if !wellen_signals.contains_key(&signal_name) {
+                    missing_signals.push(signal_name);
+                }
+            }
+            Some(wellen_signals)
+        }
+        Err(e) => {
+            let mut msg = format!("running {fn_name}() from {} raised {e}", script.name);
+            if Python::with_gil(|py| e.is_instance_of::<PyKeyError>(py)) {
+                msg.push_str(&format!(
+                    ". {fn_name}(wave, args) reads its args from --mapping-arg key=value flags, \
+                 is one missing? (got {:?})",
+                    mapping_args.keys().collect::<Vec<_>>()
+                ));
             }
+            error = Some(msg);
+            None
         }
-        Some(wellen_signals)
-    } else {
-        None
     };
 
     ValidationResult {
         signals,
+        error,
+        last_signal_path,
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
+    use std::fmt::Write;
     use std::path::PathBuf;
 
+    #[test]
+    fn test_timescale_unit() {
+        let unit = |factor, unit| timescale_unit(&Timescale { factor, unit });
+        assert_eq!(unit(1, TimescaleUnit::PicoSeconds).as_deref(), Some("ps"));
+        assert_eq!(
+            unit(10, TimescaleUnit::NanoSeconds).as_deref(),
+            Some("10ns")
+        );
+        assert_eq!(unit(100, TimescaleUnit::Seconds).as_deref(), Some("100s"));
+        assert_eq!(unit(1, TimescaleUnit::Unknown), None);
+    }
+
+    /// One 32 bit signal per entry of `signals`, changing to each value at its time index, along
+    /// with the time table they share. Time indices have to be increasing within a signal.
+    ///
+    /// wellen has no public way to put a [`Signal`] together, so this writes the changes out as
+    /// a VCD in memory and reads that back. A one bit signal toggles at every time index up to
+    /// the last change, so none of them are left out of the time table
+    pub(crate) fn synthetic_signals(
+        signals: &[&[(TimeTableIdx, u32)]],
+    ) -> (Vec<Signal>, wellen::TimeTable) {
+        let mut vcd = String::from("$timescale 1ns $end\n$scope module top $end\n");
+        vcd.push_str("$var wire 1 t tick $end\n");
+        for i in 0..signals.len() {
+            writeln!(vcd, "$var wire 32 s{i} s{i} $end").unwrap();
+        }
+        vcd.push_str("$upscope $end\n$enddefinitions $end\n");
+
+        let last = signals
+            .iter()
+            .flat_map(|changes| changes.iter().map(|(idx, _)| *idx))
+            .max()
+            .unwrap_or(0);
+        for time_idx in 0..=last {
+            writeln!(vcd, "#{time_idx}\n{}t", time_idx % 2).unwrap();
+            for (i, changes) in signals.iter().enumerate() {
+                for (_, value) in changes.iter().filter(|(idx, _)| *idx == time_idx) {
+                    writeln!(vcd, "b{value:b} s{i}").unwrap();
+                }
+            }
+        }
+
+        let header = wellen::viewers::read_header_from_bytes(
+            vcd.into_bytes(),
+            &WaveLoadOptions::default().wellen(),
+        )
+        .unwrap();
+        let hierarchy = header.hierarchy;
+        let mut body = wellen::viewers::read_body(header.body, &hierarchy, None).unwrap();
+        let signal_refs: Vec<_> = (0..signals.len())
+            .map(|i| {
+                hierarchy
+                    .iter_vars()
+                    .find(|var| var.full_name(&hierarchy) == format!("top.s{i}"))
+                    .unwrap()
+                    .signal_ref()
+            })
+            .collect();
+        let mut loaded = body.source.load_signals(&signal_refs, &hierarchy, false);
+        let signals = signal_refs
+            .iter()
+            .map(|signal_ref| {
+                let pos = loaded
+                    .iter()
+                    .position(|(loaded_ref, _)| loaded_ref == signal_ref);
+                loaded.swap_remove(pos.unwrap()).1
+            })
+            .collect();
+        (signals, body.time_table)
+    }
+
+    /// Waves with `pc` as the PC and every GPR zero from the start, and a cursor on the PC's
+    /// first change
+    pub(crate) fn synthetic_waves(
+        pc_changes: &[(TimeTableIdx, u32)],
+    ) -> (RequiredWaves, WaveCursor) {
+        let zero: &[(TimeTableIdx, u32)] = &[(0, 0)];
+        let mut all = vec![pc_changes];
+        all.extend([zero; 32]);
+        let (mut signals, all_times) = synthetic_signals(&all);
+        let gprs = signals.split_off(1);
+        let pc = signals.pop().unwrap();
+
+        let cursor = start_cursor(
+            &pc,
+            &gprs,
+            all_times,
+            pc_changes[0].1,
+            "test",
+            Path::new("test.vcd"),
+        )
+        .unwrap();
+        let waves = RequiredWaves {
+            pc: Arc::new(pc),
+            gprs,
+        };
+        (waves, cursor)
+    }
+
+    #[test]
+    fn test_no_changes_is_a_startup_error() {
+        let (signals, all_times) = synthetic_signals(&[&[], &[]]);
+        let error = start_cursor(
+            &signals[0],
+            &signals[1..],
+            all_times,
+            0x100,
+            "test",
+            Path::new("empty.vcd"),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(error.kind, crate::error::ErrorKind::Waveform);
+        assert_eq!(
+            error.detail,
+            "waveform empty.vcd contains no value changes for the mapped signals"
+        );
+
+        // a single step is a trace, one that's at its end from the start
+        let (signals, all_times) = synthetic_signals(&[&[(0, 0x100)], &[(0, 0)]]);
+        let cursor = start_cursor(
+            &signals[0],
+            &signals[1..],
+            all_times,
+            0x100,
+            "test",
+            Path::new("one.vcd"),
+        )
+        .unwrap();
+        assert_eq!((cursor.time_idx, cursor.all_times.len()), (0, 1));
+    }
+
     #[test]
     fn test_execute_get_signals() {
         // Get the path to the test script
@@ -322,7 +841,9 @@ mod tests {
         let wave_path = PathBuf::from(cargo_manifest_dir).join("../test_data/ibex/sim.fst");
 
         // Call the function
-        let result = validate_get_signals(script_path.as_path(), fn_name, wave_path.as_path());
+        let script = MappingScript::read(&script_path).unwrap();
+        let result =
+            validate_get_signals(&script, fn_name, wave_path.as_path(), &MappingArgs::new());
 
         // Check the result
         match result.signals {
@@ -336,4 +857,239 @@ mod tests {
             None => panic!("Function execution failed"),
         }
     }
+
+    #[test]
+    fn test_mapping_args_passed_to_script() {
+        let cargo_manifest_dir = env!("CARGO_MANIFEST_DIR");
+        let script_path =
+            PathBuf::from(cargo_manifest_dir).join("../test_data/ibex/signal_get_prefixed.py");
+        let wave_path = PathBuf::from(cargo_manifest_dir).join("../test_data/ibex/sim.fst");
+
+        let mut args = MappingArgs::new();
+        args.insert(
+            "prefix".to_string(),
+            "TOP.ibex_simple_system.u_top.u_ibex_top".to_string(),
+        );
+        let script = MappingScript::read(&script_path).unwrap();
+        let result = validate_get_signals(&script, "get_gdb_signals", &wave_path, &args);
+        let signals = result.signals.expect("prefixed script should succeed");
+        assert!(signals.contains_key("pc"));
+    }
+
+    #[test]
+    fn test_missing_mapping_arg_is_named() {
+        let cargo_manifest_dir = env!("CARGO_MANIFEST_DIR");
+        let script_path =
+            PathBuf::from(cargo_manifest_dir).join("../test_data/ibex/signal_get_prefixed.py");
+        let wave_path = PathBuf::from(cargo_manifest_dir).join("../test_data/ibex/sim.fst");
+
+        let script = MappingScript::read(&script_path).unwrap();
+        let result =
+            validate_get_signals(&script, "get_gdb_signals", &wave_path, &MappingArgs::new());
+        assert!(result.signals.is_none());
+        let error = result.error.expect("should explain the failure");
+        assert!(error.contains("prefix"), "{error}");
+        assert!(error.contains("--mapping-arg"), "{error}");
+    }
+
+    #[test]
+    fn test_inline_script_matches_file() {
+        let cargo_manifest_dir = env!("CARGO_MANIFEST_DIR");
+        let script_path = PathBuf::from(cargo_manifest_dir).join("../test_data/ibex/signal_get.py");
+        let wave_path = PathBuf::from(cargo_manifest_dir).join("../test_data/ibex/sim.fst");
+        let elf_path = PathBuf::from(cargo_manifest_dir).join("../test_data/ibex/hello_test.elf");
+
+        let source = fs::read_to_string(script_path).unwrap();
+        let inline = MappingScript::inline(source).unwrap();
+        assert_eq!(inline.name, INLINE_MAPPING_NAME);
+
+        let from_file = crate::runtime::tests::fixture_waver();
+        let from_inline = crate::runtime::Waver::new_with_mapping_script(
+            wave_path,
+            &inline,
+            elf_path,
+            &MappingArgs::new(),
+            &WaveLoadOptions::default(),
+        )
+        .unwrap();
+
+        let same = |a: &Signal, b: &Signal| {
+            a.time_indices() == b.time_indices()
+                && a.time_indices()
+                    .iter()
+                    .all(|&idx| a.get_val(idx).to_bit_string() == b.get_val(idx).to_bit_string())
+        };
+        let (file_waves, inline_waves) = (&from_file.waves, &from_inline.waves);
+        assert!(same(&file_waves.pc, &inline_waves.pc));
+        assert_eq!(file_waves.gprs.len(), inline_waves.gprs.len());
+        for (a, b) in file_waves.gprs.iter().zip(&inline_waves.gprs) {
+            assert!(same(a, b));
+        }
+        assert_eq!(from_file.cursor.time_idx, from_inline.cursor.time_idx);
+    }
+
+    /// A VCD with a `top.pc` that counts up from 0x100000 for `steps` time steps, and `top.x0`
+    /// to `top.x31` all zero
+    fn write_mapped_vcd(path: &Path, steps: u32) {
+        let mut vcd = String::from("$timescale 1ns $end\n$scope module top $end\n");
+        vcd.push_str("$var wire 32 p pc $end\n");
+        for i in 0..32 {
+            writeln!(vcd, "$var wire 32 r{i} x{i} $end").unwrap();
+        }
+        vcd.push_str("$upscope $end\n$enddefinitions $end\n");
+        for step in 0..steps {
+            writeln!(vcd, "#{step}\nb{:b} p", 0x100000 + 4 * step).unwrap();
+            if step == 0 {
+                for i in 0..32 {
+                    writeln!(vcd, "b0 r{i}").unwrap();
+                }
+            }
+        }
+        fs::write(path, vcd).unwrap();
+    }
+
+    #[test]
+    fn test_signals_from_another_waveform_are_rejected() {
+        let cargo_manifest_dir = env!("CARGO_MANIFEST_DIR");
+        let elf_path = PathBuf::from(cargo_manifest_dir).join("../test_data/ibex/hello_test.elf");
+        let dir = std::env::temp_dir().join(format!("dang_other_wave_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let loaded = dir.join("loaded.vcd");
+        let other = dir.join("other.vcd");
+        write_mapped_vcd(&loaded, 3);
+        write_mapped_vcd(&other, 50);
+
+        let script = MappingScript::inline(
+            "import pywellen\n\
+             def get_gdb_signals(wave, args):\n\
+             \x20   other = pywellen.Waveform(args[\"other\"], True, True)\n\
+             \x20   signals = {f\"x{i}\": other.get_signal_from_path(f\"top.x{i}\") for i in range(32)}\n\
+             \x20   signals[\"pc\"] = other.get_signal_from_path(\"top.pc\")\n\
+             \x20   return signals\n"
+                .to_string(),
+        )
+        .unwrap();
+        let mut args = MappingArgs::new();
+        args.insert("other".to_string(), other.display().to_string());
+        let result = crate::runtime::Waver::new_with_mapping_script(
+            loaded,
+            &script,
+            elf_path,
+            &args,
+            &WaveLoadOptions::default(),
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        let error = format!("{:#}", result.err().expect("foreign signals should fail"));
+        assert!(
+            error.contains("mapping script appears to have loaded a different waveform"),
+            "{error}"
+        );
+        assert!(error.contains("the Waveform it's passed"), "{error}");
+    }
+
+    #[test]
+    fn test_same_waveform_check() {
+        let (signals, all_times) = synthetic_signals(&[&[(0, 1), (4, 2)]]);
+        let file = Path::new("loaded.vcd");
+        assert!(check_same_waveform("pc", &signals[0], all_times.len(), "map.py", file).is_ok());
+        let error = check_same_waveform("pc", &signals[0], 3, "map.py", file).unwrap_err();
+        assert_eq!(error.kind, crate::error::ErrorKind::Mapping);
+        assert!(error.detail.contains("signal 'pc'"), "{}", error.detail);
+        assert!(error.detail.contains("time index 4"), "{}", error.detail);
+        assert!(
+            error.detail.contains("only 3 time table entries"),
+            "{}",
+            error.detail
+        );
+    }
+
+    #[test]
+    fn test_single_threaded_load_matches() {
+        let cargo_manifest_dir = env!("CARGO_MANIFEST_DIR");
+        let data = PathBuf::from(cargo_manifest_dir).join("../test_data/ibex");
+        let script = MappingScript::read(&data.join("signal_get.py")).unwrap();
+        let load = |threads| {
+            let options = WaveLoadOptions {
+                threads,
+                ..WaveLoadOptions::default()
+            };
+            crate::runtime::Waver::new_with_mapping_script(
+                data.join("sim.fst"),
+                &script,
+                data.join("hello_test.elf"),
+                &MappingArgs::new(),
+                &options,
+            )
+            .unwrap()
+        };
+
+        let multi = load(None);
+        for threads in [Some(1), Some(2)] {
+            let waver = load(threads);
+            assert_eq!(waver.cursor.time_idx, multi.cursor.time_idx);
+            assert_eq!(waver.get_current_pc::<u32>(), multi.get_current_pc::<u32>());
+            assert_eq!(waver.cursor.all_times, multi.cursor.all_times);
+            assert_eq!(waver.waves.pc.time_indices(), multi.waves.pc.time_indices());
+        }
+    }
+
+    #[test]
+    fn test_load_options() {
+        let single = WaveLoadOptions {
+            threads: Some(1),
+            keep_scopes_with_empty_name: false,
+        };
+        assert!(!single.wellen().multi_thread);
+        assert!(single.wellen().remove_scopes_with_empty_name);
+        assert_eq!(single.mode(), "single-threaded");
+
+        let default = WaveLoadOptions::default();
+        assert!(default.wellen().multi_thread);
+        assert!(!default.wellen().remove_scopes_with_empty_name);
+        assert_eq!(default.mode(), "multi-threaded");
+        assert_eq!(
+            WaveLoadOptions {
+                threads: Some(4),
+                ..default
+            }
+            .mode(),
+            "4 threads"
+        );
+        assert_eq!(default.run(|| 7).unwrap(), 7);
+
+        assert_eq!(parse_load_threads("1"), Ok(1));
+        assert_eq!(parse_load_threads("8"), Ok(8));
+        assert!(parse_load_threads("0").is_err());
+        assert!(parse_load_threads("many").is_err());
+
+        let summary = load_summary(
+            Path::new("sim.fst"),
+            Duration::from_millis(1500),
+            120,
+            4000,
+            &single,
+        );
+        assert_eq!(
+            summary,
+            "loaded sim.fst in 1.50s (single-threaded): 120 signals, 4000 time table entries"
+        );
+    }
+
+    #[test]
+    fn test_inline_syntax_error_names_the_script() {
+        let cargo_manifest_dir = env!("CARGO_MANIFEST_DIR");
+        let wave_path = PathBuf::from(cargo_manifest_dir).join("../test_data/ibex/sim.fst");
+
+        let script =
+            MappingScript::inline("def get_gdb_signals(wave)\n    return {}\n".into()).unwrap();
+        let result =
+            validate_get_signals(&script, "get_gdb_signals", &wave_path, &MappingArgs::new());
+        assert!(result.signals.is_none());
+        let error = result.error.expect("should explain the failure");
+        assert!(error.contains("SyntaxError"), "{error}");
+        assert!(error.contains(INLINE_MAPPING_NAME), "{error}");
+
+        assert!(MappingScript::inline("#".repeat(MAX_INLINE_MAPPING_BYTES + 1)).is_err());
+    }
 }
@@ -0,0 +1,322 @@
+//! How every pane writes an address: [`AddrFormatter`], set with
+//! `set addrfmt [width 32|64] [pad on|off] [symbols on|off|short] [symlen <n>]`.
+//!
+//! The hex is always `0x` prefixed, zero padded to the width with `pad on`. With symbols on, a
+//! ` <main+0x14>` label follows, and `short` cuts long names down to `symlen` characters with an
+//! ellipsis. Lines that already spell out the full symbolized location, the frame line and the
+//! breakpoint table, take the hex alone from [`AddrFormatter::hex`]
+
+use std::fmt;
+
+use shucks::client::Client;
+
+/// Symbol names longer than this are cut short in `short` mode unless `symlen` says otherwise
+pub const DEFAULT_SYMBOL_LEN: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddrWidth {
+    Bits32,
+    Bits64,
+}
+
+impl AddrWidth {
+    fn digits(self) -> usize {
+        match self {
+            AddrWidth::Bits32 => 8,
+            AddrWidth::Bits64 => 16,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolMode {
+    Off,
+    On,
+    /// Names past the symbol length are cut short
+    Short,
+}
+
+/// One `set addrfmt` option, the rest of the formatter stays as it was
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddrFmtOption {
+    Width(AddrWidth),
+    Pad(bool),
+    Symbols(SymbolMode),
+    SymbolLen(usize),
+}
+
+/// Where the `func+0xoff` labels come from
+pub trait SymbolSource {
+    fn symbol_offset(&self, addr: u64) -> Option<String>;
+}
+
+impl SymbolSource for Client {
+    fn symbol_offset(&self, addr: u64) -> Option<String> {
+        u32::try_from(addr)
+            .ok()
+            .and_then(|addr| Client::symbol_offset(self, addr))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddrFormatter {
+    pub width: AddrWidth,
+    pub pad: bool,
+    pub symbols: SymbolMode,
+    /// Most characters of a symbol name `short` mode keeps, the ellipsis included
+    pub symbol_len: usize,
+}
+
+impl Default for AddrFormatter {
+    fn default() -> Self {
+        Self {
+            width: AddrWidth::Bits32,
+            pad: false,
+            symbols: SymbolMode::Off,
+            symbol_len: DEFAULT_SYMBOL_LEN,
+        }
+    }
+}
+
+impl AddrFormatter {
+    pub fn apply(&mut self, option: AddrFmtOption) {
+        match option {
+            AddrFmtOption::Width(width) => self.width = width,
+            AddrFmtOption::Pad(pad) => self.pad = pad,
+            AddrFmtOption::Symbols(symbols) => self.symbols = symbols,
+            AddrFmtOption::SymbolLen(len) => self.symbol_len = len,
+        }
+    }
+
+    /// `0x` and the hex, padded to the width with `pad on`. An address too wide for the width
+    /// is written out whole rather than cut
+    pub fn hex(&self, addr: u64) -> String {
+        if self.pad {
+            format!("0x{addr:0width$x}", width = self.width.digits())
+        } else {
+            format!("0x{addr:x}")
+        }
+    }
+
+    /// `0x` and the hex padded to the width whatever `pad` says, for values lined up in a column
+    /// like register contents
+    pub fn column(&self, value: u64) -> String {
+        format!("0x{value:0width$x}", width = self.width.digits())
+    }
+
+    /// [`AddrFormatter::hex`], then ` <func+0xoff>` with symbols on and a symbol covering
+    /// `addr`. `symbols` isn't asked with symbols off
+    pub fn format(&self, addr: u64, symbols: &impl SymbolSource) -> String {
+        let hex = self.hex(addr);
+        let label = match self.symbols {
+            SymbolMode::Off => None,
+            SymbolMode::On => symbols.symbol_offset(addr),
+            SymbolMode::Short => symbols
+                .symbol_offset(addr)
+                .map(|label| self.shorten(&label)),
+        };
+        match label {
+            Some(label) => format!("{hex} <{label}>"),
+            None => hex,
+        }
+    }
+
+    /// Cut the name part of `func+0xoff` to the symbol length, keeping the offset
+    fn shorten(&self, label: &str) -> String {
+        let (name, offset) = match label.rsplit_once("+0x") {
+            Some((name, offset)) => (name, Some(offset)),
+            None => (label, None),
+        };
+        let name = if name.chars().count() > self.symbol_len {
+            let kept: String = name.chars().take(self.symbol_len - 1).collect();
+            format!("{kept}…")
+        } else {
+            name.to_string()
+        };
+        match offset {
+            Some(offset) => format!("{name}+0x{offset}"),
+            None => name,
+        }
+    }
+}
+
+impl fmt::Display for AddrFormatter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = match self.width {
+            AddrWidth::Bits32 => 32,
+            AddrWidth::Bits64 => 64,
+        };
+        let symbols = match self.symbols {
+            SymbolMode::Off => "off",
+            SymbolMode::On => "on",
+            SymbolMode::Short => "short",
+        };
+        write!(
+            f,
+            "width {width} pad {} symbols {symbols} symlen {}",
+            if self.pad { "on" } else { "off" },
+            self.symbol_len
+        )
+    }
+}
+
+/// Parse the arguments of `set addrfmt`, option and value pairs in any order
+pub fn parse_addrfmt_options(input: &str) -> Result<Vec<AddrFmtOption>, String> {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    if parts.is_empty() || parts.len() % 2 != 0 {
+        return Err(
+            "usage: set addrfmt [width 32|64] [pad on|off] [symbols on|off|short] [symlen <n>]"
+                .to_string(),
+        );
+    }
+    parts
+        .chunks(2)
+        .map(|pair| match (pair[0], pair[1]) {
+            ("width", "32") => Ok(AddrFmtOption::Width(AddrWidth::Bits32)),
+            ("width", "64") => Ok(AddrFmtOption::Width(AddrWidth::Bits64)),
+            ("width", other) => Err(format!("Expected a width of 32 or 64, got '{other}'")),
+            ("pad", "on") => Ok(AddrFmtOption::Pad(true)),
+            ("pad", "off") => Ok(AddrFmtOption::Pad(false)),
+            ("symbols", "on") => Ok(AddrFmtOption::Symbols(SymbolMode::On)),
+            ("symbols", "off") => Ok(AddrFmtOption::Symbols(SymbolMode::Off)),
+            ("symbols", "short") => Ok(AddrFmtOption::Symbols(SymbolMode::Short)),
+            ("pad" | "symbols", other) => Err(format!("Unexpected value '{other}'")),
+            ("symlen", len) => match len.parse::<usize>() {
+                Ok(len) if len > 0 => Ok(AddrFmtOption::SymbolLen(len)),
+                _ => Err(format!("Expected a positive symbol length, got '{len}'")),
+            },
+            (option, _) => Err(format!("Unknown addrfmt option: {option}")),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// `main` from 0x100200 on, nothing below it, and a count of lookups
+    struct FakeSymbols {
+        name: &'static str,
+        lookups: Cell<usize>,
+    }
+
+    impl FakeSymbols {
+        fn new(name: &'static str) -> Self {
+            Self {
+                name,
+                lookups: Cell::new(0),
+            }
+        }
+    }
+
+    impl SymbolSource for FakeSymbols {
+        fn symbol_offset(&self, addr: u64) -> Option<String> {
+            self.lookups.set(self.lookups.get() + 1);
+            (addr >= 0x100200).then(|| format!("{}+0x{:x}", self.name, addr - 0x100200))
+        }
+    }
+
+    fn formatter(width: AddrWidth, pad: bool, symbols: SymbolMode) -> AddrFormatter {
+        AddrFormatter {
+            width,
+            pad,
+            symbols,
+            ..AddrFormatter::default()
+        }
+    }
+
+    #[test]
+    fn test_width_and_pad() {
+        let symbols = FakeSymbols::new("main");
+        for width in [AddrWidth::Bits32, AddrWidth::Bits64] {
+            let fmt = formatter(width, false, SymbolMode::Off);
+            assert_eq!(fmt.format(0x100214, &symbols), "0x100214");
+        }
+        let fmt = formatter(AddrWidth::Bits32, true, SymbolMode::Off);
+        assert_eq!(fmt.format(0x100214, &symbols), "0x00100214");
+        let fmt = formatter(AddrWidth::Bits64, true, SymbolMode::Off);
+        assert_eq!(fmt.format(0x100214, &symbols), "0x0000000000100214");
+        // too wide for 32 bits, written out whole
+        let fmt = formatter(AddrWidth::Bits32, true, SymbolMode::Off);
+        assert_eq!(fmt.hex(0x1_0000_0000), "0x100000000");
+        // none of that asked for a symbol
+        assert_eq!(symbols.lookups.get(), 0);
+    }
+
+    #[test]
+    fn test_symbols() {
+        let symbols = FakeSymbols::new("main");
+        let fmt = formatter(AddrWidth::Bits32, false, SymbolMode::On);
+        assert_eq!(fmt.format(0x100214, &symbols), "0x100214 <main+0x14>");
+        // no symbol covers it, so no label
+        assert_eq!(fmt.format(0x100, &symbols), "0x100");
+        let fmt = formatter(AddrWidth::Bits64, true, SymbolMode::Short);
+        assert_eq!(
+            fmt.format(0x100200, &symbols),
+            "0x0000000000100200 <main+0x0>"
+        );
+        assert_eq!(fmt.format(0x100, &symbols), "0x0000000000000100");
+    }
+
+    #[test]
+    fn test_short_truncation() {
+        let symbols = FakeSymbols::new("uart_write_blocking");
+        let mut fmt = formatter(AddrWidth::Bits32, false, SymbolMode::Short);
+        fmt.symbol_len = 8;
+        assert_eq!(fmt.format(0x100204, &symbols), "0x100204 <uart_wr…+0x4>");
+        // exactly at the limit stays whole
+        fmt.symbol_len = 19;
+        assert_eq!(
+            fmt.format(0x100204, &symbols),
+            "0x100204 <uart_write_blocking+0x4>"
+        );
+        fmt.symbol_len = 18;
+        assert_eq!(
+            fmt.format(0x100204, &symbols),
+            "0x100204 <uart_write_blockin…+0x4>"
+        );
+        // room for nothing but the ellipsis
+        fmt.symbol_len = 1;
+        assert_eq!(fmt.format(0x100204, &symbols), "0x100204 <…+0x4>");
+        // names are cut by character, not byte
+        let symbols = FakeSymbols::new("día_de_reset");
+        fmt.symbol_len = 3;
+        assert_eq!(fmt.format(0x100200, &symbols), "0x100200 <dí…+0x0>");
+        // on leaves long names alone
+        fmt.symbols = SymbolMode::On;
+        assert_eq!(
+            fmt.format(0x100200, &symbols),
+            "0x100200 <día_de_reset+0x0>"
+        );
+    }
+
+    #[test]
+    fn test_parse_addrfmt_options() {
+        assert_eq!(
+            parse_addrfmt_options("width 64 pad on symbols short symlen 12").unwrap(),
+            [
+                AddrFmtOption::Width(AddrWidth::Bits64),
+                AddrFmtOption::Pad(true),
+                AddrFmtOption::Symbols(SymbolMode::Short),
+                AddrFmtOption::SymbolLen(12),
+            ]
+        );
+        assert_eq!(
+            parse_addrfmt_options("symbols off").unwrap(),
+            [AddrFmtOption::Symbols(SymbolMode::Off)]
+        );
+        assert!(parse_addrfmt_options("").is_err());
+        assert!(parse_addrfmt_options("width").is_err());
+        assert!(parse_addrfmt_options("width 48").is_err());
+        assert!(parse_addrfmt_options("pad maybe").is_err());
+        assert!(parse_addrfmt_options("symlen 0").is_err());
+        assert!(parse_addrfmt_options("color on").is_err());
+
+        let mut fmt = AddrFormatter::default();
+        for option in parse_addrfmt_options("pad on symbols on").unwrap() {
+            fmt.apply(option);
+        }
+        assert_eq!(fmt.to_string(), "width 32 pad on symbols on symlen 24");
+    }
+}
@@ -0,0 +1,164 @@
+//! User-defined command shortcuts, e.g. `alias ss step 5`
+
+use std::collections::HashMap;
+
+use crate::user_commands::CommandRegistry;
+
+/// How many aliases deep an expansion may go before it's treated as a loop
+pub const MAX_ALIAS_DEPTH: usize = 4;
+
+/// Aliases by name, each expanding to the start of a command line
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AliasTable {
+    entries: HashMap<String, String>,
+}
+
+impl AliasTable {
+    /// Define or redefine `name`. Built-in command names and their aliases can't be shadowed
+    pub fn define(
+        &mut self,
+        registry: &CommandRegistry,
+        name: &str,
+        expansion: &str,
+    ) -> Result<(), String> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(format!("Invalid alias name: '{name}'"));
+        }
+        if let Some(command) = registry.get_command(name) {
+            return Err(format!(
+                "'{name}' is already a name for the built-in command '{}'",
+                command.name()
+            ));
+        }
+        let expansion = expansion.trim();
+        if expansion.is_empty() {
+            return Err(format!("alias {name} needs a command to expand to"));
+        }
+        self.entries.insert(name.to_string(), expansion.to_string());
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<String, String> {
+        self.entries
+            .remove(name)
+            .ok_or_else(|| format!("No alias named '{name}'"))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries.get(name).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// `(name, expansion)` pairs sorted by name
+    pub fn sorted(&self) -> Vec<(&str, &str)> {
+        let mut entries: Vec<(&str, &str)> = self
+            .entries
+            .iter()
+            .map(|(name, expansion)| (name.as_str(), expansion.as_str()))
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    /// Expand the alias at the start of `input`, if any. Whatever follows the alias is appended
+    /// to its expansion, and an expansion starting with another alias is expanded in turn, up to
+    /// [`MAX_ALIAS_DEPTH`] aliases deep
+    pub fn expand(&self, input: &str) -> Result<String, String> {
+        let mut line = input.to_string();
+        let mut chain: Vec<&str> = Vec::new();
+        loop {
+            let (name, rest) = line.split_once(' ').unwrap_or((line.as_str(), ""));
+            let Some((name, expansion)) = self.entries.get_key_value(name) else {
+                return Ok(line);
+            };
+            chain.push(name);
+            if chain.len() > MAX_ALIAS_DEPTH {
+                return Err(format!(
+                    "Alias expansion too deep (more than {MAX_ALIAS_DEPTH}): {}",
+                    chain.join(" -> ")
+                ));
+            }
+            line = if rest.trim().is_empty() {
+                expansion.clone()
+            } else {
+                format!("{expansion} {}", rest.trim())
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(entries: &[(&str, &str)]) -> AliasTable {
+        let registry = CommandRegistry::new();
+        let mut aliases = AliasTable::default();
+        for (name, expansion) in entries {
+            aliases.define(&registry, name, expansion).unwrap();
+        }
+        aliases
+    }
+
+    #[test]
+    fn test_expand() {
+        let aliases = table(&[("ss", "step 5"), ("bm", "breakpoint main.c:")]);
+        assert_eq!(aliases.expand("ss").unwrap(), "step 5");
+        assert_eq!(aliases.expand("continue").unwrap(), "continue");
+        // only the command position is expanded
+        assert_eq!(aliases.expand("help ss").unwrap(), "help ss");
+    }
+
+    #[test]
+    fn test_expand_appends_arguments() {
+        let aliases = table(&[("bp", "breakpoint"), ("sd", "set showbytes")]);
+        assert_eq!(
+            aliases.expand("bp main.c:42").unwrap(),
+            "breakpoint main.c:42"
+        );
+        assert_eq!(aliases.expand("sd  on ").unwrap(), "set showbytes on");
+    }
+
+    #[test]
+    fn test_expand_chains_up_to_the_limit() {
+        let aliases = table(&[("a1", "a2"), ("a2", "a3"), ("a3", "a4"), ("a4", "step")]);
+        assert_eq!(aliases.expand("a1").unwrap(), "step");
+
+        let too_deep = table(&[
+            ("a1", "a2"),
+            ("a2", "a3"),
+            ("a3", "a4"),
+            ("a4", "a5"),
+            ("a5", "step"),
+        ]);
+        let err = too_deep.expand("a1").unwrap_err();
+        assert!(err.contains("a1 -> a2 -> a3 -> a4 -> a5"), "{err}");
+
+        // loops hit the same limit instead of spinning
+        let looping = table(&[("x", "y 1"), ("y", "x 2")]);
+        assert!(looping.expand("x").is_err());
+    }
+
+    #[test]
+    fn test_define_rejects_shadowing() {
+        let registry = CommandRegistry::new();
+        let mut aliases = AliasTable::default();
+        let err = aliases.define(&registry, "s", "continue").unwrap_err();
+        assert!(err.contains("'step'"), "{err}");
+        assert!(aliases.define(&registry, "breakpoint", "step").is_err());
+        assert!(aliases.define(&registry, "alias", "step").is_err());
+        assert!(aliases.define(&registry, "ss", "").is_err());
+        assert!(aliases.is_empty());
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut aliases = table(&[("ss", "step 5")]);
+        assert_eq!(aliases.remove("ss").unwrap(), "step 5");
+        assert!(aliases.remove("ss").is_err());
+        assert_eq!(aliases.expand("ss").unwrap(), "ss");
+    }
+}
@@ -0,0 +1,673 @@
+//! Breakpoint table with ids, the selectors the bulk breakpoint commands take, and the gdb
+//! command files `bexport` and `bimport` trade it in
+
+use std::collections::{BTreeMap, VecDeque};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use crate::addr_format::AddrFormatter;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub id: u32,
+    pub address: u32,
+    /// What the user typed to set it, e.g. `0x1000a4`, `main.c:42` or `main.c:42:17`
+    pub spec: String,
+    pub enabled: bool,
+    /// Hits the target will still let go by, as of the last look at its breakpoint listing
+    pub ignore_remaining: u32,
+    /// Time indices of the stops put down to this breakpoint, oldest first, the last
+    /// [`MAX_HIT_HISTORY`] of them
+    pub hits: VecDeque<u64>,
+}
+
+/// Hits each breakpoint remembers, older ones are dropped
+pub const MAX_HIT_HISTORY: usize = 50;
+
+impl Breakpoint {
+    /// The file and line this was set from, `None` if it was set by address
+    pub fn file_line(&self) -> Option<(&str, u64)> {
+        split_file_line(&self.spec).map(|(file, line, _)| (file, line))
+    }
+
+    /// [`Breakpoint::file_line`], along with the column if it was set on one
+    pub fn file_line_column(&self) -> Option<(&str, u64, Option<u64>)> {
+        split_file_line(&self.spec)
+    }
+
+    /// Whether this came from a `file:line` spec naming `file`. Matches whole path components, so
+    /// `main.c` matches `src/main.c` but not `domain.c`
+    pub fn references_file(&self, file: &str) -> bool {
+        self.file_line()
+            .is_some_and(|(spec_file, _)| Path::new(spec_file).ends_with(file))
+    }
+}
+
+/// Split `file:line` or `file:line:column` into its parts, `None` if it's neither. A column of 0
+/// is no column
+pub fn split_file_line(spec: &str) -> Option<(&str, u64, Option<u64>)> {
+    let (rest, last) = spec.rsplit_once(':')?;
+    let last = last.parse().ok()?;
+    if let Some((file, line)) = rest.rsplit_once(':') {
+        if let Ok(line) = line.parse() {
+            let column = Some(last).filter(|&column| column != 0);
+            return (!file.is_empty()).then_some((file, line, column));
+        }
+    }
+    (!rest.is_empty()).then_some((rest, last, None))
+}
+
+/// Selects breakpoints for `bdel`, `bdisable` and `benable`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selector {
+    All,
+    /// Breakpoint ids, written in decimal
+    Ids(RangeInclusive<u32>),
+    /// Breakpoint addresses, written in hex with a `0x` prefix
+    Addresses(RangeInclusive<u32>),
+}
+
+impl Selector {
+    pub fn matches(&self, breakpoint: &Breakpoint) -> bool {
+        match self {
+            Selector::All => true,
+            Selector::Ids(ids) => ids.contains(&breakpoint.id),
+            Selector::Addresses(addrs) => addrs.contains(&breakpoint.address),
+        }
+    }
+
+    /// The selector the way it's typed, addresses written by `addr_fmt`
+    pub fn describe(&self, addr_fmt: &AddrFormatter) -> String {
+        let hex = |addr: &u32| addr_fmt.hex((*addr).into());
+        match self {
+            Selector::All => "all".to_string(),
+            Selector::Ids(ids) if ids.start() == ids.end() => ids.start().to_string(),
+            Selector::Ids(ids) => format!("{}-{}", ids.start(), ids.end()),
+            Selector::Addresses(addrs) if addrs.start() == addrs.end() => hex(addrs.start()),
+            Selector::Addresses(addrs) => format!("{}-{}", hex(addrs.start()), hex(addrs.end())),
+        }
+    }
+}
+
+fn parse_address(input: &str) -> Result<u32, String> {
+    let hex = input
+        .strip_prefix("0x")
+        .or_else(|| input.strip_prefix("0X"))
+        .ok_or_else(|| format!("Addresses need a 0x prefix: {input}"))?;
+    u32::from_str_radix(hex, 16).map_err(|_| format!("Invalid address: {input}"))
+}
+
+fn parse_id(input: &str) -> Result<u32, String> {
+    input
+        .parse::<u32>()
+        .map_err(|_| format!("Invalid breakpoint id: {input}"))
+}
+
+fn parse_selector(input: &str) -> Result<Selector, String> {
+    if input == "all" {
+        return Ok(Selector::All);
+    }
+    // A 0x prefix makes it an address (range), otherwise it's an id (range)
+    let is_address = input.starts_with("0x") || input.starts_with("0X");
+    let (start, end) = input.split_once('-').unwrap_or((input, input));
+    let range = if is_address {
+        parse_address(start)?..=parse_address(end)?
+    } else {
+        parse_id(start)?..=parse_id(end)?
+    };
+    if range.is_empty() {
+        return Err(format!("Empty range: {input}"));
+    }
+    Ok(if is_address {
+        Selector::Addresses(range)
+    } else {
+        Selector::Ids(range)
+    })
+}
+
+/// Parse a selector list like `all`, `3`, `1,3,5-7` or `0x100200-0x100300`. Commas and
+/// whitespace both separate selectors
+pub fn parse_selectors(input: &str) -> Result<Vec<Selector>, String> {
+    let selectors = input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(parse_selector)
+        .collect::<Result<Vec<_>, _>>()?;
+    if selectors.is_empty() {
+        return Err("expected breakpoint ids, an address range or 'all'".to_string());
+    }
+    Ok(selectors)
+}
+
+/// What a bulk breakpoint operation touched
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BulkResult {
+    /// Ids of the breakpoints affected, in order
+    pub affected: Vec<u32>,
+    /// Selectors that matched no breakpoint at all
+    pub unmatched: Vec<String>,
+}
+
+impl BulkResult {
+    /// One line naming the affected ids, one naming the selectors that matched nothing
+    pub fn report(&self, verb: &str) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.affected.is_empty() {
+            lines.push(format!("No breakpoints {verb}"));
+        } else {
+            lines.push(format!(
+                "{} {} breakpoint{}: {}",
+                capitalize(verb),
+                self.affected.len(),
+                if self.affected.len() == 1 { "" } else { "s" },
+                self.affected
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        if !self.unmatched.is_empty() {
+            lines.push(format!(
+                "No breakpoints matched: {}",
+                self.unmatched.join(", ")
+            ));
+        }
+        lines
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Every breakpoint set through jpdb, by id. Ids count up from 1 and are never reused
+#[derive(Debug, Default)]
+pub struct BreakpointTable {
+    entries: BTreeMap<u32, Breakpoint>,
+    last_id: u32,
+}
+
+impl BreakpointTable {
+    pub fn add(&mut self, address: u32, spec: &str) -> Breakpoint {
+        self.last_id += 1;
+        let breakpoint = Breakpoint {
+            id: self.last_id,
+            address,
+            spec: spec.to_string(),
+            enabled: true,
+            ignore_remaining: 0,
+            hits: VecDeque::new(),
+        };
+        self.entries.insert(breakpoint.id, breakpoint.clone());
+        breakpoint
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Breakpoint> {
+        self.entries.values()
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Breakpoint> {
+        self.entries.get(&id)
+    }
+
+    pub fn remove(&mut self, id: u32) -> Option<Breakpoint> {
+        self.entries.remove(&id)
+    }
+
+    pub fn set_enabled(&mut self, id: u32, enabled: bool) {
+        if let Some(breakpoint) = self.entries.get_mut(&id) {
+            breakpoint.enabled = enabled;
+        }
+    }
+
+    /// The target keeps one ignore count per address, so every entry at `address` shares it
+    pub fn set_ignore_remaining(&mut self, address: u32, count: u32) {
+        for breakpoint in self.entries.values_mut() {
+            if breakpoint.address == address {
+                breakpoint.ignore_remaining = count;
+            }
+        }
+    }
+
+    /// Put a stop at `address` at `time_idx` down to every enabled breakpoint there, returning
+    /// their ids. Stopping at the same time index again isn't another hit
+    pub fn record_hit(&mut self, address: u32, time_idx: u64) -> Vec<u32> {
+        let mut ids = Vec::new();
+        for breakpoint in self.entries.values_mut() {
+            if !breakpoint.enabled || breakpoint.address != address {
+                continue;
+            }
+            ids.push(breakpoint.id);
+            if breakpoint.hits.back() == Some(&time_idx) {
+                continue;
+            }
+            if breakpoint.hits.len() == MAX_HIT_HISTORY {
+                breakpoint.hits.pop_front();
+            }
+            breakpoint.hits.push_back(time_idx);
+        }
+        ids
+    }
+
+    /// Whether any enabled breakpoint sits at `address`, i.e. the target should have it set
+    pub fn armed_at(&self, address: u32) -> bool {
+        self.iter().any(|bp| bp.enabled && bp.address == address)
+    }
+
+    /// Whether there's a breakpoint at `address`, and if so whether any of them is enabled
+    pub fn enabled_at(&self, address: u32) -> Option<bool> {
+        self.iter()
+            .filter(|bp| bp.address == address)
+            .map(|bp| bp.enabled)
+            .reduce(|a, b| a || b)
+    }
+
+    /// Ids matched by any of `selectors`, and the selectors that matched nothing
+    pub fn select(&self, selectors: &[Selector], addr_fmt: &AddrFormatter) -> BulkResult {
+        let affected = self
+            .iter()
+            .filter(|bp| selectors.iter().any(|selector| selector.matches(bp)))
+            .map(|bp| bp.id)
+            .collect();
+        let unmatched = selectors
+            .iter()
+            .filter(|selector| !self.iter().any(|bp| selector.matches(bp)))
+            .map(|selector| selector.describe(addr_fmt))
+            .collect();
+        BulkResult {
+            affected,
+            unmatched,
+        }
+    }
+
+    /// Ids of breakpoints set from a `file:line` spec naming `file`
+    pub fn select_file(&self, file: &str) -> BulkResult {
+        let affected: Vec<u32> = self
+            .iter()
+            .filter(|bp| bp.references_file(file))
+            .map(|bp| bp.id)
+            .collect();
+        let unmatched = if affected.is_empty() {
+            vec![file.to_string()]
+        } else {
+            Vec::new()
+        };
+        BulkResult {
+            affected,
+            unmatched,
+        }
+    }
+}
+
+/// Where a `break` line in a gdb command file puts its breakpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GdbLocation {
+    /// `*0x100200`
+    Address(u32),
+    /// `hello_test.c:16`
+    FileLine { file: String, line: u64 },
+    /// `main`
+    Symbol(String),
+}
+
+impl GdbLocation {
+    /// The location as gdb's `break` takes it, an address written by `addr_fmt`
+    pub fn describe(&self, addr_fmt: &AddrFormatter) -> String {
+        match self {
+            GdbLocation::Address(address) => format!("*{}", addr_fmt.hex((*address).into())),
+            GdbLocation::FileLine { file, line } => format!("{file}:{line}"),
+            GdbLocation::Symbol(name) => name.clone(),
+        }
+    }
+}
+
+/// One breakpoint out of a gdb command file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GdbBreak {
+    pub location: GdbLocation,
+    /// Set, then disabled straight away. Written as a commented out line by `bexport`
+    pub disabled: bool,
+}
+
+/// How `bexport` comments out a disabled breakpoint, and how `bimport` recognises one
+const DISABLED_PREFIX: &str = "# disabled: ";
+
+/// Parse one line of a gdb command file. Blank lines and `#` comments are `None`. Only plain
+/// `break`/`b` lines are taken, anything gdb would do more with is an error rather than half
+/// applied
+pub fn parse_gdb_line(line: &str) -> Result<Option<GdbBreak>, String> {
+    let line = line.trim();
+    let (command, disabled) = match line.strip_prefix(DISABLED_PREFIX) {
+        Some(command) => (command.trim(), true),
+        None if line.is_empty() || line.starts_with('#') => return Ok(None),
+        None => (line, false),
+    };
+
+    let (name, args) = command
+        .split_once(char::is_whitespace)
+        .map_or((command, ""), |(name, args)| (name, args.trim()));
+    match name {
+        "break" | "b" => {}
+        "commands" | "end" => return Err("commands blocks aren't supported".to_string()),
+        "condition" => return Err("conditions aren't supported".to_string()),
+        other => return Err(format!("'{other}' isn't supported, only break and b are")),
+    }
+
+    let mut words = args.split_whitespace();
+    let Some(location) = words.next() else {
+        return Err("break needs a location".to_string());
+    };
+    match words.next() {
+        None => {}
+        Some("if") => return Err("conditions aren't supported".to_string()),
+        Some("thread") => return Err("thread-specific breakpoints aren't supported".to_string()),
+        Some(other) => return Err(format!("unexpected '{other}' after the location")),
+    }
+
+    Ok(Some(GdbBreak {
+        location: parse_gdb_location(location)?,
+        disabled,
+    }))
+}
+
+fn parse_gdb_location(location: &str) -> Result<GdbLocation, String> {
+    if let Some(address) = location.strip_prefix('*') {
+        // gdb reads `*4096` as decimal, so only a 0x prefix makes it hex
+        let address = match address
+            .strip_prefix("0x")
+            .or_else(|| address.strip_prefix("0X"))
+        {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => address.parse(),
+        };
+        return address
+            .map(GdbLocation::Address)
+            .map_err(|_| format!("Invalid address: {location}"));
+    }
+    if let Some((file, line)) = location.rsplit_once(':') {
+        let line = line
+            .parse()
+            .map_err(|_| format!("Invalid line number: {location}"))?;
+        if file.is_empty() {
+            return Err(format!("Missing file name: {location}"));
+        }
+        return Ok(GdbLocation::FileLine {
+            file: file.to_string(),
+            line,
+        });
+    }
+    if location.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!(
+            "'{location}' is a line in gdb's current file, give it as file:line"
+        ));
+    }
+    Ok(GdbLocation::Symbol(location.to_string()))
+}
+
+/// `table` as a gdb command file, one `break` line per location, so a `file:line` that landed
+/// on several addresses goes out once. A location whose breakpoints are all disabled is
+/// commented out. gdb has no columns, a `file:line:column` goes out as its whole line
+pub fn export_gdb(table: &BreakpointTable, addr_fmt: &AddrFormatter) -> String {
+    let mut locations: Vec<(String, bool)> = Vec::new();
+    for bp in table.iter() {
+        let location = match bp
+            .spec
+            .strip_prefix("0x")
+            .map(|hex| u32::from_str_radix(hex, 16))
+        {
+            Some(Ok(address)) => GdbLocation::Address(address).describe(addr_fmt),
+            _ => match bp.file_line_column() {
+                Some((file, line, Some(_))) => format!("{file}:{line}"),
+                _ => bp.spec.clone(),
+            },
+        };
+        match locations.iter_mut().find(|(seen, _)| *seen == location) {
+            Some((_, enabled)) => *enabled |= bp.enabled,
+            None => locations.push((location, bp.enabled)),
+        }
+    }
+
+    let mut out = String::from("# jpdb breakpoints, load with bimport or gdb's source\n");
+    for (location, enabled) in locations {
+        if !enabled {
+            out.push_str(DISABLED_PREFIX);
+        }
+        out.push_str(&format!("break {location}\n"));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_selectors() {
+        assert_eq!(parse_selectors("all").unwrap(), vec![Selector::All]);
+        assert_eq!(parse_selectors("3").unwrap(), vec![Selector::Ids(3..=3)]);
+        assert_eq!(parse_selectors("3-7").unwrap(), vec![Selector::Ids(3..=7)]);
+        assert_eq!(
+            parse_selectors("1,3,5-7").unwrap(),
+            vec![
+                Selector::Ids(1..=1),
+                Selector::Ids(3..=3),
+                Selector::Ids(5..=7)
+            ]
+        );
+        assert_eq!(
+            parse_selectors("0x100200-0x100300").unwrap(),
+            vec![Selector::Addresses(0x100200..=0x100300)]
+        );
+        assert_eq!(
+            parse_selectors("2 0x1000a4").unwrap(),
+            vec![
+                Selector::Ids(2..=2),
+                Selector::Addresses(0x1000a4..=0x1000a4)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_selectors_errors() {
+        assert!(parse_selectors("").is_err());
+        assert!(parse_selectors("7-3").is_err());
+        assert!(parse_selectors("x").is_err());
+        // ids are decimal, only the 0x prefix makes an address
+        assert!(parse_selectors("1a").is_err());
+        assert!(parse_selectors("0x100-200").is_err());
+    }
+
+    #[test]
+    fn test_selector_describe_round_trips() {
+        for input in ["all", "4", "5-7", "0x1000a4", "0x100200-0x100300"] {
+            let selectors = parse_selectors(input).unwrap();
+            assert_eq!(selectors[0].describe(&AddrFormatter::default()), input);
+        }
+        let mut padded = AddrFormatter::default();
+        padded.pad = true;
+        assert_eq!(
+            parse_selectors("0x100200-0x100300").unwrap()[0].describe(&padded),
+            "0x00100200-0x00100300"
+        );
+    }
+
+    #[test]
+    fn test_references_file() {
+        let mut table = BreakpointTable::default();
+        let bp = table.add(0x100200, "src/main.c:42");
+        assert!(bp.references_file("main.c"));
+        assert!(bp.references_file("src/main.c"));
+        assert!(!bp.references_file("ain.c"));
+        assert!(!table.add(0x100200, "0x100200").references_file("main.c"));
+    }
+
+    fn parse_ok(line: &str) -> GdbBreak {
+        parse_gdb_line(line)
+            .unwrap_or_else(|e| panic!("{line}: {e}"))
+            .unwrap_or_else(|| panic!("{line}: nothing parsed"))
+    }
+
+    #[test]
+    fn test_parse_gdb_line() {
+        for line in ["", "   ", "# a comment", "#break *0x10"] {
+            assert_eq!(parse_gdb_line(line).unwrap(), None, "{line}");
+        }
+        let address = GdbLocation::Address(0x100200);
+        for line in [
+            "break *0x100200",
+            "b *0x100200",
+            "  b\t*0X100200  ",
+            "b *1049088",
+        ] {
+            assert_eq!(parse_ok(line).location, address, "{line}");
+            assert!(!parse_ok(line).disabled);
+        }
+        assert_eq!(
+            parse_ok("break hello_test.c:16").location,
+            GdbLocation::FileLine {
+                file: "hello_test.c".to_string(),
+                line: 16
+            }
+        );
+        assert_eq!(
+            parse_ok("b src/a:b.c:3").location,
+            GdbLocation::FileLine {
+                file: "src/a:b.c".to_string(),
+                line: 3
+            }
+        );
+        assert_eq!(
+            parse_ok("b main").location,
+            GdbLocation::Symbol("main".to_string())
+        );
+        let disabled = parse_ok("# disabled: break *0x1000a4");
+        assert!(disabled.disabled);
+        assert_eq!(disabled.location, GdbLocation::Address(0x1000a4));
+    }
+
+    #[test]
+    fn test_parse_gdb_line_rejects() {
+        for (line, why) in [
+            ("break main if x == 1", "conditions"),
+            ("condition 1 x == 1", "conditions"),
+            ("commands 1", "commands blocks"),
+            ("end", "commands blocks"),
+            ("tbreak main", "isn't supported"),
+            ("watch x", "isn't supported"),
+            ("break main thread 2", "thread-specific"),
+            ("break", "needs a location"),
+            ("break *0xzz", "Invalid address"),
+            ("break *", "Invalid address"),
+            ("break main.c:x", "Invalid line number"),
+            ("break :12", "Missing file name"),
+            ("break 42", "file:line"),
+            ("break main extra", "unexpected 'extra'"),
+            ("# disabled: tbreak main", "isn't supported"),
+        ] {
+            let err = parse_gdb_line(line).unwrap_err();
+            assert!(err.contains(why), "{line}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_export_gdb() {
+        let mut table = BreakpointTable::default();
+        table.add(0x100200, "0x100200");
+        // one line landing on two addresses
+        let line_a = table.add(0x1000a4, "hello_test.c:16").id;
+        let line_b = table.add(0x1000b0, "hello_test.c:16").id;
+        let off = table.add(0x100300, "0x100300").id;
+        table.set_enabled(off, false);
+        table.set_enabled(line_a, false);
+
+        let exported = export_gdb(&table, &AddrFormatter::default());
+        assert_eq!(
+            exported.lines().skip(1).collect::<Vec<_>>(),
+            [
+                "break *0x100200",
+                "break hello_test.c:16",
+                "# disabled: break *0x100300",
+            ]
+        );
+        table.set_enabled(line_b, false);
+        assert!(export_gdb(&table, &AddrFormatter::default())
+            .contains("# disabled: break hello_test.c:16\n"));
+
+        // statements on one line go out as that line, once
+        let mut columns = BreakpointTable::default();
+        columns.add(0x100104, "hello_test.c:7:5");
+        columns.add(0x100108, "hello_test.c:7:12");
+        assert_eq!(
+            export_gdb(&columns, &AddrFormatter::default())
+                .lines()
+                .skip(1)
+                .collect::<Vec<_>>(),
+            ["break hello_test.c:7"]
+        );
+
+        // everything exported parses back to what it came from
+        let parsed: Vec<GdbBreak> = exported
+            .lines()
+            .filter_map(|line| parse_gdb_line(line).unwrap())
+            .collect();
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed[0].location, GdbLocation::Address(0x100200));
+        assert!(parsed[2].disabled);
+    }
+
+    #[test]
+    fn test_bulk_report() {
+        let result = BulkResult {
+            affected: vec![3, 4],
+            unmatched: vec!["9".to_string()],
+        };
+        assert_eq!(
+            result.report("deleted"),
+            vec![
+                "Deleted 2 breakpoints: 3, 4".to_string(),
+                "No breakpoints matched: 9".to_string()
+            ]
+        );
+        assert_eq!(
+            BulkResult::default().report("disabled"),
+            vec!["No breakpoints disabled".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_record_hit_caps_history() {
+        let mut table = BreakpointTable::default();
+        let id = table.add(0x1000a4, "0x1000a4").id;
+        for idx in 0..MAX_HIT_HISTORY as u64 + 10 {
+            assert_eq!(table.record_hit(0x1000a4, idx), [id]);
+        }
+        // the same stop again isn't another hit
+        table.record_hit(0x1000a4, MAX_HIT_HISTORY as u64 + 9);
+        let hits = &table.get(id).unwrap().hits;
+        assert_eq!(hits.len(), MAX_HIT_HISTORY);
+        assert_eq!(hits.front(), Some(&10));
+        assert_eq!(hits.back(), Some(&(MAX_HIT_HISTORY as u64 + 9)));
+        assert!(table.record_hit(0x100084, 3).is_empty());
+    }
+
+    #[test]
+    fn test_record_hit_attributes_every_entry_at_the_address() {
+        let mut table = BreakpointTable::default();
+        let by_address = table.add(0x1000a4, "0x1000a4").id;
+        let by_line = table.add(0x1000a4, "hello_test.c:16").id;
+        let disabled = table.add(0x1000a4, "puthex").id;
+        let elsewhere = table.add(0x100084, "0x100084").id;
+        table.set_enabled(disabled, false);
+
+        assert_eq!(table.record_hit(0x1000a4, 7), [by_address, by_line]);
+        assert_eq!(table.get(by_address).unwrap().hits, [7]);
+        assert_eq!(table.get(by_line).unwrap().hits, [7]);
+        assert!(table.get(disabled).unwrap().hits.is_empty());
+        assert!(table.get(elsewhere).unwrap().hits.is_empty());
+    }
+}
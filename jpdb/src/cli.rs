@@ -1,7 +1,11 @@
 //! Command line interface for jpdb
 
 use argh::FromArgs;
-use std::path::PathBuf;
+use dang::cli::{parse_mapping_arg, parse_scratch};
+use dang::error::{ErrorKind, StartupError};
+use dang::runtime::ScratchRegion;
+use dang::waveloader::{parse_load_threads, WaveLoadOptions};
+use std::path::{Path, PathBuf};
 
 #[derive(FromArgs, Debug, Clone)]
 /// CLI to jpdb - JTAG Debugger
@@ -17,4 +21,105 @@ pub struct JpdbArgs {
     #[argh(option)]
     /// path to the ELF binary
     pub elf: PathBuf,
+
+    #[argh(option, from_str_fn(parse_mapping_arg))]
+    /// key=value parameter handed to the mapping script, can be repeated
+    pub mapping_arg: Vec<(String, String)>,
+
+    #[argh(option, from_str_fn(parse_scratch))]
+    /// writable scratch memory region for memset, as <addr>:<size> in hex
+    pub scratch: Option<ScratchRegion>,
+
+    #[argh(option, default = "UiMode::Full", from_str_fn(parse_ui_mode))]
+    /// interface to use: full (default) or simple, a plain line-oriented prompt for slow links
+    /// and scripts
+    pub ui: UiMode,
+
+    #[argh(option)]
+    /// file of jpdb commands to run, one per line, before the first prompt
+    pub init_commands: Option<PathBuf>,
+
+    #[argh(switch)]
+    /// also run the .jpdbinit next to the wave file, if there is one, before --init-commands
+    pub allow_local_init: bool,
+
+    #[argh(switch)]
+    /// don't restore the session saved next to the wave file, or save one on quit
+    pub no_session: bool,
+
+    #[argh(option, from_str_fn(parse_load_threads))]
+    /// threads to load the waveform with, 1 for the smallest memory footprint. One a core if
+    /// not given
+    pub wave_load_threads: Option<usize>,
+
+    #[argh(option, default = "true")]
+    /// whether scopes with no name stay in the hierarchy (the default), or their contents are
+    /// folded into the parent scope
+    pub wave_keep_scopes_with_empty_name: bool,
+}
+
+impl JpdbArgs {
+    /// How dang and jpdb's own signal browser both load the waveform
+    pub fn wave_load(&self) -> WaveLoadOptions {
+        WaveLoadOptions {
+            threads: self.wave_load_threads,
+            keep_scopes_with_empty_name: self.wave_keep_scopes_with_empty_name,
+        }
+    }
+}
+
+/// Startup script picked up from the wave file's directory with `--allow-local-init`
+pub const LOCAL_INIT: &str = ".jpdbinit";
+
+/// Where the `.jpdbinit` for `wave_path` would be
+pub fn local_init_path(wave_path: &Path) -> PathBuf {
+    wave_path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(LOCAL_INIT)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiMode {
+    /// The ratatui interface
+    Full,
+    /// Commands read a line at a time from stdin, output printed as plain text
+    Simple,
+}
+
+fn parse_ui_mode(value: &str) -> Result<UiMode, String> {
+    match value {
+        "full" => Ok(UiMode::Full),
+        "simple" => Ok(UiMode::Simple),
+        _ => Err(format!("expected 'full' or 'simple', got '{value}'")),
+    }
+}
+
+/// Render a startup failure for the normal (non alternate) screen
+pub fn format_startup_error(err: &(dyn std::error::Error + 'static)) -> String {
+    let mut out = String::new();
+    out.push_str("error: jpdb could not start\n\n");
+    if let Some(path_err) = err.downcast_ref::<dang::cli::PathError>() {
+        out.push_str(&format!(
+            "  {} {}\n    problem:  {}\n    expected: {}\n\n",
+            path_err.flag,
+            path_err.path.display(),
+            path_err.problem,
+            path_err.expected
+        ));
+    } else if let Some(startup_err) = err.downcast_ref::<StartupError>() {
+        let flag = match startup_err.kind {
+            ErrorKind::Waveform => "--wave-path",
+            ErrorKind::Mapping => "--mapping-path",
+            ErrorKind::Elf => "--elf",
+        };
+        out.push_str(&format!("  {startup_err}\n    check:    {flag}\n\n"));
+    } else {
+        out.push_str(&format!("  {err}\n\n"));
+    }
+    out.push_str("example:\n");
+    out.push_str("  jpdb --wave-path test_data/ibex/sim.fst \\\n");
+    out.push_str("       --mapping-path test_data/ibex/signal_get.py \\\n");
+    out.push_str("       --elf test_data/ibex/hello_test.elf\n");
+    out
 }
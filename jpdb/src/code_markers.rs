@@ -0,0 +1,163 @@
+//! Which source lines have code behind them, for the marker in the source pane. Finding out
+//! walks the line table once per line, which adds up in a big generated file, so answers are
+//! kept for good and only [`FRAME_BUDGET`] new ones are worked out per frame. Lines not looked
+//! up yet are drawn without a marker and filled in on a later frame
+
+use std::collections::{BTreeMap, HashMap};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+/// Most lookups done between two frames, the rest wait for the next one
+pub const FRAME_BUDGET: usize = 200;
+
+/// Lines either side of the shown ones looked up as well, so stepping nearby finds them ready
+pub const MARGIN: u64 = 16;
+
+/// What the source pane asked for last
+struct Wanted {
+    file: PathBuf,
+    /// The lines on screen, looked up first
+    shown: RangeInclusive<u64>,
+    /// `shown` and the margin around it
+    lines: RangeInclusive<u64>,
+}
+
+#[derive(Default)]
+pub struct CodeMarkers {
+    /// Whether each line looked up so far has code, per file
+    known: HashMap<PathBuf, BTreeMap<u64, bool>>,
+    wanted: Option<Wanted>,
+}
+
+impl CodeMarkers {
+    /// Whether `line` of `file` has code, `None` if it hasn't been looked up yet
+    pub fn get(&self, file: &Path, line: u64) -> Option<bool> {
+        self.known.get(file)?.get(&line).copied()
+    }
+
+    /// Look up `shown` of `file` and the lines around it from now on, instead of whatever was
+    /// asked for before
+    pub fn want(&mut self, file: &Path, shown: RangeInclusive<u64>) {
+        let lines =
+            shown.start().saturating_sub(MARGIN).max(1)..=shown.end().saturating_add(MARGIN);
+        self.wanted = Some(Wanted {
+            file: file.to_path_buf(),
+            shown,
+            lines,
+        });
+    }
+
+    /// Whether any wanted line is still unknown
+    pub fn pending(&self) -> bool {
+        let Some(wanted) = &self.wanted else {
+            return false;
+        };
+        let Some(known) = self.known.get(&wanted.file) else {
+            return true;
+        };
+        wanted.lines.clone().any(|line| !known.contains_key(&line))
+    }
+
+    /// Look up at most `budget` of the wanted lines that are still unknown with `has_code`,
+    /// shown lines first. Returns how many were looked up
+    pub fn fill(&mut self, budget: usize, mut has_code: impl FnMut(&Path, u64) -> bool) -> usize {
+        let Some(wanted) = &self.wanted else {
+            return 0;
+        };
+        let known = self.known.entry(wanted.file.clone()).or_default();
+        // the shown lines, the margin below them, then the margin above them working upwards
+        let order = (*wanted.shown.start()..=*wanted.lines.end())
+            .chain((*wanted.lines.start()..*wanted.shown.start()).rev());
+        let mut looked_up = 0;
+        for line in order {
+            if looked_up == budget {
+                break;
+            }
+            if known.contains_key(&line) {
+                continue;
+            }
+            known.insert(line, has_code(&wanted.file, line));
+            looked_up += 1;
+        }
+        looked_up
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stands in for the line table of a 40k line generated file
+    const LINES: u64 = 40_000;
+
+    fn has_code(line: u64) -> bool {
+        line % 3 == 0 || line % 7 == 0
+    }
+
+    #[test]
+    fn test_scrolling_stays_within_budget() {
+        let file = Path::new("generated.c");
+        let mut markers = CodeMarkers::default();
+
+        // a tall pane paged down every frame, far faster than the lookups can keep up with
+        for top in (1..LINES).step_by(997) {
+            markers.want(file, top..=top + 249);
+            let mut this_frame = 0;
+            let filled = markers.fill(FRAME_BUDGET, |_, line| {
+                this_frame += 1;
+                has_code(line)
+            });
+            assert_eq!(filled, this_frame);
+            assert_eq!(this_frame, FRAME_BUDGET);
+            // what was on screen got done first
+            assert_eq!(markers.get(file, top), Some(has_code(top)));
+        }
+
+        // settled on one spot, a few frames finish it off
+        markers.want(file, 30_500..=30_800);
+        let mut frames = 0;
+        while markers.pending() {
+            let filled = markers.fill(FRAME_BUDGET, |_, line| has_code(line));
+            assert!(filled <= FRAME_BUDGET);
+            frames += 1;
+        }
+        assert_eq!(frames, (301 + 2 * MARGIN as usize).div_ceil(FRAME_BUDGET));
+
+        // the same answers as working every line out up front
+        for line in 30_500 - MARGIN..=30_800 + MARGIN {
+            assert_eq!(markers.get(file, line), Some(has_code(line)), "line {line}");
+        }
+        // a page scrolled past without stopping only got its first lines
+        assert_eq!(markers.get(file, 10_500), None);
+        assert_eq!(markers.get(Path::new("other.c"), 20_000), None);
+    }
+
+    #[test]
+    fn test_answers_are_kept() {
+        let file = Path::new("generated.c");
+        let mut markers = CodeMarkers::default();
+        assert!(!markers.pending());
+        assert_eq!(markers.fill(FRAME_BUDGET, |_, _| unreachable!()), 0);
+
+        markers.want(file, 1..=4);
+        // clamped at the top of the file
+        assert_eq!(
+            markers.fill(FRAME_BUDGET, |_, line| has_code(line)),
+            4 + MARGIN as usize
+        );
+        assert!(!markers.pending());
+
+        // scrolling back over looked up lines doesn't look them up again
+        markers.want(file, 3..=6);
+        assert_eq!(markers.fill(FRAME_BUDGET, |_, line| has_code(line)), 2);
+        markers.want(file, 1..=4);
+        assert_eq!(markers.fill(FRAME_BUDGET, |_, _| unreachable!()), 0);
+
+        // the shown lines come first when the budget is tight
+        markers.want(file, 100..=101);
+        assert_eq!(markers.fill(2, |_, line| has_code(line)), 2);
+        assert_eq!(markers.get(file, 100), Some(has_code(100)));
+        assert_eq!(markers.get(file, 101), Some(has_code(101)));
+        assert_eq!(markers.get(file, 99), None);
+    }
+}
@@ -0,0 +1,610 @@
+use shucks::FormattingType;
+
+use crate::code_markers;
+use crate::dual_context::ImageContext;
+use crate::model::{DebuggerModel, ExecutionSnapshot, ModelResult, SigTime, SourceSnapshot};
+use crate::notifications::Notifier;
+use crate::view::{self, Pane, ViewState};
+
+/// Decides which snapshots to pull from the model and puts them into the view.
+///
+/// Panes that aren't visible are only marked stale rather than fetched, and get fetched as
+/// soon as they're shown again
+pub struct Controller {
+    pub model: DebuggerModel,
+    pub view_state: ViewState,
+    /// Time index the Signals pane is pinned to with `sigtime`, `None` to follow execution
+    signal_pin: Option<u64>,
+    /// What the Source pane shows, kept to redraw it as code markers come in
+    source: Option<SourceSnapshot>,
+    /// Where execution was the last time the Execution pane was fetched, to tell which image
+    /// it's in
+    stop_pc: Option<u32>,
+    /// Where warnings about the target's state go
+    pub notifier: Notifier,
+}
+
+impl Controller {
+    /// Fill in every visible pane straight away
+    pub fn new(model: DebuggerModel, view_state: ViewState) -> Self {
+        let mut controller = Self {
+            model,
+            view_state,
+            signal_pin: None,
+            source: None,
+            stop_pc: None,
+            notifier: Notifier::default(),
+        };
+        controller.refresh_all();
+        controller
+    }
+
+    /// The target moved, bring every pane up to date
+    pub fn refresh_all(&mut self) {
+        if self.model.pc_undefined() {
+            self.notifier.warn(
+                "trace",
+                "The PC has x or z bits here, the target state is partially undefined",
+            );
+        }
+        self.refresh_execution();
+        self.refresh_source();
+        self.refresh_signals();
+        self.refresh_timeline();
+        self.track_image();
+    }
+
+    /// Tell the dual context which image execution is in now, see [`crate::dual_context`]
+    fn track_image(&mut self) {
+        let (Some(pc), Ok(time_idx)) = (self.stop_pc, self.model.get_time_idx()) else {
+            return;
+        };
+        let context = ImageContext::new(
+            pc,
+            &self.model.client,
+            &self.view_state.instruction_lines,
+            &self.view_state.source_lines,
+        );
+        self.view_state.dual_context.stop(time_idx, context);
+    }
+
+    pub fn refresh_execution(&mut self) {
+        if !self.should_fetch(Pane::Execution) {
+            return;
+        }
+        if let Ok(execution) = self.model.fetch_execution_snapshot() {
+            self.stop_pc = match &execution {
+                ExecutionSnapshot::Stopped {
+                    frame: Ok(frame), ..
+                } => Some(frame.pc),
+                _ => None,
+            };
+            let addr_fmt = &self.model.addr_fmt;
+            let symbols = &self.model.client;
+            let execution_lines = view::execution_lines(&execution, addr_fmt, symbols);
+            let instruction_lines = view::instruction_lines(&execution, addr_fmt, symbols);
+            self.view_state.execution_lines = execution_lines;
+            self.view_state.instruction_lines = instruction_lines;
+        } else {
+            self.stop_pc = None;
+            self.view_state.execution_lines = vec!["Failed to load execution info".to_string()];
+            self.view_state.instruction_lines = vec!["Failed to load execution info".to_string()];
+        }
+    }
+
+    /// Bring the instruction window in line with `set instwindow` and the Instructions pane's
+    /// size, refetching the Execution pane if it changed. Returns whether it did, and the pane
+    /// wants drawing again
+    pub fn fit_instruction_window(&mut self) -> bool {
+        let Some(wanted) = self.view_state.instruction_window() else {
+            return false;
+        };
+        if wanted == self.model.instruction_window() {
+            return false;
+        }
+        self.model.set_instruction_window(wanted);
+        self.refresh_execution();
+        true
+    }
+
+    pub fn refresh_source(&mut self) {
+        if !self.should_fetch(Pane::Source) {
+            return;
+        }
+        if let Ok(source) = self.model.fetch_source_snapshot() {
+            self.view_state.source_lines = view::source_lines(&source);
+            self.source = Some(source);
+        } else {
+            self.view_state.source_lines = vec!["Failed to load source info".to_string()];
+            self.source = None;
+        }
+    }
+
+    /// Look up a frame's worth of the Source pane's code markers, see [`code_markers`]. Returns
+    /// whether any came in, and the pane wants drawing again
+    pub fn fill_code_markers(&mut self) -> bool {
+        if self.model.fill_code_markers(code_markers::FRAME_BUDGET) == 0 {
+            return false;
+        }
+        if let Some(source) = &mut self.source {
+            self.model.apply_code_markers(source);
+            self.view_state.source_lines = view::source_lines(source);
+        }
+        true
+    }
+
+    pub fn refresh_signals(&mut self) {
+        // Nothing to ever fetch without a waveform, whether or not the pane is up
+        if !self.model.has_waveform() {
+            self.view_state.signal_lines = vec!["no waves found".to_string()];
+            self.view_state.mark_fresh(Pane::Signals);
+            return;
+        }
+        if !self.should_fetch(Pane::Signals) {
+            return;
+        }
+        match self.model.fetch_signal_snapshot(self.signal_pin) {
+            Ok(snapshot) => {
+                self.view_state.signal_lines = view::signal_lines(&snapshot);
+                self.view_state.signals_title = view::signals_title(&snapshot);
+            }
+            Err(err) => {
+                self.view_state.signal_lines = vec![format!("Error getting signal info: {err}")];
+            }
+        }
+    }
+
+    /// Show a selected signal in another radix, see [`DebuggerModel::set_signal_format`]
+    pub fn set_signal_format(
+        &mut self,
+        query: &str,
+        format: FormattingType,
+    ) -> ModelResult<String> {
+        let name = self.model.set_signal_format(query, format)?;
+        self.refresh_signals();
+        Ok(name)
+    }
+
+    pub fn signal_pin(&self) -> Option<u64> {
+        self.signal_pin
+    }
+
+    /// Point the Signals pane somewhere other than where execution is, or back with
+    /// [`SigTime::Off`]. The pin stays put as execution moves
+    pub fn set_signal_pin(&mut self, sigtime: SigTime) -> ModelResult<Option<u64>> {
+        self.signal_pin = self.model.resolve_signal_pin(self.signal_pin, sigtime)?;
+        self.refresh_signals();
+        self.refresh_timeline();
+        Ok(self.signal_pin)
+    }
+
+    /// Bring the timeline's cursor and pin up to date. Cheap enough to keep up whether or not
+    /// it's shown, the time index is cached by the model
+    pub fn refresh_timeline(&mut self) {
+        let trace_len = self.model.trace_len();
+        let cursor = self.model.get_time_idx().ok();
+        let timeline = &mut self.view_state.timeline;
+        timeline.trace_len = trace_len;
+        timeline.cursor = cursor;
+        timeline.pin = self.signal_pin;
+    }
+
+    /// Mark where execution is as a breakpoint hit on the timeline, once per spot, and add it
+    /// to the hit history of the breakpoints there
+    pub fn record_breakpoint_hit(&mut self) {
+        let Ok(idx) = self.model.get_time_idx() else {
+            return;
+        };
+        self.model.record_breakpoint_hit(idx);
+        let hits = &mut self.view_state.timeline.hits;
+        if hits.last() != Some(&idx) {
+            hits.push(idx);
+        }
+    }
+
+    pub fn set_timeline(&mut self, show: bool) {
+        self.view_state.timeline.show = show;
+        self.refresh_timeline();
+    }
+
+    pub fn set_split_view(&mut self, show: bool) {
+        self.view_state.show_split_view = show;
+        self.refresh_newly_visible();
+    }
+
+    pub fn set_debug_panel(&mut self, show: bool) {
+        self.view_state.show_debug_panel = show;
+        self.refresh_newly_visible();
+    }
+
+    /// Fetch just the panes that came into view with stale content
+    fn refresh_newly_visible(&mut self) {
+        for pane in self.view_state.visible_stale() {
+            match pane {
+                Pane::Execution => self.refresh_execution(),
+                Pane::Source => self.refresh_source(),
+                Pane::Signals => self.refresh_signals(),
+            }
+        }
+    }
+
+    /// Returns whether `pane` should be fetched now. Hidden panes are marked stale instead
+    fn should_fetch(&mut self, pane: Pane) -> bool {
+        if self.view_state.is_visible(pane) {
+            self.view_state.mark_fresh(pane);
+            true
+        } else {
+            self.view_state.mark_stale(pane);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test_utils {
+    use super::*;
+    use shucks::Client;
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+    use std::thread;
+    use std::time::Duration;
+
+    pub fn test_data(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../test_data/ibex")
+            .join(name)
+    }
+
+    /// A controller over a live dang instance replaying the ibex hello_test trace
+    pub fn fixture_controller() -> Controller {
+        fixture_controller_with_elf(test_data("hello_test.elf"))
+    }
+
+    /// [`fixture_controller`], with dang loading `elf` instead of the fixture's own
+    pub fn fixture_controller_with_elf(elf: PathBuf) -> Controller {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let _ = dang::start_with_options_and_listener_silent(
+                test_data("sim.fst"),
+                test_data("signal_get.py"),
+                elf,
+                dang::DangOptions::default(),
+                listener,
+            );
+        });
+        thread::sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+        client.load_elf_info().expect("Failed to load elf info");
+        client
+            .load_waveform(test_data("sim.fst"))
+            .expect("Failed to load waveform");
+        Controller::new(DebuggerModel::new(client), ViewState::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_utils::*;
+    use super::*;
+    use crate::addr_format::SymbolMode;
+    use crate::model::SignalSnapshot;
+    use crate::view::InstWindow;
+    use ratatui::layout::Rect;
+
+    #[test]
+    fn test_hidden_panes_are_not_fetched() {
+        let mut controller = fixture_controller();
+        controller.set_split_view(false);
+
+        let before = controller.model.fetch_counts();
+        controller.model.step().unwrap();
+        controller.refresh_all();
+        let after = controller.model.fetch_counts();
+        assert_eq!(after.execution, before.execution + 1);
+        assert_eq!(after.source, before.source);
+        assert_eq!(after.signals, before.signals);
+        assert!(controller.view_state.is_stale(Pane::Source));
+        assert!(controller.view_state.is_stale(Pane::Signals));
+
+        // showing the panes again fetches them straight away, nothing else
+        controller.set_split_view(true);
+        let shown = controller.model.fetch_counts();
+        assert_eq!(shown.execution, after.execution);
+        assert_eq!(shown.source, after.source + 1);
+        assert_eq!(shown.signals, after.signals + 1);
+        assert!(controller.view_state.visible_stale().is_empty());
+
+        let fresh = controller.model.fetch_source_snapshot().unwrap();
+        assert_eq!(
+            controller.view_state.source_lines,
+            view::source_lines(&fresh)
+        );
+    }
+
+    #[test]
+    fn test_resize_fetches_a_bigger_window() {
+        let mut controller = fixture_controller();
+        // off the reset vector, which is the last word of .vectors, and into .text
+        for _ in 0..3 {
+            controller.model.step().unwrap();
+        }
+        controller.refresh_all();
+        // nothing laid out to size an auto window to yet
+        assert!(!controller.fit_instruction_window());
+
+        controller.view_state.instruction_pane = Some(Rect::new(0, 0, 40, 8));
+        assert!(controller.fit_instruction_window());
+        assert_eq!(controller.view_state.instruction_lines.len(), 6);
+        let before = controller.model.fetch_counts();
+        // drawn again at the same size
+        assert!(!controller.fit_instruction_window());
+        assert_eq!(controller.model.fetch_counts(), before);
+
+        controller.view_state.instruction_pane = Some(Rect::new(0, 0, 40, 22));
+        assert!(controller.fit_instruction_window());
+        let after = controller.model.fetch_counts();
+        assert_eq!(after.execution, before.execution + 1);
+        assert_eq!(after.instructions, before.instructions + 20);
+        assert_eq!(controller.view_state.instruction_lines.len(), 20);
+
+        // a fixed window doesn't follow the pane
+        controller.view_state.inst_window = InstWindow::Fixed(3);
+        assert!(controller.fit_instruction_window());
+        controller.view_state.instruction_pane = Some(Rect::new(0, 0, 40, 40));
+        assert!(!controller.fit_instruction_window());
+        assert_eq!(controller.view_state.instruction_lines.len(), 3);
+    }
+
+    #[test]
+    fn test_stop_context_matches_piecewise() {
+        let mut controller = fixture_controller();
+        controller.model.set_show_bytes(true);
+        let origin = controller.model.get_time_idx().unwrap();
+
+        // a step and everything redrawn after it
+        let stop = |controller: &mut Controller| {
+            let before = controller.model.client.packet_stats().packets_sent;
+            controller.model.step().unwrap();
+            controller.refresh_all();
+            let registers = controller.model.registers(false).unwrap();
+            let sent = controller.model.client.packet_stats().packets_sent - before;
+            let view = &controller.view_state;
+            let panes = (
+                view.execution_lines.clone(),
+                view.instruction_lines.clone(),
+                view.source_lines.clone(),
+                view.signal_lines.clone(),
+                registers,
+            );
+            (panes, sent)
+        };
+
+        let (bundled, bundled_sent) = stop(&mut controller);
+        controller.model.client.set_use_stop_context(false);
+        controller.model.goto_time_idx(origin).unwrap();
+        let (piecewise, piecewise_sent) = stop(&mut controller);
+        assert_eq!(bundled, piecewise);
+        // a g and a time_idx for the stop, and a g for the registers, in place of the one
+        // stop_context
+        assert_eq!(piecewise_sent, bundled_sent + 2);
+    }
+
+    #[test]
+    fn test_panes_show_the_snapshot() {
+        let mut controller = fixture_controller();
+        for _ in 0..3 {
+            controller.model.step().unwrap();
+        }
+        controller.refresh_all();
+
+        let pc = controller.model.client.get_current_pc().unwrap().as_u32();
+        let snapshot = controller.model.fetch_execution_snapshot().unwrap();
+        let ExecutionSnapshot::Stopped {
+            frame: Ok(frame), ..
+        } = &snapshot
+        else {
+            panic!("target should be stopped at a known pc: {snapshot:?}");
+        };
+        assert_eq!(frame.pc, pc);
+        let instructions = frame.instructions.as_ref().unwrap();
+        assert_eq!(instructions[0].addr, pc);
+        assert!(instructions[0].is_current);
+        assert!(instructions[1..].iter().all(|inst| !inst.is_current));
+
+        let view_state = &controller.view_state;
+        assert_eq!(
+            view_state.instruction_lines[0],
+            format!(
+                "->  {}: {}",
+                controller.model.addr_fmt.hex(pc.into()),
+                instructions[0].text
+            )
+        );
+        // the summary is the frame line wrapped around the same listing
+        let listing = &view_state.execution_lines[3..view_state.execution_lines.len() - 1];
+        assert_eq!(listing, view_state.instruction_lines.as_slice());
+        assert_eq!(
+            view_state.source_lines,
+            view::source_lines(&controller.model.fetch_source_snapshot().unwrap())
+        );
+
+        // with symbols on the listing labels each address through the client
+        controller.model.addr_fmt.symbols = SymbolMode::On;
+        controller.refresh_execution();
+        assert!(controller.model.client.symbol_offset(pc).is_some());
+        let model = &controller.model;
+        assert_eq!(
+            controller.view_state.instruction_lines[0],
+            format!(
+                "->  {}: {}",
+                model.addr_fmt.format(pc.into(), &model.client),
+                instructions[0].text
+            )
+        );
+    }
+
+    #[test]
+    fn test_code_markers_fill_in_after_the_frame() {
+        let mut controller = fixture_controller();
+        // puthex, which has line info
+        controller.model.set_breakpoint(0x1000a4).unwrap();
+        controller.model.continue_execution().unwrap();
+        controller.refresh_all();
+        // nothing has been looked up yet
+        let current = controller.view_state.source_lines[2].clone();
+        assert!(current.starts_with("-> "), "{current}");
+
+        let mut frames = 0;
+        while controller.fill_code_markers() {
+            frames += 1;
+        }
+        assert!(frames >= 1);
+        // the current line came out of the line table, so it has code
+        let current = &controller.view_state.source_lines[2];
+        assert!(current.starts_with("->*"), "{current}");
+        assert_eq!(
+            controller.view_state.source_lines,
+            view::source_lines(&controller.model.fetch_source_snapshot().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_signal_pin() {
+        let mut controller = fixture_controller();
+        for _ in 0..3 {
+            controller.model.step().unwrap();
+        }
+        let exec_idx = controller.model.get_time_idx().unwrap();
+        assert_eq!(controller.view_state.signals_title, "Signals");
+
+        let pin = controller.set_signal_pin(SigTime::Relative(-2)).unwrap();
+        assert_eq!(pin, Some(exec_idx - 2));
+        let title = controller.view_state.signals_title.clone();
+        assert!(title.contains("(pinned, exec at "), "{title}");
+
+        // execution moving keeps the pin, only the exec annotation follows
+        controller.model.step().unwrap();
+        controller.refresh_all();
+        assert_eq!(controller.signal_pin(), Some(exec_idx - 2));
+        let SignalSnapshot::Values {
+            time, exec_time, ..
+        } = controller.model.fetch_signal_snapshot(pin).unwrap()
+        else {
+            panic!("the fixture has a waveform");
+        };
+        let time = time.unwrap();
+        assert!(exec_time.unwrap() > time);
+        assert!(controller
+            .view_state
+            .signals_title
+            .starts_with(&format!("Signals @ t={time} ")));
+        assert_ne!(controller.view_state.signals_title, title);
+
+        assert_eq!(controller.set_signal_pin(SigTime::Off).unwrap(), None);
+        assert_eq!(controller.view_state.signals_title, "Signals");
+    }
+
+    #[test]
+    fn test_signal_pin_clamps_to_trace() {
+        let mut controller = fixture_controller();
+        assert_eq!(
+            controller
+                .set_signal_pin(SigTime::Relative(-1_000_000))
+                .unwrap(),
+            Some(0)
+        );
+        let last = controller
+            .set_signal_pin(SigTime::Relative(i64::MAX))
+            .unwrap()
+            .unwrap();
+        assert!(last > 0);
+        assert_eq!(
+            controller.set_signal_pin(SigTime::Relative(100)).unwrap(),
+            Some(last)
+        );
+        assert!(controller.set_signal_pin(SigTime::Index(last + 1)).is_err());
+        // a failed sigtime leaves the pin where it was
+        assert_eq!(controller.signal_pin(), Some(last));
+        assert_eq!(
+            controller.set_signal_pin(SigTime::Time(u64::MAX)).unwrap(),
+            Some(last)
+        );
+    }
+
+    #[test]
+    fn test_timeline() {
+        let mut controller = fixture_controller();
+        controller.set_timeline(true);
+        let trace_len = controller.view_state.timeline.trace_len;
+        assert!(trace_len > 0);
+        let start = controller.model.get_time_idx().unwrap();
+        assert_eq!(controller.view_state.timeline.cursor, Some(start));
+
+        controller.record_breakpoint_hit();
+        controller.record_breakpoint_hit();
+        controller.model.step().unwrap();
+        controller.refresh_all();
+        controller.record_breakpoint_hit();
+        let exec_idx = controller.model.get_time_idx().unwrap();
+        assert_eq!(controller.view_state.timeline.cursor, Some(exec_idx));
+        // the same spot twice is one hit
+        assert_eq!(controller.view_state.timeline.hits, [start, exec_idx]);
+
+        let pin = controller.set_signal_pin(SigTime::Index(5)).unwrap();
+        assert_eq!(controller.view_state.timeline.pin, pin);
+        assert_eq!(controller.view_state.timeline.index_at(1.0), trace_len - 1);
+    }
+
+    #[test]
+    fn test_debug_panel_toggle_fetches_nothing() {
+        let mut controller = fixture_controller();
+        let before = controller.model.fetch_counts();
+        controller.set_debug_panel(true);
+        controller.set_debug_panel(false);
+        assert_eq!(controller.model.fetch_counts(), before);
+    }
+
+    #[test]
+    fn test_switching_the_radix_of_a_selected_signal() {
+        let mut controller = fixture_controller();
+        let pc = "TOP.ibex_simple_system.u_top.u_ibex_top.u_ibex_core.wb_stage_i.pc_wb_o";
+        let (var, _) = controller
+            .model
+            .fuzzy_match_signals(pc)
+            .into_iter()
+            .find(|(_, matched)| matched == pc)
+            .unwrap();
+        controller.model.select_signal(var);
+        for _ in 0..3 {
+            controller.model.step().unwrap();
+        }
+        controller.refresh_all();
+        let line = |controller: &Controller| {
+            let line = &controller.view_state.signal_lines[2];
+            line.trim_end_matches(view::CHANGED_MARKER)
+                .trim_end()
+                .to_string()
+        };
+        let hex = line(&controller);
+        let hex = hex.strip_prefix(&format!("{pc}: ")).unwrap();
+        let value = u32::from_str_radix(&hex.replace('_', ""), 16).unwrap();
+
+        let name = controller
+            .set_signal_format("pc_wb_o", FormattingType::Decimal)
+            .unwrap();
+        assert_eq!(name, pc);
+        assert_eq!(line(&controller), format!("{pc} (dec): {value}"));
+        controller
+            .set_signal_format("pc_wb_o", FormattingType::Hex)
+            .unwrap();
+        assert_eq!(line(&controller), format!("{pc}: {hex}"));
+        assert!(controller
+            .set_signal_format("no_such_signal", FormattingType::Binary)
+            .is_err());
+    }
+}
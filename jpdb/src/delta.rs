@@ -0,0 +1,301 @@
+//! `delta`: what changed between the last two stops it was run at, for the question that comes
+//! up after stepping over a function nobody remembers writing.
+//!
+//! Each run snapshots the GPRs, the scratch region when there is one and the selected signals,
+//! then lists only what differs from the snapshot before, registers first, then memory, then
+//! signals. A register whose new value is code gets its symbol next to it. Memory is compared a
+//! [`CHUNK`] bytes at a time, with at most [`MAX_CHUNKS`] listed
+
+use std::collections::VecDeque;
+
+use shucks::client::Client;
+
+use crate::addr_format::AddrFormatter;
+use crate::view::GPR_NAMES;
+
+/// Bytes of scratch memory compared, and listed, together
+pub const CHUNK: usize = 16;
+
+/// Most changed chunks listed, the rest are only counted
+pub const MAX_CHUNKS: usize = 32;
+
+/// Snapshots kept, the two `delta` compares
+const HISTORY: usize = 2;
+
+/// A symbol for values that point into code
+pub trait TextLabels {
+    fn text_label(&self, addr: u32) -> Option<String>;
+}
+
+impl TextLabels for Client {
+    fn text_label(&self, addr: u32) -> Option<String> {
+        self.image_of(addr)?;
+        self.symbol_offset(addr)
+    }
+}
+
+/// What one `delta` saw
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub time_idx: u64,
+    pub gprs: [u32; 32],
+    /// Where the scratch region starts and what was in it, `None` without one
+    pub scratch: Option<(u32, Vec<u8>)>,
+    /// Selected signals and their values as the Signals pane shows them, in selection order
+    pub signals: Vec<(String, String)>,
+}
+
+/// The snapshots of the last two `delta`s, oldest first
+#[derive(Debug, Default)]
+pub struct DeltaHistory {
+    snapshots: VecDeque<Snapshot>,
+}
+
+impl DeltaHistory {
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if self.snapshots.len() == HISTORY {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    /// The two latest snapshots, `(older, newer)`, once there are two
+    pub fn last_two(&self) -> Option<(&Snapshot, &Snapshot)> {
+        match self.snapshots.len() {
+            HISTORY => Some((&self.snapshots[0], &self.snapshots[1])),
+            _ => None,
+        }
+    }
+}
+
+fn bytes_text(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn register_lines(
+    old: &Snapshot,
+    new: &Snapshot,
+    labels: &impl TextLabels,
+    addr_fmt: &AddrFormatter,
+) -> Vec<String> {
+    (0..GPR_NAMES.len())
+        .filter(|&reg| old.gprs[reg] != new.gprs[reg])
+        .map(|reg| {
+            let label = labels
+                .text_label(new.gprs[reg])
+                .map(|label| format!(" <{label}>"))
+                .unwrap_or_default();
+            format!(
+                "  {:<4} {} → {}{label}",
+                GPR_NAMES[reg],
+                addr_fmt.column(old.gprs[reg].into()),
+                addr_fmt.column(new.gprs[reg].into())
+            )
+        })
+        .collect()
+}
+
+fn memory_lines(old: &Snapshot, new: &Snapshot, addr_fmt: &AddrFormatter) -> Vec<String> {
+    let (Some((old_start, old_bytes)), Some((start, bytes))) = (&old.scratch, &new.scratch) else {
+        return Vec::new();
+    };
+    if old_start != start || old_bytes.len() != bytes.len() {
+        return vec![format!(
+            "  scratch region moved to {}, {} bytes",
+            addr_fmt.hex((*start).into()),
+            bytes.len()
+        )];
+    }
+    let changed: Vec<usize> = (0..bytes.len())
+        .step_by(CHUNK)
+        .filter(|&offset| {
+            let end = (offset + CHUNK).min(bytes.len());
+            old_bytes[offset..end] != bytes[offset..end]
+        })
+        .collect();
+    let mut lines: Vec<String> = changed
+        .iter()
+        .take(MAX_CHUNKS)
+        .map(|&offset| {
+            let end = (offset + CHUNK).min(bytes.len());
+            format!(
+                "  {}: {} → {}",
+                addr_fmt.column(*start as u64 + offset as u64),
+                bytes_text(&old_bytes[offset..end]),
+                bytes_text(&bytes[offset..end])
+            )
+        })
+        .collect();
+    if changed.len() > MAX_CHUNKS {
+        lines.push(format!(
+            "  ... and {} more changed chunks",
+            changed.len() - MAX_CHUNKS
+        ));
+    }
+    lines
+}
+
+/// Signals in both snapshots whose values differ. One selected or dropped in between has
+/// nothing to compare against and is left out
+fn signal_lines(old: &Snapshot, new: &Snapshot) -> Vec<String> {
+    new.signals
+        .iter()
+        .filter_map(|(name, value)| {
+            let (_, old_value) = old.signals.iter().find(|(old_name, _)| old_name == name)?;
+            (old_value != value).then(|| format!("  {name}: {old_value} → {value}"))
+        })
+        .collect()
+}
+
+/// What changed from `old` to `new`, grouped by category, only the categories with changes
+pub fn diff_lines(
+    old: &Snapshot,
+    new: &Snapshot,
+    labels: &impl TextLabels,
+    addr_fmt: &AddrFormatter,
+) -> Vec<String> {
+    let mut lines = vec![format!(
+        "Changes from time index {} to {}:",
+        old.time_idx, new.time_idx
+    )];
+    let categories = [
+        ("Registers", register_lines(old, new, labels, addr_fmt)),
+        ("Memory", memory_lines(old, new, addr_fmt)),
+        ("Signals", signal_lines(old, new)),
+    ];
+    let mut any = false;
+    for (category, changes) in categories {
+        if changes.is_empty() {
+            continue;
+        }
+        any = true;
+        lines.push(format!("{category}:"));
+        lines.extend(changes);
+    }
+    if !any {
+        lines.push("  nothing changed".to_string());
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::addr_format::AddrWidth;
+
+    /// main sits at 0x1003d0
+    struct MainLabels;
+
+    impl TextLabels for MainLabels {
+        fn text_label(&self, addr: u32) -> Option<String> {
+            (0x1003d0..0x100470)
+                .contains(&addr)
+                .then(|| format!("main+0x{:x}", addr - 0x1003d0))
+        }
+    }
+
+    fn snapshot(time_idx: u64) -> Snapshot {
+        Snapshot {
+            time_idx,
+            gprs: [0; 32],
+            scratch: Some((0x20000, vec![0; 64])),
+            signals: vec![
+                ("top.valid".to_string(), "0".to_string()),
+                ("top.state".to_string(), "0x3".to_string()),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_diff_groups_by_category() {
+        let old = snapshot(10);
+        let mut new = snapshot(42);
+        new.gprs[10] = 0x41;
+        new.gprs[1] = 0x1003f0;
+        new.scratch.as_mut().unwrap().1[17] = 0xab;
+        new.signals[1].1 = "0x4".to_string();
+        new.signals.push(("top.added".to_string(), "1".to_string()));
+
+        assert_eq!(
+            diff_lines(&old, &new, &MainLabels, &AddrFormatter::default()),
+            [
+                "Changes from time index 10 to 42:",
+                "Registers:",
+                "  ra   0x00000000 → 0x001003f0 <main+0x20>",
+                "  a0   0x00000000 → 0x00000041",
+                "Memory:",
+                "  0x00020010: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 → \
+                 00 ab 00 00 00 00 00 00 00 00 00 00 00 00 00 00",
+                "Signals:",
+                "  top.state: 0x3 → 0x4",
+            ]
+        );
+
+        // addresses and values follow set addrfmt
+        let mut wide = AddrFormatter::default();
+        wide.width = AddrWidth::Bits64;
+        new.scratch.as_mut().unwrap().0 = 0x30000;
+        assert_eq!(
+            diff_lines(&old, &new, &MainLabels, &wide)[2..5],
+            [
+                "  ra   0x0000000000000000 → 0x00000000001003f0 <main+0x20>",
+                "  a0   0x0000000000000000 → 0x0000000000000041",
+                "Memory:",
+            ]
+        );
+        assert_eq!(
+            diff_lines(&old, &new, &MainLabels, &wide)[5],
+            "  scratch region moved to 0x30000, 64 bytes"
+        );
+    }
+
+    #[test]
+    fn test_diff_leaves_out_quiet_categories() {
+        let old = snapshot(10);
+        let mut new = snapshot(11);
+        assert_eq!(
+            diff_lines(&old, &new, &MainLabels, &AddrFormatter::default()),
+            ["Changes from time index 10 to 11:", "  nothing changed"]
+        );
+
+        new.signals[0].1 = "1".to_string();
+        new.scratch = None;
+        assert_eq!(
+            diff_lines(&old, &new, &MainLabels, &AddrFormatter::default())[1..],
+            ["Signals:", "  top.valid: 0 → 1"]
+        );
+    }
+
+    #[test]
+    fn test_memory_chunks_are_capped() {
+        let old = Snapshot {
+            scratch: Some((0x20000, vec![0; CHUNK * (MAX_CHUNKS + 3) + 4])),
+            ..snapshot(0)
+        };
+        let mut new = old.clone();
+        new.scratch
+            .as_mut()
+            .unwrap()
+            .1
+            .iter_mut()
+            .for_each(|b| *b = 1);
+        let lines = memory_lines(&old, &new, &AddrFormatter::default());
+        assert_eq!(lines.len(), MAX_CHUNKS + 1);
+        assert_eq!(lines[MAX_CHUNKS], "  ... and 4 more changed chunks");
+    }
+
+    #[test]
+    fn test_history_keeps_two() {
+        let mut history = DeltaHistory::default();
+        history.push(snapshot(1));
+        assert!(history.last_two().is_none());
+        history.push(snapshot(2));
+        history.push(snapshot(3));
+        let (older, newer) = history.last_two().unwrap();
+        assert_eq!((older.time_idx, newer.time_idx), (2, 3));
+    }
+}
@@ -0,0 +1,243 @@
+//! Keeping the other image in view when execution crosses from one ELF image into another, a
+//! boot ROM calling into the application say.
+//!
+//! When a stop is in a different image than the one before it, the Instructions and Source
+//! panes split for [`SPLIT_STOPS`] stops: the few lines around where execution left the previous
+//! image, grayed, above the current ones, each titled with its image. Esc takes the split down
+//! early, `set dualcontext off` stops it showing at all
+
+use shucks::client::Client;
+
+/// Stops the split stays up for, the crossing one included
+pub const SPLIT_STOPS: u8 = 2;
+
+/// Lines of the previous image's panes kept in the split
+pub const CONTEXT_LINES: usize = 3;
+
+/// Which image a code address belongs to
+pub trait ImageSource {
+    fn image_of(&self, addr: u32) -> Option<String>;
+}
+
+impl ImageSource for Client {
+    fn image_of(&self, addr: u32) -> Option<String> {
+        Client::image_of(self, addr).map(str::to_string)
+    }
+}
+
+/// How a pane titles an image, code outside any loaded image included
+pub fn image_label(image: Option<&str>) -> &str {
+    image.unwrap_or("no image")
+}
+
+/// The [`CONTEXT_LINES`] lines around where execution is, the `->` line, or the first few if
+/// no line is marked
+fn around_current(lines: &[String]) -> Vec<String> {
+    let current = lines.iter().position(|line| line.starts_with("->"));
+    let start = current
+        .map_or(0, |current| current.saturating_sub(CONTEXT_LINES / 2))
+        .min(lines.len().saturating_sub(CONTEXT_LINES));
+    lines
+        .iter()
+        .skip(start)
+        .take(CONTEXT_LINES)
+        .cloned()
+        .collect()
+}
+
+/// What the panes showed at a stop, and which image it was in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageContext {
+    pub image: Option<String>,
+    pub instruction_lines: Vec<String>,
+    pub source_lines: Vec<String>,
+}
+
+impl ImageContext {
+    /// Execution at `pc`, with the panes showing these lines
+    pub fn new(
+        pc: u32,
+        images: &impl ImageSource,
+        instruction_lines: &[String],
+        source_lines: &[String],
+    ) -> Self {
+        Self {
+            image: images.image_of(pc),
+            instruction_lines: instruction_lines.to_vec(),
+            source_lines: source_lines.to_vec(),
+        }
+    }
+}
+
+pub struct DualContext {
+    /// `set dualcontext`
+    enabled: bool,
+    /// Where the last stop was, and what it showed
+    last: Option<(u64, ImageContext)>,
+    /// The image execution came from, cut down to the lines around where it left
+    previous: Option<ImageContext>,
+    /// Stops the split stays up for, this one included
+    stops_left: u8,
+}
+
+impl Default for DualContext {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            last: None,
+            previous: None,
+            stops_left: 0,
+        }
+    }
+}
+
+impl DualContext {
+    /// The panes show `context` with execution at `time_idx`. The same time index as last time
+    /// is the panes being redrawn, not a new stop, and only updates what they show
+    pub fn stop(&mut self, time_idx: u64, context: ImageContext) {
+        let Some((last_idx, last)) = self.last.replace((time_idx, context)) else {
+            return;
+        };
+        if last_idx == time_idx {
+            return;
+        }
+        let current = &self.last.as_ref().unwrap().1;
+        if self.enabled && last.image != current.image {
+            self.previous = Some(ImageContext {
+                image: last.image,
+                instruction_lines: around_current(&last.instruction_lines),
+                source_lines: around_current(&last.source_lines),
+            });
+            self.stops_left = SPLIT_STOPS;
+        } else if self.previous.is_some() {
+            self.stops_left = self.stops_left.saturating_sub(1);
+            if self.stops_left == 0 {
+                self.previous = None;
+            }
+        }
+    }
+
+    /// Take the split down until the next crossing
+    pub fn dismiss(&mut self) {
+        self.previous = None;
+        self.stops_left = 0;
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.dismiss();
+        }
+    }
+
+    /// The image execution came from, while the split is up
+    pub fn previous(&self) -> Option<&ImageContext> {
+        self.previous.as_ref()
+    }
+
+    /// The image of the latest stop
+    pub fn current_image(&self) -> Option<&str> {
+        self.last.as_ref()?.1.image.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A boot ROM below 0x100000 and the application above it
+    struct FakeImages;
+
+    impl ImageSource for FakeImages {
+        fn image_of(&self, addr: u32) -> Option<String> {
+            Some(
+                if addr < 0x100000 {
+                    "rom.elf"
+                } else {
+                    "app.elf"
+                }
+                .to_string(),
+            )
+        }
+    }
+
+    fn lines(prefix: &str) -> Vec<String> {
+        (0..6)
+            .map(|i| {
+                let marker = if i == 3 { "->" } else { "  " };
+                format!("{marker} {prefix} {i}")
+            })
+            .collect()
+    }
+
+    fn stop_at(dual: &mut DualContext, time_idx: u64, pc: u32) {
+        let prefix = format!("{pc:x}");
+        let context = ImageContext::new(pc, &FakeImages, &lines(&prefix), &lines(&prefix));
+        dual.stop(time_idx, context);
+    }
+
+    #[test]
+    fn test_no_split_within_an_image() {
+        let mut dual = DualContext::default();
+        for (time_idx, pc) in [(0, 0x100), (1, 0x104), (2, 0x108)] {
+            stop_at(&mut dual, time_idx, pc);
+            assert!(dual.previous().is_none());
+        }
+        assert_eq!(dual.current_image(), Some("rom.elf"));
+    }
+
+    #[test]
+    fn test_split_on_crossing_then_expiry() {
+        let mut dual = DualContext::default();
+        stop_at(&mut dual, 0, 0x100);
+        stop_at(&mut dual, 1, 0x100200);
+
+        let previous = dual.previous().unwrap();
+        assert_eq!(previous.image.as_deref(), Some("rom.elf"));
+        assert_eq!(
+            previous.instruction_lines,
+            ["   100 2", "-> 100 3", "   100 4"]
+        );
+        assert_eq!(dual.current_image(), Some("app.elf"));
+
+        // redrawing the same stop isn't another stop
+        stop_at(&mut dual, 1, 0x100200);
+        assert!(dual.previous().is_some());
+
+        stop_at(&mut dual, 2, 0x100204);
+        assert!(dual.previous().is_some());
+        stop_at(&mut dual, 3, 0x100208);
+        assert!(dual.previous().is_none());
+
+        // and back again splits the other way
+        stop_at(&mut dual, 4, 0x104);
+        assert_eq!(dual.previous().unwrap().image.as_deref(), Some("app.elf"));
+    }
+
+    #[test]
+    fn test_dismiss_and_disable() {
+        let mut dual = DualContext::default();
+        stop_at(&mut dual, 0, 0x100);
+        stop_at(&mut dual, 1, 0x100200);
+        dual.dismiss();
+        assert!(dual.previous().is_none());
+        stop_at(&mut dual, 2, 0x100204);
+        assert!(dual.previous().is_none());
+
+        dual.set_enabled(false);
+        stop_at(&mut dual, 3, 0x100);
+        assert!(dual.previous().is_none());
+        assert_eq!(dual.current_image(), Some("rom.elf"));
+    }
+
+    #[test]
+    fn test_around_current() {
+        let short = vec!["-> a".to_string(), "   b".to_string()];
+        assert_eq!(around_current(&short), short);
+        let unmarked: Vec<String> = (0..5).map(|i| i.to_string()).collect();
+        assert_eq!(around_current(&unmarked), ["0", "1", "2"]);
+        let mut last = unmarked.clone();
+        last[4] = "-> 4".to_string();
+        assert_eq!(around_current(&last), ["2", "3", "-> 4"]);
+    }
+}
@@ -0,0 +1,241 @@
+//! `dump state`: the whole debugger state as one plain text report, for attaching to bug reports
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::cli::{JpdbArgs, UiMode};
+use crate::controller::Controller;
+use crate::view::Pane;
+
+/// How much command history goes into a dump
+pub const DUMP_HISTORY_LINES: usize = 100;
+/// How many debug panel log lines go into a dump
+pub const DUMP_LOG_LINES: usize = 200;
+
+/// Everything a state dump is built from. Only already cached state, so building a report can't
+/// hang on a dead connection
+pub struct DumpSources<'a> {
+    pub cli_args: &'a JpdbArgs,
+    pub controller: &'a Controller,
+    pub command_history: &'a [String],
+    pub log_lines: &'a [String],
+    /// Background tasks, one `<name>: <state>` line each
+    pub tasks: &'a [String],
+}
+
+fn section(out: &mut String, title: &str, lines: &[String]) {
+    out.push_str(&format!("== {title} ==\n"));
+    if lines.is_empty() {
+        out.push_str("(none)\n");
+    }
+    for line in lines {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push('\n');
+}
+
+fn last(lines: &[String], n: usize) -> &[String] {
+    &lines[lines.len().saturating_sub(n)..]
+}
+
+/// Paths are kept as given, absolute ones are called out since they may be personal
+fn path_line(flag: &str, path: &Path) -> String {
+    if path.is_absolute() {
+        format!("{flag} {} (absolute path)", path.display())
+    } else {
+        format!("{flag} {}", path.display())
+    }
+}
+
+/// Render the state report
+pub fn state_report(sources: &DumpSources<'_>) -> String {
+    let controller = sources.controller;
+    let view_state = &controller.view_state;
+    let mut out = String::new();
+
+    section(
+        &mut out,
+        "version",
+        &[
+            format!("jpdb {}", env!("CARGO_PKG_VERSION")),
+            format!(
+                "build: {}",
+                if cfg!(debug_assertions) {
+                    "debug"
+                } else {
+                    "release"
+                }
+            ),
+        ],
+    );
+
+    let args = sources.cli_args;
+    let mut arg_lines = vec![
+        path_line("--wave-path", &args.wave_path),
+        path_line("--mapping-path", &args.mapping_path),
+        path_line("--elf", &args.elf),
+    ];
+    arg_lines.extend(
+        args.mapping_arg
+            .iter()
+            .map(|(key, value)| format!("--mapping-arg {key}={value}")),
+    );
+    if let Some(scratch) = &args.scratch {
+        arg_lines.push(format!("--scratch {:x}:{:x}", scratch.start, scratch.size));
+    }
+    if args.ui == UiMode::Simple {
+        arg_lines.push("--ui simple".to_string());
+    }
+    if let Some(init_commands) = &args.init_commands {
+        arg_lines.push(path_line("--init-commands", init_commands));
+    }
+    if args.allow_local_init {
+        arg_lines.push("--allow-local-init".to_string());
+    }
+    if args.no_session {
+        arg_lines.push("--no-session".to_string());
+    }
+    section(&mut out, "cli args", &arg_lines);
+
+    let mut status = controller.model.status_lines();
+    status.push(format!("addrfmt: {}", controller.model.addr_fmt));
+    status.push(format!(
+        "split view: {}, debug panel: {}",
+        view_state.show_split_view, view_state.show_debug_panel
+    ));
+    section(&mut out, "status", &status);
+    section(&mut out, "tasks", sources.tasks);
+
+    section(&mut out, "execution", &view_state.execution_lines);
+    let stale_note = |pane| {
+        if view_state.is_stale(pane) {
+            " (stale, pane hidden)"
+        } else {
+            ""
+        }
+    };
+    section(
+        &mut out,
+        &format!("source{}", stale_note(Pane::Source)),
+        &view_state.source_lines,
+    );
+    section(
+        &mut out,
+        &format!("signals{}", stale_note(Pane::Signals)),
+        &view_state.signal_lines,
+    );
+
+    section(
+        &mut out,
+        "breakpoints",
+        &controller.model.breakpoint_lines(),
+    );
+    section(
+        &mut out,
+        &format!("command history (last {DUMP_HISTORY_LINES})"),
+        last(sources.command_history, DUMP_HISTORY_LINES),
+    );
+    section(
+        &mut out,
+        &format!("debug log (last {DUMP_LOG_LINES})"),
+        last(sources.log_lines, DUMP_LOG_LINES),
+    );
+    let stats = controller.model.client.stats();
+    section(&mut out, "packet stats", &[stats.packets.to_string()]);
+    section(&mut out, "symbol cache", &[stats.symbol_cache.to_string()]);
+    let values = match controller.model.tracker_counts() {
+        Some(counts) => counts.to_string(),
+        None => "no waveform".to_string(),
+    };
+    section(&mut out, "signal values", &[values]);
+
+    out
+}
+
+/// Write `contents` to `path` through a temporary file and a rename, so a half written dump
+/// never shows up at `path`
+pub fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "dump path has no file name"))?;
+    let tmp = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::test_utils::*;
+
+    #[test]
+    fn test_state_report_sections() {
+        let mut controller = fixture_controller();
+        controller.model.set_breakpoint(0x1000a4).unwrap();
+
+        let cli_args = JpdbArgs {
+            wave_path: test_data("sim.fst"),
+            mapping_path: test_data("signal_get.py"),
+            elf: test_data("hello_test.elf"),
+            mapping_arg: Vec::new(),
+            scratch: None,
+            ui: UiMode::Full,
+            init_commands: None,
+            allow_local_init: false,
+            no_session: false,
+            wave_load_threads: None,
+            wave_keep_scopes_with_empty_name: true,
+        };
+        let history = vec!["(jpdb) b 0x1000a4".to_string()];
+        let logs = vec!["[INFO] hello".to_string()];
+        let report = state_report(&DumpSources {
+            cli_args: &cli_args,
+            controller: &controller,
+            command_history: &history,
+            log_lines: &logs,
+            tasks: &["dang: running".to_string()],
+        });
+
+        for header in [
+            "== version ==",
+            "== cli args ==",
+            "== status ==",
+            "== tasks ==",
+            "== execution ==",
+            "== source ==",
+            "== signals ==",
+            "== breakpoints ==",
+            "== command history (last 100) ==",
+            "== debug log (last 200) ==",
+            "== packet stats ==",
+            "== symbol cache ==",
+            "== signal values ==",
+        ] {
+            assert!(report.contains(header), "missing {header} in:\n{report}");
+        }
+        assert!(
+            report.contains("0x1000a4 hello_test.elf`puthex+0x0"),
+            "{report}"
+        );
+        assert!(report.contains("(absolute path)"), "{report}");
+        assert!(report.contains("(jpdb) b 0x1000a4"));
+    }
+
+    #[test]
+    fn test_write_atomically() {
+        let dir = std::env::temp_dir().join(format!("jpdb_dump_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.txt");
+
+        write_atomically(&path, "first").unwrap();
+        write_atomically(&path, "second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,314 @@
+//! `export md <path>`: the session written up as markdown, for sharing what was found.
+//!
+//! Like `dump state`, it's built from what jpdb already has and asks nothing of the target, so a
+//! session whose stub has gone away still exports. The document has the inputs and how long the
+//! session ran, every command with its output, the notifications, where execution last stopped
+//! with its source, and the breakpoint table. Redactions swap a prefix, a home directory say,
+//! for something else everywhere in the document
+
+use std::path::Path;
+use std::time::Duration;
+
+use crate::line_ui::PROMPT;
+
+/// Written in place of a redacted prefix when no replacement is given
+pub const DEFAULT_REDACTION: &str = "<redacted>";
+
+/// `from` is written as `to` wherever it shows up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redaction {
+    pub from: String,
+    pub to: String,
+}
+
+/// One command and what it printed. Output from before the first command has no command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub command: Option<String>,
+    pub output: Vec<String>,
+}
+
+/// Split the command history into commands and their output, on the prompt each command is
+/// echoed with
+pub fn history_entries(lines: &[String]) -> Vec<HistoryEntry> {
+    let mut entries: Vec<HistoryEntry> = Vec::new();
+    for line in lines {
+        match line.strip_prefix(PROMPT) {
+            Some(command) => entries.push(HistoryEntry {
+                command: Some(command.to_string()),
+                output: Vec::new(),
+            }),
+            None => match entries.last_mut() {
+                Some(entry) => entry.output.push(line.clone()),
+                None => entries.push(HistoryEntry {
+                    command: None,
+                    output: vec![line.clone()],
+                }),
+            },
+        }
+    }
+    entries
+}
+
+/// Everything a transcript is built from, all of it already cached
+pub struct TranscriptSources<'a> {
+    pub elf: &'a Path,
+    pub wave: &'a Path,
+    pub mapping: &'a Path,
+    pub duration: Duration,
+    pub command_history: &'a [String],
+    /// One line each, oldest first
+    pub notifications: &'a [String],
+    pub execution: &'a [String],
+    pub source: &'a [String],
+    pub breakpoints: &'a [String],
+}
+
+/// `1h 2m`, `3m 12s` or `40s`
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m {s}s"),
+        (h, m, _) => format!("{h}h {m}m"),
+    }
+}
+
+/// Longest run of backticks in `text`
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+/// `text` as inline code, with enough backticks around it that none inside end it early
+fn code_span(text: &str) -> String {
+    let ticks = "`".repeat(longest_backtick_run(text) + 1);
+    if ticks.len() > 1 {
+        format!("{ticks} {text} {ticks}")
+    } else {
+        format!("{ticks}{text}{ticks}")
+    }
+}
+
+/// `lines` as a fenced block, the fence longer than any backtick run in them
+fn fenced(out: &mut String, lines: &[String]) {
+    let longest = lines
+        .iter()
+        .map(|line| longest_backtick_run(line))
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    out.push_str(&format!("{fence}text\n"));
+    for line in lines {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push_str(&format!("{fence}\n\n"));
+}
+
+/// A section with `lines` fenced, or `(none)`
+fn fenced_section(out: &mut String, title: &str, lines: &[String]) {
+    out.push_str(&format!("## {title}\n\n"));
+    if lines.is_empty() {
+        out.push_str("(none)\n\n");
+    } else {
+        fenced(out, lines);
+    }
+}
+
+/// Apply `redactions`, longest prefix first so one inside another doesn't cut it short
+pub fn redact(text: &str, redactions: &[Redaction]) -> String {
+    let mut ordered: Vec<&Redaction> = redactions
+        .iter()
+        .filter(|redaction| !redaction.from.is_empty())
+        .collect();
+    ordered.sort_by_key(|redaction| std::cmp::Reverse(redaction.from.len()));
+    ordered
+        .into_iter()
+        .fold(text.to_string(), |text, redaction| {
+            text.replace(&redaction.from, &redaction.to)
+        })
+}
+
+/// Render the transcript
+pub fn render(sources: &TranscriptSources<'_>, redactions: &[Redaction]) -> String {
+    let mut out = String::from("# jpdb session\n\n");
+    for (label, path) in [
+        ("ELF", sources.elf),
+        ("Waveform", sources.wave),
+        ("Mapping", sources.mapping),
+    ] {
+        out.push_str(&format!(
+            "- **{label}:** {}\n",
+            code_span(&path.display().to_string())
+        ));
+    }
+    out.push_str(&format!(
+        "- **Duration:** {}\n\n",
+        format_duration(sources.duration)
+    ));
+
+    out.push_str("## Log\n\n");
+    let entries = history_entries(sources.command_history);
+    if entries.is_empty() {
+        out.push_str("(no commands)\n\n");
+    }
+    for entry in entries {
+        if let Some(command) = &entry.command {
+            out.push_str(&format!("{}\n\n", code_span(command)));
+        }
+        if !entry.output.is_empty() {
+            fenced(&mut out, &entry.output);
+        }
+    }
+
+    out.push_str("## Notifications\n\n");
+    if sources.notifications.is_empty() {
+        out.push_str("(none)\n\n");
+    } else {
+        for line in sources.notifications {
+            out.push_str(&format!("- {line}\n"));
+        }
+        out.push('\n');
+    }
+
+    fenced_section(&mut out, "Where execution stopped", sources.execution);
+    fenced_section(&mut out, "Source", sources.source);
+    fenced_section(&mut out, "Breakpoints", sources.breakpoints);
+
+    redact(out.trim_end(), redactions) + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_history_entries() {
+        let history = lines(&[
+            "Connected",
+            "(jpdb) b 0x1000a4",
+            "Breakpoint 1 at 0x1000a4",
+            "(jpdb) step",
+            "(jpdb) regs",
+            "pc 0x1000a4",
+            "sp 0x10ff0",
+        ]);
+        let entries = history_entries(&history);
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].command, None);
+        assert_eq!(entries[0].output, ["Connected"]);
+        assert_eq!(entries[1].command.as_deref(), Some("b 0x1000a4"));
+        assert_eq!(entries[2].command.as_deref(), Some("step"));
+        assert!(entries[2].output.is_empty());
+        assert_eq!(entries[3].output, ["pc 0x1000a4", "sp 0x10ff0"]);
+        assert!(history_entries(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_code_spans_and_fences() {
+        assert_eq!(code_span("step"), "`step`");
+        assert_eq!(code_span("echo `x`"), "`` echo `x` ``");
+
+        let mut out = String::new();
+        fenced(&mut out, &lines(&["plain"]));
+        assert_eq!(out, "```text\nplain\n```\n\n");
+        let mut out = String::new();
+        fenced(&mut out, &lines(&["has ``` inside"]));
+        assert_eq!(out, "````text\nhas ``` inside\n````\n\n");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(40)), "40s");
+        assert_eq!(format_duration(Duration::from_secs(192)), "3m 12s");
+        assert_eq!(format_duration(Duration::from_secs(3720)), "1h 2m");
+    }
+
+    #[test]
+    fn test_redact() {
+        let redactions = [
+            Redaction {
+                from: "/home/pat".to_string(),
+                to: "~".to_string(),
+            },
+            Redaction {
+                from: "/home/pat/work/dang".to_string(),
+                to: "<repo>".to_string(),
+            },
+            Redaction {
+                from: String::new(),
+                to: "never".to_string(),
+            },
+        ];
+        assert_eq!(
+            redact("/home/pat/work/dang/sim.fst and /home/pat/x", &redactions),
+            "<repo>/sim.fst and ~/x"
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let history = lines(&["(jpdb) b 0x1000a4", "Breakpoint 1 at 0x1000a4", "(jpdb) c"]);
+        let notifications = lines(&["+5s [dang] warning: trace ends soon"]);
+        let execution = lines(&["-> 0x1000a4 addi sp, sp, -16"]);
+        let breakpoints = lines(&["1 0x1000a4 puthex+0x0 hits 1"]);
+        let sources = TranscriptSources {
+            elf: Path::new("/home/pat/hello.elf"),
+            wave: Path::new("sim.fst"),
+            mapping: Path::new("signal_get.py"),
+            duration: Duration::from_secs(75),
+            command_history: &history,
+            notifications: &notifications,
+            execution: &execution,
+            source: &[],
+            breakpoints: &breakpoints,
+        };
+        let redactions = [Redaction {
+            from: "/home/pat".to_string(),
+            to: DEFAULT_REDACTION.to_string(),
+        }];
+        assert_eq!(
+            render(&sources, &redactions),
+            "# jpdb session\n\
+             \n\
+             - **ELF:** `<redacted>/hello.elf`\n\
+             - **Waveform:** `sim.fst`\n\
+             - **Mapping:** `signal_get.py`\n\
+             - **Duration:** 1m 15s\n\
+             \n\
+             ## Log\n\
+             \n\
+             `b 0x1000a4`\n\
+             \n\
+             ```text\n\
+             Breakpoint 1 at 0x1000a4\n\
+             ```\n\
+             \n\
+             `c`\n\
+             \n\
+             ## Notifications\n\
+             \n\
+             - +5s [dang] warning: trace ends soon\n\
+             \n\
+             ## Where execution stopped\n\
+             \n\
+             ```text\n\
+             -> 0x1000a4 addi sp, sp, -16\n\
+             ```\n\
+             \n\
+             ## Source\n\
+             \n\
+             (none)\n\
+             \n\
+             ## Breakpoints\n\
+             \n\
+             ```text\n\
+             1 0x1000a4 puthex+0x0 hits 1\n\
+             ```\n"
+        );
+    }
+}
@@ -0,0 +1,216 @@
+//! Golden-file checks for the full UI. [`render`] draws the app the way the terminal would show
+//! it, [`WIDTH`]x[`HEIGHT`] on ratatui's `TestBackend`, and keeps only the characters, so colors
+//! and styles don't take part. [`check`] compares that against a file in `test_data/golden`,
+//! and [`check_file`] does the same for other text, like a session export.
+//!
+//! After a change that's meant to move things around on screen, run the tests with
+//! `JPDB_UPDATE_GOLDENS=1` to write the files over, and look at the diff before committing it.
+//! A golden that isn't there is a failure too, so a new one has to be written the same way
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ratatui::backend::TestBackend;
+use ratatui::buffer::Buffer;
+use ratatui::Terminal;
+
+use crate::App;
+
+pub const WIDTH: u16 = 100;
+pub const HEIGHT: u16 = 40;
+
+/// Set to anything to write the goldens instead of checking against them
+pub const UPDATE_ENV: &str = "JPDB_UPDATE_GOLDENS";
+
+/// Stands in for the repository's path on whichever machine the tests run
+const REPO: &str = "<repo>";
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../test_data/golden")
+}
+
+/// The screen's rows, trailing blanks left off
+fn buffer_text(buffer: &Buffer) -> String {
+    let mut text = String::new();
+    for row in buffer.content().chunks(buffer.area.width as usize) {
+        let line: String = row.iter().map(|cell| cell.symbol()).collect();
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+/// Draw `app` until it settles, the way the event loop would: code markers looked up and the
+/// instruction window fitted to the pane, each followed by another frame
+pub fn render(app: &mut App) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(WIDTH, HEIGHT)).unwrap();
+    terminal.draw(|f| app.ui(f)).unwrap();
+    loop {
+        let markers = app.controller.fill_code_markers();
+        let refit = app.controller.fit_instruction_window();
+        if !markers && !refit {
+            break;
+        }
+        terminal.draw(|f| app.ui(f)).unwrap();
+    }
+    buffer_text(terminal.backend().buffer())
+}
+
+/// `hh:mm:ss` over every wall clock time in `line`
+fn mask_clock_times(line: &str) -> String {
+    let mut chars: Vec<char> = line.chars().collect();
+    let pattern = "dd:dd:dd";
+    let mut i = 0;
+    while i + pattern.len() <= chars.len() {
+        let matches = pattern.chars().zip(&chars[i..]).all(|(p, &c)| match p {
+            'd' => c.is_ascii_digit(),
+            _ => c == p,
+        });
+        if matches {
+            chars.splice(i..i + pattern.len(), "hh:mm:ss".chars());
+            i += pattern.len();
+        } else {
+            i += 1;
+        }
+    }
+    chars.into_iter().collect()
+}
+
+/// `text` with what changes from one machine or run to the next masked: the repository's path,
+/// however it was spelled, and wall clock times
+pub fn normalize(text: &str, repo: &Path) -> String {
+    let mut spellings = vec![repo.display().to_string()];
+    if let Ok(canonical) = repo.canonicalize() {
+        spellings.push(canonical.display().to_string());
+    }
+    // longest first, so a shorter spelling doesn't eat part of a longer one
+    spellings.sort_by_key(|spelling| std::cmp::Reverse(spelling.len()));
+    text.lines()
+        .map(|line| {
+            let line = spellings
+                .iter()
+                .filter(|spelling| !spelling.is_empty())
+                .fold(line.to_string(), |line, spelling| {
+                    line.replace(spelling.as_str(), REPO)
+                });
+            mask_clock_times(&line) + "\n"
+        })
+        .collect()
+}
+
+/// The lines that differ, numbered from 1, `-` for the golden and `+` for what was drawn
+pub fn line_diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let mut diff = String::new();
+    for row in 0..expected.len().max(actual.len()) {
+        let (old, new) = (expected.get(row), actual.get(row));
+        if old == new {
+            continue;
+        }
+        if let Some(old) = old {
+            diff.push_str(&format!("{:>3} - |{old}|\n", row + 1));
+        }
+        if let Some(new) = new {
+            diff.push_str(&format!("{:>3} + |{new}|\n", row + 1));
+        }
+    }
+    diff
+}
+
+/// Compare `screen` against the golden called `name`, writing it instead with [`UPDATE_ENV`]
+/// set
+pub fn check(name: &str, screen: &str) {
+    check_file(&format!("{name}.txt"), screen);
+}
+
+/// [`check`] for a golden that isn't a screen, kept as `file_name` in `test_data/golden`
+pub fn check_file(file_name: &str, text: &str) {
+    let repo = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    let actual = normalize(text, &repo);
+    let path = golden_dir().join(file_name);
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        fs::create_dir_all(golden_dir()).unwrap();
+        fs::write(&path, &actual).unwrap();
+        eprintln!("wrote {}", path.display());
+        return;
+    }
+    let Ok(expected) = fs::read_to_string(&path) else {
+        panic!(
+            "no golden for {file_name} at {}, run with {UPDATE_ENV}=1 to write it",
+            path.display()
+        );
+    };
+    if expected != actual {
+        panic!(
+            "{file_name} doesn't match {}, rerun with {UPDATE_ENV}=1 if that's intended:\n{}",
+            path.display(),
+            line_diff(&expected, &actual)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{fixture_app, type_line};
+
+    /// Two steps, a breakpoint and two signals in, typed the way a user would
+    fn scripted_app() -> App {
+        let mut app = fixture_app();
+        for line in [
+            "b 0x1000a4",
+            "step",
+            "step",
+            "addsig pc_id",
+            "addsig pc_wb_o",
+        ] {
+            type_line(&mut app, line);
+        }
+        app
+    }
+
+    #[test]
+    fn test_split_view() {
+        let mut app = scripted_app();
+        check("split_view", &render(&mut app));
+    }
+
+    #[test]
+    fn test_combined_view() {
+        let mut app = scripted_app();
+        app.controller.set_split_view(false);
+        check("combined_view", &render(&mut app));
+    }
+
+    #[test]
+    fn test_help_modal() {
+        let mut app = scripted_app();
+        type_line(&mut app, "help");
+        assert!(app.help_modal_state.is_active());
+        check("help_modal", &render(&mut app));
+    }
+
+    #[test]
+    fn test_normalize() {
+        let repo = Path::new("/home/ci/dang");
+        assert_eq!(
+            normalize(
+                "elf /home/ci/dang/test_data/ibex/hello_test.elf\n[12:03:59] stopped",
+                repo
+            ),
+            "elf <repo>/test_data/ibex/hello_test.elf\n[hh:mm:ss] stopped\n"
+        );
+        // not quite a time
+        assert_eq!(normalize("1:02:03 12:3:45", repo), "1:02:03 12:3:45\n");
+    }
+
+    #[test]
+    fn test_line_diff() {
+        assert_eq!(line_diff("a\nb\n", "a\nb\n"), "");
+        assert_eq!(
+            line_diff("a\nb\n", "a\nc\nd\n"),
+            "  2 - |b|\n  2 + |c|\n  3 + |d|\n"
+        );
+    }
+}
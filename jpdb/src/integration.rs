@@ -0,0 +1,133 @@
+//! The model and controller together against a live dang, the way the TUI drives them but
+//! without a terminal. Each test gets its own dang from
+//! [`fixture_controller`](crate::controller::test_utils::fixture_controller), loaded with
+//! `hello_test.elf` and `sim.fst`, and checks the typed snapshots along with the pane lines the
+//! view makes of them
+
+use crate::controller::test_utils::fixture_controller;
+use crate::controller::Controller;
+use crate::model::{ExecutionSnapshot, SignalSnapshot, SourceSnapshot};
+
+#[test]
+fn test_initial_refresh_fills_the_panes() {
+    let mut controller = fixture_controller();
+    let view = &controller.view_state;
+    assert!(!view.execution_lines.is_empty());
+    assert!(
+        view.instruction_lines[0].starts_with("->"),
+        "{:?}",
+        view.instruction_lines
+    );
+    assert!(!view.source_lines.is_empty());
+
+    let pc = controller.model.client.get_current_pc().unwrap().as_u32();
+    let ExecutionSnapshot::Stopped {
+        frame: Ok(frame), ..
+    } = controller.model.fetch_execution_snapshot().unwrap()
+    else {
+        panic!("a fresh session is stopped at a known pc");
+    };
+    assert_eq!(frame.pc, pc);
+    let instructions = frame.instructions.unwrap();
+    assert!(instructions[0].is_current);
+    assert!(!matches!(
+        controller.model.fetch_source_snapshot().unwrap(),
+        SourceSnapshot::Terminated | SourceSnapshot::Error(_)
+    ));
+}
+
+#[test]
+fn test_step_moves_the_pc_and_drops_the_cached_time_index() {
+    let mut model = fixture_controller().model;
+    let pc = model.client.get_current_pc().unwrap().as_u32();
+    let idx = model.get_time_idx().unwrap();
+    let sent = model.client.packet_stats().packets_sent;
+    // asked once, then cached
+    assert_eq!(model.get_time_idx().unwrap(), idx);
+    assert_eq!(model.client.packet_stats().packets_sent, sent);
+
+    model.step().unwrap();
+    let stepped = model.client.packet_stats().packets_sent;
+    let next = model.get_time_idx().unwrap();
+    assert!(next > idx);
+    assert!(model.client.packet_stats().packets_sent > stepped);
+    assert_ne!(model.client.get_current_pc().unwrap().as_u32(), pc);
+}
+
+#[test]
+fn test_line_breakpoint_then_continue() {
+    let mut controller = fixture_controller();
+    // puthex(0xDEADBEEF)
+    let breakpoints = controller
+        .model
+        .set_breakpoint_at_line("hello_test.c", 13, None)
+        .unwrap();
+    assert!(!breakpoints.is_empty());
+    assert!(breakpoints.iter().all(|bp| bp.spec == "hello_test.c:13"));
+    let addresses: Vec<u32> = breakpoints.iter().map(|bp| bp.address).collect();
+
+    controller.model.continue_execution().unwrap();
+    controller.refresh_all();
+    let pc = controller.model.client.get_current_pc().unwrap().as_u32();
+    assert!(addresses.contains(&pc), "0x{pc:x} not in {addresses:x?}");
+
+    let SourceSnapshot::Located { file, entries, .. } =
+        controller.model.fetch_source_snapshot().unwrap()
+    else {
+        panic!("hello_test.c has line info");
+    };
+    assert!(file.ends_with("hello_test.c"), "{file}");
+    let current = entries.iter().find(|entry| entry.is_current).unwrap();
+    assert_eq!(current.line_no, 13);
+    let source = &controller.view_state.source_lines;
+    assert!(source[2].starts_with("->"), "{source:?}");
+}
+
+#[test]
+fn test_selected_signal_shows_up_in_the_next_snapshot() {
+    let mut controller = fixture_controller();
+    let matches = controller.model.fuzzy_match_signals("pc_wb_o");
+    assert!(!matches.is_empty());
+    assert!(
+        matches.iter().all(|(_, name)| name.starts_with("TOP.")),
+        "{matches:?}"
+    );
+    let signals =
+        |controller: &mut Controller| match controller.model.fetch_signal_snapshot(None).unwrap() {
+            SignalSnapshot::Values { entries, .. } => entries,
+            other => panic!("the fixture has a waveform: {other:?}"),
+        };
+    assert!(signals(&mut controller).is_empty());
+
+    let (var, name) = matches[0].clone();
+    controller.model.select_signal(var);
+    controller.refresh_signals();
+    let entries = signals(&mut controller);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].name, name);
+    let line = &controller.view_state.signal_lines[2];
+    assert!(line.starts_with(&format!("{name}: ")), "{line}");
+}
+
+/// What the model does past the end is covered in its own tests, this is what the panes make
+/// of it
+#[test]
+fn test_panes_after_stepping_off_the_end_of_the_trace() {
+    let mut controller = fixture_controller();
+    let trace_len = controller.model.trace_len();
+    controller.model.goto_time_idx(trace_len - 1).unwrap();
+    assert!(controller.model.step().is_err());
+
+    controller.refresh_all();
+    let view = &controller.view_state;
+    for lines in [
+        &view.execution_lines,
+        &view.instruction_lines,
+        &view.source_lines,
+        &view.signal_lines,
+    ] {
+        assert_eq!(lines, &["Process has terminated"]);
+    }
+    // the timeline still spans the whole trace
+    assert_eq!(view.timeline.trace_len, trace_len);
+}
@@ -0,0 +1,65 @@
+//! `--ui simple`: a plain prompt on stdin/stdout, for slow SSH links and scripts.
+//!
+//! No alternate screen and no raw mode. Each line goes through the same [`CommandRegistry`]
+//! as the full interface, and whatever the command added to the command history is printed.
+//! Piping a file of commands in is the batch mode
+//!
+//! [`CommandRegistry`]: crate::user_commands::CommandRegistry
+
+use std::io::{self, BufRead, Write};
+
+use crate::App;
+
+pub const PROMPT: &str = "(jpdb) ";
+
+/// Run commands from `input` until it ends or one of them quits
+pub fn run(app: &mut App, input: impl BufRead, output: &mut impl Write) -> io::Result<()> {
+    // Panes are only ever printed on request, keep refreshes from fetching them
+    app.controller.set_split_view(false);
+
+    let mut printed = app.command_history.len();
+    write!(output, "{PROMPT}")?;
+    output.flush()?;
+    let mut lines = input.lines();
+    while let Some(line) = lines.next() {
+        app.input_buffer = line?;
+        app.process_command();
+        app.input_buffer.clear();
+        if let Some(prompt) = app.confirmation_prompt() {
+            // Same rule as a keypress: y or an empty line confirms, anything else cancels
+            write!(output, "{prompt} ")?;
+            output.flush()?;
+            let answer = lines.next().transpose()?;
+            if answer.is_none() {
+                writeln!(output)?;
+            }
+            let confirmed = answer.is_some_and(|a| matches!(a.trim(), "" | "y" | "Y"));
+            app.answer_confirmation(confirmed);
+        }
+        app.poll_dang_exit();
+        app.poll_notifications();
+
+        // `clear` empties the history from under us
+        printed = printed.min(app.command_history.len());
+        let mut new_lines = app.command_history[printed..].iter();
+        // The command was echoed into the history, the terminal already shows it
+        if app.command_history[printed..]
+            .first()
+            .is_some_and(|first| first.starts_with(PROMPT))
+        {
+            new_lines.next();
+        }
+        for line in new_lines {
+            writeln!(output, "{line}")?;
+        }
+        printed = app.command_history.len();
+
+        if app.should_quit {
+            return Ok(());
+        }
+        write!(output, "{PROMPT}")?;
+        output.flush()?;
+    }
+    writeln!(output)?;
+    Ok(())
+}
@@ -0,0 +1,261 @@
+//! Scrollback for the debug panel. Messages are formatted once as they come in and dropped
+//! oldest first past the capacity. Drawing only copies out the lines on screen, so a deep
+//! scrollback costs nothing per frame
+
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use crate::view;
+
+/// Scrollback kept unless `set logbuffer` says otherwise
+pub const DEFAULT_CAPACITY: usize = 50_000;
+
+#[derive(Debug, Clone)]
+pub struct LogMessage {
+    pub level: log::Level,
+    /// `[LEVEL] message`, as it's shown
+    pub line: String,
+}
+
+/// The lines of a [`LogBuffer`] that fit on screen
+#[derive(Debug, Default)]
+pub struct LogWindow {
+    /// Oldest first
+    pub lines: Vec<LogMessage>,
+    /// How many stored messages pass the level filter, for the scrollbar
+    pub total: usize,
+}
+
+pub struct LogBuffer {
+    messages: VecDeque<LogMessage>,
+    capacity: usize,
+    /// Stored messages per level, indexed by `level as usize - 1`
+    counts: [usize; 5],
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl LogBuffer {
+    /// Room for `capacity` messages, grown into as they arrive
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            messages: VecDeque::new(),
+            capacity: capacity.max(1),
+            counts: [0; 5],
+        }
+    }
+
+    pub fn push(&mut self, level: log::Level, message: &str) {
+        if self.messages.len() >= self.capacity {
+            self.pop_oldest();
+        }
+        self.counts[level as usize - 1] += 1;
+        self.messages.push_back(LogMessage {
+            level,
+            line: format!("[{level}] {message}"),
+        });
+    }
+
+    fn pop_oldest(&mut self) {
+        if let Some(oldest) = self.messages.pop_front() {
+            self.counts[oldest.level as usize - 1] -= 1;
+        }
+    }
+
+    /// Keep at most `capacity` messages from now on, dropping the oldest ones over it
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.messages.len() > self.capacity {
+            self.pop_oldest();
+        }
+        self.messages.shrink_to(self.capacity);
+    }
+
+    /// How many stored messages are at `min_level` or more severe
+    pub fn count_at(&self, min_level: log::Level) -> usize {
+        self.counts[..min_level as usize].iter().sum()
+    }
+
+    /// The `height` messages at `min_level` or more severe that are on screen when scrolled
+    /// `scroll` lines up from the newest, see [`view::line_window`].
+    ///
+    /// Unfiltered, only the visible messages are touched. Filtered, the walk back from the
+    /// newest also passes over the hidden messages between them
+    pub fn window(&self, min_level: log::Level, height: usize, scroll: usize) -> LogWindow {
+        self.counted_window(min_level, height, scroll).0
+    }
+
+    /// [`LogBuffer::window`], along with how many stored messages it looked at
+    fn counted_window(
+        &self,
+        min_level: log::Level,
+        height: usize,
+        scroll: usize,
+    ) -> (LogWindow, usize) {
+        let total = self.count_at(min_level);
+        let Range { start, end } = view::line_window(total, height, scroll);
+        if min_level == log::Level::Trace {
+            let lines = self.messages.range(start..end).cloned().collect();
+            return (LogWindow { lines, total }, end - start);
+        }
+
+        let mut examined = 0;
+        let mut lines: Vec<LogMessage> = self
+            .messages
+            .iter()
+            .rev()
+            .inspect(|_| examined += 1)
+            .filter(|msg| msg.level <= min_level)
+            .skip(total - end)
+            .take(end - start)
+            .cloned()
+            .collect();
+        lines.reverse();
+        (LogWindow { lines, total }, examined)
+    }
+
+    /// The newest `count` messages as shown, oldest first
+    pub fn last_lines(&self, count: usize) -> Vec<String> {
+        self.window(log::Level::Trace, count, 0)
+            .lines
+            .into_iter()
+            .map(|msg| msg.line)
+            .collect()
+    }
+}
+
+/// The next minimum level the debug panel shows, from everything down to errors only and round
+/// again
+pub fn next_min_level(level: log::Level) -> log::Level {
+    match level {
+        log::Level::Trace => log::Level::Debug,
+        log::Level::Debug => log::Level::Info,
+        log::Level::Info => log::Level::Warn,
+        log::Level::Warn => log::Level::Error,
+        log::Level::Error => log::Level::Trace,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+
+    fn lines(window: &LogWindow) -> Vec<&str> {
+        window.lines.iter().map(|msg| msg.line.as_str()).collect()
+    }
+
+    fn numbered(count: usize, capacity: usize) -> LogBuffer {
+        let mut buffer = LogBuffer::new(capacity);
+        for i in 0..count {
+            buffer.push(Level::Info, &i.to_string());
+        }
+        buffer
+    }
+
+    #[test]
+    fn test_window_scrolling() {
+        let buffer = numbered(10, 100);
+        assert_eq!(
+            lines(&buffer.window(Level::Trace, 3, 0)),
+            ["[INFO] 7", "[INFO] 8", "[INFO] 9"]
+        );
+        assert_eq!(
+            lines(&buffer.window(Level::Trace, 3, 2)),
+            ["[INFO] 5", "[INFO] 6", "[INFO] 7"]
+        );
+        // scrolled past the top sticks at the top
+        assert_eq!(
+            lines(&buffer.window(Level::Trace, 3, 50)),
+            ["[INFO] 0", "[INFO] 1", "[INFO] 2"]
+        );
+        // taller than the buffer
+        assert_eq!(buffer.window(Level::Trace, 20, 0).lines.len(), 10);
+        assert!(buffer.window(Level::Trace, 0, 0).lines.is_empty());
+        assert!(LogBuffer::new(4)
+            .window(Level::Trace, 3, 1)
+            .lines
+            .is_empty());
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut buffer = numbered(10, 4);
+        assert_eq!(buffer.count_at(Level::Trace), 4);
+        assert_eq!(
+            lines(&buffer.window(Level::Trace, 2, 3)),
+            ["[INFO] 6", "[INFO] 7"]
+        );
+
+        buffer.set_capacity(2);
+        assert_eq!(buffer.count_at(Level::Info), 2);
+        assert_eq!(buffer.last_lines(5), ["[INFO] 8", "[INFO] 9"]);
+        buffer.set_capacity(0);
+        assert_eq!(buffer.last_lines(5), ["[INFO] 9"]);
+    }
+
+    #[test]
+    fn test_level_filter() {
+        let mut buffer = LogBuffer::new(100);
+        for (i, level) in [
+            Level::Error,
+            Level::Debug,
+            Level::Warn,
+            Level::Trace,
+            Level::Info,
+        ]
+        .into_iter()
+        .cycle()
+        .take(20)
+        .enumerate()
+        {
+            buffer.push(level, &i.to_string());
+        }
+
+        assert_eq!(buffer.count_at(Level::Trace), 20);
+        assert_eq!(buffer.count_at(Level::Warn), 8);
+        let warnings = buffer.window(Level::Warn, 3, 1);
+        assert_eq!(warnings.total, 8);
+        assert_eq!(lines(&warnings), ["[ERROR] 10", "[WARN] 12", "[ERROR] 15"]);
+        let errors = buffer.window(Level::Error, 10, 0);
+        assert_eq!(errors.lines.len(), 4);
+        assert!(errors.lines.iter().all(|msg| msg.level == Level::Error));
+
+        assert_eq!(next_min_level(Level::Trace), Level::Debug);
+        assert_eq!(next_min_level(Level::Error), Level::Trace);
+    }
+
+    #[test]
+    fn test_window_work_is_bounded_by_the_window() {
+        let mut buffer = LogBuffer::default();
+        for i in 0..100_000 {
+            let level = if i % 10 == 0 {
+                Level::Warn
+            } else {
+                Level::Debug
+            };
+            buffer.push(level, &format!("message {i}"));
+        }
+        assert_eq!(buffer.count_at(Level::Trace), DEFAULT_CAPACITY);
+
+        for scroll in [0, 1_000, 40_000] {
+            let (window, examined) = buffer.counted_window(Level::Trace, 50, scroll);
+            assert_eq!(window.lines.len(), 50);
+            assert_eq!(examined, 50);
+        }
+        assert_eq!(
+            buffer.window(Level::Trace, 1, 0).lines[0].line,
+            "[DEBUG] message 99999"
+        );
+
+        // one in ten passes, so the walk covers about ten times the window, not the buffer
+        let (window, examined) = buffer.counted_window(Level::Warn, 50, 0);
+        assert_eq!(window.lines.len(), 50);
+        assert_eq!(window.total, 5_000);
+        assert!(examined <= 50 * 10, "{examined}");
+    }
+}
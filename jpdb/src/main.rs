@@ -1,83 +1,152 @@
 use std::{
-    collections::VecDeque,
     io,
     net::TcpListener,
-    path::Path,
-    sync::{Arc, Mutex},
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+mod addr_format;
+mod aliases;
+mod breakpoints;
 mod cli;
+mod code_markers;
+mod controller;
+mod delta;
+mod dual_context;
+mod dump;
+mod export_md;
+#[cfg(test)]
+mod golden;
+#[cfg(test)]
+mod integration;
+mod line_ui;
+mod log_buffer;
 mod model;
+mod notifications;
+mod picker;
+mod session;
+mod tasks;
+mod terminal_state;
 mod user_commands;
+mod value_names;
 mod view;
 mod wcp_client;
 
-use model::DebuggerModel;
-use user_commands::CommandRegistry;
-use view::ViewState;
+use addr_format::{AddrFmtOption, AddrFormatter};
+use aliases::AliasTable;
+use breakpoints::{Breakpoint, BulkResult, GdbBreak, GdbLocation, Selector};
+use cli::local_init_path;
+use controller::Controller;
+use log_buffer::{LogBuffer, LogWindow};
+use model::{DebuggerModel, SigTime};
+use notifications::{Notifications, Severity};
+use picker::{Picker, PickerText};
+use session::Session;
+use tasks::TaskRegistry;
+use terminal_state::QuitSignal;
+use user_commands::{
+    common_prefix, parse_hex_u32, CommandOutcome, CommandRegistry, Completer, Completion,
+    ConfirmedAction, DisasTarget, SkipTarget, DEFAULT_DISAS_COUNT, MAX_DISAS_INSTRUCTIONS,
+};
+use view::{Pane, RedrawFlag, ViewState};
 use wcp_client::WcpClient;
 
-use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
 
+use dang::error::StartupError;
+use dang::skip_ranges::SkipRange;
+use dang::SessionOutcome;
 use ratatui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     widgets::{Block, Borders, List, ListItem, Scrollbar},
     Frame, Terminal,
 };
+use shucks::client::{Assertion, StopReport};
+use shucks::findinst::{FindOutcome, InstPredicate, SearchDirection};
 use shucks::{Client, Var};
 
-// Custom logger that captures messages for ratatui display
-#[derive(Debug, Clone)]
-pub struct LogMessage {
-    level: log::Level,
-    message: String,
-    _timestamp: std::time::Instant,
-}
+/// Why the dang thread couldn't start, sent back to the UI thread
+type StartupFailure = Box<dyn std::error::Error + Send + Sync>;
 
+/// How the dang thread finished: it never got going, or its session ended
+type DangExit = Result<SessionOutcome, StartupFailure>;
+
+// Custom logger that captures messages for ratatui display
 pub struct AppLogger {
-    buffer: Arc<Mutex<VecDeque<LogMessage>>>,
+    buffer: Arc<Mutex<LogBuffer>>,
+    redraw: RedrawFlag,
+    /// Most verbose level let through from a module path and everything under it, the longest
+    /// matching path wins. Modules without one are let through at any level
+    module_filters: Vec<(String, log::LevelFilter)>,
 }
 
 impl AppLogger {
-    pub fn new() -> (Self, Arc<Mutex<VecDeque<LogMessage>>>) {
-        let buffer = Arc::new(Mutex::new(VecDeque::with_capacity(1000)));
+    pub fn new(redraw: RedrawFlag) -> (Self, Arc<Mutex<LogBuffer>>) {
+        let buffer = Arc::new(Mutex::new(LogBuffer::default()));
         (
             Self {
                 buffer: buffer.clone(),
+                redraw,
+                module_filters: Vec::new(),
             },
             buffer,
         )
     }
+
+    /// The logger jpdb runs with: dang is embedded, so its silent entry point's filters are
+    /// applied here
+    pub fn embedded(redraw: RedrawFlag) -> (Self, Arc<Mutex<LogBuffer>>) {
+        let (logger, buffer) = Self::new(redraw);
+        let logger = dang::tidx_log::SILENT_FILTERS
+            .iter()
+            .fold(logger, |logger, &(module, level)| {
+                logger.with_module_filter(module, level)
+            });
+        (logger, buffer)
+    }
+
+    /// Only let records from `module`, or a module under it, through at `level` and above
+    pub fn with_module_filter(mut self, module: &str, level: log::LevelFilter) -> Self {
+        self.module_filters.push((module.to_string(), level));
+        self
+    }
+
+    fn level_for(&self, target: &str) -> log::LevelFilter {
+        self.module_filters
+            .iter()
+            .filter(|(module, _)| {
+                target
+                    .strip_prefix(module.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map_or(log::LevelFilter::Trace, |&(_, level)| level)
+    }
+
+    /// Make this the global logger. A logger that's already installed, another test's or an
+    /// embedding program's, is an error rather than a panic
+    pub fn install(self) -> Result<(), log::SetLoggerError> {
+        log::set_boxed_logger(Box::new(self)).map(|()| log::set_max_level(log::LevelFilter::Debug))
+    }
 }
 
 impl log::Log for AppLogger {
-    fn enabled(&self, _metadata: &log::Metadata) -> bool {
-        true
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
     }
 
     fn log(&self, record: &log::Record) {
         if self.enabled(record.metadata()) {
-            let message = LogMessage {
-                level: record.level(),
-                message: record.args().to_string(),
-                _timestamp: std::time::Instant::now(),
-            };
-
+            let message = record.args().to_string();
             if let Ok(mut buffer) = self.buffer.lock() {
-                // Keep only the last 1000 log messages
-                if buffer.len() >= 1000 {
-                    buffer.pop_front();
-                }
-                buffer.push_back(message);
+                buffer.push(record.level(), &message);
             }
+            // The debug panel may be showing, let it catch up without a keypress
+            self.redraw.mark();
         }
     }
 
@@ -87,8 +156,7 @@ impl log::Log for AppLogger {
 pub struct AddSigState {
     active: bool,
     input: String,
-    matches: Vec<(Var, String)>,
-    selected_index: usize,
+    picker: Picker<Var>,
 }
 
 impl Default for AddSigState {
@@ -102,23 +170,20 @@ impl AddSigState {
         Self {
             active: false,
             input: String::new(),
-            matches: Vec::new(),
-            selected_index: 0,
+            picker: Picker::default(),
         }
     }
 
     pub fn activate(&mut self) {
         self.active = true;
         self.input.clear();
-        self.matches.clear();
-        self.selected_index = 0;
+        self.picker.clear();
     }
 
     pub fn deactivate(&mut self) {
         self.active = false;
         self.input.clear();
-        self.matches.clear();
-        self.selected_index = 0;
+        self.picker.clear();
     }
 
     pub fn is_active(&self) -> bool {
@@ -127,7 +192,7 @@ impl AddSigState {
 
     pub fn update_search(&mut self, input: String) {
         self.input = input;
-        self.selected_index = 0; // Reset selection when search changes
+        self.picker.select_first(); // Reset selection when search changes
     }
 
     pub fn get_input(&self) -> &str {
@@ -135,38 +200,20 @@ impl AddSigState {
     }
 
     pub fn set_matches(&mut self, matches: Vec<(Var, String)>) {
-        self.matches = matches.into_iter().take(10).collect(); // Take top 10
-        self.selected_index = self
-            .selected_index
-            .min(self.matches.len().saturating_sub(1));
-    }
-
-    pub fn get_matches(&self) -> &[(Var, String)] {
-        &self.matches
+        self.picker
+            .set_items(matches.into_iter().take(10).collect()); // Take top 10
     }
 
     pub fn select_next(&mut self) {
-        if !self.matches.is_empty() {
-            self.selected_index = (self.selected_index + 1) % self.matches.len();
-        }
+        self.picker.select_next();
     }
 
     pub fn select_prev(&mut self) {
-        if !self.matches.is_empty() {
-            self.selected_index = if self.selected_index == 0 {
-                self.matches.len() - 1
-            } else {
-                self.selected_index - 1
-            };
-        }
+        self.picker.select_prev();
     }
 
     pub fn get_selected(&self) -> Option<&(Var, String)> {
-        self.matches.get(self.selected_index)
-    }
-
-    pub fn get_selected_index(&self) -> usize {
-        self.selected_index
+        self.picker.selected()
     }
 }
 
@@ -224,20 +271,45 @@ impl HelpModalState {
     }
 }
 
+/// A Tab completion picker that's open over the input line
+struct CompletionState {
+    /// Where the word being completed starts in the input
+    start: usize,
+    /// The input from before the picker opened, put back on Esc
+    original: String,
+    /// Replacement text, listed by label
+    picker: Picker<String>,
+}
+
+/// A destructive command waiting on y/N, shown in place of the prompt
+struct PendingConfirmation {
+    prompt: String,
+    on_confirm: ConfirmedAction,
+    asked_at: Instant,
+}
+
 pub struct App {
     pub should_quit: bool,
+    // Set by SIGINT/SIGTERM/SIGHUP while the TUI is up
+    quit_signal: QuitSignal,
     input_buffer: String,
     pub command_history: Vec<String>,
-    model: DebuggerModel,
-    view_state: ViewState,
-    _dang_thread_handle: thread::JoinHandle<()>,
+    controller: Controller,
+    // Background threads, stopped together on the way out
+    tasks: TaskRegistry,
+    // How the dang thread ended, once it has
+    dang_exit: mpsc::Receiver<DangExit>,
+    dang_outcome: Option<SessionOutcome>,
     scroll_offset: usize,
     // Debug panel state
-    show_debug_panel: bool,
     debug_scroll_offset: usize, // Add scroll offset for debug panel
-    // Split view state
-    show_split_view: bool,
-    log_buffer: Arc<Mutex<VecDeque<LogMessage>>>,
+    log_buffer: Arc<Mutex<LogBuffer>>,
+    // Warnings and errors for the user that aren't command output, and the banner
+    notifications: Notifications,
+    // When the notification the last frame's banner showed came in
+    banner_drawn: Option<Instant>,
+    // Least severe level the debug panel shows
+    debug_min_level: log::Level,
     // Last executed command for repeat functionality
     last_command: Option<String>,
     // Command history navigation
@@ -252,83 +324,148 @@ pub struct App {
     surfer_process: Option<std::process::Child>,
     // CLI arguments for reference
     cli_args: cli::JpdbArgs,
+    // Asks for a redraw from outside the key handling path
+    redraw: RedrawFlag,
+    // User-defined command shortcuts
+    aliases: AliasTable,
+    // Tab completion, and its picker while one is open
+    completer: Completer,
+    completion: Option<CompletionState>,
+    // A command waiting on the user to confirm it
+    confirmation: Option<PendingConfirmation>,
+    // Set while an init script runs
+    running_script: bool,
+    // Where the timeline was last drawn, for mouse clicks on it
+    timeline_area: Option<ratatui::layout::Rect>,
+    // When the session started, for how long it ran in `export md`
+    started: Instant,
+    // The session file next to the wave file didn't load, so quitting leaves it alone
+    session_restore_failed: bool,
 }
 
+/// How long to wait for a key before checking whether something else wants a redraw
+const REDRAW_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// An unanswered confirmation prompt goes away after this, as if it had been cancelled
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(30);
+
 impl App {
-    fn new(cli_args: cli::JpdbArgs) -> App {
+    fn new(cli_args: cli::JpdbArgs) -> Result<App, Box<dyn std::error::Error>> {
+        // Catch bad paths before we spin anything up
+        dang::cli::validate_paths(&cli_args.wave_path, &cli_args.mapping_path, &cli_args.elf)?;
+
         // Initialize custom logging system
-        let (logger, log_buffer) = AppLogger::new();
-        log::set_boxed_logger(Box::new(logger))
-            .map(|()| log::set_max_level(log::LevelFilter::Debug))
-            .expect("Failed to initialize logger");
+        let redraw = RedrawFlag::default();
+        let (logger, log_buffer) = AppLogger::embedded(redraw.clone());
+        if let Err(e) = logger.install() {
+            // Everything still works, the debug panel just stays quiet
+            if let Ok(mut buffer) = log_buffer.lock() {
+                buffer.push(
+                    log::Level::Warn,
+                    &format!("Debug panel logging is off, another logger is installed: {e}"),
+                );
+            }
+        }
 
         // Create TCP listener for dang-shucks communication
-        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind listener");
-        let port = listener
-            .local_addr()
-            .expect("Failed to get local addr")
-            .port();
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
 
         // Clone paths for thread
         let wave_path = cli_args.wave_path.clone();
         let mapping_path = cli_args.mapping_path.clone();
         let elf_path = cli_args.elf.clone();
+        let options = dang::DangOptions {
+            mapping_args: cli_args.mapping_arg.iter().cloned().collect(),
+            scratch: cli_args.scratch,
+            wave_load: cli_args.wave_load(),
+            embedded_logging: true,
+            ..Default::default()
+        };
 
-        // Start dang GDB stub in a separate thread
-        let dang_handle = thread::spawn(move || {
-            dang::start_with_args_and_listener_silent(wave_path, mapping_path, elf_path, listener)
-                .expect("Failed to start dang");
-        });
+        // Start dang GDB stub in a separate thread. If it can't load the inputs, or its session
+        // dies later, the reason is handed back here rather than getting lost on another thread.
+        // It doesn't watch its shutdown signal, the session ends when jpdb's connection to it
+        // closes, see App::shutdown
+        let (exit_tx, exit_rx) = mpsc::channel::<DangExit>();
+        let mut tasks = TaskRegistry::default();
+        tasks.spawn("dang", move |_| {
+            let exit = dang::start_with_options_and_listener_silent(
+                wave_path,
+                mapping_path,
+                elf_path,
+                options,
+                listener,
+            )
+            .map_err(|e| -> StartupFailure {
+                // Box<dyn Error> isn't Send, keep the StartupError so the kind survives
+                match e.downcast::<StartupError>() {
+                    Ok(startup_err) => startup_err,
+                    Err(e) => e.to_string().into(),
+                }
+            });
+            let _ = exit_tx.send(exit);
+        })?;
+        let early_exit = |exit: DangExit| -> StartupFailure {
+            match exit {
+                Ok(outcome) => format!("dang stopped before jpdb connected: {outcome}").into(),
+                Err(startup_err) => startup_err,
+            }
+        };
 
-        // Give dang time to start
-        thread::sleep(std::time::Duration::from_millis(300));
+        // Give dang time to start, bailing out early if it already gave up
+        if let Ok(exit) = exit_rx.recv_timeout(Duration::from_millis(300)) {
+            return Err(early_exit(exit));
+        }
 
         // Create shucks client connected to dang
         let mut shucks_client = Client::new_with_port(port);
 
-        shucks_client.initialize_gdb_session().expect("");
+        if let Err(e) = shucks_client.initialize_gdb_session() {
+            return Err(match exit_rx.recv_timeout(Duration::from_secs(1)) {
+                Ok(exit) => early_exit(exit),
+                Err(_) => format!("Failed to talk to dang: {e}").into(),
+            });
+        }
         let _ = shucks_client.load_elf_info();
         shucks_client
-            .load_waveform(cli_args.wave_path.clone())
-            .expect("Failed to load waveform");
+            .load_waveform_with_options(cli_args.wave_path.clone(), &cli_args.wave_load())
+            .map_err(|e| format!("Failed to load waveform: {e}"))?;
         thread::sleep(Duration::from_millis(300));
 
-        let mut model = DebuggerModel::new(shucks_client);
-        let mut view_state = ViewState::default();
-
-        // Initialize views
-        if let Ok(execution) = model.fetch_execution_snapshot() {
-            view_state.execution_lines = execution.summary_lines;
-            view_state.instruction_lines = execution.instruction_lines;
-        } else {
-            view_state.execution_lines = vec!["Failed to load execution info".to_string()];
-            view_state.instruction_lines = vec!["Failed to load execution info".to_string()];
-        }
-
-        if let Ok(source) = model.fetch_source_snapshot() {
-            view_state.source_lines = source.lines;
-        } else {
-            view_state.source_lines = vec!["Failed to load source info".to_string()];
-        }
+        let controller = Controller::new(DebuggerModel::new(shucks_client), ViewState::default());
 
-        if let Ok(signals) = model.fetch_signal_snapshot() {
-            view_state.signal_lines = signals.lines;
-        } else {
-            view_state.signal_lines = vec!["Failed to load signal info".to_string()];
-        }
+        Ok(App::from_parts(
+            cli_args, controller, tasks, exit_rx, log_buffer, redraw,
+        ))
+    }
 
+    /// Everything past startup, without touching the logger or the terminal
+    fn from_parts(
+        cli_args: cli::JpdbArgs,
+        mut controller: Controller,
+        tasks: TaskRegistry,
+        dang_exit: mpsc::Receiver<DangExit>,
+        log_buffer: Arc<Mutex<LogBuffer>>,
+        redraw: RedrawFlag,
+    ) -> App {
+        let notifications = Notifications::default();
+        controller.notifier = notifications.notifier();
         App {
             should_quit: false,
+            quit_signal: QuitSignal::default(),
             input_buffer: String::new(),
             command_history: Vec::new(),
-            model,
-            view_state,
-            _dang_thread_handle: dang_handle,
+            controller,
+            tasks,
+            dang_exit,
+            dang_outcome: None,
             scroll_offset: 0,
-            show_debug_panel: false,
             debug_scroll_offset: 0, // Initialize debug scroll offset
-            show_split_view: true,
             log_buffer,
+            notifications,
+            banner_drawn: None,
+            debug_min_level: log::Level::Trace,
             last_command: None,
             user_command_history: Vec::new(),
             history_index: None,
@@ -337,204 +474,314 @@ impl App {
             wcp_client: None,
             surfer_process: None,
             cli_args,
+            redraw,
+            aliases: AliasTable::default(),
+            completer: Completer::default(),
+            completion: None,
+            confirmation: None,
+            running_script: false,
+            timeline_area: None,
+            started: Instant::now(),
+            session_restore_failed: false,
         }
     }
 
+    /// Draws after every key, and otherwise only when the redraw flag is set, checked every
+    /// [`REDRAW_POLL_INTERVAL`]. Idle, nothing is drawn at all.
+    ///
+    /// To check it by hand, open the debug panel (`debug`): anything logged from another thread,
+    /// like dang's stub thread, shows up within a poll interval without a keypress. Left alone,
+    /// jpdb should sit near 0% CPU
     fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> io::Result<()> {
+        let mut key_pressed = true;
         loop {
-            terminal.draw(|f| self.ui(f))?;
-
-            if let Event::Key(key) = event::read()? {
-                // Check if we're in help modal mode first
-                if self.help_modal_state.is_active() {
-                    match key.code {
-                        KeyCode::Up => {
-                            self.help_modal_state.scroll_up(1);
-                        }
-                        KeyCode::Down => {
-                            self.help_modal_state.scroll_down(1);
-                        }
-                        KeyCode::PageUp => {
-                            self.help_modal_state.scroll_up(5);
-                        }
-                        KeyCode::PageDown => {
-                            self.help_modal_state.scroll_down(5);
-                        }
-                        KeyCode::Home => {
-                            // Scroll to top
-                            let content_len = self.help_modal_state.get_content().len();
-                            self.help_modal_state.scroll_up(content_len);
-                        }
-                        KeyCode::End => {
-                            // Scroll to bottom
-                            self.help_modal_state.scroll_down(usize::MAX);
-                        }
-                        KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {
-                            // Close help modal
-                            self.help_modal_state.deactivate();
-                        }
-                        _ => {} // Ignore other keys in help modal mode
+            if self.quit_signal.is_set() {
+                log::info!("Quitting on a signal");
+                break;
+            }
+            self.poll_dang_exit();
+            self.poll_notifications();
+            self.expire_confirmation(Instant::now());
+            // The banner changed or timed out since it was drawn
+            let banner = self.notifications.banner(Instant::now());
+            if banner.map(|n| n.timestamp) != self.banner_drawn {
+                self.redraw.mark();
+            }
+            // Clear the flag before drawing, anything that comes in mid-draw gets its own redraw
+            let redraw_requested = self.redraw.take();
+            if key_pressed || redraw_requested {
+                terminal.draw(|f| self.ui(f))?;
+            }
+            key_pressed = false;
+            // Markers the frame went without get looked up now and drawn with the next one
+            if self.controller.fill_code_markers() {
+                self.redraw.mark();
+            }
+            // The Instructions pane was laid out at a new size, fill it
+            if self.controller.fit_instruction_window() {
+                self.redraw.mark();
+            }
+
+            if !event::poll(REDRAW_POLL_INTERVAL)? {
+                // A whole poll interval without input, time to get the signal values around
+                // the cursor ready. Never done before a draw, so it can't hold one up
+                self.controller.model.prefetch_signals();
+                continue;
+            }
+            key_pressed = true;
+
+            let event = event::read()?;
+            if let Event::Resize(..) = event {
+                // Pick the new size up now, so the next frame is laid out for it
+                terminal.autoresize()?;
+                continue;
+            }
+            if let Event::Key(key) = event {
+                // A late answer shouldn't reach a prompt that's already timed out
+                self.expire_confirmation(Instant::now());
+                self.handle_key(key);
+            }
+            if let Event::Mouse(mouse) = event {
+                self.handle_mouse(mouse);
+            }
+
+            if self.should_quit {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// A left click on the timeline moves execution to that spot in the trace
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let MouseEventKind::Down(MouseButton::Left) = mouse.kind else {
+            return;
+        };
+        let Some(area) = self.timeline_area else {
+            return;
+        };
+        if mouse.row != area.y || mouse.column < area.x || mouse.column >= area.x + area.width {
+            return;
+        }
+        let idx = self
+            .controller
+            .view_state
+            .timeline
+            .index_at_column((mouse.column - area.x) as usize, area.width as usize);
+        if let Err(e) = self.goto_time_idx(idx) {
+            self.command_history.push(format!("Error: {e}"));
+        }
+    }
+
+    /// Everything a keypress does, split out of [`App::run`] so tests can drive it with
+    /// synthetic events
+    fn handle_key(&mut self, key: KeyEvent) {
+        // Any key takes the banner down, and still does what it does
+        self.notifications.dismiss(Instant::now());
+        // Check if we're in help modal mode first
+        if self.help_modal_state.is_active() {
+            match key.code {
+                KeyCode::Up => {
+                    self.help_modal_state.scroll_up(1);
+                }
+                KeyCode::Down => {
+                    self.help_modal_state.scroll_down(1);
+                }
+                KeyCode::PageUp => {
+                    self.help_modal_state.scroll_up(5);
+                }
+                KeyCode::PageDown => {
+                    self.help_modal_state.scroll_down(5);
+                }
+                KeyCode::Home => {
+                    // Scroll to top
+                    let content_len = self.help_modal_state.get_content().len();
+                    self.help_modal_state.scroll_up(content_len);
+                }
+                KeyCode::End => {
+                    // Scroll to bottom
+                    self.help_modal_state.scroll_down(usize::MAX);
+                }
+                KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {
+                    // Close help modal
+                    self.help_modal_state.deactivate();
+                }
+                _ => {} // Ignore other keys in help modal mode
+            }
+        } else if self.addsig_state.is_active() {
+            // Check if we're in addsig mode
+            match key.code {
+                KeyCode::Char(c) => {
+                    // Add character to search input
+                    let mut new_input = self.addsig_state.get_input().to_string();
+                    new_input.push(c);
+                    self.addsig_state.update_search(new_input);
+
+                    // Update fuzzy matches via model
+                    let matches = self
+                        .controller
+                        .model
+                        .fuzzy_match_signals(self.addsig_state.get_input());
+                    self.addsig_state.set_matches(matches);
+                }
+                KeyCode::Backspace => {
+                    // Remove character from search input
+                    let mut new_input = self.addsig_state.get_input().to_string();
+                    new_input.pop();
+                    self.addsig_state.update_search(new_input);
+
+                    // Update fuzzy matches via model
+                    let matches = self
+                        .controller
+                        .model
+                        .fuzzy_match_signals(self.addsig_state.get_input());
+                    self.addsig_state.set_matches(matches);
+                }
+                KeyCode::Up => {
+                    self.addsig_state.select_prev();
+                }
+                KeyCode::Down => {
+                    self.addsig_state.select_next();
+                }
+                KeyCode::Enter => {
+                    // Select the signal and exit addsig mode
+                    if let Some((var, _)) = self.addsig_state.get_selected().cloned() {
+                        self.select_signal(var);
                     }
-                } else if self.addsig_state.is_active() {
-                    // Check if we're in addsig mode
-                    match key.code {
-                        KeyCode::Char(c) => {
-                            // Add character to search input
-                            let mut new_input = self.addsig_state.get_input().to_string();
-                            new_input.push(c);
-                            self.addsig_state.update_search(new_input);
-
-                            // Update fuzzy matches via model
-                            let matches = self
-                                .model
-                                .fuzzy_match_signals(self.addsig_state.get_input());
-                            self.addsig_state.set_matches(matches);
-                        }
-                        KeyCode::Backspace => {
-                            // Remove character from search input
-                            let mut new_input = self.addsig_state.get_input().to_string();
-                            new_input.pop();
-                            self.addsig_state.update_search(new_input);
-
-                            // Update fuzzy matches via model
-                            let matches = self
-                                .model
-                                .fuzzy_match_signals(self.addsig_state.get_input());
-                            self.addsig_state.set_matches(matches);
-                        }
-                        KeyCode::Up => {
-                            self.addsig_state.select_prev();
-                        }
-                        KeyCode::Down => {
-                            self.addsig_state.select_next();
-                        }
-                        KeyCode::Enter => {
-                            // Select the signal and exit addsig mode
-                            if let Some((var, _)) = self.addsig_state.get_selected().cloned() {
-                                self.model.select_signal(var);
-                                if let Some(ref mut wcp) = self.wcp_client {
-                                    if let Some(path) = self.model.most_recent_var_path() {
-                                        let _ = wcp.add_signal(path.as_str());
-                                    }
-                                }
-                                self.refresh_signal_view();
-                            }
-                            self.addsig_state.deactivate();
-                        }
-                        KeyCode::Esc => {
-                            // Exit addsig mode without selection
-                            self.addsig_state.deactivate();
-                        }
-                        _ => {} // Ignore other keys in addsig mode
+                    self.addsig_state.deactivate();
+                }
+                KeyCode::Esc => {
+                    // Exit addsig mode without selection
+                    self.addsig_state.deactivate();
+                }
+                _ => {} // Ignore other keys in addsig mode
+            }
+        } else if self.confirmation.is_some() {
+            let confirmed = matches!(key.code, KeyCode::Char('y' | 'Y') | KeyCode::Enter);
+            self.answer_confirmation(confirmed);
+        } else {
+            // Any key but Tab closes the completion picker, Esc also undoes it
+            if key.code != KeyCode::Tab {
+                if let Some(state) = self.completion.take() {
+                    if key.code == KeyCode::Esc {
+                        self.input_buffer = state.original;
+                        return;
                     }
-                } else {
-                    // Normal key handling when not in addsig mode
-                    match key.code {
-                        KeyCode::Char('d')
-                            if key.modifiers.contains(event::KeyModifiers::CONTROL) =>
-                        {
-                            // Ctrl+D: Quit the application
-                            self.should_quit = true;
-                        }
-                        KeyCode::Char('l')
-                            if key.modifiers.contains(event::KeyModifiers::CONTROL) =>
-                        {
-                            // Ctrl+L: Clear screen
-                            self.command_history.clear();
-                            self.scroll_offset = 0;
-                        }
+                }
+            }
 
-                        // Debug panel scrolling (only when debug panel is visible)
-                        KeyCode::PageUp if self.show_debug_panel => {
-                            // Scroll up in debug panel
-                            self.debug_scroll_offset = self.debug_scroll_offset.saturating_add(5);
-                        }
-                        KeyCode::PageDown if self.show_debug_panel => {
-                            // Scroll down in debug panel
-                            self.debug_scroll_offset = self.debug_scroll_offset.saturating_sub(5);
-                        }
-                        KeyCode::Home if self.show_debug_panel => {
-                            // Go to top of debug panel
-                            if let Ok(buffer) = self.log_buffer.lock() {
-                                self.debug_scroll_offset = buffer.len().saturating_sub(1);
-                            }
-                        }
-                        KeyCode::End if self.show_debug_panel => {
-                            // Go to bottom of debug panel
-                            self.debug_scroll_offset = 0;
-                        }
+            // Normal key handling when not in addsig mode
+            match key.code {
+                KeyCode::Char('d') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                    // Ctrl+D: Quit the application
+                    self.should_quit = true;
+                }
+                KeyCode::Char('l') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                    // Ctrl+L: Clear screen
+                    self.command_history.clear();
+                    self.scroll_offset = 0;
+                }
 
-                        KeyCode::Char(c) => {
-                            self.input_buffer.push(c);
-                            // Reset history navigation when user types
-                            self.history_index = None;
-                        }
-                        KeyCode::Enter => {
-                            self.process_command();
-                            self.input_buffer.clear();
-                            // Auto-scroll to bottom when new command is entered
-                            self.scroll_offset = 0;
-                        }
-                        KeyCode::Backspace => {
-                            self.input_buffer.pop();
-                            // Reset history navigation when user modifies input
-                            self.history_index = None;
-                        }
-                        KeyCode::Up => {
-                            // Navigate to previous command in history
-                            if !self.user_command_history.is_empty() {
-                                let new_index = match self.history_index {
-                                    None => self.user_command_history.len() - 1,
-                                    Some(index) => {
-                                        if index > 0 {
-                                            index - 1
-                                        } else {
-                                            // Wrap to newest (end of history)
-                                            self.user_command_history.len() - 1
-                                        }
-                                    }
-                                };
-                                self.history_index = Some(new_index);
-                                self.input_buffer = self.user_command_history[new_index].clone();
+                // Debug panel scrolling (only when debug panel is visible)
+                KeyCode::PageUp if self.controller.view_state.show_debug_panel => {
+                    // Scroll up in debug panel
+                    self.debug_scroll_offset = self.debug_scroll_offset.saturating_add(5);
+                }
+                KeyCode::PageDown if self.controller.view_state.show_debug_panel => {
+                    // Scroll down in debug panel
+                    self.debug_scroll_offset = self.debug_scroll_offset.saturating_sub(5);
+                }
+                KeyCode::Home if self.controller.view_state.show_debug_panel => {
+                    // Go to top of debug panel
+                    if let Ok(buffer) = self.log_buffer.lock() {
+                        self.debug_scroll_offset =
+                            buffer.count_at(self.debug_min_level).saturating_sub(1);
+                    }
+                }
+                KeyCode::End if self.controller.view_state.show_debug_panel => {
+                    // Go to bottom of debug panel
+                    self.debug_scroll_offset = 0;
+                }
+                KeyCode::Char('f')
+                    if key.modifiers.contains(event::KeyModifiers::CONTROL)
+                        && self.controller.view_state.show_debug_panel =>
+                {
+                    // Ctrl+F: hide the least severe level shown, round to everything after errors
+                    self.debug_min_level = log_buffer::next_min_level(self.debug_min_level);
+                    self.debug_scroll_offset = 0;
+                }
+
+                KeyCode::Left if key.modifiers.contains(event::KeyModifiers::SHIFT) => {
+                    self.scrub_signals(-1);
+                }
+                KeyCode::Right if key.modifiers.contains(event::KeyModifiers::SHIFT) => {
+                    self.scrub_signals(1);
+                }
+                KeyCode::Tab => self.complete(),
+                KeyCode::Esc => self.controller.view_state.dual_context.dismiss(),
+                KeyCode::Char(c) => {
+                    self.input_buffer.push(c);
+                    // Reset history navigation when user types
+                    self.history_index = None;
+                }
+                KeyCode::Enter => {
+                    self.process_command();
+                    self.input_buffer.clear();
+                    // Auto-scroll to bottom when new command is entered
+                    self.scroll_offset = 0;
+                }
+                KeyCode::Backspace => {
+                    self.input_buffer.pop();
+                    // Reset history navigation when user modifies input
+                    self.history_index = None;
+                }
+                KeyCode::Up => {
+                    // Navigate to previous command in history
+                    if !self.user_command_history.is_empty() {
+                        let new_index = match self.history_index {
+                            None => self.user_command_history.len() - 1,
+                            Some(index) => {
+                                if index > 0 {
+                                    index - 1
+                                } else {
+                                    // Wrap to newest (end of history)
+                                    self.user_command_history.len() - 1
+                                }
                             }
-                        }
-                        KeyCode::Down => {
-                            // Navigate to next (more recent) command in history
-                            if !self.user_command_history.is_empty() {
-                                match self.history_index {
-                                    None => {
-                                        // Do nothing if not currently navigating history
-                                    }
-                                    Some(index) => {
-                                        if index < self.user_command_history.len() - 1 {
-                                            let new_index = index + 1;
-                                            self.history_index = Some(new_index);
-                                            self.input_buffer =
-                                                self.user_command_history[new_index].clone();
-                                        } else {
-                                            // Wrap to oldest (beginning of history)
-                                            self.history_index = Some(0);
-                                            self.input_buffer =
-                                                self.user_command_history[0].clone();
-                                        }
-                                    }
+                        };
+                        self.history_index = Some(new_index);
+                        self.input_buffer = self.user_command_history[new_index].clone();
+                    }
+                }
+                KeyCode::Down => {
+                    // Navigate to next (more recent) command in history
+                    if !self.user_command_history.is_empty() {
+                        match self.history_index {
+                            None => {
+                                // Do nothing if not currently navigating history
+                            }
+                            Some(index) => {
+                                if index < self.user_command_history.len() - 1 {
+                                    let new_index = index + 1;
+                                    self.history_index = Some(new_index);
+                                    self.input_buffer =
+                                        self.user_command_history[new_index].clone();
+                                } else {
+                                    // Wrap to oldest (beginning of history)
+                                    self.history_index = Some(0);
+                                    self.input_buffer = self.user_command_history[0].clone();
                                 }
                             }
                         }
-                        _ => {}
                     }
                 }
-            }
-
-            if self.should_quit {
-                break;
+                _ => {}
             }
         }
-        Ok(())
     }
 
     pub fn step_next(&mut self) {
-        if let Err(e) = self.model.step() {
+        if let Err(e) = self.controller.model.step() {
             self.command_history.push(format!("Error stepping: {e}"));
             return;
         }
@@ -581,6 +828,16 @@ impl App {
             input
         };
 
+        if let Err(error) = self.execute_line(&command_to_execute) {
+            self.command_history.push(format!("error: {error}"));
+        }
+    }
+
+    /// Expand aliases in `line` and run it. Anything that needs confirming is left waiting in
+    /// [`App::confirmation_prompt`]
+    fn execute_line(&mut self, line: &str) -> Result<(), String> {
+        let command_to_execute = self.aliases.expand(line)?;
+
         // Parse command and arguments
         let parts: Vec<&str> = command_to_execute.splitn(2, ' ').collect();
         let command_name = parts[0];
@@ -588,119 +845,1011 @@ impl App {
 
         // Execute command using registry
         let registry = CommandRegistry::new();
-        if let Err(error) = registry.execute_command(command_name, args, self) {
-            self.command_history.push(format!("error: {error}"));
+        match registry.execute_command(command_name, args, self)? {
+            CommandOutcome::Done => {}
+            CommandOutcome::NeedsConfirmation { prompt, on_confirm } => {
+                self.confirmation = Some(PendingConfirmation {
+                    prompt,
+                    on_confirm,
+                    asked_at: Instant::now(),
+                });
+            }
         }
+        Ok(())
     }
 
-    fn refresh_all_views(&mut self) {
-        if let Ok(execution) = self.model.fetch_execution_snapshot() {
-            self.view_state.execution_lines = execution.summary_lines;
-            self.view_state.instruction_lines = execution.instruction_lines;
-        } else {
-            self.view_state.execution_lines = vec!["Failed to load execution info".to_string()];
-            self.view_state.instruction_lines = vec!["Failed to load execution info".to_string()];
-        }
-
-        if let Ok(source) = self.model.fetch_source_snapshot() {
-            self.view_state.source_lines = source.lines;
-        } else {
-            self.view_state.source_lines = vec!["Failed to load source info".to_string()];
-        }
+    /// The `.jpdbinit` next to the wave file when `--allow-local-init` lets it run, then
+    /// `--init-commands`
+    fn init_scripts(&self) -> Vec<PathBuf> {
+        let local = self
+            .cli_args
+            .allow_local_init
+            .then(|| local_init_path(&self.cli_args.wave_path))
+            .filter(|path| path.is_file());
+        local
+            .into_iter()
+            .chain(self.cli_args.init_commands.clone())
+            .collect()
+    }
 
-        if let Ok(signals) = self.model.fetch_signal_snapshot() {
-            self.view_state.signal_lines = signals.lines;
-        } else {
-            self.view_state.signal_lines = vec!["Failed to load signal info".to_string()];
+    /// Run the startup scripts before the first prompt, stopping at the first one that fails
+    pub fn run_init_scripts(&mut self) {
+        for path in self.init_scripts() {
+            if let Err(error) = self.run_init_script(&path) {
+                self.command_history.push(format!("error: {error}"));
+                return;
+            }
         }
     }
 
-    fn refresh_signal_view(&mut self) {
-        match self.model.fetch_signal_snapshot() {
-            Ok(snapshot) => self.view_state.signal_lines = snapshot.lines,
-            Err(err) => {
-                self.view_state.signal_lines = vec![format!("Error getting signal info: {err}")];
+    /// Run each line of `path` like a typed command, echoed into the command history. Blank
+    /// lines and `#` comments are skipped. Nothing modal opens, commands that need it behave as
+    /// in `--ui simple`, and the first command that fails stops the rest of the script
+    fn run_init_script(&mut self, path: &Path) -> Result<(), String> {
+        let script = std::fs::read_to_string(path)
+            .map_err(|e| format!("could not read init script {}: {e}", path.display()))?;
+
+        self.running_script = true;
+        let mut result = Ok(());
+        for (number, line) in script.lines().enumerate().map(|(i, l)| (i + 1, l.trim())) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            self.command_history.push(format!("(jpdb) {line}"));
+            let outcome = self
+                .execute_line(line)
+                .and_then(|()| match self.confirmation.take() {
+                    Some(pending) => Err(format!("{} (confirm it interactively)", pending.prompt)),
+                    None => Ok(()),
+                });
+            if let Err(error) = outcome {
+                self.command_history.push(format!("error: {error}"));
+                result = Err(format!(
+                    "{}:{number} failed, skipped the rest of the script",
+                    path.display()
+                ));
+                break;
             }
         }
+        self.running_script = false;
+        result
     }
 
-    pub fn set_breakpoint(&mut self, address: u32) -> Result<(), String> {
-        self.model.set_breakpoint(address)
+    /// The question a command is waiting on, if any
+    pub fn confirmation_prompt(&self) -> Option<&str> {
+        self.confirmation.as_ref().map(|c| c.prompt.as_str())
     }
 
-    pub fn set_breakpoint_at_line(&mut self, file: &str, line: u64) -> Result<Vec<u32>, String> {
-        self.model.set_breakpoint_at_line(file, line)
+    /// Run or drop the command waiting on confirmation
+    pub fn answer_confirmation(&mut self, confirmed: bool) {
+        let Some(pending) = self.confirmation.take() else {
+            return;
+        };
+        if !confirmed {
+            self.command_history.push("Cancelled".to_string());
+        } else if let Err(error) = (pending.on_confirm)(self) {
+            self.command_history.push(format!("error: {error}"));
+        }
     }
 
-    pub fn continue_execution(&mut self) -> Result<(), String> {
-        self.model.continue_execution()?;
+    /// Drop a confirmation that's gone unanswered for [`CONFIRM_TIMEOUT`] as of `now`
+    fn expire_confirmation(&mut self, now: Instant) {
+        if self
+            .confirmation
+            .as_ref()
+            .is_some_and(|c| now.duration_since(c.asked_at) >= CONFIRM_TIMEOUT)
+        {
+            self.confirmation = None;
+            self.command_history
+                .push("Cancelled, no answer in time".to_string());
+            self.scroll_offset = 0;
+            self.redraw.mark();
+        }
+    }
 
-        // Sync waveform position if connected to Surfer
-        if let Err(e) = self.sync_waveform_position() {
-            log::warn!("Failed to sync waveform position: {e}");
+    /// Tab: finish the word at the end of the input. A unique candidate goes straight in and
+    /// several extend to whatever they share. When that adds nothing, a picker opens on the first
+    /// candidate and every further Tab moves to the next one
+    fn complete(&mut self) {
+        if let Some(state) = &mut self.completion {
+            state.picker.select_next();
+            if let Some((replacement, _)) = state.picker.selected() {
+                self.input_buffer.truncate(state.start);
+                self.input_buffer.push_str(replacement);
+            }
+            return;
         }
 
-        Ok(())
+        let cursor = self.input_buffer.len();
+        let Completion { start, candidates } = self.completer.complete(
+            &self.input_buffer,
+            cursor,
+            &mut self.controller.model,
+            &self.aliases,
+        );
+        let word = self.input_buffer[start..].to_string();
+        match candidates.as_slice() {
+            [] => {}
+            [only] => {
+                self.input_buffer.truncate(start);
+                self.input_buffer.push_str(&only.replacement);
+                // `file:` is waiting for a line number
+                if !only.replacement.ends_with(':') {
+                    self.input_buffer.push(' ');
+                }
+            }
+            _ => {
+                let prefix =
+                    common_prefix(candidates.iter().map(|c| c.replacement.as_str())).to_string();
+                if prefix.len() > word.len() && prefix.starts_with(&word) {
+                    self.input_buffer.truncate(start);
+                    self.input_buffer.push_str(&prefix);
+                    return;
+                }
+                let original = self.input_buffer.clone();
+                let picker = Picker::new(
+                    candidates
+                        .into_iter()
+                        .map(|c| (c.replacement, c.label))
+                        .collect(),
+                );
+                if let Some((replacement, _)) = picker.selected() {
+                    self.input_buffer.truncate(start);
+                    self.input_buffer.push_str(replacement);
+                }
+                self.completion = Some(CompletionState {
+                    start,
+                    original,
+                    picker,
+                });
+            }
+        }
     }
 
-    pub fn invalidate_time_idx_cache(&mut self) {
-        self.model.invalidate_time_index();
+    fn refresh_all_views(&mut self) {
+        self.warn_if_elf_changed();
+        self.controller.refresh_all();
     }
 
-    /// Launch Surfer waveform viewer and connect to it via WCP
-    pub fn launch_surfer(&mut self, wave_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-        use std::process::{Command, Stdio};
-
-        //TODO: get a random port, i am lazy
-        let wcp_port = 54321;
-        let mut tmp_script = std::env::temp_dir();
-        tmp_script.push("surfer_commands.sucl");
-
-        std::fs::write(tmp_script.as_path(), "wcp_server_start")?;
+    /// Symbol and line lookups are about to happen, say so if they'd come from a stale ELF
+    fn warn_if_elf_changed(&mut self) {
+        if let Some(warning) = self.controller.model.check_elf() {
+            self.notifications
+                .push(Severity::Warning, "elfwatch", warning);
+        }
+    }
 
-        let child = Command::new("surfer")
-            .arg(wave_path.to_str().ok_or("Invalid wave path")?)
-            .arg("--script")
-            .arg(
-                tmp_script
-                    .as_os_str()
-                    .to_str()
-                    .ok_or("Invalid script path")?,
-            )
-            .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()?;
-        log::info!("Launched Surfer with PID {}", child.id());
+    /// Checked before the things that read a lot of the waveform: continues, monitor commands
+    /// such as `coverage`, and `sigstats`
+    fn warn_if_wave_changed(&mut self) {
+        if let Some(warning) = self.controller.model.check_wave() {
+            self.notifications
+                .push(Severity::Warning, "wavewatch", warning);
+        }
+    }
 
-        self.surfer_process = Some(child);
+    pub fn set_split_view(&mut self, show: bool) {
+        self.controller.set_split_view(show);
+    }
 
-        // Give Surfer time to start
-        std::thread::sleep(std::time::Duration::from_millis(1000));
+    /// Pick up how the dang thread ended, if it has. Anything but a clean end is shown as an
+    /// error, otherwise the only symptom would be every command timing out
+    fn poll_dang_exit(&mut self) {
+        let Ok(exit) = self.dang_exit.try_recv() else {
+            return;
+        };
+        match exit {
+            Ok(outcome) => {
+                if !outcome.is_clean() {
+                    self.notifications.push(
+                        Severity::Error,
+                        "dang",
+                        format!("dang stopped: {outcome}"),
+                    );
+                }
+                self.dang_outcome = Some(outcome);
+            }
+            Err(e) => {
+                self.notifications
+                    .push(Severity::Error, "dang", format!("dang stopped: {e}"))
+            }
+        }
+        self.redraw.mark();
+    }
 
-        // Connect to Surfer via WCP
-        self.connect_to_surfer(&format!("127.0.0.1:{wcp_port}"))?;
+    /// Pick up notifications pushed since the last look. Line mode has no banner, so there
+    /// they go in the command history as well
+    fn poll_notifications(&mut self) {
+        let new = self.notifications.poll();
+        if new.is_empty() {
+            return;
+        }
+        if self.line_mode() {
+            self.command_history
+                .extend(new.iter().map(view::notification_line));
+        }
+        self.redraw.mark();
+    }
 
-        Ok(())
+    /// Every notification so far, for the `notifications` command
+    pub fn notification_lines(&self) -> Vec<String> {
+        view::notification_lines(self.notifications.history(), Instant::now())
     }
 
-    /// Connect to a running Surfer instance via WCP
-    pub fn connect_to_surfer(&mut self, addr: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let client = WcpClient::connect(addr)?;
-        self.wcp_client = Some(client);
-        log::info!("Connected to Surfer via WCP at {addr}");
+    /// Least severe notification the banner shows
+    pub fn set_banner_min(&mut self, severity: Severity) {
+        self.notifications.banner_min = severity;
+        self.redraw.mark();
+    }
 
-        // Sync current waveform state
-        self.sync_waveform_position()?;
+    /// How long a notification stays in the banner
+    pub fn set_banner_timeout(&mut self, timeout: Duration) {
+        self.notifications.banner_timeout = timeout;
+        self.redraw.mark();
+    }
 
-        Ok(())
+    /// Running with `--ui simple` or through a startup script, where nothing modal can be shown
+    pub fn line_mode(&self) -> bool {
+        self.cli_args.ui == cli::UiMode::Simple || self.running_script
     }
 
-    /// Sync the waveform viewer to the current simulation time
+    /// Show `lines` in the help modal, or straight in the command history in line mode
+    pub fn show_modal(&mut self, lines: Vec<String>) {
+        if self.line_mode() {
+            self.command_history.extend(lines);
+        } else {
+            self.help_modal_state.activate(lines);
+        }
+    }
+
+    /// The `sigstats` table. Counted between the `sigtime` pin and execution when the Signals
+    /// pane is pinned, unless `whole_trace` says otherwise
+    pub fn signal_stats_lines(&mut self, whole_trace: bool) -> Result<Vec<String>, String> {
+        self.warn_if_wave_changed();
+        let pin = match whole_trace {
+            true => None,
+            false => self.controller.signal_pin(),
+        };
+        let (window, rows) = self.controller.model.signal_stats(pin)?;
+        Ok(view::sigstats_lines(
+            &rows,
+            window,
+            self.controller.model.time_unit(),
+        ))
+    }
+
+    /// The `sigchanged` listing, for the last step or continue
+    pub fn signal_changes_lines(&mut self) -> Result<Vec<String>, String> {
+        let (window, rows) = self.controller.model.signal_changes()?;
+        Ok(view::sigchanged_lines(window, &rows))
+    }
+
+    /// Show command output split into lines, in the command history if it's short and in the
+    /// modal if not, see [`view::output_route`]
+    pub fn show_output(&mut self, output: &str) {
+        let lines: Vec<String> = output.lines().map(str::to_string).collect();
+        match view::output_route(output) {
+            view::OutputRoute::History => self.command_history.extend(lines),
+            view::OutputRoute::Modal => self.show_modal(lines),
+        }
+    }
+
+    /// A pane's contents as plain text, fetched now whether or not it's on screen
+    pub fn pane_lines(&mut self, pane: Pane) -> Result<Vec<String>, String> {
+        let model = &mut self.controller.model;
+        let addr_fmt = model.addr_fmt;
+        Ok(match pane {
+            Pane::Execution => {
+                let snapshot = model.fetch_execution_snapshot()?;
+                view::execution_lines(&snapshot, &addr_fmt, &model.client)
+            }
+            Pane::Source => view::source_lines(&model.fetch_source_snapshot()?),
+            Pane::Signals => {
+                view::signal_lines(&model.fetch_signal_snapshot(self.controller.signal_pin())?)
+            }
+        })
+    }
+
+    /// Add the best fuzzy match for `query` to the signal pane, without the popup
+    pub fn add_signal_matching(&mut self, query: &str) -> Result<String, String> {
+        if !self.controller.model.has_waveform() {
+            return Err("No waveform loaded".to_string());
+        }
+        let (var, name) = self
+            .controller
+            .model
+            .fuzzy_match_signals(query)
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("No signal matches '{query}'"))?;
+        self.select_signal(var);
+        Ok(name)
+    }
+
+    fn select_signal(&mut self, var: Var) {
+        self.controller.model.select_signal(var);
+        if let Some(ref mut wcp) = self.wcp_client {
+            if let Some(path) = self.controller.model.most_recent_var_path() {
+                let _ = wcp.add_signal(path.as_str());
+            }
+        }
+        self.refresh_signal_view();
+    }
+
+    pub fn set_debug_panel(&mut self, show: bool) {
+        self.controller.set_debug_panel(show);
+    }
+
+    /// Plain text report of the whole session for `dump state`, built from cached state only
+    pub fn state_report(&self) -> String {
+        let log_lines: Vec<String> = match self.log_buffer.lock() {
+            Ok(buffer) => buffer.last_lines(dump::DUMP_LOG_LINES),
+            Err(_) => Vec::new(),
+        };
+        dump::state_report(&dump::DumpSources {
+            cli_args: &self.cli_args,
+            controller: &self.controller,
+            command_history: &self.command_history,
+            log_lines: &log_lines,
+            tasks: &self.tasks.lines(),
+        })
+    }
+
+    /// The session as markdown for `export md`, built from cached state only like
+    /// [`App::state_report`]
+    pub fn markdown_transcript(&self, redactions: &[export_md::Redaction]) -> String {
+        let notifications: Vec<String> = self
+            .notifications
+            .history()
+            .map(|notification| {
+                let at = notification
+                    .timestamp
+                    .saturating_duration_since(self.started);
+                format!(
+                    "+{} [{}] {}",
+                    export_md::format_duration(at),
+                    notification.source,
+                    view::notification_line(notification)
+                )
+            })
+            .collect();
+        let view_state = &self.controller.view_state;
+        export_md::render(
+            &export_md::TranscriptSources {
+                elf: &self.cli_args.elf,
+                wave: &self.cli_args.wave_path,
+                mapping: &self.cli_args.mapping_path,
+                duration: self.started.elapsed(),
+                command_history: &self.command_history,
+                notifications: &notifications,
+                execution: &view_state.execution_lines,
+                source: &view_state.source_lines,
+                breakpoints: &self.breakpoint_lines(),
+            },
+            redactions,
+        )
+    }
+
+    pub fn set_stop_on_livelock(&mut self, stop: bool) -> Result<(), String> {
+        self.controller.model.set_stop_on_livelock(stop)
+    }
+
+    pub fn stopped_on_livelock(&self) -> bool {
+        self.controller.model.stopped_on_livelock()
+    }
+
+    pub fn stop_report(&mut self) -> Result<StopReport, String> {
+        self.controller.model.stop_report()
+    }
+
+    pub fn stopped_on_assertion(&self) -> bool {
+        self.controller.model.stopped_on_assertion()
+    }
+
+    pub fn assert_reg(&mut self, reg: usize, op: &str, value: u32) -> Result<Assertion, String> {
+        self.controller.model.assert_reg(reg, op, value)
+    }
+
+    pub fn assert_pc_range(&mut self, lo: u32, hi: u32, inside: bool) -> Result<Assertion, String> {
+        self.controller.model.assert_pc_range(lo, hi, inside)
+    }
+
+    pub fn delete_assertion(&mut self, id: u32) -> Result<(), String> {
+        self.controller.model.delete_assertion(id)
+    }
+
+    pub fn add_skip_range(&mut self, target: &SkipTarget) -> Result<SkipRange, String> {
+        self.controller.model.add_skip_range(target)
+    }
+
+    pub fn monitor(&mut self, cmd: &str) -> Result<String, String> {
+        self.warn_if_wave_changed();
+        self.controller.model.monitor(cmd)
+    }
+
+    pub fn reg_history(&mut self, reg: usize) -> Result<Vec<(u64, u32)>, String> {
+        self.controller.model.reg_history(reg)
+    }
+
+    pub fn skip_ranges(&mut self) -> Result<Vec<SkipRange>, String> {
+        self.controller.model.skip_ranges()
+    }
+
+    pub fn delete_skip_range(&mut self, id: u32) -> Result<(), String> {
+        self.controller.model.delete_skip_range(id)
+    }
+
+    pub fn set_fastforward_ranges(&mut self, on: bool) -> Result<(), String> {
+        self.controller.model.set_fastforward_ranges(on)
+    }
+
+    pub fn breakpoint_count(&self) -> usize {
+        self.controller.model.breakpoints().iter().count()
+    }
+
+    /// The breakpoint table as a gdb command file, for `bexport`
+    pub fn export_breakpoints(&self) -> String {
+        let model = &self.controller.model;
+        breakpoints::export_gdb(model.breakpoints(), &model.addr_fmt)
+    }
+
+    /// What `session save` writes: the selected signals, the breakpoints as `bexport` lines and
+    /// the prompt's history
+    fn session_snapshot(&self) -> Session {
+        Session {
+            signals: self.controller.model.selected_signals(),
+            breakpoints: self
+                .export_breakpoints()
+                .lines()
+                .filter(|line| matches!(breakpoints::parse_gdb_line(line), Ok(Some(_))))
+                .map(str::to_string)
+                .collect(),
+            history: self.user_command_history.clone(),
+        }
+    }
+
+    /// Write the session to `path`, and say what went into it
+    pub fn save_session(&self, path: &Path) -> Result<String, String> {
+        let session = self.session_snapshot();
+        session.save(path)?;
+        Ok(format!(
+            "Saved {} signals, {} breakpoints and {} history entries to {}",
+            session.signals.len(),
+            session.breakpoints.len(),
+            session.history.len().min(session::MAX_SAVED_HISTORY),
+            path.display()
+        ))
+    }
+
+    /// Bring back the session saved in `path` on top of this one. Signals that aren't in this
+    /// waveform and breakpoints that can't be set are skipped, with a warning each in what's
+    /// returned
+    pub fn load_session(&mut self, path: &Path) -> Result<Vec<String>, String> {
+        let session = Session::load(path)?;
+        let mut report = Vec::new();
+
+        let mut vars = Vec::new();
+        let mut found = Vec::new();
+        for (name, format) in &session.signals {
+            match self.controller.model.find_signal(name) {
+                Some(var) => {
+                    vars.push(var);
+                    found.push((name, *format));
+                }
+                None => report.push(format!("warning: {name} isn't in this waveform, skipped")),
+            }
+        }
+        let signals = found.len();
+        self.controller.model.select_signals(vars);
+        for (name, format) in found {
+            if let Err(e) = self.controller.model.set_signal_format(name, format) {
+                report.push(format!("warning: {name}: {e}, left in hex"));
+            }
+        }
+
+        let mut breakpoints = 0;
+        for line in &session.breakpoints {
+            let result = match breakpoints::parse_gdb_line(line) {
+                Ok(None) => continue,
+                Ok(Some(gdb_break)) => self.import_breakpoint(&gdb_break),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(_) => breakpoints += 1,
+                Err(e) => report.push(format!("warning: {line}: {e}, skipped")),
+            }
+        }
+
+        // what was typed before comes first, so up-arrow reaches it after this session's
+        let history = session.history.len();
+        let typed = std::mem::take(&mut self.user_command_history);
+        self.user_command_history = session.history.into_iter().chain(typed).collect();
+        self.history_index = None;
+
+        self.refresh_all_views();
+        report.push(format!(
+            "Loaded {signals} of {} signals, {breakpoints} of {} breakpoints and {history} history \
+             entries from {}",
+            session.signals.len(),
+            session.breakpoints.len(),
+            path.display()
+        ));
+        Ok(report)
+    }
+
+    /// Load the session next to the wave file on startup, if there is one and `--no-session`
+    /// doesn't say otherwise. What comes of it goes in the command history
+    pub fn restore_session(&mut self) {
+        if self.cli_args.no_session {
+            return;
+        }
+        let path = session::session_path(&self.cli_args.wave_path);
+        if !path.is_file() {
+            return;
+        }
+        match self.load_session(&path) {
+            Ok(report) => self.command_history.extend(report),
+            Err(e) => {
+                self.command_history.push(format!(
+                    "warning: {e}, {} won't be saved over on quit",
+                    path.display()
+                ));
+                self.session_restore_failed = true;
+            }
+        }
+    }
+
+    /// Save the session next to the wave file on quit, unless `--no-session` or the one there
+    /// couldn't be restored, which would be lost. There's no terminal left to show a failure
+    /// in, so it goes to stderr
+    pub fn save_session_on_exit(&self) {
+        if self.cli_args.no_session {
+            return;
+        }
+        let path = session::session_path(&self.cli_args.wave_path);
+        if self.session_restore_failed {
+            eprintln!(
+                "jpdb: not saving the session, {} didn't load at startup",
+                path.display()
+            );
+            return;
+        }
+        if let Err(e) = self.save_session(&path) {
+            eprintln!("jpdb: {e}");
+        }
+    }
+
+    /// Set one breakpoint out of a `bimport` file through the same paths as `breakpoint`, and
+    /// say what came of it
+    pub fn import_breakpoint(&mut self, gdb_break: &GdbBreak) -> Result<String, String> {
+        let set = match &gdb_break.location {
+            GdbLocation::Address(address) => vec![self.set_breakpoint(*address)?],
+            GdbLocation::FileLine { file, line } => {
+                self.set_breakpoint_at_line(file, *line, None)?
+            }
+            GdbLocation::Symbol(name) => {
+                let address = self
+                    .controller
+                    .model
+                    .symbol_address(name)
+                    .ok_or_else(|| format!("No symbol named {name}"))?;
+                vec![self.set_breakpoint(address)?]
+            }
+        };
+        let ids: Vec<u32> = set.iter().map(|bp| bp.id).collect();
+        if gdb_break.disabled {
+            let selectors: Vec<Selector> = ids.iter().map(|&id| Selector::Ids(id..=id)).collect();
+            self.set_breakpoints_enabled(&selectors, false)?;
+        }
+        Ok(format!(
+            "{} {} set at {}{}",
+            if ids.len() == 1 {
+                "Breakpoint"
+            } else {
+                "Breakpoints"
+            },
+            ids.iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            gdb_break.location.describe(&self.addr_fmt()),
+            if gdb_break.disabled { ", disabled" } else { "" }
+        ))
+    }
+
+    /// How `set addrfmt` has every pane write addresses
+    pub fn addr_fmt(&self) -> AddrFormatter {
+        self.controller.model.addr_fmt
+    }
+
+    /// `addr` the way `set addrfmt` has every pane write it
+    pub fn format_addr(&self, addr: u32) -> String {
+        let model = &self.controller.model;
+        model.addr_fmt.format(addr.into(), &model.client)
+    }
+
+    /// Apply `set addrfmt` options and redraw with them, returning the formatter as it is now
+    pub fn set_addr_fmt(&mut self, options: &[AddrFmtOption]) -> AddrFormatter {
+        let addr_fmt = &mut self.controller.model.addr_fmt;
+        for &option in options {
+            addr_fmt.apply(option);
+        }
+        let addr_fmt = *addr_fmt;
+        self.refresh_all_views();
+        addr_fmt
+    }
+
+    /// `set dualcontext`, off also takes down a split that's up
+    pub fn set_dual_context(&mut self, enabled: bool) {
+        self.controller.view_state.dual_context.set_enabled(enabled);
+    }
+
+    /// Breakpoints and assertions, one per line
+    pub fn breakpoint_lines(&self) -> Vec<String> {
+        self.controller.model.breakpoint_lines()
+    }
+
+    pub fn write_memory(&mut self, addr: u32, data: &[u8]) -> Result<(), String> {
+        self.controller.model.write_memory(addr, data)?;
+        self.refresh_all_views();
+        Ok(())
+    }
+
+    /// `delta`'s listing, see [`DebuggerModel::delta`]
+    pub fn delta(&mut self) -> Result<Vec<String>, String> {
+        self.controller.model.delta()
+    }
+
+    pub fn set_show_bytes(&mut self, show_bytes: bool) {
+        self.controller.model.set_show_bytes(show_bytes);
+        self.refresh_all_views();
+    }
+
+    pub fn set_show_heat(&mut self, show_heat: bool) {
+        self.controller.model.set_show_heat(show_heat);
+        self.refresh_all_views();
+    }
+
+    fn refresh_signal_view(&mut self) {
+        self.controller.refresh_signals();
+    }
+
+    /// Take a selected signal off the Signals pane, see [`DebuggerModel::deselect_signal`]
+    pub fn remove_signal(&mut self, query: &str) -> Result<String, String> {
+        let name = self.controller.model.deselect_signal(query)?;
+        self.refresh_signal_view();
+        Ok(name)
+    }
+
+    /// Empty the Signals pane. Returns how many signals were on it
+    pub fn clear_signals(&mut self) -> Result<usize, String> {
+        let count = self.controller.model.clear_signals()?;
+        self.refresh_signal_view();
+        Ok(count)
+    }
+
+    pub fn set_breakpoint(&mut self, address: u32) -> Result<Breakpoint, String> {
+        self.warn_if_elf_changed();
+        self.controller.model.set_breakpoint(address)
+    }
+
+    pub fn ignore_breakpoint(&mut self, id: u32, count: u32) -> Result<Breakpoint, String> {
+        let result = self.controller.model.ignore_breakpoint(id, count);
+        self.refresh_all_views();
+        result
+    }
+
+    /// The `bhits` listing for breakpoint `id`
+    pub fn breakpoint_hit_lines(&self, id: u32) -> Result<Vec<String>, String> {
+        let model = &self.controller.model;
+        let breakpoint = model
+            .breakpoints()
+            .get(id)
+            .ok_or_else(|| format!("No breakpoint {id}"))?;
+        let times: Vec<Option<u64>> = breakpoint
+            .hits
+            .iter()
+            .map(|&idx| model.time_at(idx))
+            .collect();
+        Ok(view::bhits_lines(
+            breakpoint,
+            &self.format_addr(breakpoint.address),
+            &times,
+            model.time_unit(),
+        ))
+    }
+
+    pub fn set_breakpoint_at_line(
+        &mut self,
+        file: &str,
+        line: u64,
+        column: Option<u64>,
+    ) -> Result<Vec<Breakpoint>, String> {
+        self.warn_if_elf_changed();
+        self.controller
+            .model
+            .set_breakpoint_at_line(file, line, column)
+    }
+
+    /// Bulk breakpoint operations touch the target once per address, then refresh the view once
+    pub fn delete_breakpoints(&mut self, selectors: &[Selector]) -> Result<BulkResult, String> {
+        let result = self.controller.model.delete_breakpoints(selectors);
+        self.refresh_all_views();
+        result
+    }
+
+    pub fn set_breakpoints_enabled(
+        &mut self,
+        selectors: &[Selector],
+        enabled: bool,
+    ) -> Result<BulkResult, String> {
+        let result = self
+            .controller
+            .model
+            .set_breakpoints_enabled(selectors, enabled);
+        self.refresh_all_views();
+        result
+    }
+
+    pub fn clear_file_breakpoints(&mut self, file: &str) -> Result<BulkResult, String> {
+        let result = self.controller.model.clear_file_breakpoints(file);
+        self.refresh_all_views();
+        result
+    }
+
+    pub fn set_bpcheck(&mut self, bpcheck: bool) {
+        self.controller.model.set_bpcheck(bpcheck);
+    }
+
+    pub fn set_elfwatch(&mut self, elfwatch: bool) {
+        self.controller.model.set_elfwatch(elfwatch);
+    }
+
+    /// Reload symbols and line info from the rebuilt ELF, one report line per thing that changed
+    pub fn reload_elf(&mut self) -> Result<Vec<String>, String> {
+        let lines = self.controller.model.reload_elf()?;
+        self.completer.invalidate();
+        self.refresh_all_views();
+        Ok(lines)
+    }
+
+    /// Read the waveform again after the simulation rewrote it, see
+    /// [`DebuggerModel::reload_wave`]
+    pub fn reload_wave(&mut self) -> Result<Vec<String>, String> {
+        let lines = self.controller.model.reload_wave()?;
+        self.refresh_all_views();
+        Ok(lines)
+    }
+
+    /// Pin the Signals pane to another time index, see [`Controller::set_signal_pin`]
+    pub fn set_signal_pin(&mut self, sigtime: SigTime) -> Result<Option<u64>, String> {
+        self.controller.set_signal_pin(sigtime)
+    }
+
+    pub fn set_timeline(&mut self, show: bool) {
+        self.controller.set_timeline(show);
+    }
+
+    /// Move execution straight to time index `idx`, for the timeline
+    pub fn goto_time_idx(&mut self, idx: u64) -> Result<(), String> {
+        self.controller.model.goto_time_idx(idx)?;
+        if let Err(e) = self.sync_waveform_position() {
+            log::warn!("Failed to sync waveform position: {e}");
+        }
+        self.refresh_all_views();
+        Ok(())
+    }
+
+    /// Mark where continue stopped on the timeline
+    pub fn record_breakpoint_hit(&mut self) {
+        self.controller.record_breakpoint_hit();
+    }
+
+    /// Move the Signals pane `delta` time indices, pinning it if it was following execution
+    fn scrub_signals(&mut self, delta: i64) {
+        if let Err(e) = self.set_signal_pin(SigTime::Relative(delta)) {
+            self.command_history.push(format!("Error: {e}"));
+        }
+    }
+
+    /// How many messages the debug panel keeps, the oldest go first
+    pub fn set_log_capacity(&mut self, capacity: usize) {
+        if let Ok(mut buffer) = self.log_buffer.lock() {
+            buffer.set_capacity(capacity);
+        }
+    }
+
+    pub fn set_find_budget(&mut self, find_budget: usize) {
+        self.controller.model.set_find_budget(find_budget);
+    }
+
+    /// The `disas` listing for `target`. A symbol lists up to where the function ends unless
+    /// `count` says otherwise. Nothing moves, the panes stay where execution is
+    pub fn disassemble(
+        &mut self,
+        target: &DisasTarget,
+        count: Option<usize>,
+    ) -> Result<Vec<String>, String> {
+        let model = &self.controller.model;
+        let (addr, what, end) = match target {
+            DisasTarget::Address(addr) => (*addr, self.format_addr(*addr), None),
+            DisasTarget::FileLine { file, line, column } => {
+                let addr = model
+                    .client
+                    .addresses_for_location(file, *line, *column)
+                    .map_err(|e| e.to_string())?
+                    .into_iter()
+                    .min()
+                    .ok_or_else(|| format!("No executable code found for {file}:{line}"))?;
+                (addr, format!("{file}:{line}"), None)
+            }
+            DisasTarget::Name(name) => match model.symbol_named(name) {
+                Some(sym) => {
+                    let end = (sym.size > 0).then(|| (sym.addr + sym.size) as u32);
+                    (sym.addr as u32, format!("function {name}"), end)
+                }
+                None => {
+                    let addr =
+                        parse_hex_u32(name).ok_or_else(|| format!("No symbol named {name}"))?;
+                    (addr, self.format_addr(addr), None)
+                }
+            },
+        };
+        let default_count = match end {
+            Some(_) => MAX_DISAS_INSTRUCTIONS,
+            None => DEFAULT_DISAS_COUNT,
+        };
+        let end = end.filter(|_| count.is_none());
+        let instructions =
+            self.controller
+                .model
+                .disassemble(addr, count.unwrap_or(default_count), end)?;
+        Ok(view::disas_lines(
+            &what,
+            &instructions,
+            self.controller.model.breakpoints(),
+            &self.controller.model.addr_fmt,
+            &self.controller.model.client,
+        ))
+    }
+
+    /// Search the executed instructions and, if something matches, move there
+    pub fn find_instruction(
+        &mut self,
+        direction: SearchDirection,
+        predicate: &InstPredicate,
+    ) -> Result<FindOutcome, String> {
+        let outcome = self
+            .controller
+            .model
+            .find_instruction(direction, predicate)?;
+        if let FindOutcome::Found { .. } = outcome {
+            self.refresh_all_views();
+            if let Err(e) = self.sync_waveform_position() {
+                log::warn!("Failed to sync waveform position: {e}");
+            }
+        }
+        Ok(outcome)
+    }
+
+    pub fn load_debug_file(&mut self, path: &Path) -> Result<(), String> {
+        self.controller.model.load_debug_file(path)?;
+        self.refresh_all_views();
+        Ok(())
+    }
+
+    pub fn status_lines(&self) -> Vec<String> {
+        let mut lines = self.controller.model.status_lines();
+        lines.push(format!("addrfmt: {}", self.controller.model.addr_fmt));
+        lines.push(match &self.dang_outcome {
+            Some(outcome) => format!("dang: {outcome}"),
+            None => "dang: running".to_string(),
+        });
+        lines.extend(self.tasks.lines().iter().map(|task| format!("task {task}")));
+        lines
+    }
+
+    /// Stop every background task, see [`tasks`]. dang's session only ends once the connection
+    /// to it closes, so the controller holding that goes first. Returns the tasks that didn't
+    /// stop in time
+    fn shutdown(self) -> Vec<String> {
+        let App {
+            controller,
+            tasks: mut registry,
+            ..
+        } = self;
+        drop(controller);
+        registry.shutdown(tasks::SHUTDOWN_TIMEOUT)
+    }
+
+    pub fn register_lines(&mut self, verbose: bool) -> Result<Vec<String>, String> {
+        let registers = self.controller.model.registers(verbose)?;
+        let addr_fmt = &self.controller.model.addr_fmt;
+        Ok(registers
+            .iter()
+            .map(|entry| view::register_line(entry, addr_fmt))
+            .collect())
+    }
+
+    pub fn never_hit_breakpoints(&mut self, addresses: &[u32]) -> Vec<u32> {
+        self.controller.model.never_hit(addresses)
+    }
+
+    pub fn mid_instruction(&self, address: u32) -> Option<u32> {
+        self.controller.model.mid_instruction(address)
+    }
+
+    pub fn continue_execution(&mut self) -> Result<(), String> {
+        self.warn_if_wave_changed();
+        self.controller.model.continue_execution()?;
+
+        // Sync waveform position if connected to Surfer
+        if let Err(e) = self.sync_waveform_position() {
+            log::warn!("Failed to sync waveform position: {e}");
+        }
+
+        Ok(())
+    }
+
+    pub fn invalidate_time_idx_cache(&mut self) {
+        self.controller.model.invalidate_time_index();
+    }
+
+    /// Launch Surfer waveform viewer and connect to it via WCP
+    pub fn launch_surfer(&mut self, wave_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        use std::process::{Command, Stdio};
+
+        //TODO: get a random port, i am lazy
+        let wcp_port = 54321;
+        let mut tmp_script = std::env::temp_dir();
+        tmp_script.push("surfer_commands.sucl");
+
+        std::fs::write(tmp_script.as_path(), "wcp_server_start")?;
+
+        let child = Command::new("surfer")
+            .arg(wave_path.to_str().ok_or("Invalid wave path")?)
+            .arg("--script")
+            .arg(
+                tmp_script
+                    .as_os_str()
+                    .to_str()
+                    .ok_or("Invalid script path")?,
+            )
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        log::info!("Launched Surfer with PID {}", child.id());
+
+        self.surfer_process = Some(child);
+
+        // Give Surfer time to start
+        std::thread::sleep(std::time::Duration::from_millis(1000));
+
+        // Connect to Surfer via WCP
+        self.connect_to_surfer(&format!("127.0.0.1:{wcp_port}"))?;
+
+        Ok(())
+    }
+
+    /// Connect to a running Surfer instance via WCP
+    pub fn connect_to_surfer(&mut self, addr: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let client = WcpClient::connect(addr)?;
+        self.wcp_client = Some(client);
+        log::info!("Connected to Surfer via WCP at {addr}");
+
+        // Sync current waveform state
+        self.sync_waveform_position()?;
+
+        Ok(())
+    }
+
+    /// Sync the waveform viewer to the current simulation time
     fn sync_waveform_position(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         if let Some(ref mut wcp) = self.wcp_client {
-            if let Ok(time_idx) = self.model.get_time_idx() {
+            if let Ok(time_idx) = self.controller.model.get_time_idx() {
                 let time = self
+                    .controller
                     .model
                     .client
                     .wave_tracker
@@ -708,32 +1857,68 @@ impl App {
                     .unwrap()
                     .get_current_time(time_idx as shucks::TimeTableIdx);
 
-                wcp.goto_time(time)?;
+                match time {
+                    Some(time) => wcp.goto_time(time)?,
+                    None => log::warn!("No waveform time at index {time_idx}, not syncing"),
+                }
             }
         }
         Ok(())
     }
 
     fn ui(&mut self, f: &mut Frame) {
-        if self.show_debug_panel {
+        self.timeline_area = None;
+        if view::too_small(f.area()) {
+            self.render_too_small(f, f.area());
+            return;
+        }
+
+        // The notification banner takes the top line while there's one to show
+        let banner = self.notifications.banner(Instant::now()).cloned();
+        self.banner_drawn = banner.as_ref().map(|n| n.timestamp);
+        let screen = match &banner {
+            Some(notification) => {
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref())
+                    .split(f.area());
+                self.render_banner(f, chunks[0], notification);
+                chunks[1]
+            }
+            None => f.area(),
+        };
+
+        // The timeline takes the bottom line when it's on
+        let main_area = if self.controller.view_state.timeline.show {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+                .split(screen);
+            self.render_timeline(f, chunks[1]);
+            chunks[0]
+        } else {
+            screen
+        };
+
+        if self.controller.view_state.show_debug_panel {
             // Split the layout: main area (70%) and debug panel (30%)
             let chunks = Layout::default()
                 .direction(Direction::Horizontal)
                 .constraints([Constraint::Percentage(70), Constraint::Percentage(30)].as_ref())
-                .split(f.area());
+                .split(main_area);
 
-            if self.show_split_view {
+            if self.controller.view_state.show_split_view {
                 self.render_split_view(f, chunks[0]);
             } else {
                 self.render_combined_output(f, chunks[0]);
             }
             self.render_debug_panel(f, chunks[1]);
-        } else if self.show_split_view {
+        } else if self.controller.view_state.show_split_view {
             // Show split view without debug panel
-            self.render_split_view(f, f.area());
+            self.render_split_view(f, main_area);
         } else {
             // Render everything as one continuous output with prompt at the end
-            self.render_combined_output(f, f.area());
+            self.render_combined_output(f, main_area);
         }
 
         // Render addsig popup on top if active
@@ -741,12 +1926,73 @@ impl App {
             self.render_addsig_popup(f, f.area());
         }
 
+        if let Some(state) = &self.completion {
+            // Small, it's a handful of words
+            let popup_area = view::centered_popup(f.area(), 40, 40, 24, 5);
+            picker::render(
+                f,
+                popup_area,
+                f.area(),
+                &state.picker,
+                &PickerText {
+                    title: "",
+                    input: None,
+                    list_title: "Completions",
+                    help: "Tab: Next | Esc: Undo | any other key: Keep",
+                },
+            );
+        }
+
         // Render help modal on top if active
         if self.help_modal_state.is_active() {
             self.render_help_modal(f, f.area());
         }
     }
 
+    fn render_banner(
+        &self,
+        f: &mut Frame,
+        area: ratatui::layout::Rect,
+        notification: &notifications::Notification,
+    ) {
+        use ratatui::widgets::Paragraph;
+
+        let color = match notification.severity {
+            Severity::Error => Color::Red,
+            Severity::Warning => Color::Yellow,
+            Severity::Info => Color::Blue,
+        };
+        f.render_widget(
+            Paragraph::new(view::notification_line(notification))
+                .style(Style::default().fg(Color::Black).bg(color)),
+            area,
+        );
+    }
+
+    fn render_timeline(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
+        use ratatui::widgets::Paragraph;
+
+        let line = view::render_timeline(&self.controller.view_state.timeline, area.width as usize);
+        f.render_widget(
+            Paragraph::new(line).style(Style::default().fg(Color::Cyan)),
+            area,
+        );
+        self.timeline_area = Some(area);
+    }
+
+    fn render_too_small(&self, f: &mut Frame, area: ratatui::layout::Rect) {
+        use ratatui::widgets::{Paragraph, Wrap};
+
+        let message = Paragraph::new(format!(
+            "terminal too small (need at least {}x{})",
+            view::MIN_WIDTH,
+            view::MIN_HEIGHT
+        ))
+        .style(Style::default().fg(Color::Yellow))
+        .wrap(Wrap { trim: true });
+        f.render_widget(message, area);
+    }
+
     fn render_combined_output(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
         // Split the area vertically: instruction panel (top 40%) and command area (bottom 60%)
         let chunks = Layout::default()
@@ -763,6 +2009,7 @@ impl App {
 
     fn render_instruction_panel_combined(&self, f: &mut Frame, area: ratatui::layout::Rect) {
         let items: Vec<ListItem> = self
+            .controller
             .view_state
             .execution_lines
             .iter()
@@ -806,27 +2053,23 @@ impl App {
         };
 
         // Add the current prompt line
-        let prompt_text = format!("(jpdb) {}", self.input_buffer);
+        let prompt_text = match self.confirmation_prompt() {
+            Some(question) => format!("(jpdb) {question}"),
+            None => format!("(jpdb) {}", self.input_buffer),
+        };
         all_lines.push(prompt_text);
 
         // Calculate how many lines can fit in the terminal
-        let available_height = area.height.saturating_sub(2) as usize; // Account for borders
-        let total_lines = all_lines.len();
+        let available_height = view::inner_height(area);
 
-        // Determine which lines to show based on scroll offset (only for full history mode)
-        let visible_lines = if show_full_history && total_lines > available_height {
-            // Need to scroll - calculate the start index
-            let max_scroll = total_lines.saturating_sub(available_height);
-            let actual_scroll = self.scroll_offset.min(max_scroll);
-            let start_idx = total_lines.saturating_sub(available_height + actual_scroll);
-            let end_idx = start_idx + available_height;
-
-            all_lines[start_idx..end_idx.min(total_lines)].to_vec()
+        // Only the full history scrolls, the split view always shows the end
+        let scroll = if show_full_history {
+            self.scroll_offset
         } else {
-            // For split view or when all lines fit, show from the end
-            let start_idx = total_lines.saturating_sub(available_height);
-            all_lines[start_idx..].to_vec()
+            0
         };
+        let visible_lines =
+            &all_lines[view::line_window(all_lines.len(), available_height, scroll)];
 
         let items: Vec<ListItem> = visible_lines
             .iter()
@@ -861,31 +2104,21 @@ impl App {
     }
 
     fn render_debug_panel(&self, f: &mut Frame, area: ratatui::layout::Rect) {
-        // Get all log messages from buffer
-        let all_log_messages = if let Ok(buffer) = self.log_buffer.lock() {
-            buffer.iter().cloned().collect::<Vec<_>>()
-        } else {
-            Vec::new()
+        let available_height = view::inner_height(area);
+        // Only what's on screen is copied out, the lock is held just for that
+        let window = match self.log_buffer.lock() {
+            Ok(buffer) => buffer.window(
+                self.debug_min_level,
+                available_height,
+                self.debug_scroll_offset,
+            ),
+            Err(_) => LogWindow::default(),
         };
+        let total_messages = window.total;
 
-        let available_height = area.height.saturating_sub(2) as usize; // Account for borders
-        let total_messages = all_log_messages.len();
-
-        // Calculate which messages to show based on scroll offset
-        let visible_messages = if total_messages > available_height {
-            let max_scroll = total_messages.saturating_sub(available_height);
-            let actual_scroll = self.debug_scroll_offset.min(max_scroll);
-            let start_idx = total_messages.saturating_sub(available_height + actual_scroll);
-            let end_idx = start_idx + available_height;
-
-            all_log_messages[start_idx..end_idx.min(total_messages)].to_vec()
-        } else {
-            // If all messages fit, show them all
-            all_log_messages
-        };
-
-        let items: Vec<ListItem> = visible_messages
-            .iter()
+        let items: Vec<ListItem> = window
+            .lines
+            .into_iter()
             .map(|msg| {
                 let style = match msg.level {
                     log::Level::Error => Style::default().fg(Color::Red),
@@ -894,28 +2127,22 @@ impl App {
                     log::Level::Debug => Style::default().fg(Color::Gray),
                     log::Level::Trace => Style::default().fg(Color::DarkGray),
                 };
-                let formatted_msg = format!("[{}] {}", msg.level, msg.message);
-                ListItem::new(formatted_msg).style(style)
+                ListItem::new(msg.line).style(style)
             })
             .collect();
 
         let debug_panel = List::new(items).block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Debug (d to toggle, PgUp/PgDn to scroll, Home/End)"),
+                .title(view::debug_panel_title(self.debug_min_level)),
         );
 
         f.render_widget(debug_panel, area);
 
         // Add scrollbar if there are more messages than can fit
-        if total_messages > available_height {
-            let scrollbar_area = Rect {
-                x: area.x + area.width - 1,
-                y: area.y + 1,
-                width: 1,
-                height: area.height - 2,
-            };
-
+        if let Some(scrollbar_area) =
+            view::scrollbar_area(area).filter(|_| total_messages > available_height)
+        {
             let max_scroll = total_messages.saturating_sub(available_height);
             let scrollbar = Scrollbar::default()
                 .orientation(ratatui::widgets::ScrollbarOrientation::VerticalRight)
@@ -953,95 +2180,55 @@ impl App {
             )
             .split(main_chunks[0]);
 
-        self.render_instruction_pane(f, panel_chunks[0]);
-        self.render_source_pane(f, panel_chunks[1]);
-        self.render_signal_panel(f, panel_chunks[2]);
-        self.render_command_bar(f, main_chunks[1]);
-    }
-
-    fn render_instruction_pane(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
-        let items: Vec<ListItem> = self
-            .view_state
-            .instruction_lines
-            .iter()
-            .map(|line| {
-                let style = if line.starts_with("->") {
-                    Style::default()
-                        .fg(Color::Green)
-                        .add_modifier(Modifier::BOLD)
-                } else if line.starts_with("Error:") {
-                    Style::default().fg(Color::Red)
-                } else {
-                    Style::default().fg(Color::White)
-                };
-                ListItem::new(line.clone()).style(style)
-            })
-            .collect();
-
-        let instruction_panel = List::new(items).block(
-            Block::default()
-                .borders(ratatui::widgets::Borders::ALL)
-                .title("Instructions"),
-        );
+        self.render_instruction_pane(f, panel_chunks[0]);
+        self.render_source_pane(f, panel_chunks[1]);
+        self.render_signal_panel(f, panel_chunks[2]);
+        self.render_command_bar(f, main_chunks[1]);
+    }
 
-        f.render_widget(instruction_panel, area);
+    fn render_instruction_pane(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
+        let view_state = &self.controller.view_state;
+        let dual = &view_state.dual_context;
+        let listing = render_code_pane(
+            f,
+            area,
+            "Instructions",
+            &view_state.instruction_lines,
+            dual.current_image(),
+            dual.previous()
+                .map(|previous| (previous.image.as_deref(), &previous.instruction_lines[..])),
+        );
+        self.controller.view_state.instruction_pane = Some(listing);
     }
 
     fn render_source_pane(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
-        let items: Vec<ListItem> = self
-            .view_state
-            .source_lines
-            .iter()
-            .map(|line| {
-                let style = if line.starts_with("->") {
-                    Style::default()
-                        .fg(Color::Green)
-                        .add_modifier(Modifier::BOLD)
-                } else if line.starts_with("Error:") {
-                    Style::default().fg(Color::Red)
-                } else {
-                    Style::default().fg(Color::White)
-                };
-                ListItem::new(line.clone()).style(style)
-            })
-            .collect();
-
-        let source_panel = List::new(items).block(
-            Block::default()
-                .borders(ratatui::widgets::Borders::ALL)
-                .title("Source Code"),
+        let view_state = &self.controller.view_state;
+        let dual = &view_state.dual_context;
+        render_code_pane(
+            f,
+            area,
+            "Source Code",
+            &view_state.source_lines,
+            dual.current_image(),
+            dual.previous()
+                .map(|previous| (previous.image.as_deref(), &previous.source_lines[..])),
         );
-
-        f.render_widget(source_panel, area);
     }
 
     fn render_signal_panel(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
         let items: Vec<ListItem> = self
+            .controller
             .view_state
             .signal_lines
             .iter()
             .enumerate()
-            .map(|(i, line)| {
-                let style = if i == 0 && line.ends_with(" ps") {
-                    // Time header - make it bold and colored
-                    Style::default()
-                        .fg(Color::Cyan)
-                        .add_modifier(Modifier::BOLD)
-                } else if line.starts_with("Error:") || line.starts_with("Error ") {
-                    Style::default().fg(Color::Red)
-                } else if line == "no waves found" || line == "No signals selected" {
-                    Style::default().fg(Color::Yellow)
-                } else {
-                    Style::default().fg(Color::White)
-                };
-                ListItem::new(line.clone()).style(style)
-            })
+            .map(|(i, line)| ListItem::new(line.clone()).style(signal_line_style(i, line)))
             .collect();
 
         let signal_panel = List::new(items).block(
             Block::default()
                 .borders(ratatui::widgets::Borders::ALL)
-                .title("Signals"),
+                .title(self.controller.view_state.signals_title.as_str()),
         );
 
         f.render_widget(signal_panel, area);
@@ -1053,133 +2240,42 @@ impl App {
     }
 
     fn render_addsig_popup(&self, f: &mut Frame, area: ratatui::layout::Rect) {
-        use ratatui::layout::Alignment;
-        use ratatui::widgets::{Clear, Paragraph};
-
-        // Calculate popup size and position (centered, 60% width, 50% height)
-        let popup_area = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Percentage(25), // Top margin
-                Constraint::Percentage(50), // Popup height
-                Constraint::Percentage(25), // Bottom margin
-            ])
-            .split(area)[1];
-
-        let popup_area = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Percentage(20), // Left margin
-                Constraint::Percentage(60), // Popup width
-                Constraint::Percentage(20), // Right margin
-            ])
-            .split(popup_area)[1];
-
-        // Clear the background
-        f.render_widget(Clear, popup_area);
-
-        // Split popup into search input and results
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(3), // Search input
-                Constraint::Min(0),    // Results
-            ])
-            .split(popup_area);
-
-        // Render search input
-        let input_text = format!("Search: {}", self.addsig_state.get_input());
-        let input_paragraph = Paragraph::new(input_text)
-            .block(Block::default().borders(Borders::ALL).title("Add Signal"))
-            .alignment(Alignment::Left);
-        f.render_widget(input_paragraph, chunks[0]);
-
-        // Render search results
-        let matches = self.addsig_state.get_matches();
-        let selected_index = self.addsig_state.get_selected_index();
-
-        let items: Vec<ListItem> = matches
-            .iter()
-            .enumerate()
-            .map(|(i, (_, signal_name))| {
-                let style = if i == selected_index {
-                    Style::default()
-                        .bg(Color::Blue)
-                        .fg(Color::White)
-                        .add_modifier(Modifier::BOLD)
-                } else {
-                    Style::default().fg(Color::White)
-                };
-                ListItem::new(signal_name.clone()).style(style)
-            })
-            .collect();
-
-        let results_list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title("Signals"))
-            .highlight_style(
-                Style::default()
-                    .bg(Color::Blue)
-                    .add_modifier(Modifier::BOLD),
-            );
-
-        f.render_widget(results_list, chunks[1]);
-
-        // Add help text at the bottom
-        let help_area = Rect {
-            x: popup_area.x,
-            y: popup_area.y + popup_area.height,
-            width: popup_area.width,
-            height: 1,
-        };
-        let help_text = Paragraph::new("↑↓: Navigate | Enter: Select | Esc: Cancel")
-            .style(Style::default().fg(Color::Gray))
-            .alignment(Alignment::Center);
-        f.render_widget(help_text, help_area);
+        // Centered, 60% width and 50% height, but at least big enough for the search box and
+        // a couple of results
+        let popup_area = view::centered_popup(area, 60, 50, 30, 8);
+        picker::render(
+            f,
+            popup_area,
+            area,
+            &self.addsig_state.picker,
+            &PickerText {
+                title: "Add Signal",
+                input: Some(format!("Search: {}", self.addsig_state.get_input())),
+                list_title: "Signals",
+                help: "↑↓: Navigate | Enter: Select | Esc: Cancel",
+            },
+        );
     }
 
     fn render_help_modal(&self, f: &mut Frame, area: ratatui::layout::Rect) {
         use ratatui::layout::Alignment;
         use ratatui::widgets::{Clear, Paragraph};
 
-        // Calculate popup size and position (centered, 70% width, 60% height)
-        let popup_area = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Percentage(20), // Top margin
-                Constraint::Percentage(60), // Popup height
-                Constraint::Percentage(20), // Bottom margin
-            ])
-            .split(area)[1];
-
-        let popup_area = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Percentage(15), // Left margin
-                Constraint::Percentage(70), // Popup width
-                Constraint::Percentage(15), // Right margin
-            ])
-            .split(popup_area)[1];
+        // Centered, 70% width and 60% height, with a floor so a few lines always fit
+        let popup_area = view::centered_popup(area, 70, 60, 36, 6);
 
         // Clear the background
         f.render_widget(Clear, popup_area);
 
         // Get content and calculate visible area
         let content = self.help_modal_state.get_content();
-        let available_height = popup_area.height.saturating_sub(2) as usize; // Account for borders
+        let available_height = view::inner_height(popup_area);
         let total_lines = content.len();
         let scroll_offset = self.help_modal_state.get_scroll_offset();
 
         // Calculate which lines to show based on scroll offset
-        let visible_content = if total_lines > available_height {
-            let max_scroll = total_lines.saturating_sub(available_height);
-            let actual_scroll = scroll_offset.min(max_scroll);
-            let start_idx = total_lines.saturating_sub(available_height + actual_scroll);
-            let end_idx = start_idx + available_height;
-
-            &content[start_idx..end_idx.min(total_lines)]
-        } else {
-            content
-        };
+        let window = view::line_window(total_lines, available_height, scroll_offset);
+        let visible_content = &content[window.clone()];
 
         // Render help content
         let items: Vec<ListItem> = visible_content
@@ -1192,6 +2288,8 @@ impl App {
                 } else if line.starts_with("  ") && line.contains("--") {
                     // Command line
                     Style::default().fg(Color::Yellow)
+                } else if line.ends_with(view::CONSTANT_MARKER) {
+                    Style::default().fg(Color::DarkGray)
                 } else if line.starts_with("Keyboard shortcuts:")
                     || line.starts_with("Description:")
                     || line.starts_with("Usage:")
@@ -1211,20 +2309,15 @@ impl App {
         let help_list = List::new(items).block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Help (Press Esc, Enter, or 'q' to close)"),
+                .title(view::help_title(window, total_lines)),
         );
 
         f.render_widget(help_list, popup_area);
 
         // Add scrollbar if there's more content than can fit
-        if total_lines > available_height {
-            let scrollbar_area = Rect {
-                x: popup_area.x + popup_area.width - 1,
-                y: popup_area.y + 1,
-                width: 1,
-                height: popup_area.height - 2,
-            };
-
+        if let Some(scrollbar_area) =
+            view::scrollbar_area(popup_area).filter(|_| total_lines > available_height)
+        {
             let max_scroll = total_lines.saturating_sub(available_height);
             let scrollbar = Scrollbar::default()
                 .orientation(ratatui::widgets::ScrollbarOrientation::VerticalRight)
@@ -1238,46 +2331,1086 @@ impl App {
             f.render_stateful_widget(scrollbar, scrollbar_area, &mut scrollbar_state);
         }
 
-        // Add navigation help text at the bottom
-        let help_area = Rect {
-            x: popup_area.x,
-            y: popup_area.y + popup_area.height,
-            width: popup_area.width,
-            height: 1,
-        };
-        let nav_text = Paragraph::new(
-            "↑↓: Scroll | PgUp/PgDn: Page | Home/End: Top/Bottom | Esc/Enter/q: Close",
-        )
-        .style(Style::default().fg(Color::Gray))
-        .alignment(Alignment::Center);
-        f.render_widget(nav_text, help_area);
+        // Add navigation help text at the bottom, if there's a row left for it
+        if let Some(help_area) = view::line_below(popup_area, area) {
+            let nav_text = Paragraph::new(
+                "↑↓: Scroll | PgUp/PgDn: Page | Home/End: Top/Bottom | Esc/Enter/q: Close",
+            )
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center);
+            f.render_widget(nav_text, help_area);
+        }
+    }
+}
+
+/// The lines of the Instructions or Source pane, current line highlighted. Dimmed ones are all
+/// gray, for the image execution came from
+/// How line `i` of the Signals pane is drawn. The first line is the time header unless the pane
+/// is showing a message instead, whatever unit the time is in
+fn signal_line_style(i: usize, line: &str) -> Style {
+    if line.starts_with("Error:") || line.starts_with("Error ") {
+        Style::default().fg(Color::Red)
+    } else if line == "no waves found" || line == "No signals selected" {
+        Style::default().fg(Color::Yellow)
+    } else if i == 0 && line != view::TERMINATED {
+        // Time header - make it bold and colored
+        Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(Color::White)
     }
 }
 
+fn code_list(lines: &[String], title: String, dimmed: bool) -> List<'static> {
+    let items: Vec<ListItem> = lines
+        .iter()
+        .map(|line| {
+            let style = if dimmed {
+                Style::default().fg(Color::DarkGray)
+            } else if line.starts_with("->") {
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD)
+            } else if line.starts_with("Error:") {
+                Style::default().fg(Color::Red)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(line.clone()).style(style)
+        })
+        .collect();
+
+    List::new(items).block(Block::default().borders(Borders::ALL).title(title))
+}
+
+/// The Instructions or Source pane. While the dual context is up, `previous` has the image
+/// execution came from and its lines, which take a strip along the top, and each part is
+/// titled with its image. Returns where `lines` went
+fn render_code_pane(
+    f: &mut Frame,
+    area: ratatui::layout::Rect,
+    title: &str,
+    lines: &[String],
+    image: Option<&str>,
+    previous: Option<(Option<&str>, &[String])>,
+) -> ratatui::layout::Rect {
+    let Some((previous_image, previous_lines)) = previous else {
+        f.render_widget(code_list(lines, title.to_string(), false), area);
+        return area;
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(previous_lines.len() as u16 + 2),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+    let titled = |image| format!("{title} [{}]", dual_context::image_label(image));
+    f.render_widget(
+        code_list(previous_lines, titled(previous_image), true),
+        chunks[0],
+    );
+    f.render_widget(code_list(lines, titled(image), false), chunks[1]);
+    chunks[1]
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Before anything can panic with the terminal in raw mode
+    terminal_state::install_panic_hook();
+
     // Parse command line arguments
     let cli_args: cli::JpdbArgs = argh::from_env();
 
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    // Only touch the terminal once we know we can actually start
+    let mut app = match App::new(cli_args) {
+        Ok(app) => app,
+        Err(err) => {
+            eprintln!("{}", cli::format_startup_error(err.as_ref()));
+            std::process::exit(2);
+        }
+    };
+
+    app.restore_session();
+    app.run_init_scripts();
 
-    let mut app = App::new(cli_args);
-    let res = app.run(&mut terminal);
+    if app.cli_args.ui == cli::UiMode::Simple {
+        // whatever the init scripts printed
+        for line in &app.command_history {
+            println!("{line}");
+        }
+        line_ui::run(&mut app, io::stdin().lock(), &mut io::stdout())?;
+        app.save_session_on_exit();
+        report_stuck_tasks(app.shutdown());
+        return Ok(());
+    }
 
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+    app.quit_signal = QuitSignal::install()?;
+    let res = run_tui(&mut app);
+    // Put the terminal back whichever way the TUI ended
+    terminal_state::restore()?;
 
     if let Err(err) = res {
+        eprintln!("jpdb: {err}");
         log::error!("{err:?}");
     }
+    app.save_session_on_exit();
+    report_stuck_tasks(app.shutdown());
 
     Ok(())
 }
+
+/// The TUI from taking over the terminal until the run loop ends. Leaves the terminal for the
+/// caller to restore
+fn run_tui(app: &mut App) -> io::Result<()> {
+    terminal_state::enter()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    app.run(&mut terminal)
+}
+
+/// The log only goes to the debug panel, which is gone by now
+fn report_stuck_tasks(stuck: Vec<String>) {
+    if !stuck.is_empty() {
+        eprintln!(
+            "jpdb: background tasks didn't stop within {:?}: {}",
+            tasks::SHUTDOWN_TIMEOUT,
+            stuck.join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn test_data(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../test_data/ibex")
+            .join(name)
+    }
+
+    fn startup_error(args: cli::JpdbArgs) -> String {
+        match App::new(args) {
+            Ok(_) => panic!("App::new should have failed"),
+            Err(e) => cli::format_startup_error(e.as_ref()),
+        }
+    }
+
+    /// An app over the fixture, with a logger that isn't installed globally
+    pub(crate) fn fixture_app() -> App {
+        let redraw = RedrawFlag::default();
+        let (_logger, log_buffer) = AppLogger::new(redraw.clone());
+        App::from_parts(
+            cli::JpdbArgs {
+                wave_path: test_data("sim.fst"),
+                mapping_path: test_data("signal_get.py"),
+                elf: test_data("hello_test.elf"),
+                mapping_arg: Vec::new(),
+                scratch: None,
+                ui: cli::UiMode::Full,
+                init_commands: None,
+                allow_local_init: false,
+                no_session: true,
+                wave_load_threads: None,
+                wave_keep_scopes_with_empty_name: true,
+            },
+            controller::test_utils::fixture_controller(),
+            TaskRegistry::default(),
+            mpsc::channel().1,
+            log_buffer,
+            redraw,
+        )
+    }
+
+    #[test]
+    fn test_tiny_terminal_does_not_panic() {
+        use ratatui::backend::TestBackend;
+
+        let mut app = fixture_app();
+        app.command_history = (0..50).map(|i| format!("line {i}")).collect();
+        app.help_modal_state
+            .activate((0..50).map(|i| format!("help {i}")).collect());
+        app.addsig_state.activate();
+        app.controller.set_debug_panel(true);
+
+        let mut terminal = Terminal::new(TestBackend::new(5, 5)).unwrap();
+        terminal.draw(|f| app.ui(f)).unwrap();
+
+        // at the minimum every pane and popup only gets a sliver of space
+        for (width, height) in [(view::MIN_WIDTH, view::MIN_HEIGHT), (41, 11), (1, 1)] {
+            terminal.backend_mut().resize(width, height);
+            terminal.autoresize().unwrap();
+            terminal.draw(|f| app.ui(f)).unwrap();
+            app.controller.set_split_view(false);
+            terminal.draw(|f| app.ui(f)).unwrap();
+            app.controller.set_split_view(true);
+        }
+    }
+
+    #[test]
+    fn test_dual_context_splits_code_panes() {
+        use dual_context::ImageContext;
+        use ratatui::backend::TestBackend;
+
+        let screen = |app: &mut App| {
+            let mut terminal = Terminal::new(TestBackend::new(160, 48)).unwrap();
+            terminal.draw(|f| app.ui(f)).unwrap();
+            let buffer = terminal.backend().buffer();
+            buffer
+                .content()
+                .chunks(buffer.area.width as usize)
+                .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let context = |image: &str, line: &str| ImageContext {
+            image: Some(image.to_string()),
+            instruction_lines: vec![format!("->  {line}")],
+            source_lines: vec![format!("->  {line}")],
+        };
+
+        let mut app = fixture_app();
+        let dual = &mut app.controller.view_state.dual_context;
+        dual.stop(1_000_000, context("rom.elf", "left the rom here"));
+        dual.stop(1_000_001, context("hello_test.elf", "in the app now"));
+        let shown = screen(&mut app);
+        assert!(shown.contains("Instructions [rom.elf]"), "{shown}");
+        assert!(shown.contains("Instructions [hello_test.elf]"), "{shown}");
+        assert!(shown.contains("Source Code [rom.elf]"), "{shown}");
+        assert!(shown.contains("left the rom here"), "{shown}");
+
+        // Esc takes it down
+        app.handle_key(KeyEvent::from(KeyCode::Esc));
+        let shown = screen(&mut app);
+        assert!(!shown.contains("[rom.elf]"), "{shown}");
+        assert!(shown.contains("Instructions"), "{shown}");
+    }
+
+    #[test]
+    fn test_signal_header_style_by_position() {
+        let header = Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD);
+        for line in ["40210 ps", "40 ns", "7", view::TIME_UNAVAILABLE] {
+            assert_eq!(signal_line_style(0, line), header, "{line}");
+        }
+        // a signal that happens to end like a time isn't the header
+        assert_ne!(signal_line_style(2, "top.count: 40 ns"), header);
+        assert_ne!(signal_line_style(0, view::TERMINATED), header);
+        assert_ne!(signal_line_style(0, "no waves found"), header);
+    }
+
+    #[test]
+    fn test_bhits() {
+        let mut app = fixture_app();
+        // putchar, called for every character printed
+        app.execute_line("b 0x100084").unwrap();
+        app.execute_line("continue").unwrap();
+        app.execute_line("continue").unwrap();
+
+        let model = &app.controller.model;
+        let hits: Vec<u64> = model
+            .breakpoints()
+            .get(1)
+            .unwrap()
+            .hits
+            .iter()
+            .copied()
+            .collect();
+        assert_eq!(hits.len(), 2);
+        assert!(hits[0] < hits[1], "{hits:?}");
+        assert!(model.time_at(hits[0]).unwrap() < model.time_at(hits[1]).unwrap());
+        assert_eq!(app.controller.view_state.timeline.hits, hits);
+
+        app.command_history.clear();
+        app.execute_line("bhits 1").unwrap();
+        let output = &app.command_history;
+        assert_eq!(output[0], "Breakpoint 1 (0x100084), 2 hits:");
+        assert!(output[1].starts_with(&format!("  time index {}: ", hits[0])));
+        let unit = app.controller.model.time_unit();
+        assert!(output[2].ends_with(&format!(" {unit}")), "{}", output[2]);
+        assert!(app.execute_line("bhits 2").is_err());
+        assert!(app.execute_line("bhits").is_err());
+    }
+
+    #[test]
+    fn test_delsig() {
+        let mut app = fixture_app();
+        for query in ["pc_id", "pc_wb_o", "clk_sys"] {
+            app.execute_line(&format!("addsig {query}")).unwrap();
+        }
+        let names = app
+            .controller
+            .model
+            .client
+            .wave_tracker
+            .as_ref()
+            .unwrap()
+            .get_signal_names();
+        assert_eq!(names.len(), 3);
+
+        app.execute_line(&format!("delsig {}", names[1])).unwrap();
+        assert_eq!(
+            app.command_history.last().unwrap(),
+            &format!("Removed signal {}", names[1])
+        );
+        // the pane has already caught up
+        let lines = &app.controller.view_state.signal_lines;
+        assert_eq!(lines.len(), 4, "{lines:?}");
+        assert!(lines[2].starts_with(&format!("{}: ", names[0])));
+        assert!(lines[3].starts_with(&format!("{}: ", names[2])));
+
+        // one that isn't there any more is an error in the history, not a silent success
+        app.input_buffer = format!("ds {}", names[1]);
+        app.process_command();
+        assert_eq!(
+            app.command_history.last().unwrap(),
+            &format!("error: No selected signal is named '{}'", names[1])
+        );
+
+        app.execute_line("delsig --all").unwrap();
+        assert_eq!(app.command_history.last().unwrap(), "Removed 2 signals");
+        assert_eq!(
+            app.controller.view_state.signal_lines[2],
+            "No signals selected"
+        );
+        assert!(app.execute_line("delsig").is_err());
+    }
+
+    #[test]
+    fn test_breakpoint_mid_instruction() {
+        let mut app = fixture_app();
+        // the second halfword of puthex's lui at 0x1000a8
+        app.execute_line("b 0x1000aa").unwrap();
+        let warning = "Warning: address 0x1000aa is 2 bytes into the instruction at 0x1000a8";
+        assert!(
+            app.command_history
+                .iter()
+                .any(|line| line.starts_with(warning)),
+            "{:?}",
+            app.command_history
+        );
+
+        app.command_history.clear();
+        app.execute_line("b 0x1000a8").unwrap();
+        assert!(!app
+            .command_history
+            .iter()
+            .any(|line| line.contains("bytes into")));
+
+        app.execute_line("set bpcheck off").unwrap();
+        app.command_history.clear();
+        app.execute_line("b 0x1000ae").unwrap();
+        assert!(!app
+            .command_history
+            .iter()
+            .any(|line| line.contains("Warning")));
+    }
+
+    #[test]
+    fn test_delta_across_a_step() {
+        let mut app = fixture_app();
+        app.execute_line("delta").unwrap();
+        assert!(app
+            .command_history
+            .last()
+            .unwrap()
+            .starts_with("Snapshot taken"));
+
+        // step until one changes a0 and nothing else
+        for _ in 0..200 {
+            let before = app.controller.model.client.read_gprs().unwrap();
+            app.execute_line("step").unwrap();
+            let after = app.controller.model.client.read_gprs().unwrap();
+            app.command_history.clear();
+            app.execute_line("delta").unwrap();
+            let changed: Vec<usize> = (0..32).filter(|&reg| before[reg] != after[reg]).collect();
+            if changed != [10] {
+                continue;
+            }
+            let output = &app.command_history;
+            let registers = output.iter().position(|line| line == "Registers:").unwrap();
+            let listed: Vec<&String> = output[registers + 1..]
+                .iter()
+                .take_while(|line| line.starts_with("  "))
+                .collect();
+            assert_eq!(listed.len(), 1, "{output:?}");
+            // a value that points into code gets its symbol after it
+            let fmt = app.addr_fmt();
+            assert!(listed[0].starts_with(&format!(
+                "  a0   {} → {}",
+                fmt.column(before[10].into()),
+                fmt.column(after[10].into())
+            )));
+            return;
+        }
+        panic!("no step changed just a0");
+    }
+
+    #[test]
+    fn test_disas() {
+        let mut app = fixture_app();
+        let pc = app
+            .controller
+            .model
+            .client
+            .get_current_pc()
+            .unwrap()
+            .as_u32();
+
+        // puthex is 0x24 bytes at 0x1000a4, its listing ends with it
+        app.execute_line("disas puthex").unwrap();
+        let listing = app.help_modal_state.get_content().to_vec();
+        assert_eq!(listing[0], "Dump of assembler code for function puthex:");
+        assert_eq!(listing.last().unwrap(), "End of assembler dump.");
+        let addrs: Vec<u32> = listing[1..listing.len() - 1]
+            .iter()
+            .map(|line| {
+                let addr = line[4..].split(':').next().unwrap();
+                parse_hex_u32(addr).unwrap()
+            })
+            .collect();
+        assert_eq!(addrs[0], 0x1000a4);
+        assert!(addrs.len() > 1);
+        assert!(
+            addrs.iter().all(|&addr| addr < 0x1000a4 + 0x24),
+            "{listing:?}"
+        );
+        app.help_modal_state.deactivate();
+
+        // a count wins over the symbol's size, and the current instruction is marked
+        app.execute_line("disas puthex 30").unwrap();
+        assert_eq!(app.help_modal_state.get_content().len(), 32);
+        app.help_modal_state.deactivate();
+        app.execute_line(&format!("disas 0x{pc:x} 1")).unwrap();
+        assert!(app.help_modal_state.get_content()[1].starts_with("->"));
+        app.help_modal_state.deactivate();
+        assert_eq!(
+            app.controller
+                .model
+                .client
+                .get_current_pc()
+                .unwrap()
+                .as_u32(),
+            pc
+        );
+
+        // what can't be resolved is an error, not an empty listing
+        for line in [
+            "disas no_such_symbol",
+            "disas nosuchfile.c:3",
+            "disas puthex 101",
+        ] {
+            assert!(app.execute_line(line).is_err(), "{line}");
+            assert!(!app.help_modal_state.is_active(), "{line}");
+        }
+    }
+
+    #[test]
+    fn test_simple_ui_script() {
+        let mut app = fixture_app();
+        app.cli_args.ui = cli::UiMode::Simple;
+        let script = "step\nstep\nshow exec\nregs\naddsig\nhelp step\nquit\nstep\n";
+        let mut output = Vec::new();
+        line_ui::run(&mut app, script.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("frame #0: 0x"), "{output}");
+        assert!(output.contains("x2  sp   = 0x"), "{output}");
+        assert!(output.contains("error: usage: addsig <query>"), "{output}");
+        // help is printed inline, nothing modal is left open
+        assert!(output.contains("Help for 'step':"), "{output}");
+        assert!(!app.help_modal_state.is_active());
+        // commands aren't echoed back, and nothing runs after quit
+        assert!(!output.contains("(jpdb) step"), "{output}");
+        assert_eq!(output.matches(line_ui::PROMPT).count(), 7, "{output}");
+    }
+
+    /// Export a short scripted session and compare it to the checked in golden, see
+    /// [`golden::check_file`]
+    #[test]
+    fn test_export_md_golden() {
+        let mut app = fixture_app();
+        let dir = std::env::temp_dir().join(format!("jpdb-export-md-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("session.jpdb");
+        std::fs::write(&script, "b 0x1000a4\nc\nstep\nregs\n").unwrap();
+        app.run_init_script(&script).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let manifest = env!("CARGO_MANIFEST_DIR");
+        let root = PathBuf::from(manifest)
+            .parent()
+            .unwrap()
+            .display()
+            .to_string();
+        let redactions = [
+            export_md::Redaction {
+                from: format!("{manifest}/.."),
+                to: "<root>".to_string(),
+            },
+            export_md::Redaction {
+                from: root,
+                to: "<root>".to_string(),
+            },
+        ];
+        // how long the test took and when its notifications came in vary from run to run
+        let transcript: String = app
+            .markdown_transcript(&redactions)
+            .lines()
+            .map(|line| {
+                if line.starts_with("- **Duration:**") {
+                    "- **Duration:** <duration>\n".to_string()
+                } else if let Some((_, rest)) = line
+                    .strip_prefix("- +")
+                    .and_then(|line| line.split_once(' '))
+                {
+                    format!("- +<time> {rest}\n")
+                } else {
+                    format!("{line}\n")
+                }
+            })
+            .collect();
+        assert!(transcript.contains("`b 0x1000a4`"), "{transcript}");
+        assert!(!transcript.contains(manifest), "{transcript}");
+
+        golden::check_file("session.md", &transcript);
+    }
+
+    #[test]
+    fn test_dang_failure_is_shown() {
+        let mut app = fixture_app();
+        let (exit_tx, exit_rx) = mpsc::channel();
+        app.dang_exit = exit_rx;
+        app.poll_dang_exit();
+        assert!(app.command_history.is_empty());
+        assert_eq!(app.status_lines().last().unwrap(), "dang: running");
+
+        exit_tx
+            .send(Ok(SessionOutcome::TargetError("out of trace".to_string())))
+            .unwrap();
+        app.poll_dang_exit();
+        app.poll_notifications();
+        let expected = "error: dang stopped: target encountered a fatal error: out of trace";
+        // in the banner, not the command history
+        assert!(app.command_history.is_empty());
+        let banner = app.notifications.banner(Instant::now()).unwrap();
+        assert_eq!(view::notification_line(banner), expected);
+        assert_eq!(
+            app.status_lines().last().unwrap(),
+            "dang: target encountered a fatal error: out of trace"
+        );
+
+        // a key takes the banner down, it's still listed
+        app.handle_key(KeyEvent::from(KeyCode::Char('s')));
+        assert!(app.notifications.banner(Instant::now()).is_none());
+        let listed = app.notification_lines();
+        assert!(
+            listed[0].ends_with(&format!("[dang] {expected}")),
+            "{listed:?}"
+        );
+
+        // line mode has no banner, it's printed
+        let mut app = fixture_app();
+        app.cli_args.ui = cli::UiMode::Simple;
+        let (exit_tx, exit_rx) = mpsc::channel();
+        app.dang_exit = exit_rx;
+        exit_tx
+            .send(Ok(SessionOutcome::TargetError("out of trace".to_string())))
+            .unwrap();
+        app.poll_dang_exit();
+        app.poll_notifications();
+        assert_eq!(app.command_history, vec![expected]);
+
+        // a detach is how sessions normally end, nothing to shout about
+        let mut app = fixture_app();
+        let (exit_tx, exit_rx) = mpsc::channel();
+        app.dang_exit = exit_rx;
+        exit_tx.send(Ok(SessionOutcome::CleanDisconnect)).unwrap();
+        app.poll_dang_exit();
+        app.poll_notifications();
+        assert!(app.command_history.is_empty());
+        assert_eq!(app.notifications.history().count(), 0);
+    }
+
+    /// Type `line` into the input and press Enter, the way the terminal would
+    pub(crate) fn type_line(app: &mut App, line: &str) {
+        for c in line.chars() {
+            app.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+        app.handle_key(KeyEvent::from(KeyCode::Enter));
+    }
+
+    #[test]
+    fn test_confirmation_prompt() {
+        let mut app = fixture_app();
+        app.set_breakpoint(0x1003d0).unwrap();
+        app.set_breakpoint(0x1003d4).unwrap();
+
+        type_line(&mut app, "bdel all");
+        assert_eq!(
+            app.confirmation_prompt(),
+            Some("really delete 2 breakpoints? [y/N]")
+        );
+        app.handle_key(KeyEvent::from(KeyCode::Char('n')));
+        assert_eq!(app.confirmation_prompt(), None);
+        assert_eq!(app.breakpoint_count(), 2);
+        assert_eq!(app.command_history.last().unwrap(), "Cancelled");
+        assert!(app.input_buffer.is_empty());
+
+        // the help modal takes keys first and leaves the question waiting
+        type_line(&mut app, "bdel all");
+        app.help_modal_state.activate(vec!["help".to_string()]);
+        app.handle_key(KeyEvent::from(KeyCode::Enter));
+        assert!(!app.help_modal_state.is_active());
+        assert!(app.confirmation_prompt().is_some());
+        app.handle_key(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(app.confirmation_prompt(), None);
+        assert_eq!(app.breakpoint_count(), 0);
+
+        // one breakpoint isn't worth asking about
+        app.set_breakpoint(0x1003d0).unwrap();
+        type_line(&mut app, "bdel all");
+        assert_eq!(app.confirmation_prompt(), None);
+        assert_eq!(app.breakpoint_count(), 0);
+    }
+
+    #[test]
+    fn test_confirmation_times_out() {
+        let mut app = fixture_app();
+        app.set_breakpoint(0x1003d0).unwrap();
+        app.set_breakpoint(0x1003d4).unwrap();
+        type_line(&mut app, "bdel all");
+        let asked_at = app.confirmation.as_ref().unwrap().asked_at;
+
+        app.expire_confirmation(asked_at + CONFIRM_TIMEOUT / 2);
+        assert!(app.confirmation_prompt().is_some());
+        app.expire_confirmation(asked_at + CONFIRM_TIMEOUT);
+        assert_eq!(app.confirmation_prompt(), None);
+        assert_eq!(
+            app.command_history.last().unwrap(),
+            "Cancelled, no answer in time"
+        );
+
+        // back to normal input, a late y is just typed
+        app.handle_key(KeyEvent::from(KeyCode::Char('y')));
+        assert_eq!(app.input_buffer, "y");
+        assert_eq!(app.breakpoint_count(), 2);
+    }
+
+    #[test]
+    fn test_dump_asks_before_overwriting() {
+        let dir = std::env::temp_dir().join(format!("jpdb_confirm_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.txt");
+        std::fs::write(&path, "keep me").unwrap();
+
+        let mut app = fixture_app();
+        app.cli_args.ui = cli::UiMode::Simple;
+        let script = format!("dump state {0}\nn\ndump state {0}\ny\n", path.display());
+        let mut output = Vec::new();
+        line_ui::run(&mut app, script.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(
+            output.contains(&format!("overwrite {}? [y/N] Cancelled", path.display())),
+            "{output}"
+        );
+        assert!(output.contains("Wrote state dump to"), "{output}");
+        assert_ne!(std::fs::read_to_string(&path).unwrap(), "keep me");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// A scratch directory holding `script` as `name`
+    fn script_dir(test: &str, name: &str, script: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("jpdb_{test}_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(name), script).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_bexport_bimport_round_trip() {
+        let table = |app: &App| -> Vec<(u32, String, bool)> {
+            app.controller
+                .model
+                .breakpoints()
+                .iter()
+                .map(|bp| (bp.address, bp.spec.clone(), bp.enabled))
+                .collect()
+        };
+        let mut app = fixture_app();
+        app.set_breakpoint(0x1003d0).unwrap();
+        app.set_breakpoint_at_line("simple_system_common.c", 21, None)
+            .unwrap();
+        app.set_breakpoint(0x1003d4).unwrap();
+        type_line(&mut app, "bdisable 0x1003d4");
+
+        let extra = "b main\nbreak main if 1\n\n# unaligned\nb *0x1003d5\n";
+        let dir = script_dir("bexport", "extra.gdb", extra);
+        let path = dir.join("bps.gdb");
+        type_line(&mut app, &format!("bexport {}", path.display()));
+        assert_eq!(
+            app.command_history.last().unwrap(),
+            &format!("Wrote 3 breakpoint lines to {}", path.display())
+        );
+
+        let mut fresh = fixture_app();
+        type_line(&mut fresh, &format!("bimport {}", path.display()));
+        assert_eq!(table(&fresh), table(&app));
+        assert_eq!(
+            fresh.command_history.last().unwrap(),
+            &format!("Imported 3 of 3 breakpoint lines from {}", path.display())
+        );
+
+        // bad lines are reported by number and the rest still go in
+        let extra = dir.join("extra.gdb");
+        type_line(&mut fresh, &format!("bimport {}", extra.display()));
+        let history = fresh.command_history.join("\n");
+        assert!(
+            history.contains(&format!("{}:1: Breakpoint", extra.display())),
+            "{history}"
+        );
+        assert!(
+            history.contains(&format!(
+                "{}:2: error: conditions aren't supported",
+                extra.display()
+            )),
+            "{history}"
+        );
+        assert!(
+            history.contains(&format!("{}:5: error: ", extra.display())),
+            "{history}"
+        );
+        assert_eq!(
+            fresh.command_history.last().unwrap(),
+            &format!("Imported 1 of 3 breakpoint lines from {}", extra.display())
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_session_save_and_load() {
+        let mut app = fixture_app();
+        for line in [
+            "addsig pc_id",
+            "addsig pc_wb_o",
+            "format pc_wb_o sdec",
+            "b 0x1003d0",
+            "b simple_system_common.c:21",
+        ] {
+            type_line(&mut app, line);
+        }
+        let signals = app.controller.model.selected_signals();
+        assert_eq!(signals.len(), 2);
+        assert_eq!(signals[1].1, shucks::FormattingType::SignedDecimal);
+
+        let dir = script_dir("session", "unused", "");
+        let path = dir.join("saved.json");
+        type_line(&mut app, &format!("session save {}", path.display()));
+        assert_eq!(
+            app.command_history.last().unwrap(),
+            &format!(
+                "Saved 2 signals, 2 breakpoints and 6 history entries to {}",
+                path.display()
+            )
+        );
+
+        // picked up from next to the wave file on startup
+        let mut fresh = fixture_app();
+        fresh.cli_args.wave_path = dir.join("sim.fst");
+        fresh.cli_args.no_session = false;
+        std::fs::rename(&path, dir.join(session::SESSION_FILE)).unwrap();
+        fresh.restore_session();
+        assert_eq!(fresh.controller.model.selected_signals(), signals);
+        assert_eq!(fresh.export_breakpoints(), app.export_breakpoints());
+        assert_eq!(fresh.user_command_history, app.user_command_history);
+        assert!(
+            fresh
+                .command_history
+                .last()
+                .unwrap()
+                .starts_with("Loaded 2 of 2 signals, 2 of 2 breakpoints and 6 history entries"),
+            "{:?}",
+            fresh.command_history
+        );
+
+        // what's gone from the waveform or can't be set again is skipped with a warning
+        let stale = r#"{
+            "signals": [{"name": "TOP.gone", "radix": "bin"}],
+            "breakpoints": ["break nowhere.c:1", "break *0x1003d4"]
+        }"#;
+        std::fs::write(&path, stale).unwrap();
+        let mut fresh = fixture_app();
+        type_line(&mut fresh, &format!("session load {}", path.display()));
+        let history = fresh.command_history.join("\n");
+        assert!(
+            history.contains("warning: TOP.gone isn't in this waveform, skipped"),
+            "{history}"
+        );
+        assert!(
+            history.contains("warning: break nowhere.c:1: "),
+            "{history}"
+        );
+        assert!(
+            history.contains("Loaded 0 of 1 signals, 1 of 2 breakpoints"),
+            "{history}"
+        );
+        assert_eq!(fresh.breakpoint_count(), 1);
+
+        type_line(&mut fresh, "session");
+        assert_eq!(
+            fresh.command_history.last().unwrap(),
+            "error: usage: session <save|load> [path]"
+        );
+
+        // a session file that doesn't load isn't saved over on quit
+        let startup = dir.join(session::SESSION_FILE);
+        std::fs::write(&startup, "not a session").unwrap();
+        let mut broken = fixture_app();
+        broken.cli_args.wave_path = dir.join("sim.fst");
+        broken.cli_args.no_session = false;
+        broken.restore_session();
+        assert!(
+            broken
+                .command_history
+                .last()
+                .unwrap()
+                .starts_with("warning: "),
+            "{:?}",
+            broken.command_history
+        );
+        broken.save_session_on_exit();
+        assert_eq!(std::fs::read_to_string(&startup).unwrap(), "not a session");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_init_script() {
+        let script = "# project setup\naddsig pc_id\n\nb 0x1003d0\nset showbytes on\nhelp step\n";
+        let dir = script_dir("init", "init.jpdb", script);
+        let mut app = fixture_app();
+        app.cli_args.init_commands = Some(dir.join("init.jpdb"));
+        app.run_init_scripts();
+
+        assert_eq!(app.breakpoint_count(), 1);
+        let signals = app.pane_lines(Pane::Signals).unwrap().join("\n");
+        assert!(signals.contains("pc_id"), "{signals}");
+        assert!(app.command_history.contains(&"showbytes is on".to_string()));
+        assert!(app
+            .command_history
+            .contains(&"(jpdb) b 0x1003d0".to_string()));
+        // help went to the history, nothing modal is left open for the first prompt
+        assert!(!app.help_modal_state.is_active());
+        assert!(app
+            .command_history
+            .contains(&"Help for 'step':".to_string()));
+        assert!(!app.line_mode());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_init_script_stops_at_first_error() {
+        let script = "b 0x1003d0\nnosuchcommand\nb 0x1003d4\nset showbytes on\n";
+        let dir = script_dir("init_error", "init.jpdb", script);
+        let mut app = fixture_app();
+        app.cli_args.init_commands = Some(dir.join("init.jpdb"));
+        app.run_init_scripts();
+
+        assert_eq!(app.breakpoint_count(), 1);
+        assert!(!app
+            .command_history
+            .contains(&"(jpdb) b 0x1003d4".to_string()));
+        let last = app.command_history.last().unwrap();
+        assert!(
+            last.ends_with("init.jpdb:2 failed, skipped the rest of the script"),
+            "{last}"
+        );
+        // the session carries on
+        type_line(&mut app, "b 0x1003d4");
+        assert_eq!(app.breakpoint_count(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_local_init_needs_opting_in() {
+        let dir = script_dir("local_init", cli::LOCAL_INIT, "b 0x1003d0\n");
+        let mut app = fixture_app();
+        app.cli_args.wave_path = dir.join("sim.fst");
+        app.run_init_scripts();
+        assert_eq!(app.breakpoint_count(), 0);
+
+        app.cli_args.allow_local_init = true;
+        app.run_init_scripts();
+        assert_eq!(app.breakpoint_count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_logging_requests_redraw() {
+        use log::Log;
+
+        let redraw = RedrawFlag::default();
+        let (logger, buffer) = AppLogger::new(redraw.clone());
+        assert!(!redraw.take());
+
+        for _ in 0..3 {
+            logger.log(
+                &log::Record::builder()
+                    .args(format_args!("progress"))
+                    .level(log::Level::Info)
+                    .build(),
+            );
+        }
+        assert_eq!(buffer.lock().unwrap().count_at(log::Level::Trace), 3);
+        assert!(redraw.take());
+        assert!(!redraw.take());
+    }
+
+    #[test]
+    fn test_embedded_logger_levels() {
+        use log::Log;
+
+        let (logger, buffer) = AppLogger::embedded(RedrawFlag::default());
+        for (target, level) in [
+            ("dang::runtime", log::Level::Info),
+            ("dang::gdb", log::Level::Error),
+            ("gdbstub::protocol", log::Level::Warn),
+            ("dangling", log::Level::Debug),
+            ("shucks::client", log::Level::Debug),
+        ] {
+            logger.log(
+                &log::Record::builder()
+                    .args(format_args!("from {target}"))
+                    .target(target)
+                    .level(level)
+                    .build(),
+            );
+        }
+        // dang's errors get through, as does everything from outside it
+        assert_eq!(
+            buffer.lock().unwrap().last_lines(10),
+            [
+                "[ERROR] from dang::gdb",
+                "[DEBUG] from dangling",
+                "[DEBUG] from shucks::client"
+            ]
+        );
+
+        let logger = logger.with_module_filter("dang::runtime", log::LevelFilter::Debug);
+        let metadata = |target| {
+            log::Metadata::builder()
+                .target(target)
+                .level(log::Level::Debug)
+                .build()
+        };
+        assert!(logger.enabled(&metadata("dang::runtime::step")));
+        assert!(!logger.enabled(&metadata("dang::gdb")));
+    }
+
+    #[test]
+    fn test_logger_installs_twice_without_panicking() {
+        let (first, buffer) = AppLogger::embedded(RedrawFlag::default());
+        let (second, _) = AppLogger::embedded(RedrawFlag::default());
+        // dang's test stubs may have installed theirs already, which is as good as a first
+        let installed = first.install().is_ok();
+        assert!(second.install().is_err());
+
+        if installed {
+            log::error!(target: "dang::gdb", "embedded logger check error");
+            log::info!(target: "dang::gdb", "embedded logger check info");
+            let lines = buffer.lock().unwrap().last_lines(1000);
+            assert!(lines
+                .iter()
+                .any(|line| line.contains("embedded logger check error")));
+            assert!(!lines
+                .iter()
+                .any(|line| line.contains("embedded logger check info")));
+        }
+    }
+
+    #[test]
+    fn test_wave_load_flags_reach_both_loads() {
+        use argh::FromArgs;
+
+        let args = cli::JpdbArgs::from_args(
+            &["jpdb"],
+            &[
+                "--wave-path",
+                "sim.fst",
+                "--mapping-path",
+                "signal_get.py",
+                "--elf",
+                "hello_test.elf",
+                "--wave-load-threads",
+                "1",
+                "--wave-keep-scopes-with-empty-name",
+                "false",
+            ],
+        )
+        .unwrap();
+        let load = args.wave_load();
+        assert_eq!(load.threads, Some(1));
+        assert!(!load.keep_scopes_with_empty_name);
+
+        // jpdb's own signal browser loads single-threaded too
+        let mut controller = controller::test_utils::fixture_controller();
+        let client = &mut controller.model.client;
+        client
+            .load_waveform_with_options(test_data("sim.fst"), &load)
+            .unwrap();
+        assert!(client.wave_tracker.is_some());
+    }
+
+    #[test]
+    fn test_bad_paths_fail_before_touching_terminal() {
+        let good = cli::JpdbArgs {
+            wave_path: test_data("sim.fst"),
+            mapping_path: test_data("signal_get.py"),
+            elf: test_data("hello_test.elf"),
+            mapping_arg: Vec::new(),
+            scratch: None,
+            ui: cli::UiMode::Full,
+            init_commands: None,
+            allow_local_init: false,
+            no_session: false,
+            wave_load_threads: None,
+            wave_keep_scopes_with_empty_name: true,
+        };
+
+        let cases = [
+            (
+                "--wave-path",
+                cli::JpdbArgs {
+                    wave_path: test_data("missing.fst"),
+                    ..good.clone()
+                },
+            ),
+            (
+                "--mapping-path",
+                cli::JpdbArgs {
+                    mapping_path: test_data("missing.py"),
+                    ..good.clone()
+                },
+            ),
+            (
+                "--elf",
+                cli::JpdbArgs {
+                    elf: test_data("missing.elf"),
+                    ..good.clone()
+                },
+            ),
+        ];
+
+        for (flag, args) in cases {
+            let msg = startup_error(args);
+            assert!(msg.contains(flag), "{msg} should name {flag}");
+            assert!(msg.contains("missing."), "{msg} should name the path");
+            assert!(!crossterm::terminal::is_raw_mode_enabled().unwrap_or(false));
+        }
+    }
+}
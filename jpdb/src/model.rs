@@ -1,47 +1,500 @@
-use shucks::{Client, TimeTableIdx, Var};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use dang::convert::Mappable;
+use dang::skip_ranges::SkipRange;
+use shucks::client::{
+    Assertion, BreakpointChange, DebugInfo, RegChange, StopReport, SymbolInfo,
+    DEFAULT_INSTRUCTION_WINDOW, MAX_INSTRUCTION_WINDOW,
+};
+use shucks::findinst::{FindOutcome, InstPredicate, SearchDirection, DEFAULT_FIND_BUDGET};
+use shucks::response::ThreadId;
+use shucks::{
+    Client, FormattingType, NameFormat, SignalStats, SignalValueResult, TimeTableIdx,
+    TrackerCounts, Var,
+};
+
+use crate::addr_format::AddrFormatter;
+use crate::breakpoints::{Breakpoint, BreakpointTable, BulkResult, Selector};
+use crate::code_markers::CodeMarkers;
+use crate::delta::{self, DeltaHistory, Snapshot};
+use crate::user_commands::SkipTarget;
+use crate::value_names;
 
 pub struct DebuggerModel {
     pub client: Client,
     cached_time_idx: Option<u64>,
     terminated: bool,
+    show_bytes: bool,
+    show_heat: bool,
+    bpcheck: bool,
+    /// How many instructions `findinst` looks at before giving up
+    find_budget: usize,
+    /// How many instructions an execution snapshot decodes, `set instwindow`
+    instruction_window: usize,
+    /// Check for the ELF being rebuilt before symbol and line lookups
+    elfwatch: bool,
+    /// Set once the rebuilt ELF has been warned about, until it's reloaded
+    elf_change_warned: bool,
+    /// Set once the rewritten waveform has been warned about, until it's reloaded
+    wave_change_warned: bool,
+    fetch_counts: FetchCounts,
+    /// Every breakpoint set through jpdb, so it can be listed without asking the target
+    breakpoints: BreakpointTable,
+    /// Assertions set through jpdb by id, mirrored for the same reason
+    assertions: BTreeMap<u32, String>,
+    /// Which source lines have code, filled in a frame at a time
+    code_markers: CodeMarkers,
+    /// Full path of the file the last source snapshot is in
+    source_path: Option<PathBuf>,
+    /// Where the last step or continue started, once there's been one. `Some(None)` when the
+    /// time index wasn't cached at the time, [`DebuggerModel::last_resume`] asks the target then
+    resumed_from: Option<Option<u64>>,
+    /// What the last two `delta`s saw
+    delta_history: DeltaHistory,
+    /// The waveform's timescale, which the Signals pane shows times in
+    time_unit: String,
+    /// How addresses are written everywhere, `set addrfmt`
+    pub addr_fmt: AddrFormatter,
+}
+
+/// What times are taken to be in when the target can't say, as most traces are
+const DEFAULT_TIME_UNIT: &str = "ps";
+
+/// The waveform's timescale, asked of the target once there's a waveform to show times from,
+/// or read off the waveform's own header when the target can't say
+fn time_unit(client: &mut Client) -> String {
+    let Some(tracker) = &client.wave_tracker else {
+        return DEFAULT_TIME_UNIT.to_string();
+    };
+    let header_unit = tracker.get_scale_factor();
+    match client.get_time() {
+        Ok((_, unit)) => unit,
+        Err(e) => {
+            let unit = header_unit.unwrap_or_else(|| DEFAULT_TIME_UNIT.to_string());
+            log::debug!("The target didn't say what times are in, taking {unit}: {e}");
+            unit
+        }
+    }
+}
+
+/// The hart a thread id stands for, dang numbering its harts as threads from 1
+fn hart_label(thread: &ThreadId) -> String {
+    match thread {
+        ThreadId::Specific(tid) | ThreadId::Process { tid, .. } => tid.to_string(),
+        ThreadId::Any | ThreadId::All => "unknown".to_string(),
+    }
+}
+
+/// How many snapshots of each kind have been fetched, so hidden panes can be checked to stay
+/// quiet
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FetchCounts {
+    pub execution: u64,
+    pub source: u64,
+    pub signals: u64,
+    /// Instructions asked for over all the execution snapshots
+    pub instructions: u64,
+}
+
+/// Why the target is sitting where it is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopKind {
+    Step,
+    /// Continue gave up on a PC looping with no breakpoint
+    Livelock,
+    /// One of the target-side assertions stopped holding
+    Assertion,
+}
+
+/// One line of the instruction listing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionEntry {
+    pub addr: u32,
+    /// Disassembly, or a `.word` placeholder for bytes that don't decode
+    pub text: String,
+    /// Raw encoding, only fetched with `showbytes on`
+    pub bytes: Option<Vec<u8>>,
+    pub is_current: bool,
+    /// `module`function+offset at file:line`, only looked up for the current instruction
+    pub symbol: Option<String>,
+}
+
+/// Where the target is stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub pc: u32,
+    pub symbol: String,
+    /// The current instruction and the ones after it
+    pub instructions: Result<Vec<InstructionEntry>, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecutionSnapshot {
+    Terminated,
+    Stopped {
+        stop: StopKind,
+        frame: Result<Frame, String>,
+    },
+}
+
+/// One line of the source listing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceEntry {
+    pub line_no: u64,
+    /// `None` if the file can't be read
+    pub text: Option<String>,
+    pub is_current: bool,
+    /// Whether the line table has code on the line, `None` until it's been looked up
+    pub has_code: Option<bool>,
+    /// How many times the line ran, only fetched with `heat on`
+    pub heat: Option<u64>,
+    /// 1-based column execution is at, on the current line when the line info has columns
+    pub column: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceSnapshot {
+    Terminated,
+    Located {
+        file: String,
+        /// The current line first
+        entries: Vec<SourceEntry>,
+        /// Heat of the hottest line around the current one, what the heat gutter scales against
+        max_heat: u64,
+        /// Set if the lines after the current one couldn't be fetched
+        next_lines_error: Option<String>,
+    },
+    /// The PC has no line info
+    NoDebugInfo,
+    /// The whole ELF has no line info, so there's nothing to look up
+    ElfWithoutDebugInfo {
+        elf: String,
+        /// Why a debug file named by the ELF couldn't be used, `None` if it never named one
+        missing: Option<String>,
+    },
+    Error(String),
+}
+
+/// A selected signal's [`SignalStats`], with its first and last change as times in the waveform's
+/// timescale
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignalStatsRow {
+    pub name: String,
+    pub stats: SignalStats,
+    pub first_time: Option<u64>,
+    pub last_time: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignalEntry {
+    /// Full hierarchical name
+    pub name: String,
+    /// Value in `format`, or why there isn't one
+    pub value: SignalValueResult,
+    pub format: FormattingType,
+    /// Changed at all during the last step or continue, even if it's back where it started
+    pub changed: bool,
 }
 
-pub struct ExecutionSnapshot {
-    pub summary_lines: Vec<String>,
-    pub instruction_lines: Vec<String>,
+/// Whether a selected signal changed during the last step or continue, for `sigchanged`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignalChangeRow {
+    pub name: String,
+    pub changed: bool,
+    /// How many times, `None` if the signal's data isn't loaded
+    pub changes: Option<usize>,
 }
 
-pub struct SourceSnapshot {
-    pub lines: Vec<String>,
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignalSnapshot {
+    Terminated,
+    NoWaveform,
+    Values {
+        /// Simulation time at the cursor, in `unit`, `None` if the cursor is past the end of the
+        /// waveform's time table
+        time: Option<u64>,
+        /// Where execution is stopped, in `unit`, when the pane is pinned somewhere else
+        exec_time: Option<u64>,
+        /// The waveform's timescale, `ps` or `10ns`, empty if it doesn't have one
+        unit: String,
+        entries: Vec<SignalEntry>,
+    },
 }
 
-pub struct SignalSnapshot {
-    pub lines: Vec<String>,
+/// Where `sigtime` points the Signals pane
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigTime {
+    /// Follow execution again
+    Off,
+    /// A time index
+    Index(u64),
+    /// A simulation time in ps, pinned to the last time index at or before it
+    Time(u64),
+    /// This many time indices from wherever the pane is now
+    Relative(i64),
+}
+
+/// A GPR's value, and when it last changed if that was asked for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterEntry {
+    pub reg: usize,
+    pub value: u32,
+    pub last_change: Option<RegChange>,
 }
 
 pub type ModelResult<T> = Result<T, String>;
 
+/// A breakpoint that isn't on an instruction boundary can never fire
+pub fn check_breakpoint_alignment(
+    address: u32,
+    alignment: u32,
+    addr_fmt: &AddrFormatter,
+) -> ModelResult<()> {
+    if address % alignment != 0 {
+        return Err(format!(
+            "{} is not on a {alignment} byte instruction boundary",
+            addr_fmt.hex(address.into())
+        ));
+    }
+    Ok(())
+}
+
 impl DebuggerModel {
-    pub fn new(client: Client) -> Self {
+    pub fn new(mut client: Client) -> Self {
         Self {
+            time_unit: time_unit(&mut client),
+            // the handshake's `?` can find the program already gone
+            terminated: client.has_exited(),
             client,
             cached_time_idx: None,
-            terminated: false,
+            show_bytes: false,
+            show_heat: false,
+            bpcheck: true,
+            find_budget: DEFAULT_FIND_BUDGET,
+            instruction_window: DEFAULT_INSTRUCTION_WINDOW,
+            elfwatch: true,
+            elf_change_warned: false,
+            wave_change_warned: false,
+            fetch_counts: FetchCounts::default(),
+            breakpoints: BreakpointTable::default(),
+            assertions: BTreeMap::new(),
+            code_markers: CodeMarkers::default(),
+            source_path: None,
+            resumed_from: None,
+            delta_history: DeltaHistory::default(),
+            addr_fmt: AddrFormatter::default(),
         }
     }
 
+    pub fn set_show_bytes(&mut self, show_bytes: bool) {
+        self.show_bytes = show_bytes;
+    }
+
+    /// Decode `count` instructions from the PC on for each execution snapshot, at most
+    /// [`MAX_INSTRUCTION_WINDOW`]
+    pub fn set_instruction_window(&mut self, count: usize) {
+        self.instruction_window = count.clamp(1, MAX_INSTRUCTION_WINDOW);
+    }
+
+    pub fn instruction_window(&self) -> usize {
+        self.instruction_window
+    }
+
+    pub fn set_show_heat(&mut self, show_heat: bool) {
+        self.show_heat = show_heat;
+    }
+
+    pub fn set_bpcheck(&mut self, bpcheck: bool) {
+        self.bpcheck = bpcheck;
+    }
+
+    pub fn set_find_budget(&mut self, find_budget: usize) {
+        self.find_budget = find_budget;
+    }
+
+    pub fn set_elfwatch(&mut self, elfwatch: bool) {
+        self.elfwatch = elfwatch;
+    }
+
+    /// A warning the first time the ELF on disk is seen to differ from the one symbols and line
+    /// info were read from, `None` otherwise or with `set elfwatch off`. Only a stat unless the
+    /// file looks changed
+    pub fn check_elf(&mut self) -> Option<String> {
+        if !self.elfwatch || self.elf_change_warned || !self.client.elf_is_stale() {
+            return None;
+        }
+        self.elf_change_warned = true;
+        let path = self
+            .client
+            .elf_path()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| "the ELF".to_string());
+        Some(format!(
+            "{path} has changed on disk, symbols and line info are from the old build. Run \
+             'reloadelf' to load the new one"
+        ))
+    }
+
+    /// Re-read the ELF after a rebuild: symbols, line info and the symbol cache, then move
+    /// `file:line` breakpoints to wherever their lines are now
+    pub fn reload_elf(&mut self) -> ModelResult<Vec<String>> {
+        self.client.load_elf_info().map_err(|e| e.to_string())?;
+        self.elf_change_warned = false;
+        let path = self
+            .client
+            .elf_path()
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        let mut lines = vec![
+            format!("Reloaded symbols and line info from {path}"),
+            "Note: dang keeps serving memory from the ELF it was started with, so memory reads \
+             still show the old build"
+                .to_string(),
+        ];
+        lines.extend(self.reresolve_line_breakpoints());
+        Ok(lines)
+    }
+
+    /// A warning the first time the waveform on disk is seen to differ from the one the Signals
+    /// pane reads from, `None` otherwise. Only a stat
+    pub fn check_wave(&mut self) -> Option<String> {
+        if self.wave_change_warned || !self.client.waveform_is_stale() {
+            return None;
+        }
+        self.wave_change_warned = true;
+        let path = self
+            .client
+            .wave_tracker
+            .as_ref()
+            .map(|tracker| tracker.path().display().to_string())
+            .unwrap_or_else(|| "the waveform".to_string());
+        Some(format!(
+            "{path} changed on disk. Restart dang or run 'reloadwave' to pick up the new one"
+        ))
+    }
+
+    /// Re-read the waveform after the simulation rewrote it, selecting the same signals again
+    /// by name. One line for the reload, one per signal that's no longer there, and one if dang
+    /// is still serving the old trace
+    pub fn reload_wave(&mut self) -> ModelResult<Vec<String>> {
+        let tracker = self
+            .client
+            .wave_tracker
+            .as_mut()
+            .ok_or("No waveform loaded")?;
+        let missing = tracker.reload().map_err(|e| e.to_string())?;
+        let mut lines = vec![format!(
+            "Reloaded signals from {}",
+            tracker.path().display()
+        )];
+        lines.extend(missing.iter().map(|name| {
+            format!("{name} is no longer in the waveform, dropped from the Signals pane")
+        }));
+        self.wave_change_warned = false;
+        self.invalidate_time_index();
+        if let Ok(Some(stale)) = self.client.stub_waveform_change() {
+            lines.push(format!(
+                "Note: dang is still serving the old trace: {stale}"
+            ));
+        }
+        Ok(lines)
+    }
+
+    /// Move every `file:line` breakpoint whose line now sits somewhere else, keeping whether it
+    /// was enabled. Moved breakpoints get new ids. One line per spec that moved or no longer
+    /// resolves
+    fn reresolve_line_breakpoints(&mut self) -> Vec<String> {
+        let mut by_spec: BTreeMap<String, Vec<Breakpoint>> = BTreeMap::new();
+        for bp in self
+            .breakpoints
+            .iter()
+            .filter(|bp| bp.file_line().is_some())
+        {
+            by_spec.entry(bp.spec.clone()).or_default().push(bp.clone());
+        }
+
+        let addr_fmt = self.addr_fmt;
+        let hex_list = |addrs: &BTreeSet<u32>| {
+            addrs
+                .iter()
+                .map(|&addr| addr_fmt.hex(addr.into()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut report = Vec::new();
+        for (spec, old) in by_spec {
+            let Some((file, line, column)) = old[0].file_line_column() else {
+                continue;
+            };
+            let old_addrs: BTreeSet<u32> = old.iter().map(|bp| bp.address).collect();
+            let resolved = self.client.addresses_for_location(file, line, column);
+            let new_addrs: BTreeSet<u32> = match resolved {
+                Ok(addrs) => addrs.into_iter().collect(),
+                Err(e) => {
+                    report.push(format!(
+                        "Breakpoint {spec} no longer resolves, left at {}: {e}",
+                        hex_list(&old_addrs)
+                    ));
+                    continue;
+                }
+            };
+            if new_addrs == old_addrs {
+                continue;
+            }
+
+            let enabled = old.iter().any(|bp| bp.enabled);
+            let touched: BTreeSet<u32> = old_addrs.union(&new_addrs).copied().collect();
+            let armed_before: BTreeSet<u32> = touched
+                .iter()
+                .copied()
+                .filter(|&addr| self.breakpoints.armed_at(addr))
+                .collect();
+            for bp in &old {
+                self.breakpoints.remove(bp.id);
+            }
+            let ids: Vec<String> = new_addrs
+                .iter()
+                .map(|&addr| {
+                    let bp = self.breakpoints.add(addr, &spec);
+                    self.breakpoints.set_enabled(bp.id, enabled);
+                    bp.id.to_string()
+                })
+                .collect();
+            if let Err(e) = self.sync_target(&touched, &armed_before) {
+                report.push(e);
+            }
+            report.push(format!(
+                "Breakpoint {spec} moved from {} to {} (now {})",
+                hex_list(&old_addrs),
+                hex_list(&new_addrs),
+                ids.join(", ")
+            ));
+        }
+        report
+    }
+
+    pub fn fetch_counts(&self) -> FetchCounts {
+        self.fetch_counts
+    }
+
+    /// Whether there's a waveform to pull signal values from
+    pub fn has_waveform(&self) -> bool {
+        self.client.wave_tracker.is_some()
+    }
+
     pub fn step(&mut self) -> ModelResult<()> {
         if self.terminated {
             return Err("Process has terminated".to_string());
         }
 
-        let still_alive = self.client.step().map_err(|e| e.to_string())?;
-        if !still_alive {
-            self.terminated = true;
-            return Err("Process has terminated".to_string());
-        }
+        self.note_resume_start();
+        let result = self.client.step();
+        self.note_exit()?;
+        result.map_err(|e| e.to_string())?;
 
         self.invalidate_time_index();
+        self.refresh_ignore_counts();
         Ok(())
     }
 
@@ -50,174 +503,970 @@ impl DebuggerModel {
             return Err("Process has terminated".to_string());
         }
 
-        let still_alive = self
-            .client
-            .continue_execution()
-            .map_err(|e| e.to_string())?;
-        if !still_alive {
+        self.note_resume_start();
+        let result = self.client.continue_execution();
+        self.note_exit()?;
+        result.map_err(|e| e.to_string())?;
+
+        self.invalidate_time_index();
+        self.refresh_ignore_counts();
+        Ok(())
+    }
+
+    /// Whether the target has exited or been terminated, after which nothing can be resumed
+    pub fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+
+    /// Take a `W` or `X` stop reply to the last resume as the end of the program
+    fn note_exit(&mut self) -> ModelResult<()> {
+        if self.client.has_exited() {
             self.terminated = true;
             return Err("Process has terminated".to_string());
         }
+        Ok(())
+    }
+
+    /// Note where a resume starts, for [`DebuggerModel::last_resume`]. The time index is only
+    /// uncached here when nothing looked at the stop before, `step 5` say, and then the resume
+    /// carries on from the last stop that was shown
+    fn note_resume_start(&mut self) {
+        if self.cached_time_idx.is_some() || self.resumed_from.is_none() {
+            self.resumed_from = Some(self.cached_time_idx);
+        }
+    }
+
+    /// Re-read what's left of the ignore counts from the target, which uses them up without
+    /// telling us. Only asks while some breakpoint has one
+    fn refresh_ignore_counts(&mut self) {
+        if self.breakpoints.iter().all(|bp| bp.ignore_remaining == 0) {
+            return;
+        }
+        match self.client.target_breakpoint_entries() {
+            Ok(entries) => {
+                for entry in entries {
+                    self.breakpoints
+                        .set_ignore_remaining(entry.addr, entry.ignore_remaining);
+                }
+            }
+            Err(e) => log::warn!("Couldn't refresh breakpoint ignore counts: {e}"),
+        }
+    }
+
+    /// Move to the nearest executed instruction in `direction` matching `predicate`, looking at
+    /// no more than the find budget's worth
+    pub fn find_instruction(
+        &mut self,
+        direction: SearchDirection,
+        predicate: &InstPredicate,
+    ) -> ModelResult<FindOutcome> {
+        if self.terminated {
+            return Err("Process has terminated".to_string());
+        }
+        let outcome = self
+            .client
+            .find_instruction(direction, predicate, self.find_budget)
+            .map_err(|e| e.to_string())?;
+        self.invalidate_time_index();
+        Ok(outcome)
+    }
 
+    pub fn set_stop_on_livelock(&mut self, stop: bool) -> ModelResult<()> {
+        let output = self
+            .client
+            .send_monitor_command(if stop {
+                "stop_on_livelock on"
+            } else {
+                "stop_on_livelock off"
+            })
+            .map_err(|e| e.to_string())?;
+        log::debug!("stop_on_livelock: {}", output.trim());
+        Ok(())
+    }
+
+    /// Send `cmd` to dang as is, like gdb's `monitor`. It may have moved the cursor, so the
+    /// cached time index is dropped
+    pub fn monitor(&mut self, cmd: &str) -> ModelResult<String> {
+        let output = self
+            .client
+            .send_monitor_command(cmd)
+            .map_err(|e| e.to_string())?;
         self.invalidate_time_index();
+        Ok(output)
+    }
+
+    pub fn stopped_on_livelock(&self) -> bool {
+        self.client.stopped_on_livelock()
+    }
+
+    /// Every value GPR `reg` takes over the whole trace as `(time_idx, value)`, read off the
+    /// trace without moving the cursor
+    pub fn reg_history(&mut self, reg: usize) -> ModelResult<Vec<(u64, u32)>> {
+        let history = self
+            .client
+            .reg_history(reg, 0..u64::MAX, 1)
+            .map_err(|e| e.to_string())?;
+        Ok(history.collect())
+    }
+
+    /// What the last step or continue covered, as counted by the target, and the assertion
+    /// that stopped it if one did
+    pub fn stop_report(&mut self) -> ModelResult<StopReport> {
+        self.client.stop_report().map_err(|e| e.to_string())
+    }
+
+    pub fn stopped_on_assertion(&self) -> bool {
+        self.client.stopped_on_assertion()
+    }
+
+    /// Stop whenever `x<reg> <op> value` stops holding
+    pub fn assert_reg(&mut self, reg: usize, op: &str, value: u32) -> ModelResult<Assertion> {
+        let assertion = self
+            .client
+            .assert_reg(reg, op, value)
+            .map_err(|e| e.to_string())?;
+        self.assertions
+            .insert(assertion.id, assertion.check.clone());
+        Ok(assertion)
+    }
+
+    /// Stop whenever the PC leaves `[lo, hi]`, or enters it if `inside` is false
+    pub fn assert_pc_range(&mut self, lo: u32, hi: u32, inside: bool) -> ModelResult<Assertion> {
+        let assertion = self
+            .client
+            .assert_pc_range(lo, hi, inside)
+            .map_err(|e| e.to_string())?;
+        self.assertions
+            .insert(assertion.id, assertion.check.clone());
+        Ok(assertion)
+    }
+
+    pub fn delete_assertion(&mut self, id: u32) -> ModelResult<()> {
+        let deleted = self
+            .client
+            .delete_assertion(id)
+            .map_err(|e| e.to_string())?;
+        self.assertions.remove(&id);
+        if !deleted {
+            return Err(format!("No assertion {id}"));
+        }
         Ok(())
     }
 
-    pub fn set_breakpoint(&mut self, address: u32) -> ModelResult<()> {
+    /// Register `target` as a skip range. Lines resolve through the line info to the lowest
+    /// and highest address behind them, lines without code are passed over
+    pub fn add_skip_range(&mut self, target: &SkipTarget) -> ModelResult<SkipRange> {
+        let (lo, hi) = match target {
+            SkipTarget::Addresses { lo, hi } => (*lo, *hi),
+            SkipTarget::Lines { file, first, last } => {
+                let addresses: Vec<u32> = (*first..=*last)
+                    .filter_map(|line| self.client.addresses_for_line(file, line).ok())
+                    .flatten()
+                    .collect();
+                match (addresses.iter().min(), addresses.iter().max()) {
+                    (Some(lo), Some(hi)) => (*lo, *hi),
+                    _ => return Err(format!("No code behind {file}:{first}-{last}")),
+                }
+            }
+        };
         self.client
-            .set_breakpoint(address)
+            .add_skip_range(lo, hi)
             .map_err(|e| e.to_string())
     }
 
-    pub fn set_breakpoint_at_line(&mut self, file: &str, line: u64) -> ModelResult<Vec<u32>> {
+    pub fn skip_ranges(&mut self) -> ModelResult<Vec<SkipRange>> {
+        self.client.skip_ranges().map_err(|e| e.to_string())
+    }
+
+    pub fn delete_skip_range(&mut self, id: u32) -> ModelResult<()> {
+        let deleted = self
+            .client
+            .delete_skip_range(id)
+            .map_err(|e| e.to_string())?;
+        if !deleted {
+            return Err(format!("No skip range {id}"));
+        }
+        Ok(())
+    }
+
+    /// Whether continue jumps across the skip ranges
+    pub fn set_fastforward_ranges(&mut self, on: bool) -> ModelResult<()> {
         self.client
-            .set_breakpoint_at_line(file, line)
+            .set_fastforward_ranges(on)
             .map_err(|e| e.to_string())
     }
 
-    pub fn fetch_execution_snapshot(&mut self) -> ModelResult<ExecutionSnapshot> {
-        if self.terminated {
-            return Ok(ExecutionSnapshot {
-                summary_lines: vec!["Process has terminated".to_string()],
-                instruction_lines: vec!["Process has terminated".to_string()],
-            });
+    /// Patch target memory. Only the scratch region is writable, so check that up front and
+    /// give a useful message instead of a bare error code
+    pub fn write_memory(&mut self, addr: u32, data: &[u8]) -> ModelResult<()> {
+        let scratch = self.client.scratch_info().map_err(|e| e.to_string())?;
+        let Some((start, size)) = scratch else {
+            return Err("No scratch region configured, start jpdb with --scratch".to_string());
+        };
+        let end = addr as u64 + data.len() as u64;
+        if addr < start || end > start as u64 + size as u64 {
+            let hex = |addr: u64| self.addr_fmt.hex(addr);
+            return Err(format!(
+                "{}..{} is outside the scratch region {}..{}",
+                hex(addr.into()),
+                hex(end),
+                hex(start.into()),
+                hex(start as u64 + size as u64)
+            ));
         }
+        self.client
+            .write_memory(addr, data)
+            .map_err(|e| e.to_string())
+    }
 
-        let mut summary_lines = Vec::new();
-        summary_lines.push("Process 1 stopped".to_string());
-        summary_lines.push("* thread #1, stop reason = instruction step over".to_string());
+    pub fn set_breakpoint(&mut self, address: u32) -> ModelResult<Breakpoint> {
+        check_breakpoint_alignment(address, self.client.instruction_alignment(), &self.addr_fmt)?;
+        let change = self
+            .client
+            .set_breakpoint(address)
+            .map_err(|e| e.to_string())?;
+        log_breakpoint_change(address, change);
+        let spec = self.addr_fmt.hex(address.into());
+        Ok(self.breakpoints.add(address, &spec))
+    }
 
-        let mut instruction_lines = Vec::new();
+    /// Breakpoints on every address of `file:line`, or only those of the statement at `column`
+    pub fn set_breakpoint_at_line(
+        &mut self,
+        file: &str,
+        line: u64,
+        column: Option<u64>,
+    ) -> ModelResult<Vec<Breakpoint>> {
+        if !self.client.has_debug_info() {
+            return Err(format!(
+                "{} has no debug info, so {file}:{line} can't be resolved. \
+                 Break on an address instead, Tab after `b ` completes symbols to theirs",
+                self.elf_name()
+            ));
+        }
+        let addresses = self
+            .client
+            .set_breakpoint_at_line(file, line, column)
+            .map_err(|e| e.to_string())?;
+        let spec = match column {
+            Some(column) => format!("{file}:{line}:{column}"),
+            None => format!("{file}:{line}"),
+        };
+        Ok(addresses
+            .into_iter()
+            .map(|address| self.breakpoints.add(address, &spec))
+            .collect())
+    }
+
+    /// Let the next `count` hits of breakpoint `id` go by. The target does the counting, so a
+    /// continue runs straight past them
+    pub fn ignore_breakpoint(&mut self, id: u32, count: u32) -> ModelResult<Breakpoint> {
+        let breakpoint = self
+            .breakpoints
+            .get(id)
+            .cloned()
+            .ok_or_else(|| format!("No breakpoint {id}"))?;
+        if !breakpoint.enabled {
+            return Err(format!("Breakpoint {id} is disabled, enable it first"));
+        }
+        self.client
+            .set_breakpoint_ignore(breakpoint.address, count)
+            .map_err(|e| e.to_string())?;
+        self.breakpoints
+            .set_ignore_remaining(breakpoint.address, count);
+        Ok(Breakpoint {
+            ignore_remaining: count,
+            ..breakpoint
+        })
+    }
+
+    /// Where the ELF symbol `name` starts
+    pub fn symbol_address(&self, name: &str) -> Option<u32> {
+        self.symbol_named(name).map(|sym| sym.addr as u32)
+    }
+
+    /// The ELF symbol `name`
+    pub fn symbol_named(&self, name: &str) -> Option<&SymbolInfo> {
+        self.client.symbols().iter().find(|sym| sym.name == name)
+    }
 
+    /// Up to `count` instructions from `addr` for `disas`, ending before `end` if there is
+    /// one. Where execution is stays as it was, the instruction it's at is only marked
+    pub fn disassemble(
+        &mut self,
+        addr: u32,
+        count: usize,
+        end: Option<u32>,
+    ) -> ModelResult<Vec<InstructionEntry>> {
+        let current = if self.terminated {
+            None
+        } else {
+            self.client.get_current_pc().ok().map(|pc| pc.as_u32())
+        };
+        let instructions = self
+            .client
+            .disassemble(addr, count)
+            .map_err(|e| e.to_string())?;
+        Ok(instructions
+            .iter()
+            .map(|inst| (inst.pc().as_u32(), inst))
+            .take_while(|(inst_addr, _)| end.map_or(true, |end| *inst_addr < end))
+            .map(|(inst_addr, inst)| InstructionEntry {
+                addr: inst_addr,
+                text: inst.to_string(),
+                bytes: self.show_bytes.then(|| inst.bytes().to_vec()),
+                is_current: current == Some(inst_addr),
+                symbol: None,
+            })
+            .collect())
+    }
+
+    pub fn breakpoints(&self) -> &BreakpointTable {
+        &self.breakpoints
+    }
+
+    /// Put the stop at `time_idx` down to the breakpoints where execution is, returning their ids
+    pub fn record_breakpoint_hit(&mut self, time_idx: u64) -> Vec<u32> {
         match self.client.get_current_pc() {
-            Ok(current_pc) => {
-                summary_lines.push(format!("    frame #0: 0x{current_pc}"));
-
-                match self.client.get_current_and_next_inst() {
-                    Ok(insts) => {
-                        for (i, inst) in insts.iter().enumerate() {
-                            let inst_pc = inst.pc().as_u32();
-                            let formatted = if i == 0 {
-                                format!("->  0x{inst_pc:x}: {inst}")
-                            } else {
-                                format!("    0x{inst_pc:x}: {inst}")
-                            };
-                            summary_lines.push(formatted.clone());
-                            instruction_lines.push(formatted);
-                        }
-                    }
-                    Err(_) => {
-                        summary_lines
-                            .push(format!("->  0x{current_pc}: <unable to get instructions>"));
-                        instruction_lines
-                            .push(format!("->  0x{current_pc}: <unable to get instructions>"));
-                    }
+            Ok(pc) => self.breakpoints.record_hit(pc.as_u32(), time_idx),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// What waveform times are in, `ps` when neither the target nor the waveform says
+    pub fn time_unit(&self) -> &str {
+        &self.time_unit
+    }
+
+    /// The waveform time at `time_idx`, `None` without a waveform or past its end
+    pub fn time_at(&self, time_idx: u64) -> Option<u64> {
+        self.client
+            .wave_tracker
+            .as_ref()
+            .and_then(|tracker| tracker.get_current_time(time_idx as TimeTableIdx))
+    }
+
+    /// Delete every breakpoint matched by `selectors`
+    pub fn delete_breakpoints(&mut self, selectors: &[Selector]) -> ModelResult<BulkResult> {
+        let result = self.breakpoints.select(selectors, &self.addr_fmt);
+        self.delete_selected(result)
+    }
+
+    /// Delete every breakpoint set from a `file:line` spec naming `file`
+    pub fn clear_file_breakpoints(&mut self, file: &str) -> ModelResult<BulkResult> {
+        let result = self.breakpoints.select_file(file);
+        self.delete_selected(result)
+    }
+
+    /// Enable or disable every breakpoint matched by `selectors`. Disabled breakpoints stay in
+    /// the table but are taken off the target
+    pub fn set_breakpoints_enabled(
+        &mut self,
+        selectors: &[Selector],
+        enabled: bool,
+    ) -> ModelResult<BulkResult> {
+        let result = self.breakpoints.select(selectors, &self.addr_fmt);
+        let addresses = self.addresses_of(&result.affected);
+        let armed_before: BTreeSet<u32> = addresses
+            .iter()
+            .copied()
+            .filter(|&addr| self.breakpoints.armed_at(addr))
+            .collect();
+        for &id in &result.affected {
+            self.breakpoints.set_enabled(id, enabled);
+        }
+        self.sync_target(&addresses, &armed_before)?;
+        Ok(result)
+    }
+
+    fn delete_selected(&mut self, result: BulkResult) -> ModelResult<BulkResult> {
+        let addresses = self.addresses_of(&result.affected);
+        let armed_before: BTreeSet<u32> = addresses
+            .iter()
+            .copied()
+            .filter(|&addr| self.breakpoints.armed_at(addr))
+            .collect();
+        for &id in &result.affected {
+            self.breakpoints.remove(id);
+        }
+        self.sync_target(&addresses, &armed_before)?;
+        Ok(result)
+    }
+
+    fn addresses_of(&self, ids: &[u32]) -> BTreeSet<u32> {
+        ids.iter()
+            .filter_map(|&id| self.breakpoints.get(id))
+            .map(|bp| bp.address)
+            .collect()
+    }
+
+    /// Bring the target in line with the table for `addresses`: one Z0 or z0 per address whose
+    /// armed state changed, however many table entries share it
+    fn sync_target(
+        &mut self,
+        addresses: &BTreeSet<u32>,
+        armed_before: &BTreeSet<u32>,
+    ) -> ModelResult<()> {
+        let mut errors = Vec::new();
+        for &addr in addresses {
+            let armed_now = self.breakpoints.armed_at(addr);
+            let result = match (armed_before.contains(&addr), armed_now) {
+                (true, false) => {
+                    // the count goes with the target's breakpoint
+                    self.breakpoints.set_ignore_remaining(addr, 0);
+                    self.client.remove_breakpoint(addr)
                 }
-            }
-            Err(e) => {
-                summary_lines.push(format!("Error getting PC: {e}"));
-                instruction_lines.push(format!("Error: {e}"));
+                (false, true) => self.client.set_breakpoint(addr),
+                _ => continue,
+            };
+            match result {
+                Ok(change) => log_breakpoint_change(addr, change),
+                Err(e) => errors.push(format!("{}: {e}", self.addr_fmt.hex(addr.into()))),
             }
         }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "target rejected breakpoint changes at {}",
+                errors.join(", ")
+            ))
+        }
+    }
 
-        summary_lines.push("Target 0: (No executable module.) stopped.".to_string());
+    /// Breakpoint table, one `<id>: <addr> <symbolized>` line per breakpoint, then one
+    /// `assert <id>: <check>` line per assertion
+    pub fn breakpoint_lines(&self) -> Vec<String> {
+        let assertions = self
+            .assertions
+            .iter()
+            .map(|(id, check)| format!("assert {id}: {check}"));
+        self.breakpoints
+            .iter()
+            .map(|bp| {
+                let addr = bp.address;
+                let from_line = if bp.spec.starts_with("0x") {
+                    String::new()
+                } else {
+                    format!(" (set from {})", bp.spec)
+                };
+                let disabled = if bp.enabled { "" } else { " (disabled)" };
+                let ignore = match bp.ignore_remaining {
+                    0 => String::new(),
+                    n => format!(" (ignore next {n})"),
+                };
+                format!(
+                    "{}: {} {}{from_line}{ignore}{disabled}",
+                    bp.id,
+                    self.addr_fmt.hex(addr.into()),
+                    self.client.symbolize(addr)
+                )
+            })
+            .chain(assertions)
+            .collect()
+    }
 
-        Ok(ExecutionSnapshot {
-            summary_lines,
-            instruction_lines,
+    /// The GPRs at the current position. `verbose` adds when each last changed and from what,
+    /// fetched for all of them in one query
+    pub fn registers(&mut self, verbose: bool) -> ModelResult<Vec<RegisterEntry>> {
+        let gprs = self.client.read_gprs().map_err(|e| e.to_string())?;
+        let changes = if verbose {
+            self.client.regs_last_change().map_err(|e| e.to_string())?
+        } else {
+            vec![None; gprs.len()]
+        };
+        Ok(gprs
+            .into_iter()
+            .zip(changes)
+            .enumerate()
+            .map(|(reg, (value, last_change))| RegisterEntry {
+                reg,
+                value,
+                last_change,
+            })
+            .collect())
+    }
+
+    /// Where execution is, as `delta` compares it: the GPRs, the scratch region if there is one
+    /// and the selected signals
+    fn delta_snapshot(&mut self) -> ModelResult<Snapshot> {
+        let time_idx = self.get_time_index()?;
+        let gprs = self.client.read_gprs().map_err(|e| e.to_string())?;
+        let scratch = match self.client.scratch_info().map_err(|e| e.to_string())? {
+            Some((start, size)) => {
+                let bytes = self
+                    .client
+                    .read_memory(start, size)
+                    .map_err(|e| e.to_string())?;
+                Some((start, bytes))
+            }
+            None => None,
+        };
+        let signals = match self.client.wave_tracker {
+            Some(ref mut tracker) => {
+                let values = tracker.get_values(time_idx as TimeTableIdx);
+                tracker
+                    .get_signal_names()
+                    .into_iter()
+                    .zip(values)
+                    .map(|(name, value)| (name, value.unwrap_or_else(|e| e.to_string())))
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        Ok(Snapshot {
+            time_idx,
+            gprs,
+            scratch,
+            signals,
         })
     }
 
-    pub fn fetch_source_snapshot(&mut self) -> ModelResult<SourceSnapshot> {
+    /// `delta`: snapshot where execution is, and list what changed since the last `delta`
+    pub fn delta(&mut self) -> ModelResult<Vec<String>> {
         if self.terminated {
-            return Ok(SourceSnapshot {
-                lines: vec!["Process has terminated".to_string()],
-            });
+            return Err("Process has terminated".to_string());
         }
+        let snapshot = self.delta_snapshot()?;
+        let time_idx = snapshot.time_idx;
+        self.delta_history.push(snapshot);
+        Ok(match self.delta_history.last_two() {
+            Some((old, new)) => delta::diff_lines(old, new, &self.client, &self.addr_fmt),
+            None => vec![format!(
+                "Snapshot taken at time index {time_idx}, run delta at another stop to see what \
+                 changed"
+            )],
+        })
+    }
 
-        let mut lines = Vec::new();
+    /// File name of the ELF, for messages
+    fn elf_name(&self) -> String {
+        self.client
+            .elf_path()
+            .and_then(|path| path.file_name())
+            .map_or_else(
+                || "the ELF".to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            )
+    }
 
-        match self.client.get_current_source_line() {
-            Ok(Some(current_line)) => {
-                lines.push(format!(
-                    "{}:{}",
-                    current_line
-                        .path
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .unwrap_or("unknown"),
-                    current_line.line
-                ));
-                lines.push(String::new());
+    /// Take line info from a separate debug file, for a stripped ELF whose debuglink couldn't be
+    /// followed
+    pub fn load_debug_file(&mut self, path: &Path) -> ModelResult<()> {
+        self.client.load_debug_file(path).map_err(|e| e.to_string())
+    }
 
-                if let Some(ref text) = current_line.text {
-                    lines.push(format!("-> {}: {}", current_line.line, text));
+    /// Process state and settings, from what's already cached
+    pub fn status_lines(&self) -> Vec<String> {
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        vec![
+            format!(
+                "process: {}",
+                if self.terminated {
+                    "terminated"
                 } else {
-                    lines.push(format!("-> {}: <source not available>", current_line.line));
+                    "stopped"
                 }
+            ),
+            format!("hart: {}", hart_label(self.client.current_thread())),
+            format!(
+                "time index: {}",
+                self.cached_time_idx
+                    .map(|idx| idx.to_string())
+                    .unwrap_or_else(|| "unknown".to_string())
+            ),
+            format!("stopped on livelock: {}", self.stopped_on_livelock()),
+            format!("showbytes: {}", on_off(self.show_bytes)),
+            format!("heat: {}", on_off(self.show_heat)),
+            format!("bpcheck: {}", on_off(self.bpcheck)),
+            format!("findbudget: {}", self.find_budget),
+            format!("elfwatch: {}", on_off(self.elfwatch)),
+            format!("waveform loaded: {}", self.has_waveform()),
+            format!("debug info: {}", self.client.debug_info()),
+        ]
+    }
 
-                match self.client.get_consecutive_source_lines_after_current(3) {
-                    Ok(next_lines) => {
-                        for line in next_lines {
-                            if let Some(ref text) = line.text {
-                                lines.push(format!("   {}: {}", line.line, text));
-                            } else {
-                                lines.push(format!("   {}: <source not available>", line.line));
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        lines.push(format!("Error getting next lines: {e}"));
-                    }
+    /// Which of `addresses` the PC never reaches anywhere in the trace. Always empty with
+    /// `set bpcheck off`
+    pub fn never_hit(&mut self, addresses: &[u32]) -> Vec<u32> {
+        if !self.bpcheck {
+            return Vec::new();
+        }
+        addresses
+            .iter()
+            .copied()
+            .filter(|&addr| match self.client.pc_visit_count(addr) {
+                Ok(count) => count == 0,
+                Err(e) => {
+                    log::warn!("Couldn't check whether 0x{addr:x} is ever hit: {e}");
+                    false
                 }
+            })
+            .collect()
+    }
+
+    /// Where the instruction `address` is partway into starts, by the ELF's decode. `None` on an
+    /// instruction boundary, where the ELF can't tell, and with `set bpcheck off`
+    pub fn mid_instruction(&self, address: u32) -> Option<u32> {
+        if !self.bpcheck {
+            return None;
+        }
+        self.client
+            .instruction_start(address)
+            .filter(|&start| start != address)
+    }
+
+    /// Whether the trace has x or z bits in the PC where the target is stopped. dang hands
+    /// those out as the all ones sentinel, which no real PC looks like
+    pub fn pc_undefined(&mut self) -> bool {
+        !self.terminated
+            && self
+                .client
+                .get_current_pc()
+                .is_ok_and(|pc| pc.as_u32() == u32::sentinel())
+    }
+
+    pub fn fetch_execution_snapshot(&mut self) -> ModelResult<ExecutionSnapshot> {
+        self.fetch_counts.execution += 1;
+        if self.terminated {
+            return Ok(ExecutionSnapshot::Terminated);
+        }
+        self.fetch_counts.instructions += self.instruction_window as u64;
+
+        let stop = if self.client.stopped_on_livelock() {
+            StopKind::Livelock
+        } else if self.client.stopped_on_assertion() {
+            StopKind::Assertion
+        } else {
+            StopKind::Step
+        };
+        let frame = match self.client.get_current_pc() {
+            Ok(current_pc) => {
+                let pc = current_pc.as_u32();
+                let symbol = self.client.symbolize(pc);
+                let instructions = self
+                    .client
+                    .get_current_and_next_inst(self.instruction_window)
+                    .map(|insts| {
+                        insts
+                            .iter()
+                            .enumerate()
+                            .map(|(i, inst)| InstructionEntry {
+                                addr: inst.pc().as_u32(),
+                                text: inst.to_string(),
+                                bytes: self.show_bytes.then(|| inst.bytes().to_vec()),
+                                is_current: i == 0,
+                                symbol: (i == 0).then(|| symbol.clone()),
+                            })
+                            .collect()
+                    })
+                    .map_err(|e| e.to_string());
+                Ok(Frame {
+                    pc,
+                    symbol,
+                    instructions,
+                })
             }
-            Ok(None) => {
-                lines.push("Source Code:".to_string());
-                lines.push("No debug information available".to_string());
-            }
-            Err(e) => {
-                lines.push("Source Code:".to_string());
-                lines.push(format!("Error: {e}"));
+            Err(e) => Err(e.to_string()),
+        };
+
+        Ok(ExecutionSnapshot::Stopped { stop, frame })
+    }
+
+    pub fn fetch_source_snapshot(&mut self) -> ModelResult<SourceSnapshot> {
+        self.fetch_counts.source += 1;
+        if self.terminated {
+            return Ok(SourceSnapshot::Terminated);
+        }
+        if !self.client.has_debug_info() {
+            return Ok(SourceSnapshot::ElfWithoutDebugInfo {
+                elf: self.elf_name(),
+                missing: match self.client.debug_info() {
+                    DebugInfo::Missing(reason) => Some(reason.clone()),
+                    _ => None,
+                },
+            });
+        }
+
+        let current_line = match self.client.get_current_source_line() {
+            Ok(Some(current_line)) => current_line,
+            Ok(None) => return Ok(SourceSnapshot::NoDebugInfo),
+            Err(e) => return Ok(SourceSnapshot::Error(e.to_string())),
+        };
+
+        let heat = if self.show_heat {
+            self.client
+                .line_heat(
+                    &current_line.path,
+                    current_line.line..=current_line.line + 3,
+                )
+                .inspect_err(|e| log::warn!("Failed to get line heat: {e}"))
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let max_heat = heat.iter().map(|(_, count)| *count).max().unwrap_or(0);
+        let show_heat = self.show_heat;
+        let heat_of = |line: u64| {
+            show_heat.then(|| {
+                heat.iter()
+                    .find(|(l, _)| *l == line)
+                    .map(|(_, count)| *count)
+                    .unwrap_or(0)
+            })
+        };
+
+        let mut entries = vec![SourceEntry {
+            line_no: current_line.line,
+            heat: heat_of(current_line.line),
+            text: current_line.text.clone(),
+            is_current: true,
+            has_code: None,
+            column: current_line.column,
+        }];
+        let next_lines_error = match self.client.get_consecutive_source_lines_after_current(3) {
+            Ok(next_lines) => {
+                entries.extend(next_lines.into_iter().map(|line| SourceEntry {
+                    line_no: line.line,
+                    heat: heat_of(line.line),
+                    text: line.text,
+                    is_current: false,
+                    has_code: None,
+                    column: None,
+                }));
+                None
             }
+            Err(e) => Some(e.to_string()),
+        };
+
+        // only what's already known, the rest is looked up after the frame is drawn
+        let last_line = entries
+            .last()
+            .map_or(current_line.line, |entry| entry.line_no);
+        self.code_markers
+            .want(&current_line.path, current_line.line..=last_line);
+        self.source_path = Some(current_line.path.clone());
+        self.mark_code_lines(&mut entries);
+
+        Ok(SourceSnapshot::Located {
+            file: current_line
+                .path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown")
+                .to_string(),
+            entries,
+            max_heat,
+            next_lines_error,
+        })
+    }
+
+    /// Look up to `budget` of the source pane's code markers that aren't known yet. Returns how
+    /// many were looked up
+    pub fn fill_code_markers(&mut self, budget: usize) -> usize {
+        let client = &self.client;
+        self.code_markers.fill(budget, |file, line| {
+            client
+                .addresses_for_line(&file.to_string_lossy(), line)
+                .is_ok()
+        })
+    }
+
+    /// Fill in the code markers of `snapshot`, the last one fetched, from what's known by now
+    pub fn apply_code_markers(&self, snapshot: &mut SourceSnapshot) {
+        if let SourceSnapshot::Located { entries, .. } = snapshot {
+            self.mark_code_lines(entries);
         }
+    }
 
-        Ok(SourceSnapshot { lines })
+    fn mark_code_lines(&self, entries: &mut [SourceEntry]) {
+        let Some(path) = &self.source_path else {
+            return;
+        };
+        for entry in entries {
+            entry.has_code = self.code_markers.get(path, entry.line_no);
+        }
     }
 
-    pub fn fetch_signal_snapshot(&mut self) -> ModelResult<SignalSnapshot> {
+    /// Values of the selected signals where execution is, or at time index `pin` instead
+    pub fn fetch_signal_snapshot(&mut self, pin: Option<u64>) -> ModelResult<SignalSnapshot> {
+        self.fetch_counts.signals += 1;
         if self.terminated {
-            return Ok(SignalSnapshot {
-                lines: vec!["Process has terminated".to_string()],
-            });
+            return Ok(SignalSnapshot::Terminated);
         }
 
         if self.client.wave_tracker.is_none() {
-            return Ok(SignalSnapshot {
-                lines: vec!["no waves found".to_string()],
-            });
+            return Ok(SignalSnapshot::NoWaveform);
         }
 
-        let time_idx = self.get_time_index()?;
+        let exec_idx = self.get_time_index()?;
+        let time_idx = pin.unwrap_or(exec_idx);
+        // only marked when it's known without asking, which it is whenever the pane was up
+        // before the resume
+        let resumed = match self.resumed_from {
+            Some(Some(from)) => Some((from.min(exec_idx), from.max(exec_idx))),
+            _ => None,
+        };
 
-        let mut lines = Vec::new();
-        if let Some(ref mut tracker) = self.client.wave_tracker {
-            let current_time = tracker.get_current_time(time_idx as TimeTableIdx);
-            lines.push(format!("{current_time} ps"));
-            lines.push(String::new());
-
-            let signal_names = tracker.get_signal_names();
-            if signal_names.is_empty() {
-                lines.push("No signals selected".to_string());
-                lines.push("Use 'addsig' to add signals".to_string());
-            } else {
-                let signal_values = tracker.get_values(time_idx as TimeTableIdx);
-                for (name, value) in signal_names.iter().zip(signal_values.iter()) {
-                    lines.push(format!("{name}: {value}"));
-                }
+        let Some(ref mut tracker) = self.client.wave_tracker else {
+            return Ok(SignalSnapshot::NoWaveform);
+        };
+        let time = tracker.get_current_time(time_idx as TimeTableIdx);
+        let exec_time = pin.and_then(|_| tracker.get_current_time(exec_idx as TimeTableIdx));
+        let values = tracker.get_values(time_idx as TimeTableIdx);
+        let entries = tracker
+            .get_signal_names()
+            .into_iter()
+            .zip(values)
+            .zip(tracker.get_formats())
+            .enumerate()
+            .map(|(i, ((name, value), format))| SignalEntry {
+                name,
+                value,
+                format,
+                changed: resumed.is_some_and(|(from, to)| {
+                    tracker.changed_in_range(i, from as TimeTableIdx, to as TimeTableIdx)
+                }),
+            })
+            .collect();
+
+        Ok(SignalSnapshot::Values {
+            time,
+            exec_time,
+            unit: self.time_unit.clone(),
+            entries,
+        })
+    }
+
+    /// [`SignalStats`] for every selected signal, in selection order. With the Signals pane
+    /// pinned, changes are also counted between `pin` and execution, and that window is returned
+    /// along with them
+    pub fn signal_stats(
+        &mut self,
+        pin: Option<u64>,
+    ) -> ModelResult<(Option<(u64, u64)>, Vec<SignalStatsRow>)> {
+        if self.client.wave_tracker.is_none() {
+            return Err("No waveform loaded".to_string());
+        }
+        let window = match pin {
+            Some(pin) => {
+                let exec_idx = self.get_time_index()?;
+                Some((pin.min(exec_idx), pin.max(exec_idx)))
             }
+            None => None,
+        };
+        let Some(tracker) = &self.client.wave_tracker else {
+            return Err("No waveform loaded".to_string());
+        };
+        let tracker_window = window.map(|(lo, hi)| (lo as TimeTableIdx, hi as TimeTableIdx));
+        let rows = tracker
+            .get_signal_names()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, name)| {
+                let Some(stats) = tracker.signal_stats(i, tracker_window) else {
+                    log::warn!("No data loaded for {name}, leaving it out of the stats");
+                    return None;
+                };
+                Some(SignalStatsRow {
+                    name,
+                    first_time: stats.first.and_then(|idx| tracker.get_current_time(idx)),
+                    last_time: stats.last.and_then(|idx| tracker.get_current_time(idx)),
+                    stats,
+                })
+            })
+            .collect();
+        Ok((window, rows))
+    }
+
+    /// The time indices the last step or continue went between, `None` before there's been one
+    /// or after a `goto`. Where it started comes from the target's `resume_stats` if it wasn't
+    /// cached at the time
+    pub fn last_resume(&mut self) -> ModelResult<Option<(u64, u64)>> {
+        let from = match self.resumed_from {
+            None => return Ok(None),
+            Some(Some(from)) => from,
+            Some(None) => match self.client.resume_stats() {
+                Ok(stats) => {
+                    self.resumed_from = Some(Some(stats.start_time_idx));
+                    stats.start_time_idx
+                }
+                Err(e) => {
+                    self.resumed_from = None;
+                    return Err(format!("Couldn't tell where the last resume started: {e}"));
+                }
+            },
+        };
+        Ok(Some((from, self.get_time_index()?)))
+    }
+
+    /// Which selected signals changed during the last step or continue, and the time indices
+    /// it went between. A change where it started doesn't count, one where it stopped does
+    pub fn signal_changes(&mut self) -> ModelResult<((u64, u64), Vec<SignalChangeRow>)> {
+        if self.client.wave_tracker.is_none() {
+            return Err("No waveform loaded".to_string());
         }
+        let Some((from, to)) = self.last_resume()? else {
+            return Err("Nothing has run yet, step or continue first".to_string());
+        };
+        let Some(tracker) = &self.client.wave_tracker else {
+            return Err("No waveform loaded".to_string());
+        };
+        let (lo, hi) = (from.min(to) as TimeTableIdx, from.max(to) as TimeTableIdx);
+        let rows = tracker
+            .get_signal_names()
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| {
+                // signal_stats counts both ends, and the start isn't a change
+                let changes = match lo < hi {
+                    true => tracker
+                        .signal_stats(i, Some((lo + 1, hi)))
+                        .map(|stats| stats.in_window),
+                    false => tracker.signal_stats(i, None).map(|_| 0),
+                };
+                SignalChangeRow {
+                    name,
+                    changed: tracker.changed_in_range(i, lo, hi),
+                    changes,
+                }
+            })
+            .collect();
+        Ok(((from, to), rows))
+    }
 
-        Ok(SignalSnapshot { lines })
+    /// The time index the Signals pane is pinned to after `sigtime`, `pin` being where it's
+    /// pinned now. Relative moves stop at either end of the trace
+    pub fn resolve_signal_pin(
+        &mut self,
+        pin: Option<u64>,
+        sigtime: SigTime,
+    ) -> ModelResult<Option<u64>> {
+        let last = match &self.client.wave_tracker {
+            Some(tracker) => (tracker.time_table_len() as u64).saturating_sub(1),
+            None => return Err("No waveform loaded".to_string()),
+        };
+        match sigtime {
+            SigTime::Off => Ok(None),
+            SigTime::Index(idx) if idx > last => Err(format!(
+                "Time index {idx} is past the end of the trace, the last is {last}"
+            )),
+            SigTime::Index(idx) => Ok(Some(idx)),
+            SigTime::Time(time) => self
+                .client
+                .wave_tracker
+                .as_ref()
+                .and_then(|tracker| tracker.index_at_time(time))
+                .map(|idx| Some(idx as u64))
+                .ok_or_else(|| format!("{time} ps is before the trace starts")),
+            SigTime::Relative(delta) => {
+                let from = match pin {
+                    Some(idx) => idx,
+                    None => self.get_time_index()?,
+                };
+                Ok(Some(from.saturating_add_signed(delta).min(last)))
+            }
+        }
     }
 
     pub fn fuzzy_match_signals(&mut self, query: &str) -> Vec<(Var, String)> {
@@ -228,12 +1477,150 @@ impl DebuggerModel {
         }
     }
 
+    /// The signal with the full name `name`, matched exactly rather than fuzzily
+    pub fn find_signal(&self, name: &str) -> Option<Var> {
+        self.client.wave_tracker.as_ref()?.find_var(name)
+    }
+
     pub fn select_signal(&mut self, var: Var) {
-        if let Some(ref mut tracker) = self.client.wave_tracker {
-            tracker.select_signal(var);
+        self.select_signals(vec![var]);
+    }
+
+    /// Select all of `vars` with a single load of their data, skipping ones already selected.
+    /// Returns how many were added
+    pub fn select_signals(&mut self, vars: Vec<Var>) -> usize {
+        match self.client.wave_tracker {
+            Some(ref mut tracker) => tracker.select_signals(vars),
+            None => 0,
+        }
+    }
+
+    /// The selected signals by full name with their radix, in the order they were selected
+    pub fn selected_signals(&self) -> Vec<(String, FormattingType)> {
+        match self.client.wave_tracker {
+            Some(ref tracker) => tracker
+                .get_signal_names()
+                .into_iter()
+                .zip(tracker.get_formats())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Take the selected signal `query` names off the Signals pane. Returns its full name
+    pub fn deselect_signal(&mut self, query: &str) -> ModelResult<String> {
+        let (_, name) = self.find_selected_signal(query)?;
+        let Some(ref mut tracker) = self.client.wave_tracker else {
+            return Err("No waveform loaded".to_string());
+        };
+        tracker.deselect_signal(&name);
+        Ok(name)
+    }
+
+    /// Take every signal off the Signals pane. Returns how many there were
+    pub fn clear_signals(&mut self) -> ModelResult<usize> {
+        match self.client.wave_tracker {
+            Some(ref mut tracker) => Ok(tracker.clear_signals()),
+            None => Err("No waveform loaded".to_string()),
         }
     }
 
+    /// The selected signal `query` names, by its full name or a `.` separated suffix only one
+    /// selected signal ends with. Returns its index and full name
+    fn find_selected_signal(&self, query: &str) -> ModelResult<(usize, String)> {
+        let Some(ref tracker) = self.client.wave_tracker else {
+            return Err("No waveform loaded".to_string());
+        };
+        let names = tracker.get_signal_names();
+        if let Some(i) = names.iter().position(|name| name == query) {
+            return Ok((i, names[i].clone()));
+        }
+        let suffix = format!(".{query}");
+        let mut matches = names
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| name.ends_with(&suffix));
+        match (matches.next(), matches.next()) {
+            (Some((i, name)), None) => Ok((i, name.clone())),
+            (Some(_), Some(_)) => Err(format!(
+                "'{query}' matches more than one selected signal, give more of its name"
+            )),
+            (None, _) => Err(format!("No selected signal is named '{query}'")),
+        }
+    }
+
+    /// Show the selected signal `query` names with its values named from `names`. Returns its
+    /// full name, and a warning when some of the names are for values it's too narrow to hold
+    pub fn set_value_names(
+        &mut self,
+        query: &str,
+        names: HashMap<u64, String>,
+        format: NameFormat,
+    ) -> ModelResult<(String, Option<String>)> {
+        let (i, name) = self.find_selected_signal(query)?;
+        let Some(ref mut tracker) = self.client.wave_tracker else {
+            return Err("No waveform loaded".to_string());
+        };
+        let unreachable = tracker
+            .set_value_names(i, names, format)
+            .unwrap_or_default();
+        let warning = value_names::width_warning(&name, tracker.signal_width(i), &unreachable);
+        Ok((name, warning))
+    }
+
+    /// Go back to plain values for the selected signal `query` names. Returns its full name
+    pub fn clear_value_names(&mut self, query: &str) -> ModelResult<String> {
+        let (i, name) = self.find_selected_signal(query)?;
+        let Some(ref mut tracker) = self.client.wave_tracker else {
+            return Err("No waveform loaded".to_string());
+        };
+        if !tracker.clear_value_names(i) {
+            return Err(format!("{name} has no value names"));
+        }
+        Ok(name)
+    }
+
+    /// Show the selected signal `query` names in `format`. Returns its full name
+    pub fn set_signal_format(
+        &mut self,
+        query: &str,
+        format: FormattingType,
+    ) -> ModelResult<String> {
+        let (i, name) = self.find_selected_signal(query)?;
+        let Some(ref mut tracker) = self.client.wave_tracker else {
+            return Err("No waveform loaded".to_string());
+        };
+        tracker.set_format(i, format);
+        Ok(name)
+    }
+
+    /// Work out signal values around where they were last asked for, so scrubbing nearby finds
+    /// them ready. Cheap to call when there's nothing to do. Returns how many time indices it
+    /// worked out
+    pub fn prefetch_signals(&mut self) -> usize {
+        self.client
+            .wave_tracker
+            .as_mut()
+            .map_or(0, |tracker| tracker.prefetch())
+    }
+
+    /// Change indices either side of the cursor to work out ahead of time, `None` for none
+    pub fn set_prefetch(&mut self, span: Option<usize>) -> ModelResult<()> {
+        let Some(ref mut tracker) = self.client.wave_tracker else {
+            return Err("No waveform loaded".to_string());
+        };
+        tracker.set_prefetch_span(span);
+        Ok(())
+    }
+
+    /// How much work fetching signal values has been, `None` without a waveform
+    pub fn tracker_counts(&self) -> Option<TrackerCounts> {
+        self.client
+            .wave_tracker
+            .as_ref()
+            .map(|tracker| tracker.counts())
+    }
+
     pub fn most_recent_var_path(&self) -> Option<String> {
         if let Some(ref tracker) = self.client.wave_tracker {
             tracker.get_signal_names().last().cloned()
@@ -242,6 +1629,25 @@ impl DebuggerModel {
         }
     }
 
+    /// Time indices in the whole trace, 0 without a waveform
+    pub fn trace_len(&self) -> u64 {
+        self.client
+            .wave_tracker
+            .as_ref()
+            .map_or(0, |tracker| tracker.time_table_len() as u64)
+    }
+
+    /// Move execution straight to time index `idx`
+    pub fn goto_time_idx(&mut self, idx: u64) -> ModelResult<()> {
+        if self.terminated {
+            return Err("Process has terminated".to_string());
+        }
+        self.client.goto_time_idx(idx).map_err(|e| e.to_string())?;
+        self.resumed_from = None;
+        self.invalidate_time_index();
+        Ok(())
+    }
+
     pub fn invalidate_time_index(&mut self) {
         self.cached_time_idx = None;
     }
@@ -260,3 +1666,393 @@ impl DebuggerModel {
         Ok(idx)
     }
 }
+
+/// Note a Z0 or z0 that found the target already as asked. Harmless, but worth a line in the
+/// debug panel
+fn log_breakpoint_change(addr: u32, change: BreakpointChange) {
+    match change {
+        BreakpointChange::Changed => {}
+        BreakpointChange::AlreadySet => {
+            log::info!("The target already had a breakpoint at 0x{addr:x}")
+        }
+        BreakpointChange::NotSet => log::info!("The target had no breakpoint at 0x{addr:x}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::breakpoints::parse_selectors;
+    use crate::controller::test_utils::{
+        fixture_controller, fixture_controller_with_elf, test_data,
+    };
+
+    /// Breakpoints 1-4 by address, then the locations of simple_system_common.c:21 from 5 on
+    fn populated_model() -> DebuggerModel {
+        let mut model = fixture_controller().model;
+        for addr in [0x1000a4, 0x1000a6, 0x1000a8, 0x1003d0] {
+            model.set_breakpoint(addr).unwrap();
+        }
+        let line_bps = model
+            .set_breakpoint_at_line("simple_system_common.c", 21, None)
+            .unwrap();
+        assert!(line_bps.iter().any(|bp| bp.address == 0x1000a4));
+        model
+    }
+
+    fn ids(model: &DebuggerModel) -> Vec<u32> {
+        model.breakpoints().iter().map(|bp| bp.id).collect()
+    }
+
+    #[test]
+    fn test_signal_changes_over_a_continue() {
+        let mut model = fixture_controller().model;
+        assert!(model.signal_changes().is_err());
+
+        let pc = "TOP.ibex_simple_system.u_top.u_ibex_top.u_ibex_core.wb_stage_i.pc_wb_o";
+        let x0 =
+            "TOP.ibex_simple_system.u_top.u_ibex_top.gen_regfile_ff.register_file_i.rf_reg.[0]";
+        for name in [pc, x0] {
+            let (var, _) = model
+                .fuzzy_match_signals(name)
+                .into_iter()
+                .find(|(_, matched)| matched == name)
+                .unwrap();
+            model.select_signal(var);
+        }
+
+        let start = model.get_time_idx().unwrap();
+        model.set_breakpoint(0x1000a4).unwrap();
+        model.continue_execution().unwrap();
+        let ((from, to), rows) = model.signal_changes().unwrap();
+        assert_eq!(from, start);
+        assert!(to > from);
+        // the PC moves on every instruction, x0 never does
+        assert_eq!(rows[0].name, pc);
+        assert!(rows[0].changed);
+        assert!(rows[0].changes.unwrap() > 1);
+        assert_eq!(rows[1].name, x0);
+        assert!(!rows[1].changed);
+        assert_eq!(rows[1].changes, Some(0));
+
+        // the pane marks the same ones, with times in the unit dang reports
+        let SignalSnapshot::Values {
+            time,
+            unit,
+            entries,
+            ..
+        } = model.fetch_signal_snapshot(None).unwrap()
+        else {
+            panic!("the fixture has a waveform");
+        };
+        let marked: Vec<bool> = entries.iter().map(|entry| entry.changed).collect();
+        assert_eq!(marked, [true, false]);
+        assert_eq!(model.client.get_time().unwrap(), (time.unwrap(), unit));
+
+        // steps with nothing looking at the stops in between count from the last one shown
+        model.step().unwrap();
+        model.step().unwrap();
+        let ((from, after), _) = model.signal_changes().unwrap();
+        assert_eq!(from, to);
+        assert!(after > to);
+
+        model.goto_time_idx(start).unwrap();
+        assert!(model.signal_changes().is_err());
+    }
+
+    #[test]
+    fn test_status_shows_the_hart() {
+        let mut model = fixture_controller().model;
+        assert_eq!(model.status_lines()[1], "hart: 1");
+        model.step().unwrap();
+        assert_eq!(model.status_lines()[1], "hart: 1");
+
+        assert_eq!(hart_label(&ThreadId::Process { pid: 1, tid: 2 }), "2");
+        assert_eq!(hart_label(&ThreadId::Any), "unknown");
+    }
+
+    #[test]
+    fn test_stepping_off_the_end_of_the_trace() {
+        let mut model = fixture_controller().model;
+        assert!(!model.is_terminated());
+        model.goto_time_idx(model.trace_len() - 1).unwrap();
+
+        // dang answers with an X packet, which is the end
+        assert_eq!(model.step().unwrap_err(), "Process has terminated");
+        assert!(model.is_terminated());
+        assert!(model.client.has_exited());
+        assert!(matches!(
+            model.fetch_execution_snapshot(),
+            Ok(ExecutionSnapshot::Terminated)
+        ));
+        assert!(model.continue_execution().is_err());
+    }
+
+    #[test]
+    fn test_delete_id_range() {
+        let mut model = populated_model();
+        let result = model
+            .delete_breakpoints(&parse_selectors("2-3,99").unwrap())
+            .unwrap();
+        assert_eq!(result.affected, vec![2, 3]);
+        assert_eq!(result.unmatched, vec!["99".to_string()]);
+        assert!(!ids(&model).contains(&2));
+        assert!(!ids(&model).contains(&3));
+        assert!(ids(&model).contains(&1));
+    }
+
+    #[test]
+    fn test_delete_mixed_list() {
+        let mut model = populated_model();
+        let result = model
+            .delete_breakpoints(&parse_selectors("1,3 4").unwrap())
+            .unwrap();
+        assert_eq!(result.affected, vec![1, 3, 4]);
+        assert!(result.unmatched.is_empty());
+    }
+
+    #[test]
+    fn test_delete_address_range() {
+        let mut model = populated_model();
+        let result = model
+            .delete_breakpoints(&parse_selectors("0x1000a6-0x1000a8 0x200000-0x200100").unwrap())
+            .unwrap();
+        assert_eq!(result.affected, vec![2, 3]);
+        assert_eq!(result.unmatched, vec!["0x200000-0x200100".to_string()]);
+    }
+
+    #[test]
+    fn test_delete_all() {
+        let mut model = populated_model();
+        let before = ids(&model);
+        let result = model
+            .delete_breakpoints(&parse_selectors("all").unwrap())
+            .unwrap();
+        assert_eq!(result.affected, before);
+        assert_eq!(model.breakpoints().iter().count(), 0);
+
+        let result = model
+            .delete_breakpoints(&parse_selectors("all").unwrap())
+            .unwrap();
+        assert!(result.affected.is_empty());
+        assert_eq!(result.unmatched, vec!["all".to_string()]);
+    }
+
+    #[test]
+    fn test_shared_address_stays_armed() {
+        // breakpoint 1 and a line breakpoint both sit at 0x1000a4
+        let mut model = populated_model();
+        model
+            .delete_breakpoints(&parse_selectors("1").unwrap())
+            .unwrap();
+        assert!(model.breakpoints().armed_at(0x1000a4));
+    }
+
+    #[test]
+    fn test_ignore_count_stops_on_later_hit() {
+        let mut model = fixture_controller().model;
+        model.set_stop_on_livelock(false).unwrap();
+        let start = model.get_time_idx().unwrap();
+        // the first PC a put loop comes back to a fourth time, and when it does
+        let trace = model.monitor(&format!("pc_trace {start} 5000")).unwrap();
+        let mut arrivals = BTreeMap::new();
+        let (fourth, pc) = trace
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(idx, pc)| {
+                (
+                    idx.parse::<u64>().unwrap(),
+                    u32::from_str_radix(pc, 16).unwrap(),
+                )
+            })
+            .find(|&(_, pc)| {
+                let seen = arrivals.entry(pc).or_insert(0);
+                *seen += 1;
+                *seen == 4
+            })
+            .expect("no PC comes up four times");
+
+        let id = model.set_breakpoint(pc).unwrap().id;
+        assert_eq!(model.ignore_breakpoint(id, 3).unwrap().ignore_remaining, 3);
+        assert!(model.breakpoint_lines()[0].ends_with("(ignore next 3)"));
+        assert!(model.ignore_breakpoint(id + 1, 3).is_err());
+
+        model.continue_execution().unwrap();
+        assert_eq!(model.get_time_idx().unwrap(), fourth);
+        assert_eq!(model.breakpoints().get(id).unwrap().ignore_remaining, 0);
+        assert!(!model.breakpoint_lines()[0].contains("ignore"));
+    }
+
+    #[test]
+    fn test_disable_and_enable() {
+        let mut model = populated_model();
+        let result = model
+            .set_breakpoints_enabled(&parse_selectors("0x1000a4").unwrap(), false)
+            .unwrap();
+        assert!(result.affected.contains(&1));
+        assert!(!model.breakpoints().armed_at(0x1000a4));
+        assert!(model.breakpoints().armed_at(0x1000a6));
+        assert!(model
+            .breakpoint_lines()
+            .iter()
+            .any(|line| line.starts_with("1: 0x1000a4") && line.ends_with("(disabled)")));
+
+        model
+            .set_breakpoints_enabled(&parse_selectors("1").unwrap(), true)
+            .unwrap();
+        assert!(model.breakpoints().armed_at(0x1000a4));
+        assert!(model.breakpoints().get(1).unwrap().enabled);
+    }
+
+    #[test]
+    fn test_panes_symbolize_alike() {
+        let mut controller = fixture_controller();
+        let pc = controller.model.client.get_current_pc().unwrap().as_u32();
+        controller.model.set_breakpoint(pc).unwrap();
+        controller.refresh_execution();
+
+        let symbol = controller.model.client.symbolize(pc);
+        assert!(symbol.contains('`'), "{symbol}");
+        assert!(controller
+            .view_state
+            .execution_lines
+            .iter()
+            .any(|line| line.ends_with(&format!("0x{pc:x} {symbol}"))));
+        assert_eq!(
+            controller.model.breakpoint_lines(),
+            vec![format!("1: 0x{pc:x} {symbol}")]
+        );
+    }
+
+    #[test]
+    fn test_assertions_listed_with_breakpoints() {
+        let mut model = fixture_controller().model;
+        model.set_breakpoint(0x1000a4).unwrap();
+        let assertion = model.assert_reg(2, ">=", 0xf000_0000).unwrap();
+        let lines = model.breakpoint_lines();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("1: 0x1000a4"), "{}", lines[0]);
+        assert_eq!(
+            lines[1],
+            format!("assert {}: x2 >= 0xf0000000", assertion.id)
+        );
+
+        model.continue_execution().unwrap();
+        assert!(model.stopped_on_assertion());
+        let violation = model.stop_report().unwrap().assertion.unwrap();
+        assert_eq!(violation.id, assertion.id);
+        assert!(matches!(
+            model.fetch_execution_snapshot().unwrap(),
+            ExecutionSnapshot::Stopped {
+                stop: StopKind::Assertion,
+                ..
+            }
+        ));
+
+        model.delete_assertion(assertion.id).unwrap();
+        assert!(model.delete_assertion(assertion.id).is_err());
+        assert_eq!(model.breakpoint_lines().len(), 1);
+    }
+
+    #[test]
+    fn test_clear_file() {
+        let mut model = populated_model();
+        let result = model
+            .clear_file_breakpoints("simple_system_common.c")
+            .unwrap();
+        assert!(!result.affected.is_empty());
+        assert!(result.affected.iter().all(|&id| id >= 5));
+        assert_eq!(ids(&model), vec![1, 2, 3, 4]);
+
+        let result = model.clear_file_breakpoints("hello_test.c").unwrap();
+        assert!(result.affected.is_empty());
+        assert_eq!(result.unmatched, vec!["hello_test.c".to_string()]);
+    }
+
+    #[test]
+    fn test_breakpoint_alignment() {
+        let mut fmt = AddrFormatter::default();
+        assert!(check_breakpoint_alignment(0x100200, 2, &fmt).is_ok());
+        assert!(check_breakpoint_alignment(0x100202, 2, &fmt).is_ok());
+        assert_eq!(
+            check_breakpoint_alignment(0x100201, 2, &fmt).unwrap_err(),
+            "0x100201 is not on a 2 byte instruction boundary"
+        );
+        assert!(check_breakpoint_alignment(0x100202, 4, &fmt).is_err());
+        fmt.pad = true;
+        assert_eq!(
+            check_breakpoint_alignment(0x100201, 2, &fmt).unwrap_err(),
+            "0x00100201 is not on a 2 byte instruction boundary"
+        );
+    }
+
+    #[test]
+    fn test_elf_without_debug_info() {
+        // The stripped fixture, with its debuglink renamed away so it reads like a build without -g
+        let mut data = std::fs::read(test_data("stripped/hello_test.elf")).unwrap();
+        let name = b".gnu_debuglink";
+        let at = data
+            .windows(name.len())
+            .position(|window| window == name)
+            .unwrap();
+        data[at..at + name.len()].copy_from_slice(b".not_debuglink");
+        let elf = std::env::temp_dir().join(format!("jpdb_{}_nodebug.elf", std::process::id()));
+        std::fs::write(&elf, data).unwrap();
+
+        let mut model = fixture_controller_with_elf(elf.clone()).model;
+        assert!(!model.client.has_debug_info());
+        assert_eq!(*model.client.debug_info(), DebugInfo::Absent);
+
+        let expected = SourceSnapshot::ElfWithoutDebugInfo {
+            elf: elf.file_name().unwrap().to_string_lossy().into_owned(),
+            missing: None,
+        };
+        assert_eq!(model.fetch_source_snapshot().unwrap(), expected);
+        model.step().unwrap();
+        assert_eq!(model.fetch_source_snapshot().unwrap(), expected);
+
+        let err = model
+            .set_breakpoint_at_line("simple_system_common.c", 21, None)
+            .unwrap_err();
+        assert!(err.contains("has no debug info"), "{err}");
+        assert!(err.contains("Break on an address instead"), "{err}");
+
+        // symbols still come from the symbol table
+        assert!(model.client.symbolize(0x1003d0).contains("main+0x0"));
+        model.set_breakpoint(0x1003d0).unwrap();
+        std::fs::remove_file(&elf).unwrap();
+    }
+
+    #[test]
+    fn test_rebuilt_elf() {
+        let elf = std::env::temp_dir().join(format!("jpdb_{}_rebuilt.elf", std::process::id()));
+        std::fs::copy(test_data("hello_test.elf"), &elf).unwrap();
+        let mut model = fixture_controller_with_elf(elf.clone()).model;
+        model
+            .set_breakpoint_at_line("simple_system_common.c", 21, None)
+            .unwrap();
+        assert_eq!(model.check_elf(), None);
+
+        // "rebuild" into a binary without line info
+        std::fs::copy(test_data("stripped/hello_test.elf"), &elf).unwrap();
+        let warning = model.check_elf().unwrap();
+        assert!(warning.contains("reloadelf"), "{warning}");
+        // warned once, not on every lookup
+        assert_eq!(model.check_elf(), None);
+
+        let report = model.reload_elf().unwrap();
+        assert!(
+            report
+                .iter()
+                .any(|line| line.contains("simple_system_common.c:21 no longer resolves")),
+            "{report:?}"
+        );
+        assert_ne!(*model.client.debug_info(), DebugInfo::Embedded);
+        assert_eq!(model.check_elf(), None);
+
+        // the ELF is gone, but watching is off
+        std::fs::remove_file(&elf).unwrap();
+        model.set_elfwatch(false);
+        assert_eq!(model.check_elf(), None);
+    }
+}
@@ -0,0 +1,275 @@
+//! Things the user should hear about that aren't the output of a command they typed: dang
+//! going away, the ELF changing on disk, a stop where the trace has no defined PC. Each is a
+//! [`Notification`] with a severity, kept in a history the `notifications` command lists.
+//!
+//! The most important recent one is shown as a banner across the top of the screen, see
+//! [`Notifications::banner`]. It goes away on its own after a while, or on the next key.
+//!
+//! Anything can push through a [`Notifier`], a cheap clonable handle that never blocks, so
+//! background threads can too. Pushes are picked up by [`Notifications::poll`]
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// How long the banner stays up unless `set notifytime` says otherwise
+pub const DEFAULT_BANNER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many notifications the history keeps, the oldest go first
+const HISTORY_CAPACITY: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "info" => Ok(Severity::Info),
+            "warning" | "warn" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => Err(format!("Expected 'info', 'warning' or 'error', got '{s}'")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub severity: Severity,
+    pub text: String,
+    /// When it was pushed, or last repeated
+    pub timestamp: Instant,
+    /// What pushed it, e.g. `dang` or `elfwatch`
+    pub source: &'static str,
+    /// How many more times the same notification came in right after
+    pub repeats: usize,
+}
+
+impl Notification {
+    pub fn new(severity: Severity, source: &'static str, text: impl Into<String>) -> Self {
+        Self {
+            severity,
+            text: text.into(),
+            timestamp: Instant::now(),
+            source,
+            repeats: 0,
+        }
+    }
+}
+
+/// Sends notifications to the [`Notifications`] it came from. Pushing never blocks, and one
+/// whose [`Notifications`] is gone is dropped
+#[derive(Debug, Clone)]
+pub struct Notifier(mpsc::Sender<Notification>);
+
+impl Notifier {
+    pub fn push(&self, severity: Severity, source: &'static str, text: impl Into<String>) {
+        let _ = self.0.send(Notification::new(severity, source, text));
+    }
+
+    pub fn warn(&self, source: &'static str, text: impl Into<String>) {
+        self.push(Severity::Warning, source, text);
+    }
+}
+
+impl Default for Notifier {
+    /// Connected to nothing, everything pushed is dropped
+    fn default() -> Self {
+        Notifier(mpsc::channel().0)
+    }
+}
+
+pub struct Notifications {
+    history: VecDeque<Notification>,
+    sender: mpsc::Sender<Notification>,
+    receiver: mpsc::Receiver<Notification>,
+    /// Least severe notification the banner shows, `set notify`
+    pub banner_min: Severity,
+    /// How long a notification stays in the banner, `set notifytime`
+    pub banner_timeout: Duration,
+    /// Everything pushed before this instant has been dismissed from the banner
+    dismissed_at: Option<Instant>,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            history: VecDeque::new(),
+            sender,
+            receiver,
+            banner_min: Severity::Warning,
+            banner_timeout: DEFAULT_BANNER_TIMEOUT,
+            dismissed_at: None,
+        }
+    }
+}
+
+impl Notifications {
+    /// A handle to push with from anywhere
+    pub fn notifier(&self) -> Notifier {
+        Notifier(self.sender.clone())
+    }
+
+    /// Push from whatever owns these, it turns up with the next [`Self::poll`] like the rest
+    pub fn push(&self, severity: Severity, source: &'static str, text: impl Into<String>) {
+        let _ = self.sender.send(Notification::new(severity, source, text));
+    }
+
+    /// Move everything pushed since the last poll into the history and return it, oldest
+    /// first. The same notification twice in a row is kept once, with its repeats counted
+    pub fn poll(&mut self) -> Vec<Notification> {
+        let new: Vec<Notification> = self.receiver.try_iter().collect();
+        for notification in &new {
+            self.add(notification.clone());
+        }
+        new
+    }
+
+    fn add(&mut self, notification: Notification) {
+        if let Some(last) = self.history.back_mut() {
+            if last.severity == notification.severity
+                && last.source == notification.source
+                && last.text == notification.text
+            {
+                last.repeats += 1;
+                last.timestamp = notification.timestamp;
+                return;
+            }
+        }
+        if self.history.len() == HISTORY_CAPACITY {
+            self.history.pop_front();
+        }
+        self.history.push_back(notification);
+    }
+
+    /// Oldest first
+    pub fn history(&self) -> impl Iterator<Item = &Notification> {
+        self.history.iter()
+    }
+
+    /// What the banner shows at `now`: of the notifications at least [`Self::banner_min`]
+    /// severe, pushed within [`Self::banner_timeout`] and since the last dismissal, the most
+    /// severe, and the newest of those
+    pub fn banner(&self, now: Instant) -> Option<&Notification> {
+        self.history
+            .iter()
+            .filter(|n| n.severity >= self.banner_min)
+            .filter(|n| now.saturating_duration_since(n.timestamp) < self.banner_timeout)
+            .filter(|n| self.dismissed_at.map_or(true, |at| n.timestamp > at))
+            .max_by_key(|n| (n.severity, n.timestamp))
+    }
+
+    /// Take down whatever the banner shows, and everything else pushed before `now`
+    pub fn dismiss(&mut self, now: Instant) {
+        self.dismissed_at = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Severity::{Error, Info, Warning};
+    use super::*;
+
+    /// Push `text` as if it had come in at `at`
+    fn push_at(notes: &mut Notifications, at: Instant, severity: Severity, text: &str) {
+        let mut notification = Notification::new(severity, "test", text);
+        notification.timestamp = at;
+        notes.sender.send(notification).unwrap();
+        notes.poll();
+    }
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_banner_selection() {
+        let mut notes = Notifications::default();
+        let now = Instant::now() + secs(60);
+        assert_eq!(notes.banner(now), None);
+
+        push_at(&mut notes, now - secs(5), Warning, "old warning");
+        push_at(&mut notes, now - secs(4), Error, "old error");
+        push_at(&mut notes, now - secs(3), Error, "new error");
+        push_at(&mut notes, now - secs(1), Warning, "newest");
+        // highest severity wins, the newest of those
+        assert_eq!(notes.banner(now).unwrap().text, "new error");
+
+        // info is below the default filter
+        push_at(&mut notes, now, Info, "info");
+        assert_eq!(notes.banner(now).unwrap().text, "new error");
+        assert_eq!(notes.history().count(), 5);
+    }
+
+    #[test]
+    fn test_banner_dismissal() {
+        let mut notes = Notifications::default();
+        notes.banner_timeout = secs(10);
+        let now = Instant::now() + secs(60);
+        push_at(&mut notes, now - secs(4), Warning, "warning");
+
+        // gone on its own once the timeout's up
+        assert!(notes.banner(now).is_some());
+        assert!(notes.banner(now + secs(5)).is_some());
+        assert_eq!(notes.banner(now + secs(6)), None);
+
+        // or straight away on a key, until something new comes in
+        notes.dismiss(now);
+        assert_eq!(notes.banner(now), None);
+        push_at(&mut notes, now + secs(1), Warning, "later");
+        assert_eq!(notes.banner(now + secs(1)).unwrap().text, "later");
+        // still in the history
+        assert_eq!(notes.history().count(), 2);
+    }
+
+    #[test]
+    fn test_severity_filter() {
+        let mut notes = Notifications::default();
+        let now = Instant::now() + secs(60);
+        push_at(&mut notes, now, Info, "info");
+        push_at(&mut notes, now, Warning, "warning");
+        assert_eq!(notes.banner(now).unwrap().text, "warning");
+
+        notes.banner_min = Error;
+        assert_eq!(notes.banner(now), None);
+        notes.banner_min = Info;
+        assert_eq!(notes.banner(now).unwrap().text, "warning");
+
+        assert_eq!("warn".parse(), Ok(Warning));
+        assert!("loud".parse::<Severity>().is_err());
+    }
+
+    #[test]
+    fn test_repeats_coalesce() {
+        let mut notes = Notifications::default();
+        let notifier = notes.notifier();
+        notifier.warn("dang", "connection lost");
+        notifier.warn("dang", "connection lost");
+        notifier.push(Error, "dang", "connection lost");
+        assert_eq!(notes.poll().len(), 3);
+        let history: Vec<(Severity, usize)> =
+            notes.history().map(|n| (n.severity, n.repeats)).collect();
+        assert_eq!(history, [(Warning, 1), (Error, 0)]);
+
+        // pushing into a notifier with nothing behind it is fine
+        Notifier::default().warn("test", "nobody listening");
+    }
+}
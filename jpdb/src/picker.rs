@@ -0,0 +1,155 @@
+//! A short list of labelled choices with one of them selected, drawn as a popup. The addsig
+//! search and Tab completion both pick from one of these
+
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
+    Frame,
+};
+
+use crate::view;
+
+/// Choices of `T`, each with the label it's listed under
+pub struct Picker<T> {
+    items: Vec<(T, String)>,
+    selected: usize,
+}
+
+impl<T> Default for Picker<T> {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            selected: 0,
+        }
+    }
+}
+
+impl<T> Picker<T> {
+    pub fn new(items: Vec<(T, String)>) -> Self {
+        Self { items, selected: 0 }
+    }
+
+    /// Replace the choices, keeping the selection where it was if it's still in range
+    pub fn set_items(&mut self, items: Vec<(T, String)>) {
+        self.items = items;
+        self.selected = self.selected.min(self.items.len().saturating_sub(1));
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.selected = 0;
+    }
+
+    pub fn items(&self) -> &[(T, String)] {
+        &self.items
+    }
+
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected(&self) -> Option<&(T, String)> {
+        self.items.get(self.selected)
+    }
+
+    pub fn select_first(&mut self) {
+        self.selected = 0;
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = (self.selected + 1) % self.items.len();
+        }
+    }
+
+    pub fn select_prev(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = if self.selected == 0 {
+                self.items.len() - 1
+            } else {
+                self.selected - 1
+            };
+        }
+    }
+}
+
+/// How a picker popup is labelled
+pub struct PickerText<'a> {
+    /// Title of the input box, only drawn along with `input`
+    pub title: &'a str,
+    /// What's been typed so far, `None` to leave the input box out
+    pub input: Option<String>,
+    pub list_title: &'a str,
+    /// Key hints under the popup
+    pub help: &'a str,
+}
+
+/// Draw `picker` into `popup_area`, with the key hints on the line below it if `area` has room
+pub fn render<T>(
+    f: &mut Frame,
+    popup_area: Rect,
+    area: Rect,
+    picker: &Picker<T>,
+    text: &PickerText,
+) {
+    // Clear the background
+    f.render_widget(Clear, popup_area);
+
+    let list_area = match &text.input {
+        Some(input) => {
+            // Split popup into search input and results
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(3), // Search input
+                    Constraint::Min(0),    // Results
+                ])
+                .split(popup_area);
+            let input_paragraph = Paragraph::new(input.as_str())
+                .block(Block::default().borders(Borders::ALL).title(text.title))
+                .alignment(Alignment::Left);
+            f.render_widget(input_paragraph, chunks[0]);
+            chunks[1]
+        }
+        None => popup_area,
+    };
+
+    let items: Vec<ListItem> = picker
+        .items()
+        .iter()
+        .enumerate()
+        .map(|(i, (_, label))| {
+            let style = if i == picker.selected_index() {
+                Style::default()
+                    .bg(Color::Blue)
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(label.clone()).style(style)
+        })
+        .collect();
+
+    let results_list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(text.list_title),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+        );
+    f.render_widget(results_list, list_area);
+
+    // Add help text at the bottom, if there's a row left for it
+    if let Some(help_area) = view::line_below(popup_area, area) {
+        let help_text = Paragraph::new(text.help)
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center);
+        f.render_widget(help_text, help_area);
+    }
+}
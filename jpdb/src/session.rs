@@ -0,0 +1,176 @@
+//! What a debugging session has built up, kept across restarts: the selected signals with
+//! their radix, the breakpoints and what was typed at the prompt.
+//!
+//! It's written as JSON to `.jpdb_session.json` next to the wave file when jpdb quits, and read
+//! back when it starts, unless `--no-session` says otherwise. `session save` and
+//! `session load` do the same on request, to another file too:
+//!
+//! ```json
+//! {
+//!   "signals": [{ "name": "TOP.ibex_simple_system.clk_sys", "radix": "hex" }],
+//!   "breakpoints": ["break *0x1000a4", "# disabled: break hello_test.c:13"],
+//!   "history": ["b 0x1000a4", "continue"]
+//! }
+//! ```
+//!
+//! Breakpoints are the lines `bexport` writes, and come back the way `bimport` reads them
+
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+use shucks::FormattingType;
+
+/// The session file picked up from the wave file's directory
+pub const SESSION_FILE: &str = ".jpdb_session.json";
+
+/// Commands kept from the prompt's history, the most recent ones
+pub const MAX_SAVED_HISTORY: usize = 1000;
+
+/// Where the session for `wave_path` is kept
+pub fn session_path(wave_path: &Path) -> PathBuf {
+    wave_path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(SESSION_FILE)
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Session {
+    /// Selected signals by full name, in the order they were selected
+    pub signals: Vec<(String, FormattingType)>,
+    /// One gdb `break` line per breakpoint location, see [`crate::breakpoints::export_gdb`]
+    pub breakpoints: Vec<String>,
+    /// Commands typed at the prompt, oldest first
+    pub history: Vec<String>,
+}
+
+impl Session {
+    pub fn to_json(&self) -> String {
+        let skip = self.history.len().saturating_sub(MAX_SAVED_HISTORY);
+        let session = json!({
+            "signals": self
+                .signals
+                .iter()
+                .map(|(name, format)| json!({ "name": name, "radix": format.name() }))
+                .collect::<Vec<_>>(),
+            "breakpoints": self.breakpoints,
+            "history": &self.history[skip..],
+        });
+        // a Value always serializes
+        serde_json::to_string_pretty(&session).unwrap_or_default()
+    }
+
+    /// Parse a session file. Any of the three lists can be left out
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let json: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        let strings = |key: &str| -> Result<Vec<String>, String> {
+            let Some(list) = json.get(key) else {
+                return Ok(Vec::new());
+            };
+            list.as_array()
+                .and_then(|list| {
+                    list.iter()
+                        .map(|item| item.as_str().map(str::to_string))
+                        .collect()
+                })
+                .ok_or_else(|| format!("'{key}' should be a list of strings"))
+        };
+
+        let mut signals = Vec::new();
+        let listed: &[Value] = match json.get("signals") {
+            Some(list) => list
+                .as_array()
+                .ok_or("'signals' should be a list of signals")?
+                .as_slice(),
+            None => &[],
+        };
+        for signal in listed {
+            let name = signal
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or_else(|| format!("A signal without a name: {signal}"))?;
+            let format = match signal.get("radix").and_then(Value::as_str) {
+                None => FormattingType::Hex,
+                Some(radix) => FormattingType::from_name(radix)
+                    .ok_or_else(|| format!("Unknown radix '{radix}' for {name}"))?,
+            };
+            signals.push((name.to_string(), format));
+        }
+
+        Ok(Self {
+            signals,
+            breakpoints: strings("breakpoints")?,
+            history: strings("history")?,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.to_json() + "\n")
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        Self::from_json(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let session = Session {
+            signals: vec![
+                ("TOP.a".to_string(), FormattingType::Hex),
+                ("TOP.b".to_string(), FormattingType::SignedDecimal),
+            ],
+            breakpoints: vec![
+                "break *0x1000a4".to_string(),
+                "# disabled: break hello_test.c:13".to_string(),
+            ],
+            history: vec!["b 0x1000a4".to_string(), "continue".to_string()],
+        };
+        assert_eq!(Session::from_json(&session.to_json()).unwrap(), session);
+        assert_eq!(
+            Session::from_json("{}").unwrap(),
+            Session::default(),
+            "every list is optional"
+        );
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let session = Session {
+            history: (0..MAX_SAVED_HISTORY + 5).map(|i| i.to_string()).collect(),
+            ..Session::default()
+        };
+        let history = Session::from_json(&session.to_json()).unwrap().history;
+        assert_eq!(history.len(), MAX_SAVED_HISTORY);
+        assert_eq!(history[0], "5");
+    }
+
+    #[test]
+    fn test_bad_files() {
+        let error = |text: &str| Session::from_json(text).unwrap_err();
+        assert!(error("not json").contains("expected"));
+        assert_eq!(
+            error(r#"{"signals": [{"name": "TOP.a", "radix": "oct"}]}"#),
+            "Unknown radix 'oct' for TOP.a"
+        );
+        assert!(error(r#"{"signals": [{"radix": "hex"}]}"#).starts_with("A signal without a name"));
+        assert_eq!(
+            error(r#"{"breakpoints": [1]}"#),
+            "'breakpoints' should be a list of strings"
+        );
+        // a signal without a radix is in hex
+        assert_eq!(
+            Session::from_json(r#"{"signals": [{"name": "TOP.a"}]}"#)
+                .unwrap()
+                .signals,
+            [("TOP.a".to_string(), FormattingType::Hex)]
+        );
+    }
+}
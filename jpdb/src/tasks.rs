@@ -0,0 +1,166 @@
+//! Every background thread jpdb starts, so quitting has one place that stops them all. A task
+//! is kept with a name, its handle and the [`ShutdownSignal`] it watches. On the way out
+//! [`TaskRegistry::shutdown`] sets every signal, then waits a while for each thread to end and
+//! names the ones that didn't.
+//!
+//! New background threads go through [`TaskRegistry::spawn`] rather than `thread::spawn`
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How long a task gets to end once it's been told to
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often a task that hasn't ended yet is checked on
+const JOIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Set once jpdb wants a task to end. A task checks it between units of work, or stops some
+/// other way the registry's owner arranges before calling [`TaskRegistry::shutdown`]
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownSignal(Arc<AtomicBool>);
+
+impl ShutdownSignal {
+    pub fn set(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+struct Task {
+    name: String,
+    handle: JoinHandle<()>,
+    shutdown: ShutdownSignal,
+}
+
+#[derive(Default)]
+pub struct TaskRegistry {
+    tasks: Vec<Task>,
+}
+
+impl TaskRegistry {
+    /// Start `task` on a thread called `name`, handing it the signal it has to watch
+    pub fn spawn(
+        &mut self,
+        name: &str,
+        task: impl FnOnce(ShutdownSignal) + Send + 'static,
+    ) -> io::Result<()> {
+        let shutdown = ShutdownSignal::default();
+        let signal = shutdown.clone();
+        let handle = thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || task(signal))?;
+        self.tasks.push(Task {
+            name: name.to_string(),
+            handle,
+            shutdown,
+        });
+        Ok(())
+    }
+
+    /// One line per task, `<name>: running` or `<name>: finished`, in the order they started
+    pub fn lines(&self) -> Vec<String> {
+        self.tasks
+            .iter()
+            .map(|task| {
+                let state = if task.handle.is_finished() {
+                    "finished"
+                } else {
+                    "running"
+                };
+                format!("{}: {state}", task.name)
+            })
+            .collect()
+    }
+
+    /// Signal every task, then join them one after another, giving each up to `timeout`.
+    /// Tasks still running after that are left to the process exit. Returns their names
+    pub fn shutdown(&mut self, timeout: Duration) -> Vec<String> {
+        for task in &self.tasks {
+            task.shutdown.set();
+        }
+        let mut stuck = Vec::new();
+        for task in self.tasks.drain(..) {
+            let deadline = Instant::now() + timeout;
+            while !task.handle.is_finished() && Instant::now() < deadline {
+                thread::sleep(JOIN_POLL_INTERVAL);
+            }
+            if !task.handle.is_finished() {
+                log::warn!("Task {} didn't stop within {timeout:?}", task.name);
+                stuck.push(task.name);
+                continue;
+            }
+            if task.handle.join().is_err() {
+                log::warn!("Task {} panicked", task.name);
+            }
+        }
+        stuck
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A task that does work in small steps until it's told to stop
+    fn polite(shutdown: ShutdownSignal) {
+        while !shutdown.is_set() {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_clean_shutdown() {
+        let mut tasks = TaskRegistry::default();
+        tasks.spawn("worker", polite).unwrap();
+        tasks.spawn("listener", polite).unwrap();
+        tasks.spawn("oneshot", |_| {}).unwrap();
+
+        let started = Instant::now();
+        assert!(tasks.shutdown(SHUTDOWN_TIMEOUT).is_empty());
+        assert!(started.elapsed() < SHUTDOWN_TIMEOUT);
+        assert!(tasks.lines().is_empty());
+    }
+
+    #[test]
+    fn test_task_ignoring_the_signal_times_out() {
+        let mut tasks = TaskRegistry::default();
+        tasks.spawn("worker", polite).unwrap();
+        // never looks at its signal
+        let (release, wait) = std::sync::mpsc::channel::<()>();
+        tasks
+            .spawn("stubborn", move |_| {
+                let _ = wait.recv();
+            })
+            .unwrap();
+
+        let timeout = Duration::from_millis(100);
+        let started = Instant::now();
+        assert_eq!(tasks.shutdown(timeout), ["stubborn"]);
+        assert!(started.elapsed() >= timeout);
+        drop(release);
+    }
+
+    #[test]
+    fn test_listing() {
+        let mut tasks = TaskRegistry::default();
+        tasks.spawn("dang", polite).unwrap();
+        let (done, finished) = std::sync::mpsc::channel();
+        tasks
+            .spawn("oneshot", move |_| done.send(()).unwrap())
+            .unwrap();
+        finished.recv().unwrap();
+        // sending isn't quite the end of the thread
+        while !tasks.tasks[1].handle.is_finished() {
+            thread::sleep(JOIN_POLL_INTERVAL);
+        }
+
+        assert_eq!(tasks.lines(), ["dang: running", "oneshot: finished"]);
+        assert!(tasks.shutdown(SHUTDOWN_TIMEOUT).is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+//! Putting the terminal back the way jpdb found it. The TUI runs with raw mode on, on the
+//! alternate screen and with mouse capture, and a panic or an early `Err` that skips the
+//! teardown leaves the user's shell unusable. [`restore`] undoes all of it, and is safe to call
+//! from the panic hook, from `main` and more than once.
+//!
+//! SIGINT, SIGTERM and SIGHUP don't kill jpdb while the TUI is up, they set a [`QuitSignal`]
+//! the run loop checks, so quitting goes through the same teardown as `quit`
+
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crossterm::{
+    cursor::Show,
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+/// Whether [`enter`] has run without a [`restore`] after it
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Raw mode, the alternate screen and mouse capture, everything the TUI needs
+pub fn enter() -> io::Result<()> {
+    ACTIVE.store(true, Ordering::SeqCst);
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)
+}
+
+/// Undo [`enter`]. Does nothing if it never ran or the terminal's already been put back. Every
+/// step is tried even if an earlier one fails, the first error is what comes back
+pub fn restore() -> io::Result<()> {
+    if !ACTIVE.swap(false, Ordering::SeqCst) {
+        return Ok(());
+    }
+    let mut stdout = io::stdout();
+    let results = [
+        disable_raw_mode(),
+        execute!(stdout, LeaveAlternateScreen),
+        execute!(stdout, DisableMouseCapture),
+        execute!(stdout, Show),
+        stdout.flush(),
+    ];
+    results.into_iter().collect()
+}
+
+/// Restore the terminal before a panic message is printed, so it lands on the user's screen
+/// rather than the alternate one, readable. The backtrace comes along even without
+/// `RUST_BACKTRACE`, there's no rerunning to get it
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore();
+        default_hook(info);
+        let backtrace = Backtrace::capture();
+        if backtrace.status() != BacktraceStatus::Captured {
+            eprintln!("backtrace:\n{}", Backtrace::force_capture());
+        }
+    }));
+}
+
+/// Set when jpdb's been asked to quit from outside, by a signal
+#[derive(Debug, Clone, Default)]
+pub struct QuitSignal(Arc<AtomicBool>);
+
+impl QuitSignal {
+    /// Catch SIGINT, SIGTERM and SIGHUP. A second one while the first is still being handled
+    /// exits straight away, for a jpdb that's stuck. Elsewhere than unix there's nothing to
+    /// catch and the signal never gets set
+    pub fn install() -> io::Result<Self> {
+        let signal = QuitSignal::default();
+        #[cfg(unix)]
+        {
+            use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+            for sig in [SIGINT, SIGTERM, SIGHUP] {
+                signal_hook::flag::register_conditional_shutdown(sig, 1, Arc::clone(&signal.0))?;
+                signal_hook::flag::register(sig, Arc::clone(&signal.0))?;
+            }
+        }
+        Ok(signal)
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restore_without_enter() {
+        // nothing to undo, and nothing touched, however many times it's called
+        assert!(restore().is_ok());
+        assert!(restore().is_ok());
+        assert!(!QuitSignal::default().is_set());
+    }
+}
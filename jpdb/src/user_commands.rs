@@ -1,47 +1,701 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::addr_format::{parse_addrfmt_options, AddrFmtOption, AddrFormatter};
+use crate::aliases::AliasTable;
+use crate::breakpoints::{parse_gdb_line, parse_selectors, split_file_line, Selector};
+use crate::export_md::{Redaction, DEFAULT_REDACTION};
+use crate::model::{DebuggerModel, SigTime};
+use crate::notifications::Severity;
+use crate::value_names;
+use crate::view::{self, InstWindow, Pane, GPR_NAMES};
+use shucks::client::MAX_INSTRUCTION_WINDOW;
+use shucks::findinst::{FindOutcome, InstPredicate, SearchDirection};
+use shucks::{FormattingType, NameFormat};
 
 /// Represents a parsed breakpoint argument
 #[derive(Debug, PartialEq)]
 pub enum BreakpointTarget {
     Address(u32),
-    FileLine { file: PathBuf, line: u64 },
+    FileLine {
+        file: PathBuf,
+        line: u64,
+        /// Only the statement at this column of the line, `None` for all of it
+        column: Option<u64>,
+    },
 }
 
-/// Parse a breakpoint argument into either an address or file:line format
+/// Parse a breakpoint argument into either an address or file:line[:column] format
 pub fn parse_breakpoint_arg(input: &str) -> Result<BreakpointTarget, String> {
     let input = input.trim();
     if input.is_empty() {
         return Err("breakpoint requires an address or file:line argument".to_string());
     }
 
-    // Check if input is in file:line format
-    if let Some(colon_pos) = input.rfind(':') {
-        let file_part = &input[..colon_pos];
-        let line_part = &input[colon_pos + 1..];
+    // Check if input is in file:line or file:line:column format
+    if let Some((file, line, column)) = split_file_line(input) {
+        return Ok(BreakpointTarget::FileLine {
+            file: PathBuf::from(file),
+            line,
+            column,
+        });
+    }
 
-        // Try to parse the line number and ensure file part is not empty
-        if !file_part.is_empty() {
-            if let Ok(line_num) = line_part.parse::<u64>() {
-                // This looks like file:line format
-                return Ok(BreakpointTarget::FileLine {
-                    file: PathBuf::from(file_part),
-                    line: line_num,
-                });
+    match parse_hex_u32(input) {
+        Some(address) => Ok(BreakpointTarget::Address(address)),
+        None => Err(format!("Invalid breakpoint format: {input}")),
+    }
+}
+
+/// Addresses and values are hex, with or without a 0x prefix
+pub fn parse_hex_u32(input: &str) -> Option<u32> {
+    let hex = input
+        .strip_prefix("0x")
+        .or_else(|| input.strip_prefix("0X"))
+        .unwrap_or(input);
+    u32::from_str_radix(hex, 16).ok()
+}
+
+/// A GPR by ABI name (`sp`, `a0`, `fp`) or number (`x2`, `2`)
+pub fn parse_gpr(input: &str) -> Option<usize> {
+    if input == "fp" {
+        return Some(8);
+    }
+    if let Some(idx) = GPR_NAMES.iter().position(|name| *name == input) {
+        return Some(idx);
+    }
+    let idx = input
+        .strip_prefix('x')
+        .unwrap_or(input)
+        .parse::<usize>()
+        .ok()?;
+    (idx < 32).then_some(idx)
+}
+
+/// What an `assert` command asks for
+#[derive(Debug, PartialEq)]
+pub enum AssertArg {
+    List,
+    Reg { reg: usize, op: String, value: u32 },
+    PcRange { lo: u32, hi: u32, inside: bool },
+    Delete(u32),
+}
+
+const COMPARE_OPS: [&str; 6] = ["<", "<=", ">", ">=", "==", "!="];
+
+/// Parse the arguments of an `assert` command, e.g. `reg sp >= 0x20000000`
+pub fn parse_assert_arg(input: &str, addr_fmt: &AddrFormatter) -> Result<AssertArg, String> {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    let hex = |value: &str| parse_hex_u32(value).ok_or_else(|| format!("Invalid value: {value}"));
+    match parts[..] {
+        [] | ["list"] => Ok(AssertArg::List),
+        ["reg", reg, op, value] => {
+            let reg = parse_gpr(reg).ok_or_else(|| format!("Unknown register: {reg}"))?;
+            if !COMPARE_OPS.contains(&op) {
+                return Err(format!(
+                    "Unknown comparison '{op}', expected one of {}",
+                    COMPARE_OPS.join(" ")
+                ));
+            }
+            Ok(AssertArg::Reg {
+                reg,
+                op: op.to_string(),
+                value: hex(value)?,
+            })
+        }
+        ["pc", lo, hi, side] => {
+            let inside = match side {
+                "inside" => true,
+                "outside" => false,
+                _ => return Err(format!("Expected 'inside' or 'outside', got '{side}'")),
+            };
+            let (lo, hi) = (hex(lo)?, hex(hi)?);
+            if lo > hi {
+                return Err(format!(
+                    "Empty range {}-{}",
+                    addr_fmt.hex(lo.into()),
+                    addr_fmt.hex(hi.into())
+                ));
+            }
+            Ok(AssertArg::PcRange { lo, hi, inside })
+        }
+        ["del", id] => id
+            .parse::<u32>()
+            .map(AssertArg::Delete)
+            .map_err(|_| format!("Invalid assertion id: {id}")),
+        _ => Err(
+            "usage: assert [list] | reg <reg> <op> <value> | pc <lo> <hi> <inside|outside> | del <id>"
+                .to_string(),
+        ),
+    }
+}
+
+/// The code a `skip add` covers
+#[derive(Debug, PartialEq)]
+pub enum SkipTarget {
+    /// `[lo, hi]`, both ends included
+    Addresses { lo: u32, hi: u32 },
+    /// Every address behind lines `first` to `last` of `file`
+    Lines { file: String, first: u64, last: u64 },
+}
+
+/// What a `skip` command asks for
+#[derive(Debug, PartialEq)]
+pub enum SkipArg {
+    List,
+    Add(SkipTarget),
+    Delete(u32),
+}
+
+/// Parse the arguments of a `skip` command, e.g. `add 0x100-0x1ff` or `add main.c:10-14`
+pub fn parse_skip_arg(input: &str, addr_fmt: &AddrFormatter) -> Result<SkipArg, String> {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    match parts[..] {
+        [] | ["list"] => Ok(SkipArg::List),
+        ["add", target] => {
+            let (file, range) = match target.rfind(':') {
+                Some(colon) => (Some(&target[..colon]), &target[colon + 1..]),
+                None => (None, target),
+            };
+            let (first, last) = range
+                .split_once('-')
+                .ok_or_else(|| format!("Expected a range like <lo>-<hi>, got '{target}'"))?;
+            let target = match file {
+                Some(file) => {
+                    let line = |value: &str| {
+                        value
+                            .parse::<u64>()
+                            .map_err(|_| format!("Invalid line number: {value}"))
+                    };
+                    let (first, last) = (line(first)?, line(last)?);
+                    if file.is_empty() || first > last {
+                        return Err(format!("Invalid line range: {target}"));
+                    }
+                    SkipTarget::Lines {
+                        file: file.to_string(),
+                        first,
+                        last,
+                    }
+                }
+                None => {
+                    let hex = |value: &str| {
+                        parse_hex_u32(value).ok_or_else(|| format!("Invalid address: {value}"))
+                    };
+                    let (lo, hi) = (hex(first)?, hex(last)?);
+                    if lo > hi {
+                        return Err(format!(
+                            "Empty range {}-{}",
+                            addr_fmt.hex(lo.into()),
+                            addr_fmt.hex(hi.into())
+                        ));
+                    }
+                    SkipTarget::Addresses { lo, hi }
+                }
+            };
+            Ok(SkipArg::Add(target))
+        }
+        ["del", id] => id
+            .parse::<u32>()
+            .map(SkipArg::Delete)
+            .map_err(|_| format!("Invalid skip range id: {id}")),
+        _ => Err(
+            "usage: skip [list] | add <lo>-<hi> | add <file>:<first>-<last> | del <id>".to_string(),
+        ),
+    }
+}
+
+/// A debugger setting that can be changed with `set`
+#[derive(Debug, PartialEq)]
+pub enum Setting {
+    ShowBytes(bool),
+    Heat(bool),
+    StopOnLivelock(bool),
+    BpCheck(bool),
+    FindBudget(usize),
+    ElfWatch(bool),
+    LogBuffer(usize),
+    FastForward(bool),
+    /// Least severe notification the banner shows
+    Notify(Severity),
+    /// Seconds a notification stays in the banner
+    NotifyTime(u64),
+    /// Changes to how addresses are written, the rest stays as it was
+    AddrFmt(Vec<AddrFmtOption>),
+    /// Split the code panes when execution crosses into another image
+    DualContext(bool),
+    /// Signal value changes either side of the cursor to work out ahead of time, `None` for off
+    Prefetch(Option<usize>),
+    /// How many instructions the Instructions pane shows
+    InstWindow(InstWindow),
+}
+
+fn parse_on_off(value: &str) -> Result<bool, String> {
+    match value {
+        "on" | "true" | "1" => Ok(true),
+        "off" | "false" | "0" => Ok(false),
+        _ => Err(format!("Expected 'on' or 'off', got '{value}'")),
+    }
+}
+
+/// Parse the arguments of a `set` command, e.g. `showbytes on`
+pub fn parse_set_arg(input: &str) -> Result<Setting, String> {
+    let mut parts = input.split_whitespace();
+    let name = parts
+        .next()
+        .ok_or_else(|| "set requires a setting name".to_string())?;
+    if name == "addrfmt" {
+        let options = parts.collect::<Vec<_>>().join(" ");
+        return Ok(Setting::AddrFmt(parse_addrfmt_options(&options)?));
+    }
+    let value = parts
+        .next()
+        .ok_or_else(|| format!("set {name} requires a value"))?;
+
+    match name {
+        "showbytes" => Ok(Setting::ShowBytes(parse_on_off(value)?)),
+        "heat" => Ok(Setting::Heat(parse_on_off(value)?)),
+        "livelock" => Ok(Setting::StopOnLivelock(parse_on_off(value)?)),
+        "bpcheck" => Ok(Setting::BpCheck(parse_on_off(value)?)),
+        "elfwatch" => Ok(Setting::ElfWatch(parse_on_off(value)?)),
+        "fastforward" => Ok(Setting::FastForward(parse_on_off(value)?)),
+        "dualcontext" => Ok(Setting::DualContext(parse_on_off(value)?)),
+        "findbudget" => match value.parse::<usize>() {
+            Ok(budget) if budget > 0 => Ok(Setting::FindBudget(budget)),
+            _ => Err(format!(
+                "Expected a positive instruction count, got '{value}'"
+            )),
+        },
+        "logbuffer" => match value.parse::<usize>() {
+            Ok(capacity) if capacity > 0 => Ok(Setting::LogBuffer(capacity)),
+            _ => Err(format!("Expected a positive message count, got '{value}'")),
+        },
+        "notify" => Ok(Setting::Notify(value.parse()?)),
+        "prefetch" => match value {
+            "off" => Ok(Setting::Prefetch(None)),
+            _ => match value.parse::<usize>() {
+                Ok(span) if span > 0 => Ok(Setting::Prefetch(Some(span))),
+                _ => Err(format!(
+                    "Expected a positive change count or 'off', got '{value}'"
+                )),
+            },
+        },
+        "instwindow" => match value {
+            "auto" => Ok(Setting::InstWindow(InstWindow::Auto)),
+            _ => match value.parse::<usize>() {
+                Ok(count) if (1..=MAX_INSTRUCTION_WINDOW).contains(&count) => {
+                    Ok(Setting::InstWindow(InstWindow::Fixed(count)))
+                }
+                _ => Err(format!(
+                    "Expected 1 to {MAX_INSTRUCTION_WINDOW} instructions or 'auto', got '{value}'"
+                )),
+            },
+        },
+        "notifytime" => match value.parse::<u64>() {
+            Ok(secs) if secs > 0 => Ok(Setting::NotifyTime(secs)),
+            _ => Err(format!(
+                "Expected a positive number of seconds, got '{value}'"
+            )),
+        },
+        _ => Err(format!("Unknown setting: {name}")),
+    }
+}
+
+/// Parse the argument of a `show` command, the pane to print
+pub fn parse_show_arg(input: &str) -> Result<Pane, String> {
+    match input.trim() {
+        "exec" | "execution" => Ok(Pane::Execution),
+        "source" | "src" => Ok(Pane::Source),
+        "signals" | "sig" => Ok(Pane::Signals),
+        _ => Err("usage: show <exec|source|signals>".to_string()),
+    }
+}
+
+/// Parse the arguments of a `reghist` command, a register and optionally `csv <path>`
+pub fn parse_reghist_arg(input: &str) -> Result<(usize, Option<PathBuf>), String> {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    let (reg, path) = match parts[..] {
+        [reg] => (reg, None),
+        [reg, "csv", path] => (reg, Some(PathBuf::from(path))),
+        _ => return Err("usage: reghist <reg> [csv <path>]".to_string()),
+    };
+    let reg = parse_gpr(reg).ok_or_else(|| format!("Unknown register: {reg}"))?;
+    Ok((reg, path))
+}
+
+/// What a `sigenum` command does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SigenumArg {
+    Load {
+        signal: String,
+        path: PathBuf,
+        format: NameFormat,
+    },
+    Clear {
+        signal: String,
+    },
+}
+
+/// Parse the arguments of a `sigenum` command: a signal and a file of names for its values,
+/// `short` to leave the value out, or `clear` and the signal
+pub fn parse_sigenum_arg(input: &str) -> Result<SigenumArg, String> {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    let (signal, path, format) = match parts[..] {
+        ["clear", signal] => {
+            return Ok(SigenumArg::Clear {
+                signal: signal.to_string(),
+            })
+        }
+        [signal, path] => (signal, path, NameFormat::NameAndValue),
+        [signal, path, "short"] => (signal, path, NameFormat::Name),
+        _ => {
+            return Err(
+                "usage: sigenum <signal> <file> [short] | sigenum clear <signal>".to_string(),
+            )
+        }
+    };
+    Ok(SigenumArg::Load {
+        signal: signal.to_string(),
+        path: PathBuf::from(path),
+        format,
+    })
+}
+
+/// Parse the arguments of a `format` command: a selected signal and the radix to show it in
+pub fn parse_format_arg(input: &str) -> Result<(String, FormattingType), String> {
+    match input.split_whitespace().collect::<Vec<_>>()[..] {
+        [signal, radix] => FormattingType::from_name(radix)
+            .map(|format| (signal.to_string(), format))
+            .ok_or_else(|| format!("Unknown radix '{radix}', expected hex, dec, bin or sdec")),
+        _ => Err("usage: format <signal> <hex|dec|bin|sdec>".to_string()),
+    }
+}
+
+/// Parse the argument of a `sigtime` command: a time index, a time with a `ps`, `ns` or `us`
+/// unit, a signed move from where the pane is, or `off`
+pub fn parse_sigtime_arg(input: &str) -> Result<SigTime, String> {
+    let input = input.trim();
+    let usage = || "usage: sigtime <index|<time>ps|+N|-N|off>".to_string();
+    if input == "off" {
+        return Ok(SigTime::Off);
+    }
+    if input.starts_with(['+', '-']) {
+        return input.parse().map(SigTime::Relative).map_err(|_| usage());
+    }
+    for (unit, ps) in [("ps", 1), ("ns", 1_000), ("us", 1_000_000)] {
+        if let Some(time) = input.strip_suffix(unit) {
+            let time: u64 = time.trim().parse().map_err(|_| usage())?;
+            return time.checked_mul(ps).map(SigTime::Time).ok_or_else(usage);
+        }
+    }
+    input.parse().map(SigTime::Index).map_err(|_| usage())
+}
+
+/// What a `timeline` command asks for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimelineArg {
+    Show(bool),
+    /// Move execution this fraction of the way along the trace
+    Goto(f64),
+}
+
+/// Parse the argument of a `timeline` command: `on`, `off`, or `goto` a percentage of the trace
+pub fn parse_timeline_arg(input: &str) -> Result<TimelineArg, String> {
+    let usage = || "usage: timeline <on|off|goto <percent>%>".to_string();
+    let mut parts = input.split_whitespace();
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some("goto"), Some(percent), None) => {
+            let percent: f64 = percent
+                .strip_suffix('%')
+                .unwrap_or(percent)
+                .parse()
+                .map_err(|_| usage())?;
+            if !(0.0..=100.0).contains(&percent) {
+                return Err(format!(
+                    "Expected a percentage from 0 to 100, got {percent}"
+                ));
             }
+            Ok(TimelineArg::Goto(percent / 100.0))
+        }
+        (Some(value), None, None) => parse_on_off(value)
+            .map(TimelineArg::Show)
+            .map_err(|_| usage()),
+        _ => Err(usage()),
+    }
+}
+
+/// Parse the arguments of a `findinst` command: `<mnemonic|rd=<reg>|mask:<value>/<mask>>`,
+/// optionally followed by `back` to search backwards
+pub fn parse_findinst_arg(input: &str) -> Result<(InstPredicate, SearchDirection), String> {
+    let mut parts = input.split_whitespace();
+    let pattern = parts.next().ok_or_else(|| {
+        "findinst requires a mnemonic, rd=<reg> or mask:<value>/<mask>".to_string()
+    })?;
+    let direction = match parts.next() {
+        None => SearchDirection::Forward,
+        Some("back") => SearchDirection::Backward,
+        Some(other) => return Err(format!("Expected 'back', got '{other}'")),
+    };
+    if parts.next().is_some() {
+        return Err("findinst takes one pattern and an optional 'back'".to_string());
+    }
+
+    let predicate = if let Some(reg) = pattern.strip_prefix("rd=") {
+        InstPredicate::Rd(parse_gpr(reg).ok_or_else(|| format!("Invalid register: {reg}"))?)
+    } else if let Some(mask) = pattern.strip_prefix("mask:") {
+        let (value, mask) = mask
+            .split_once('/')
+            .ok_or_else(|| format!("Expected mask:<value>/<mask>, got '{pattern}'"))?;
+        let parse =
+            |hex: &str| parse_hex_u32(hex).ok_or_else(|| format!("Invalid hex value: {hex}"));
+        InstPredicate::Mask {
+            value: parse(value)?,
+            mask: parse(mask)?,
+        }
+    } else {
+        InstPredicate::Mnemonic(pattern.to_lowercase())
+    };
+    Ok((predicate, direction))
+}
+
+/// What to tell the user about a `findinst` that ended with `outcome`, addresses written by
+/// `fmt_addr`
+pub fn find_outcome_line(
+    predicate: &InstPredicate,
+    direction: SearchDirection,
+    outcome: &FindOutcome,
+    fmt_addr: impl Fn(u32) -> String,
+) -> String {
+    match outcome {
+        FindOutcome::Found {
+            time_idx,
+            pc,
+            instruction,
+            scanned,
+        } => format!(
+            "Found {instruction} at {}, time index {time_idx} \
+             ({scanned} instructions scanned)",
+            fmt_addr(*pc)
+        ),
+        FindOutcome::OverBudget { scanned } => {
+            format!("No {predicate} found within {scanned} instructions")
+        }
+        FindOutcome::EndOfTrace { scanned } => {
+            let end = match direction {
+                SearchDirection::Forward => "end",
+                SearchDirection::Backward => "start",
+            };
+            format!(
+                "No {predicate} found before the {end} of the trace \
+                 ({scanned} instructions scanned)"
+            )
+        }
+    }
+}
+
+/// Warning for breakpoint locations the PC never reaches, `None` if they all get hit
+pub fn never_hit_warning(
+    never_hit: &[u32],
+    total: usize,
+    fmt_addr: impl Fn(u32) -> String,
+) -> Option<String> {
+    match never_hit {
+        [] => None,
+        [addr] if total == 1 => Some(format!(
+            "Warning: address {} never appears in the PC trace, breakpoint will never be hit",
+            fmt_addr(*addr)
+        )),
+        _ => Some(format!(
+            "Warning: {} of {} locations never execute ({})",
+            never_hit.len(),
+            total,
+            never_hit
+                .iter()
+                .map(|&a| fmt_addr(a))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Parse the arguments of a `dump` command: `state`, optionally followed by a path to write to
+pub fn parse_dump_arg(input: &str) -> Result<Option<PathBuf>, String> {
+    let mut parts = input.split_whitespace();
+    match parts.next() {
+        Some("state") => {}
+        Some(other) => return Err(format!("Unknown dump target: {other}")),
+        None => return Err("dump requires a target, e.g. 'dump state'".to_string()),
+    }
+    let path = parts.next().map(PathBuf::from);
+    if parts.next().is_some() {
+        return Err("dump state takes at most one path".to_string());
+    }
+    Ok(path)
+}
+
+/// Parse the arguments of an `export` command: `md`, where to write it, and any number of
+/// `redact <prefix>[=<replacement>]`
+pub fn parse_export_arg(input: &str) -> Result<(PathBuf, Vec<Redaction>), String> {
+    let usage = || "usage: export md <path> [redact <prefix>[=<replacement>]]...".to_string();
+    let mut parts = input.split_whitespace();
+    match parts.next() {
+        Some("md") => {}
+        Some(other) => return Err(format!("Unknown export format: {other}")),
+        None => return Err(usage()),
+    }
+    let path = parts.next().map(PathBuf::from).ok_or_else(usage)?;
+    let mut redactions = Vec::new();
+    while let Some(word) = parts.next() {
+        let spec = parts
+            .next()
+            .filter(|_| word == "redact")
+            .ok_or_else(usage)?;
+        let (from, to) = spec.split_once('=').unwrap_or((spec, DEFAULT_REDACTION));
+        if from.is_empty() {
+            return Err(usage());
         }
+        redactions.push(Redaction {
+            from: from.to_string(),
+            to: to.to_string(),
+        });
+    }
+    Ok((path, redactions))
+}
+
+/// The one path `command` takes
+fn parse_path_arg(command: &str, input: &str) -> Result<PathBuf, String> {
+    let mut parts = input.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some(path), None) => Ok(PathBuf::from(path)),
+        _ => Err(format!("usage: {command} <path>")),
+    }
+}
+
+/// Parse the arguments of a `session` command, e.g. `save debug.json`. Returns whether it's a
+/// load, and the path if one was given
+fn parse_session_arg(input: &str) -> Result<(bool, Option<PathBuf>), String> {
+    let mut parts = input.split_whitespace();
+    let load = match parts.next() {
+        Some("save") => false,
+        Some("load") => true,
+        _ => return Err("usage: session <save|load> [path]".to_string()),
+    };
+    match (parts.next(), parts.next()) {
+        (path, None) => Ok((load, path.map(PathBuf::from))),
+        _ => Err("usage: session <save|load> [path]".to_string()),
+    }
+}
+
+/// Parse the arguments of a `memset` command, e.g. `0x200000 de ad be ef`
+pub fn parse_memset_arg(input: &str) -> Result<(u32, Vec<u8>), String> {
+    let mut parts = input.split_whitespace();
+    let addr_str = parts
+        .next()
+        .ok_or_else(|| "memset requires an address".to_string())?;
+    let addr = parse_hex_u32(addr_str).ok_or_else(|| format!("Invalid address: {addr_str}"))?;
+
+    let data = parts
+        .map(|b| {
+            u8::from_str_radix(b.trim_start_matches("0x"), 16)
+                .map_err(|_| format!("Invalid byte: {b}"))
+        })
+        .collect::<Result<Vec<u8>, String>>()?;
+    if data.is_empty() {
+        return Err("memset requires at least one byte".to_string());
     }
+    Ok((addr, data))
+}
+
+/// Parse the arguments of an `ignore` command, a breakpoint id and how many hits to let go by
+pub fn parse_ignore_arg(input: &str) -> Result<(u32, u32), String> {
+    let mut parts = input.split_whitespace();
+    let (Some(id), Some(count), None) = (parts.next(), parts.next(), parts.next()) else {
+        return Err("usage: ignore <id> <count>".to_string());
+    };
+    let id = id
+        .parse()
+        .map_err(|_| format!("Invalid breakpoint id: {id}"))?;
+    let count = count
+        .parse()
+        .map_err(|_| format!("Invalid ignore count: {count}"))?;
+    Ok((id, count))
+}
+
+/// Most instructions one `disas` lists, and what a function's listing is cut off at
+pub const MAX_DISAS_INSTRUCTIONS: usize = 100;
+
+/// Instructions `disas` lists from an address or line when it isn't told how many
+pub const DEFAULT_DISAS_COUNT: usize = 10;
+
+/// Where a `disas` listing starts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisasTarget {
+    /// A `0x` address
+    Address(u32),
+    /// A symbol, or failing that a hex address without its `0x`
+    Name(String),
+    /// The first address of a line
+    FileLine {
+        file: String,
+        line: u64,
+        column: Option<u64>,
+    },
+}
 
-    // Try to parse as address (support both hex with 0x prefix and without)
-    let addr = if input.starts_with("0x") || input.starts_with("0X") {
-        u32::from_str_radix(&input[2..], 16)
+/// Parse the arguments of a `disas` command: where to start and optionally how many
+/// instructions, e.g. `main`, `0x1000a4 20` or `main.c:42`
+pub fn parse_disas_arg(input: &str) -> Result<(DisasTarget, Option<usize>), String> {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    let (target, count) = match parts[..] {
+        [target] => (target, None),
+        [target, count] => (target, Some(count)),
+        _ => return Err("usage: disas <addr|symbol|file:line> [count]".to_string()),
+    };
+    let count = count
+        .map(|count| match count.parse::<usize>() {
+            Ok(n) if (1..=MAX_DISAS_INSTRUCTIONS).contains(&n) => Ok(n),
+            _ => Err(format!(
+                "Expected 1 to {MAX_DISAS_INSTRUCTIONS} instructions, got '{count}'"
+            )),
+        })
+        .transpose()?;
+    let target = if let Some((file, line, column)) = split_file_line(target) {
+        DisasTarget::FileLine {
+            file: file.to_string(),
+            line,
+            column,
+        }
+    } else if target.starts_with("0x") || target.starts_with("0X") {
+        DisasTarget::Address(
+            parse_hex_u32(target).ok_or_else(|| format!("Invalid address: {target}"))?,
+        )
     } else {
-        u32::from_str_radix(input, 16)
+        DisasTarget::Name(target.to_string())
     };
+    Ok((target, count))
+}
+
+/// Runs a confirmed action, see [`CommandOutcome::NeedsConfirmation`]
+pub type ConfirmedAction = Box<dyn FnOnce(&mut crate::App) -> Result<(), String>>;
+
+/// What became of a command that didn't fail
+pub enum CommandOutcome {
+    Done,
+    /// Destructive, `on_confirm` only runs once the user answers `prompt` with y or Enter
+    NeedsConfirmation {
+        prompt: String,
+        on_confirm: ConfirmedAction,
+    },
+}
 
-    match addr {
-        Ok(address) => Ok(BreakpointTarget::Address(address)),
-        Err(_) => Err(format!("Invalid breakpoint format: {input}")),
+impl CommandOutcome {
+    fn confirm(
+        prompt: String,
+        on_confirm: impl FnOnce(&mut crate::App) -> Result<(), String> + 'static,
+    ) -> Self {
+        CommandOutcome::NeedsConfirmation {
+            prompt,
+            on_confirm: Box::new(on_confirm),
+        }
     }
 }
 
@@ -54,29 +708,68 @@ pub enum UserCommand {
     Help,
     Clear,
     Breakpoint,
+    Bdel,
+    Bdisable,
+    Benable,
+    Bclear,
+    Bexport,
+    Bimport,
+    Session,
+    Ignore,
+    Bhits,
     Continue,
     Toggle,
     Addsig,
+    Delsig,
     Debug,
     Surfer,
     SurferConnect,
+    Set,
+    Memset,
+    Delta,
+    Dump,
+    Export,
+    Regs,
+    Assert,
+    Skip,
+    Debugfile,
+    Status,
+    Notifications,
+    Monitor,
+    Findinst,
+    Reloadelf,
+    Reloadwave,
+    Show,
+    Sigtime,
+    Sigstats,
+    Sigchanged,
+    Sigenum,
+    Format,
+    Reghist,
+    Timeline,
+    Disas,
+    Alias,
+    Unalias,
+    /// Panics on purpose, to check the terminal comes back. Debug builds only
+    #[cfg(debug_assertions)]
+    Panic,
 }
 
 impl UserCommand {
     /// Execute the command with the given application context
-    pub fn execute(&self, app: &mut crate::App, args: &str) -> Result<(), String> {
+    pub fn execute(&self, app: &mut crate::App, args: &str) -> Result<CommandOutcome, String> {
         match self {
             UserCommand::Quit => {
                 app.should_quit = true;
-                Ok(())
+                Ok(CommandOutcome::Done)
             }
             UserCommand::Next => {
                 app.step_next();
-                Ok(())
+                Ok(CommandOutcome::Done)
             }
             UserCommand::Step => {
                 app.step_next();
-                Ok(())
+                Ok(CommandOutcome::Done)
             }
             UserCommand::Help => {
                 let registry = CommandRegistry::new();
@@ -95,6 +788,14 @@ impl UserCommand {
                         content.push(format!("  {:<9} -- {}", aliases_str, cmd.description()));
                     }
 
+                    if !app.aliases.is_empty() {
+                        content.push("".to_string());
+                        content.push("User aliases:".to_string());
+                        for (name, expansion) in app.aliases.sorted() {
+                            content.push(format!("  {name:<9} -- {expansion}"));
+                        }
+                    }
+
                     content.push("".to_string());
                     content.push("Keyboard shortcuts:".to_string());
                     content.push("  Ctrl+D    -- Quit the debugger".to_string());
@@ -114,59 +815,233 @@ impl UserCommand {
                         for example in command.examples() {
                             content.push(format!("  {example}"));
                         }
+                    } else if let Some(expansion) = app.aliases.get(command_name) {
+                        content.push(format!(
+                            "'{command_name}' is a user alias for '{expansion}'"
+                        ));
                     } else {
                         return Err(format!("Unknown command: {command_name}"));
                     }
                 }
 
-                // Activate the help modal with the content
-                app.help_modal_state.activate(content);
-                Ok(())
+                app.show_modal(content);
+                Ok(CommandOutcome::Done)
             }
             UserCommand::Clear => {
                 app.command_history.clear();
                 app.scroll_offset = 0;
-                Ok(())
+                Ok(CommandOutcome::Done)
             }
             UserCommand::Breakpoint => match parse_breakpoint_arg(args)? {
                 BreakpointTarget::Address(address) => match app.set_breakpoint(address) {
-                    Ok(()) => {
-                        app.command_history
-                            .push(format!("Breakpoint set at address 0x{address:x}"));
-                        Ok(())
+                    Ok(breakpoint) => {
+                        app.command_history.push(format!(
+                            "Breakpoint {} set at address {}",
+                            breakpoint.id,
+                            app.format_addr(address)
+                        ));
+                        if let Some(start) = app.mid_instruction(address) {
+                            app.command_history.push(format!(
+                                "Warning: address {} is {} bytes into the instruction at {}",
+                                app.format_addr(address),
+                                address - start,
+                                app.format_addr(start)
+                            ));
+                        }
+                        let never_hit = app.never_hit_breakpoints(&[address]);
+                        if let Some(warning) =
+                            never_hit_warning(&never_hit, 1, |addr| app.format_addr(addr))
+                        {
+                            app.command_history.push(warning);
+                        }
+                        Ok(CommandOutcome::Done)
                     }
                     Err(e) => Err(format!("Failed to set breakpoint: {e}")),
                 },
-                BreakpointTarget::FileLine { file, line } => {
+                BreakpointTarget::FileLine { file, line, column } => {
                     let file_str = file.to_string_lossy();
-                    match app.set_breakpoint_at_line(&file_str, line) {
-                        Ok(addresses) => {
+                    let location = match column {
+                        Some(column) => format!("{file_str}:{line}:{column}"),
+                        None => format!("{file_str}:{line}"),
+                    };
+                    match app.set_breakpoint_at_line(&file_str, line, column) {
+                        Ok(breakpoints) => {
+                            let addresses: Vec<u32> =
+                                breakpoints.iter().map(|bp| bp.address).collect();
+                            let ids = breakpoints
+                                .iter()
+                                .map(|bp| bp.id.to_string())
+                                .collect::<Vec<_>>()
+                                .join(", ");
                             if addresses.len() == 1 {
                                 app.command_history.push(format!(
-                                    "Breakpoint set at {}:{} (address 0x{:x})",
-                                    file_str, line, addresses[0]
+                                    "Breakpoint {} set at {} (address {})",
+                                    ids,
+                                    location,
+                                    app.format_addr(addresses[0])
                                 ));
                             } else {
                                 app.command_history.push(format!(
-                                    "Breakpoint set at {}:{} ({} addresses: {})",
-                                    file_str,
-                                    line,
+                                    "Breakpoints {} set at {} ({} addresses: {})",
+                                    ids,
+                                    location,
                                     addresses.len(),
                                     addresses
                                         .iter()
-                                        .map(|a| format!("0x{a:x}"))
+                                        .map(|&a| app.format_addr(a))
                                         .collect::<Vec<_>>()
                                         .join(", ")
                                 ));
                             }
-                            Ok(())
+                            let never_hit = app.never_hit_breakpoints(&addresses);
+                            if let Some(warning) =
+                                never_hit_warning(&never_hit, addresses.len(), |addr| {
+                                    app.format_addr(addr)
+                                })
+                            {
+                                app.command_history.push(warning);
+                            }
+                            Ok(CommandOutcome::Done)
                         }
-                        Err(e) => Err(format!(
-                            "Failed to set breakpoint at {file_str}:{line}: {e}"
-                        )),
+                        Err(e) => Err(format!("Failed to set breakpoint at {location}: {e}")),
                     }
                 }
             },
+            UserCommand::Bdel => {
+                let selectors = parse_selectors(args)?;
+                let count = app.breakpoint_count();
+                let ask = selectors.contains(&Selector::All) && count > 1;
+                let delete = move |app: &mut crate::App| -> Result<(), String> {
+                    let result = app.delete_breakpoints(&selectors)?;
+                    app.command_history.extend(result.report("deleted"));
+                    Ok(())
+                };
+                if ask {
+                    return Ok(CommandOutcome::confirm(
+                        format!("really delete {count} breakpoints? [y/N]"),
+                        delete,
+                    ));
+                }
+                delete(app)?;
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Bdisable => {
+                let selectors = parse_selectors(args)?;
+                let result = app.set_breakpoints_enabled(&selectors, false)?;
+                app.command_history.extend(result.report("disabled"));
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Benable => {
+                let selectors = parse_selectors(args)?;
+                let result = app.set_breakpoints_enabled(&selectors, true)?;
+                app.command_history.extend(result.report("enabled"));
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Bclear => {
+                let file = args.trim();
+                if file.is_empty() {
+                    return Err("bclear requires a file name".to_string());
+                }
+                let result = app.clear_file_breakpoints(file)?;
+                app.command_history.extend(result.report("deleted"));
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Bexport => {
+                let path = parse_path_arg("bexport", args)?;
+                let script = app.export_breakpoints();
+                let count = script
+                    .lines()
+                    .filter(|line| matches!(parse_gdb_line(line), Ok(Some(_))))
+                    .count();
+                let prompt = format!("overwrite {}? [y/N]", path.display());
+                let overwrite = path.exists();
+                let write = move |app: &mut crate::App| -> Result<(), String> {
+                    crate::dump::write_atomically(&path, &script)
+                        .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+                    app.command_history.push(format!(
+                        "Wrote {count} breakpoint line{} to {}",
+                        if count == 1 { "" } else { "s" },
+                        path.display()
+                    ));
+                    Ok(())
+                };
+                if overwrite {
+                    return Ok(CommandOutcome::confirm(prompt, write));
+                }
+                write(app)?;
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Bimport => {
+                let path = parse_path_arg("bimport", args)?;
+                let script = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+                let (mut imported, mut failed) = (0, 0);
+                // a bad line is reported and skipped, the rest still go in
+                for (number, line) in script.lines().enumerate().map(|(i, l)| (i + 1, l)) {
+                    let result = match parse_gdb_line(line) {
+                        Ok(None) => continue,
+                        Ok(Some(gdb_break)) => app.import_breakpoint(&gdb_break),
+                        Err(e) => Err(e),
+                    };
+                    let report = match result {
+                        Ok(report) => {
+                            imported += 1;
+                            report
+                        }
+                        Err(e) => {
+                            failed += 1;
+                            format!("error: {e}")
+                        }
+                    };
+                    app.command_history
+                        .push(format!("{}:{number}: {report}", path.display()));
+                }
+                app.command_history.push(format!(
+                    "Imported {imported} of {} breakpoint lines from {}",
+                    imported + failed,
+                    path.display()
+                ));
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Session => {
+                let (load, path) = parse_session_arg(args)?;
+                let path =
+                    path.unwrap_or_else(|| crate::session::session_path(&app.cli_args.wave_path));
+                if load {
+                    let report = app.load_session(&path)?;
+                    app.command_history.extend(report);
+                } else {
+                    let report = app.save_session(&path)?;
+                    app.command_history.push(report);
+                }
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Ignore => {
+                let (id, count) = parse_ignore_arg(args)?;
+                let breakpoint = app.ignore_breakpoint(id, count)?;
+                app.command_history.push(if count == 0 {
+                    format!("Breakpoint {id} will stop on its next hit")
+                } else {
+                    format!(
+                        "Breakpoint {id} ({}) will let the next {count} hit{} go by",
+                        app.format_addr(breakpoint.address),
+                        if count == 1 { "" } else { "s" }
+                    )
+                });
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Bhits => {
+                let id = args.trim();
+                if id.is_empty() {
+                    return Err("usage: bhits <id>".to_string());
+                }
+                let id = id
+                    .parse()
+                    .map_err(|_| format!("Invalid breakpoint id: {id}"))?;
+                let lines = app.breakpoint_hit_lines(id)?;
+                app.command_history.extend(lines);
+                Ok(CommandOutcome::Done)
+            }
             UserCommand::Continue => {
                 app.command_history.push("Continuing...".to_string());
                 // Send continue command via shucks client
@@ -174,32 +1049,86 @@ impl UserCommand {
                     return Err(format!("Error continuing execution: {e}"));
                 }
 
-                app.command_history.push("Hit breakpoint...".to_string());
+                // the stats and any assertion come back together in one round trip
+                let report = app.stop_report();
+                if app.stopped_on_livelock() {
+                    app.command_history.push(
+                        "Stopped: PC appears to be looping (livelock), continue again to keep going"
+                            .to_string(),
+                    );
+                } else if app.stopped_on_assertion() {
+                    let violation = report.as_ref().ok().and_then(|r| r.assertion.as_ref());
+                    let line = match violation {
+                        Some(violation) => format!(
+                            "Stopped: assertion {} failed at time index {}: {} (was {})",
+                            violation.id,
+                            violation.time_idx,
+                            violation.check,
+                            app.addr_fmt().hex(violation.observed.into())
+                        ),
+                        None => "Stopped: assertion failed".to_string(),
+                    };
+                    app.command_history.push(line);
+                } else {
+                    app.command_history.push("Hit breakpoint...".to_string());
+                    app.record_breakpoint_hit();
+                }
+                match report {
+                    Ok(report) => app.command_history.push(view::resume_banner(&report.stats)),
+                    Err(e) => log::warn!("Failed to fetch resume stats: {e}"),
+                }
                 app.refresh_all_views();
-                Ok(())
+                Ok(CommandOutcome::Done)
             }
             UserCommand::Toggle => {
-                app.show_split_view = !app.show_split_view;
-                if app.show_split_view {
+                let show = !app.controller.view_state.show_split_view;
+                app.set_split_view(show);
+                if show {
                     app.command_history
                         .push("Split view enabled (instructions | source code)".to_string());
                 } else {
                     app.command_history.push("Split view disabled".to_string());
                 }
-                Ok(())
+                Ok(CommandOutcome::Done)
             }
             UserCommand::Addsig => {
-                app.addsig_state.activate();
-                Ok(())
+                let query = args.trim();
+                if !query.is_empty() {
+                    let name = app.add_signal_matching(query)?;
+                    app.command_history.push(format!("Added signal {name}"));
+                } else if app.line_mode() {
+                    return Err("usage: addsig <query> (the picker needs the full UI)".to_string());
+                } else {
+                    app.addsig_state.activate();
+                }
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Delsig => {
+                match args.trim() {
+                    "" => return Err("usage: delsig <signal> | delsig --all".to_string()),
+                    "--all" => {
+                        let count = app.clear_signals()?;
+                        app.command_history.push(format!(
+                            "Removed {count} signal{}",
+                            if count == 1 { "" } else { "s" }
+                        ));
+                    }
+                    query => {
+                        let name = app.remove_signal(query)?;
+                        app.command_history.push(format!("Removed signal {name}"));
+                    }
+                }
+                Ok(CommandOutcome::Done)
             }
             UserCommand::Debug => {
-                app.show_debug_panel = !app.show_debug_panel;
-                if app.show_debug_panel {
+                let show = !app.controller.view_state.show_debug_panel;
+                app.set_debug_panel(show);
+                if show {
                     app.command_history.push("Debug panel enabled".to_string());
                 } else {
                     app.command_history.push("Debug panel disabled".to_string());
                 }
-                Ok(())
+                Ok(CommandOutcome::Done)
             }
             UserCommand::Surfer => {
                 let wave_path = app.cli_args.wave_path.clone();
@@ -207,7 +1136,7 @@ impl UserCommand {
                     .map_err(|e| format!("Failed to launch Surfer: {e}"))?;
                 app.command_history
                     .push("Surfer launched successfully".to_string());
-                Ok(())
+                Ok(CommandOutcome::Done)
             }
             UserCommand::SurferConnect => {
                 //FIXME: bad constant evil evil evil
@@ -220,103 +1149,832 @@ impl UserCommand {
                     .map_err(|e| format!("Failed to connect to Surfer: {e}"))?;
                 app.command_history
                     .push(format!("Connected to Surfer at {addr}"));
-                Ok(())
+                Ok(CommandOutcome::Done)
             }
-        }
-    }
-
-    /// Get the primary name of the command
-    pub fn name(&self) -> &'static str {
-        match self {
-            UserCommand::Quit => "quit",
-            UserCommand::Next => "next",
-            UserCommand::Step => "step",
-            UserCommand::Help => "help",
-            UserCommand::Clear => "clear",
-            UserCommand::Breakpoint => "breakpoint",
-            UserCommand::Continue => "continue",
-            UserCommand::Toggle => "toggle",
-            UserCommand::Addsig => "addsig",
-            UserCommand::Debug => "debug",
-            UserCommand::Surfer => "surfer",
-            UserCommand::SurferConnect => "surferconnect",
-        }
-    }
-
-    /// Get all aliases for this command (including the primary name)
-    pub fn aliases(&self) -> &'static [&'static str] {
-        match self {
-            UserCommand::Quit => &["quit", "q"],
-            UserCommand::Next => &["next", "n", " "],
-            UserCommand::Step => &["step", "s"],
-            UserCommand::Help => &["help", "h"],
-            UserCommand::Clear => &["clear", "cl"],
-            UserCommand::Breakpoint => &["breakpoint", "b"],
-            UserCommand::Continue => &["continue", "c"],
-            UserCommand::Toggle => &["toggle", "t"],
-            UserCommand::Addsig => &["addsig", "as"],
-            UserCommand::Debug => &["debug", "d"],
-            UserCommand::Surfer => &["surfer", "sf"],
-            UserCommand::SurferConnect => &["surferconnect", "sfc"],
-        }
-    }
-
-    /// Get a brief description for help listings
-    pub fn description(&self) -> &'static str {
-        match self {
-            UserCommand::Quit => "Exit the debugger",
-            UserCommand::Next => "Execute the next instruction",
-            UserCommand::Step => "Step one instruction (same as next)",
-            UserCommand::Help => "Show help information",
-            UserCommand::Clear => "Clear the screen",
-            UserCommand::Breakpoint => "Set a breakpoint at the specified address or file:line",
-            UserCommand::Continue => "Continue execution until breakpoint",
-            UserCommand::Toggle => "Toggle split view (instructions | source code)",
-            UserCommand::Addsig => "Open floating window to add waveform signals via fuzzy search",
-            UserCommand::Debug => "Toggle debug panel",
-            UserCommand::Surfer => "Launch Surfer waveform viewer and connect to it",
-            UserCommand::SurferConnect => "Connect to a running Surfer instance",
-        }
-    }
-
-    /// Get detailed usage information
-    pub fn usage(&self) -> &'static str {
-        match self {
-            UserCommand::Quit => "quit",
-            UserCommand::Next => "next",
-            UserCommand::Step => "step",
-            UserCommand::Help => "help [command]",
-            UserCommand::Clear => "clear",
-            UserCommand::Breakpoint => "breakpoint <address|file:line>",
-            UserCommand::Continue => "continue",
-            UserCommand::Toggle => "toggle",
-            UserCommand::Addsig => "addsig",
-            UserCommand::Debug => "debug",
-            UserCommand::Surfer => "surfer",
-            UserCommand::SurferConnect => "surferconnect [address:port]",
-        }
-    }
-
-    /// Get usage examples
-    pub fn examples(&self) -> &'static [&'static str] {
-        match self {
-            UserCommand::Quit => &["quit", "q"],
-            UserCommand::Next => &["next", "n", " "],
-            UserCommand::Step => &["step", "s"],
-            UserCommand::Help => &["help", "help next", "h quit"],
-            UserCommand::Clear => &["clear", "cl"],
-            UserCommand::Breakpoint => &[
-                "breakpoint 0x1000",
-                "b 1000",
-                "b main.c:42",
-                "b src/lib.rs:123",
-            ],
+            UserCommand::Set => match parse_set_arg(args)? {
+                Setting::ShowBytes(show_bytes) => {
+                    app.set_show_bytes(show_bytes);
+                    app.command_history.push(format!(
+                        "showbytes is {}",
+                        if show_bytes { "on" } else { "off" }
+                    ));
+                    Ok(CommandOutcome::Done)
+                }
+                Setting::StopOnLivelock(stop) => {
+                    app.set_stop_on_livelock(stop)?;
+                    app.command_history.push(if stop {
+                        "Continue will stop when the PC is stuck in a loop".to_string()
+                    } else {
+                        "Continue will only warn when the PC is stuck in a loop".to_string()
+                    });
+                    Ok(CommandOutcome::Done)
+                }
+                Setting::Heat(show_heat) => {
+                    app.set_show_heat(show_heat);
+                    app.command_history
+                        .push(format!("heat is {}", if show_heat { "on" } else { "off" }));
+                    Ok(CommandOutcome::Done)
+                }
+                Setting::BpCheck(bpcheck) => {
+                    app.set_bpcheck(bpcheck);
+                    app.command_history
+                        .push(format!("bpcheck is {}", if bpcheck { "on" } else { "off" }));
+                    Ok(CommandOutcome::Done)
+                }
+                Setting::FindBudget(budget) => {
+                    app.set_find_budget(budget);
+                    app.command_history
+                        .push(format!("findinst gives up after {budget} instructions"));
+                    Ok(CommandOutcome::Done)
+                }
+                Setting::ElfWatch(elfwatch) => {
+                    app.set_elfwatch(elfwatch);
+                    app.command_history.push(format!(
+                        "elfwatch is {}",
+                        if elfwatch { "on" } else { "off" }
+                    ));
+                    Ok(CommandOutcome::Done)
+                }
+                Setting::LogBuffer(capacity) => {
+                    app.set_log_capacity(capacity);
+                    app.command_history
+                        .push(format!("debug panel keeps the last {capacity} messages"));
+                    Ok(CommandOutcome::Done)
+                }
+                Setting::FastForward(on) => {
+                    app.set_fastforward_ranges(on)?;
+                    app.command_history.push(if on {
+                        "Continue will jump across skip ranges".to_string()
+                    } else {
+                        "Continue will step through skip ranges".to_string()
+                    });
+                    Ok(CommandOutcome::Done)
+                }
+                Setting::Notify(severity) => {
+                    app.set_banner_min(severity);
+                    app.command_history
+                        .push(format!("banner shows notifications from {severity} up"));
+                    Ok(CommandOutcome::Done)
+                }
+                Setting::NotifyTime(secs) => {
+                    app.set_banner_timeout(std::time::Duration::from_secs(secs));
+                    app.command_history
+                        .push(format!("banner stays up for {secs}s"));
+                    Ok(CommandOutcome::Done)
+                }
+                Setting::AddrFmt(options) => {
+                    let addr_fmt = app.set_addr_fmt(&options);
+                    app.command_history.push(format!("addrfmt is {addr_fmt}"));
+                    Ok(CommandOutcome::Done)
+                }
+                Setting::DualContext(on) => {
+                    app.set_dual_context(on);
+                    app.command_history
+                        .push(format!("dualcontext is {}", if on { "on" } else { "off" }));
+                    Ok(CommandOutcome::Done)
+                }
+                Setting::Prefetch(span) => {
+                    app.controller.model.set_prefetch(span)?;
+                    app.command_history.push(match span {
+                        Some(span) => format!(
+                            "prefetch works out {span} signal changes either side of the cursor"
+                        ),
+                        None => "prefetch is off".to_string(),
+                    });
+                    Ok(CommandOutcome::Done)
+                }
+                Setting::InstWindow(window) => {
+                    app.controller.view_state.inst_window = window;
+                    app.controller.fit_instruction_window();
+                    app.command_history.push(match window {
+                        InstWindow::Auto => "instwindow fills the Instructions pane".to_string(),
+                        InstWindow::Fixed(count) => format!("instwindow is {count} instructions"),
+                    });
+                    Ok(CommandOutcome::Done)
+                }
+            },
+            UserCommand::Memset => {
+                let (addr, data) = parse_memset_arg(args)?;
+                app.write_memory(addr, &data)?;
+                let line = format!("Wrote {} bytes at {}", data.len(), app.format_addr(addr));
+                app.command_history.push(line);
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Delta => {
+                if !args.trim().is_empty() {
+                    return Err("usage: delta".to_string());
+                }
+                let lines = app.delta()?;
+                app.command_history.extend(lines);
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Dump => {
+                let path = parse_dump_arg(args)?;
+                let report = app.state_report();
+                let Some(path) = path else {
+                    app.show_modal(report.lines().map(str::to_string).collect());
+                    return Ok(CommandOutcome::Done);
+                };
+                let prompt = format!("overwrite {}? [y/N]", path.display());
+                let overwrite = path.exists();
+                let write = move |app: &mut crate::App| -> Result<(), String> {
+                    crate::dump::write_atomically(&path, &report)
+                        .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+                    app.command_history
+                        .push(format!("Wrote state dump to {}", path.display()));
+                    Ok(())
+                };
+                if overwrite {
+                    return Ok(CommandOutcome::confirm(prompt, write));
+                }
+                write(app)?;
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Export => {
+                let (path, redactions) = parse_export_arg(args)?;
+                let transcript = app.markdown_transcript(&redactions);
+                let prompt = format!("overwrite {}? [y/N]", path.display());
+                let overwrite = path.exists();
+                let write = move |app: &mut crate::App| -> Result<(), String> {
+                    crate::dump::write_atomically(&path, &transcript)
+                        .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+                    app.command_history
+                        .push(format!("Wrote session transcript to {}", path.display()));
+                    Ok(())
+                };
+                if overwrite {
+                    return Ok(CommandOutcome::confirm(prompt, write));
+                }
+                write(app)?;
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Regs => {
+                let verbose = match args.trim() {
+                    "" => false,
+                    "-v" => true,
+                    other => return Err(format!("Unknown regs option: {other}")),
+                };
+                let lines = app.register_lines(verbose)?;
+                app.command_history.extend(lines);
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Assert => match parse_assert_arg(args, &app.addr_fmt())? {
+                AssertArg::List => {
+                    let lines = app.breakpoint_lines();
+                    if lines.is_empty() {
+                        app.command_history
+                            .push("No breakpoints or assertions".to_string());
+                    }
+                    app.command_history.extend(lines);
+                    Ok(CommandOutcome::Done)
+                }
+                AssertArg::Reg { reg, op, value } => {
+                    let assertion = app.assert_reg(reg, &op, value)?;
+                    app.command_history
+                        .push(format!("Assertion {}: {}", assertion.id, assertion.check));
+                    Ok(CommandOutcome::Done)
+                }
+                AssertArg::PcRange { lo, hi, inside } => {
+                    let assertion = app.assert_pc_range(lo, hi, inside)?;
+                    app.command_history
+                        .push(format!("Assertion {}: {}", assertion.id, assertion.check));
+                    Ok(CommandOutcome::Done)
+                }
+                AssertArg::Delete(id) => {
+                    app.delete_assertion(id)?;
+                    app.command_history.push(format!("Deleted assertion {id}"));
+                    Ok(CommandOutcome::Done)
+                }
+            },
+            UserCommand::Skip => match parse_skip_arg(args, &app.addr_fmt())? {
+                SkipArg::List => {
+                    let ranges = app.skip_ranges()?;
+                    if ranges.is_empty() {
+                        app.command_history.push("No skip ranges".to_string());
+                    }
+                    let lines: Vec<String> = ranges
+                        .iter()
+                        .map(|r| {
+                            format!(
+                                "skip {}: {}-{}",
+                                r.id,
+                                app.format_addr(r.lo),
+                                app.format_addr(r.hi)
+                            )
+                        })
+                        .collect();
+                    app.command_history.extend(lines);
+                    Ok(CommandOutcome::Done)
+                }
+                SkipArg::Add(target) => {
+                    let range = app.add_skip_range(&target)?;
+                    app.command_history.push(format!(
+                        "Skip range {}: {}-{}",
+                        range.id,
+                        app.format_addr(range.lo),
+                        app.format_addr(range.hi)
+                    ));
+                    Ok(CommandOutcome::Done)
+                }
+                SkipArg::Delete(id) => {
+                    app.delete_skip_range(id)?;
+                    app.command_history.push(format!("Deleted skip range {id}"));
+                    Ok(CommandOutcome::Done)
+                }
+            },
+            UserCommand::Debugfile => {
+                let path = args.trim();
+                if path.is_empty() {
+                    return Err("debugfile requires a path".to_string());
+                }
+                app.load_debug_file(Path::new(path))?;
+                app.command_history
+                    .push(format!("Using debug info from {path}"));
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Status => {
+                let lines = app.status_lines();
+                app.command_history.extend(lines);
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Notifications => {
+                let lines = app.notification_lines();
+                app.show_modal(lines);
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Monitor => {
+                let cmd = args.trim();
+                if cmd.is_empty() {
+                    return Err("monitor requires a command".to_string());
+                }
+                let output = app.monitor(cmd)?;
+                app.show_output(&output);
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Findinst => {
+                let (predicate, direction) = parse_findinst_arg(args)?;
+                let outcome = app.find_instruction(direction, &predicate)?;
+                let line = find_outcome_line(&predicate, direction, &outcome, |addr| {
+                    app.format_addr(addr)
+                });
+                app.command_history.push(line);
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Reloadelf => {
+                let lines = app.reload_elf()?;
+                app.command_history.extend(lines);
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Reloadwave => {
+                let lines = app.reload_wave()?;
+                app.command_history.extend(lines);
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Show => {
+                let lines = app.pane_lines(parse_show_arg(args)?)?;
+                app.command_history.extend(lines);
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Sigtime => {
+                let line = match app.set_signal_pin(parse_sigtime_arg(args)?)? {
+                    Some(idx) => format!("Signals pane pinned to time index {idx}"),
+                    None => "Signals pane follows execution".to_string(),
+                };
+                app.command_history.push(line);
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Sigstats => {
+                let whole_trace = match args.trim() {
+                    "" => false,
+                    "all" => true,
+                    other => return Err(format!("Unknown sigstats option: {other}")),
+                };
+                let lines = app.signal_stats_lines(whole_trace)?;
+                app.show_modal(lines);
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Sigchanged => {
+                if !args.trim().is_empty() {
+                    return Err("sigchanged takes no arguments".to_string());
+                }
+                let lines = app.signal_changes_lines()?;
+                app.show_modal(lines);
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Sigenum => {
+                match parse_sigenum_arg(args)? {
+                    SigenumArg::Load {
+                        signal,
+                        path,
+                        format,
+                    } => {
+                        let names = value_names::load(&path)?;
+                        let count = names.len();
+                        let (name, warning) = app
+                            .controller
+                            .model
+                            .set_value_names(&signal, names, format)?;
+                        app.command_history.push(format!(
+                            "{name}: {count} value name{} from {}",
+                            if count == 1 { "" } else { "s" },
+                            path.display()
+                        ));
+                        app.command_history.extend(warning);
+                    }
+                    SigenumArg::Clear { signal } => {
+                        let name = app.controller.model.clear_value_names(&signal)?;
+                        app.command_history
+                            .push(format!("{name}: value names cleared"));
+                    }
+                }
+                app.controller.refresh_signals();
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Format => {
+                let (signal, format) = parse_format_arg(args)?;
+                let name = app.controller.set_signal_format(&signal, format)?;
+                app.command_history
+                    .push(format!("{name}: shown in {format}"));
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Reghist => {
+                let (reg, path) = parse_reghist_arg(args)?;
+                let history = app.reg_history(reg)?;
+                let Some(path) = path else {
+                    app.command_history
+                        .extend(view::reghist_lines(reg, &history, &app.addr_fmt()));
+                    return Ok(CommandOutcome::Done);
+                };
+                let csv = view::reghist_csv(&history);
+                let count = history.len();
+                let prompt = format!("overwrite {}? [y/N]", path.display());
+                let overwrite = path.exists();
+                let write = move |app: &mut crate::App| -> Result<(), String> {
+                    crate::dump::write_atomically(&path, &csv)
+                        .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+                    app.command_history.push(format!(
+                        "Wrote {count} value{} of x{reg} to {}",
+                        if count == 1 { "" } else { "s" },
+                        path.display()
+                    ));
+                    Ok(())
+                };
+                if overwrite {
+                    return Ok(CommandOutcome::confirm(prompt, write));
+                }
+                write(app)?;
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Timeline => {
+                let line = match parse_timeline_arg(args)? {
+                    TimelineArg::Show(show) => {
+                        app.set_timeline(show);
+                        format!("Timeline {}", if show { "on" } else { "off" })
+                    }
+                    TimelineArg::Goto(fraction) => {
+                        let idx = app.controller.view_state.timeline.index_at(fraction);
+                        app.goto_time_idx(idx)?;
+                        format!("Moved to time index {idx}")
+                    }
+                };
+                app.command_history.push(line);
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Disas => {
+                let (target, count) = parse_disas_arg(args)?;
+                let lines = app.disassemble(&target, count)?;
+                app.show_modal(lines);
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Alias => {
+                let args = args.trim();
+                if args.is_empty() {
+                    if app.aliases.is_empty() {
+                        app.command_history.push("No aliases defined".to_string());
+                    }
+                    let lines: Vec<String> = app
+                        .aliases
+                        .sorted()
+                        .into_iter()
+                        .map(|(name, expansion)| format!("alias {name} {expansion}"))
+                        .collect();
+                    app.command_history.extend(lines);
+                    return Ok(CommandOutcome::Done);
+                }
+                let (name, expansion) = args.split_once(' ').unwrap_or((args, ""));
+                if expansion.trim().is_empty() {
+                    let expansion = app
+                        .aliases
+                        .get(name)
+                        .ok_or_else(|| format!("No alias named '{name}'"))?;
+                    app.command_history
+                        .push(format!("alias {name} {expansion}"));
+                    return Ok(CommandOutcome::Done);
+                }
+                app.aliases
+                    .define(&CommandRegistry::new(), name, expansion)?;
+                app.command_history
+                    .push(format!("Alias '{name}' expands to '{}'", expansion.trim()));
+                Ok(CommandOutcome::Done)
+            }
+            UserCommand::Unalias => {
+                let name = args.trim();
+                if name.is_empty() {
+                    return Err("unalias requires an alias name".to_string());
+                }
+                let expansion = app.aliases.remove(name)?;
+                app.command_history
+                    .push(format!("Removed alias '{name}' ({expansion})"));
+                Ok(CommandOutcome::Done)
+            }
+            #[cfg(debug_assertions)]
+            UserCommand::Panic => panic!("panic command: {}", args.trim()),
+        }
+    }
+
+    /// Get the primary name of the command
+    pub fn name(&self) -> &'static str {
+        match self {
+            UserCommand::Quit => "quit",
+            UserCommand::Next => "next",
+            UserCommand::Step => "step",
+            UserCommand::Help => "help",
+            UserCommand::Clear => "clear",
+            UserCommand::Breakpoint => "breakpoint",
+            UserCommand::Bdel => "bdel",
+            UserCommand::Bdisable => "bdisable",
+            UserCommand::Benable => "benable",
+            UserCommand::Bclear => "bclear",
+            UserCommand::Bexport => "bexport",
+            UserCommand::Bimport => "bimport",
+            UserCommand::Session => "session",
+            UserCommand::Ignore => "ignore",
+            UserCommand::Bhits => "bhits",
+            UserCommand::Continue => "continue",
+            UserCommand::Toggle => "toggle",
+            UserCommand::Addsig => "addsig",
+            UserCommand::Delsig => "delsig",
+            UserCommand::Debug => "debug",
+            UserCommand::Surfer => "surfer",
+            UserCommand::SurferConnect => "surferconnect",
+            UserCommand::Set => "set",
+            UserCommand::Memset => "memset",
+            UserCommand::Delta => "delta",
+            UserCommand::Dump => "dump",
+            UserCommand::Export => "export",
+            UserCommand::Regs => "regs",
+            UserCommand::Assert => "assert",
+            UserCommand::Skip => "skip",
+            UserCommand::Debugfile => "debugfile",
+            UserCommand::Status => "status",
+            UserCommand::Notifications => "notifications",
+            UserCommand::Monitor => "monitor",
+            UserCommand::Findinst => "findinst",
+            UserCommand::Reloadelf => "reloadelf",
+            UserCommand::Reloadwave => "reloadwave",
+            UserCommand::Show => "show",
+            UserCommand::Sigtime => "sigtime",
+            UserCommand::Sigstats => "sigstats",
+            UserCommand::Sigchanged => "sigchanged",
+            UserCommand::Sigenum => "sigenum",
+            UserCommand::Format => "format",
+            UserCommand::Reghist => "reghist",
+            UserCommand::Timeline => "timeline",
+            UserCommand::Disas => "disas",
+            UserCommand::Alias => "alias",
+            UserCommand::Unalias => "unalias",
+            #[cfg(debug_assertions)]
+            UserCommand::Panic => "panic",
+        }
+    }
+
+    /// Get all aliases for this command (including the primary name)
+    pub fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            UserCommand::Quit => &["quit", "q"],
+            UserCommand::Next => &["next", "n", " "],
+            UserCommand::Step => &["step", "s"],
+            UserCommand::Help => &["help", "h"],
+            UserCommand::Clear => &["clear", "cl"],
+            UserCommand::Breakpoint => &["breakpoint", "b"],
+            UserCommand::Bdel => &["bdel"],
+            UserCommand::Bdisable => &["bdisable"],
+            UserCommand::Benable => &["benable"],
+            UserCommand::Bclear => &["bclear"],
+            UserCommand::Bexport => &["bexport"],
+            UserCommand::Bimport => &["bimport"],
+            UserCommand::Session => &["session"],
+            UserCommand::Ignore => &["ignore"],
+            UserCommand::Bhits => &["bhits"],
             UserCommand::Continue => &["continue", "c"],
             UserCommand::Toggle => &["toggle", "t"],
             UserCommand::Addsig => &["addsig", "as"],
+            UserCommand::Delsig => &["delsig", "ds"],
+            UserCommand::Debug => &["debug", "d"],
+            UserCommand::Surfer => &["surfer", "sf"],
+            UserCommand::SurferConnect => &["surferconnect", "sfc"],
+            UserCommand::Set => &["set"],
+            UserCommand::Memset => &["memset"],
+            UserCommand::Delta => &["delta"],
+            UserCommand::Dump => &["dump"],
+            UserCommand::Export => &["export"],
+            UserCommand::Regs => &["regs"],
+            UserCommand::Assert => &["assert"],
+            UserCommand::Skip => &["skip"],
+            UserCommand::Debugfile => &["debugfile"],
+            UserCommand::Status => &["status"],
+            UserCommand::Notifications => &["notifications"],
+            UserCommand::Monitor => &["monitor", "mon"],
+            UserCommand::Findinst => &["findinst", "fi"],
+            UserCommand::Reloadelf => &["reloadelf"],
+            UserCommand::Reloadwave => &["reloadwave"],
+            UserCommand::Show => &["show"],
+            UserCommand::Sigtime => &["sigtime"],
+            UserCommand::Sigstats => &["sigstats"],
+            UserCommand::Sigchanged => &["sigchanged"],
+            UserCommand::Sigenum => &["sigenum"],
+            UserCommand::Format => &["format", "fmt"],
+            UserCommand::Reghist => &["reghist"],
+            UserCommand::Timeline => &["timeline", "tl"],
+            UserCommand::Disas => &["disas", "disassemble"],
+            UserCommand::Alias => &["alias"],
+            UserCommand::Unalias => &["unalias"],
+            #[cfg(debug_assertions)]
+            UserCommand::Panic => &["panic"],
+        }
+    }
+
+    /// Get a brief description for help listings
+    pub fn description(&self) -> &'static str {
+        match self {
+            UserCommand::Quit => "Exit the debugger",
+            UserCommand::Next => "Execute the next instruction",
+            UserCommand::Step => "Step one instruction (same as next)",
+            UserCommand::Help => "Show help information",
+            UserCommand::Clear => "Clear the screen",
+            UserCommand::Breakpoint => {
+                "Set a breakpoint at the specified address, file:line or file:line:column"
+            }
+            UserCommand::Bdel => "Delete breakpoints by id, id range, address range or all",
+            UserCommand::Bdisable => "Disable breakpoints, keeping them in the table",
+            UserCommand::Benable => "Re-enable disabled breakpoints",
+            UserCommand::Bclear => "Delete every breakpoint set from a line in a file",
+            UserCommand::Bexport => "Write the breakpoints to a file as gdb break commands",
+            UserCommand::Bimport => {
+                "Set the breakpoints in a file of gdb break commands, reporting each line"
+            }
+            UserCommand::Session => {
+                "Save or load selected signals, breakpoints and command history, next to the wave \
+                 file unless a path is given"
+            }
+            UserCommand::Ignore => "Let a breakpoint's next hits go by without stopping",
+            UserCommand::Bhits => "List the time of each recorded stop at a breakpoint",
+            UserCommand::Continue => "Continue execution until breakpoint",
+            UserCommand::Toggle => "Toggle split view (instructions | source code)",
+            UserCommand::Addsig => {
+                "Add the best fuzzy match for a query to the signal pane, or pick one in a floating window"
+            }
+            UserCommand::Delsig => "Remove a signal from the signal pane, or all of them",
+            UserCommand::Debug => "Toggle debug panel",
+            UserCommand::Surfer => "Launch Surfer waveform viewer and connect to it",
+            UserCommand::SurferConnect => "Connect to a running Surfer instance",
+            UserCommand::Set => "Change a debugger setting",
+            UserCommand::Memset => "Write bytes into the target's scratch memory region",
+            UserCommand::Delta => {
+                "Show what changed in registers, scratch memory and signals since the last delta"
+            }
+            UserCommand::Dump => "Dump the whole debugger state as text, for bug reports",
+            UserCommand::Export => {
+                "Write the session up as markdown, commands and output, where it stopped and the breakpoints, to share findings"
+            }
+            UserCommand::Regs => {
+                "Show the general purpose registers, -v adds when each last changed"
+            }
+            UserCommand::Assert => {
+                "Stop when a register comparison or PC range check fails, or list breakpoints and assertions"
+            }
+            UserCommand::Skip => {
+                "Mark code continue can jump across when fastforward is set, like delay loops"
+            }
+            UserCommand::Debugfile => {
+                "Load line info from a separate debug file, for stripped ELFs"
+            }
+            UserCommand::Status => {
+                "Show the process state, settings and where debug info comes from"
+            }
+            UserCommand::Notifications => "List warnings and errors that weren't command output",
+            UserCommand::Monitor => {
+                "Send a command to dang as is, long output opens in a scrollable window"
+            }
+            UserCommand::Findinst => {
+                "Go to the next (or previous) executed instruction matching a mnemonic, destination register or encoding mask"
+            }
+            UserCommand::Reloadelf => {
+                "Reload symbols and line info after the ELF was rebuilt, moving file:line breakpoints"
+            }
+            UserCommand::Reloadwave => {
+                "Read the waveform again after the simulation rewrote it, keeping selected signals by name"
+            }
+            UserCommand::Show => "Print a pane as plain text, for the simple UI",
+            UserCommand::Sigtime => {
+                "Show signals at another time than execution is stopped at, Shift+Left/Right scrubs"
+            }
+            UserCommand::Sigstats => {
+                "Count how often each signal changes, between the sigtime pin and execution too, to find stuck ones"
+            }
+            UserCommand::Sigchanged => {
+                "List which signals changed at all during the last step or continue, even ones back where they started"
+            }
+            UserCommand::Sigenum => {
+                "Show a signal's values by name from a TOML or JSON file of value = name, FSM states say"
+            }
+            UserCommand::Format => {
+                "Show a selected signal in hex, decimal, binary or signed decimal, x and z bits stay as bits"
+            }
+            UserCommand::Reghist => {
+                "Summarize every value a register takes over the trace, or export them all as CSV"
+            }
+            UserCommand::Timeline => {
+                "Show a minimap of the trace along the bottom, or jump to a point along it"
+            }
+            UserCommand::Disas => {
+                "Disassemble a function, address or line wherever execution is, a symbol lists the whole function"
+            }
+            UserCommand::Alias => "Define a command shortcut, or list them",
+            UserCommand::Unalias => "Remove a command shortcut",
+            #[cfg(debug_assertions)]
+            UserCommand::Panic => "Panic on purpose (debug builds only)",
+        }
+    }
+
+    /// Get detailed usage information
+    pub fn usage(&self) -> &'static str {
+        match self {
+            UserCommand::Quit => "quit",
+            UserCommand::Next => "next",
+            UserCommand::Step => "step",
+            UserCommand::Help => "help [command]",
+            UserCommand::Clear => "clear",
+            UserCommand::Breakpoint => "breakpoint <address|file:line[:column]>",
+            UserCommand::Bdel => "bdel <all|id[-id]|0xaddr[-0xaddr]>[,...]",
+            UserCommand::Bdisable => "bdisable <all|id[-id]|0xaddr[-0xaddr]>[,...]",
+            UserCommand::Benable => "benable <all|id[-id]|0xaddr[-0xaddr]>[,...]",
+            UserCommand::Bclear => "bclear <file>",
+            UserCommand::Bexport => "bexport <path>",
+            UserCommand::Bimport => "bimport <path>",
+            UserCommand::Session => "session <save|load> [path]",
+            UserCommand::Ignore => "ignore <id> <count>",
+            UserCommand::Bhits => "bhits <id>",
+            UserCommand::Continue => "continue",
+            UserCommand::Toggle => "toggle",
+            UserCommand::Addsig => "addsig [query]",
+            UserCommand::Delsig => "delsig <signal> | delsig --all",
+            UserCommand::Debug => "debug",
+            UserCommand::Surfer => "surfer",
+            UserCommand::SurferConnect => "surferconnect [address:port]",
+            UserCommand::Set => {
+                "set <showbytes|heat|livelock|bpcheck|elfwatch|fastforward|dualcontext> <on|off> | set findbudget <instructions> | set logbuffer <messages> | set notify <info|warning|error> | set notifytime <seconds> | set prefetch <changes|off> | set instwindow <instructions|auto> | set addrfmt [width 32|64] [pad on|off] [symbols on|off|short] [symlen <n>]"
+            }
+            UserCommand::Memset => "memset <address> <byte> [byte...]",
+            UserCommand::Delta => "delta",
+            UserCommand::Dump => "dump state [path]",
+            UserCommand::Export => "export md <path> [redact <prefix>[=<replacement>]]...",
+            UserCommand::Regs => "regs [-v]",
+            UserCommand::Assert => {
+                "assert [list] | reg <reg> <op> <value> | pc <lo> <hi> <inside|outside> | del <id>"
+            }
+            UserCommand::Skip => "skip [list] | add <lo>-<hi> | add <file>:<first>-<last> | del <id>",
+            UserCommand::Debugfile => "debugfile <path>",
+            UserCommand::Status => "status",
+            UserCommand::Notifications => "notifications",
+            UserCommand::Monitor => "monitor [limit=<bytes>] <command...>",
+            UserCommand::Findinst => "findinst <mnemonic|rd=<reg>|mask:<value>/<mask>> [back]",
+            UserCommand::Reloadelf => "reloadelf",
+            UserCommand::Reloadwave => "reloadwave",
+            UserCommand::Show => "show <exec|source|signals>",
+            UserCommand::Sigtime => "sigtime <index|<time>ps|+N|-N|off>",
+            UserCommand::Sigstats => "sigstats [all]",
+            UserCommand::Sigchanged => "sigchanged",
+            UserCommand::Sigenum => "sigenum <signal> <file> [short] | sigenum clear <signal>",
+            UserCommand::Format => "format <signal> <hex|dec|bin|sdec>",
+            UserCommand::Reghist => "reghist <reg> [csv <path>]",
+            UserCommand::Timeline => "timeline <on|off|goto <percent>%>",
+            UserCommand::Disas => "disas <addr|symbol|file:line> [count]",
+            UserCommand::Alias => "alias [name [command...]]",
+            UserCommand::Unalias => "unalias <name>",
+            #[cfg(debug_assertions)]
+            UserCommand::Panic => "panic [message]",
+        }
+    }
+
+    /// Get usage examples
+    pub fn examples(&self) -> &'static [&'static str] {
+        match self {
+            UserCommand::Quit => &["quit", "q"],
+            UserCommand::Next => &["next", "n", " "],
+            UserCommand::Step => &["step", "s"],
+            UserCommand::Help => &["help", "help next", "h quit"],
+            UserCommand::Clear => &["clear", "cl"],
+            UserCommand::Breakpoint => &[
+                "breakpoint 0x1000",
+                "b 1000",
+                "b main.c:42",
+                "b main.c:42:17",
+                "b src/lib.rs:123",
+            ],
+            UserCommand::Bdel => &[
+                "bdel 3",
+                "bdel 1,3,5-7",
+                "bdel 0x100200-0x100300",
+                "bdel all",
+            ],
+            UserCommand::Bdisable => &["bdisable 2", "bdisable all"],
+            UserCommand::Benable => &["benable 2-4", "benable all"],
+            UserCommand::Bclear => &["bclear main.c", "bclear src/lib.rs"],
+            UserCommand::Bexport => &["bexport repro.gdb"],
+            UserCommand::Bimport => &["bimport repro.gdb"],
+            UserCommand::Session => &["session save", "session load", "session save debug.json"],
+            UserCommand::Ignore => &["ignore 2 100", "ignore 2 0"],
+            UserCommand::Bhits => &["bhits 2"],
+            UserCommand::Continue => &["continue", "c"],
+            UserCommand::Toggle => &["toggle", "t"],
+            UserCommand::Addsig => &["addsig", "as", "addsig pc_id"],
+            UserCommand::Delsig => &["delsig pc_id", "ds u_ibex_core.pc_id", "delsig --all"],
             UserCommand::Debug => &["debug", "d"],
             UserCommand::Surfer => &["surfer", "sf"],
             UserCommand::SurferConnect => &["surferconnect", "sfc", "surferconnect 127.0.0.1:3333"],
+            UserCommand::Set => &[
+                "set showbytes on",
+                "set showbytes off",
+                "set heat on",
+                "set bpcheck off",
+                "set findbudget 500000",
+                "set elfwatch off",
+                "set logbuffer 200000",
+                "set fastforward on",
+                "set notify error",
+                "set notifytime 30",
+                "set addrfmt pad on symbols short",
+                "set addrfmt width 64 symlen 16",
+                "set dualcontext off",
+                "set prefetch 4",
+                "set prefetch off",
+                "set instwindow 32",
+                "set instwindow auto",
+            ],
+            UserCommand::Memset => &["memset 0x200000 13 00 00 00", "memset 200010 ff"],
+            UserCommand::Delta => &["delta"],
+            UserCommand::Dump => &["dump state", "dump state jpdb-state.txt"],
+            UserCommand::Export => &[
+                "export md findings.md",
+                "export md findings.md redact /home/pat=~",
+            ],
+            UserCommand::Regs => &["regs", "regs -v"],
+            UserCommand::Assert => &[
+                "assert reg sp >= 0x20000000",
+                "assert pc 0 ff outside",
+                "assert del 1",
+                "assert",
+            ],
+            UserCommand::Skip => &[
+                "skip add 0x100-0x1ff",
+                "skip add delay.c:12-20",
+                "skip del 1",
+                "skip",
+            ],
+            UserCommand::Debugfile => &["debugfile build/app.debug"],
+            UserCommand::Status => &["status"],
+            UserCommand::Notifications => &["notifications"],
+            UserCommand::Monitor => &[
+                "monitor time_idx",
+                "mon coverage 100000 100fff 0 50",
+                "monitor limit=1048576 coverage 0 ffffffff",
+            ],
+            UserCommand::Findinst => &["findinst ecall", "findinst rd=a0 back", "fi mask:73/7f"],
+            UserCommand::Reloadelf => &["reloadelf"],
+            UserCommand::Reloadwave => &["reloadwave"],
+            UserCommand::Show => &["show exec", "show source", "show signals"],
+            UserCommand::Sigtime => &[
+                "sigtime 120",
+                "sigtime 3900ps",
+                "sigtime -100",
+                "sigtime off",
+            ],
+            UserCommand::Sigstats => &["sigstats", "sigstats all"],
+            UserCommand::Sigchanged => &["sigchanged"],
+            UserCommand::Sigenum => &[
+                "sigenum ctrl_fsm_cs ctrl_fsm.toml",
+                "sigenum id_stage_i.ctrl_fsm_cs states.json short",
+                "sigenum clear ctrl_fsm_cs",
+            ],
+            UserCommand::Format => &["format pc_id dec", "fmt ctrl_fsm_cs bin"],
+            UserCommand::Reghist => &["reghist sp", "reghist a0 csv a0.csv"],
+            UserCommand::Timeline => &["timeline on", "timeline goto 50%", "tl off"],
+            UserCommand::Disas => &["disas puthex", "disas 0x1000a4 20", "disas main.c:42"],
+            UserCommand::Alias => &["alias", "alias ss step 5", "alias bm breakpoint main.c:"],
+            UserCommand::Unalias => &["unalias ss"],
+            #[cfg(debug_assertions)]
+            UserCommand::Panic => &["panic"],
         }
     }
 
@@ -329,16 +1987,257 @@ impl UserCommand {
             UserCommand::Help,
             UserCommand::Clear,
             UserCommand::Breakpoint,
+            UserCommand::Bdel,
+            UserCommand::Bdisable,
+            UserCommand::Benable,
+            UserCommand::Bclear,
+            UserCommand::Bexport,
+            UserCommand::Bimport,
+            UserCommand::Session,
+            UserCommand::Ignore,
+            UserCommand::Bhits,
             UserCommand::Continue,
             UserCommand::Toggle,
             UserCommand::Addsig,
+            UserCommand::Delsig,
             UserCommand::Debug,
             UserCommand::Surfer,
             UserCommand::SurferConnect,
+            UserCommand::Set,
+            UserCommand::Memset,
+            UserCommand::Delta,
+            UserCommand::Dump,
+            UserCommand::Export,
+            UserCommand::Regs,
+            UserCommand::Assert,
+            UserCommand::Skip,
+            UserCommand::Debugfile,
+            UserCommand::Status,
+            UserCommand::Notifications,
+            UserCommand::Monitor,
+            UserCommand::Findinst,
+            UserCommand::Reloadelf,
+            UserCommand::Reloadwave,
+            UserCommand::Show,
+            UserCommand::Sigtime,
+            UserCommand::Sigstats,
+            UserCommand::Sigchanged,
+            UserCommand::Sigenum,
+            UserCommand::Format,
+            UserCommand::Reghist,
+            UserCommand::Timeline,
+            UserCommand::Disas,
+            UserCommand::Alias,
+            UserCommand::Unalias,
+            #[cfg(debug_assertions)]
+            UserCommand::Panic,
         ]
     }
 }
 
+/// What the word being completed is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionContext {
+    /// The first word, a command name or alias
+    Command,
+    /// `breakpoint`'s argument: a source file from the line info, or a symbol
+    Location,
+    /// `addsig`'s argument, a waveform variable
+    Signal,
+    /// `help`'s argument, a command name
+    HelpTopic,
+    /// Nothing to offer
+    Nothing,
+}
+
+/// The context for completing `buffer` at `cursor`, and the byte offset the word under the
+/// cursor starts at. Only a command's first argument is completed
+pub fn completion_context(
+    buffer: &str,
+    cursor: usize,
+    aliases: &AliasTable,
+) -> (CompletionContext, usize) {
+    let before = &buffer[..cursor.min(buffer.len())];
+    let word_start = before.rfind(' ').map_or(0, |space| space + 1);
+    let mut earlier_words = before[..word_start].split_whitespace();
+    let Some(command) = earlier_words.next() else {
+        return (CompletionContext::Command, word_start);
+    };
+    if earlier_words.next().is_some() {
+        return (CompletionContext::Nothing, word_start);
+    }
+
+    // A user alias completes like whatever it expands to
+    let command = aliases
+        .get(command)
+        .and_then(|expansion| expansion.split_whitespace().next())
+        .unwrap_or(command);
+    let context = match CommandRegistry::new().get_command(command) {
+        Some(UserCommand::Breakpoint) => CompletionContext::Location,
+        Some(UserCommand::Addsig) => CompletionContext::Signal,
+        Some(UserCommand::Help) => CompletionContext::HelpTopic,
+        _ => CompletionContext::Nothing,
+    };
+    (context, word_start)
+}
+
+/// The longest prefix all of `words` share, on a char boundary
+pub fn common_prefix<'a>(words: impl IntoIterator<Item = &'a str>) -> &'a str {
+    let mut words = words.into_iter();
+    let Some(mut prefix) = words.next() else {
+        return "";
+    };
+    for word in words {
+        let shared = prefix
+            .char_indices()
+            .zip(word.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(prefix.len().min(word.len()), |((i, _), _)| i);
+        prefix = &prefix[..shared];
+    }
+    prefix
+}
+
+/// One way to finish the word under the cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    /// What the picker lists
+    pub label: String,
+    /// What goes into the input line in place of the word
+    pub replacement: String,
+}
+
+impl Candidate {
+    fn plain(text: &str) -> Self {
+        Self {
+            label: text.to_string(),
+            replacement: text.to_string(),
+        }
+    }
+}
+
+/// Candidates for the word starting at `start`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub start: usize,
+    pub candidates: Vec<Candidate>,
+}
+
+/// How many signals the fuzzy matcher offers at once
+const SIGNAL_CANDIDATES: usize = 10;
+
+/// Completes the input line. File names and symbols are read once and kept, so Tab doesn't
+/// walk the DWARF every time
+#[derive(Default)]
+pub struct Completer {
+    files: Option<Vec<String>>,
+    symbols: Option<Vec<(String, u32)>>,
+}
+
+impl Completer {
+    /// Forget the cached file names and symbols, e.g. after the ELF was reloaded
+    pub fn invalidate(&mut self) {
+        self.files = None;
+        self.symbols = None;
+    }
+
+    pub fn complete(
+        &mut self,
+        buffer: &str,
+        cursor: usize,
+        model: &mut DebuggerModel,
+        aliases: &AliasTable,
+    ) -> Completion {
+        let (context, start) = completion_context(buffer, cursor, aliases);
+        let word = &buffer[start..cursor.min(buffer.len())];
+        let candidates = match context {
+            CompletionContext::Command => {
+                let mut names: Vec<&str> = UserCommand::all()
+                    .iter()
+                    .flat_map(|command| command.aliases().iter().copied())
+                    .chain(aliases.sorted().into_iter().map(|(name, _)| name))
+                    .filter(|name| !name.trim().is_empty() && name.starts_with(word))
+                    .collect();
+                names.sort_unstable();
+                names.dedup();
+                names.into_iter().map(Candidate::plain).collect()
+            }
+            CompletionContext::HelpTopic => UserCommand::all()
+                .iter()
+                .map(|command| command.name())
+                .filter(|name| name.starts_with(word))
+                .map(Candidate::plain)
+                .collect(),
+            CompletionContext::Location => {
+                let files = self
+                    .files(model)
+                    .iter()
+                    .filter(|file| file.starts_with(word));
+                // `file:` so the line number can be typed straight after
+                let mut candidates: Vec<Candidate> = files
+                    .map(|file| Candidate {
+                        label: file.clone(),
+                        replacement: format!("{file}:"),
+                    })
+                    .collect();
+                // Breakpoints go on addresses, a symbol completes to where it starts
+                let addr_fmt = model.addr_fmt;
+                candidates.extend(
+                    self.symbols(model)
+                        .iter()
+                        .filter(|(name, _)| name.starts_with(word))
+                        .map(|(name, &addr)| Candidate {
+                            label: format!("{name} ({})", addr_fmt.hex(addr.into())),
+                            replacement: addr_fmt.hex(addr.into()),
+                        }),
+                );
+                candidates
+            }
+            CompletionContext::Signal if !word.is_empty() => model
+                .fuzzy_match_signals(word)
+                .into_iter()
+                .take(SIGNAL_CANDIDATES)
+                .map(|(_, name)| Candidate::plain(&name))
+                .collect(),
+            CompletionContext::Signal | CompletionContext::Nothing => Vec::new(),
+        };
+        Completion { start, candidates }
+    }
+
+    /// Source file names, without their directories since that's how `file:line` is usually
+    /// typed
+    fn files(&mut self, model: &DebuggerModel) -> &[String] {
+        self.files.get_or_insert_with(|| {
+            let mut names: Vec<String> = model
+                .client
+                .list_dwarf_files()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|path| path.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .collect();
+            names.sort_unstable();
+            names.dedup();
+            names
+        })
+    }
+
+    fn symbols(&mut self, model: &DebuggerModel) -> &[(String, u32)] {
+        self.symbols.get_or_insert_with(|| {
+            let mut symbols: Vec<(String, u32)> = model
+                .client
+                .symbols()
+                .iter()
+                .filter(|sym| !sym.name.is_empty())
+                .map(|sym| (sym.name.clone(), sym.addr as u32))
+                .collect();
+            symbols.sort_unstable();
+            symbols.dedup_by(|a, b| a.0 == b.0);
+            symbols
+        })
+    }
+}
+
 /// Registry that holds all available commands and handles lookup
 pub struct CommandRegistry {
     alias_map: HashMap<String, UserCommand>,
@@ -368,7 +2267,7 @@ impl CommandRegistry {
         name: &str,
         args: &str,
         app: &mut crate::App,
-    ) -> Result<(), String> {
+    ) -> Result<CommandOutcome, String> {
         if let Some(command) = self.get_command(name) {
             command.execute(app, args)
         } else {
@@ -380,6 +2279,519 @@ impl CommandRegistry {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::addr_format::SymbolMode;
+
+    #[test]
+    fn test_completion_context() {
+        let mut aliases = AliasTable::default();
+        aliases
+            .define(&CommandRegistry::new(), "bb", "breakpoint")
+            .unwrap();
+        let context = |buffer: &str| completion_context(buffer, buffer.len(), &aliases);
+        assert_eq!(context("b"), (CompletionContext::Command, 0));
+        assert_eq!(context("b "), (CompletionContext::Location, 2));
+        assert_eq!(context("b  main.c"), (CompletionContext::Location, 3));
+        assert_eq!(context("help st"), (CompletionContext::HelpTopic, 5));
+        assert_eq!(context("addsig clk"), (CompletionContext::Signal, 7));
+        assert_eq!(context("bb ma"), (CompletionContext::Location, 3));
+        assert_eq!(context("b x y"), (CompletionContext::Nothing, 4));
+        assert_eq!(context("step "), (CompletionContext::Nothing, 5));
+    }
+
+    #[test]
+    fn test_common_prefix() {
+        assert_eq!(common_prefix(["step", "stepi", "stats"]), "st");
+        assert_eq!(common_prefix(["main.c:"]), "main.c:");
+        assert_eq!(common_prefix(["héllo", "hélp"]), "hél");
+        assert_eq!(common_prefix(["a", "b"]), "");
+        assert_eq!(common_prefix(Vec::<&str>::new()), "");
+    }
+
+    #[test]
+    fn test_complete_symbol_for_breakpoint() {
+        let mut controller = crate::controller::test_utils::fixture_controller();
+        let completion = Completer::default().complete(
+            "b mai",
+            5,
+            &mut controller.model,
+            &AliasTable::default(),
+        );
+        assert_eq!(completion.start, 2);
+        let main = completion
+            .candidates
+            .iter()
+            .find(|c| c.label.starts_with("main "))
+            .expect("main is a symbol in the fixture");
+        assert_eq!(main.replacement, "0x1003d0");
+    }
+
+    #[test]
+    fn test_parse_timeline_arg() {
+        assert_eq!(parse_timeline_arg("on").unwrap(), TimelineArg::Show(true));
+        assert_eq!(
+            parse_timeline_arg(" off ").unwrap(),
+            TimelineArg::Show(false)
+        );
+        assert_eq!(
+            parse_timeline_arg("goto 50%").unwrap(),
+            TimelineArg::Goto(0.5)
+        );
+        assert_eq!(
+            parse_timeline_arg("goto 100").unwrap(),
+            TimelineArg::Goto(1.0)
+        );
+        assert!(parse_timeline_arg("goto 101%").is_err());
+        assert!(parse_timeline_arg("goto -1%").is_err());
+        assert!(parse_timeline_arg("goto").is_err());
+        assert!(parse_timeline_arg("").is_err());
+        assert!(parse_timeline_arg("on off").is_err());
+    }
+
+    #[test]
+    fn test_parse_sigtime_arg() {
+        assert_eq!(parse_sigtime_arg("off").unwrap(), SigTime::Off);
+        assert_eq!(parse_sigtime_arg(" 120 ").unwrap(), SigTime::Index(120));
+        assert_eq!(parse_sigtime_arg("3900ps").unwrap(), SigTime::Time(3900));
+        assert_eq!(parse_sigtime_arg("4 ns").unwrap(), SigTime::Time(4000));
+        assert_eq!(parse_sigtime_arg("+100").unwrap(), SigTime::Relative(100));
+        assert_eq!(parse_sigtime_arg("-100").unwrap(), SigTime::Relative(-100));
+        assert!(parse_sigtime_arg("").is_err());
+        assert!(parse_sigtime_arg("-").is_err());
+        assert!(parse_sigtime_arg("12ms").is_err());
+    }
+
+    #[test]
+    fn test_parse_show_arg() {
+        assert_eq!(parse_show_arg("exec").unwrap(), Pane::Execution);
+        assert_eq!(parse_show_arg(" source ").unwrap(), Pane::Source);
+        assert_eq!(parse_show_arg("sig").unwrap(), Pane::Signals);
+        assert!(parse_show_arg("").is_err());
+        assert!(parse_show_arg("regs").is_err());
+    }
+
+    #[test]
+    fn test_parse_set_arg() {
+        assert_eq!(
+            parse_set_arg("showbytes on").unwrap(),
+            Setting::ShowBytes(true)
+        );
+        assert_eq!(
+            parse_set_arg("showbytes off").unwrap(),
+            Setting::ShowBytes(false)
+        );
+        assert_eq!(parse_set_arg("heat on").unwrap(), Setting::Heat(true));
+        assert_eq!(
+            parse_set_arg("bpcheck off").unwrap(),
+            Setting::BpCheck(false)
+        );
+        assert_eq!(
+            parse_set_arg("findbudget 5000").unwrap(),
+            Setting::FindBudget(5000)
+        );
+        assert!(parse_set_arg("findbudget 0").is_err());
+        assert_eq!(
+            parse_set_arg("elfwatch off").unwrap(),
+            Setting::ElfWatch(false)
+        );
+        assert_eq!(
+            parse_set_arg("logbuffer 100").unwrap(),
+            Setting::LogBuffer(100)
+        );
+        assert!(parse_set_arg("logbuffer 0").is_err());
+        assert_eq!(
+            parse_set_arg("fastforward on").unwrap(),
+            Setting::FastForward(true)
+        );
+        assert_eq!(
+            parse_set_arg("notify error").unwrap(),
+            Setting::Notify(Severity::Error)
+        );
+        assert!(parse_set_arg("notify loud").is_err());
+        assert_eq!(
+            parse_set_arg("notifytime 30").unwrap(),
+            Setting::NotifyTime(30)
+        );
+        assert!(parse_set_arg("notifytime 0").is_err());
+        assert_eq!(
+            parse_set_arg("addrfmt pad on symbols short").unwrap(),
+            Setting::AddrFmt(vec![
+                AddrFmtOption::Pad(true),
+                AddrFmtOption::Symbols(SymbolMode::Short)
+            ])
+        );
+        assert_eq!(
+            parse_set_arg("dualcontext off").unwrap(),
+            Setting::DualContext(false)
+        );
+        assert_eq!(
+            parse_set_arg("prefetch 4").unwrap(),
+            Setting::Prefetch(Some(4))
+        );
+        assert_eq!(
+            parse_set_arg("prefetch off").unwrap(),
+            Setting::Prefetch(None)
+        );
+        assert!(parse_set_arg("prefetch 0").is_err());
+        assert_eq!(
+            parse_set_arg("instwindow 32").unwrap(),
+            Setting::InstWindow(InstWindow::Fixed(32))
+        );
+        assert_eq!(
+            parse_set_arg("instwindow auto").unwrap(),
+            Setting::InstWindow(InstWindow::Auto)
+        );
+        assert!(parse_set_arg("instwindow 0").is_err());
+        assert!(parse_set_arg("instwindow 65").is_err());
+        assert!(parse_set_arg("addrfmt").is_err());
+        assert!(parse_set_arg("addrfmt pad").is_err());
+        assert!(parse_set_arg("showbytes").is_err());
+        assert!(parse_set_arg("showbytes maybe").is_err());
+        assert!(parse_set_arg("nonsense on").is_err());
+    }
+
+    #[test]
+    fn test_parse_skip_arg() {
+        assert_eq!(
+            parse_skip_arg("", &AddrFormatter::default()).unwrap(),
+            SkipArg::List
+        );
+        assert_eq!(
+            parse_skip_arg("list", &AddrFormatter::default()).unwrap(),
+            SkipArg::List
+        );
+        assert_eq!(
+            parse_skip_arg("add 0x100-1ff", &AddrFormatter::default()).unwrap(),
+            SkipArg::Add(SkipTarget::Addresses {
+                lo: 0x100,
+                hi: 0x1ff
+            })
+        );
+        assert_eq!(
+            parse_skip_arg("add src/delay.c:12-20", &AddrFormatter::default()).unwrap(),
+            SkipArg::Add(SkipTarget::Lines {
+                file: "src/delay.c".to_string(),
+                first: 12,
+                last: 20
+            })
+        );
+        // only the last colon splits off the lines
+        assert_eq!(
+            parse_skip_arg("add C:/src/delay.c:3-3", &AddrFormatter::default()).unwrap(),
+            SkipArg::Add(SkipTarget::Lines {
+                file: "C:/src/delay.c".to_string(),
+                first: 3,
+                last: 3
+            })
+        );
+        assert_eq!(
+            parse_skip_arg("del 2", &AddrFormatter::default()).unwrap(),
+            SkipArg::Delete(2)
+        );
+
+        assert!(parse_skip_arg("add 1ff-100", &AddrFormatter::default()).is_err());
+        assert!(parse_skip_arg("add delay.c:20-12", &AddrFormatter::default()).is_err());
+        assert!(parse_skip_arg("add delay.c:12", &AddrFormatter::default()).is_err());
+        assert!(parse_skip_arg("add :1-2", &AddrFormatter::default()).is_err());
+        assert!(parse_skip_arg("add 0x100", &AddrFormatter::default()).is_err());
+        assert!(parse_skip_arg("add zz-ff", &AddrFormatter::default()).is_err());
+        assert!(parse_skip_arg("del one", &AddrFormatter::default()).is_err());
+        assert!(parse_skip_arg("add 0-1 extra", &AddrFormatter::default()).is_err());
+    }
+
+    #[test]
+    fn test_parse_findinst_arg() {
+        assert_eq!(
+            parse_findinst_arg("ecall").unwrap(),
+            (
+                InstPredicate::Mnemonic("ecall".to_string()),
+                SearchDirection::Forward
+            )
+        );
+        assert_eq!(
+            parse_findinst_arg("JAL back").unwrap(),
+            (
+                InstPredicate::Mnemonic("jal".to_string()),
+                SearchDirection::Backward
+            )
+        );
+        assert_eq!(
+            parse_findinst_arg("rd=a0").unwrap().0,
+            InstPredicate::Rd(10)
+        );
+        assert_eq!(parse_findinst_arg("rd=x5").unwrap().0, InstPredicate::Rd(5));
+        assert_eq!(
+            parse_findinst_arg("mask:0x73/7f").unwrap().0,
+            InstPredicate::Mask {
+                value: 0x73,
+                mask: 0x7f
+            }
+        );
+        assert!(parse_findinst_arg("").is_err());
+        assert!(parse_findinst_arg("rd=q9").is_err());
+        assert!(parse_findinst_arg("mask:73").is_err());
+        assert!(parse_findinst_arg("mask:73/zz").is_err());
+        assert!(parse_findinst_arg("ecall forward").is_err());
+        assert!(parse_findinst_arg("ecall back again").is_err());
+    }
+
+    fn hex(addr: u32) -> String {
+        AddrFormatter::default().hex(addr.into())
+    }
+
+    #[test]
+    fn test_find_outcome_line() {
+        let ecall = InstPredicate::Mnemonic("ecall".to_string());
+        assert_eq!(
+            find_outcome_line(
+                &ecall,
+                SearchDirection::Forward,
+                &FindOutcome::OverBudget { scanned: 100 },
+                hex
+            ),
+            "No ecall found within 100 instructions"
+        );
+        assert_eq!(
+            find_outcome_line(
+                &ecall,
+                SearchDirection::Backward,
+                &FindOutcome::EndOfTrace { scanned: 7 },
+                hex
+            ),
+            "No ecall found before the start of the trace (7 instructions scanned)"
+        );
+    }
+
+    #[test]
+    fn test_never_hit_warning() {
+        assert_eq!(never_hit_warning(&[], 1, hex), None);
+        assert_eq!(never_hit_warning(&[], 3, hex), None);
+        assert_eq!(
+            never_hit_warning(&[0x0], 1, hex).unwrap(),
+            "Warning: address 0x0 never appears in the PC trace, breakpoint will never be hit"
+        );
+        assert_eq!(
+            never_hit_warning(&[0x100200, 0x100210], 3, hex).unwrap(),
+            "Warning: 2 of 3 locations never execute (0x100200, 0x100210)"
+        );
+    }
+
+    #[test]
+    fn test_parse_disas_arg() {
+        assert_eq!(
+            parse_disas_arg("puthex").unwrap(),
+            (DisasTarget::Name("puthex".to_string()), None)
+        );
+        assert_eq!(
+            parse_disas_arg("0x1000a4 20").unwrap(),
+            (DisasTarget::Address(0x1000a4), Some(20))
+        );
+        assert_eq!(
+            parse_disas_arg("main.c:42").unwrap(),
+            (
+                DisasTarget::FileLine {
+                    file: "main.c".to_string(),
+                    line: 42,
+                    column: None
+                },
+                None
+            )
+        );
+        assert_eq!(
+            parse_disas_arg("main 100").unwrap().1,
+            Some(MAX_DISAS_INSTRUCTIONS)
+        );
+        assert!(parse_disas_arg("main 101")
+            .unwrap_err()
+            .contains("Expected 1 to 100"));
+        assert!(parse_disas_arg("main 0").is_err());
+        assert!(parse_disas_arg("0xzz").is_err());
+        assert!(parse_disas_arg("").unwrap_err().starts_with("usage"));
+        assert!(parse_disas_arg("main 4 more").is_err());
+    }
+
+    #[test]
+    fn test_parse_dump_arg() {
+        assert_eq!(parse_dump_arg("state").unwrap(), None);
+        assert_eq!(
+            parse_dump_arg("state out.txt").unwrap(),
+            Some(PathBuf::from("out.txt"))
+        );
+        assert!(parse_dump_arg("").is_err());
+        assert!(parse_dump_arg("stuff").is_err());
+        assert!(parse_dump_arg("state a b").is_err());
+    }
+
+    #[test]
+    fn test_parse_memset_arg() {
+        assert_eq!(
+            parse_memset_arg("0x200000 de ad 0x01").unwrap(),
+            (0x200000, vec![0xde, 0xad, 0x01])
+        );
+        assert!(parse_memset_arg("").is_err());
+        assert!(parse_memset_arg("0x200000").is_err());
+        assert!(parse_memset_arg("0x200000 100").is_err());
+        assert!(parse_memset_arg("zz 00").is_err());
+    }
+
+    #[test]
+    fn test_parse_ignore_arg() {
+        assert_eq!(parse_ignore_arg("2 100").unwrap(), (2, 100));
+        assert!(parse_ignore_arg("2").is_err());
+        assert!(parse_ignore_arg("2 100 3").is_err());
+        assert!(parse_ignore_arg("0x2 100").is_err());
+        assert!(parse_ignore_arg("2 -1").is_err());
+    }
+
+    #[test]
+    fn test_parse_export_arg() {
+        assert_eq!(
+            parse_export_arg("md out.md").unwrap(),
+            (PathBuf::from("out.md"), Vec::new())
+        );
+        let (_, redactions) =
+            parse_export_arg("md out.md redact /home/pat=~ redact /opt/eda").unwrap();
+        assert_eq!(
+            redactions,
+            [
+                Redaction {
+                    from: "/home/pat".to_string(),
+                    to: "~".to_string()
+                },
+                Redaction {
+                    from: "/opt/eda".to_string(),
+                    to: DEFAULT_REDACTION.to_string()
+                },
+            ]
+        );
+        assert!(parse_export_arg("").is_err());
+        assert!(parse_export_arg("md").is_err());
+        assert!(parse_export_arg("html out.html").is_err());
+        assert!(parse_export_arg("md out.md redact").is_err());
+        assert!(parse_export_arg("md out.md strip /home").is_err());
+        assert!(parse_export_arg("md out.md redact =x").is_err());
+    }
+
+    #[test]
+    fn test_parse_sigenum_arg() {
+        assert_eq!(
+            parse_sigenum_arg("ctrl_fsm_cs fsm.toml").unwrap(),
+            SigenumArg::Load {
+                signal: "ctrl_fsm_cs".to_string(),
+                path: PathBuf::from("fsm.toml"),
+                format: NameFormat::NameAndValue,
+            }
+        );
+        assert_eq!(
+            parse_sigenum_arg("ctrl_fsm_cs fsm.json short").unwrap(),
+            SigenumArg::Load {
+                signal: "ctrl_fsm_cs".to_string(),
+                path: PathBuf::from("fsm.json"),
+                format: NameFormat::Name,
+            }
+        );
+        assert_eq!(
+            parse_sigenum_arg("clear ctrl_fsm_cs").unwrap(),
+            SigenumArg::Clear {
+                signal: "ctrl_fsm_cs".to_string()
+            }
+        );
+        assert!(parse_sigenum_arg("").is_err());
+        assert!(parse_sigenum_arg("ctrl_fsm_cs").is_err());
+        assert!(parse_sigenum_arg("ctrl_fsm_cs fsm.toml long").is_err());
+    }
+
+    #[test]
+    fn test_parse_format_arg() {
+        assert_eq!(
+            parse_format_arg("alu_adder_result_ex sdec").unwrap(),
+            (
+                "alu_adder_result_ex".to_string(),
+                FormattingType::SignedDecimal
+            )
+        );
+        assert_eq!(
+            parse_format_arg(" pc_id  bin ").unwrap(),
+            ("pc_id".to_string(), FormattingType::Binary)
+        );
+        assert!(parse_format_arg("pc_id oct")
+            .unwrap_err()
+            .contains("Unknown radix 'oct'"));
+        assert!(parse_format_arg("pc_id").is_err());
+        assert!(parse_format_arg("pc_id dec extra").is_err());
+    }
+
+    #[test]
+    fn test_parse_reghist_arg() {
+        assert_eq!(parse_reghist_arg("sp").unwrap(), (2, None));
+        assert_eq!(
+            parse_reghist_arg("x10 csv a0.csv").unwrap(),
+            (10, Some(PathBuf::from("a0.csv")))
+        );
+        assert!(parse_reghist_arg("").is_err());
+        assert!(parse_reghist_arg("sp csv").is_err());
+        assert!(parse_reghist_arg("sp json out.json").is_err());
+        assert!(parse_reghist_arg("x32")
+            .unwrap_err()
+            .contains("Unknown register"));
+    }
+
+    #[test]
+    fn test_parse_assert_arg() {
+        assert_eq!(
+            parse_assert_arg("", &AddrFormatter::default()).unwrap(),
+            AssertArg::List
+        );
+        assert_eq!(
+            parse_assert_arg("list", &AddrFormatter::default()).unwrap(),
+            AssertArg::List
+        );
+        assert_eq!(
+            parse_assert_arg("reg sp >= 0x20000000", &AddrFormatter::default()).unwrap(),
+            AssertArg::Reg {
+                reg: 2,
+                op: ">=".to_string(),
+                value: 0x2000_0000
+            }
+        );
+        assert_eq!(
+            parse_assert_arg("reg x10 != 0", &AddrFormatter::default()).unwrap(),
+            AssertArg::Reg {
+                reg: 10,
+                op: "!=".to_string(),
+                value: 0
+            }
+        );
+        assert_eq!(
+            parse_assert_arg("pc 0 ff outside", &AddrFormatter::default()).unwrap(),
+            AssertArg::PcRange {
+                lo: 0,
+                hi: 0xff,
+                inside: false
+            }
+        );
+        assert_eq!(
+            parse_assert_arg("del 3", &AddrFormatter::default()).unwrap(),
+            AssertArg::Delete(3)
+        );
+
+        assert!(parse_assert_arg("reg x32 < 0", &AddrFormatter::default()).is_err());
+        assert!(parse_assert_arg("reg sp => 0", &AddrFormatter::default()).is_err());
+        assert!(parse_assert_arg("reg sp < zz", &AddrFormatter::default()).is_err());
+        assert!(parse_assert_arg("pc ff 0 inside", &AddrFormatter::default()).is_err());
+        assert!(parse_assert_arg("pc 0 ff around", &AddrFormatter::default()).is_err());
+        assert!(parse_assert_arg("del one", &AddrFormatter::default()).is_err());
+        assert!(parse_assert_arg("sp > 0", &AddrFormatter::default()).is_err());
+    }
+
+    #[test]
+    fn test_parse_gpr() {
+        assert_eq!(parse_gpr("sp"), Some(2));
+        assert_eq!(parse_gpr("fp"), Some(8));
+        assert_eq!(parse_gpr("x31"), Some(31));
+        assert_eq!(parse_gpr("10"), Some(10));
+        assert_eq!(parse_gpr("x32"), None);
+        assert_eq!(parse_gpr("pc"), None);
+    }
 
     #[test]
     fn test_parse_breakpoint_arg_invalid_address() {
@@ -411,15 +2823,48 @@ mod tests {
             parse_breakpoint_arg("main.c:42").unwrap(),
             BreakpointTarget::FileLine {
                 file: PathBuf::from("main.c"),
-                line: 42
+                line: 42,
+                column: None,
             }
         );
         assert_eq!(
             parse_breakpoint_arg("src/lib.rs:123").unwrap(),
             BreakpointTarget::FileLine {
                 file: PathBuf::from("src/lib.rs"),
-                line: 123
+                line: 123,
+                column: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_breakpoint_arg_with_column() {
+        assert_eq!(
+            parse_breakpoint_arg("main.c:42:17").unwrap(),
+            BreakpointTarget::FileLine {
+                file: PathBuf::from("main.c"),
+                line: 42,
+                column: Some(17),
+            }
+        );
+        // column 0 is the whole line
+        assert_eq!(
+            parse_breakpoint_arg("main.c:42:0").unwrap(),
+            BreakpointTarget::FileLine {
+                file: PathBuf::from("main.c"),
+                line: 42,
+                column: None,
+            }
+        );
+        // a colon in the path isn't a line
+        assert_eq!(
+            parse_breakpoint_arg("C:main.c:42").unwrap(),
+            BreakpointTarget::FileLine {
+                file: PathBuf::from("C:main.c"),
+                line: 42,
+                column: None,
             }
         );
+        assert!(parse_breakpoint_arg(":42:17").is_err());
     }
 }
@@ -0,0 +1,145 @@
+//! Names for the values of enum-like signals, an FSM's state say, loaded with
+//! `sigenum <signal> <file>`.
+//!
+//! The file maps values to names, as TOML or, for a `.json` file, a JSON object. Values are
+//! decimal, `0x` hex or `0b` binary, with `_` allowed between digits:
+//!
+//! ```toml
+//! 0 = "RESET"
+//! 0x3 = "FLUSH"
+//! 0b1_0000 = "WAIT_SLEEP"
+//! ```
+//!
+//! A signal with names shows `FLUSH (0x3)` for a value that has one, its hex and a `?` for one
+//! that doesn't, and x or z values as they are
+
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A value as a map key: decimal, `0x` hex or `0b` binary
+fn parse_key(key: &str) -> Result<u64, String> {
+    let digits = key.trim().replace('_', "");
+    let parsed = if let Some(hex) = digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16)
+    } else if let Some(bin) = digits.strip_prefix("0b").or(digits.strip_prefix("0B")) {
+        u64::from_str_radix(bin, 2)
+    } else {
+        digits.parse()
+    };
+    parsed.map_err(|_| format!("'{key}' is not a value, expected decimal, 0x hex or 0b binary"))
+}
+
+/// The value to name map from `(key, name)` pairs, `None` for a name that isn't a string. Two
+/// keys for the same value are an error, whichever name was meant
+fn build<'a>(
+    entries: impl Iterator<Item = (&'a str, Option<&'a str>)>,
+) -> Result<HashMap<u64, String>, String> {
+    let mut names = HashMap::new();
+    for (key, name) in entries {
+        let value = parse_key(key)?;
+        let name = name.ok_or_else(|| format!("The name for '{key}' is not a string"))?;
+        if names.insert(value, name.to_string()).is_some() {
+            return Err(format!("Value 0x{value:x} is named more than once"));
+        }
+    }
+    Ok(names)
+}
+
+/// Parse a TOML value to name map
+pub fn parse_toml(text: &str) -> Result<HashMap<u64, String>, String> {
+    let table: toml::Table = toml::from_str(text).map_err(|e| e.to_string())?;
+    build(
+        table
+            .iter()
+            .map(|(key, name)| (key.as_str(), name.as_str())),
+    )
+}
+
+/// Parse a JSON value to name map, an object with the values as keys
+pub fn parse_json(text: &str) -> Result<HashMap<u64, String>, String> {
+    let json: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let object = json
+        .as_object()
+        .ok_or_else(|| "Expected a JSON object of values to names".to_string())?;
+    build(
+        object
+            .iter()
+            .map(|(key, name)| (key.as_str(), name.as_str())),
+    )
+}
+
+/// Read a value to name map, JSON for a `.json` file and TOML for anything else
+pub fn load(path: &Path) -> Result<HashMap<u64, String>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let names = if is_json {
+        parse_json(&text)
+    } else {
+        parse_toml(&text)
+    };
+    names.map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// The warning for names `signal` is too narrow to ever show, `None` if there are none
+pub fn width_warning(signal: &str, width: Option<u32>, unreachable: &[u64]) -> Option<String> {
+    let width = width?;
+    if unreachable.is_empty() {
+        return None;
+    }
+    let keys: Vec<String> = unreachable.iter().map(|key| format!("0x{key:x}")).collect();
+    Some(format!(
+        "warning: {signal} is {width} bit{} wide, the names for {} will never show",
+        if width == 1 { "" } else { "s" },
+        keys.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_toml() {
+        let names =
+            parse_toml("0 = \"RESET\"\n0x3 = \"FLUSH\"\n0b1_0000 = \"WAIT_SLEEP\"\n").unwrap();
+        assert_eq!(names.len(), 3);
+        assert_eq!(names[&0], "RESET");
+        assert_eq!(names[&3], "FLUSH");
+        assert_eq!(names[&16], "WAIT_SLEEP");
+
+        assert!(parse_toml("0x3 = 3").unwrap_err().contains("not a string"));
+        assert!(parse_toml("idle = \"IDLE\"")
+            .unwrap_err()
+            .contains("not a value"));
+        assert!(parse_toml("3 = \"A\"\n0x3 = \"B\"")
+            .unwrap_err()
+            .contains("more than once"));
+        assert!(parse_toml("0x3 = ").is_err());
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let names = parse_json(r#"{"0": "RESET", "0x3": "FLUSH"}"#).unwrap();
+        assert_eq!(names[&3], "FLUSH");
+        assert_eq!(names.len(), 2);
+        assert!(parse_json(r#"["RESET"]"#).is_err());
+        assert!(parse_json(r#"{"0x3": null}"#).is_err());
+    }
+
+    #[test]
+    fn test_width_warning() {
+        assert_eq!(
+            width_warning("ctrl_fsm_cs", Some(4), &[16]).unwrap(),
+            "warning: ctrl_fsm_cs is 4 bits wide, the names for 0x10 will never show"
+        );
+        assert_eq!(
+            width_warning("valid", Some(1), &[2, 3]).unwrap(),
+            "warning: valid is 1 bit wide, the names for 0x2, 0x3 will never show"
+        );
+        assert_eq!(width_warning("ctrl_fsm_cs", Some(4), &[]), None);
+        assert_eq!(width_warning("real", None, &[16]), None);
+    }
+}
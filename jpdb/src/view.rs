@@ -1,8 +1,1625 @@
-#[derive(Default)]
+use std::collections::HashSet;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use ratatui::layout::Rect;
+use shucks::client::{format_bytes_column, RegChange, ResumeStats, MAX_INSTRUCTION_WINDOW};
+use shucks::FormattingType;
+
+use crate::addr_format::{AddrFormatter, SymbolSource};
+use crate::breakpoints::{Breakpoint, BreakpointTable, MAX_HIT_HISTORY};
+use crate::dual_context::DualContext;
+use crate::model::{
+    ExecutionSnapshot, InstructionEntry, RegisterEntry, SignalChangeRow, SignalSnapshot,
+    SignalStatsRow, SourceEntry, SourceSnapshot, StopKind,
+};
+use crate::notifications::Notification;
+
+/// Below this the layout can't fit its panes, so only a "too small" message is drawn
+pub const MIN_WIDTH: u16 = 40;
+pub const MIN_HEIGHT: u16 = 10;
+
+pub fn too_small(area: Rect) -> bool {
+    area.width < MIN_WIDTH || area.height < MIN_HEIGHT
+}
+
+/// Rows left inside a bordered block
+pub fn inner_height(area: Rect) -> usize {
+    area.height.saturating_sub(2) as usize
+}
+
+/// How many instructions the Instructions pane asks for, `set instwindow`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InstWindow {
+    /// As many as fit in the pane, refetched when it's resized
+    #[default]
+    Auto,
+    Fixed(usize),
+}
+
+/// Instructions that fill a pane laid out at `area`, one a row inside its border. At least one,
+/// and no more than the client will decode
+pub fn auto_instruction_window(area: Rect) -> usize {
+    inner_height(area).clamp(1, MAX_INSTRUCTION_WINDOW)
+}
+
+/// Which of `total` lines fit in `height` rows when scrolled `scroll` lines up from the bottom.
+/// Never out of bounds, and empty when there's no room at all
+pub fn line_window(total: usize, height: usize, scroll: usize) -> Range<usize> {
+    let scroll = scroll.min(total.saturating_sub(height));
+    let end = total - scroll;
+    end.saturating_sub(height)..end
+}
+
+/// The column just inside the right border of a bordered block, `None` if the block has no
+/// inside to put it in
+pub fn scrollbar_area(area: Rect) -> Option<Rect> {
+    if area.width < 2 || area.height < 3 {
+        return None;
+    }
+    Some(Rect {
+        x: area.x + area.width - 1,
+        y: area.y + 1,
+        width: 1,
+        height: area.height - 2,
+    })
+}
+
+/// A popup centered in `area`, taking the given percentages of it but never less than the
+/// minimum size, and never more than `area` itself
+pub fn centered_popup(
+    area: Rect,
+    percent_x: u16,
+    percent_y: u16,
+    min_width: u16,
+    min_height: u16,
+) -> Rect {
+    let width = ((area.width as u32 * percent_x as u32 / 100) as u16)
+        .max(min_width)
+        .min(area.width);
+    let height = ((area.height as u32 * percent_y as u32 / 100) as u16)
+        .max(min_height)
+        .min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+/// The row right under `popup`, if it's still inside `area`
+pub fn line_below(popup: Rect, area: Rect) -> Option<Rect> {
+    let y = popup.y + popup.height;
+    (y < area.y + area.height).then_some(Rect {
+        x: popup.x,
+        y,
+        width: popup.width,
+        height: 1,
+    })
+}
+
+/// What every pane shows once the target has exited
+pub const TERMINATED: &str = "Process has terminated";
+
+const HEAT_GLYPHS: [char; 4] = ['▂', '▄', '▆', '█'];
+
+/// ABI names of x0 through x31
+pub const GPR_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+/// Marks an instruction with an enabled breakpoint in the `disas` listing
+pub const BREAKPOINT_MARKER: char = 'B';
+/// Marks an instruction whose breakpoints are all disabled
+pub const DISABLED_BREAKPOINT_MARKER: char = 'b';
+
+/// One line of an instruction listing, `->` on the current instruction and `breakpoint` in the
+/// column after it
+fn instruction_line(
+    inst: &InstructionEntry,
+    breakpoint: char,
+    addr_fmt: &AddrFormatter,
+    symbols: &impl SymbolSource,
+) -> String {
+    let marker = if inst.is_current { "->" } else { "  " };
+    let bytes = inst
+        .bytes
+        .as_deref()
+        .map(|bytes| format!("{}   ", format_bytes_column(bytes)))
+        .unwrap_or_default();
+    format!(
+        "{marker}{breakpoint} {}: {bytes}{}",
+        addr_fmt.format(inst.addr.into(), symbols),
+        inst.text
+    )
+}
+
+/// The `disas` listing of `what`, laid out like the Instructions pane with breakpoints marked
+pub fn disas_lines(
+    what: &str,
+    instructions: &[InstructionEntry],
+    breakpoints: &BreakpointTable,
+    addr_fmt: &AddrFormatter,
+    symbols: &impl SymbolSource,
+) -> Vec<String> {
+    let mut lines = vec![format!("Dump of assembler code for {what}:")];
+    lines.extend(instructions.iter().map(|inst| {
+        let breakpoint = match breakpoints.enabled_at(inst.addr) {
+            Some(true) => BREAKPOINT_MARKER,
+            Some(false) => DISABLED_BREAKPOINT_MARKER,
+            None => ' ',
+        };
+        instruction_line(inst, breakpoint, addr_fmt, symbols)
+    }));
+    lines.push("End of assembler dump.".to_string());
+    lines
+}
+
+/// `time` in `unit`, the waveform's timescale. Just the number when there's no unit
+pub fn time_label(time: u64, unit: &str) -> String {
+    format!("{time} {unit}").trim_end().to_string()
+}
+
+/// The `bhits` listing: each recorded hit of `breakpoint`, at `location`, with its time index
+/// and, given a waveform, its time in `unit`
+pub fn bhits_lines(
+    breakpoint: &Breakpoint,
+    location: &str,
+    times: &[Option<u64>],
+    unit: &str,
+) -> Vec<String> {
+    let count = breakpoint.hits.len();
+    let mut lines = vec![match count {
+        0 => format!(
+            "Breakpoint {} ({location}) has no recorded hits",
+            breakpoint.id
+        ),
+        MAX_HIT_HISTORY => format!(
+            "Breakpoint {} ({location}), last {count} hits:",
+            breakpoint.id
+        ),
+        _ => format!(
+            "Breakpoint {} ({location}), {count} hit{}:",
+            breakpoint.id,
+            if count == 1 { "" } else { "s" }
+        ),
+    }];
+    lines.extend(
+        breakpoint
+            .hits
+            .iter()
+            .zip(times.iter().chain(std::iter::repeat(&None)))
+            .map(|(idx, time)| match time {
+                Some(time) => format!("  time index {idx}: {}", time_label(*time, unit)),
+                None => format!("  time index {idx}"),
+            }),
+    );
+    lines
+}
+
+/// The instruction listing, current instruction marked with `->`
+pub fn instruction_lines(
+    snapshot: &ExecutionSnapshot,
+    addr_fmt: &AddrFormatter,
+    symbols: &impl SymbolSource,
+) -> Vec<String> {
+    let frame = match snapshot {
+        ExecutionSnapshot::Terminated => return vec![TERMINATED.to_string()],
+        ExecutionSnapshot::Stopped { frame, .. } => frame,
+    };
+    match frame {
+        Ok(frame) => match &frame.instructions {
+            Ok(instructions) => instructions
+                .iter()
+                .map(|inst| instruction_line(inst, ' ', addr_fmt, symbols))
+                .collect(),
+            Err(_) => vec![format!(
+                "->  {}: <unable to get instructions>",
+                addr_fmt.format(frame.pc.into(), symbols)
+            )],
+        },
+        Err(e) => vec![format!("Error: {e}")],
+    }
+}
+
+/// One line summing up how far a continue went
+pub fn resume_banner(stats: &ResumeStats) -> String {
+    format!(
+        "Advanced {} PC changes ({} trace changes), time index {} -> {}",
+        stats.pc_advances, stats.changes_crossed, stats.start_time_idx, stats.end_time_idx
+    )
+}
+
+/// The lldb style stop summary: process state, frame and the instruction listing
+pub fn execution_lines(
+    snapshot: &ExecutionSnapshot,
+    addr_fmt: &AddrFormatter,
+    symbols: &impl SymbolSource,
+) -> Vec<String> {
+    let (stop, frame) = match snapshot {
+        ExecutionSnapshot::Terminated => return vec![TERMINATED.to_string()],
+        ExecutionSnapshot::Stopped { stop, frame } => (stop, frame),
+    };
+    let stop_reason = match stop {
+        StopKind::Livelock => "livelock (PC looping with no breakpoint)",
+        StopKind::Assertion => "assertion failed",
+        StopKind::Step => "instruction step over",
+    };
+    let mut lines = vec![
+        "Process 1 stopped".to_string(),
+        format!("* thread #1, stop reason = {stop_reason}"),
+    ];
+    match frame {
+        Ok(frame) => {
+            lines.push(format!(
+                "    frame #0: {} {}",
+                addr_fmt.hex(frame.pc.into()),
+                frame.symbol
+            ));
+            lines.extend(instruction_lines(snapshot, addr_fmt, symbols));
+        }
+        Err(e) => lines.push(format!("Error getting PC: {e}")),
+    }
+    lines.push("Target 0: (No executable module.) stopped.".to_string());
+    lines
+}
+
+/// Source pane gutter for a line that ran `count` times, scaled against the hottest line shown
+pub fn heat_gutter(count: u64, max: u64) -> String {
+    if count == 0 || max == 0 {
+        return format!("  {:>5} ", 0);
+    }
+    let level = ((count - 1) * HEAT_GLYPHS.len() as u64 / max) as usize;
+    format!(
+        "{} {count:>5} ",
+        HEAT_GLYPHS[level.min(HEAT_GLYPHS.len() - 1)]
+    )
+}
+
+fn source_line(entry: &SourceEntry, max_heat: u64) -> String {
+    let marker = if entry.is_current { "->" } else { "  " };
+    let code = if entry.has_code == Some(true) {
+        "*"
+    } else {
+        " "
+    };
+    let gutter = entry
+        .heat
+        .map(|count| heat_gutter(count, max_heat))
+        .unwrap_or_default();
+    let text = entry.text.as_deref().unwrap_or("<source not available>");
+    format!("{marker}{code}{gutter}{}: {text}", entry.line_no)
+}
+
+/// A `^` under column `column` of `source_line`, the line [`source_line`] drew. Tabs before the
+/// column are kept so the caret lines up however the terminal expands them
+fn column_caret(source_line: &str, text: &str, column: u64) -> String {
+    let prefix = source_line.chars().count() - text.chars().count();
+    let indent: String = text
+        .chars()
+        .take(column as usize - 1)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    format!("{}{indent}^", " ".repeat(prefix))
+}
+
+/// `file:line`, a blank line, then the current line and the few after it. A caret under the
+/// current line marks the column execution is at, when the line info has one
+pub fn source_lines(snapshot: &SourceSnapshot) -> Vec<String> {
+    match snapshot {
+        SourceSnapshot::Terminated => vec![TERMINATED.to_string()],
+        SourceSnapshot::Located {
+            file,
+            entries,
+            max_heat,
+            next_lines_error,
+        } => {
+            let current_line = entries
+                .iter()
+                .find(|entry| entry.is_current)
+                .map_or(0, |entry| entry.line_no);
+            let mut lines = vec![format!("{file}:{current_line}"), String::new()];
+            for entry in entries {
+                let line = source_line(entry, *max_heat);
+                let caret = match (&entry.text, entry.column) {
+                    (Some(text), Some(column)) if column > 0 => {
+                        Some(column_caret(&line, text, column))
+                    }
+                    _ => None,
+                };
+                lines.push(line);
+                lines.extend(caret);
+            }
+            if let Some(e) = next_lines_error {
+                lines.push(format!("Error getting next lines: {e}"));
+            }
+            lines
+        }
+        SourceSnapshot::NoDebugInfo => vec![
+            "Source Code:".to_string(),
+            "No debug information available".to_string(),
+        ],
+        SourceSnapshot::ElfWithoutDebugInfo { elf, missing } => vec![
+            "Source Code:".to_string(),
+            match missing {
+                None => format!("no debug info in {elf} (compile with -g)"),
+                Some(reason) => format!("no debug info in {elf}: {reason}"),
+            },
+        ],
+        SourceSnapshot::Error(e) => vec!["Source Code:".to_string(), format!("Error: {e}")],
+    }
+}
+
+/// Ends the Signals pane line of a signal that changed during the last step or continue
+pub const CHANGED_MARKER: &str = "*";
+
+/// The Signals pane header when the cursor is past the end of the waveform
+pub const TIME_UNAVAILABLE: &str = "time unavailable";
+
+/// The time at the cursor, then `name: value` for each selected signal. Signals without a value
+/// show why in angle brackets, which no hex value can be mistaken for. One that changed at all
+/// during the last step or continue ends in [`CHANGED_MARKER`], even if it's back where it was
+pub fn signal_lines(snapshot: &SignalSnapshot) -> Vec<String> {
+    match snapshot {
+        SignalSnapshot::Terminated => vec![TERMINATED.to_string()],
+        SignalSnapshot::NoWaveform => vec!["no waves found".to_string()],
+        SignalSnapshot::Values {
+            time,
+            unit,
+            entries,
+            ..
+        } => {
+            let header = match time {
+                Some(time) => time_label(*time, unit),
+                None => TIME_UNAVAILABLE.to_string(),
+            };
+            let mut lines = vec![header, String::new()];
+            if entries.is_empty() {
+                lines.push("No signals selected".to_string());
+                lines.push("Use 'addsig' to add signals".to_string());
+            }
+            lines.extend(entries.iter().map(|entry| {
+                // hex goes without saying
+                let name = match entry.format {
+                    FormattingType::Hex => entry.name.clone(),
+                    format => format!("{} ({format})", entry.name),
+                };
+                let line = match &entry.value {
+                    Ok(value) => format!("{name}: {value}"),
+                    Err(e) => format!("{name}: {e}"),
+                };
+                match entry.changed {
+                    true => format!("{line} {CHANGED_MARKER}"),
+                    false => line,
+                }
+            }));
+            lines
+        }
+    }
+}
+
+const SIGNALS_TITLE: &str = "Signals";
+
+/// The Signals pane title, which says so when `sigtime` has the pane away from execution
+pub fn signals_title(snapshot: &SignalSnapshot) -> String {
+    match snapshot {
+        SignalSnapshot::Values {
+            time: Some(time),
+            exec_time: Some(exec_time),
+            ..
+        } => format!("{SIGNALS_TITLE} @ t={time} (pinned, exec at {exec_time})"),
+        _ => SIGNALS_TITLE.to_string(),
+    }
+}
+
+/// The debug panel title, with the least severe level shown when some are hidden
+pub fn debug_panel_title(min_level: log::Level) -> String {
+    let filter = match min_level {
+        log::Level::Trace => String::new(),
+        log::Level::Error => " [ERROR only]".to_string(),
+        level => format!(" [{level} and up]"),
+    };
+    format!("Debug{filter} (d to toggle, PgUp/PgDn to scroll, Home/End, Ctrl+F: level)")
+}
+
+/// The help modal's title, with which lines are showing when they don't all fit
+pub fn help_title(window: Range<usize>, total: usize) -> String {
+    if window.is_empty() || window.len() >= total {
+        return "Help (Esc to close)".to_string();
+    }
+    format!(
+        "Help, lines {}-{} of {total} (Esc to close)",
+        window.start + 1,
+        window.end
+    )
+}
+
+/// One `regs` line, e.g. `x10 a0   = 0x00000013`, followed by its last change if it was fetched
+pub fn register_line(entry: &RegisterEntry, addr_fmt: &AddrFormatter) -> String {
+    let line = format!(
+        "x{:<2} {:<4} = {}",
+        entry.reg,
+        GPR_NAMES[entry.reg],
+        addr_fmt.column(entry.value.into())
+    );
+    match entry.last_change {
+        Some(RegChange {
+            time,
+            previous: Some(previous),
+            ..
+        }) => format!(
+            "{line}  (changed @t={time}, was {})",
+            addr_fmt.hex(previous.into())
+        ),
+        Some(RegChange {
+            time,
+            previous: None,
+            ..
+        }) => format!("{line}  (set @t={time})"),
+        None => line,
+    }
+}
+
+/// Set by anything that changes what's on screen outside of a keypress: the logger, background
+/// threads, async output. The draw loop takes it once per poll interval, so a burst of events
+/// costs a single redraw
+#[derive(Debug, Clone, Default)]
+pub struct RedrawFlag(Arc<AtomicBool>);
+
+impl RedrawFlag {
+    pub fn mark(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+
+    /// Whether a redraw was asked for since the last call, clearing the request
+    pub fn take(&self) -> bool {
+        self.0.swap(false, Ordering::AcqRel)
+    }
+}
+
+/// A pane whose content is fetched from the target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pane {
+    /// Stop summary and instructions, shown in every layout
+    Execution,
+    Source,
+    Signals,
+}
+
+/// Ends a `sigstats` row for a signal that never changes, and has the modal draw it dim
+pub const CONSTANT_MARKER: &str = "(constant)";
+
+/// The `sigstats` table, busiest signal in `window` first, or over the whole trace without
+/// one, times in `unit`. Columns are lined up to the longest name
+pub fn sigstats_lines(
+    rows: &[SignalStatsRow],
+    window: Option<(u64, u64)>,
+    unit: &str,
+) -> Vec<String> {
+    let mut rows: Vec<&SignalStatsRow> = rows.iter().collect();
+    rows.sort_by(|a, b| b.stats.in_window.cmp(&a.stats.in_window));
+    let width = rows
+        .iter()
+        .map(|row| row.name.len())
+        .chain(["signal".len()])
+        .max()
+        .unwrap_or_default();
+    let time = |time: Option<u64>| time.map_or("-".to_string(), |time| time_label(time, unit));
+
+    let mut lines = vec![
+        match window {
+            Some((lo, hi)) => format!("Changes between time index {lo} and {hi}"),
+            None => "Changes over the whole trace".to_string(),
+        },
+        format!(
+            "{:<width$}  {:>8}  {:>8}  {:>14}  {:>14}",
+            "signal", "total", "window", "first", "last"
+        ),
+    ];
+    for row in rows {
+        let mut line = format!(
+            "{:<width$}  {:>8}  {:>8}  {:>14}  {:>14}",
+            row.name,
+            row.stats.total,
+            row.stats.in_window,
+            time(row.first_time),
+            time(row.last_time)
+        );
+        if row.stats.is_constant() {
+            line.push_str("  ");
+            line.push_str(CONSTANT_MARKER);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// The `sigchanged` listing: the signals that changed at all between the two time indices of
+/// `window`, with how many times when that's known, then the ones that didn't
+pub fn sigchanged_lines(window: (u64, u64), rows: &[SignalChangeRow]) -> Vec<String> {
+    if rows.is_empty() {
+        return vec!["No signals selected".to_string()];
+    }
+    let (changed, unchanged): (Vec<&SignalChangeRow>, Vec<&SignalChangeRow>) =
+        rows.iter().partition(|row| row.changed);
+    let mut lines = vec![format!(
+        "Changed between time index {} and {} ({} of {}):",
+        window.0,
+        window.1,
+        changed.len(),
+        rows.len()
+    )];
+    lines.extend(changed.iter().map(|row| match row.changes {
+        Some(1) => format!("  {}  (1 change)", row.name),
+        Some(changes) => format!("  {}  ({changes} changes)", row.name),
+        None => format!("  {}", row.name),
+    }));
+    lines.push(format!("Unchanged ({}):", unchanged.len()));
+    lines.extend(unchanged.iter().map(|row| format!("  {}", row.name)));
+    lines
+}
+
+/// The `reghist` summary of GPR `reg`'s `history`, its `(time_idx, value)` at each change
+pub fn reghist_lines(reg: usize, history: &[(u64, u32)], addr_fmt: &AddrFormatter) -> Vec<String> {
+    let name = format!("x{reg} ({})", GPR_NAMES[reg]);
+    let (Some(first), Some(last)) = (history.first(), history.last()) else {
+        return vec![format!("{name} has no known value in the trace")];
+    };
+    let values = history.iter().map(|(_, value)| *value);
+    let (min, max) = values.fold((u32::MAX, u32::MIN), |(min, max), value| {
+        (min.min(value), max.max(value))
+    });
+    vec![
+        format!(
+            "{name}: {} changes between time index {} and {}",
+            history.len(),
+            first.0,
+            last.0
+        ),
+        format!(
+            "  min {}  max {}",
+            addr_fmt.column(min.into()),
+            addr_fmt.column(max.into())
+        ),
+        format!(
+            "  first {}  last {}",
+            addr_fmt.column(first.1.into()),
+            addr_fmt.column(last.1.into())
+        ),
+    ]
+}
+
+/// `history` for `reghist csv`, a `time_idx,value` header then a row per change, values in hex
+pub fn reghist_csv(history: &[(u64, u32)]) -> String {
+    let mut csv = String::from("time_idx,value\n");
+    for (time_idx, value) in history {
+        csv.push_str(&format!("{time_idx},0x{value:08x}\n"));
+    }
+    csv
+}
+
+/// `<severity>: <text>`, with how many times it came in if more than once. The banner line,
+/// and what line mode prints
+pub fn notification_line(notification: &Notification) -> String {
+    let mut line = format!("{}: {}", notification.severity, notification.text);
+    if notification.repeats > 0 {
+        line.push_str(&format!(" (x{})", notification.repeats + 1));
+    }
+    line
+}
+
+/// The `notifications` listing, oldest first, each with how long ago it came in and from where
+pub fn notification_lines<'a>(
+    history: impl Iterator<Item = &'a Notification>,
+    now: Instant,
+) -> Vec<String> {
+    let lines: Vec<String> = history
+        .map(|notification| {
+            let age = now.saturating_duration_since(notification.timestamp);
+            format!(
+                "{:>6}s ago  [{}] {}",
+                age.as_secs(),
+                notification.source,
+                notification_line(notification)
+            )
+        })
+        .collect();
+    if lines.is_empty() {
+        return vec!["No notifications".to_string()];
+    }
+    lines
+}
+
+/// Command output with more lines than this goes to the modal, where it can be scrolled,
+/// instead of the command history
+pub const HISTORY_OUTPUT_LINES: usize = 20;
+
+/// Or with more bytes than this, for a few very long lines
+pub const HISTORY_OUTPUT_BYTES: usize = 4 * 1024;
+
+/// Where command output is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputRoute {
+    History,
+    Modal,
+}
+
+/// Where `output` goes, by [`HISTORY_OUTPUT_LINES`] and [`HISTORY_OUTPUT_BYTES`]
+pub fn output_route(output: &str) -> OutputRoute {
+    if output.len() > HISTORY_OUTPUT_BYTES || output.lines().count() > HISTORY_OUTPUT_LINES {
+        OutputRoute::Modal
+    } else {
+        OutputRoute::History
+    }
+}
+
+/// What the timeline minimap marks, all in time indices
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Timeline {
+    /// Drawn along the bottom of the screen, `timeline on|off`
+    pub show: bool,
+    /// Time indices in the whole trace, 0 without a waveform
+    pub trace_len: u64,
+    /// Where execution is
+    pub cursor: Option<u64>,
+    /// Where `sigtime` has the Signals pane pinned
+    pub pin: Option<u64>,
+    /// Every place continue stopped on a breakpoint this session, oldest first
+    pub hits: Vec<u64>,
+}
+
+impl Timeline {
+    /// The time index `fraction` of the way along the trace, clamped to it
+    pub fn index_at(&self, fraction: f64) -> u64 {
+        let last = self.trace_len.saturating_sub(1);
+        ((last as f64 * fraction.clamp(0.0, 1.0)).round() as u64).min(last)
+    }
+
+    /// The time index under `column` of a timeline drawn `width` wide
+    pub fn index_at_column(&self, column: usize, width: usize) -> u64 {
+        match width {
+            0 | 1 => 0,
+            _ => self.index_at(column as f64 / (width - 1) as f64),
+        }
+    }
+}
+
+/// Column `idx` falls in on a `width` wide timeline of a `trace_len` long trace. The first index
+/// is the first column, the last the last, and everything between rounds to the nearest
+pub fn timeline_column(idx: u64, trace_len: u64, width: usize) -> usize {
+    let last = trace_len.saturating_sub(1) as u128;
+    if width == 0 || last == 0 {
+        return 0;
+    }
+    let idx = (idx as u128).min(last);
+    ((idx * (width - 1) as u128 + last / 2) / last) as usize
+}
+
+/// The timeline minimap, `width` characters: a bar with the cursor (█), the sigtime pin (◆)
+/// and breakpoint hits (·) on it. Hits that land in the same column collapse into a count, past 9
+/// shown as `+`, and the cursor and pin cover whatever else is in their column
+pub fn render_timeline(timeline: &Timeline, width: usize) -> String {
+    if width == 0 {
+        return String::new();
+    }
+    if timeline.trace_len == 0 {
+        return "no waves found".chars().take(width).collect();
+    }
+    let column = |idx: u64| timeline_column(idx, timeline.trace_len, width);
+
+    let mut hits = vec![0usize; width];
+    for &hit in &timeline.hits {
+        hits[column(hit)] += 1;
+    }
+    let mut cells: Vec<char> = hits
+        .into_iter()
+        .map(|count| match count {
+            0 => '─',
+            1 => '·',
+            2..=9 => char::from_digit(count as u32, 10).unwrap(),
+            _ => '+',
+        })
+        .collect();
+    if let Some(pin) = timeline.pin {
+        cells[column(pin)] = '◆';
+    }
+    if let Some(cursor) = timeline.cursor {
+        cells[column(cursor)] = '█';
+    }
+    cells.into_iter().collect()
+}
+
 pub struct ViewState {
     pub execution_lines: Vec<String>,
     pub instruction_lines: Vec<String>,
     pub source_lines: Vec<String>,
     pub signal_lines: Vec<String>,
+    pub signals_title: String,
+    pub show_split_view: bool,
+    pub show_debug_panel: bool,
+    pub timeline: Timeline,
+    /// The image execution last crossed from, split into the code panes for a few stops
+    pub dual_context: DualContext,
+    /// `set instwindow`
+    pub inst_window: InstWindow,
+    /// Where the Instructions pane's listing was last laid out, what an auto window is sized to
+    pub instruction_pane: Option<Rect>,
+    /// Panes that were hidden when the target moved, so their content is out of date
+    stale: HashSet<Pane>,
 }
 
+impl Default for ViewState {
+    fn default() -> Self {
+        Self {
+            execution_lines: Vec::new(),
+            instruction_lines: Vec::new(),
+            source_lines: Vec::new(),
+            signal_lines: Vec::new(),
+            signals_title: SIGNALS_TITLE.to_string(),
+            show_split_view: true,
+            show_debug_panel: false,
+            timeline: Timeline::default(),
+            dual_context: DualContext::default(),
+            inst_window: InstWindow::default(),
+            instruction_pane: None,
+            stale: HashSet::new(),
+        }
+    }
+}
+
+impl ViewState {
+    /// Whether `pane` is drawn in the current layout
+    pub fn is_visible(&self, pane: Pane) -> bool {
+        match pane {
+            Pane::Execution => true,
+            Pane::Source | Pane::Signals => self.show_split_view,
+        }
+    }
+
+    /// Instructions the Execution pane should be fetched with. `None` for an auto window while
+    /// there's no Instructions pane laid out to size it to
+    pub fn instruction_window(&self) -> Option<usize> {
+        match self.inst_window {
+            InstWindow::Fixed(count) => Some(count),
+            InstWindow::Auto => self.instruction_pane.map(auto_instruction_window),
+        }
+    }
+
+    pub fn mark_stale(&mut self, pane: Pane) {
+        self.stale.insert(pane);
+    }
+
+    pub fn is_stale(&self, pane: Pane) -> bool {
+        self.stale.contains(&pane)
+    }
+
+    pub fn mark_fresh(&mut self, pane: Pane) {
+        self.stale.remove(&pane);
+    }
+
+    /// Stale panes that are visible now, and so need fetching
+    pub fn visible_stale(&self) -> Vec<Pane> {
+        [Pane::Execution, Pane::Source, Pane::Signals]
+            .into_iter()
+            .filter(|pane| self.is_visible(*pane) && self.is_stale(*pane))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeline_column() {
+        // ends land on the ends at every width
+        for width in [3, 10, 80, 1000] {
+            assert_eq!(timeline_column(0, 5000, width), 0);
+            assert_eq!(timeline_column(4999, 5000, width), width - 1);
+            // past the end sticks at the end
+            assert_eq!(timeline_column(9999, 5000, width), width - 1);
+        }
+        // width 3: the middle third of the trace rounds to the middle column
+        assert_eq!(timeline_column(1249, 5000, 3), 0);
+        assert_eq!(timeline_column(1250, 5000, 3), 1);
+        assert_eq!(timeline_column(3749, 5000, 3), 1);
+        assert_eq!(timeline_column(3750, 5000, 3), 2);
+        // wider than the trace spreads it out
+        assert_eq!(timeline_column(1, 3, 80), 40);
+        assert_eq!(timeline_column(2, 3, 80), 79);
+        assert_eq!(timeline_column(40, 101, 11), 4);
+        assert_eq!(timeline_column(45, 101, 11), 5);
+        // nothing to spread over
+        assert_eq!(timeline_column(0, 1, 80), 0);
+        assert_eq!(timeline_column(0, 0, 80), 0);
+        assert_eq!(timeline_column(7, 100, 0), 0);
+    }
+
+    #[test]
+    fn test_timeline_index() {
+        let timeline = Timeline {
+            trace_len: 101,
+            ..Timeline::default()
+        };
+        assert_eq!(timeline.index_at(0.5), 50);
+        assert_eq!(timeline.index_at(1.0), 100);
+        assert_eq!(timeline.index_at(2.0), 100);
+        assert_eq!(timeline.index_at(-1.0), 0);
+        assert_eq!(timeline.index_at_column(5, 11), 50);
+        assert_eq!(timeline.index_at_column(10, 11), 100);
+        assert_eq!(timeline.index_at_column(0, 1), 0);
+        // a click lands back on its own column
+        for column in 0..11 {
+            let idx = timeline.index_at_column(column, 11);
+            assert_eq!(timeline_column(idx, 101, 11), column);
+        }
+    }
+
+    #[test]
+    fn test_render_timeline() {
+        let mut timeline = Timeline {
+            show: true,
+            trace_len: 101,
+            cursor: Some(50),
+            pin: None,
+            hits: vec![10, 30],
+        };
+        assert_eq!(render_timeline(&timeline, 11), "─·─·─█─────");
+
+        timeline.pin = Some(100);
+        timeline.hits = vec![10, 11, 12, 90];
+        assert_eq!(render_timeline(&timeline, 11), "─3───█───·◆");
+
+        // squeezed into 3 columns everything collapses, the cursor wins its column
+        timeline.hits = (0..40).collect();
+        timeline.cursor = Some(0);
+        assert_eq!(render_timeline(&timeline, 3), "█+◆");
+        timeline.cursor = Some(100);
+        timeline.pin = None;
+        assert_eq!(render_timeline(&timeline, 3), "++█");
+
+        assert_eq!(render_timeline(&timeline, 0), "");
+        assert_eq!(render_timeline(&Timeline::default(), 8), "no waves");
+    }
+
+    #[test]
+    fn test_line_window_tiny_heights() {
+        assert_eq!(line_window(10, 0, 0), 10..10);
+        assert_eq!(line_window(10, 0, 4), 6..6);
+        assert_eq!(line_window(10, 1, 0), 9..10);
+        assert_eq!(line_window(10, 1, 100), 0..1);
+        assert_eq!(line_window(10, 2, 3), 5..7);
+        assert_eq!(line_window(0, 0, 0), 0..0);
+        assert_eq!(line_window(0, 2, 5), 0..0);
+        assert_eq!(line_window(1, 2, 0), 0..1);
+    }
+
+    #[test]
+    fn test_help_title() {
+        assert_eq!(help_title(0..5, 5), "Help (Esc to close)");
+        assert_eq!(help_title(10..10, 40), "Help (Esc to close)");
+        assert_eq!(
+            help_title(line_window(40, 22, 0), 40),
+            "Help, lines 19-40 of 40 (Esc to close)"
+        );
+        assert_eq!(
+            help_title(line_window(40, 22, 100), 40),
+            "Help, lines 1-22 of 40 (Esc to close)"
+        );
+    }
+
+    #[test]
+    fn test_inner_height() {
+        for (height, inner) in [(0, 0), (1, 0), (2, 0), (3, 1)] {
+            assert_eq!(inner_height(Rect::new(0, 0, 10, height)), inner);
+        }
+    }
+
+    #[test]
+    fn test_auto_instruction_window() {
+        // a row for each instruction inside the border
+        assert_eq!(auto_instruction_window(Rect::new(0, 0, 30, 12)), 10);
+        // always at least the current one
+        for height in [0, 1, 2, 3] {
+            assert_eq!(auto_instruction_window(Rect::new(0, 0, 30, height)), 1);
+        }
+        assert_eq!(
+            auto_instruction_window(Rect::new(0, 0, 30, 200)),
+            MAX_INSTRUCTION_WINDOW
+        );
+
+        let mut view_state = ViewState::default();
+        assert_eq!(view_state.instruction_window(), None);
+        view_state.instruction_pane = Some(Rect::new(0, 0, 30, 20));
+        assert_eq!(view_state.instruction_window(), Some(18));
+        view_state.inst_window = InstWindow::Fixed(6);
+        assert_eq!(view_state.instruction_window(), Some(6));
+    }
+
+    #[test]
+    fn test_scrollbar_area_needs_an_inside() {
+        assert_eq!(scrollbar_area(Rect::new(0, 0, 10, 2)), None);
+        assert_eq!(scrollbar_area(Rect::new(0, 0, 1, 10)), None);
+        assert_eq!(
+            scrollbar_area(Rect::new(2, 3, 10, 5)),
+            Some(Rect::new(11, 4, 1, 3))
+        );
+    }
+
+    #[test]
+    fn test_centered_popup_clamps() {
+        let area = Rect::new(0, 0, 100, 40);
+        assert_eq!(
+            centered_popup(area, 60, 50, 20, 6),
+            Rect::new(20, 10, 60, 20)
+        );
+
+        // below the minimum percentage size the popup keeps its minimum, within the area
+        let tiny = Rect::new(0, 0, 15, 5);
+        let popup = centered_popup(tiny, 60, 50, 20, 6);
+        assert_eq!(popup, tiny);
+        assert_eq!(line_below(popup, tiny), None);
+        assert_eq!(
+            line_below(Rect::new(2, 1, 5, 2), tiny),
+            Some(Rect::new(2, 3, 5, 1))
+        );
+    }
+
+    use crate::addr_format::{AddrFmtOption, AddrWidth, SymbolMode};
+    use crate::model::{Frame, SignalEntry};
+    use shucks::ValueError;
+
+    /// Labels everything in puthex, which starts at 0x1000a4
+    struct PuthexSymbols;
+
+    impl SymbolSource for PuthexSymbols {
+        fn symbol_offset(&self, addr: u64) -> Option<String> {
+            (addr >= 0x1000a4).then(|| format!("puthex+0x{:x}", addr - 0x1000a4))
+        }
+    }
+
+    fn execution_lines(snapshot: &ExecutionSnapshot) -> Vec<String> {
+        super::execution_lines(snapshot, &AddrFormatter::default(), &PuthexSymbols)
+    }
+
+    fn instruction_lines(snapshot: &ExecutionSnapshot) -> Vec<String> {
+        super::instruction_lines(snapshot, &AddrFormatter::default(), &PuthexSymbols)
+    }
+
+    /// A stop in puthex as the hello_test session shows it, with `showbytes` on or off
+    fn puthex_stop(show_bytes: bool) -> ExecutionSnapshot {
+        let symbol = "hello_test.elf`puthex+0x0 at simple_system_common.c:21".to_string();
+        let inst = |addr: u32, text: &str, bytes: &[u8]| InstructionEntry {
+            addr,
+            text: text.to_string(),
+            bytes: show_bytes.then(|| bytes.to_vec()),
+            is_current: addr == 0x1000a4,
+            symbol: (addr == 0x1000a4).then(|| symbol.clone()),
+        };
+        ExecutionSnapshot::Stopped {
+            stop: StopKind::Step,
+            frame: Ok(Frame {
+                pc: 0x1000a4,
+                symbol: symbol.clone(),
+                instructions: Ok(vec![
+                    inst(0x1000a4, "c.addi sp, -16", &[0x41, 0x11]),
+                    inst(0x1000a6, "c.sw s0, 8(sp)", &[0x22, 0xc4]),
+                    inst(0x1000a8, "addi a1, zero, 7", &[0x93, 0x05, 0x70, 0x00]),
+                ]),
+            }),
+        }
+    }
+
+    // The strings below are what the model formatted itself before the snapshots were typed,
+    // the panes have to keep looking exactly like this
+
+    #[test]
+    fn test_execution_golden() {
+        assert_eq!(
+            execution_lines(&puthex_stop(false)),
+            vec![
+                "Process 1 stopped",
+                "* thread #1, stop reason = instruction step over",
+                "    frame #0: 0x1000a4 hello_test.elf`puthex+0x0 at simple_system_common.c:21",
+                "->  0x1000a4: c.addi sp, -16",
+                "    0x1000a6: c.sw s0, 8(sp)",
+                "    0x1000a8: addi a1, zero, 7",
+                "Target 0: (No executable module.) stopped.",
+            ]
+        );
+        assert_eq!(
+            instruction_lines(&puthex_stop(true)),
+            vec![
+                "->  0x1000a4: 41 11         c.addi sp, -16",
+                "    0x1000a6: 22 c4         c.sw s0, 8(sp)",
+                "    0x1000a8: 93 05 70 00   addi a1, zero, 7",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_disas_lines() {
+        let ExecutionSnapshot::Stopped {
+            frame: Ok(frame), ..
+        } = puthex_stop(true)
+        else {
+            unreachable!()
+        };
+        let instructions = frame.instructions.unwrap();
+        let mut breakpoints = BreakpointTable::default();
+        breakpoints.add(0x1000a6, "0x1000a6");
+        let disabled = breakpoints.add(0x1000a8, "0x1000a8");
+        breakpoints.set_enabled(disabled.id, false);
+        assert_eq!(
+            disas_lines(
+                "function puthex",
+                &instructions,
+                &breakpoints,
+                &AddrFormatter::default(),
+                &PuthexSymbols
+            ),
+            vec![
+                "Dump of assembler code for function puthex:",
+                "->  0x1000a4: 41 11         c.addi sp, -16",
+                "  B 0x1000a6: 22 c4         c.sw s0, 8(sp)",
+                "  b 0x1000a8: 93 05 70 00   addi a1, zero, 7",
+                "End of assembler dump.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bhits_lines() {
+        let mut breakpoints = BreakpointTable::default();
+        let id = breakpoints.add(0x100084, "putchar").id;
+        assert_eq!(
+            bhits_lines(breakpoints.get(id).unwrap(), "0x100084", &[], "ps"),
+            ["Breakpoint 1 (0x100084) has no recorded hits"]
+        );
+        breakpoints.record_hit(0x100084, 120);
+        breakpoints.record_hit(0x100084, 310);
+        assert_eq!(
+            bhits_lines(
+                breakpoints.get(id).unwrap(),
+                "0x100084",
+                &[Some(2400), Some(6200)],
+                "ns"
+            ),
+            [
+                "Breakpoint 1 (0x100084), 2 hits:",
+                "  time index 120: 2400 ns",
+                "  time index 310: 6200 ns",
+            ]
+        );
+        // without a waveform there's only the index
+        assert_eq!(
+            bhits_lines(breakpoints.get(id).unwrap(), "0x100084", &[], "ps")[1],
+            "  time index 120"
+        );
+    }
+
+    #[test]
+    fn test_execution_addrfmt() {
+        let mut addr_fmt = AddrFormatter::default();
+        addr_fmt.apply(AddrFmtOption::Pad(true));
+        addr_fmt.apply(AddrFmtOption::Symbols(SymbolMode::On));
+        assert_eq!(
+            super::execution_lines(&puthex_stop(false), &addr_fmt, &PuthexSymbols)[2..5],
+            [
+                // the frame line already names the symbol
+                "    frame #0: 0x001000a4 hello_test.elf`puthex+0x0 at simple_system_common.c:21",
+                "->  0x001000a4 <puthex+0x0>: c.addi sp, -16",
+                "    0x001000a6 <puthex+0x2>: c.sw s0, 8(sp)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_execution_golden_failures() {
+        assert_eq!(
+            execution_lines(&ExecutionSnapshot::Terminated),
+            vec!["Process has terminated"]
+        );
+        assert_eq!(
+            instruction_lines(&ExecutionSnapshot::Terminated),
+            vec!["Process has terminated"]
+        );
+
+        let no_pc = ExecutionSnapshot::Stopped {
+            stop: StopKind::Livelock,
+            frame: Err("connection reset".to_string()),
+        };
+        assert_eq!(
+            execution_lines(&no_pc),
+            vec![
+                "Process 1 stopped",
+                "* thread #1, stop reason = livelock (PC looping with no breakpoint)",
+                "Error getting PC: connection reset",
+                "Target 0: (No executable module.) stopped.",
+            ]
+        );
+        assert_eq!(instruction_lines(&no_pc), vec!["Error: connection reset"]);
+
+        let no_insts = ExecutionSnapshot::Stopped {
+            stop: StopKind::Step,
+            frame: Ok(Frame {
+                pc: 0x1000a4,
+                symbol: "hello_test.elf`puthex+0x0".to_string(),
+                instructions: Err("no ELF".to_string()),
+            }),
+        };
+        assert_eq!(
+            instruction_lines(&no_insts),
+            vec!["->  0x1000a4: <unable to get instructions>"]
+        );
+    }
+
+    fn source_entry(line_no: u64, text: Option<&str>, heat: Option<u64>) -> SourceEntry {
+        SourceEntry {
+            line_no,
+            text: text.map(str::to_string),
+            is_current: line_no == 21,
+            heat,
+            has_code: None,
+            column: None,
+        }
+    }
+
+    #[test]
+    fn test_source_golden() {
+        let snapshot = SourceSnapshot::Located {
+            file: "simple_system_common.c".to_string(),
+            entries: vec![
+                source_entry(21, Some("void puthex(uint32_t h) {"), None),
+                source_entry(28, Some("  for (int i = 0; i < 8; i++) {"), None),
+                source_entry(29, None, None),
+            ],
+            max_heat: 0,
+            next_lines_error: None,
+        };
+        assert_eq!(
+            source_lines(&snapshot),
+            vec![
+                "simple_system_common.c:21",
+                "",
+                "-> 21: void puthex(uint32_t h) {",
+                "   28:   for (int i = 0; i < 8; i++) {",
+                "   29: <source not available>",
+            ]
+        );
+
+        let hot = SourceSnapshot::Located {
+            file: "simple_system_common.c".to_string(),
+            entries: vec![
+                source_entry(21, Some("void puthex(uint32_t h) {"), Some(2)),
+                source_entry(28, Some("  for (int i = 0; i < 8; i++) {"), Some(16)),
+                source_entry(29, Some("}"), Some(0)),
+            ],
+            max_heat: 16,
+            next_lines_error: Some("bad line table".to_string()),
+        };
+        assert_eq!(
+            source_lines(&hot),
+            vec![
+                "simple_system_common.c:21",
+                "",
+                "-> ▂     2 21: void puthex(uint32_t h) {",
+                "   █    16 28:   for (int i = 0; i < 8; i++) {",
+                "         0 29: }",
+                "Error getting next lines: bad line table",
+            ]
+        );
+
+        // lines with code get a marker once they've been looked up
+        let mut entries = vec![
+            source_entry(21, Some("void puthex(uint32_t h) {"), None),
+            source_entry(22, Some(""), None),
+            source_entry(23, Some("  x = 1;"), None),
+        ];
+        entries[0].has_code = Some(true);
+        entries[1].has_code = Some(false);
+        let marked = SourceSnapshot::Located {
+            file: "simple_system_common.c".to_string(),
+            entries,
+            max_heat: 0,
+            next_lines_error: None,
+        };
+        assert_eq!(
+            source_lines(&marked)[2..],
+            [
+                "->*21: void puthex(uint32_t h) {",
+                "   22: ",
+                "   23:   x = 1;"
+            ]
+        );
+
+        // a caret under the column execution is at, when the line info has one
+        let mut entries = vec![
+            source_entry(21, Some("\tif (x) a(); else b();"), None),
+            source_entry(22, None, None),
+        ];
+        entries[0].column = Some(9);
+        entries[1].column = Some(3);
+        let at_column = SourceSnapshot::Located {
+            file: "simple_system_common.c".to_string(),
+            entries,
+            max_heat: 0,
+            next_lines_error: None,
+        };
+        assert_eq!(
+            source_lines(&at_column)[2..],
+            [
+                "-> 21: \tif (x) a(); else b();",
+                "       \t       ^",
+                "   22: <source not available>",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_source_golden_failures() {
+        assert_eq!(
+            source_lines(&SourceSnapshot::Terminated),
+            vec!["Process has terminated"]
+        );
+        assert_eq!(
+            source_lines(&SourceSnapshot::NoDebugInfo),
+            vec!["Source Code:", "No debug information available"]
+        );
+        assert_eq!(
+            source_lines(&SourceSnapshot::ElfWithoutDebugInfo {
+                elf: "fw.elf".to_string(),
+                missing: None
+            }),
+            vec!["Source Code:", "no debug info in fw.elf (compile with -g)"]
+        );
+        assert_eq!(
+            source_lines(&SourceSnapshot::ElfWithoutDebugInfo {
+                elf: "fw.elf".to_string(),
+                missing: Some("fw.debug not found".to_string())
+            }),
+            vec![
+                "Source Code:",
+                "no debug info in fw.elf: fw.debug not found"
+            ]
+        );
+        assert_eq!(
+            source_lines(&SourceSnapshot::Error("no ELF".to_string())),
+            vec!["Source Code:", "Error: no ELF"]
+        );
+    }
+
+    #[test]
+    fn test_signal_golden() {
+        let snapshot = SignalSnapshot::Values {
+            time: Some(40210),
+            exec_time: None,
+            unit: "ps".to_string(),
+            entries: vec![
+                SignalEntry {
+                    name: "TOP.ibex_simple_system.u_top.u_ibex_top.u_ibex_core.pc_id".to_string(),
+                    value: Ok("1000a4".to_string()),
+                    format: FormattingType::Hex,
+                    changed: false,
+                },
+                SignalEntry {
+                    name: "TOP.ibex_simple_system.clk_sys".to_string(),
+                    value: Ok("1".to_string()),
+                    format: FormattingType::Hex,
+                    changed: true,
+                },
+                SignalEntry {
+                    name: "TOP.ibex_simple_system.u_top.u_ibex_top.u_ibex_core.alu_adder_result_ex"
+                        .to_string(),
+                    value: Ok("-4".to_string()),
+                    format: FormattingType::SignedDecimal,
+                    changed: true,
+                },
+            ],
+        };
+        assert_eq!(
+            signal_lines(&snapshot),
+            vec![
+                "40210 ps",
+                "",
+                "TOP.ibex_simple_system.u_top.u_ibex_top.u_ibex_core.pc_id: 1000a4",
+                "TOP.ibex_simple_system.clk_sys: 1 *",
+                "TOP.ibex_simple_system.u_top.u_ibex_top.u_ibex_core.alu_adder_result_ex (sdec): -4 *",
+            ]
+        );
+        assert_eq!(
+            signal_lines(&SignalSnapshot::Values {
+                time: Some(0),
+                exec_time: None,
+                unit: "ns".to_string(),
+                entries: Vec::new()
+            }),
+            vec![
+                "0 ns",
+                "",
+                "No signals selected",
+                "Use 'addsig' to add signals"
+            ]
+        );
+        let unitless = SignalSnapshot::Values {
+            time: Some(7),
+            exec_time: None,
+            unit: String::new(),
+            entries: Vec::new(),
+        };
+        assert_eq!(signal_lines(&unitless)[0], "7");
+        let past_the_end = SignalSnapshot::Values {
+            time: None,
+            exec_time: None,
+            unit: "ps".to_string(),
+            entries: Vec::new(),
+        };
+        assert_eq!(signal_lines(&past_the_end)[0], "time unavailable");
+        let errors = SignalSnapshot::Values {
+            time: Some(0),
+            exec_time: None,
+            unit: "ps".to_string(),
+            entries: [
+                ("a", ValueError::NotLoaded),
+                ("b", ValueError::Undriven),
+                ("c", ValueError::Conversion("Real(1.5)".to_string())),
+            ]
+            .into_iter()
+            .map(|(name, e)| SignalEntry {
+                name: name.to_string(),
+                value: Err(e),
+                format: FormattingType::Hex,
+                changed: false,
+            })
+            .collect(),
+        };
+        assert_eq!(
+            signal_lines(&errors)[2..],
+            [
+                "a: <not loaded>",
+                "b: <undriven>",
+                "c: <unconvertible: Real(1.5)>"
+            ]
+        );
+        assert_eq!(
+            signal_lines(&SignalSnapshot::NoWaveform),
+            vec!["no waves found"]
+        );
+        assert_eq!(
+            signal_lines(&SignalSnapshot::Terminated),
+            vec!["Process has terminated"]
+        );
+    }
+
+    #[test]
+    fn test_signals_title() {
+        let pinned = SignalSnapshot::Values {
+            time: Some(3900),
+            exec_time: Some(4242),
+            unit: "ps".to_string(),
+            entries: Vec::new(),
+        };
+        assert_eq!(
+            signals_title(&pinned),
+            "Signals @ t=3900 (pinned, exec at 4242)"
+        );
+        assert_eq!(signals_title(&SignalSnapshot::NoWaveform), "Signals");
+    }
+
+    #[test]
+    fn test_resume_banner() {
+        let stats = ResumeStats {
+            pc_advances: 12,
+            changes_crossed: 40,
+            start_time_idx: 100,
+            end_time_idx: 163,
+        };
+        assert_eq!(
+            resume_banner(&stats),
+            "Advanced 12 PC changes (40 trace changes), time index 100 -> 163"
+        );
+    }
+
+    #[test]
+    fn test_register_line() {
+        let entry = |reg, value, last_change| RegisterEntry {
+            reg,
+            value,
+            last_change,
+        };
+        let fmt = AddrFormatter::default();
+        assert_eq!(
+            register_line(&entry(0, 0, None), &fmt),
+            "x0  zero = 0x00000000"
+        );
+        let change = RegChange {
+            time_idx: 402,
+            time: 4021,
+            previous: Some(0),
+        };
+        assert_eq!(
+            register_line(&entry(10, 0x13, Some(change)), &fmt),
+            "x10 a0   = 0x00000013  (changed @t=4021, was 0x0)"
+        );
+        let first = RegChange {
+            previous: None,
+            ..change
+        };
+        assert_eq!(
+            register_line(&entry(2, 0x80000, Some(first)), &fmt),
+            "x2  sp   = 0x00080000  (set @t=4021)"
+        );
+        // the column follows the width, the change follows pad
+        let wide = AddrFormatter {
+            width: AddrWidth::Bits64,
+            pad: true,
+            ..fmt
+        };
+        assert_eq!(
+            register_line(&entry(10, 0x13, Some(change)), &wide),
+            "x10 a0   = 0x0000000000000013  (changed @t=4021, was 0x0000000000000000)"
+        );
+    }
+
+    #[test]
+    fn test_sigchanged_lines() {
+        let row = |name: &str, changed: bool, changes: Option<usize>| SignalChangeRow {
+            name: name.to_string(),
+            changed,
+            changes,
+        };
+        let rows = [
+            row("top.clk", true, Some(40)),
+            row("top.rst_n", false, Some(0)),
+            row("top.core.valid", true, Some(1)),
+            row("top.core.stall", false, None),
+            row("top.core.pc", true, None),
+        ];
+        assert_eq!(
+            sigchanged_lines((100, 120), &rows),
+            [
+                "Changed between time index 100 and 120 (3 of 5):",
+                "  top.clk  (40 changes)",
+                "  top.core.valid  (1 change)",
+                "  top.core.pc",
+                "Unchanged (2):",
+                "  top.rst_n",
+                "  top.core.stall",
+            ]
+        );
+        assert_eq!(sigchanged_lines((0, 1), &[]), ["No signals selected"]);
+    }
+
+    #[test]
+    fn test_sigstats_lines() {
+        use shucks::SignalStats;
+        let row = |name: &str, total: usize, in_window: usize| SignalStatsRow {
+            name: name.to_string(),
+            stats: SignalStats {
+                total,
+                in_window,
+                first: Some(0),
+                last: Some(total.saturating_sub(1) as u32),
+            },
+            first_time: Some(0),
+            last_time: Some(total.saturating_sub(1) as u64 * 10),
+        };
+        let rows = [
+            row("top.rst_n", 1, 0),
+            row("top.core.pc", 900, 12),
+            row("top.clk", 2000, 40),
+        ];
+
+        let lines = sigstats_lines(&rows, Some((100, 120)), "ns");
+        assert_eq!(lines[0], "Changes between time index 100 and 120");
+        assert_eq!(
+            lines[2..],
+            [
+                "top.clk          2000        40            0 ns        19990 ns",
+                "top.core.pc       900        12            0 ns         8990 ns",
+                "top.rst_n           1         0            0 ns            0 ns  (constant)",
+            ]
+        );
+        // every column lines up under the header
+        assert_eq!(lines[1].len(), lines[2].len());
+        assert_eq!(
+            sigstats_lines(&[], None, "ps"),
+            [
+                "Changes over the whole trace",
+                "signal     total    window           first            last"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reghist() {
+        let history = [(10, 0x1000), (25, 0x0ff0), (40, 0x1010)];
+        assert_eq!(
+            reghist_lines(2, &history, &AddrFormatter::default()),
+            [
+                "x2 (sp): 3 changes between time index 10 and 40",
+                "  min 0x00000ff0  max 0x00001010",
+                "  first 0x00001000  last 0x00001010",
+            ]
+        );
+        assert_eq!(
+            reghist_lines(10, &[], &AddrFormatter::default()),
+            ["x10 (a0) has no known value in the trace"]
+        );
+        assert_eq!(
+            reghist_csv(&history[..2]),
+            "time_idx,value\n10,0x00001000\n25,0x00000ff0\n"
+        );
+    }
+
+    #[test]
+    fn test_notification_lines() {
+        use crate::notifications::Severity;
+        use std::time::Duration;
+
+        let now = Instant::now() + Duration::from_secs(100);
+        let mut elf = Notification::new(Severity::Warning, "elfwatch", "hello.elf has changed");
+        elf.timestamp = now - Duration::from_secs(42);
+        let mut dang = Notification::new(Severity::Error, "dang", "dang stopped");
+        dang.timestamp = now;
+        dang.repeats = 2;
+
+        assert_eq!(notification_line(&elf), "warning: hello.elf has changed");
+        assert_eq!(
+            notification_lines([&elf, &dang].into_iter(), now),
+            [
+                "    42s ago  [elfwatch] warning: hello.elf has changed",
+                "     0s ago  [dang] error: dang stopped (x3)",
+            ]
+        );
+        assert_eq!(
+            notification_lines(std::iter::empty(), now),
+            ["No notifications"]
+        );
+    }
+
+    #[test]
+    fn test_output_route() {
+        assert_eq!(output_route(""), OutputRoute::History);
+        assert_eq!(output_route("1234\n"), OutputRoute::History);
+
+        let lines = |count: usize| "100a4 3\n".repeat(count);
+        assert_eq!(
+            output_route(&lines(HISTORY_OUTPUT_LINES)),
+            OutputRoute::History
+        );
+        assert_eq!(
+            output_route(&lines(HISTORY_OUTPUT_LINES + 1)),
+            OutputRoute::Modal
+        );
+        // a handful of lines can still be too much to scroll past
+        let wide = "x".repeat(HISTORY_OUTPUT_BYTES);
+        assert_eq!(output_route(&format!("{wide}\n")), OutputRoute::Modal);
+        // a capped dump from dang
+        assert_eq!(
+            output_route(&"x".repeat(dang::bounded_output::DEFAULT_LIMIT)),
+            OutputRoute::Modal
+        );
+    }
+
+    #[test]
+    fn test_redraw_flag_coalesces() {
+        let flag = RedrawFlag::default();
+        assert!(!flag.take());
+
+        // two events from different owners before the loop looks, one redraw
+        let logger_side = flag.clone();
+        logger_side.mark();
+        flag.mark();
+        assert!(flag.take());
+        assert!(!flag.take());
+
+        logger_side.mark();
+        assert!(flag.take());
+    }
+}
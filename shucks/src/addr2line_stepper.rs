@@ -1,7 +1,7 @@
 use anyhow::Result;
 use object::{Object, ObjectSection};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fs::File,
     io::{BufRead, BufReader},
     path::{Path, PathBuf},
@@ -13,9 +13,49 @@ use std::{
 pub struct SourceLine {
     pub path: PathBuf,
     pub line: u64,            // 1-based
+    pub column: Option<u64>,  // 1-based, None if the line info doesn't say
     pub text: Option<String>, // None if the file can't be read
 }
 
+/// One row of a line program, in runtime address space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LineRow {
+    line: u64,
+    /// 1-based, `None` for the left edge, which is what a producer without columns emits
+    column: Option<u64>,
+    address: u64,
+}
+
+/// Addresses of the rows in `rows` on `line`, sorted and without repeats.
+///
+/// With a `column`, only the statement it falls in: the rows starting at the nearest column at
+/// or before it, or at the line's first column if it's before all of them. A line without
+/// column info, or a column of 0, gives the whole line
+fn addresses_on_line(
+    rows: impl IntoIterator<Item = LineRow>,
+    line: u64,
+    column: Option<u64>,
+) -> Vec<u64> {
+    let on_line: Vec<LineRow> = rows.into_iter().filter(|row| row.line == line).collect();
+    let statement = column.filter(|&column| column != 0).and_then(|column| {
+        let columns = on_line.iter().filter_map(|row| row.column);
+        columns
+            .clone()
+            .filter(|&start| start <= column)
+            .max()
+            .or_else(|| columns.min())
+    });
+
+    let mut addrs: Vec<u64> = on_line
+        .iter()
+        .filter(|row| statement.is_none() || row.column == statement)
+        .map(|row| row.address)
+        .collect();
+    addrs.sort_unstable();
+    addrs.dedup();
+    addrs
+}
+
 /// addr2line logic holder
 pub struct Addr2lineStepper {
     ctx: addr2line::Context<gimli::EndianSlice<'static, gimli::RunTimeEndian>>,
@@ -106,22 +146,55 @@ impl Addr2lineStepper {
     }
 
     /// Resolve and return the *current* source line for `runtime_pc`.
+    /// The column comes along when the line info has one.
     pub fn current_line(&self, runtime_pc: u64) -> Result<Option<SourceLine>> {
-        match self.map_addr(runtime_pc)? {
-            Some((path, line)) => Ok(Some(SourceLine {
-                text: self.read_line_1_based(&path, line as usize),
-                path,
-                line,
-            })),
-            None => Ok(None),
-        }
+        let file_addr = runtime_pc.saturating_sub(self.load_bias);
+        let Some(loc) = self.ctx.find_location(file_addr)? else {
+            return Ok(None);
+        };
+        let (Some(file), Some(line)) = (loc.file, loc.line) else {
+            return Ok(None);
+        };
+        let path = PathBuf::from(file);
+        Ok(Some(SourceLine {
+            text: self.read_line_1_based(&path, line as usize),
+            path,
+            line: line as u64,
+            // 0 is the left edge, which says nothing about where on the line we are
+            column: loc.column.filter(|&column| column != 0).map(u64::from),
+        }))
     }
 
     /// Find addresses that correspond to a specific source file and line number.
     /// Returns a vector of runtime addresses that map to the given file:line.
-    pub fn find_addresses_for_line(&self, file_path: &Path, target_line: u64) -> Result<Vec<u64>> {
-        let mut addrs = Vec::new();
+    ///
+    /// With a `column`, only the addresses of the statement at that column.
+    pub fn find_addresses_for_line(
+        &self,
+        file_path: &Path,
+        target_line: u64,
+        column: Option<u64>,
+    ) -> Result<Vec<u64>> {
+        let mut rows = Vec::new();
+        self.for_each_row_in_file(file_path, |row| {
+            if row.line == target_line {
+                rows.push(row);
+            }
+        })?;
+        Ok(addresses_on_line(rows, target_line, column))
+    }
 
+    /// Map every runtime address in `file_path`'s line table to its 1-based line.
+    pub fn line_table_for_file(&self, file_path: &Path) -> Result<BTreeMap<u64, u64>> {
+        let mut table = BTreeMap::new();
+        self.for_each_row_in_file(file_path, |row| {
+            table.insert(row.address, row.line);
+        })?;
+        Ok(table)
+    }
+
+    /// Walk the DWARF line programs and call `f` for every row in `file_path`
+    fn for_each_row_in_file(&self, file_path: &Path, mut f: impl FnMut(LineRow)) -> Result<()> {
         let inp_file = file_path.to_path_buf();
         let is_absolute = inp_file.is_absolute();
 
@@ -144,9 +217,6 @@ impl Addr2lineStepper {
                         Some(l) => l.get() as u64,
                         None => continue,
                     };
-                    if row_line != target_line {
-                        continue;
-                    }
 
                     // Resolve file path for this row
                     let file_entry = match row.file(header) {
@@ -180,18 +250,21 @@ impl Addr2lineStepper {
                         || !is_absolute && resolved.ends_with(&inp_file)
                     {
                         let file_addr = row.address();
-                        let runtime_addr = file_addr.saturating_add(self.load_bias);
-                        addrs.push(runtime_addr);
+                        let column = match row.column() {
+                            gimli::ColumnType::LeftEdge => None,
+                            gimli::ColumnType::Column(column) => Some(column.get()),
+                        };
+                        f(LineRow {
+                            line: row_line,
+                            column,
+                            address: file_addr.saturating_add(self.load_bias),
+                        });
                     }
                 }
             }
         }
 
-        // Remove duplicates and sort
-        addrs.sort_unstable();
-        addrs.dedup();
-
-        Ok(addrs)
+        Ok(())
     }
 
     /// Return the next `n` **unique** source lines *after* `runtime_pc`, using your
@@ -233,6 +306,7 @@ impl Addr2lineStepper {
                 out.push(SourceLine {
                     path: path.clone(),
                     line,
+                    column: None,
                     text,
                 });
                 last_emitted = Some((path, line));
@@ -264,6 +338,7 @@ impl Addr2lineStepper {
             result.push(SourceLine {
                 path: path.to_path_buf(),
                 line: line_num,
+                column: None,
                 text,
             });
         }
@@ -274,7 +349,7 @@ impl Addr2lineStepper {
     // --- helpers -------------------------------------------------------------
 
     /// Map a runtime address to (path, line) using addr2line.
-    fn map_addr(&self, runtime_addr: u64) -> Result<Option<(PathBuf, u64)>> {
+    pub fn map_addr(&self, runtime_addr: u64) -> Result<Option<(PathBuf, u64)>> {
         let file_addr = runtime_addr.saturating_sub(self.load_bias);
         if let Some(loc) = self.ctx.find_location(file_addr)? {
             if let (Some(file), Some(line)) = (loc.file, loc.line) {
@@ -402,7 +477,15 @@ mod tests {
         // If we found any mapping, test the next_lines functionality
         if found_any {
             // Test next_lines_from_instructions with some sequential addresses
-            let next_addrs = (0..10).map(|i| 0x00100084 + i * 4); // RISC-V instructions are 4 bytes
+            let elf = goblin::elf::Elf::parse(&elf_bytes)?;
+            let text = elf
+                .section_headers
+                .iter()
+                .find(|sh| elf.shdr_strtab.get_at(sh.sh_name) == Some(".text"))
+                .expect("hello_test.elf has a .text section");
+            let text_bytes = &elf_bytes[text.file_range().expect("no file range for .text")];
+            // RV32C is in use, so instructions are 2 or 4 bytes apart
+            let next_addrs = crate::client::InstructionAddrs::new(text_bytes, text.sh_addr, 10);
             let next_lines = stepper.next_lines_from_instructions(0x00100084, next_addrs, 3)?;
 
             println!("Found {} next source lines", next_lines.len());
@@ -432,7 +515,7 @@ mod tests {
 
         // Pick lines that should generate code: puts/puthex/putchar lines (1-based)
         for &line in &[12u64, 13u64, 16u64] {
-            let addrs = stepper.find_addresses_for_line(&target_path, line)?;
+            let addrs = stepper.find_addresses_for_line(&target_path, line, None)?;
             assert!(
                 !addrs.is_empty(),
                 "Expected at least one address for {}:{}",
@@ -462,6 +545,37 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_column_picks_one_statement() {
+        // `if (x) do_a(); else do_b();` on line 7, the test at column 5, the calls at 12 and 25
+        let row = |line, column, address| LineRow {
+            line,
+            column,
+            address,
+        };
+        let rows = [
+            row(6, None, 0x100),
+            row(7, Some(5), 0x104),
+            row(7, Some(12), 0x108),
+            row(7, Some(12), 0x10c),
+            row(7, Some(25), 0x110),
+            row(7, Some(5), 0x114),
+            row(8, Some(1), 0x118),
+        ];
+
+        let whole_line = addresses_on_line(rows, 7, None);
+        assert_eq!(whole_line, [0x104, 0x108, 0x10c, 0x110, 0x114]);
+        assert_eq!(addresses_on_line(rows, 7, Some(0)), whole_line);
+
+        assert_eq!(addresses_on_line(rows, 7, Some(12)), [0x108, 0x10c]);
+        // anywhere in `do_b();` is that statement
+        assert_eq!(addresses_on_line(rows, 7, Some(27)), [0x110]);
+        // before the first statement, the first statement
+        assert_eq!(addresses_on_line(rows, 7, Some(1)), [0x104, 0x114]);
+        // no columns to pick from
+        assert_eq!(addresses_on_line(rows, 6, Some(3)), [0x100]);
+    }
+
     #[test]
     fn test_list_dwarf_files() -> Result<()> {
         // Load the test ELF file - go up one directory from crate root to workspace root
@@ -1,34 +1,473 @@
 use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
     fs,
     io::{Read, Write},
     net::TcpStream,
-    path::PathBuf,
+    ops::{Range, RangeInclusive},
+    path::{Path, PathBuf},
 };
 
 use crate::{
     addr2line_stepper::Addr2lineStepper,
-    commands::{Base, GdbCommand, Resume},
-    response::{GdbResponse, RawGdbResponse},
+    commands::{Base, GdbCommand, Resume, ThreadOp},
+    debuglink::DebugLink,
+    decoder::PacketDecoder,
+    elf_watch::ElfFingerprint,
+    findinst::{
+        parse_pc_trace, pc_trace_command, FindOutcome, InstPredicate, SearchDirection,
+        PC_TRACE_BATCH,
+    },
+    response::{GdbResponse, RawGdbResponse, StopReason, ThreadId},
+    session::{InitReport, Session},
+    symbolize::{CacheStats, SymbolCache},
     wavetracker::WaveformTracker,
     Packet,
 };
+use dang::breakpoints::BreakpointEntry;
+pub use dang::disasm::format_bytes_column;
+use dang::skip_ranges::SkipRange;
+use dang::stop_context::StopContext;
+use dang::waveloader::WaveLoadOptions;
 use goblin::elf::Elf;
 use raki::{Decode, Isa};
 
 pub struct Client {
     strm: TcpStream,
     packet_scratch: [u8; 4096],
-    response_buffer: Vec<u8>,
+    decoder: PacketDecoder,
+    /// Packets the stub sent that didn't answer anything, oldest first, see
+    /// [`Client::take_async_output`]
+    async_output: VecDeque<String>,
     elf_info: Option<ElfInfo>,
     addr2line_stepper: Option<Addr2lineStepper>,
     pub wave_tracker: Option<WaveformTracker>,
     cached_state: CachedState,
+    line_heat_cache: HashMap<PathBuf, LineHeatCache>,
+    last_stop_reason: Option<StopReason>,
+    /// Connected as a read-only observer, someone else is driving the target
+    observer: bool,
+    packet_stats: PacketStats,
+    /// File name of the loaded ELF, the module part of symbolized addresses
+    module_name: Option<String>,
+    symbol_cache: std::sync::Mutex<SymbolCache>,
+    /// `func+0xoff` alone for [`Client::symbol_offset`], empty where no symbol covers the address
+    symbol_offset_cache: std::sync::Mutex<SymbolCache>,
+    /// Extra directories to look for `.gnu_debuglink` files in
+    debug_search_paths: Vec<PathBuf>,
+    debug_info: DebugInfo,
+    /// What the stub advertised in `qSupported`
+    stub_features: Vec<String>,
+    /// The thread, a hart for dang, that register and memory accesses and resumes go to
+    current_thread: ThreadId,
+    /// Ask for everything about a stop with one `stop_context`, cleared once the stub turns
+    /// out not to have it
+    use_stop_context: bool,
+    /// How long a reply gets to arrive before the read gives up, see
+    /// [`Client::set_reply_timeout`]
+    reply_timeout: std::time::Duration,
+    /// Instructions the last [`Client::get_current_and_next_inst`] asked for. A stop's
+    /// `stop_context` brings back that much code, and the source preview looks at least as far
+    instruction_window: usize,
+}
+
+/// Where line info comes from
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum DebugInfo {
+    /// No ELF loaded yet
+    #[default]
+    Unloaded,
+    /// The ELF carries its own DWARF
+    Embedded,
+    /// A separate debug file, found through the ELF's `.gnu_debuglink` or given explicitly
+    Separate(PathBuf),
+    /// The ELF is stripped and its debug file couldn't be used, and why
+    Missing(String),
+    /// The ELF has no `.debug_line` and doesn't name a debug file, it was built without `-g`
+    Absent,
+}
+
+impl std::fmt::Display for DebugInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DebugInfo::Unloaded => write!(f, "no ELF loaded"),
+            DebugInfo::Embedded => write!(f, "embedded in the ELF"),
+            DebugInfo::Separate(path) => write!(f, "{}", path.display()),
+            DebugInfo::Missing(reason) => write!(f, "none ({reason})"),
+            DebugInfo::Absent => write!(f, "none (built without -g)"),
+        }
+    }
+}
+
+/// Everything [`Client`] counts, for profiling
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ClientStats {
+    pub packets: PacketStats,
+    pub symbol_cache: CacheStats,
+}
+
+/// Running totals of the packets exchanged with the stub
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PacketStats {
+    pub packets_sent: u64,
+    pub bytes_sent: u64,
+    pub packets_received: u64,
+    pub bytes_received: u64,
+}
+
+impl std::fmt::Display for PacketStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "sent {} packets ({} bytes), received {} packets ({} bytes)",
+            self.packets_sent, self.bytes_sent, self.packets_received, self.bytes_received
+        )
+    }
+}
+
+/// How far ahead of the PC the source preview decodes at the least, however small the
+/// instruction window is
+const SOURCE_PREVIEW_INSTRUCTIONS: usize = 16;
+
+/// Instructions in the window until something asks for another size, what a stop's
+/// `stop_context` brings back before the first [`Client::get_current_and_next_inst`]
+pub const DEFAULT_INSTRUCTION_WINDOW: usize = 4;
+
+/// Most instructions the instruction window ever takes in
+pub const MAX_INSTRUCTION_WINDOW: usize = 64;
+
+/// Longest RISC-V instruction, in bytes
+const MAX_INSTRUCTION_LEN: usize = 4;
+
+/// e_flags bit for ELFs that use the C extension
+const EF_RISCV_RVC: u32 = 0x1;
+
+/// dang reports a PC livelock as a stop with this signal (SIGXCPU)
+pub const LIVELOCK_SIGNAL: u8 = 24;
+
+/// dang reports a violated assertion as a stop with this signal (SIGABRT)
+pub const ASSERTION_SIGNAL: u8 = 6;
+
+/// What a stop reply that turned up with no resume in flight is filed under in
+/// [`Client::take_async_output`]
+pub const STALE_STOP: &str = "stale stop (possibly from retransmission)";
+
+/// dang answers writes outside its scratch region with this errno
+const EROFS: u8 = 30;
+
+/// dang answers z0 for an address with no breakpoint with this errno
+const EINVAL: u8 = 22;
+
+/// Largest payload sent in one M/X packet, keeps us well inside the packet buffer
+const MAX_WRITE_CHUNK: usize = 1024;
+
+/// Changes asked for per `reg_history` round trip
+const REG_HISTORY_PAGE: usize = 4096;
+
+/// How long a reply gets to arrive unless [`Client::set_reply_timeout`] says otherwise
+pub const DEFAULT_REPLY_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// How long to wait before asking again while dang is still building its PC index
+const PC_INDEX_RETRY: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// A memory write was refused because the range isn't writable on the target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadOnlyMemory {
+    pub addr: u32,
+    pub len: usize,
+}
+
+impl std::fmt::Display for ReadOnlyMemory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "memory at 0x{:x}..0x{:x} is read-only on the target",
+            self.addr,
+            self.addr as usize + self.len
+        )
+    }
+}
+
+impl std::error::Error for ReadOnlyMemory {}
+
+/// The stub answered a query with an empty reply, the RSP's way of saying it doesn't do it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedByStub {
+    /// The packet's name, e.g. `qXfer:exec-file:read`
+    pub command: String,
+}
+
+impl UnsupportedByStub {
+    fn new(command: &Base) -> Self {
+        Self {
+            command: command.base_str().to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for UnsupportedByStub {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the stub doesn't support {}", self.command)
+    }
+}
+
+impl std::error::Error for UnsupportedByStub {}
+
+/// One sub-command of a [`Client::send_monitor_batch`] failed, or has no output in the reply
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchCommandError {
+    pub command: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for BatchCommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "monitor {} failed: {}", self.command, self.message)
+    }
+}
+
+impl std::error::Error for BatchCommandError {}
+
+/// Tried to resume, break or write through an observer connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObserverReadOnly;
+
+impl std::fmt::Display for ObserverReadOnly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "observer connection is read-only")
+    }
+}
+
+impl std::error::Error for ObserverReadOnly {}
+
+/// What a Z0 or z0 did to the target's breakpoints
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakpointChange {
+    /// The breakpoint went in or came out
+    Changed,
+    /// Set where the target already had one, nothing changed
+    AlreadySet,
+    /// Removed where the target had none, nothing changed
+    NotSet,
+}
+
+/// PC -> line mappings for one source file. The trace is immutable, so these never go stale
+#[derive(Default)]
+struct LineHeatCache {
+    /// runtime address -> line, straight from the DWARF line table
+    rows: BTreeMap<u64, u64>,
+    /// visited pc -> line, `None` if the pc belongs to some other file
+    pcs: HashMap<u64, Option<u64>>,
+}
+
+/// When a register last changed, as reported by dang's `reg_last_change` monitor command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegChange {
+    pub time_idx: u64,
+    /// Simulation time at `time_idx`, in the waveform's timescale
+    pub time: u64,
+    /// The value before the change, `None` if this was the register's first value
+    pub previous: Option<u32>,
+}
+
+/// Parse a `time` reply, `<time> [unit]`
+fn parse_time(line: &str) -> Result<(u64, String), String> {
+    let (time, unit) = line.split_once(' ').unwrap_or((line, ""));
+    let time = time
+        .parse::<u64>()
+        .map_err(|_| format!("Target couldn't tell the time: {line}"))?;
+    Ok((time, unit.trim().to_string()))
+}
+
+/// Where dang's `goto_time` monitor command put the cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeJump {
+    pub time_idx: u64,
+    /// Simulation time at `time_idx`, in the waveform's timescale
+    pub time: u64,
+    pub pc: u32,
+}
+
+/// Parse a `goto_time` reply, `<time_idx> <time> <pc in hex>`
+fn parse_time_jump(line: &str) -> Result<TimeJump, String> {
+    let refused = || format!("Target refused goto_time: {line}");
+    let mut parts = line.split_whitespace();
+    let mut next = |radix| {
+        parts
+            .next()
+            .and_then(|part| u64::from_str_radix(part, radix).ok())
+            .ok_or_else(refused)
+    };
+    let (time_idx, time, pc) = (next(10)?, next(10)?, next(16)?);
+    Ok(TimeJump {
+        time_idx,
+        time,
+        pc: u32::try_from(pc).map_err(|_| refused())?,
+    })
+}
+
+/// A page of register history: where the next page starts, `None` at the end, and each
+/// `(time_idx, value)`, the value `None` where the trace has x or z bits
+type RegHistoryPage = (Option<u64>, Vec<(u64, Option<u32>)>);
+
+/// Parse a `reg_history` reply, the next page's start or `end`, then one
+/// `<time_idx> <value in hex|x>` per line
+fn parse_reg_history(output: &str) -> Result<RegHistoryPage, String> {
+    let mut lines = output.lines().map(str::trim).filter(|l| !l.is_empty());
+    let next = match lines.next() {
+        Some("end") => None,
+        Some(line) if line.starts_with("usage") => {
+            return Err(format!("Target rejected reg_history: {line}"))
+        }
+        Some(line) => Some(
+            line.parse::<u64>()
+                .map_err(|_| format!("Malformed reg_history page start: {line}"))?,
+        ),
+        None => return Err("Empty reg_history reply".to_string()),
+    };
+    let samples = lines
+        .map(|line| {
+            let malformed = || format!("Malformed reg_history line: {line}");
+            let (time_idx, value) = line.split_once(' ').ok_or_else(malformed)?;
+            let time_idx = time_idx.parse::<u64>().map_err(|_| malformed())?;
+            let value = match value {
+                "x" => None,
+                hex => Some(u32::from_str_radix(hex, 16).map_err(|_| malformed())?),
+            };
+            Ok((time_idx, value))
+        })
+        .collect::<Result<_, String>>()?;
+    Ok((next, samples))
+}
+
+/// Parse one `<reg> <time_idx> <time> <previous|->` or `<reg> none` line
+fn parse_reg_change(line: &str) -> Result<(usize, Option<RegChange>), String> {
+    let malformed = || format!("Malformed register change: {line}");
+    let mut parts = line.split_whitespace();
+    let reg = parts
+        .next()
+        .and_then(|reg| reg.parse::<usize>().ok())
+        .ok_or_else(malformed)?;
+    let change = match parts.next().ok_or_else(malformed)? {
+        "none" => None,
+        time_idx => {
+            let time_idx = time_idx.parse::<u64>().map_err(|_| malformed())?;
+            let time = parts
+                .next()
+                .and_then(|time| time.parse::<u64>().ok())
+                .ok_or_else(malformed)?;
+            let previous = match parts.next().ok_or_else(malformed)? {
+                "-" => None,
+                hex => Some(u32::from_str_radix(hex, 16).map_err(|_| malformed())?),
+            };
+            Some(RegChange {
+                time_idx,
+                time,
+                previous,
+            })
+        }
+    };
+    Ok((reg, change))
+}
+
+/// What the target did during the last step or continue, as reported by `resume_stats`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResumeStats {
+    /// How many times the PC moved on to its next change in the trace
+    pub pc_advances: u64,
+    /// Entries of the target's merged PC/GPR change list crossed
+    pub changes_crossed: u64,
+    pub start_time_idx: u64,
+    pub end_time_idx: u64,
+}
+
+/// Parse a `<pc_advances> <changes_crossed> <start_time_idx> <end_time_idx>` line
+fn parse_resume_stats(line: &str) -> Result<ResumeStats, String> {
+    let fields = line
+        .split_whitespace()
+        .map(str::parse::<u64>)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format!("Malformed resume stats: {line}"))?;
+    match fields[..] {
+        [pc_advances, changes_crossed, start_time_idx, end_time_idx] => Ok(ResumeStats {
+            pc_advances,
+            changes_crossed,
+            start_time_idx,
+            end_time_idx,
+        }),
+        _ => Err(format!("Malformed resume stats: {line}")),
+    }
+}
+
+/// An invariant dang checks after every step, as listed by `assert_list`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assertion {
+    pub id: u32,
+    /// What has to hold, e.g. `x2 > 0x20000000` or `pc outside 0x0-0xff`
+    pub check: String,
+}
+
+/// The assertion that stopped the target, as reported by `last_assert`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssertionViolation {
+    pub id: u32,
+    pub time_idx: u64,
+    /// The register or PC value that broke the check
+    pub observed: u32,
+    pub check: String,
+}
+
+/// What the last resume left behind, see [`Client::stop_report`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StopReport {
+    pub stats: ResumeStats,
+    /// The most recent assertion to stop the target, if any has
+    pub assertion: Option<AssertionViolation>,
+}
+
+/// Parse one `<id> <check>` line
+fn parse_assertion(line: &str) -> Result<Assertion, String> {
+    let (id, check) = line
+        .trim()
+        .split_once(' ')
+        .ok_or_else(|| format!("Malformed assertion: {line}"))?;
+    let id = id
+        .parse::<u32>()
+        .map_err(|_| format!("Malformed assertion: {line}"))?;
+    Ok(Assertion {
+        id,
+        check: check.to_string(),
+    })
+}
+
+/// Parse a `<id> <time_idx> <observed> <check>` or `none` line
+fn parse_violation(line: &str) -> Result<Option<AssertionViolation>, String> {
+    let line = line.trim();
+    if line == "none" {
+        return Ok(None);
+    }
+    let malformed = || format!("Malformed assertion violation: {line}");
+    let mut parts = line.splitn(4, ' ');
+    let mut field = || parts.next().ok_or_else(malformed);
+    let id = field()?.parse::<u32>().map_err(|_| malformed())?;
+    let time_idx = field()?.parse::<u64>().map_err(|_| malformed())?;
+    let observed = u32::from_str_radix(field()?, 16).map_err(|_| malformed())?;
+    let check = field()?.to_string();
+    Ok(Some(AssertionViolation {
+        id,
+        time_idx,
+        observed,
+        check,
+    }))
 }
 
 #[derive(Default, Clone)]
 pub struct CachedState {
     pc: Option<PC>,
     time_idx: Option<u64>,
+    /// Only filled in by a `stop_context`
+    gprs: Option<[u32; 32]>,
+    /// Target memory from the PC on, only filled in by a `stop_context`
+    code: Option<Vec<u8>>,
 }
 
 #[derive(Copy, Clone)]
@@ -48,17 +487,217 @@ impl PC {
 
 use raki::Instruction as RVInst;
 
-pub struct Instruction(RVInst, PC);
+pub struct Instruction {
+    /// `None` if the bytes didn't decode; we still keep them around so they can be shown
+    inst: Option<RVInst>,
+    pc: PC,
+    bytes: [u8; 4],
+    len: usize,
+}
 
 impl std::fmt::Display for Instruction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        match &self.inst {
+            Some(inst) => write!(f, "{inst}"),
+            None => write!(
+                f,
+                ".word 0x{:04x}  <undecodable>",
+                u16::from_le_bytes([self.bytes[0], self.bytes[1]])
+            ),
+        }
     }
 }
 
 impl Instruction {
     pub fn pc(&self) -> &PC {
-        &self.1
+        &self.pc
+    }
+
+    /// Raw encoding of this instruction, 2 or 4 bytes
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    pub fn is_decoded(&self) -> bool {
+        self.inst.is_some()
+    }
+
+    /// Raw bytes as a fixed width hex column, see [`format_bytes_column`]
+    pub fn bytes_column(&self) -> String {
+        format_bytes_column(self.bytes())
+    }
+
+    /// The mnemonic, e.g. `addi` or `c.jal`. `None` if the bytes didn't decode
+    pub fn mnemonic(&self) -> Option<String> {
+        let text = self.inst.as_ref()?.to_string();
+        text.split_whitespace().next().map(String::from)
+    }
+
+    /// The register this instruction writes, if any
+    pub fn rd(&self) -> Option<usize> {
+        self.inst.as_ref()?.rd
+    }
+
+    /// The encoding as an integer, compressed instructions zero extended
+    pub fn encoding(&self) -> u32 {
+        let mut raw = [0u8; 4];
+        raw[..self.len].copy_from_slice(self.bytes());
+        u32::from_le_bytes(raw)
+    }
+}
+
+/// Decode the instruction at the front of `bytes`, along with its length in bytes.
+///
+/// Instruction length is picked from the low two bits of the first halfword. `None` if the
+/// bytes don't decode or there aren't enough of them
+fn decode_one(bytes: &[u8]) -> Option<(RVInst, usize)> {
+    let is_compressed = *bytes.first()? & 0b11 != 0b11;
+    if is_compressed {
+        let uu16 = u16::from_le_bytes(bytes.get(..2)?.try_into().unwrap());
+        uu16.decode(Isa::Rv32)
+            .inspect_err(|e| log::debug!("u16 err is {e:?}, 0x{uu16:x}"))
+            .ok()
+            .map(|inst| (inst, 2))
+    } else {
+        let uu32 = u32::from_le_bytes(bytes.get(..4)?.try_into().unwrap());
+        uu32.decode(Isa::Rv32)
+            .inspect_err(|e| log::debug!("u32 err is {e:?}, 0x{uu32:x}"))
+            .ok()
+            .map(|inst| (inst, 4))
+    }
+}
+
+/// Decode a window of instruction bytes starting at `pc`.
+///
+/// Anything that doesn't decode becomes a 2 byte placeholder so one bad halfword doesn't end
+/// the listing
+fn decode_instruction_window(bytes: &[u8], pc: PC, count: usize) -> Vec<Instruction> {
+    let mut rv = Vec::new();
+    let mut start = 0;
+    while rv.len() < count && start + 2 <= bytes.len() {
+        // the last instruction before the end of `bytes` can be shorter than a whole word
+        let rest = &bytes[start..];
+        let mut word = [0u8; MAX_INSTRUCTION_LEN];
+        let len = rest.len().min(MAX_INSTRUCTION_LEN);
+        word[..len].copy_from_slice(&rest[..len]);
+        let inst = decode_instruction(&word, pc.add(start as u32));
+        if inst.len > rest.len() {
+            break;
+        }
+        start += inst.len;
+        rv.push(inst);
+    }
+    rv
+}
+
+/// Decode the instruction at the front of `bytes`, which sits at `pc`. Something that doesn't
+/// decode comes back as a 2 byte placeholder. `bytes` needs at least 4 bytes in it
+pub fn decode_instruction(bytes: &[u8], pc: PC) -> Instruction {
+    let mut raw = [0u8; 4];
+    match decode_one(bytes) {
+        Some((inst, len)) => {
+            log::debug!("{inst}");
+            raw[..len].copy_from_slice(&bytes[..len]);
+            Instruction {
+                inst: Some(inst),
+                pc,
+                bytes: raw,
+                len,
+            }
+        }
+        None => {
+            log::error!("failed to decode instruction at 0x{pc}");
+            raw[..2].copy_from_slice(&bytes[..2]);
+            Instruction {
+                inst: None,
+                pc,
+                bytes: raw,
+                len: 2,
+            }
+        }
+    }
+}
+
+/// The values a GPR takes over a stretch of the trace as `(time_idx, value)`, from
+/// [`Client::reg_history`]. Pages come from the target's `reg_history` as they're needed, and
+/// values with x or z bits are left out. A page that fails to come back ends it early
+pub struct RegHistory<'a> {
+    client: &'a mut Client,
+    reg: usize,
+    stride: usize,
+    end: u64,
+    /// Where the page after `page` starts
+    next: Option<u64>,
+    page: std::vec::IntoIter<(u64, Option<u32>)>,
+}
+
+impl Iterator for RegHistory<'_> {
+    type Item = (u64, u32);
+
+    fn next(&mut self) -> Option<(u64, u32)> {
+        loop {
+            for (time_idx, value) in self.page.by_ref() {
+                if time_idx >= self.end {
+                    self.next = None;
+                    return None;
+                }
+                if let Some(value) = value {
+                    return Some((time_idx, value));
+                }
+            }
+            let from = self.next.filter(|&from| from < self.end)?;
+            match self.client.reg_history_page(self.reg, from, self.stride) {
+                Ok((next, page)) => {
+                    self.next = next;
+                    self.page = page.into_iter();
+                }
+                Err(e) => {
+                    log::warn!("History of x{} cut short at {from}: {e}", self.reg);
+                    self.next = None;
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Exact addresses of consecutive instructions, honoring 2 and 4 byte lengths.
+///
+/// Walks the bytes straight through, so branches aren't followed. Ends at the first thing that
+/// doesn't decode rather than guessing how long it is
+pub struct InstructionAddrs<'a> {
+    bytes: &'a [u8],
+    addr: u64,
+    remaining: usize,
+}
+
+impl<'a> InstructionAddrs<'a> {
+    /// Up to `count` addresses, starting with `addr` itself, from instruction `bytes` at `addr`
+    pub fn new(bytes: &'a [u8], addr: u64, count: usize) -> Self {
+        Self {
+            bytes,
+            addr,
+            remaining: count,
+        }
+    }
+}
+
+impl Iterator for InstructionAddrs<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let Some((_, len)) = decode_one(self.bytes) else {
+            self.remaining = 0;
+            return None;
+        };
+        let addr = self.addr;
+        self.bytes = &self.bytes[len..];
+        self.addr += len as u64;
+        self.remaining -= 1;
+        Some(addr)
     }
 }
 
@@ -103,18 +742,153 @@ pub struct ElfInfo {
     pub entry_point: u64,
     pub is_32bit: bool,
     pub machine: u16,
-    pub text_section: Option<TextSectionInfo>,
+    /// Built for the C extension, so instructions may sit on any 2 byte boundary
+    pub compressed: bool,
+    /// Every section with code in it, in address order. Firmware often has more than `.text`,
+    /// like `.text.startup`, `.vectors` or code copied to a tightly coupled memory
+    pub exec_sections: Vec<ExecSection>,
     pub symbols: Vec<SymbolInfo>,
     pub elf_data: Vec<u8>,
+    /// Whether there's any line info in the ELF itself
+    pub has_debug_line: bool,
+    /// Set when the debug info was split out into a separate file
+    pub debuglink: Option<DebugLink>,
+    /// What the file looked like when it was read, `None` if it was handed over as bytes
+    pub fingerprint: Option<ElfFingerprint>,
 }
 
-#[derive(Debug, Clone)]
-pub struct TextSectionInfo {
+/// An ELF section with `SHF_EXECINSTR` set and its bytes in the file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecSection {
+    pub name: String,
     pub addr: u64,
     pub size: u64,
     pub file_offset: u64,
 }
 
+impl ExecSection {
+    pub fn contains(&self, addr: u64) -> bool {
+        addr >= self.addr && addr - self.addr < self.size
+    }
+}
+
+impl ElfInfo {
+    /// Parse a RISC-V ELF, failing on any other machine
+    pub fn parse(elf_data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let elf = Elf::parse(elf_data)?;
+
+        // Check if it's 32-bit and RISC-V
+        let is_32bit = elf.header.e_ident[4] == 1; // EI_CLASS: ELFCLASS32
+        let is_riscv = elf.header.e_machine == 0xf3; // EM_RISCV
+
+        if !is_riscv {
+            return Err(format!(
+                "Not a RISC-V binary (machine type: 0x{:x})",
+                elf.header.e_machine
+            )
+            .into());
+        }
+
+        // Every section with code in it. NOBITS ones have nothing in the file to read
+        let mut exec_sections: Vec<ExecSection> = elf
+            .section_headers
+            .iter()
+            .filter(|sh| {
+                sh.is_executable()
+                    && sh.sh_type != goblin::elf::section_header::SHT_NOBITS
+                    && sh.sh_size > 0
+            })
+            .map(|sh| ExecSection {
+                name: elf
+                    .shdr_strtab
+                    .get_at(sh.sh_name)
+                    .unwrap_or_default()
+                    .to_string(),
+                addr: sh.sh_addr,
+                size: sh.sh_size,
+                file_offset: sh.sh_offset,
+            })
+            .collect();
+        exec_sections.sort_by_key(|section| section.addr);
+
+        // Extract symbols
+        let mut symbols = Vec::new();
+        for sym in &elf.syms {
+            if let Some(name_str) = elf.strtab.get_at(sym.st_name) {
+                if !name_str.is_empty() && sym.st_value != 0 {
+                    symbols.push(SymbolInfo {
+                        name: name_str.to_string(),
+                        addr: sym.st_value,
+                        size: sym.st_size,
+                    });
+                }
+            }
+        }
+
+        // Sort symbols by address for efficient lookup
+        symbols.sort_by_key(|s| s.addr);
+
+        let has_debug_line = elf.section_headers.iter().any(|sh| {
+            sh.sh_size > 0
+                && elf
+                    .shdr_strtab
+                    .get_at(sh.sh_name)
+                    .is_some_and(|name| name == ".debug_line")
+        });
+        let debuglink = DebugLink::from_elf(&elf, elf_data);
+
+        Ok(ElfInfo {
+            entry_point: elf.header.e_entry,
+            is_32bit,
+            machine: elf.header.e_machine,
+            compressed: elf.header.e_flags & EF_RISCV_RVC != 0,
+            exec_sections,
+            symbols,
+            elf_data: elf_data.to_vec(),
+            has_debug_line,
+            debuglink,
+            fingerprint: None,
+        })
+    }
+
+    /// The executable section `addr` is in, if any
+    pub fn exec_section_at(&self, addr: u64) -> Option<&ExecSection> {
+        self.exec_sections
+            .iter()
+            .find(|section| section.contains(addr))
+    }
+
+    /// Whether `addr` is in any of the executable sections
+    pub fn is_code(&self, addr: u64) -> bool {
+        self.exec_section_at(addr).is_some()
+    }
+
+    /// The bytes of the file from `addr` to the end of the executable section it's in
+    pub fn code_from(&self, addr: u64) -> Result<&[u8], String> {
+        if self.exec_sections.is_empty() {
+            return Err("No executable section found in ELF file".to_string());
+        }
+        let section = self.exec_section_at(addr).ok_or_else(|| {
+            let sections: Vec<String> = self
+                .exec_sections
+                .iter()
+                .map(|s| format!("{} 0x{:x}-0x{:x}", s.name, s.addr, s.addr + s.size))
+                .collect();
+            format!(
+                "PC 0x{addr:x} is outside every executable section ({})",
+                sections.join(", ")
+            )
+        })?;
+
+        let start_idx = (section.file_offset + (addr - section.addr)) as usize;
+        let end_idx = ((section.file_offset + section.size) as usize).min(self.elf_data.len());
+        if start_idx >= end_idx {
+            return Err("File offset is beyond ELF data bounds".to_string());
+        }
+        Ok(&self.elf_data[start_idx..end_idx])
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SymbolInfo {
     pub name: String,
@@ -137,56 +911,162 @@ impl Client {
         let addr = format!("127.0.0.1:{port}");
         let strm = TcpStream::connect(addr).unwrap();
         strm.set_nodelay(true).unwrap();
+        Self::from_stream(strm, false)
+    }
+
+    /// Connect to a dang instance as a read-only observer.
+    ///
+    /// Registers, memory and monitor queries work as usual and always reflect wherever the
+    /// primary connection has moved the target. Stepping, continuing, breakpoints and memory
+    /// writes fail with [`ObserverReadOnly`]
+    pub fn connect_observer(addr: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let strm = TcpStream::connect(addr)?;
+        strm.set_nodelay(true)?;
+        Ok(Self::from_stream(strm, true))
+    }
+
+    fn from_stream(strm: TcpStream, observer: bool) -> Self {
         Self {
             strm,
             packet_scratch: [0; 4096],
             elf_info: None,
             addr2line_stepper: None,
             wave_tracker: None,
-            response_buffer: Vec::new(),
+            decoder: PacketDecoder::default(),
+            async_output: VecDeque::new(),
             cached_state: CachedState::default(),
+            line_heat_cache: HashMap::new(),
+            last_stop_reason: None,
+            observer,
+            packet_stats: PacketStats::default(),
+            debug_search_paths: Vec::new(),
+            debug_info: DebugInfo::default(),
+            stub_features: Vec::new(),
+            current_thread: ThreadId::Any,
+            module_name: None,
+            symbol_cache: std::sync::Mutex::new(SymbolCache::default()),
+            symbol_offset_cache: std::sync::Mutex::new(SymbolCache::default()),
+            use_stop_context: true,
+            reply_timeout: DEFAULT_REPLY_TIMEOUT,
+            instruction_window: DEFAULT_INSTRUCTION_WINDOW,
+        }
+    }
+
+    pub fn is_observer(&self) -> bool {
+        self.observer
+    }
+
+    /// Refuse anything that would change the target when we're only observing
+    fn ensure_driver(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.observer {
+            return Err(ObserverReadOnly.into());
         }
+        Ok(())
     }
 
     /// Drain any remaining data in the response buffer to ensure synchronization
     fn drain_response_buffer(&mut self) {
-        if !self.response_buffer.is_empty() {
-            log::warn!(
-                "Draining {} bytes from response buffer to maintain synchronization",
-                self.response_buffer.len()
-            );
-            self.response_buffer.clear();
+        let drained = self.decoder.clear();
+        if drained > 0 {
+            log::warn!("Draining {drained} bytes from response buffer to maintain synchronization");
         }
     }
 
+    /// Stop `+` acks reaching callers, once the stub has agreed to `QStartNoAckMode`
+    pub fn set_no_ack(&mut self, no_ack: bool) {
+        self.decoder.set_no_ack(no_ack);
+    }
+
+    /// Everything the stub sent that wasn't the answer to what was asked, oldest first, and
+    /// forget it. A stop reply that came in with no resume in flight is here as
+    /// `<STALE_STOP>: <packet>`
+    pub fn take_async_output(&mut self) -> Vec<String> {
+        self.async_output.drain(..).collect()
+    }
+
+    /// How long to wait for a reply before the read fails with `TimedOut`. A continue over a
+    /// long trace can take a lot longer than the default
+    pub fn set_reply_timeout(&mut self, timeout: std::time::Duration) {
+        self.reply_timeout = timeout;
+    }
+
+    pub fn reply_timeout(&self) -> std::time::Duration {
+        self.reply_timeout
+    }
+
+    /// Write `bytes` to the stub as they are, no framing or checksum added. For exercising how a
+    /// stub copes with what a well behaved client wouldn't send; read the replies with
+    /// [`Client::pop_raw_response`]
+    pub fn send_raw(&mut self, bytes: &[u8]) -> Result<(), std::io::Error> {
+        self.strm.write_all(bytes)?;
+        self.packet_stats.packets_sent += 1;
+        self.packet_stats.bytes_sent += bytes.len() as u64;
+        Ok(())
+    }
+
     pub fn send_command(&mut self, packet: &Packet) -> Result<RawGdbResponse, std::io::Error> {
         let pkt = packet.to_finished_packet(self.packet_scratch.as_mut_slice())?;
 
         self.strm.write_all(pkt.0)?;
+        self.packet_stats.packets_sent += 1;
+        self.packet_stats.bytes_sent += pkt.0.len() as u64;
 
         // Read response with proper packet handling
-        let response = self.read_gdb_packet()?;
+        let mut response = self.read_gdb_packet()?;
+        self.count_received(&response);
         log::trace!("Read {} bytes, content is {:?}", response.len(), &response);
 
-        let _tstr = String::from_utf8_lossy(response.as_slice());
+        if packet.expects_stop_reply() {
+            // the stub answering a retransmitted resume as well as the original
+            let repeats = self.decoder.drop_repeats_of(&response);
+            if repeats > 0 {
+                log::debug!(
+                    "Coalesced {repeats} repeats of stop reply {}",
+                    String::from_utf8_lossy(response.as_slice())
+                );
+            }
+        } else {
+            while response.is_stop_reply() {
+                let stop = String::from_utf8_lossy(response.as_slice()).into_owned();
+                log::debug!("Stop reply {stop} arrived with no resume in flight, set aside");
+                self.async_output.push_back(format!("{STALE_STOP}: {stop}"));
+                response = self.read_gdb_packet()?;
+                self.count_received(&response);
+            }
+        }
 
         Ok(response)
     }
 
+    fn count_received(&mut self, response: &RawGdbResponse) {
+        self.packet_stats.packets_received += 1;
+        self.packet_stats.bytes_received += response.len() as u64;
+    }
+
+    pub fn packet_stats(&self) -> PacketStats {
+        self.packet_stats
+    }
+
+    pub fn stats(&self) -> ClientStats {
+        ClientStats {
+            packets: self.packet_stats,
+            symbol_cache: self.symbol_cache().stats(),
+        }
+    }
+
     /// Read a complete GDB packet, handling partial reads and multiple packets
     fn read_gdb_packet(&mut self) -> Result<RawGdbResponse, std::io::Error> {
         use std::io::ErrorKind;
         use std::time::{Duration, Instant};
 
-        let timeout = Duration::from_millis(500);
+        let timeout = self.reply_timeout;
         let start_time = Instant::now();
 
         // First, check if we have a complete packet in the buffer from previous reads
-        if let Some((packet, remaining)) = Self::find_first_complete_packet(&self.response_buffer) {
-            self.response_buffer = remaining;
+        if let Some(packet) = self.decoder.next_packet() {
             log::debug!(
                 "Returned buffered packet, {} bytes remaining in buffer",
-                self.response_buffer.len()
+                self.decoder.buffered()
             );
             return Ok(packet);
         }
@@ -205,31 +1085,24 @@ impl Client {
                 }
                 Ok(n) => {
                     // Add new data to our response buffer
-                    self.response_buffer.extend_from_slice(&temp_buffer[..n]);
-
-                    // Try to extract a complete packet from buffer - only check if we potentially have enough data
-                    if self.response_buffer.len() >= 4 {
-                        // Minimum packet size: $#xx
-                        if let Some((packet, remaining)) =
-                            Self::find_first_complete_packet(&self.response_buffer)
-                        {
-                            self.response_buffer = remaining;
-                            log::trace!(
-                                "Extracted packet, {} bytes remaining in buffer",
-                                self.response_buffer.len()
-                            );
-                            self.strm.set_read_timeout(None)?;
-
-                            return Ok(packet);
-                        }
+                    self.decoder.push(&temp_buffer[..n]);
+
+                    if let Some(packet) = self.decoder.next_packet() {
+                        log::trace!(
+                            "Extracted packet, {} bytes remaining in buffer",
+                            self.decoder.buffered()
+                        );
+                        self.strm.set_read_timeout(None)?;
+
+                        return Ok(packet);
                     }
                 }
                 Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
                     // Timeout occurred, check if we have any partial data
-                    if !self.response_buffer.is_empty() && start_time.elapsed() < timeout {
+                    if self.decoder.buffered() > 0 && start_time.elapsed() < timeout {
                         // We have partial data, keep trying for a bit longer
                         continue;
-                    } else if self.response_buffer.is_empty() && start_time.elapsed() < timeout {
+                    } else if self.decoder.buffered() == 0 && start_time.elapsed() < timeout {
                         // No data yet but still within overall timeout
                         continue;
                     } else {
@@ -253,14 +1126,11 @@ impl Client {
 
         // If we have any data in buffer but no complete packet, return it as is
         // This handles cases where server sends malformed data
-        if !self.response_buffer.is_empty() {
-            if let Some((packet, remaining)) =
-                Self::find_first_complete_packet(&self.response_buffer)
-            {
-                self.response_buffer = remaining;
+        if self.decoder.buffered() > 0 {
+            if let Some(packet) = self.decoder.next_packet() {
                 log::trace!(
                     "Extracted packet, {} bytes remaining in buffer",
-                    self.response_buffer.len()
+                    self.decoder.buffered()
                 );
                 self.strm.set_read_timeout(None)?;
 
@@ -279,69 +1149,217 @@ impl Client {
         }
     }
 
-    /// Find packet boundaries in buffer and return the first complete packet
-    /// Returns (packet_data, remaining_buffer) or None if no complete packet found
-    fn find_first_complete_packet(buffer: &[u8]) -> Option<(RawGdbResponse, Vec<u8>)> {
-        let mdata = RawGdbResponse::find_packet_data(buffer).ok();
-        if let Some(data) = mdata {
-            let remaining = buffer[data.entire_packet_len()..].to_vec();
-            return Some((data, remaining));
+    /// Single-step. `Ok(false)` once the target has exited or been terminated, which for dang
+    /// is the end of the trace, `Ok(true)` for any other stop
+    pub fn step(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+        self.resume(Resume::Step)
+    }
+
+    /// Continue until something stops the target, with the same result as [`Client::step`]
+    pub fn continue_execution(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+        self.resume(Resume::Continue)
+    }
+
+    /// Send `resume` and read the stop it ends in. An error packet in place of a stop reply
+    /// is an error, the target didn't move
+    fn resume(&mut self, resume: Resume) -> Result<bool, Box<dyn std::error::Error>> {
+        self.ensure_driver()?;
+        let resp = self.send_command_parsed(Packet::Command(GdbCommand::Resume(resume.clone())))?;
+        if let GdbResponse::Error { code } = resp {
+            return Err(
+                format!("{resume:?} failed, the stub answered with error 0x{code:02x}").into(),
+            );
+        }
+        self.cached_state = CachedState::default();
+
+        if self.note_exit(&resp) {
+            return Ok(false);
+        }
+        if let GdbResponse::StopReply {
+            reason, thread_id, ..
+        } = &resp
+        {
+            if let Some(thread) = thread_id {
+                self.current_thread = thread.clone();
+            }
+            self.last_stop_reason = Some(reason.clone());
+            log::info!("{resume:?} stopped: {reason:?}");
         }
-        None
+
+        self.refresh_stop_state()?;
+        Ok(true)
     }
 
-    pub fn step(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
-        let resp = self.send_command_parsed(Packet::Command(GdbCommand::Resume(Resume::Step)))?;
-        if let crate::response::GdbResponse::StopReply { reason, .. } = &resp {
-            match reason {
-                crate::response::StopReason::ProcessExit { code } => {
-                    log::info!("Program exited with code 0x{:02x}", code);
-                    return Ok(false);
+    /// Remember `resp` as the last stop if it says the program is gone. True if it did
+    pub(crate) fn note_exit(&mut self, resp: &GdbResponse) -> bool {
+        let reason = match *resp {
+            GdbResponse::Exited { code } => {
+                log::info!("Program exited with code 0x{code:02x}");
+                StopReason::ProcessExit { code }
+            }
+            GdbResponse::TerminatedWithSignal { signal } => {
+                log::info!("Program terminated with signal 0x{signal:02x}");
+                StopReason::SignalTermination(signal)
+            }
+            _ => return false,
+        };
+        self.last_stop_reason = Some(reason);
+        true
+    }
+
+    /// Whether the last stop was the program exiting or being terminated, after which there's
+    /// nothing left to resume
+    pub fn has_exited(&self) -> bool {
+        matches!(
+            self.last_stop_reason,
+            Some(StopReason::ProcessExit { .. } | StopReason::SignalTermination(_))
+        )
+    }
+
+    /// Fill the cache in for where the target just stopped. One `stop_context` if the stub has
+    /// it, otherwise the PC and time index one query at a time
+    fn refresh_stop_state(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.use_stop_context {
+            match self.stop_context(self.instruction_window)? {
+                Some(context) => {
+                    self.cached_state = CachedState {
+                        pc: Some(PC::_32(context.pc)),
+                        time_idx: Some(context.time_idx),
+                        gprs: Some(context.gprs),
+                        code: Some(context.code),
+                    };
+                    return Ok(());
                 }
-                crate::response::StopReason::SignalTermination(sig) => {
-                    log::info!("Program terminated with signal 0x{:02x}", sig);
-                    return Ok(false);
+                None => {
+                    log::info!("The stub has no stop_context, fetching stop state piecewise");
+                    self.use_stop_context = false;
                 }
-                _ => {}
             }
         }
-
-        self.cached_state.time_idx = None;
-        self.cached_state.pc = None;
         self.cached_state.pc = Some(self.get_current_pc()?);
         self.cached_state.time_idx = Some(self.get_time_idx()?);
-        Ok(true)
+        Ok(())
     }
 
-    //returns false
-    pub fn continue_execution(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
-        let resp =
-            self.send_command_parsed(Packet::Command(GdbCommand::Resume(Resume::Continue)))?;
-        self.cached_state.time_idx = None;
-        self.cached_state.pc = None;
-
-        // Check if the program has terminated
-        if let crate::response::GdbResponse::StopReply { reason, .. } = &resp {
-            match reason {
-                crate::response::StopReason::ProcessExit { code } => {
-                    log::info!("Program exited with code 0x{:02x}", code);
-                    return Ok(false);
+    /// Whether to fetch stop state with one `stop_context`. On by default, and turned off for
+    /// good the first time the stub turns it down
+    pub fn set_use_stop_context(&mut self, on: bool) {
+        self.use_stop_context = on;
+    }
+
+    /// The time index, PC, GPRs and `n_inst` instructions' worth of memory at the PC, in one
+    /// round trip. `None` if the stub doesn't know `stop_context`
+    pub fn stop_context(
+        &mut self,
+        n_inst: usize,
+    ) -> Result<Option<StopContext>, Box<dyn std::error::Error>> {
+        let output = match self.send_monitor_command(&format!("stop_context {n_inst}")) {
+            Ok(output) => output,
+            Err(e) if e.is::<UnsupportedByStub>() => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if output.starts_with(dang::gdb::UNKNOWN_COMMAND) {
+            return Ok(None);
+        }
+        Ok(Some(output.parse()?))
+    }
+
+    /// Why the target stopped after the last step or continue
+    pub fn last_stop_reason(&self) -> Option<&StopReason> {
+        self.last_stop_reason.as_ref()
+    }
+
+    /// True if the last continue was cut short because the PC was spinning in a loop
+    pub fn stopped_on_livelock(&self) -> bool {
+        matches!(
+            self.last_stop_reason,
+            Some(StopReason::Signal(LIVELOCK_SIGNAL))
+        )
+    }
+
+    /// True if the last step or continue stopped because an assertion failed, see
+    /// [`Client::last_assertion`] for which
+    pub fn stopped_on_assertion(&self) -> bool {
+        matches!(
+            self.last_stop_reason,
+            Some(StopReason::Signal(ASSERTION_SIGNAL))
+        )
+    }
+
+    /// Write `data` to target memory.
+    ///
+    /// Uses the binary `X` packet unless escaping would make it larger than the hex `M` form.
+    /// dang only accepts writes inside its scratch region, anything else comes back as a
+    /// [`ReadOnlyMemory`] error
+    pub fn write_memory(
+        &mut self,
+        addr: u32,
+        data: &[u8],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.ensure_driver()?;
+        for (i, chunk) in data.chunks(MAX_WRITE_CHUNK).enumerate() {
+            let chunk_addr = addr + (i * MAX_WRITE_CHUNK) as u32;
+            let escaped_len = crate::packet::escape_binary(chunk).len();
+            let base = if escaped_len < 2 * chunk.len() {
+                Base::UpperX {
+                    addr: chunk_addr,
+                    data: chunk.to_vec(),
+                }
+            } else {
+                Base::UpperM {
+                    addr: chunk_addr,
+                    data: chunk.to_vec(),
                 }
-                crate::response::StopReason::SignalTermination(sig) => {
-                    log::info!("Program terminated with signal 0x{:02x}", sig);
-                    return Ok(false);
+            };
+
+            match self.send_command_parsed(Packet::Command(GdbCommand::Base(base)))? {
+                GdbResponse::Ok => {}
+                GdbResponse::Error { code: EROFS } => {
+                    return Err(ReadOnlyMemory {
+                        addr: chunk_addr,
+                        len: chunk.len(),
+                    }
+                    .into())
                 }
-                _ => {
-                    log::info!("cont exec reason: {reason:?}");
+                other => {
+                    return Err(format!("Memory write at 0x{chunk_addr:x} failed: {other}").into())
                 }
             }
+            // the code at the PC may be what was just written
+            self.cached_state.code = None;
         }
+        Ok(())
+    }
 
-        self.cached_state.pc = Some(self.get_current_pc()?);
-        self.cached_state.time_idx = Some(self.get_time_idx()?);
-        log::info!("Continue execution response: {resp:?}");
+    /// Read `len` bytes of target memory
+    pub fn read_memory(
+        &mut self,
+        addr: u32,
+        len: u32,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        match self.send_command_parsed(Packet::Command(GdbCommand::Base(Base::LowerM {
+            addr,
+            length: len,
+        })))? {
+            GdbResponse::MemoryData { data } => Ok(data),
+            other => Err(format!("Memory read at 0x{addr:x} failed: {other}").into()),
+        }
+    }
 
-        Ok(true)
+    /// The writable scratch region on the target as `(start, size)`, if one is configured
+    pub fn scratch_info(&mut self) -> Result<Option<(u32, u32)>, Box<dyn std::error::Error>> {
+        let output = self.send_monitor_command("scratch_info")?;
+        let output = output.trim();
+        if output == "none" {
+            return Ok(None);
+        }
+        let (start, size) = output
+            .split_once(' ')
+            .ok_or_else(|| format!("Malformed scratch_info output: {output}"))?;
+        Ok(Some((
+            u32::from_str_radix(start, 16)?,
+            u32::from_str_radix(size, 16)?,
+        )))
     }
 
     pub fn send_command_parsed(
@@ -349,105 +1367,42 @@ impl Client {
         packet: Packet,
     ) -> Result<GdbResponse, Box<dyn std::error::Error>> {
         let raw_response = self.send_command(&packet)?;
-        let parsed_response = GdbResponse::parse_packet(raw_response, &packet)?;
+        let parsed_response = GdbResponse::parse_reply(raw_response, &packet)?;
         log::info!("Sent packet: {packet:?} and got response: {parsed_response:?}");
         Ok(parsed_response)
     }
 
     pub fn pop_response(&mut self) -> Result<GdbResponse, Box<dyn std::error::Error>> {
-        let raw_response = self.read_gdb_packet()?;
+        let raw_response = self.pop_raw_response()?;
         let parsed_response = GdbResponse::parse_packet(raw_response, &Packet::default())?;
         Ok(parsed_response)
     }
 
-    pub fn initialize_gdb_session(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        // QStartNoAckMode must return OK per RSP
-        match self.send_command_parsed(Packet::Command(GdbCommand::Base(Base::QStartNoAckMode)))? {
-            GdbResponse::Ack => {
-                log::trace!("QStartNoAckMode acknowledged with an ack");
-                let resp = self.pop_response()?;
-                if resp != GdbResponse::Ok {
-                    return Err(format!("Expected Ok for QStartNoAckMode, got: {resp}").into());
-                }
-            }
-            GdbResponse::Ok => {
-                log::trace!("QStartNoAckMode acknowledged with an ok");
-            }
-            other => {
-                return Err(format!("Expected Ack for QStartNoAckMode, got: {other}").into());
-            }
-        }
-
-        // qSupported should return a feature list; require PacketSize (commonly provided)
-        match self.send_command_parsed(Packet::Command(GdbCommand::Base(Base::QSupported)))? {
-            GdbResponse::Supported { features } => {
-                let has_packet_size = features.iter().any(|f| f.starts_with("PacketSize="));
-                if !has_packet_size {
-                    return Err(
-                        format!("qSupported missing PacketSize in features: {features:?}").into(),
-                    );
-                }
-                log::trace!("qSupported features: {features:?}");
-            }
-            other => {
-                return Err(format!("Expected qSupported feature list, got: {other}").into());
-            }
-        }
-
-        // qfThreadInfo must return thread list (may be empty) or 'm...' chunk
-
-        match self.send_command_parsed(Packet::Command(GdbCommand::Base(Base::QfThreadInfo)))? {
-            GdbResponse::ThreadInfo { threads, .. } => {
-                log::trace!("qfThreadInfo threads: {threads:?}");
-            }
-            other => {
-                return Err(format!("Expected thread info for qfThreadInfo, got: {other}").into());
-            }
-        }
-
-        // qsThreadInfo should continue list or return end
-
-        match self.send_command_parsed(Packet::Command(GdbCommand::Base(Base::QsThreadInfo)))? {
-            GdbResponse::ThreadInfo { threads, .. } => {
-                log::trace!("qsThreadInfo threads: {threads:?}");
-            }
-            other => {
-                return Err(format!("Expected thread info for qsThreadInfo, got: {other}").into());
-            }
-        }
-
-        // '?' must return a stop reply (Sxx or Txx)
-        match self.send_command_parsed(Packet::Command(GdbCommand::Base(Base::QuestionMark)))? {
-            GdbResponse::StopReply { signal, .. } => {
-                log::trace!("Got stop reply with signal 0x{signal:02x}");
-            }
-            other => {
-                return Err(format!("Expected stop reply for '?', got: {other}").into());
-            }
-        }
+    /// Read the next packet the stub sends without parsing it, e.g. the `OK` after monitor output
+    pub fn pop_raw_response(&mut self) -> Result<RawGdbResponse, std::io::Error> {
+        let raw_response = self.read_gdb_packet()?;
+        self.count_received(&raw_response);
+        Ok(raw_response)
+    }
 
-        // 'g' must return register data; for RV32 expect >= 132 bytes (32 GPRs + PC), 4-byte aligned
-        match self.send_command_parsed(Packet::Command(GdbCommand::Base(Base::LowerG)))? {
-            GdbResponse::RegisterData { data } => {
-                if data.len() < 132 || (data.len() % 4) != 0 {
-                    return Err(format!(
-                        "Unexpected register data length (got {}, expected >= 132 and multiple of 4)",
-                        data.len()
-                    ).into());
-                }
-                log::trace!("Register read length OK: {} bytes", data.len());
-            }
-            other => {
-                return Err(format!("Expected RegisterData for 'g' (LowerG), got: {other}").into());
-            }
-        }
+    /// Run the whole connection handshake, see [`Session`]
+    pub fn initialize_gdb_session(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.initialize_with(Session::default()).map(|_| ())
+    }
 
-        log::info!("GDB initialization sequence complete!");
-        Ok(())
+    /// Run whatever of the handshake `session` has left, with its skips and checks, and report
+    /// what each stage found
+    pub fn initialize_with(
+        &mut self,
+        mut session: Session,
+    ) -> Result<InitReport, Box<dyn std::error::Error>> {
+        session.run(self)?;
+        Ok(session.into_report())
     }
 
     pub fn get_time_idx(&mut self) -> Result<u64, Box<dyn std::error::Error>> {
-        if let Some(time_idx) = self.cached_state.time_idx {
+        // An observer's target moves under its feet, so nothing can be cached
+        if let Some(time_idx) = self.cached_state.time_idx.filter(|_| !self.observer) {
             return Ok(time_idx);
         }
 
@@ -458,6 +1413,13 @@ impl Client {
         rv
     }
 
+    /// Simulation time at the cursor and the waveform's timescale it's in, `ps` or `10ns`. The
+    /// unit is empty if the waveform doesn't have one
+    pub fn get_time(&mut self) -> Result<(u64, String), Box<dyn std::error::Error>> {
+        let output = self.send_monitor_command("time")?;
+        Ok(parse_time(output.trim())?)
+    }
+
     /// Send a monitor command to the GDB server
     pub fn send_monitor_command(
         &mut self,
@@ -466,11 +1428,12 @@ impl Client {
         // Drain any lingering responses before sending critical commands
         self.drain_response_buffer();
 
-        let monitor_packet = Packet::Command(GdbCommand::Base(Base::QRcmd {
+        let monitor = Base::QRcmd {
             command: cmd.to_string(),
-        }));
+        };
 
-        let response = self.send_command_parsed(monitor_packet)?;
+        let response =
+            self.send_command_parsed(Packet::Command(GdbCommand::Base(monitor.clone())))?;
 
         match response {
             crate::response::GdbResponse::MonitorOutput { output } => {
@@ -480,19 +1443,73 @@ impl Client {
                 if ok_response != crate::response::GdbResponse::Ok {
                     log::warn!("Expected OK after monitor output, got: {ok_response}");
                 }
+                if output.trim_end().ends_with(dang::bounded_output::TRUNCATED) {
+                    log::warn!("Output of monitor command '{cmd}' was cut short by the stub");
+                }
                 Ok(output)
             }
+            GdbResponse::Unsupported => Err(UnsupportedByStub::new(&monitor).into()),
             other => Err(format!("Expected monitor output, got: {other}").into()),
         }
     }
 
+    /// Run `commands` with one `monitor batch`, a single round trip. Each gets its own output
+    /// back, or why it failed, in order. A `;` inside a command is escaped on the way
+    pub fn send_monitor_batch(
+        &mut self,
+        commands: &[&str],
+    ) -> Result<Vec<Result<String, BatchCommandError>>, Box<dyn std::error::Error>> {
+        let output =
+            self.send_monitor_command(&format!("batch {}", dang::monitor_batch::join(commands)))?;
+        if output.starts_with(dang::gdb::UNKNOWN_COMMAND) {
+            return Err(format!("The stub doesn't do monitor batch: {}", output.trim()).into());
+        }
+        let mut sections = dang::monitor_batch::split_output(&output).into_iter();
+        Ok(commands
+            .iter()
+            .map(|command| {
+                let failed = |message: &str| BatchCommandError {
+                    command: command.to_string(),
+                    message: message.to_string(),
+                };
+                if command.trim().is_empty() {
+                    return Err(failed("empty command"));
+                }
+                let Some((_, output)) = sections.next() else {
+                    return Err(failed("missing from the reply"));
+                };
+                if let Some(message) = output.strip_prefix(dang::monitor_batch::FAILED) {
+                    return Err(failed(message.trim()));
+                }
+                if output.starts_with(dang::gdb::UNKNOWN_COMMAND) {
+                    return Err(failed("unknown command"));
+                }
+                Ok(output)
+            })
+            .collect())
+    }
+
+    /// [`Client::send_monitor_command`] for the commands dang answers from its PC index, which
+    /// reply with how far along the index is until it's built. Waits that out
+    fn send_pc_index_command(&mut self, cmd: &str) -> Result<String, Box<dyn std::error::Error>> {
+        loop {
+            let output = self.send_monitor_command(cmd)?;
+            if !output.trim_start().starts_with(dang::pc_index::BUILDING) {
+                return Ok(output);
+            }
+            log::debug!("{}", output.trim());
+            std::thread::sleep(PC_INDEX_RETRY);
+        }
+    }
+
     /// Get the executable file path from the remote target
     pub fn get_executable_path(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        let exec_file = Base::QXferExecFile {
+            offset: 0,
+            length: 1000,
+        };
         let response =
-            self.send_command_parsed(Packet::Command(GdbCommand::Base(Base::QXferExecFile {
-                offset: 0,
-                length: 1000,
-            })))?;
+            self.send_command_parsed(Packet::Command(GdbCommand::Base(exec_file.clone())))?;
 
         match response {
             crate::response::GdbResponse::QXferData { data, .. } => {
@@ -500,6 +1517,7 @@ impl Client {
                 let path = String::from_utf8(data)?;
                 Ok(path)
             }
+            GdbResponse::Unsupported => Err(UnsupportedByStub::new(&exec_file).into()),
             _ => Err(format!(
                 "Unexpected response format for qXfer:exec-file:read, got {response:?}"
             )
@@ -509,116 +1527,404 @@ impl Client {
 
     /// Parse ELF file from the given path and store information
     pub fn parse_elf_file(&mut self, elf_data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
-        let elf = Elf::parse(&elf_data)?;
+        self.elf_info = Some(ElfInfo::parse(elf_data)?);
+        Ok(())
+    }
 
-        // Check if it's 32-bit and RISC-V
-        let is_32bit = elf.header.e_ident[4] == 1; // EI_CLASS: ELFCLASS32
-        let is_riscv = elf.header.e_machine == 0xf3; // EM_RISCV
+    /// The rest of the executable section `pc` is in, from `pc` on
+    fn elf_text_from(&self, pc: PC) -> Result<&[u8], Box<dyn std::error::Error>> {
+        let elf_info = self
+            .elf_info
+            .as_ref()
+            .ok_or("No ELF file loaded. Call parse_elf_file() first")?;
+        Ok(elf_info.code_from(pc.as_u64())?)
+    }
 
-        if !is_riscv {
-            return Err(format!(
-                "Not a RISC-V binary (machine type: 0x{:x})",
-                elf.header.e_machine
-            )
-            .into());
+    /// Up to `max_bytes` of code from the ELF starting at `pc`, fewer where the section ends
+    /// first
+    pub fn get_instruction_window(
+        &self,
+        pc: PC,
+        max_bytes: usize,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let text = self.elf_text_from(pc)?;
+        Ok(text[..text.len().min(max_bytes)].to_vec())
+    }
+
+    /// Instructions start on a multiple of this many bytes: 2 with RV32C, 4 without.
+    /// Without an ELF to go on, the more lenient 2 is assumed
+    pub fn instruction_alignment(&self) -> u32 {
+        match &self.elf_info {
+            Some(elf_info) if !elf_info.compressed => 4,
+            _ => 2,
         }
+    }
 
-        // Find .text section
-        let text_section = elf
-            .section_headers
-            .iter()
-            .find(|sh| {
-                if let Some(name) = elf.shdr_strtab.get_at(sh.sh_name) {
-                    name == ".text"
-                } else {
-                    false
-                }
-            })
-            .map(|sh| TextSectionInfo {
-                addr: sh.sh_addr,
-                size: sh.sh_size,
-                file_offset: sh.sh_offset,
-            });
+    /// How many times the PC sits at `addr` over the whole trace. 0 means a breakpoint there
+    /// can never be hit
+    pub fn pc_visit_count(&mut self, addr: u32) -> Result<u64, Box<dyn std::error::Error>> {
+        let output = self.send_pc_index_command(&format!("coverage {addr:x} {addr:x}"))?;
+        let Some(entry) = output.lines().find(|l| !l.trim().is_empty()) else {
+            return Ok(0);
+        };
+        let count = entry
+            .trim()
+            .split_once(' ')
+            .and_then(|(_, count)| count.parse::<u64>().ok())
+            .ok_or_else(|| format!("Unexpected coverage entry: {entry}"))?;
+        Ok(count)
+    }
 
-        // Extract symbols
-        let mut symbols = Vec::new();
-        for sym in &elf.syms {
-            if let Some(name_str) = elf.strtab.get_at(sym.st_name) {
-                if !name_str.is_empty() && sym.st_value != 0 {
-                    symbols.push(SymbolInfo {
-                        name: name_str.to_string(),
-                        addr: sym.st_value,
-                        size: sym.st_size,
-                    });
+    /// When GPR `reg` last changed at or before the current position, `None` if it never had a
+    /// value
+    pub fn reg_last_change(
+        &mut self,
+        reg: usize,
+    ) -> Result<Option<RegChange>, Box<dyn std::error::Error>> {
+        let output = self.send_monitor_command(&format!("reg_last_change {reg}"))?;
+        let line = output.trim();
+        if line.starts_with("usage") {
+            return Err(format!("No such register: x{reg}").into());
+        }
+        Ok(parse_reg_change(line)?.1)
+    }
+
+    /// [`Client::reg_last_change`] for all 32 GPRs in a single round trip, indexed by register
+    pub fn regs_last_change(
+        &mut self,
+    ) -> Result<Vec<Option<RegChange>>, Box<dyn std::error::Error>> {
+        let output = self.send_monitor_command("regs_last_change")?;
+        let mut changes = vec![None; 32];
+        for line in output.lines().filter(|l| !l.trim().is_empty()) {
+            let (reg, change) = parse_reg_change(line)?;
+            let slot = changes
+                .get_mut(reg)
+                .ok_or_else(|| format!("Register out of range: {line}"))?;
+            *slot = change;
+        }
+        Ok(changes)
+    }
+
+    /// The values GPR `reg` takes at its changes with a time index in `range`, or at every
+    /// `stride`-th of them, oldest first. Read off the trace a page at a time without moving
+    /// the cursor, see [`RegHistory`]
+    pub fn reg_history(
+        &mut self,
+        reg: usize,
+        range: Range<u64>,
+        stride: usize,
+    ) -> Result<RegHistory<'_>, Box<dyn std::error::Error>> {
+        if reg >= 32 {
+            return Err(format!("No such register: x{reg}").into());
+        }
+        if stride == 0 {
+            return Err("The stride has to be at least 1".into());
+        }
+        // the first page up front, so a target without reg_history fails here
+        let (next, page) = match range.is_empty() {
+            true => (None, Vec::new()),
+            false => self.reg_history_page(reg, range.start, stride)?,
+        };
+        Ok(RegHistory {
+            client: self,
+            reg,
+            stride,
+            end: range.end,
+            next,
+            page: page.into_iter(),
+        })
+    }
+
+    fn reg_history_page(
+        &mut self,
+        reg: usize,
+        from: u64,
+        stride: usize,
+    ) -> Result<RegHistoryPage, Box<dyn std::error::Error>> {
+        let output = self.send_monitor_command(&format!(
+            "reg_history {reg} {from} {REG_HISTORY_PAGE} {stride}"
+        ))?;
+        Ok(parse_reg_history(&output)?)
+    }
+
+    /// Values of x0 through x31 at the current position
+    pub fn read_gprs(&mut self) -> Result<[u32; 32], Box<dyn std::error::Error>> {
+        if let Some(gprs) = self.cached_state.gprs.filter(|_| !self.observer) {
+            return Ok(gprs);
+        }
+        match self.send_command_parsed(Packet::Command(GdbCommand::Base(Base::LowerG)))? {
+            GdbResponse::RegisterData { data } if data.len() >= 128 => {
+                let mut gprs = [0; 32];
+                for (gpr, bytes) in gprs.iter_mut().zip(data.chunks_exact(4)) {
+                    *gpr = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
                 }
+                Ok(gprs)
             }
+            other => Err(format!("Unexpected response format for register read: {other}").into()),
         }
+    }
 
-        // Sort symbols by address for efficient lookup
-        symbols.sort_by_key(|s| s.addr);
+    /// Counts for the most recent step or continue, straight from the target
+    pub fn resume_stats(&mut self) -> Result<ResumeStats, Box<dyn std::error::Error>> {
+        let output = self.send_monitor_command("resume_stats")?;
+        Ok(parse_resume_stats(output.trim())?)
+    }
 
-        self.elf_info = Some(ElfInfo {
-            entry_point: elf.header.e_entry,
-            is_32bit,
-            machine: elf.header.e_machine,
-            text_section,
-            symbols,
-            elf_data: elf_data.to_vec(),
-        });
+    /// [`Client::resume_stats`] and [`Client::last_assertion`] together, in one round trip
+    pub fn stop_report(&mut self) -> Result<StopReport, Box<dyn std::error::Error>> {
+        let replies = self.send_monitor_batch(&["resume_stats", "last_assert"])?;
+        let [stats, assertion]: [_; 2] = replies.try_into().expect("one reply per batched command");
+        Ok(StopReport {
+            stats: parse_resume_stats(stats?.trim())?,
+            assertion: parse_violation(&assertion?)?,
+        })
+    }
 
-        Ok(())
+    /// Stop whenever `x<reg> <op> value` stops holding, `op` being one of `< <= > >= == !=`
+    pub fn assert_reg(
+        &mut self,
+        reg: usize,
+        op: &str,
+        value: u32,
+    ) -> Result<Assertion, Box<dyn std::error::Error>> {
+        let output = self.send_monitor_command(&format!("assert_reg {reg} {op} {value:x}"))?;
+        Self::parse_assertion_reply(&output)
     }
 
-    /// Get 12 bytes of instruction data from ELF file starting at given PC
-    pub fn get_instruction_bytes_from_elf(
-        &self,
-        pc: PC,
-    ) -> Result<[u8; 12], Box<dyn std::error::Error>> {
-        let elf_info = self
-            .elf_info
-            .as_ref()
-            .ok_or("No ELF file loaded. Call parse_elf_file() first")?;
+    /// Stop whenever the PC leaves `[lo, hi]`, or enters it if `inside` is false
+    pub fn assert_pc_range(
+        &mut self,
+        lo: u32,
+        hi: u32,
+        inside: bool,
+    ) -> Result<Assertion, Box<dyn std::error::Error>> {
+        let side = if inside { "inside" } else { "outside" };
+        let output = self.send_monitor_command(&format!("assert_pc_range {lo:x} {hi:x} {side}"))?;
+        Self::parse_assertion_reply(&output)
+    }
 
-        let text_section = elf_info
-            .text_section
-            .as_ref()
-            .ok_or("No .text section found in ELF file")?;
+    fn parse_assertion_reply(output: &str) -> Result<Assertion, Box<dyn std::error::Error>> {
+        let line = output.trim();
+        if let Some(usage) = line.strip_prefix("usage: ") {
+            return Err(format!("Rejected assertion, expected {usage}").into());
+        }
+        Ok(parse_assertion(line)?)
+    }
 
-        // Check if PC is within .text section bounds
-        let pc_u64 = pc.as_u64();
-        if pc_u64 < text_section.addr || pc_u64 >= text_section.addr + text_section.size {
-            return Err(format!(
-                "PC 0x{} is outside .text section (0x{:x}-0x{:x})",
-                pc,
-                text_section.addr,
-                text_section.addr + text_section.size
-            )
-            .into());
+    pub fn assertions(&mut self) -> Result<Vec<Assertion>, Box<dyn std::error::Error>> {
+        let output = self.send_monitor_command("assert_list")?;
+        Ok(output
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(parse_assertion)
+            .collect::<Result<_, _>>()?)
+    }
+
+    /// Returns false if there was no assertion `id`
+    pub fn delete_assertion(&mut self, id: u32) -> Result<bool, Box<dyn std::error::Error>> {
+        let output = self.send_monitor_command(&format!("assert_del {id}"))?;
+        Ok(output.trim().starts_with("deleted"))
+    }
+
+    /// Let continue jump across `[lo, hi]` while fast-forwarding is on, see
+    /// [`Client::set_fastforward_ranges`]
+    pub fn add_skip_range(
+        &mut self,
+        lo: u32,
+        hi: u32,
+    ) -> Result<SkipRange, Box<dyn std::error::Error>> {
+        let output = self.send_monitor_command(&format!("skip_range add {lo:x} {hi:x}"))?;
+        let line = output.trim();
+        if let Some(usage) = line.strip_prefix("usage: ") {
+            return Err(format!("Rejected skip range, expected {usage}").into());
         }
+        Ok(line.parse::<SkipRange>()?)
+    }
+
+    pub fn skip_ranges(&mut self) -> Result<Vec<SkipRange>, Box<dyn std::error::Error>> {
+        let output = self.send_monitor_command("skip_range list")?;
+        Ok(output
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(str::parse::<SkipRange>)
+            .collect::<Result<_, _>>()?)
+    }
+
+    /// Returns false if there was no skip range `id`
+    pub fn delete_skip_range(&mut self, id: u32) -> Result<bool, Box<dyn std::error::Error>> {
+        let output = self.send_monitor_command(&format!("skip_range del {id}"))?;
+        Ok(output.trim().starts_with("deleted"))
+    }
 
-        // Calculate offset in file
-        let offset_in_section = pc_u64 - text_section.addr;
-        let file_offset = text_section.file_offset + offset_in_section;
+    /// Whether continue jumps across the skip ranges instead of stepping through them.
+    /// Breakpoints inside a range still stop it, and nothing is skipped while there are
+    /// assertions
+    pub fn set_fastforward_ranges(&mut self, on: bool) -> Result<(), Box<dyn std::error::Error>> {
+        self.ensure_driver()?;
+        let output = self.send_monitor_command(if on {
+            "fastforward_ranges on"
+        } else {
+            "fastforward_ranges off"
+        })?;
+        log::debug!("fastforward_ranges: {}", output.trim());
+        Ok(())
+    }
+
+    /// The most recent assertion to stop the target, if any has
+    pub fn last_assertion(
+        &mut self,
+    ) -> Result<Option<AssertionViolation>, Box<dyn std::error::Error>> {
+        let output = self.send_monitor_command("last_assert")?;
+        Ok(parse_violation(&output)?)
+    }
 
-        // Ensure we don't read past the section boundary
-        let available_bytes = (text_section.size - offset_in_section).min(12) as usize;
-        if available_bytes == 0 {
-            return Err("No bytes available at the specified PC".into());
+    /// Move the target's cursor straight to `time_idx`
+    pub fn goto_time_idx(&mut self, time_idx: u64) -> Result<(), Box<dyn std::error::Error>> {
+        self.ensure_driver()?;
+        let output = self.send_monitor_command(&format!("goto_idx {time_idx}"))?;
+        if output.trim().parse::<u64>().ok() != Some(time_idx) {
+            return Err(format!("Target refused goto_idx: {}", output.trim()).into());
         }
+        self.cached_state = CachedState {
+            time_idx: Some(time_idx),
+            ..CachedState::default()
+        };
+        Ok(())
+    }
+
+    /// Move the target's cursor to the first PC or GPR change at or after `time_idx`, which
+    /// needn't be one itself. Fails if nothing changes from there to the end of the trace
+    pub fn goto_time(&mut self, time_idx: u64) -> Result<TimeJump, Box<dyn std::error::Error>> {
+        self.ensure_driver()?;
+        let output = self.send_monitor_command(&format!("goto_time {time_idx}"))?;
+        let jump = parse_time_jump(output.trim())?;
+        self.cached_state = CachedState {
+            pc: Some(PC::_32(jump.pc)),
+            time_idx: Some(jump.time_idx),
+            ..CachedState::default()
+        };
+        Ok(jump)
+    }
+
+    /// The instruction at `pc`, decoded from the ELF. `None` if `pc` isn't in an executable
+    /// section
+    pub fn instruction_at(&self, pc: u32) -> Option<Instruction> {
+        let bytes = self
+            .get_instruction_window(PC::_32(pc), MAX_INSTRUCTION_LEN)
+            .ok()?;
+        decode_instruction_window(&bytes, PC::_32(pc), 1).pop()
+    }
+
+    /// Where the instruction `addr` falls in starts, decoding forward from the start of the
+    /// symbol it's in. `addr` itself on an instruction boundary, `None` without a symbol in the
+    /// ELF's code to start from
+    pub fn instruction_start(&self, addr: u32) -> Option<u32> {
+        let (symbol, offset) = self.find_symbol_at_address(addr.into())?;
+        let start = symbol.addr;
+        let code = self.elf_info.as_ref()?.code_from(start).ok()?;
+        let code = &code[..code.len().min(offset as usize + MAX_INSTRUCTION_LEN)];
+        decode_instruction_window(code, PC::_32(start as u32), usize::MAX)
+            .iter()
+            .map(|inst| inst.pc().as_u32())
+            .take_while(|&pc| pc <= addr)
+            .last()
+    }
 
-        // Read up to 12 bytes from the ELF data
-        let mut instruction_bytes = [0u8; 12];
-        let start_idx = file_offset as usize;
-        let end_idx = (start_idx + available_bytes).min(elf_info.elf_data.len());
+    /// Up to `count` instructions laid out from `addr`, wherever the PC is. The bytes come from
+    /// the ELF, or from target memory for an address outside its executable sections. Fewer
+    /// where the section or the readable memory ends first
+    pub fn disassemble(
+        &mut self,
+        addr: u32,
+        count: usize,
+    ) -> Result<Vec<Instruction>, Box<dyn std::error::Error>> {
+        let pc = PC::_32(addr);
+        let max_bytes = count * MAX_INSTRUCTION_LEN;
+        let bytes = match self.get_instruction_window(pc, max_bytes) {
+            Ok(bytes) => bytes,
+            Err(elf_error) => self.read_memory(addr, max_bytes as u32).map_err(|e| {
+                format!(
+                    "0x{addr:x} is not in the ELF ({elf_error}) and the target can't read it: {e}"
+                )
+            })?,
+        };
+        let instructions = decode_instruction_window(&bytes, pc, count);
+        if instructions.is_empty() {
+            return Err(format!("No instructions at 0x{addr:x}").into());
+        }
+        Ok(instructions)
+    }
 
-        if start_idx >= elf_info.elf_data.len() {
-            return Err("File offset is beyond ELF data bounds".into());
+    /// Walk the executed instructions from the cursor in `direction` until one matches
+    /// `predicate`, and move the cursor there. Gives up after `budget` instructions.
+    ///
+    /// PCs come from the target's `pc_trace` in batches, and are decoded here from the ELF
+    pub fn find_instruction(
+        &mut self,
+        direction: SearchDirection,
+        predicate: &InstPredicate,
+        budget: usize,
+    ) -> Result<FindOutcome, Box<dyn std::error::Error>> {
+        self.ensure_driver()?;
+        let mut from = self.get_time_idx()?;
+        let mut scanned = 0;
+        // loops come back to the same PCs over and over, so only decode each once
+        let mut matching: HashMap<u32, Option<String>> = HashMap::new();
+        while scanned < budget {
+            let count = PC_TRACE_BATCH.min(budget - scanned);
+            let output = self.send_monitor_command(&pc_trace_command(from, count, direction))?;
+            let batch = parse_pc_trace(&output)?;
+            if batch.is_empty() {
+                return Ok(FindOutcome::EndOfTrace { scanned });
+            }
+            for (time_idx, pc) in batch {
+                scanned += 1;
+                from = time_idx;
+                let found = matching.entry(pc).or_insert_with(|| {
+                    self.instruction_at(pc)
+                        .filter(|inst| predicate.matches(inst))
+                        .map(|inst| inst.to_string())
+                });
+                if let Some(instruction) = found.clone() {
+                    self.goto_time_idx(time_idx)?;
+                    return Ok(FindOutcome::Found {
+                        time_idx,
+                        pc,
+                        instruction,
+                        scanned,
+                    });
+                }
+            }
         }
+        Ok(FindOutcome::OverBudget { scanned })
+    }
+
+    /// Addresses of the `count` instructions laid out from `from_pc` onwards, `from_pc` included.
+    ///
+    /// Decoded from the ELF so RV32C instructions advance by 2 bytes. The iterator ends early at
+    /// the end of the executable section or at anything that fails to decode
+    pub fn iter_instruction_addrs(
+        &self,
+        from_pc: PC,
+        count: usize,
+    ) -> Result<InstructionAddrs<'_>, Box<dyn std::error::Error>> {
+        let text = self.elf_text_from(from_pc)?;
+        Ok(InstructionAddrs::new(text, from_pc.as_u64(), count))
+    }
 
-        let actual_bytes = end_idx - start_idx;
-        instruction_bytes[..actual_bytes].copy_from_slice(&elf_info.elf_data[start_idx..end_idx]);
+    /// Every symbol in the ELF, in address order
+    pub fn symbols(&self) -> &[SymbolInfo] {
+        self.elf_info
+            .as_ref()
+            .map_or(&[], |elf_info| elf_info.symbols.as_slice())
+    }
 
-        Ok(instruction_bytes)
+    /// Every source file the line info mentions, empty without debug info
+    pub fn list_dwarf_files(&self) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+        match self.addr2line_stepper {
+            Some(ref stepper) => Ok(stepper.list_dwarf_files()?),
+            None => Ok(Vec::new()),
+        }
     }
 
     /// Find symbol containing the given address
@@ -664,18 +1970,276 @@ impl Client {
         let elf_path = self.get_executable_path()?;
         let elf_data = fs::read(&elf_path)?;
         self.parse_elf_file(&elf_data)?;
-        self.addr2line_stepper = Addr2lineStepper::new(&elf_data, 0).ok();
+        if let Some(info) = self.elf_info.as_mut() {
+            info.fingerprint = Some(ElfFingerprint::new(Path::new(&elf_path), &elf_data));
+        }
+        self.attach_debug_info(Path::new(&elf_path), &elf_data);
+        self.module_name = Path::new(&elf_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        // The only time cached symbols go stale
+        self.clear_symbol_caches();
         Ok(())
     }
 
-    pub fn load_waveform(&mut self, wave_path: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        self.wave_tracker = Some(WaveformTracker::new(wave_path)?);
-        Ok(())
+    /// Whether the ELF on disk has changed since [`Client::load_elf_info`] read it, see
+    /// [`ElfFingerprint::is_stale`]. Calling `load_elf_info` again picks up the new one
+    pub fn elf_is_stale(&mut self) -> bool {
+        self.elf_info
+            .as_mut()
+            .and_then(|info| info.fingerprint.as_mut())
+            .is_some_and(|fingerprint| fingerprint.is_stale())
     }
 
-    /// Get the current program counter (PC) from registers
+    /// Path of the ELF symbols and line info come from, if it was read from a file
+    pub fn elf_path(&self) -> Option<&Path> {
+        let fingerprint = self.elf_info.as_ref()?.fingerprint.as_ref()?;
+        Some(&fingerprint.path)
+    }
+
+    fn symbol_cache(&self) -> std::sync::MutexGuard<'_, SymbolCache> {
+        self.symbol_cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn clear_symbol_caches(&self) {
+        self.symbol_cache().clear();
+        self.symbol_offset_cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clear();
+    }
+
+    /// Build the line lookup from the ELF's own DWARF, or for a stripped ELF from the file its
+    /// `.gnu_debuglink` names
+    fn attach_debug_info(&mut self, elf_path: &Path, elf_data: &[u8]) {
+        let (has_debug_line, debuglink) = match &self.elf_info {
+            Some(info) => (info.has_debug_line, info.debuglink.clone()),
+            None => (false, None),
+        };
+        if !has_debug_line && debuglink.is_none() {
+            // Nothing to build a line lookup from, don't make every lookup fail on its own
+            log::warn!("{} has no debug info", elf_path.display());
+            self.addr2line_stepper = None;
+            self.debug_info = DebugInfo::Absent;
+            self.line_heat_cache.clear();
+            return;
+        }
+        let (debug_data, debug_info) = match debuglink {
+            Some(link) if !has_debug_line => match link.find(elf_path, &self.debug_search_paths) {
+                Ok((path, data)) => {
+                    log::info!("Using debug info from {}", path.display());
+                    (data, DebugInfo::Separate(path))
+                }
+                Err(e) => {
+                    log::warn!("No line info for {}: {e}", elf_path.display());
+                    (elf_data.to_vec(), DebugInfo::Missing(e.to_string()))
+                }
+            },
+            _ => (elf_data.to_vec(), DebugInfo::Embedded),
+        };
+        self.addr2line_stepper = Addr2lineStepper::new(&debug_data, 0).ok();
+        self.debug_info = debug_info;
+        self.line_heat_cache.clear();
+    }
+
+    /// Also look for `.gnu_debuglink` files in `dir`, from the next [`Client::load_elf_info`]
+    pub fn add_debug_search_path(&mut self, dir: impl Into<PathBuf>) {
+        self.debug_search_paths.push(dir.into());
+    }
+
+    /// Take line info from the debug file at `path`, for when it can't be found automatically.
+    /// If the ELF has a `.gnu_debuglink`, the file's CRC has to match it
+    pub fn load_debug_file(&mut self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let data = fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        if let Some(link) = self
+            .elf_info
+            .as_ref()
+            .and_then(|info| info.debuglink.as_ref())
+        {
+            link.verify(path, &data)?;
+        }
+        let stepper = Addr2lineStepper::new(&data, 0)
+            .map_err(|e| format!("No usable debug info in {}: {e}", path.display()))?;
+        self.addr2line_stepper = Some(stepper);
+        self.debug_info = DebugInfo::Separate(path.to_path_buf());
+        self.line_heat_cache.clear();
+        self.clear_symbol_caches();
+        Ok(())
+    }
+
+    /// The features the stub advertised in `qSupported`, e.g. `ReverseStep+`. Empty until
+    /// [`Client::initialize_gdb_session`]
+    pub fn stub_features(&self) -> &[String] {
+        &self.stub_features
+    }
+
+    pub(crate) fn set_stub_features(&mut self, features: Vec<String>) {
+        self.stub_features = features;
+    }
+
+    /// The thread the stub has selected, from `qC` during [`Client::initialize_gdb_session`] and
+    /// the `thread:` of each stop reply after. [`ThreadId::Any`] until the handshake learns it
+    pub fn current_thread(&self) -> &ThreadId {
+        &self.current_thread
+    }
+
+    pub(crate) fn set_current_thread(&mut self, thread: ThreadId) {
+        self.current_thread = thread;
+    }
+
+    /// Send `H` for `thread`, both `Hg` for register and memory accesses and `Hc` for resuming.
+    /// A thread the stub doesn't have is an error reply to the `Hg`, and the selection stays as
+    /// it was
+    pub fn set_thread(&mut self, thread: ThreadId) -> Result<(), Box<dyn std::error::Error>> {
+        if matches!(thread, ThreadId::Any | ThreadId::All) {
+            return Err(format!("Can only select one thread, not {thread:?}").into());
+        }
+        for op in [ThreadOp::General, ThreadOp::Continue] {
+            let select = Base::H {
+                op,
+                thread: thread.clone(),
+            };
+            match self.send_command_parsed(Packet::Command(GdbCommand::Base(select)))? {
+                GdbResponse::Ok => {}
+                GdbResponse::Error { code } => {
+                    return Err(format!(
+                        "No thread {thread} on the target, the stub answered with error 0x{code:02x}"
+                    )
+                    .into());
+                }
+                other => {
+                    return Err(
+                        format!("Expected OK selecting thread {thread}, got: {other}").into(),
+                    )
+                }
+            }
+        }
+        self.current_thread = thread;
+        self.cached_state = CachedState::default();
+        Ok(())
+    }
+
+    /// The largest packet the stub takes, from its advertised `PacketSize`
+    pub fn stub_packet_size(&self) -> Option<usize> {
+        self.stub_features
+            .iter()
+            .find_map(|feature| feature.strip_prefix("PacketSize="))
+            .and_then(|size| usize::from_str_radix(size, 16).ok())
+    }
+
+    /// Where line info is coming from
+    pub fn debug_info(&self) -> &DebugInfo {
+        &self.debug_info
+    }
+
+    /// Whether there's line info to map addresses to source with. Symbols come from the symbol
+    /// table and work either way
+    pub fn has_debug_info(&self) -> bool {
+        self.addr2line_stepper.is_some()
+            && matches!(
+                self.debug_info,
+                DebugInfo::Embedded | DebugInfo::Separate(_)
+            )
+    }
+
+    /// `module`func+0xoff at file:line` for `addr`, leaving out whatever isn't known. Every pane
+    /// labels addresses through this, so they all agree
+    pub fn symbolize(&self, addr: u32) -> String {
+        self.symbol_cache()
+            .get_or_insert_with(addr, || self.format_symbol(addr))
+    }
+
+    /// Name of the loaded image `addr` is code of, the ELF's file name if it's in one of its
+    /// executable sections. There's only the one ELF, so code anywhere else, a boot ROM say, is
+    /// `None`
+    pub fn image_of(&self, addr: u32) -> Option<&str> {
+        if !self.elf_info.as_ref()?.is_code(addr.into()) {
+            return None;
+        }
+        self.module_name.as_deref()
+    }
+
+    /// Just `func+0xoff` for `addr`, `None` if no symbol covers it. For labels that sit next to
+    /// the address rather than stand in for it
+    pub fn symbol_offset(&self, addr: u32) -> Option<String> {
+        let label = self
+            .symbol_offset_cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get_or_insert_with(addr, || {
+                self.find_symbol_at_address(addr as u64)
+                    .map(|(symbol, offset)| format!("{}+0x{offset:x}", symbol.name))
+                    .unwrap_or_default()
+            });
+        (!label.is_empty()).then_some(label)
+    }
+
+    fn format_symbol(&self, addr: u32) -> String {
+        let mut out = String::new();
+        if let Some(module) = &self.module_name {
+            out.push_str(module);
+            out.push('`');
+        }
+        match self.find_symbol_at_address(addr as u64) {
+            Some((symbol, offset)) => out.push_str(&format!("{}+0x{offset:x}", symbol.name)),
+            None => out.push_str(&format!("0x{addr:x}")),
+        }
+        let location = self
+            .addr2line_stepper
+            .as_ref()
+            .and_then(|stepper| stepper.map_addr(addr as u64).ok().flatten());
+        if let Some((path, line)) = location {
+            let file = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string());
+            out.push_str(&format!(" at {file}:{line}"));
+        }
+        out
+    }
+
+    pub fn load_waveform(&mut self, wave_path: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        self.load_waveform_with_options(wave_path, &WaveLoadOptions::default())
+    }
+
+    /// [`Client::load_waveform`], loaded the same way dang was told to load it
+    pub fn load_waveform_with_options(
+        &mut self,
+        wave_path: PathBuf,
+        options: &WaveLoadOptions,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.wave_tracker = Some(WaveformTracker::new_with_options(wave_path, options)?);
+        Ok(())
+    }
+
+    /// Whether the waveform file has been rewritten since the tracker read it. False without one
+    pub fn waveform_is_stale(&self) -> bool {
+        self.wave_tracker
+            .as_ref()
+            .is_some_and(|tracker| tracker.is_stale())
+    }
+
+    /// Whether dang's waveform still matches the file on disk, with its `wavecheck`. `None`
+    /// when it does, or when the stub can't tell, otherwise what it said. dang can't load a new
+    /// waveform under a session, so what it says asks for a restart
+    pub fn stub_waveform_change(&mut self) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let output = match self.send_monitor_command("wavecheck") {
+            Ok(output) => output,
+            Err(e) if e.is::<UnsupportedByStub>() => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let output = output.trim();
+        if output == "ok" || output.starts_with(dang::gdb::UNKNOWN_COMMAND) {
+            return Ok(None);
+        }
+        Ok(Some(output.to_string()))
+    }
+
+    /// Get the current program counter (PC) from registers
     pub fn get_current_pc(&mut self) -> Result<PC, Box<dyn std::error::Error>> {
-        if let Some(pc) = self.cached_state.pc {
+        if let Some(pc) = self.cached_state.pc.filter(|_| !self.observer) {
             return Ok(pc);
         }
 
@@ -710,10 +2274,16 @@ impl Client {
         }
     }
 
-    /// Show current instruction and next 3 instructions using raki decoder and ELF data
+    /// The current instruction and the ones laid out after it, `count` in all. Capped at
+    /// [`MAX_INSTRUCTION_WINDOW`], and fewer where the section ends first.
+    ///
+    /// The count sticks: the next stop's `stop_context` brings back enough code for it
     pub fn get_current_and_next_inst(
         &mut self,
+        count: usize,
     ) -> Result<Vec<Instruction>, Box<dyn std::error::Error>> {
+        let count = count.clamp(1, MAX_INSTRUCTION_WINDOW);
+        self.instruction_window = count;
         // Get current PC using the dedicated method
         let pc = self.get_current_pc()?;
 
@@ -724,8 +2294,7 @@ impl Client {
             log::debug!("Current function: {} + 0x{:x}", symbol.name, offset);
         }
 
-        // Get instruction bytes directly from ELF binary (16 bytes = 4 instructions)
-        let instruction_bytes = self.get_instruction_bytes_from_elf(pc)?;
+        let instruction_bytes = self.get_instruction_window(pc, count * MAX_INSTRUCTION_LEN)?;
 
         // Determine ISA based on ELF info
         let _isa = if let Some(elf_info) = &self.elf_info {
@@ -737,210 +2306,1414 @@ impl Client {
         } else {
             return Err("No ELF info available. Call load_elf_info() first".into());
         };
-        let mut rv = Vec::new();
-        let mut start = 0;
-        while start + 4 < 12 {
-            let ichunk1 = &instruction_bytes[start..start + 2];
-
-            let ichunk = &instruction_bytes[start..start + 4];
-
-            let uu16 = u16::from_le_bytes(ichunk1.try_into().unwrap());
-            let uu32 = u32::from_le_bytes(ichunk.try_into().unwrap());
-
-            let u16inst = uu16
-                .decode(Isa::Rv32)
-                .inspect_err(|e| log::error!("u16 err is {e:?}, 0x{uu16:x}"))
-                .inspect(|arg| log::debug!("{arg}"))
-                .map(|val| Instruction(val, pc.add(start as u32)))
-                .ok();
-            let u32inst = uu32
-                .decode(Isa::Rv32)
-                .map(|val| Instruction(val, pc.add(start as u32)))
-                .ok();
-            match (u16inst, u32inst) {
-                (Some(inst16), None) => {
-                    start += 2;
-                    rv.push(inst16);
-                }
-                (None, Some(inst32)) => {
-                    start += 4;
-                    rv.push(inst32)
-                }
+        // the code the last stop brought back from the target while it's still good and covers
+        // the window, the ELF's otherwise
+        let from_target = self
+            .cached_state
+            .code
+            .as_deref()
+            .filter(|_| !self.observer)
+            .map(|code| decode_instruction_window(code, pc, count))
+            .filter(|insts| insts.len() == count);
+        let rv =
+            from_target.unwrap_or_else(|| decode_instruction_window(&instruction_bytes, pc, count));
+
+        //let rv= instruction_bytes.into_iter().array_chunks::<4>().map(|val| u32::from_le_bytes(val).decode(isa)).collect();
+
+        Ok(rv)
+    }
+
+    /// Every address the target has a software breakpoint at, from dang's `breakpoints` monitor
+    /// command. Sorted, and each address at most once
+    pub fn target_breakpoints(&mut self) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+        Ok(self
+            .target_breakpoint_entries()?
+            .into_iter()
+            .map(|entry| entry.addr)
+            .collect())
+    }
+
+    /// [`Client::target_breakpoints`] with each one's hit count and the hits it has left to
+    /// ignore
+    pub fn target_breakpoint_entries(
+        &mut self,
+    ) -> Result<Vec<BreakpointEntry>, Box<dyn std::error::Error>> {
+        let output = self.send_monitor_command("breakpoints")?;
+        output
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.parse().map_err(|e: String| e.into()))
+            .collect()
+    }
+
+    /// Have the target let the next `count` hits of the breakpoint at `addr` go by, without
+    /// stopping or coming back to us for each one
+    pub fn set_breakpoint_ignore(
+        &mut self,
+        addr: u32,
+        count: u32,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let output = self.send_monitor_command(&format!("bp_ignore {addr:x} {count}"))?;
+        let output = output.trim();
+        if output.starts_with("will ignore") {
+            Ok(())
+        } else {
+            Err(format!("bp_ignore 0x{addr:x} failed: {output}").into())
+        }
+    }
+
+    /// Set a software breakpoint at the specified address. Z0 is OK either way, so whether the
+    /// target already had it comes from [`Client::target_breakpoints`]
+    pub fn set_breakpoint(
+        &mut self,
+        addr: u32,
+    ) -> Result<BreakpointChange, Box<dyn std::error::Error>> {
+        self.ensure_driver()?;
+        let already_set = self.target_breakpoints()?.contains(&addr);
+        let command = Base::Z0 { addr };
+        let response =
+            self.send_command_parsed(Packet::Command(GdbCommand::Base(command.clone())))?;
+
+        match response {
+            crate::response::GdbResponse::Ok if already_set => Ok(BreakpointChange::AlreadySet),
+            crate::response::GdbResponse::Ok => Ok(BreakpointChange::Changed),
+            GdbResponse::Unsupported => Err(UnsupportedByStub::new(&command).into()),
+            _ => Err(format!(
+                "Failed to set breakpoint at address 0x{:x}: {}",
+                addr, response
+            )
+            .into()),
+        }
+    }
+
+    /// Remove a software breakpoint at the specified address
+    pub fn remove_breakpoint(
+        &mut self,
+        addr: u32,
+    ) -> Result<BreakpointChange, Box<dyn std::error::Error>> {
+        self.ensure_driver()?;
+        let command = Base::Z0Remove { addr };
+        let response =
+            self.send_command_parsed(Packet::Command(GdbCommand::Base(command.clone())))?;
+
+        match response {
+            crate::response::GdbResponse::Ok => Ok(BreakpointChange::Changed),
+            crate::response::GdbResponse::Error { code: EINVAL } => Ok(BreakpointChange::NotSet),
+            GdbResponse::Unsupported => Err(UnsupportedByStub::new(&command).into()),
+            _ => Err(format!(
+                "Failed to remove breakpoint at address 0x{:x}: {}",
+                addr, response
+            )
+            .into()),
+        }
+    }
 
-                _ => {
-                    if start == 0 {
-                        panic!("THERE S A BOMB IN MY CAR");
+    /// Set a breakpoint at the specified file and line number, only on the statement at `column`
+    /// if there's one
+    pub fn set_breakpoint_at_line(
+        &mut self,
+        file_path: &str,
+        line: u64,
+        column: Option<u64>,
+    ) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+        let addresses = self.addresses_for_location(file_path, line, column)?;
+        let mut set_addresses = Vec::new();
+        for &addr32 in &addresses {
+            match self.set_breakpoint(addr32) {
+                Ok(_) => set_addresses.push(addr32),
+                Err(e) => {
+                    // If we fail to set a breakpoint, remove any we've already set and return error
+                    for &cleanup_addr in &set_addresses {
+                        let _ = self.remove_breakpoint(cleanup_addr);
                     }
-                    log::debug!("Done")
+                    return Err(format!(
+                        "Failed to set breakpoint at address 0x{:x}: {}",
+                        addr32, e
+                    )
+                    .into());
                 }
             }
         }
 
-        //let rv= instruction_bytes.into_iter().array_chunks::<4>().map(|val| u32::from_le_bytes(val).decode(isa)).collect();
+        Ok(set_addresses)
+    }
+
+    /// Every address the line info puts at `file_path:line`, without touching the target
+    pub fn addresses_for_line(
+        &self,
+        file_path: &str,
+        line: u64,
+    ) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+        self.addresses_for_location(file_path, line, None)
+    }
+
+    /// [`Client::addresses_for_line`], narrowed down to the statement at `column` on the line.
+    /// Without a column, or without columns in the line info, that's the whole line
+    pub fn addresses_for_location(
+        &self,
+        file_path: &str,
+        line: u64,
+        column: Option<u64>,
+    ) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+        let Some(ref stepper) = self.addr2line_stepper else {
+            return Err(
+                "No debug information available - unable to resolve file:line to address".into(),
+            );
+        };
+        let path = std::path::Path::new(file_path);
+        let addresses = stepper.find_addresses_for_line(path, line, column)?;
+
+        if addresses.is_empty() {
+            let location = match column {
+                Some(column) => format!("{file_path}:{line}:{column}"),
+                None => format!("{file_path}:{line}"),
+            };
+            return Err(format!("No executable code found for {location}").into());
+        }
+        // Convert u64 to u32 (assuming 32-bit addresses for now)
+        Ok(addresses.into_iter().map(|addr| addr as u32).collect())
+    }
+
+    /// Get the current source line for the current PC
+    pub fn get_current_source_line(
+        &mut self,
+    ) -> Result<Option<crate::addr2line_stepper::SourceLine>, Box<dyn std::error::Error>> {
+        let pc = self.get_current_pc()?;
+        if let Some(ref stepper) = self.addr2line_stepper {
+            Ok(stepper.current_line(pc.as_u64())?)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// How far ahead of the PC the source preview looks, the instruction window or
+    /// [`SOURCE_PREVIEW_INSTRUCTIONS`], whichever is further
+    fn source_preview_instructions(&self) -> usize {
+        self.instruction_window.max(SOURCE_PREVIEW_INSTRUCTIONS)
+    }
+
+    /// Get the next `count` source lines after the current PC using upcoming instruction addresses
+    pub fn get_next_source_lines(
+        &mut self,
+        count: usize,
+    ) -> Result<Vec<crate::addr2line_stepper::SourceLine>, Box<dyn std::error::Error>> {
+        let pc = self.get_current_pc()?;
+
+        if let Some(ref stepper) = self.addr2line_stepper {
+            // Skip the first address as it's the current instruction
+            let next_addrs = self
+                .iter_instruction_addrs(pc, self.source_preview_instructions())?
+                .skip(1);
+            Ok(stepper.next_lines_from_instructions(pc.as_u64(), next_addrs, count)?)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Get the next source lines after the current PC from the same file.
+    ///
+    /// These follow the instructions laid out after the PC, so the preview shows what runs next
+    /// rather than what's written next. Falls back to the lines below the current one when there
+    /// is nothing to decode
+    pub fn get_consecutive_source_lines_after_current(
+        &mut self,
+        count: usize,
+    ) -> Result<Vec<crate::addr2line_stepper::SourceLine>, Box<dyn std::error::Error>> {
+        let pc = self.get_current_pc()?;
+        let Some(current_line) = self.get_current_source_line()? else {
+            return Ok(Vec::new());
+        };
+        let Some(ref stepper) = self.addr2line_stepper else {
+            return Ok(Vec::new());
+        };
+
+        let preview = self.source_preview_instructions();
+        let from_instructions = match self.iter_instruction_addrs(pc, preview) {
+            Ok(addrs) => stepper
+                .next_lines_from_instructions(pc.as_u64(), addrs.skip(1), count)?
+                .into_iter()
+                .filter(|line| line.path == current_line.path)
+                .collect(),
+            Err(e) => {
+                log::debug!("no instructions to preview from 0x{pc}: {e}");
+                Vec::new()
+            }
+        };
+        if !from_instructions.is_empty() {
+            return Ok(from_instructions);
+        }
+
+        Ok(stepper.get_consecutive_lines_after(&current_line.path, current_line.line, count)?)
+    }
+
+    /// How many times each line in `lines` of `file` executed over the whole trace.
+    ///
+    /// Only the PCs belonging to `lines` are fetched from the target, so keep the range to what
+    /// is actually being displayed
+    pub fn line_heat(
+        &mut self,
+        file: &Path,
+        lines: RangeInclusive<u64>,
+    ) -> Result<Vec<(u64, u64)>, Box<dyn std::error::Error>> {
+        let stepper = self
+            .addr2line_stepper
+            .as_ref()
+            .ok_or("No debug info available. Call load_elf_info() first")?;
+
+        if !self.line_heat_cache.contains_key(file) {
+            let rows = stepper.line_table_for_file(file)?;
+            self.line_heat_cache.insert(
+                file.to_path_buf(),
+                LineHeatCache {
+                    rows,
+                    pcs: HashMap::new(),
+                },
+            );
+        }
+
+        let mut heat: BTreeMap<u64, u64> = lines.clone().map(|line| (line, 0)).collect();
+
+        // Address range covering the window: first row in it up to the row that follows the last
+        let cache = &self.line_heat_cache[file];
+        let window_rows: Vec<u64> = cache
+            .rows
+            .iter()
+            .filter(|(_, line)| lines.contains(line))
+            .map(|(addr, _)| *addr)
+            .collect();
+        let (Some(&lo), Some(&last)) = (window_rows.first(), window_rows.last()) else {
+            return Ok(heat.into_iter().collect());
+        };
+        let hi = cache
+            .rows
+            .range(last + 1..)
+            .next()
+            .map(|(addr, _)| addr - 1)
+            .unwrap_or(last + 3);
+
+        let output = self.send_pc_index_command(&format!("coverage {lo:x} {hi:x}"))?;
+
+        let stepper = self.addr2line_stepper.as_ref().unwrap();
+        let cache = self.line_heat_cache.get_mut(file).unwrap();
+        for entry in output.lines() {
+            let Some((pc, count)) = entry.trim().split_once(' ') else {
+                continue;
+            };
+            let (Ok(pc), Ok(count)) = (u64::from_str_radix(pc, 16), count.parse::<u64>()) else {
+                log::warn!("Unexpected coverage entry: {entry}");
+                continue;
+            };
+
+            let line = match cache.pcs.get(&pc) {
+                Some(line) => *line,
+                None => {
+                    let line = stepper
+                        .map_addr(pc)?
+                        .filter(|(path, _)| path.ends_with(file) || file.ends_with(path))
+                        .map(|(_, line)| line);
+                    cache.pcs.insert(pc, line);
+                    line
+                }
+            };
+
+            // A line runs once per visit to any of its instructions, so take the busiest one
+            if let Some(slot) = line.and_then(|line| heat.get_mut(&line)) {
+                *slot = (*slot).max(count);
+            }
+        }
+
+        Ok(heat.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+pub mod test_utils {
+    use std::net::TcpListener;
+    use std::thread;
+
+    pub fn create_test_listener() -> (TcpListener, u16) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        (listener, port)
+    }
+
+    pub fn start_dang_instance(listener: TcpListener) -> thread::JoinHandle<()> {
+        start_dang_instance_with_options(listener, Default::default())
+    }
+
+    pub fn start_dang_instance_with_options(
+        listener: TcpListener,
+        options: dang::DangOptions,
+    ) -> thread::JoinHandle<()> {
+        start_dang_instance_with_elf(listener, options, "test_data/ibex/hello_test.elf")
+    }
+
+    /// Serve a different ELF over the same trace, `elf` relative to the workspace root
+    pub fn start_dang_instance_with_elf(
+        listener: TcpListener,
+        options: dang::DangOptions,
+        elf: &'static str,
+    ) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            let workspace_root = std::env::current_dir()
+                .unwrap()
+                .parent()
+                .unwrap()
+                .to_path_buf();
+
+            let wave_path = workspace_root.join("test_data/ibex/sim.fst");
+            let mapping_path = workspace_root.join("test_data/ibex/signal_get.py");
+            let elf_path = workspace_root.join(elf);
+
+            let _ = dang::start_with_options_and_listener(
+                wave_path,
+                mapping_path,
+                elf_path,
+                options,
+                listener,
+            );
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_utils::*;
+    use super::*;
+    use dang::breakpoints::DEFAULT_KIND;
+
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    /// A stub that turns down everything with an empty reply
+    fn start_empty_stub() -> u16 {
+        let (listener, port) = create_test_listener();
+        std::thread::spawn(move || {
+            let (mut strm, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 4096];
+            while let Ok(n) = strm.read(&mut buf) {
+                if n == 0 || strm.write_all(b"$#00").is_err() {
+                    break;
+                }
+            }
+        });
+        port
+    }
+
+    /// A stub that answers the n-th packet it gets with `replies[n]` as one write, and then
+    /// hangs up
+    fn start_canned_stub(replies: Vec<&'static [u8]>) -> u16 {
+        let (listener, port) = create_test_listener();
+        std::thread::spawn(move || {
+            let (mut strm, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 4096];
+            for reply in replies {
+                match strm.read(&mut buf) {
+                    Ok(n) if n > 0 => strm.write_all(reply).unwrap(),
+                    _ => break,
+                }
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn test_repeated_stop_replies_coalesced() {
+        crate::init_test_logger();
+        // the stub answered a retransmitted step as well as the original, and acked both
+        let port = start_canned_stub(vec![b"+$S05#b8+$S05#b8", b"$OK#9a"]);
+        let mut client = Client::new_with_port(port);
+        client.set_no_ack(true);
+
+        let step = Packet::Command(GdbCommand::Resume(Resume::Step));
+        assert_eq!(client.send_command(&step).unwrap().as_slice(), b"S05");
+        let detach = Packet::Command(GdbCommand::Base(Base::D));
+        assert_eq!(client.send_command(&detach).unwrap().as_slice(), b"OK");
+        assert!(client.take_async_output().is_empty());
+    }
+
+    #[test]
+    fn test_stale_stop_reply_set_aside() {
+        crate::init_test_logger();
+        // a stop reply from a resume that was answered long ago, ahead of the real answer
+        let port = start_canned_stub(vec![b"$T05#b9$1#31", b"$S05#b8"]);
+        let mut client = Client::new_with_port(port);
+        client.set_no_ack(true);
+
+        let attached = Packet::Command(GdbCommand::Base(Base::QAttached));
+        assert_eq!(client.send_command(&attached).unwrap().as_slice(), b"1");
+        assert_eq!(client.take_async_output(), [format!("{STALE_STOP}: T05")]);
+        assert!(client.take_async_output().is_empty());
+
+        // a stop reply to `?` is the answer
+        let halted = Packet::Command(GdbCommand::Base(Base::QuestionMark));
+        assert_eq!(client.send_command(&halted).unwrap().as_slice(), b"S05");
+        assert!(client.take_async_output().is_empty());
+    }
+
+    #[test]
+    fn test_unsupported_by_stub() {
+        crate::init_test_logger();
+        let mut client = Client::new_with_port(start_empty_stub());
+        let unsupported = |err: Box<dyn std::error::Error>| {
+            err.downcast_ref::<UnsupportedByStub>()
+                .map(|e| e.command.clone())
+        };
+
+        let err = client.get_executable_path().unwrap_err();
+        assert_eq!(unsupported(err).as_deref(), Some("qXfer:exec-file:read"));
+        let err = client.send_monitor_command("time_idx").unwrap_err();
+        assert_eq!(unsupported(err).as_deref(), Some("qRcmd"));
+        let err = client.remove_breakpoint(0x100).unwrap_err();
+        assert_eq!(unsupported(err).as_deref(), Some("z0"));
+        assert_eq!(
+            UnsupportedByStub::new(&Base::Z0 { addr: 0 }).to_string(),
+            "the stub doesn't support Z0"
+        );
+
+        // an empty memory read isn't the stub turning the read down
+        let err = client.read_memory(0x100, 4).unwrap_err();
+        assert_eq!(unsupported(err), None);
+    }
+
+    #[test]
+    fn test_instruction_bytes_column() {
+        // addi a0, a0, 0 ; c.nop ; addi a0, a0, 0
+        let bytes = [
+            0x13, 0x05, 0x05, 0x00, 0x01, 0x00, 0x13, 0x05, 0x05, 0x00, 0x00, 0x00,
+        ];
+        let insts = decode_instruction_window(&bytes, PC::_32(0x100200), 3);
+        assert_eq!(insts.len(), 3);
+
+        assert_eq!(insts[0].bytes(), &[0x13, 0x05, 0x05, 0x00]);
+        assert_eq!(insts[0].bytes_column(), "13 05 05 00");
+
+        assert_eq!(insts[1].pc().as_u32(), 0x100204);
+        assert_eq!(insts[1].bytes(), &[0x01, 0x00]);
+        assert_eq!(insts[1].bytes_column(), "01 00      ");
+    }
+
+    #[test]
+    fn test_undecodable_instruction_placeholder() {
+        // 0x7f is a reserved major opcode, the listing should carry on past it
+        let bytes = [
+            0xff, 0xff, 0x13, 0x05, 0x05, 0x00, 0x13, 0x05, 0x05, 0x00, 0x00, 0x00,
+        ];
+        let insts = decode_instruction_window(&bytes, PC::_32(0x0), 3);
+        assert!(!insts[0].is_decoded());
+        assert_eq!(insts[0].bytes(), &[0xff, 0xff]);
+        assert_eq!(insts[0].to_string(), ".word 0xffff  <undecodable>");
+        assert_eq!(insts[1].pc().as_u32(), 0x2);
+        assert!(insts[1].is_decoded());
+    }
+
+    #[test]
+    fn test_instruction_window_count_and_end() {
+        // addi a0, a0, 0 ; c.nop ; addi a0, a0, 0 ; c.li a0, 0
+        let bytes = [
+            0x13, 0x05, 0x05, 0x00, 0x01, 0x00, 0x13, 0x05, 0x05, 0x00, 0x01, 0x45,
+        ];
+        assert_eq!(decode_instruction_window(&bytes, PC::_32(0x0), 2).len(), 2);
+
+        // a compressed instruction in the last two bytes is still there
+        let insts = decode_instruction_window(&bytes, PC::_32(0x0), MAX_INSTRUCTION_WINDOW);
+        assert_eq!(insts.len(), 4);
+        assert_eq!(insts[3].pc().as_u32(), 0xa);
+        assert!(insts[3].is_decoded());
+
+        // half of a full width one isn't
+        let insts = decode_instruction_window(&bytes[..8], PC::_32(0x0), MAX_INSTRUCTION_WINDOW);
+        assert_eq!(insts.len(), 2);
+    }
+
+    #[test]
+    fn test_instruction_addrs_mixed_lengths() {
+        // addi a0, a0, 0 ; c.nop ; addi a0, a0, 0 ; c.li a0, 0 ; reserved ; addi a0, a0, 0
+        let bytes = [
+            0x13, 0x05, 0x05, 0x00, 0x01, 0x00, 0x13, 0x05, 0x05, 0x00, 0x01, 0x45, 0xff, 0xff,
+            0x13, 0x05, 0x05, 0x00,
+        ];
+        let addrs: Vec<u64> = InstructionAddrs::new(&bytes, 0x100200, 10).collect();
+        // stops at the undecodable halfword instead of guessing past it
+        assert_eq!(addrs, vec![0x100200, 0x100204, 0x100206, 0x10020a]);
+
+        let addrs: Vec<u64> = InstructionAddrs::new(&bytes, 0x100200, 2).collect();
+        assert_eq!(addrs, vec![0x100200, 0x100204]);
+
+        // half of a full width instruction at the end of the bytes
+        let addrs: Vec<u64> = InstructionAddrs::new(&bytes[..8], 0x0, 10).collect();
+        assert_eq!(addrs, vec![0x0, 0x4]);
+    }
+
+    #[test]
+    fn test_source_preview_after_compressed_instruction() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+        client.load_elf_info().expect("Failed to load elf info");
+
+        // puthex starts with `c.li a4, 8` (line 21), the next instruction at 0x1000a6 is line 28.
+        // Assuming 4 byte spacing lands on 0x1000a8, which is the inlined putchar at line 8
+        client.set_breakpoint(0x1000a4).unwrap();
+        client.continue_execution().unwrap();
+        assert_eq!(client.get_current_pc().unwrap().as_u32(), 0x1000a4);
+
+        let addrs: Vec<u64> = client
+            .iter_instruction_addrs(PC::_32(0x1000a4), 3)
+            .unwrap()
+            .collect();
+        assert_eq!(addrs, vec![0x1000a4, 0x1000a6, 0x1000a8]);
+
+        let current = client.get_current_source_line().unwrap().unwrap();
+        assert_eq!(current.line, 21);
+        let next = client
+            .get_consecutive_source_lines_after_current(1)
+            .unwrap();
+        assert_eq!(next[0].line, 28);
+
+        drop(handle);
+    }
+
+    #[test]
+    fn test_pc_visit_count() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+        client.load_elf_info().expect("Failed to load elf info");
+
+        // hello_test.elf is built with RVC
+        assert_eq!(client.instruction_alignment(), 2);
+
+        // main is entered, nothing ever executes from address 0
+        assert!(client.pc_visit_count(0x1003d0).unwrap() > 0);
+        assert_eq!(client.pc_visit_count(0x0).unwrap(), 0);
+
+        // puthex opens with two compressed instructions, then a lui
+        assert_eq!(client.instruction_start(0x1000a6), Some(0x1000a6));
+        assert_eq!(client.instruction_start(0x1000aa), Some(0x1000a8));
+        assert_eq!(client.instruction_start(0x1000ae), Some(0x1000ac));
+        assert_eq!(client.instruction_start(0x0), None);
+
+        drop(handle);
+    }
+
+    #[test]
+    fn test_symbolize() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+        client.load_elf_info().expect("Failed to load elf info");
+
+        let expected = "hello_test.elf`puthex+0x0 at simple_system_common.c:21";
+        assert_eq!(client.symbolize(0x1000a4), expected);
+        assert_eq!(client.symbolize(0x1000a4), expected);
+        assert_eq!(
+            client.symbolize(0x1000a6),
+            "hello_test.elf`puthex+0x2 at simple_system_common.c:28"
+        );
+        let stats = client.stats().symbol_cache;
+        assert_eq!((stats.hits, stats.misses, stats.size), (1, 2, 2));
+
+        assert_eq!(
+            client.symbol_offset(0x1000a6).as_deref(),
+            Some("puthex+0x2")
+        );
+        assert_eq!(client.image_of(0x1000a6), Some("hello_test.elf"));
+        assert_eq!(client.image_of(0x10), None);
+
+        // reloading the ELF starts the cache over
+        client.load_elf_info().unwrap();
+        assert_eq!(client.stats().symbol_cache.size, 0);
+
+        drop(handle);
+    }
+
+    #[test]
+    fn test_parse_reg_history() {
+        assert_eq!(
+            parse_reg_history("40\n10 1ff0\n22 x\n").unwrap(),
+            (Some(40), vec![(10, Some(0x1ff0)), (22, None)])
+        );
+        assert_eq!(parse_reg_history("end\n").unwrap(), (None, vec![]));
+        assert!(
+            parse_reg_history("usage: reg_history <0-31> <from_idx> <count> [stride]").is_err()
+        );
+        assert!(parse_reg_history("end\n10\n").is_err());
+        assert!(parse_reg_history("").is_err());
+    }
+
+    #[test]
+    fn test_parse_reg_change() {
+        assert_eq!(
+            parse_reg_change("10 4021 40210 0").unwrap(),
+            (
+                10,
+                Some(RegChange {
+                    time_idx: 4021,
+                    time: 40210,
+                    previous: Some(0)
+                })
+            )
+        );
+        assert_eq!(
+            parse_reg_change("2 0 0 -").unwrap().1.unwrap().previous,
+            None
+        );
+        assert_eq!(parse_reg_change("5 none").unwrap(), (5, None));
+        assert!(parse_reg_change("5").is_err());
+        assert!(parse_reg_change("5 12 zz 0").is_err());
+    }
+
+    #[test]
+    fn test_parse_resume_stats() {
+        assert_eq!(
+            parse_resume_stats("12 40 100 163").unwrap(),
+            ResumeStats {
+                pc_advances: 12,
+                changes_crossed: 40,
+                start_time_idx: 100,
+                end_time_idx: 163
+            }
+        );
+        assert!(parse_resume_stats("12 40 100").is_err());
+        assert!(parse_resume_stats("12 40 100 x").is_err());
+    }
+
+    #[test]
+    fn test_resume_stats_after_step() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+        client.load_elf_info().expect("Failed to load elf info");
+
+        let start = client.get_time_idx().unwrap();
+        assert!(client.step().unwrap());
+        let stats = client.resume_stats().unwrap();
+        assert_eq!(stats.pc_advances, 1);
+        assert_eq!(stats.start_time_idx, start);
+        assert_eq!(stats.end_time_idx, client.get_time_idx().unwrap());
+        assert!(stats.changes_crossed >= 1);
+        drop(handle);
+    }
+
+    #[test]
+    fn test_find_instruction_jal() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+        client.load_elf_info().expect("Failed to load elf info");
+
+        let jal = InstPredicate::Mnemonic("jal".to_string());
+        let start = client.get_time_idx().unwrap();
+        // _start calls main with a jal at 0x1003ba
+        let FindOutcome::Found { time_idx, pc, .. } = client
+            .find_instruction(SearchDirection::Forward, &jal, 1000)
+            .unwrap()
+        else {
+            panic!("should find the jal into main");
+        };
+        assert_eq!(pc, 0x1003ba);
+        assert!(time_idx > start);
+        assert_eq!(client.get_current_pc().unwrap().as_u32(), 0x1003ba);
+        assert_eq!(client.get_time_idx().unwrap(), time_idx);
+
+        // searching on doesn't find the same one again
+        match client
+            .find_instruction(SearchDirection::Forward, &jal, 1000)
+            .unwrap()
+        {
+            FindOutcome::Found { time_idx: next, .. } => assert!(next > time_idx),
+            other => panic!("main calls more functions, got {other:?}"),
+        }
+
+        let ecall = InstPredicate::Mnemonic("ecall".to_string());
+        assert_eq!(
+            client
+                .find_instruction(SearchDirection::Backward, &ecall, 10)
+                .unwrap(),
+            FindOutcome::OverBudget { scanned: 10 }
+        );
+        drop(handle);
+    }
+
+    #[test]
+    fn test_parse_assertions() {
+        assert_eq!(
+            parse_assertion("3 x2 > 0x20000000").unwrap(),
+            Assertion {
+                id: 3,
+                check: "x2 > 0x20000000".to_string()
+            }
+        );
+        assert!(parse_assertion("x2 > 0").is_err());
+        assert_eq!(parse_violation("none").unwrap(), None);
+        assert_eq!(
+            parse_violation("1 4021 1ff00 pc outside 0x0-0xff").unwrap(),
+            Some(AssertionViolation {
+                id: 1,
+                time_idx: 4021,
+                observed: 0x1ff00,
+                check: "pc outside 0x0-0xff".to_string()
+            })
+        );
+        assert!(parse_violation("1 4021").is_err());
+    }
+
+    #[test]
+    fn test_assertion_stops_continue() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+        client.load_elf_info().expect("Failed to load elf info");
+
+        // the hello test's stack is nowhere near this high, so this trips on the first step
+        const SP: usize = 2;
+        let sp_floor = client.assert_reg(SP, ">=", 0xf000_0000).unwrap();
+        assert_eq!(sp_floor.check, "x2 >= 0xf0000000");
+        let pc_guard = client.assert_pc_range(0, 0xff, false).unwrap();
+        assert_eq!(
+            client.assertions().unwrap(),
+            vec![sp_floor.clone(), pc_guard]
+        );
+        assert!(client.assert_reg(SP, "=>", 0).is_err());
+        assert_eq!(client.last_assertion().unwrap(), None);
+
+        assert!(client.continue_execution().unwrap());
+        assert!(client.stopped_on_assertion());
+        assert!(!client.stopped_on_livelock());
+        let violation = client
+            .last_assertion()
+            .unwrap()
+            .expect("continue tripped it");
+        assert_eq!(violation.id, sp_floor.id);
+        assert_eq!(violation.check, sp_floor.check);
+        assert_eq!(violation.time_idx, client.get_time_idx().unwrap());
+        assert_eq!(violation.observed, client.read_gprs().unwrap()[SP]);
+        assert!(violation.observed < 0xf000_0000);
+
+        assert!(client.delete_assertion(sp_floor.id).unwrap());
+        assert!(!client.delete_assertion(sp_floor.id).unwrap());
+        assert_eq!(client.assertions().unwrap().len(), 1);
+        drop(handle);
+    }
+
+    #[test]
+    fn test_monitor_batch_is_one_round_trip() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+        client.step().unwrap();
+
+        let time_idx = client.send_monitor_command("time_idx").unwrap();
+        let stats = client.send_monitor_command("resume_stats").unwrap();
+        let before = client.packet_stats();
+        let replies = client
+            .send_monitor_batch(&["time_idx", "resume_stats", "meminfo"])
+            .unwrap();
+        assert_eq!(client.packet_stats().packets_sent, before.packets_sent + 1);
+        assert_eq!(replies.len(), 3);
+        assert_eq!(replies[0].as_ref().unwrap(), &time_idx);
+        assert_eq!(replies[1].as_ref().unwrap(), &stats);
+        assert!(replies[2].as_ref().unwrap().starts_with("pc_index"));
+
+        // a failing command doesn't take the rest down with it
+        let replies = client
+            .send_monitor_batch(&["nonsense", "batch time_idx", "time_idx"])
+            .unwrap();
+        assert_eq!(replies[0].as_ref().unwrap_err().command, "nonsense");
+        assert!(replies[1].is_err());
+        assert_eq!(replies[2].as_ref().unwrap(), &time_idx);
+
+        let report = client.stop_report().unwrap();
+        assert_eq!(report.stats, client.resume_stats().unwrap());
+        assert_eq!(report.assertion, None);
+        drop(handle);
+    }
+
+    #[test]
+    fn test_skip_ranges_fast_forward_continue() {
+        crate::init_test_logger();
+        let continue_to_puthex = |ranges: Option<(u32, u32)>| {
+            let (listener, port) = create_test_listener();
+            let handle = start_dang_instance(listener);
+            sleep(Duration::from_millis(1000));
+
+            let mut client = Client::new_with_port(port);
+            sleep(Duration::from_millis(200));
+            client
+                .initialize_gdb_session()
+                .expect("failed to init gdb session");
+            client.load_elf_info().expect("Failed to load elf info");
+            if let Some((lo, hi)) = ranges {
+                client.add_skip_range(lo, hi).unwrap();
+                client.set_fastforward_ranges(true).unwrap();
+            }
+            client.set_breakpoint(0x1000a4).unwrap();
+            assert!(client.continue_execution().unwrap());
+            assert_eq!(client.get_current_pc().unwrap().as_u32(), 0x1000a4);
+            (client, handle)
+        };
+
+        let (mut client, handle) = continue_to_puthex(None);
+        let stepped = client.resume_stats().unwrap();
+        drop(handle);
+
+        // crt0 and the library code below main, puthex included
+        let (mut client, handle) = continue_to_puthex(Some((0, 0x1003cf)));
+        let skipped = client.resume_stats().unwrap();
+        // the breakpoint inside the range still stopped it, on the same change
+        assert_eq!(skipped.end_time_idx, stepped.end_time_idx);
+        assert!(
+            skipped.pc_advances < stepped.pc_advances,
+            "{skipped:?} vs {stepped:?}"
+        );
+
+        let ranges = client.skip_ranges().unwrap();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!((ranges[0].lo, ranges[0].hi), (0, 0x1003cf));
+        assert!(client.add_skip_range(0x10, 0x20).is_ok());
+        assert!(client.delete_skip_range(ranges[0].id).unwrap());
+        assert!(!client.delete_skip_range(ranges[0].id).unwrap());
+        assert_eq!(client.skip_ranges().unwrap().len(), 1);
+        drop(handle);
+    }
+
+    #[test]
+    fn test_reg_last_change() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+        client.load_elf_info().expect("Failed to load elf info");
+
+        // step until a0 changes, it's written on the way into puts
+        const A0: usize = 10;
+        let a0 = client.read_gprs().unwrap()[A0];
+        let mut time_idx = client.get_time_idx().unwrap();
+        for _ in 0..2000 {
+            let before = time_idx;
+            assert!(client.step().unwrap(), "trace ended before a0 changed");
+            time_idx = client.get_time_idx().unwrap();
+            let now = client.read_gprs().unwrap()[A0];
+            if now == a0 {
+                continue;
+            }
+
+            let change = client.reg_last_change(A0).unwrap().expect("a0 has a value");
+            assert!(
+                before < change.time_idx && change.time_idx <= time_idx,
+                "a0 changed stepping from {before} to {time_idx}, reported {}",
+                change.time_idx
+            );
+            assert_eq!(change.previous, Some(a0));
+
+            let bulk = client.regs_last_change().unwrap();
+            assert_eq!(bulk.len(), 32);
+            assert_eq!(bulk[A0], Some(change));
+            drop(handle);
+            return;
+        }
+        panic!("a0 never changed from 0x{a0:x}");
+    }
+
+    #[test]
+    fn test_reg_history() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+
+        const SP: usize = 2;
+        let start = client.get_time_idx().unwrap();
+        let history: Vec<(u64, u32)> = client.reg_history(SP, 0..u64::MAX, 1).unwrap().collect();
+        assert!(history.len() > 3, "sp should change over the trace");
+        assert!(history.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(client.get_time_idx().unwrap(), start);
+
+        // the same values as going there and reading the registers
+        let spots = [
+            history[0],
+            history[history.len() / 2],
+            history[history.len() - 1],
+        ];
+        for (time_idx, sp) in spots {
+            client.goto_time_idx(time_idx).unwrap();
+            assert_eq!(client.read_gprs().unwrap()[SP], sp, "sp at {time_idx}");
+        }
+
+        // a window of it
+        let window = history[1].0..history[history.len() - 1].0;
+        let windowed: Vec<(u64, u32)> =
+            client.reg_history(SP, window.clone(), 1).unwrap().collect();
+        let expected: Vec<(u64, u32)> = history
+            .iter()
+            .copied()
+            .filter(|(time_idx, _)| window.contains(time_idx))
+            .collect();
+        assert_eq!(windowed, expected);
+
+        assert!(client.reg_history(32, 0..10, 1).is_err());
+        assert!(client.reg_history(SP, 0..10, 0).is_err());
+        drop(handle);
+    }
+
+    #[test]
+    fn test_goto_time_forward_and_back() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+
+        let start = client.get_time_idx().unwrap();
+        let start_pc = client.get_current_pc().unwrap().as_u32();
+
+        let ahead = client.goto_time(start + 500).unwrap();
+        assert!(ahead.time_idx >= start + 500);
+        assert_eq!(client.get_time_idx().unwrap(), ahead.time_idx);
+        assert_eq!(client.get_current_pc().unwrap().as_u32(), ahead.pc);
+        // the same PC the stub reads back from scratch
+        client.goto_time_idx(ahead.time_idx).unwrap();
+        assert_eq!(client.get_current_pc().unwrap().as_u32(), ahead.pc);
+
+        let back = client.goto_time(start).unwrap();
+        assert_eq!(back.time_idx, start);
+        assert!(back.time < ahead.time);
+        assert_eq!(client.get_current_pc().unwrap().as_u32(), start_pc);
+        assert_eq!(back.pc, start_pc);
+
+        let err = client.goto_time(u64::MAX).unwrap_err();
+        assert!(err.to_string().contains("out of range"), "{err}");
+        assert_eq!(client.get_time_idx().unwrap(), start);
+        drop(handle);
+    }
+
+    #[test]
+    fn test_get_time() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+
+        let (start, unit) = client.get_time().unwrap();
+        assert!(!unit.is_empty());
+        let start_idx = client.get_time_idx().unwrap();
+        let jump = client.goto_time(start_idx + 100).unwrap();
+        assert_eq!(client.get_time().unwrap(), (jump.time, unit));
+        assert!(jump.time > start);
+        drop(handle);
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("40210 ps"), Ok((40210, "ps".to_string())));
+        assert_eq!(parse_time("4021 10ns"), Ok((4021, "10ns".to_string())));
+        assert_eq!(parse_time("4021"), Ok((4021, String::new())));
+        assert!(parse_time("time index 9 is past the end of the trace").is_err());
+    }
+
+    #[test]
+    fn test_parse_time_jump() {
+        assert_eq!(
+            parse_time_jump("812 4060 1000a4"),
+            Ok(TimeJump {
+                time_idx: 812,
+                time: 4060,
+                pc: 0x1000a4,
+            })
+        );
+        assert!(parse_time_jump("usage: goto_time <time_idx>").is_err());
+        assert!(parse_time_jump("812 4060").is_err());
+    }
+
+    #[test]
+    fn test_stop_context_round_trips() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+
+        // what a redraw after a stop asks for
+        let redraw = |client: &mut Client| {
+            let pc = client.get_current_pc().unwrap().as_u32();
+            let time_idx = client.get_time_idx().unwrap();
+            let gprs = client.read_gprs().unwrap();
+            let insts: Vec<String> = client
+                .get_current_and_next_inst(DEFAULT_INSTRUCTION_WINDOW)
+                .unwrap()
+                .iter()
+                .map(|inst| format!("{:x} {inst}", inst.pc().as_u32()))
+                .collect();
+            (pc, time_idx, gprs, insts)
+        };
 
-        Ok(rv)
+        // the resume and the stop_context, nothing else
+        let before = client.packet_stats().packets_sent;
+        assert!(client.step().unwrap());
+        let bundled = redraw(&mut client);
+        assert_eq!(client.packet_stats().packets_sent, before + 2);
+
+        // the same stop piecewise, from scratch
+        client.set_use_stop_context(false);
+        client.goto_time_idx(bundled.1).unwrap();
+        assert_eq!(redraw(&mut client), bundled);
+
+        // the resume, a g and a time_idx for the stop, then a g for the registers
+        let before = client.packet_stats().packets_sent;
+        assert!(client.step().unwrap());
+        redraw(&mut client);
+        assert_eq!(client.packet_stats().packets_sent, before + 4);
+        drop(handle);
     }
 
-    /// Set a software breakpoint at the specified address
-    pub fn set_breakpoint(&mut self, addr: u32) -> Result<(), Box<dyn std::error::Error>> {
-        let response =
-            self.send_command_parsed(Packet::Command(GdbCommand::Base(Base::Z0 { addr })))?;
+    #[test]
+    fn test_breakpoint_stop_reports_swbreak() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
 
-        match response {
-            crate::response::GdbResponse::Ok => Ok(()),
-            _ => Err(format!(
-                "Failed to set breakpoint at address 0x{:x}: {}",
-                addr, response
-            )
-            .into()),
-        }
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+        client.load_elf_info().expect("Failed to load elf info");
+        assert!(
+            client.stub_features().iter().any(|f| f == "swbreak+"),
+            "swbreak+ missing from {:?}",
+            client.stub_features()
+        );
+
+        // Z0 writes no trap, the bytes read back are still the ELF's
+        let elf_bytes = client.get_instruction_window(PC::_32(0x1000a4), 4).unwrap();
+        client.set_breakpoint(0x1000a4).unwrap();
+        assert_eq!(client.read_memory(0x1000a4, 4).unwrap(), elf_bytes);
+
+        // the stop as it comes off the wire, so gdb doesn't back the PC up over the breakpoint
+        let reply = client
+            .send_command(&Packet::Command(GdbCommand::Resume(Resume::Continue)))
+            .unwrap();
+        let reply = String::from_utf8_lossy(reply.as_slice()).into_owned();
+        assert!(reply.starts_with("T05"), "{reply}");
+        assert!(reply.contains("swbreak:;"), "{reply}");
+        // past the client's cache, which the raw continue left alone
+        assert_eq!(client.stop_context(1).unwrap().unwrap().pc, 0x1000a4);
+        assert_eq!(client.read_memory(0x1000a4, 4).unwrap(), elf_bytes);
+        drop(handle);
     }
 
-    /// Remove a software breakpoint at the specified address
-    pub fn remove_breakpoint(&mut self, addr: u32) -> Result<(), Box<dyn std::error::Error>> {
-        let response =
-            self.send_command_parsed(Packet::Command(GdbCommand::Base(Base::Z0Remove { addr })))?;
+    #[test]
+    fn test_thread_round_trip() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
 
-        match response {
-            crate::response::GdbResponse::Ok => Ok(()),
-            _ => Err(format!(
-                "Failed to remove breakpoint at address 0x{:x}: {}",
-                addr, response
-            )
-            .into()),
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        assert_eq!(client.current_thread(), &ThreadId::Any);
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+
+        // one hart, number 1, from qC and from every stop
+        assert_eq!(client.current_thread(), &ThreadId::Specific(1));
+        client.set_thread(ThreadId::Specific(1)).unwrap();
+        client.step().unwrap();
+        assert_eq!(client.current_thread(), &ThreadId::Specific(1));
+        let time_idx = client.get_time_idx().unwrap();
+
+        // a hart that isn't there is an error off the wire, not silence, for `T` and both `H`s
+        let missing = [
+            Base::T {
+                thread: ThreadId::Specific(2),
+            },
+            Base::H {
+                op: ThreadOp::General,
+                thread: ThreadId::Specific(2),
+            },
+            Base::H {
+                op: ThreadOp::Continue,
+                thread: ThreadId::Specific(2),
+            },
+        ];
+        for packet in missing {
+            let packet = Packet::Command(GdbCommand::Base(packet));
+            assert!(matches!(
+                client.send_command_parsed(packet).unwrap(),
+                GdbResponse::Error { .. }
+            ));
         }
+        let err = client.set_thread(ThreadId::Specific(2)).unwrap_err();
+        assert!(err.to_string().contains("No thread 2"), "{err}");
+        assert!(client.set_thread(ThreadId::All).is_err());
+        assert_eq!(client.current_thread(), &ThreadId::Specific(1));
+
+        // and the connection carries on where it was
+        assert_eq!(client.get_time_idx().unwrap(), time_idx);
+        assert!(client.step().unwrap());
+        drop(handle);
     }
 
-    /// Set a breakpoint at the specified file and line number
-    pub fn set_breakpoint_at_line(
-        &mut self,
-        file_path: &str,
-        line: u64,
-    ) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
-        if let Some(ref stepper) = self.addr2line_stepper {
-            let path = std::path::Path::new(file_path);
-            let addresses = stepper.find_addresses_for_line(path, line)?;
-
-            if addresses.is_empty() {
-                return Err(format!("No executable code found for {}:{}", file_path, line).into());
-            }
-
-            let mut set_addresses = Vec::new();
-            for &addr in &addresses {
-                // Convert u64 to u32 (assuming 32-bit addresses for now)
-                let addr32 = addr as u32;
-                match self.set_breakpoint(addr32) {
-                    Ok(()) => set_addresses.push(addr32),
-                    Err(e) => {
-                        // If we fail to set a breakpoint, remove any we've already set and return error
-                        for &cleanup_addr in &set_addresses {
-                            let _ = self.remove_breakpoint(cleanup_addr);
-                        }
-                        return Err(format!(
-                            "Failed to set breakpoint at address 0x{:x}: {}",
-                            addr32, e
-                        )
-                        .into());
-                    }
-                }
-            }
+    #[test]
+    fn test_qsupported_features() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
 
-            Ok(set_addresses)
-        } else {
-            Err("No debug information available - unable to resolve file:line to address".into())
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        assert!(client.stub_features().is_empty());
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+
+        assert_eq!(
+            client.stub_packet_size(),
+            Some(dang::cli::PACKET_BUFFER_SIZE)
+        );
+        // gdb only offers reverse-stepi and reverse-continue when these are advertised
+        for feature in ["ReverseStep+", "ReverseContinue+", "qXfer:exec-file:read+"] {
+            assert!(
+                client.stub_features().iter().any(|f| f == feature),
+                "{feature} missing from {:?}",
+                client.stub_features()
+            );
         }
+        drop(handle);
     }
 
-    /// Get the current source line for the current PC
-    pub fn get_current_source_line(
-        &mut self,
-    ) -> Result<Option<crate::addr2line_stepper::SourceLine>, Box<dyn std::error::Error>> {
-        let pc = self.get_current_pc()?;
-        if let Some(ref stepper) = self.addr2line_stepper {
-            Ok(stepper.current_line(pc.as_u64())?)
-        } else {
-            Ok(None)
+    /// `.text` at 0x1000 and `.fastcode` at 0x2000, eight bytes each, with the gap between
+    /// them in no section at all
+    fn two_section_elf() -> ElfInfo {
+        let section = |name: &str, addr, file_offset| ExecSection {
+            name: name.to_string(),
+            addr,
+            size: 8,
+            file_offset,
+        };
+        ElfInfo {
+            entry_point: 0x1000,
+            is_32bit: true,
+            machine: 0xf3,
+            compressed: false,
+            exec_sections: vec![section(".text", 0x1000, 0), section(".fastcode", 0x2000, 8)],
+            symbols: Vec::new(),
+            elf_data: (0..16).collect(),
+            has_debug_line: false,
+            debuglink: None,
+            fingerprint: None,
         }
     }
 
-    /// Get the next 3 source lines after the current PC using upcoming instruction addresses
-    pub fn get_next_source_lines(
-        &mut self,
-        count: usize,
-    ) -> Result<Vec<crate::addr2line_stepper::SourceLine>, Box<dyn std::error::Error>> {
-        let pc = self.get_current_pc()?;
-        let instructions = self.get_current_and_next_inst()?;
-
-        if let Some(ref stepper) = self.addr2line_stepper {
-            // Extract addresses from instructions (skip the first one as it's current)
-            let next_addrs: Vec<u64> = instructions
-                .iter()
-                .skip(1)
-                .map(|inst| inst.pc().as_u64())
-                .collect();
-
-            Ok(stepper.next_lines_from_instructions(pc.as_u64(), next_addrs, count)?)
-        } else {
-            Ok(Vec::new())
+    #[test]
+    fn test_code_from_any_exec_section() {
+        let elf = two_section_elf();
+        assert_eq!(elf.code_from(0x1004).unwrap(), [4, 5, 6, 7]);
+        assert_eq!(
+            elf.code_from(0x2000).unwrap(),
+            [8, 9, 10, 11, 12, 13, 14, 15]
+        );
+        assert_eq!(elf.code_from(0x2006).unwrap(), [14, 15]);
+        assert_eq!(elf.exec_section_at(0x2006).unwrap().name, ".fastcode");
+
+        // the gap, and just past each end
+        for addr in [0x1008, 0x1800, 0x1fff, 0x2008] {
+            let err = elf.code_from(addr).unwrap_err();
+            assert!(err.contains("outside every executable section"), "{err}");
+            assert!(!elf.is_code(addr));
         }
+        assert!(elf
+            .code_from(0x1800)
+            .unwrap_err()
+            .contains(".fastcode 0x2000-0x2008"));
+
+        let none = ElfInfo {
+            exec_sections: Vec::new(),
+            ..two_section_elf()
+        };
+        assert!(none.code_from(0x1000).is_err());
     }
 
-    /// Get the next consecutive source lines after the current PC from the same file
-    pub fn get_consecutive_source_lines_after_current(
-        &mut self,
-        count: usize,
-    ) -> Result<Vec<crate::addr2line_stepper::SourceLine>, Box<dyn std::error::Error>> {
-        if let Some(current_line) = self.get_current_source_line()? {
-            if let Some(ref stepper) = self.addr2line_stepper {
-                Ok(stepper.get_consecutive_lines_after(
-                    &current_line.path,
-                    current_line.line,
-                    count,
-                )?)
-            } else {
-                Ok(Vec::new())
-            }
-        } else {
-            Ok(Vec::new())
-        }
+    #[test]
+    fn test_instruction_window_stops_at_section_end() {
+        let mut client = Client::new_with_port(start_canned_stub(Vec::new()));
+        client.elf_info = Some(two_section_elf());
+
+        let window =
+            |client: &Client, pc, max_bytes| client.get_instruction_window(PC::_32(pc), max_bytes);
+        assert_eq!(window(&client, 0x1000, 4).unwrap(), [0, 1, 2, 3]);
+        // .text is 8 bytes, a bigger window ends with it rather than running on into .fastcode
+        assert_eq!(window(&client, 0x1004, 256).unwrap(), [4, 5, 6, 7]);
+        assert_eq!(window(&client, 0x2006, 256).unwrap(), [14, 15]);
+        assert!(window(&client, 0x1800, 256).is_err());
     }
-}
 
-#[cfg(test)]
-pub mod test_utils {
-    use std::net::TcpListener;
-    use std::thread;
+    #[test]
+    fn test_disassemble_falls_back_to_target_memory() {
+        crate::init_test_logger();
+        // two `nop`s, read from the target since 0x1800 is in neither section
+        let port = start_canned_stub(vec![b"+$1300000013000000#08"]);
+        let mut client = Client::new_with_port(port);
+        client.set_no_ack(true);
+        client.elf_info = Some(two_section_elf());
 
-    pub fn create_test_listener() -> (TcpListener, u16) {
-        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
-        let port = listener.local_addr().unwrap().port();
-        (listener, port)
+        let listing = client.disassemble(0x1800, 2).unwrap();
+        let addrs: Vec<u32> = listing.iter().map(|inst| inst.pc().as_u32()).collect();
+        assert_eq!(addrs, [0x1800, 0x1804]);
+        assert!(listing.iter().all(|inst| inst.inst.is_some()));
     }
 
-    pub fn start_dang_instance(listener: TcpListener) -> thread::JoinHandle<()> {
-        thread::spawn(move || {
-            let workspace_root = std::env::current_dir()
-                .unwrap()
-                .parent()
-                .unwrap()
-                .to_path_buf();
+    #[test]
+    fn test_hello_test_exec_sections() {
+        let path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../test_data/ibex/hello_test.elf");
+        let data = fs::read(path).unwrap();
+        let elf = ElfInfo::parse(&data).unwrap();
+
+        // .text is in there, exactly as goblin has it
+        let parsed = Elf::parse(&data).unwrap();
+        let text = parsed
+            .section_headers
+            .iter()
+            .find(|sh| parsed.shdr_strtab.get_at(sh.sh_name) == Some(".text"))
+            .unwrap();
+        let section = elf.exec_section_at(text.sh_addr).unwrap();
+        assert_eq!(section.name, ".text");
+        assert_eq!((section.addr, section.size), (text.sh_addr, text.sh_size));
+        let range = text.file_range().unwrap();
+        assert_eq!(elf.code_from(text.sh_addr).unwrap(), &data[range]);
+        assert!(elf
+            .exec_sections
+            .windows(2)
+            .all(|pair| pair[0].addr < pair[1].addr));
+
+        // puthex, and nothing below the first section
+        assert!(elf.is_code(0x1000a4));
+        assert!(!elf.is_code(0x10));
+    }
 
-            let wave_path = workspace_root.join("test_data/ibex/sim.fst");
-            let mapping_path = workspace_root.join("test_data/ibex/signal_get.py");
-            let elf_path = workspace_root.join("test_data/ibex/hello_test.elf");
+    #[test]
+    fn test_stripped_elf_uses_debuglink() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance_with_elf(
+            listener,
+            Default::default(),
+            "test_data/ibex/stripped/hello_test.elf",
+        );
+        sleep(Duration::from_millis(1000));
 
-            let _ = dang::start_with_args_and_listener(wave_path, mapping_path, elf_path, listener);
-        })
-    }
-}
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+        client.load_elf_info().expect("Failed to load elf info");
 
-#[cfg(test)]
-mod tests {
-    use super::test_utils::*;
-    use super::*;
+        let DebugInfo::Separate(path) = client.debug_info().clone() else {
+            panic!(
+                "expected a separate debug file, got {}",
+                client.debug_info()
+            );
+        };
+        assert!(path.ends_with("stripped/.debug/hello_test.debug"));
+        assert_eq!(
+            client.symbolize(0x1000a4),
+            "hello_test.elf`puthex+0x0 at simple_system_common.c:21"
+        );
+
+        // a debug file from some other build is refused, and the good one stays in use
+        let stripped_dir = path.parent().unwrap().parent().unwrap();
+        let err = client
+            .load_debug_file(&stripped_dir.join("mismatched/hello_test.debug"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("expected 0xbce030e7"), "{err}");
+        assert_eq!(client.debug_info(), &DebugInfo::Separate(path));
 
-    use std::thread::sleep;
-    use std::time::Duration;
+        drop(handle);
+    }
 
     #[test]
     fn test_get_instructions() {
@@ -965,7 +3738,7 @@ mod tests {
         client.load_elf_info().expect("Failed to load elf info");
 
         let instructions = client
-            .get_current_and_next_inst()
+            .get_current_and_next_inst(DEFAULT_INSTRUCTION_WINDOW)
             .expect("Instructions not found");
         assert_ne!(instructions.len(), 0);
 
@@ -973,6 +3746,139 @@ mod tests {
         drop(handle);
     }
 
+    #[test]
+    fn test_line_heat_hello_test() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+        client.load_elf_info().expect("Failed to load elf info");
+
+        let heat: HashMap<u64, u64> = client
+            .line_heat(Path::new("hello_test.c"), 12..=20)
+            .expect("line heat failed")
+            .into_iter()
+            .collect();
+
+        for line in [12, 13, 16] {
+            assert!(heat[&line] > 0, "hello_test.c:{line} should have executed");
+        }
+        // just a comment
+        assert_eq!(heat[&20], 0);
+
+        drop(handle);
+    }
+
+    #[test]
+    fn test_scratch_memory_write() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let options = dang::DangOptions {
+            scratch: Some(dang::runtime::ScratchRegion {
+                start: 0x200000,
+                size: 0x100,
+            }),
+            ..Default::default()
+        };
+        let handle = start_dang_instance_with_options(listener, options);
+        sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+
+        assert_eq!(client.scratch_info().unwrap(), Some((0x200000, 0x100)));
+
+        // Includes bytes that need escaping in an X packet
+        let pattern = [0xde, 0xad, b'$', b'#', b'}', b'*', 0x00, 0xff];
+        client
+            .write_memory(0x200010, &pattern)
+            .expect("write inside scratch should succeed");
+        assert_eq!(client.read_memory(0x200010, 8).unwrap(), pattern);
+
+        // Mostly escapable bytes, goes out as an M packet instead
+        client.write_memory(0x200020, b"$$$$").unwrap();
+        assert_eq!(client.read_memory(0x200020, 4).unwrap(), b"$$$$");
+
+        let err = client
+            .write_memory(0x100000, &[0x13, 0x00])
+            .expect_err("write outside scratch should fail");
+        let err = err
+            .downcast_ref::<ReadOnlyMemory>()
+            .expect("expected a ReadOnlyMemory error");
+        assert_eq!(err.addr, 0x100000);
+
+        drop(handle);
+    }
+
+    #[test]
+    fn test_observer_follows_primary() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let options = dang::DangOptions {
+            observers: 1,
+            ..Default::default()
+        };
+        let handle = start_dang_instance_with_options(listener, options);
+        sleep(Duration::from_millis(1000));
+
+        let mut primary = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        primary
+            .initialize_gdb_session()
+            .expect("failed to init primary session");
+
+        // observers are only accepted once the primary is connected
+        let mut observer = Client::connect_observer(&format!("127.0.0.1:{port}"))
+            .expect("failed to connect observer");
+        sleep(Duration::from_millis(200));
+        observer
+            .initialize_gdb_session()
+            .expect("failed to init observer session");
+        assert!(observer.is_observer());
+
+        let start_pc = observer.get_current_pc().unwrap().as_u32();
+        primary.step().unwrap();
+        primary.step().unwrap();
+        let primary_pc = primary.get_current_pc().unwrap().as_u32();
+        assert_ne!(primary_pc, start_pc);
+        assert_eq!(observer.get_current_pc().unwrap().as_u32(), primary_pc);
+
+        let err = observer
+            .step()
+            .expect_err("observer should not be able to step");
+        assert!(err.downcast_ref::<ObserverReadOnly>().is_some(), "{err}");
+        assert_eq!(err.to_string(), "observer connection is read-only");
+
+        // dang refuses on its end too, for clients that don't know they're observing
+        let resp = observer
+            .send_command_parsed(Packet::Command(GdbCommand::Base(Base::Z0 {
+                addr: primary_pc,
+            })))
+            .unwrap();
+        assert_eq!(resp, GdbResponse::Error { code: 1 });
+
+        // nor can a batch sneak a cursor move past it
+        let replies = observer
+            .send_monitor_batch(&["goto_idx 0", "time_idx"])
+            .unwrap();
+        assert!(replies[0].is_err());
+        assert_eq!(
+            replies[1].as_ref().unwrap().trim(),
+            primary.get_time_idx().unwrap().to_string()
+        );
+
+        drop(handle);
+    }
+
     #[test]
     fn test_get_current_pc_method() {
         crate::init_test_logger();
@@ -1114,6 +4020,77 @@ mod tests {
         drop(handle);
     }
 
+    #[test]
+    fn test_breakpoint_changes_are_idempotent() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+        assert!(client.target_breakpoints().unwrap().is_empty());
+
+        assert_eq!(
+            client.set_breakpoint(0x1003d4).unwrap(),
+            BreakpointChange::Changed
+        );
+        assert_eq!(
+            client.set_breakpoint(0x1003d0).unwrap(),
+            BreakpointChange::Changed
+        );
+        for _ in 0..3 {
+            assert_eq!(
+                client.set_breakpoint(0x1003d0).unwrap(),
+                BreakpointChange::AlreadySet
+            );
+        }
+        assert_eq!(client.target_breakpoints().unwrap(), [0x1003d0, 0x1003d4]);
+
+        assert_eq!(
+            client.remove_breakpoint(0x1003d0).unwrap(),
+            BreakpointChange::Changed
+        );
+        assert_eq!(
+            client.remove_breakpoint(0x1003d0).unwrap(),
+            BreakpointChange::NotSet
+        );
+        assert_eq!(client.target_breakpoints().unwrap(), [0x1003d4]);
+        drop(handle);
+    }
+
+    #[test]
+    fn test_breakpoint_ignore_count() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+        assert!(client.set_breakpoint_ignore(0x1003d4, 2).is_err());
+
+        client.set_breakpoint(0x1003d4).unwrap();
+        client.set_breakpoint_ignore(0x1003d4, 2).unwrap();
+        assert_eq!(
+            client.target_breakpoint_entries().unwrap(),
+            [BreakpointEntry {
+                addr: 0x1003d4,
+                hits: 0,
+                ignore_remaining: 2,
+                kind: DEFAULT_KIND,
+            }]
+        );
+        assert_eq!(client.target_breakpoints().unwrap(), [0x1003d4]);
+        drop(handle);
+    }
+
     #[test]
     fn test_time_idx_then_pc() {
         crate::init_test_logger();
@@ -1159,6 +4136,84 @@ mod tests {
         drop(handle);
     }
 
+    #[test]
+    fn test_resume_error_packet_is_an_error() {
+        crate::init_test_logger();
+        let port = start_canned_stub(vec![b"+$E01#a6", b"$E0e#da"]);
+        let mut client = Client::new_with_port(port);
+        client.set_no_ack(true);
+
+        let err = client.step().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Step failed, the stub answered with error 0x01"
+        );
+        assert!(client.continue_execution().is_err());
+    }
+
+    #[test]
+    fn test_resume_exit_replies() {
+        crate::init_test_logger();
+        let port = start_canned_stub(vec![b"+$W00#b7", b"$X0b#ea"]);
+        let mut client = Client::new_with_port(port);
+        client.set_no_ack(true);
+        assert!(!client.has_exited());
+
+        assert!(!client.step().unwrap());
+        assert!(client.has_exited());
+        assert_eq!(
+            client.last_stop_reason(),
+            Some(&StopReason::ProcessExit { code: 0 })
+        );
+        assert!(!client.continue_execution().unwrap());
+        assert_eq!(
+            client.last_stop_reason(),
+            Some(&StopReason::SignalTermination(0x0b))
+        );
+    }
+
+    #[test]
+    fn test_step_until_trace_ends() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let _handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+        let mut client = Client::new_with_port(port);
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+
+        // the last few instructions of the trace
+        let mut from = client.get_time_idx().unwrap();
+        let mut tail = Vec::new();
+        loop {
+            let command = pc_trace_command(from, PC_TRACE_BATCH, SearchDirection::Forward);
+            let batch = parse_pc_trace(&client.send_monitor_command(&command).unwrap()).unwrap();
+            let Some(&(last, _)) = batch.last() else {
+                break;
+            };
+            from = last;
+            tail = batch;
+        }
+        assert!(tail.len() > 4);
+        let (near_end, _) = tail[tail.len() - 4];
+        client.goto_time_idx(near_end).unwrap();
+
+        let mut alive_steps = 0;
+        loop {
+            match client.step().unwrap() {
+                true => alive_steps += 1,
+                false => break,
+            }
+            assert!(alive_steps < 16, "stepped past the end of the trace");
+        }
+        assert!(alive_steps <= 3);
+        assert!(matches!(
+            client.last_stop_reason,
+            Some(StopReason::SignalTermination(_) | StopReason::ProcessExit { .. })
+        ));
+    }
+
     #[test]
     fn test_continue_past_program_end() {
         crate::init_test_logger();
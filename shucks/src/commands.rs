@@ -1,6 +1,7 @@
 use std::io;
 
-use crate::packet::{FinishedPacket, PacketCursor};
+use crate::packet::{escape_binary, FinishedPacket, PacketCursor};
+use crate::response::ThreadId;
 
 #[derive(Clone, Debug)]
 pub enum GdbCommand {
@@ -13,15 +14,17 @@ pub enum Base {
     D,
     LowerG,
     UpperG,
-    H,
+    H { op: ThreadOp, thread: ThreadId },
     K,
     LowerM { addr: u32, length: u32 },
-    UpperM,
+    UpperM { addr: u32, data: Vec<u8> },
+    UpperX { addr: u32, data: Vec<u8> },
     QAttached,
+    QC,
     QfThreadInfo,
     QsThreadInfo,
     QSupported,
-    T,
+    T { thread: ThreadId },
     VKill,
     QStartNoAckMode,
     QXferExecFile { offset: u32, length: u32 },
@@ -30,6 +33,15 @@ pub enum Base {
     Z0Remove { addr: u32 },  // Remove software breakpoint
 }
 
+/// What an `H` packet picks the thread for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThreadOp {
+    /// `Hg`, register and memory accesses
+    General,
+    /// `Hc`, stepping and continuing
+    Continue,
+}
+
 #[derive(Clone, Debug)]
 pub enum Resume {
     Continue,
@@ -68,23 +80,44 @@ impl Resume {
 }
 
 impl Base {
-    fn base_str(&self) -> &'static str {
+    /// Whether an empty reply means the stub doesn't do this: the `q`, `Q` and `v` packets, and
+    /// `Z`/`z`, which the RSP lets a stub turn down that way too
+    pub fn is_query(&self) -> bool {
+        matches!(
+            self,
+            Self::QAttached
+                | Self::QC
+                | Self::QfThreadInfo
+                | Self::QsThreadInfo
+                | Self::QSupported
+                | Self::QStartNoAckMode
+                | Self::QXferExecFile { .. }
+                | Self::QRcmd { .. }
+                | Self::VKill
+                | Self::Z0 { .. }
+                | Self::Z0Remove { .. }
+        )
+    }
+
+    pub(crate) fn base_str(&self) -> &'static str {
         match self {
             Self::QuestionMark => "?",
             Self::D => "D",
             Self::LowerG => "g",
             Self::UpperG => "G",
-            Self::H => "H",
+            Self::H { .. } => "H",
             Self::K => "k",
             Self::LowerM { .. } => "m",
-            Self::UpperM => "M",
+            Self::UpperM { .. } => "M",
+            Self::UpperX { .. } => "X",
+            Self::QC => "qC",
             Self::QsThreadInfo => "qsThreadInfo",
             Self::QfThreadInfo => "qfThreadInfo",
             Self::QSupported => "qSupported",
             Self::VKill => "vKill",
             Self::QStartNoAckMode => "QStartNoAckMode",
             Self::QAttached => "qAttached",
-            Self::T => "T",
+            Self::T { .. } => "T",
             Self::QXferExecFile { .. } => "qXfer:exec-file:read",
             Self::QRcmd { .. } => "qRcmd",
             Self::Z0 { .. } => "Z0",
@@ -101,9 +134,28 @@ impl Base {
             Self::QSupported => {
                 cursor.write_content(b":xmlRegisters=riscv")?;
             }
+            Self::H { op, thread } => {
+                let op = match op {
+                    ThreadOp::General => "g",
+                    ThreadOp::Continue => "c",
+                };
+                cursor.write_content(format!("{op}{thread}").as_bytes())?;
+            }
+            Self::T { thread } => {
+                cursor.write_content(thread.to_string().as_bytes())?;
+            }
             Self::LowerM { addr, length } => {
                 cursor.write_content(format!("{addr:x},{length:x}").as_bytes())?;
             }
+            Self::UpperM { addr, data } => {
+                let hex_data: String = data.iter().map(|b| format!("{b:02x}")).collect();
+                cursor.write_content(format!("{addr:x},{:x}:{hex_data}", data.len()).as_bytes())?;
+            }
+            Self::UpperX { addr, data } => {
+                // The checksum covers the escaped payload, exactly as it goes out on the wire
+                cursor.write_content(format!("{addr:x},{:x}:", data.len()).as_bytes())?;
+                cursor.write_content(&escape_binary(data))?;
+            }
             Self::QXferExecFile { offset, length } => {
                 cursor.write_content(format!("::{offset:x},{length:x}").as_bytes())?;
             }
@@ -0,0 +1,245 @@
+//! Finding the separate debug info file of a stripped ELF through its `.gnu_debuglink` section,
+//! the way `objcopy --only-keep-debug` / `--add-gnu-debuglink` set it up
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use goblin::elf::Elf;
+
+/// Contents of a `.gnu_debuglink` section: the debug file's name and the CRC32 of its contents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugLink {
+    pub file_name: String,
+    pub crc: u32,
+}
+
+impl DebugLink {
+    /// Read the section out of `elf`, `None` if there isn't one or it's malformed
+    pub fn from_elf(elf: &Elf, elf_data: &[u8]) -> Option<Self> {
+        let section = elf.section_headers.iter().find(|sh| {
+            elf.shdr_strtab
+                .get_at(sh.sh_name)
+                .is_some_and(|name| name == ".gnu_debuglink")
+        })?;
+        let start = section.sh_offset as usize;
+        let data = elf_data.get(start..start.checked_add(section.sh_size as usize)?)?;
+
+        // NUL terminated name, padded to 4 bytes, then the CRC in the ELF's byte order
+        let name_len = data.iter().position(|&b| b == 0)?;
+        let crc_offset = (name_len + 1).next_multiple_of(4);
+        let crc_bytes: [u8; 4] = data.get(crc_offset..crc_offset + 4)?.try_into().ok()?;
+        let crc = if elf.little_endian {
+            u32::from_le_bytes(crc_bytes)
+        } else {
+            u32::from_be_bytes(crc_bytes)
+        };
+        Some(Self {
+            file_name: String::from_utf8_lossy(&data[..name_len]).into_owned(),
+            crc,
+        })
+    }
+
+    /// Where to look for the debug file of the ELF at `elf_path`, in order: next to it, in a
+    /// `.debug` directory next to it, then in each of `search_paths`
+    pub fn candidates(&self, elf_path: &Path, search_paths: &[PathBuf]) -> Vec<PathBuf> {
+        let elf_dir = elf_path.parent().unwrap_or(Path::new(""));
+        let mut candidates = vec![
+            elf_dir.join(&self.file_name),
+            elf_dir.join(".debug").join(&self.file_name),
+        ];
+        candidates.extend(search_paths.iter().map(|dir| dir.join(&self.file_name)));
+        candidates
+    }
+
+    /// Check that `data` is the debug file this link points at
+    pub fn verify(&self, path: &Path, data: &[u8]) -> Result<(), DebugFileError> {
+        let actual = crc32(data);
+        if actual != self.crc {
+            return Err(DebugFileError::CrcMismatch {
+                path: path.to_path_buf(),
+                expected: self.crc,
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    /// The first candidate that exists, along with its contents. A candidate with the wrong CRC
+    /// is skipped, but reported if nothing else turns up
+    pub fn find(
+        &self,
+        elf_path: &Path,
+        search_paths: &[PathBuf],
+    ) -> Result<(PathBuf, Vec<u8>), DebugFileError> {
+        let candidates = self.candidates(elf_path, search_paths);
+        let mut mismatch = None;
+        for candidate in &candidates {
+            let Ok(data) = fs::read(candidate) else {
+                continue;
+            };
+            match self.verify(candidate, &data) {
+                Ok(()) => return Ok((candidate.clone(), data)),
+                Err(e) => {
+                    log::warn!("{e}");
+                    mismatch.get_or_insert(e);
+                }
+            }
+        }
+        Err(mismatch.unwrap_or(DebugFileError::NotFound {
+            file_name: self.file_name.clone(),
+            searched: candidates,
+        }))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DebugFileError {
+    NotFound {
+        file_name: String,
+        searched: Vec<PathBuf>,
+    },
+    /// The file is there but isn't the one the ELF was linked against, probably from another
+    /// build
+    CrcMismatch {
+        path: PathBuf,
+        expected: u32,
+        actual: u32,
+    },
+}
+
+impl fmt::Display for DebugFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DebugFileError::NotFound {
+                file_name,
+                searched,
+            } => {
+                write!(f, "debug file {file_name} not found, looked in: ")?;
+                let searched: Vec<String> =
+                    searched.iter().map(|p| p.display().to_string()).collect();
+                write!(
+                    f,
+                    "{}. Point at it with 'debugfile <path>'",
+                    searched.join(", ")
+                )
+            }
+            DebugFileError::CrcMismatch {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} doesn't match the ELF's debuglink (CRC 0x{actual:08x}, expected 0x{expected:08x}), \
+                 is it from a different build?",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DebugFileError {}
+
+/// CRC-32 (IEEE, reflected, as zlib computes it), the checksum `.gnu_debuglink` records
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::addr2line_stepper::Addr2lineStepper;
+
+    fn stripped(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../test_data/ibex/stripped")
+            .join(name)
+    }
+
+    fn link_of(path: &Path) -> DebugLink {
+        let data = fs::read(path).unwrap();
+        let elf = Elf::parse(&data).unwrap();
+        DebugLink::from_elf(&elf, &data).expect("fixture should have a debuglink")
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn test_read_debuglink() {
+        let link = link_of(&stripped("hello_test.elf"));
+        assert_eq!(link.file_name, "hello_test.debug");
+        assert_eq!(link.crc, 0xbce0_30e7);
+
+        let data = fs::read(stripped("../hello_test.elf")).unwrap();
+        let elf = Elf::parse(&data).unwrap();
+        assert_eq!(DebugLink::from_elf(&elf, &data), None);
+    }
+
+    #[test]
+    fn test_lines_resolve_through_debuglink() {
+        let elf_path = stripped("hello_test.elf");
+        let elf_data = fs::read(&elf_path).unwrap();
+        let no_lines = Addr2lineStepper::new(&elf_data, 0)
+            .ok()
+            .and_then(|stepper| stepper.map_addr(0x1000a4).ok().flatten());
+        assert_eq!(no_lines, None, "the stripped ELF shouldn't have line info");
+
+        let (path, debug_data) = link_of(&elf_path).find(&elf_path, &[]).unwrap();
+        assert_eq!(path, stripped(".debug/hello_test.debug"));
+        let stepper = Addr2lineStepper::new(&debug_data, 0).unwrap();
+        let (file, line) = stepper.map_addr(0x1000a4).unwrap().unwrap();
+        assert!(
+            file.ends_with("simple_system_common.c"),
+            "{}",
+            file.display()
+        );
+        assert_eq!(line, 21);
+    }
+
+    #[test]
+    fn test_crc_mismatch() {
+        let elf_path = stripped("hello_test.elf");
+        let link = link_of(&elf_path);
+        let wrong = stripped("mismatched/hello_test.debug");
+        let err = link.verify(&wrong, &fs::read(&wrong).unwrap()).unwrap_err();
+        assert!(matches!(err, DebugFileError::CrcMismatch { .. }));
+        let msg = err.to_string();
+        assert!(msg.contains("mismatched/hello_test.debug"), "{msg}");
+        assert!(msg.contains("0xbce030e7"), "{msg}");
+
+        // the real file sitting in .debug still wins over a bad one on the search path
+        let (path, _) = link.find(&elf_path, &[stripped("mismatched")]).unwrap();
+        assert_eq!(path, stripped(".debug/hello_test.debug"));
+    }
+
+    #[test]
+    fn test_not_found_lists_candidates() {
+        let link = DebugLink {
+            file_name: "nope.debug".to_string(),
+            crc: 0,
+        };
+        let elf_path = stripped("hello_test.elf");
+        let err = link
+            .find(&elf_path, &[PathBuf::from("/opt/debug")])
+            .unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("/opt/debug/nope.debug"), "{msg}");
+        assert!(msg.contains(".debug/nope.debug"), "{msg}");
+        assert!(msg.contains("debugfile"), "{msg}");
+    }
+}
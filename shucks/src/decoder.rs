@@ -0,0 +1,121 @@
+//! Splits what comes in from the stub into packets. Bytes are pushed in as they're read and
+//! complete packets come out the front, the rest waits for the next read.
+//!
+//! Once no-ack mode is on the stub has no reason to send a bare `+`, so one that turns up
+//! anyway, usually the stub acking a retransmitted packet long after the fact, is dropped here
+//! and never reaches a caller
+
+use crate::response::RawGdbResponse;
+
+#[derive(Debug, Default)]
+pub struct PacketDecoder {
+    buffer: Vec<u8>,
+    no_ack: bool,
+}
+
+impl PacketDecoder {
+    /// Add bytes read from the stub
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Drop bare acks from now on, once `QStartNoAckMode` has gone through
+    pub fn set_no_ack(&mut self, no_ack: bool) {
+        self.no_ack = no_ack;
+    }
+
+    /// Bytes waiting for the rest of their packet, or for someone to ask for them
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Throw away everything buffered, returns how many bytes that was
+    pub fn clear(&mut self) -> usize {
+        std::mem::take(&mut self.buffer).len()
+    }
+
+    /// The packet at the front of the buffer, if it's all there
+    pub fn next_packet(&mut self) -> Option<RawGdbResponse> {
+        loop {
+            let packet = RawGdbResponse::find_packet_data(&self.buffer).ok()?;
+            // split on the bytes the packet took on the wire, not the payload's length
+            self.buffer.drain(..packet.entire_packet_len());
+            if self.no_ack && packet.as_slice() == b"+" {
+                log::trace!("Dropping an ack in no-ack mode");
+                continue;
+            }
+            return Some(packet);
+        }
+    }
+
+    /// Drop copies of `packet` that are already buffered right behind it, returns how many
+    pub fn drop_repeats_of(&mut self, packet: &RawGdbResponse) -> usize {
+        let mut dropped = 0;
+        loop {
+            let Ok(next) = RawGdbResponse::find_packet_data(&self.buffer) else {
+                return dropped;
+            };
+            let is_ack = next.as_slice() == b"+";
+            if next.as_slice() != packet.as_slice() && !(self.no_ack && is_ack) {
+                return dropped;
+            }
+            self.buffer.drain(..next.entire_packet_len());
+            if !is_ack {
+                dropped += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_after_run_length_packet() {
+        // a `g` reply with run lengths, then an OK in the same read
+        let mut decoder = PacketDecoder::default();
+        decoder.push(b"$0* 1*!#f6$OK");
+        assert_eq!(decoder.next_packet().unwrap().as_slice(), b"0* 1*!");
+        assert!(decoder.next_packet().is_none());
+
+        decoder.push(b"#9a");
+        assert_eq!(decoder.next_packet().unwrap().as_slice(), b"OK");
+        assert_eq!(decoder.buffered(), 0);
+    }
+
+    #[test]
+    fn test_acks_dropped_in_no_ack_mode() {
+        let mut decoder = PacketDecoder::default();
+        decoder.push(b"+$OK#9a");
+        assert_eq!(decoder.next_packet().unwrap().as_slice(), b"+");
+        assert_eq!(decoder.next_packet().unwrap().as_slice(), b"OK");
+
+        decoder.set_no_ack(true);
+        decoder.push(b"+$OK#9a++");
+        assert_eq!(decoder.next_packet().unwrap().as_slice(), b"OK");
+        assert!(decoder.next_packet().is_none());
+        assert_eq!(decoder.buffered(), 0);
+        // a nak isn't an ack
+        decoder.push(b"-");
+        assert_eq!(decoder.next_packet().unwrap().as_slice(), b"-");
+    }
+
+    #[test]
+    fn test_drop_repeats() {
+        let mut decoder = PacketDecoder::default();
+        decoder.set_no_ack(true);
+        decoder.push(b"$S05#b8$S05#b8+$S05#b8$OK#9a$S05#b8");
+        let stop = decoder.next_packet().unwrap();
+        assert_eq!(decoder.drop_repeats_of(&stop), 2);
+        // only the ones right behind it, the OK answers something else
+        assert_eq!(decoder.next_packet().unwrap().as_slice(), b"OK");
+        assert_eq!(decoder.drop_repeats_of(&stop), 1);
+        assert_eq!(decoder.buffered(), 0);
+
+        // a different stop isn't a repeat
+        decoder.push(b"$S02#b5");
+        assert_eq!(decoder.drop_repeats_of(&stop), 0);
+        assert_eq!(decoder.clear(), 7);
+    }
+}
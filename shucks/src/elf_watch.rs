@@ -0,0 +1,139 @@
+//! Noticing the ELF being rebuilt while a session is open.
+//!
+//! Symbols and line info are read once at load time, so after a rebuild they silently describe
+//! the old binary. A stat is cheap enough to do before every symbol lookup, and only when the
+//! size or mtime has moved is the file read to compare against what was loaded
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// How much of each end of the file goes into the hash
+pub const HASH_SPAN: u64 = 64 * 1024;
+
+/// What the ELF on disk looked like when it was loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElfFingerprint {
+    pub path: PathBuf,
+    pub size: u64,
+    pub mtime: Option<SystemTime>,
+    /// Hash of the first and last [`HASH_SPAN`] bytes
+    pub hash: u64,
+}
+
+impl ElfFingerprint {
+    /// Fingerprint `data`, which was just read from `path`
+    pub fn new(path: &Path, data: &[u8]) -> Self {
+        let span = HASH_SPAN as usize;
+        let tail_start = data.len().saturating_sub(span);
+        Self {
+            path: path.to_path_buf(),
+            size: data.len() as u64,
+            mtime: fs::metadata(path).and_then(|m| m.modified()).ok(),
+            hash: hash_ends(&data[..data.len().min(span)], &data[tail_start..]),
+        }
+    }
+
+    /// Whether the file at `path` is no longer what was loaded. A file that's gone counts as
+    /// changed. Rewriting the same bytes (a `touch`, or a rebuild that changed nothing) doesn't,
+    /// the new mtime is remembered so the file isn't read again on every check
+    pub fn is_stale(&mut self) -> bool {
+        let Ok(metadata) = fs::metadata(&self.path) else {
+            return true;
+        };
+        if metadata.len() != self.size {
+            return true;
+        }
+        let mtime = metadata.modified().ok();
+        if mtime == self.mtime {
+            return false;
+        }
+        match hash_file_ends(&self.path, self.size) {
+            Ok(hash) if hash == self.hash => {
+                self.mtime = mtime;
+                false
+            }
+            _ => true,
+        }
+    }
+}
+
+fn hash_ends(head: &[u8], tail: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(head);
+    hasher.write(tail);
+    hasher.finish()
+}
+
+/// [`hash_ends`] of a `size` byte file, reading only the ends of it
+fn hash_file_ends(path: &Path, size: u64) -> std::io::Result<u64> {
+    let mut file = File::open(path)?;
+    let mut head = vec![0; size.min(HASH_SPAN) as usize];
+    file.read_exact(&mut head)?;
+    let tail_start = size.saturating_sub(HASH_SPAN);
+    let mut tail = vec![0; (size - tail_start) as usize];
+    file.seek(SeekFrom::Start(tail_start))?;
+    file.read_exact(&mut tail)?;
+    Ok(hash_ends(&head, &tail))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn temp_elf(name: &str, data: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("shucks_{}_{name}", std::process::id()));
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    fn set_mtime(path: &Path, mtime: SystemTime) {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_unchanged_and_touched() {
+        let data: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+        let path = temp_elf("touched.elf", &data);
+        let mut fingerprint = ElfFingerprint::new(&path, &data);
+        assert!(!fingerprint.is_stale());
+
+        // same bytes, newer mtime
+        set_mtime(&path, SystemTime::now() + Duration::from_secs(60));
+        assert!(!fingerprint.is_stale());
+        assert_eq!(
+            fingerprint.mtime,
+            fs::metadata(&path).unwrap().modified().ok()
+        );
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_rebuilt() {
+        let data = vec![0u8; 1000];
+        let path = temp_elf("rebuilt.elf", &data);
+        let mut fingerprint = ElfFingerprint::new(&path, &data);
+
+        // same size, different contents
+        let mut rebuilt = data.clone();
+        rebuilt[500] = 1;
+        fs::write(&path, &rebuilt).unwrap();
+        set_mtime(&path, SystemTime::now() + Duration::from_secs(60));
+        assert!(fingerprint.is_stale());
+
+        fs::write(&path, [0u8; 10]).unwrap();
+        assert!(fingerprint.is_stale());
+
+        fs::remove_file(&path).unwrap();
+        assert!(fingerprint.is_stale());
+    }
+}
@@ -0,0 +1,163 @@
+//! Searching the executed instruction stream, e.g. "the next `ecall`" or "the previous write to
+//! a0".
+//!
+//! The target dumps upcoming (or earlier) PCs in bulk with `pc_trace`, and each one is decoded
+//! from the ELF on this side until something matches the [`InstPredicate`]
+
+use std::fmt;
+
+use crate::client::Instruction;
+
+/// PCs asked for per `pc_trace` request
+pub const PC_TRACE_BATCH: usize = 256;
+
+/// How many instructions a search looks at before giving up, unless told otherwise
+pub const DEFAULT_FIND_BUDGET: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchDirection {
+    Forward,
+    Backward,
+}
+
+/// What the instruction being searched for looks like
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstPredicate {
+    /// Mnemonic equality. `jal` also matches its compressed form `c.jal`
+    Mnemonic(String),
+    /// Writes register `x<n>`
+    Rd(usize),
+    /// `encoding & mask == value`, compressed encodings zero extended
+    Mask { value: u32, mask: u32 },
+}
+
+impl InstPredicate {
+    pub fn matches(&self, inst: &Instruction) -> bool {
+        match self {
+            InstPredicate::Mnemonic(want) => inst.mnemonic().is_some_and(|mnemonic| {
+                mnemonic == *want || mnemonic.strip_prefix("c.") == Some(want.as_str())
+            }),
+            InstPredicate::Rd(rd) => inst.rd() == Some(*rd),
+            InstPredicate::Mask { value, mask } => inst.encoding() & mask == *value,
+        }
+    }
+}
+
+impl fmt::Display for InstPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstPredicate::Mnemonic(mnemonic) => write!(f, "{mnemonic}"),
+            InstPredicate::Rd(rd) => write!(f, "rd=x{rd}"),
+            InstPredicate::Mask { value, mask } => write!(f, "mask:0x{value:x}/0x{mask:x}"),
+        }
+    }
+}
+
+/// How a search ended. `scanned` counts every instruction looked at, including the match
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FindOutcome {
+    /// The cursor has been moved to the match
+    Found {
+        time_idx: u64,
+        pc: u32,
+        instruction: String,
+        scanned: usize,
+    },
+    /// Gave up after the budget ran out
+    OverBudget { scanned: usize },
+    /// Ran off the end (or the start) of the trace
+    EndOfTrace { scanned: usize },
+}
+
+pub fn pc_trace_command(from: u64, count: usize, direction: SearchDirection) -> String {
+    match direction {
+        SearchDirection::Forward => format!("pc_trace {from} {count}"),
+        SearchDirection::Backward => format!("pc_trace {from} {count} back"),
+    }
+}
+
+/// `(time_idx, pc)` pairs from a `pc_trace` reply, one `<time_idx> <pc in hex>` per line
+pub fn parse_pc_trace(output: &str) -> Result<Vec<(u64, u32)>, String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(|line| {
+            if line.starts_with("usage") {
+                return Err(format!("Target rejected pc_trace: {line}"));
+            }
+            let malformed = || format!("Malformed pc_trace line: {line}");
+            let (idx, pc) = line.split_once(' ').ok_or_else(malformed)?;
+            let idx = idx.parse::<u64>().map_err(|_| malformed())?;
+            let pc = u32::from_str_radix(pc, 16).map_err(|_| malformed())?;
+            Ok((idx, pc))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{decode_instruction, PC};
+
+    fn inst(encoding: &[u8]) -> Instruction {
+        let mut bytes = encoding.to_vec();
+        bytes.resize(8, 0);
+        decode_instruction(&bytes, PC::_32(0x1000))
+    }
+
+    #[test]
+    fn test_parse_pc_trace() {
+        assert_eq!(
+            parse_pc_trace("12 100398\n15 10039c\n").unwrap(),
+            vec![(12, 0x100398), (15, 0x10039c)]
+        );
+        assert_eq!(parse_pc_trace("").unwrap(), vec![]);
+        assert!(parse_pc_trace("12").is_err());
+        assert!(parse_pc_trace("usage: pc_trace <time_idx> <count> [back]").is_err());
+    }
+
+    #[test]
+    fn test_match_mnemonic() {
+        // jal ra, 0x10
+        let jal = inst(&0x010000efu32.to_le_bytes());
+        assert!(InstPredicate::Mnemonic("jal".into()).matches(&jal));
+        assert!(!InstPredicate::Mnemonic("jalr".into()).matches(&jal));
+        // addi a0, a0, 1
+        let addi = inst(&0x00150513u32.to_le_bytes());
+        assert!(InstPredicate::Mnemonic("addi".into()).matches(&addi));
+        assert!(!InstPredicate::Mnemonic("jal".into()).matches(&addi));
+    }
+
+    #[test]
+    fn test_match_rd() {
+        // addi a0, a0, 1
+        let addi = inst(&0x00150513u32.to_le_bytes());
+        assert!(InstPredicate::Rd(10).matches(&addi));
+        assert!(!InstPredicate::Rd(11).matches(&addi));
+        // sw a0, 0(sp) doesn't write a register
+        let sw = inst(&0x00a12023u32.to_le_bytes());
+        assert!(!InstPredicate::Rd(0).matches(&sw));
+        assert!(!InstPredicate::Rd(10).matches(&sw));
+    }
+
+    #[test]
+    fn test_match_mask() {
+        let ecall = inst(&0x00000073u32.to_le_bytes());
+        let any_system = InstPredicate::Mask {
+            value: 0x73,
+            mask: 0x7f,
+        };
+        assert!(any_system.matches(&ecall));
+        let addi = inst(&0x00150513u32.to_le_bytes());
+        assert!(!any_system.matches(&addi));
+
+        // c.li a0, 0 is 0x4501, compressed encodings are zero extended
+        let c_li = inst(&[0x01, 0x45]);
+        let exact = InstPredicate::Mask {
+            value: 0x4501,
+            mask: 0xffff_ffff,
+        };
+        assert!(exact.matches(&c_li));
+    }
+}
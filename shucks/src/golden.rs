@@ -0,0 +1,401 @@
+//! Golden protocol transcripts.
+//!
+//! A fixed sequence of requests is run against a stub and every request/response payload is
+//! recorded, one packet per line. Fields that legitimately change from run to run (the exec file
+//! path, absolute times in monitor output) are masked and checksums are recomputed over the
+//! masked payload, so two transcripts only differ when the stub behaves differently
+
+use std::io;
+
+use crate::{
+    commands::{Base, GdbCommand, Resume},
+    Client, Packet,
+};
+
+/// Breakpoint the script sets and continues to, `puthex` in the ibex hello_test fixture
+pub const GOLDEN_BREAKPOINT: u32 = 0x1000a4;
+
+/// Placeholder for the exec file path
+const EXEC_FILE_MASK: &str = "<exec-file>";
+/// Placeholder for absolute times in monitor output
+const TIME_MASK: &str = "<time>";
+/// Placeholder for the workspace root wherever it shows up
+const ROOT_MASK: &str = "<root>";
+
+const TIME_UNITS: &[&str] = &["fs", "ps", "ns", "us", "ms", "s"];
+
+fn monitor(command: &str) -> Packet {
+    Packet::Command(GdbCommand::Base(Base::QRcmd {
+        command: command.to_string(),
+    }))
+}
+
+/// The scripted sequence: session init, registers, a few fixed memory reads, three steps, the
+/// time index, then a breakpoint set, continued to and removed
+pub fn golden_script() -> Vec<Packet> {
+    let base = |base| Packet::Command(GdbCommand::Base(base));
+    let resume = |resume| Packet::Command(GdbCommand::Resume(resume));
+    vec![
+        base(Base::QStartNoAckMode),
+        base(Base::QSupported),
+        base(Base::QfThreadInfo),
+        base(Base::QsThreadInfo),
+        base(Base::QuestionMark),
+        base(Base::QXferExecFile {
+            offset: 0,
+            length: 0x100,
+        }),
+        base(Base::LowerG),
+        base(Base::LowerM {
+            addr: 0x100084,
+            length: 16,
+        }),
+        base(Base::LowerM {
+            addr: GOLDEN_BREAKPOINT,
+            length: 8,
+        }),
+        base(Base::LowerM {
+            addr: 0x100470,
+            length: 16,
+        }),
+        resume(Resume::Step),
+        resume(Resume::Step),
+        resume(Resume::Step),
+        monitor("time_idx"),
+        base(Base::LowerG),
+        base(Base::Z0 {
+            addr: GOLDEN_BREAKPOINT,
+        }),
+        resume(Resume::Continue),
+        monitor("time_idx"),
+        base(Base::Z0Remove {
+            addr: GOLDEN_BREAKPOINT,
+        }),
+        base(Base::LowerG),
+    ]
+}
+
+/// Masks volatile fields out of recorded payloads
+#[derive(Debug, Default, Clone)]
+pub struct Normalizer {
+    /// Absolute workspace root, replaced wherever it appears
+    pub root: Option<String>,
+}
+
+impl Normalizer {
+    pub fn with_root(root: impl Into<String>) -> Self {
+        Self {
+            root: Some(root.into()),
+        }
+    }
+
+    /// Normalize the payload of a response to `request`
+    pub fn response(&self, request: &str, response: &str) -> String {
+        if request.starts_with("qXfer:exec-file:read") {
+            // `l`/`m` says whether there's more to read, the rest is the path itself
+            return match response.chars().next() {
+                Some(marker @ ('l' | 'm')) => format!("{marker}{EXEC_FILE_MASK}"),
+                _ => response.to_string(),
+            };
+        }
+        if let Some(text) = console_output(response) {
+            return format!("O{}", hex_encode(&self.text(&text)));
+        }
+        self.text(response)
+    }
+
+    /// Normalize free text, monitor output or a payload
+    pub fn text(&self, text: &str) -> String {
+        let text = match &self.root {
+            Some(root) if !root.is_empty() => text.replace(root.as_str(), ROOT_MASK),
+            _ => text.to_string(),
+        };
+        mask_times(&text)
+    }
+}
+
+/// Replace `<number> <unit>` and `<number><unit>` with the time mask. Plain numbers like the time
+/// index are left alone
+pub fn mask_times(text: &str) -> String {
+    text.split_inclusive('\n')
+        .map(|line| {
+            let (body, newline) = match line.strip_suffix('\n') {
+                Some(body) => (body, "\n"),
+                None => (line, ""),
+            };
+            let words: Vec<&str> = body.split(' ').collect();
+            let mut out = Vec::with_capacity(words.len());
+            let mut i = 0;
+            while i < words.len() {
+                let word = words[i];
+                if let Some(unit) = words.get(i + 1).filter(|_| is_number(word)) {
+                    let (unit, punct) = split_punct(unit);
+                    if is_time_unit(unit) {
+                        out.push(format!("{TIME_MASK}{punct}"));
+                        i += 2;
+                        continue;
+                    }
+                }
+                let (core, punct) = split_punct(word);
+                let number = core.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+                if is_number(number) && is_time_unit(&core[number.len()..]) {
+                    out.push(format!("{TIME_MASK}{punct}"));
+                } else {
+                    out.push(word.to_string());
+                }
+                i += 1;
+            }
+            format!("{}{newline}", out.join(" "))
+        })
+        .collect()
+}
+
+fn is_number(word: &str) -> bool {
+    !word.is_empty()
+        && word.chars().any(|c| c.is_ascii_digit())
+        && word.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+fn is_time_unit(word: &str) -> bool {
+    TIME_UNITS.contains(&word)
+}
+
+/// Split trailing punctuation off a word, `12ns,` is still a time
+fn split_punct(word: &str) -> (&str, &str) {
+    let core = word.trim_end_matches([',', ')', ';']);
+    (core, &word[core.len()..])
+}
+
+/// Decoded text of an `O` console output packet. `OK` isn't one
+pub fn console_output(payload: &str) -> Option<String> {
+    let hex = payload.strip_prefix('O')?;
+    if hex.is_empty() || payload == "OK" {
+        return None;
+    }
+    hex_decode(hex)
+}
+
+fn hex_encode(text: &str) -> String {
+    text.bytes().map(|b| format!("{b:02x}")).collect()
+}
+
+fn hex_decode(hex: &str) -> Option<String> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+pub fn checksum(payload: &str) -> u8 {
+    payload.bytes().fold(0u8, |acc, b| acc.wrapping_add(b))
+}
+
+/// One transcript line: direction, the payload with a recomputed checksum, and the decoded text
+/// of monitor traffic so the diff is readable
+pub(crate) fn render(direction: &str, payload: &str) -> String {
+    let line = format!("{direction} ${payload}#{:02x}", checksum(payload));
+    let decoded = payload
+        .strip_prefix("qRcmd,")
+        .and_then(hex_decode)
+        .or_else(|| console_output(payload));
+    match decoded {
+        Some(text) => format!("{line}  ; {text:?}"),
+        None => line,
+    }
+}
+
+/// Whether the stub has more to say after `payload`: an ack before the real reply, or console
+/// output before the final `OK`
+fn expects_more(payload: &str) -> bool {
+    payload == "+" || console_output(payload).is_some()
+}
+
+/// Run [`golden_script`] over `client` and return the normalized transcript
+pub fn record_transcript(client: &mut Client, normalizer: &Normalizer) -> io::Result<String> {
+    let mut scratch = [0u8; 4096];
+    let mut out = String::new();
+    for packet in golden_script() {
+        let wire = packet.to_finished_packet(&mut scratch)?;
+        let wire = String::from_utf8_lossy(wire.0);
+        // `$payload#cs` on the wire, checksums get recomputed after masking anyway
+        let request = wire
+            .strip_prefix('$')
+            .and_then(|rest| rest.rsplit_once('#'))
+            .map(|(payload, _)| payload.to_string())
+            .unwrap_or_else(|| wire.to_string());
+        out.push_str(&render("->", &normalizer.text(&request)));
+        out.push('\n');
+
+        let mut response = client.send_command(&packet)?;
+        loop {
+            let payload = String::from_utf8_lossy(response.as_slice()).to_string();
+            out.push_str(&render("<-", &normalizer.response(&request, &payload)));
+            out.push('\n');
+            if !expects_more(&payload) {
+                break;
+            }
+            response = client.pop_raw_response()?;
+        }
+    }
+    Ok(out)
+}
+
+/// Compare two transcripts line by line. `None` when they match, otherwise a diff with `-` for
+/// lines only in `expected` and `+` for lines only in `actual`, with line numbers from `expected`
+pub fn diff_transcripts(expected: &str, actual: &str) -> Option<String> {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+    if old == new {
+        return None;
+    }
+
+    // Longest common subsequence table, transcripts are a few dozen lines
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            diff.push_str(&format!("{:>4} + {}\n", i + 1, new[j]));
+            j += 1;
+        } else {
+            diff.push_str(&format!("{:>4} - {}\n", i + 1, old[i]));
+            i += 1;
+        }
+    }
+    Some(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::test_utils::*;
+
+    use std::path::PathBuf;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    #[test]
+    fn test_exec_file_path_is_masked() {
+        let normalizer = Normalizer::default();
+        let request = "qXfer:exec-file:read::0,100";
+        assert_eq!(
+            normalizer.response(request, "l/home/someone/dang/test_data/ibex/hello_test.elf"),
+            "l<exec-file>"
+        );
+        // errors aren't paths
+        assert_eq!(normalizer.response(request, "E01"), "E01");
+    }
+
+    #[test]
+    fn test_times_are_masked_but_indices_are_not() {
+        assert_eq!(mask_times("1234\n"), "1234\n");
+        assert_eq!(mask_times("time 1234 ps\n"), "time <time>\n");
+        assert_eq!(mask_times("at 12.5ns, idx 7"), "at <time>, idx 7");
+        assert_eq!(mask_times("5 steps"), "5 steps");
+
+        let normalizer = Normalizer::default();
+        let output = format!("O{}", hex_encode("now 40 ns\n"));
+        let masked = normalizer.response("qRcmd,74696d65", &output);
+        assert_eq!(console_output(&masked).unwrap(), "now <time>\n");
+        assert_eq!(normalizer.response("qRcmd,74696d65", "OK"), "OK");
+    }
+
+    #[test]
+    fn test_root_is_masked() {
+        let normalizer = Normalizer::with_root("/work/dang");
+        assert_eq!(
+            normalizer.text("loaded /work/dang/test_data/ibex/sim.fst"),
+            "loaded <root>/test_data/ibex/sim.fst"
+        );
+    }
+
+    #[test]
+    fn test_register_data_is_untouched() {
+        // behavior changes must still show up, only volatile fields are masked
+        let normalizer = Normalizer::with_root("/work/dang");
+        let regs = "00000000a4001000100000000000000000001000";
+        assert_eq!(normalizer.response("g", regs), regs);
+        assert_eq!(normalizer.response("s", "T05thread:01;"), "T05thread:01;");
+    }
+
+    #[test]
+    fn test_render_recomputes_checksum() {
+        assert_eq!(render("<-", "OK"), "<- $OK#9a");
+        assert_eq!(
+            render("->", "qRcmd,74696d655f696478"),
+            format!(
+                "-> $qRcmd,74696d655f696478#{:02x}  ; \"time_idx\"",
+                checksum("qRcmd,74696d655f696478")
+            )
+        );
+    }
+
+    #[test]
+    fn test_diff_transcripts() {
+        let expected = "-> $g#67\n<- $00#60\n-> $s#73\n<- $S05#b8\n";
+        assert_eq!(diff_transcripts(expected, expected), None);
+
+        let actual = "-> $g#67\n<- $01#61\n-> $s#73\n<- $S05#b8\n";
+        let diff = diff_transcripts(expected, actual).unwrap();
+        assert_eq!(diff, "   2 + <- $01#61\n   2 - <- $00#60\n");
+    }
+
+    fn golden_path() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../test_data/ibex/golden_transcript.txt")
+    }
+
+    /// Record the transcript against the ibex fixture and compare it to the checked in golden.
+    /// Run with `SHUCKS_BLESS_GOLDEN=1` to rewrite the golden after an intended behavior change
+    #[test]
+    fn test_golden_transcript() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let _server_handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+
+        let root = std::env::current_dir()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let mut client = Client::new_with_port(port);
+        let transcript =
+            record_transcript(&mut client, &Normalizer::with_root(root)).expect("record failed");
+
+        let path = golden_path();
+        if std::env::var_os("SHUCKS_BLESS_GOLDEN").is_some() {
+            std::fs::write(&path, &transcript).expect("failed to write golden transcript");
+            return;
+        }
+        let Ok(golden) = std::fs::read_to_string(&path) else {
+            panic!(
+                "no golden transcript at {}, run with SHUCKS_BLESS_GOLDEN=1 to record it",
+                path.display()
+            );
+        };
+        if let Some(diff) = diff_transcripts(&golden, &transcript) {
+            panic!(
+                "protocol transcript differs from {}:\n{diff}\nrerun with SHUCKS_BLESS_GOLDEN=1 if the change is intended",
+                path.display()
+            );
+        }
+    }
+}
@@ -3,12 +3,24 @@ use std::io;
 pub mod addr2line_stepper;
 pub mod client;
 pub mod commands;
+pub mod debuglink;
+pub mod decoder;
+pub mod elf_watch;
+pub mod findinst;
+pub mod golden;
 pub mod packet;
+pub mod remote_vars;
 pub mod response;
+pub mod session;
+pub mod stress;
+pub mod symbolize;
 mod wavetracker;
 
 pub use addr2line_stepper::SourceLine;
 pub use client::Client;
+pub use wavetracker::{
+    FormattingType, NameFormat, SignalStats, SignalValueResult, TrackerCounts, ValueError,
+};
 use commands::{Base, GdbCommand};
 use packet::FinishedPacket;
 pub use wellen::{TimeTableIdx, Var};
@@ -41,6 +53,25 @@ impl Packet {
         }
     }
 
+    /// Whether an empty reply to this is the RSP's "not supported", see [`Base::is_query`]
+    pub fn is_query(&self) -> bool {
+        match self {
+            Self::Ack => false,
+            Self::Command(GdbCommand::Base(base)) => base.is_query(),
+            Self::Command(GdbCommand::Resume(_)) => false,
+        }
+    }
+
+    /// Whether the answer to this is a stop reply: a resume, or `?`
+    pub fn expects_stop_reply(&self) -> bool {
+        match self {
+            Self::Ack => false,
+            Self::Command(GdbCommand::Base(Base::QuestionMark)) => true,
+            Self::Command(GdbCommand::Base(_)) => false,
+            Self::Command(GdbCommand::Resume(_)) => true,
+        }
+    }
+
     pub fn is_monitor_command(&self) -> bool {
         match self {
             Self::Ack => false,
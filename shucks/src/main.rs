@@ -1,6 +1,116 @@
+use std::process::ExitCode;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use argh::FromArgs;
+use shucks::stress::{parse_mem_range, run_stress, StressConfig, DEFAULT_HISTORY};
 use shucks::Client;
 
-fn main() {
-    println!("Hello, world!");
-    let _cl = Client::new();
+#[derive(FromArgs, Debug)]
+/// shucks - a GDB remote protocol client
+struct ShucksArgs {
+    #[argh(option, default = "9001")]
+    /// port of the stub on 127.0.0.1
+    port: u16,
+
+    #[argh(subcommand)]
+    command: Command,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand)]
+enum Command {
+    Stress(StressArgs),
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "stress")]
+/// run a seeded, randomized sequence of exchanges against the stub, checking after each that it
+/// still answers `?`
+struct StressArgs {
+    #[argh(option)]
+    /// seed to plan the run from, printed at the start so a failing run can be replayed. Picked
+    /// from the clock if not given
+    seed: Option<u64>,
+
+    #[argh(option, default = "200")]
+    /// number of exchanges
+    iterations: usize,
+
+    #[argh(option, from_str_fn(parse_mem_range))]
+    /// where memory reads land, as <addr>:<size> in hex. No memory reads without it
+    mem_range: Option<(u32, u32)>,
+
+    #[argh(option)]
+    /// monitor command to send now and then, can be repeated
+    monitor: Vec<String>,
+
+    #[argh(switch)]
+    /// step only, never continue
+    no_continue: bool,
+
+    #[argh(option, default = "10000")]
+    /// milliseconds a step or continue gets to stop
+    resume_timeout_ms: u64,
+
+    #[argh(option, default = "5")]
+    /// percent of reads sent twice back to back
+    retransmit_percent: u32,
+
+    #[argh(option, default = "2")]
+    /// percent of packets sent with a wrong checksum
+    bad_checksum_percent: u32,
+
+    #[argh(option, default = "DEFAULT_HISTORY")]
+    /// exchanges to dump on failure
+    history: usize,
+}
+
+fn stress(port: u16, args: StressArgs) -> ExitCode {
+    let seed = args.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_nanos() as u64)
+    });
+    println!("seed: {seed}");
+
+    let config = StressConfig {
+        seed,
+        iterations: args.iterations,
+        mem_range: args.mem_range,
+        monitor_cmds: args.monitor,
+        continues: !args.no_continue,
+        resume_timeout: Duration::from_millis(args.resume_timeout_ms),
+        retransmit_percent: args.retransmit_percent,
+        bad_checksum_percent: args.bad_checksum_percent,
+        history: args.history,
+    };
+
+    let mut client = Client::new_with_port(port);
+    if let Err(e) = client.initialize_gdb_session() {
+        eprintln!("error: session setup failed: {e}");
+        return ExitCode::FAILURE;
+    }
+
+    match run_stress(&mut client, &config) {
+        Ok(report) => {
+            println!("{} exchanges ok", report.exchanges);
+            if let Some(exited) = report.exited {
+                println!("stopped early, the target exited: {exited}");
+            }
+            ExitCode::SUCCESS
+        }
+        Err(failure) => {
+            eprintln!("error: {failure}");
+            eprintln!("last exchanges:\n{}", failure.history);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    env_logger::init();
+    let args: ShucksArgs = argh::from_env();
+    match args.command {
+        Command::Stress(stress_args) => stress(args.port, stress_args),
+    }
 }
@@ -37,3 +37,33 @@ impl<'a> PacketCursor<'a> {
         Ok(FinishedPacket(slice))
     }
 }
+
+/// Escape a binary payload for the `X` packet: `$`, `#`, `}` and `*` are sent as `}` followed
+/// by the byte xor 0x20
+pub fn escape_binary(data: &[u8]) -> Vec<u8> {
+    let mut rv = Vec::with_capacity(data.len());
+    for &b in data {
+        match b {
+            b'$' | b'#' | b'}' | b'*' => {
+                rv.push(b'}');
+                rv.push(b ^ 0x20);
+            }
+            _ => rv.push(b),
+        }
+    }
+    rv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_binary() {
+        assert_eq!(escape_binary(&[0x00, 0x13, 0xff]), vec![0x00, 0x13, 0xff]);
+        assert_eq!(
+            escape_binary(b"$#}*"),
+            vec![b'}', 0x04, b'}', 0x03, b'}', 0x5d, b'}', 0x0a]
+        );
+    }
+}
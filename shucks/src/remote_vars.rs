@@ -0,0 +1,412 @@
+//! Fuzzy matching against the variable list dang serves over `list_vars`, for when the waveform
+//! isn't loaded on this side.
+//!
+//! A waveform can have hundreds of thousands of variables, so nothing is fetched up front. Each
+//! query is turned into a filter the target applies before sending anything back (see
+//! [`filter_for_query`]), the matching names are fetched a page at a time, and the pages stick
+//! around for as long as the next query's filter can be answered from them
+
+use std::error::Error;
+
+use dang::var_list::filter_matches;
+use nucleo_matcher::{Config, Matcher};
+
+use crate::wavetracker::rank_matches;
+use crate::Client;
+
+/// Names fetched per `list_vars` request
+pub const DEFAULT_PAGE_SIZE: usize = 256;
+
+/// Something monitor commands can be sent to, [`Client`] outside of tests
+pub trait MonitorTarget {
+    fn monitor(&mut self, cmd: &str) -> Result<String, Box<dyn Error>>;
+}
+
+impl MonitorTarget for Client {
+    fn monitor(&mut self, cmd: &str) -> Result<String, Box<dyn Error>> {
+        self.send_monitor_command(cmd)
+    }
+}
+
+/// One `list_vars` reply: how many names match the filter, then a page of them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VarPage {
+    pub total: usize,
+    pub names: Vec<String>,
+}
+
+pub fn list_vars_command(filter: &str, offset: usize, count: usize) -> String {
+    if filter.is_empty() {
+        format!("list_vars {offset} {count}")
+    } else {
+        format!("list_vars {offset} {count} {filter}")
+    }
+}
+
+pub fn parse_var_page(output: &str) -> Result<VarPage, String> {
+    let mut lines = output.lines();
+    let first = lines.next().unwrap_or("").trim();
+    if first.starts_with("usage") {
+        return Err(format!("Target rejected list_vars: {first}"));
+    }
+    let total = first
+        .parse::<usize>()
+        .map_err(|_| format!("Malformed list_vars reply: {first}"))?;
+    Ok(VarPage {
+        total,
+        names: lines
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(String::from)
+            .collect(),
+    })
+}
+
+/// The target-side filter for a fuzzy `query`: its longest word. Every fuzzy match contains the
+/// characters of each word in order, which is exactly what the target filters on
+pub fn filter_for_query(query: &str) -> &str {
+    query.split_whitespace().fold("", |longest, word| {
+        if word.len() > longest.len() {
+            word
+        } else {
+            longest
+        }
+    })
+}
+
+/// The remote variable list under one filter, fetched lazily and kept between queries
+pub struct RemoteVarIndex {
+    filter: String,
+    page_size: usize,
+    /// How many names match `filter`, unknown until the first page comes back
+    total: Option<usize>,
+    /// The first `names.len()` matches, in hierarchy order
+    names: Vec<String>,
+    matcher: Matcher,
+}
+
+impl Default for RemoteVarIndex {
+    fn default() -> Self {
+        Self::new(DEFAULT_PAGE_SIZE)
+    }
+}
+
+impl RemoteVarIndex {
+    pub fn new(page_size: usize) -> Self {
+        Self {
+            filter: String::new(),
+            page_size: page_size.max(1),
+            total: None,
+            names: Vec::new(),
+            matcher: Matcher::new(Config::DEFAULT),
+        }
+    }
+
+    pub fn filter(&self) -> &str {
+        &self.filter
+    }
+
+    /// Every name matching the filter has been fetched
+    pub fn is_complete(&self) -> bool {
+        self.total == Some(self.names.len())
+    }
+
+    /// Names fetched so far
+    pub fn cached(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Switch to `filter`. When it only narrows the current one and everything under the
+    /// current one is already here, the cache is filtered down in place. Otherwise it's dropped
+    /// and refetched on demand. Returns whether the cache was kept
+    pub fn refine(&mut self, filter: &str) -> bool {
+        if filter == self.filter {
+            return true;
+        }
+        // anything containing `filter`'s characters in order also contains the old filter's
+        let narrows = filter_matches(&self.filter, filter);
+        self.filter = filter.to_string();
+        if narrows && self.is_complete() {
+            self.names.retain(|name| filter_matches(filter, name));
+            self.total = Some(self.names.len());
+            true
+        } else {
+            self.names.clear();
+            self.total = None;
+            false
+        }
+    }
+
+    /// Fetch the next page. Returns false once there's nothing left to fetch
+    pub fn fetch_page(&mut self, target: &mut impl MonitorTarget) -> Result<bool, Box<dyn Error>> {
+        if self.is_complete() {
+            return Ok(false);
+        }
+        let cmd = list_vars_command(&self.filter, self.names.len(), self.page_size);
+        let page = parse_var_page(&target.monitor(&cmd)?)?;
+        if page.names.is_empty() && page.total > self.names.len() {
+            return Err(format!(
+                "list_vars returned nothing at offset {} of {}",
+                self.names.len(),
+                page.total
+            )
+            .into());
+        }
+        self.names.extend(page.names);
+        self.names.truncate(page.total);
+        self.total = Some(page.total);
+        Ok(!self.is_complete())
+    }
+
+    /// Fuzzy match `query` against the remote list, ranked the same as a local waveform would
+    /// be. Pages are fetched until every candidate under the query's filter is here, checking
+    /// `cancel` before each one. Returns `None` if cancelled, the pages fetched so far are kept
+    /// for the next call
+    pub fn fuzzy_match(
+        &mut self,
+        target: &mut impl MonitorTarget,
+        query: &str,
+        mut cancel: impl FnMut() -> bool,
+    ) -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        self.refine(filter_for_query(query));
+        while !self.is_complete() {
+            if cancel() {
+                return Ok(None);
+            }
+            self.fetch_page(target)?;
+        }
+        let ranked = rank_matches(
+            &mut self.matcher,
+            query,
+            self.names.iter().map(String::as_str),
+        );
+        Ok(Some(
+            ranked
+                .into_iter()
+                .map(|idx| self.names[idx].clone())
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::test_utils::{create_test_listener, start_dang_instance};
+    use crate::wavetracker::WaveformTracker;
+    use dang::var_list::VarList;
+    use std::path::PathBuf;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    /// Answers `list_vars` the way dang does, and remembers what it was asked
+    struct MockTarget {
+        vars: VarList,
+        requests: Vec<String>,
+    }
+
+    impl MockTarget {
+        fn new(names: &[&str]) -> Self {
+            Self {
+                vars: VarList::new(names.iter().map(|n| n.to_string()).collect()),
+                requests: Vec::new(),
+            }
+        }
+
+        fn take_requests(&mut self) -> Vec<String> {
+            std::mem::take(&mut self.requests)
+        }
+    }
+
+    impl MonitorTarget for MockTarget {
+        fn monitor(&mut self, cmd: &str) -> Result<String, Box<dyn Error>> {
+            self.requests.push(cmd.to_string());
+            let mut args = cmd.strip_prefix("list_vars ").unwrap().split(' ');
+            let offset = args.next().unwrap().parse().unwrap();
+            let count = args.next().unwrap().parse().unwrap();
+            let filter = args.next().unwrap_or("");
+            let (total, names) = self.vars.page(filter, offset, count);
+            let mut out = format!("{total}\n");
+            for name in names {
+                out.push_str(name);
+                out.push('\n');
+            }
+            Ok(out)
+        }
+    }
+
+    const NAMES: [&str; 7] = [
+        "top.clk",
+        "top.cpu.pc",
+        "top.cpu.rf.x1",
+        "top.cpu.rf.x2",
+        "top.cpu.csr.mepc",
+        "top.uart.tx",
+        "top.uart.rx",
+    ];
+
+    fn never() -> bool {
+        false
+    }
+
+    #[test]
+    fn test_filter_for_query() {
+        assert_eq!(filter_for_query(""), "");
+        assert_eq!(filter_for_query("cpu"), "cpu");
+        assert_eq!(filter_for_query("rf cpu.pc x"), "cpu.pc");
+    }
+
+    #[test]
+    fn test_parse_var_page() {
+        assert_eq!(
+            parse_var_page("2\ntop.a\ntop.b\n").unwrap(),
+            VarPage {
+                total: 2,
+                names: vec!["top.a".to_string(), "top.b".to_string()]
+            }
+        );
+        assert_eq!(parse_var_page("0\n").unwrap().names, Vec::<String>::new());
+        assert!(parse_var_page("usage: list_vars <offset> <count> [filter]").is_err());
+        assert!(parse_var_page("").is_err());
+    }
+
+    #[test]
+    fn test_pages_fetched_on_demand() {
+        let mut target = MockTarget::new(&NAMES);
+        let mut index = RemoteVarIndex::new(2);
+
+        let matches = index
+            .fuzzy_match(&mut target, "cpu", never)
+            .unwrap()
+            .unwrap();
+        assert_eq!(matches.len(), 4);
+        // four matches under "cpu", two to a page
+        assert_eq!(
+            target.take_requests(),
+            vec!["list_vars 0 2 cpu", "list_vars 2 2 cpu"]
+        );
+        assert!(index.is_complete());
+    }
+
+    #[test]
+    fn test_narrowing_reuses_the_cache() {
+        let mut target = MockTarget::new(&NAMES);
+        let mut index = RemoteVarIndex::new(2);
+        index.fuzzy_match(&mut target, "cpu", never).unwrap();
+        target.take_requests();
+
+        // "cpurf" and "cpu rf" both only narrow "cpu"
+        let matches = index
+            .fuzzy_match(&mut target, "cpurf", never)
+            .unwrap()
+            .unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(index.filter(), "cpurf");
+        assert!(target.take_requests().is_empty());
+
+        // same filter as before, "x1" is shorter than "cpurf"
+        index
+            .fuzzy_match(&mut target, "cpurf x1", never)
+            .unwrap()
+            .unwrap();
+        assert!(target.take_requests().is_empty());
+
+        // widening can't be served from what's cached
+        assert!(!index.refine("cp"));
+        let matches = index
+            .fuzzy_match(&mut target, "cp", never)
+            .unwrap()
+            .unwrap();
+        assert_eq!(matches.len(), 4);
+        assert_eq!(
+            target.take_requests(),
+            vec!["list_vars 0 2 cp", "list_vars 2 2 cp"]
+        );
+
+        // neither narrower nor wider
+        assert!(!index.refine("uart"));
+    }
+
+    #[test]
+    fn test_partial_cache_is_refetched() {
+        let mut target = MockTarget::new(&NAMES);
+        let mut index = RemoteVarIndex::new(2);
+        index.refine("top");
+        assert!(index.fetch_page(&mut target).unwrap());
+        assert!(!index.is_complete());
+
+        // narrower, but the cache doesn't hold everything under "top" so it can't be filtered
+        assert!(!index.refine("topuart"));
+        assert!(index.cached().is_empty());
+        target.take_requests();
+        let matches = index
+            .fuzzy_match(&mut target, "topuart", never)
+            .unwrap()
+            .unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(target.take_requests(), vec!["list_vars 0 2 topuart"]);
+    }
+
+    #[test]
+    fn test_cancel_keeps_fetched_pages() {
+        let mut target = MockTarget::new(&NAMES);
+        let mut index = RemoteVarIndex::new(2);
+        let mut pages_allowed = 1;
+        let cancelled = index
+            .fuzzy_match(&mut target, "top", || {
+                if pages_allowed == 0 {
+                    return true;
+                }
+                pages_allowed -= 1;
+                false
+            })
+            .unwrap();
+        assert_eq!(cancelled, None);
+        assert_eq!(target.take_requests(), vec!["list_vars 0 2 top"]);
+
+        index
+            .fuzzy_match(&mut target, "top", never)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            target.take_requests(),
+            vec![
+                "list_vars 2 2 top",
+                "list_vars 4 2 top",
+                "list_vars 6 2 top"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matches_local_hierarchy() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        client
+            .initialize_gdb_session()
+            .expect("failed to init gdb session");
+
+        let wave_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../test_data/ibex/sim.fst");
+        let mut local = WaveformTracker::new(wave_path).unwrap();
+        let mut remote = RemoteVarIndex::new(64);
+
+        for query in ["pc", "rf_reg", "clk", "ibex core", "zzzzzz"] {
+            let expected: Vec<String> = local
+                .fuzzy_match_var(query)
+                .into_iter()
+                .map(|(_, name)| name)
+                .collect();
+            let actual = remote
+                .fuzzy_match(&mut client, query, never)
+                .unwrap()
+                .unwrap();
+            assert_eq!(actual, expected, "results differ for '{query}'");
+        }
+        drop(handle);
+    }
+}
@@ -11,7 +11,11 @@ pub enum GdbResponse {
 
     /// Simple status responses  
     Ok, // "OK"
-    Empty, // Empty response for unsupported commands
+    /// A zero-length payload, e.g. reading no bytes of memory
+    Empty,
+    /// An empty reply to a query, the RSP's way of saying the stub doesn't do it. Only
+    /// [`GdbResponse::parse_reply`] gives this
+    Unsupported,
 
     /// Error responses
     Error {
@@ -23,6 +27,18 @@ pub enum GdbResponse {
         signal: u8,
         thread_id: Option<ThreadId>,
         reason: StopReason,
+        /// Register values a `T` reply carries, by register number, in target byte order
+        regs: Vec<(u8, Vec<u8>)>,
+    },
+
+    /// `Wxx`, the process exited with this code
+    Exited {
+        code: u8,
+    },
+
+    /// `Xxx`, the process was terminated by this signal
+    TerminatedWithSignal {
+        signal: u8,
     },
 
     /// Memory read response - hex-encoded data
@@ -41,6 +57,11 @@ pub enum GdbResponse {
         more_data: bool, // true if this is partial data (qfThreadInfo vs qsThreadInfo)
     },
 
+    /// `QC<tid>`, the reply to `qC`: the thread the stub has selected
+    CurrentThread {
+        thread: ThreadId,
+    },
+
     /// qSupported response - feature negotiation
     Supported {
         features: Vec<String>,
@@ -77,6 +98,18 @@ pub enum ThreadId {
     Process { pid: u32, tid: u32 }, // process.thread for multiprocess
 }
 
+/// As the RSP writes it, the way [`GdbResponse::parse_thread_id`] reads it
+impl fmt::Display for ThreadId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThreadId::Any => write!(f, "0"),
+            ThreadId::All => write!(f, "-1"),
+            ThreadId::Specific(tid) => write!(f, "{tid:x}"),
+            ThreadId::Process { pid, tid } => write!(f, "p{pid:x}.{tid:x}"),
+        }
+    }
+}
+
 /// Reasons why the target stopped
 #[derive(Debug, Clone, PartialEq)]
 pub enum StopReason {
@@ -123,7 +156,9 @@ impl From<std::io::Error> for ParseError {
 #[derive(Debug, Clone, PartialEq)]
 pub struct RawGdbResponse {
     data: Vec<u8>,
-    omitted: usize,
+    /// Bytes the packet took up on the wire, framing and checksum included. Kept as read rather
+    /// than worked out from `data`, so decoding the payload can't throw it off
+    wire_len: usize,
 }
 
 impl RawGdbResponse {
@@ -135,17 +170,23 @@ impl RawGdbResponse {
         self.data.len()
     }
 
-    /// Returns the length of the entire packet, including the checksum
+    /// Returns the length of the entire packet as it was on the wire, including the checksum
     pub fn entire_packet_len(&self) -> usize {
-        // ack and nacks are single bytes
-        if self.data.len() == 1 && (self.data[0] == b'+' || self.data[0] == b'-') {
-            1
-        } else {
-            // add 4 to account for the $ prefix, # separator, and checksum
-            self.data.len() + self.omitted
+        self.wire_len
+    }
+
+    /// Whether this is a stop reply, `S`, `T`, `W` or `X` and a two digit hex number
+    pub fn is_stop_reply(&self) -> bool {
+        match self.data.as_slice() {
+            [b'S' | b'T' | b'W' | b'X', hi, lo, ..] => {
+                hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit()
+            }
+            _ => false,
         }
     }
 
+    /// Parse the packet at the start of `data`, which may be followed by more. The payload is
+    /// kept as sent, run lengths and escapes are decoded later
     pub fn find_packet_data(data: &[u8]) -> Result<Self, ParseError> {
         if data.is_empty() {
             return Err(ParseError::InvalidFormat("no data"));
@@ -155,7 +196,7 @@ impl RawGdbResponse {
         if data[0] == b'+' || data[0] == b'-' {
             return Ok(Self {
                 data: vec![data[0]],
-                omitted: 0,
+                wire_len: 1,
             });
         }
 
@@ -195,7 +236,8 @@ impl RawGdbResponse {
         }
         Ok(RawGdbResponse {
             data: content.to_vec(),
-            omitted: 4, // 4 bytes omitted -- one for $ prefix, one for # separator, and two for checksum
+            // everything up to and including the two checksum digits
+            wire_len: hash_pos + 3,
         })
     }
 }
@@ -206,180 +248,99 @@ impl GdbResponse {
         Self::parse_content(content, packet)
     }
 
-    /// Parse the content portion of a GDB packet
-    fn parse_content(raw_resp: RawGdbResponse, packet: &Packet) -> Result<Self, ParseError> {
-        let content = raw_resp.as_slice();
-
-        if content.is_empty() {
-            log::trace!("Empty content -> GdbResponse::Empty");
-            return Ok(GdbResponse::Empty);
+    /// [`GdbResponse::parse_packet`] for the reply to `packet` in flight. An empty reply to a
+    /// query is [`GdbResponse::Unsupported`], to anything else it stays [`GdbResponse::Empty`]
+    pub fn parse_reply(content: RawGdbResponse, packet: &Packet) -> Result<Self, ParseError> {
+        match Self::parse_packet(content, packet)? {
+            GdbResponse::Empty if packet.is_query() => Ok(GdbResponse::Unsupported),
+            response => Ok(response),
         }
+    }
 
-        let content_str = str::from_utf8(content).unwrap_or(""); // Allow non-UTF8 for binary data
-
-        match content {
-            b"" => Ok(GdbResponse::Empty),
-
-            b"+" => Ok(GdbResponse::Ack),
-            b"-" => Ok(GdbResponse::Nack),
-            // Simple OK response
-            b"OK" => Ok(GdbResponse::Ok),
+    /// Parse the content portion of a GDB packet, by the first of [`RULES`] that takes it
+    fn parse_content(raw_resp: RawGdbResponse, packet: &Packet) -> Result<Self, ParseError> {
+        let content = raw_resp.as_slice();
+        let rule = matching_rule(content, packet);
+        log::debug!(
+            "{} byte reply to {packet:?} classified by rule '{}'",
+            content.len(),
+            rule.name
+        );
+        (rule.build)(content, packet).map(|response| {
+            log::trace!("Final parsed response: {response}");
+            response
+        })
+    }
 
-            // Error response (Exx)
-            content if content.len() >= 3 && content[0] == b'E' => {
-                let code_str =
-                    str::from_utf8(&content[1..3]).map_err(|_| ParseError::InvalidHex)?;
-                let code = u8::from_str_radix(code_str, 16).map_err(|_| ParseError::InvalidHex)?;
-                Ok(GdbResponse::Error { code })
-            }
+    /// Error response, `Exx` with a hex error code
+    fn parse_error(content: &[u8]) -> Result<Self, ParseError> {
+        let code_str = str::from_utf8(&content[1..3]).map_err(|_| ParseError::InvalidHex)?;
+        let code = u8::from_str_radix(code_str, 16).map_err(|_| ParseError::InvalidHex)?;
+        Ok(GdbResponse::Error { code })
+    }
 
-            // Stop reply packet (Sxx, Txx, Wxx, or Xxx)
-            content if content.len() >= 3 && (content[0] == b'S' || content[0] == b'T' || content[0] == b'W' || content[0] == b'X') => {
-                Self::parse_stop_reply(content)
-            }
+    /// The text of a monitor command's reply. Console output comes as `O<hex>`, some stubs send
+    /// the hex alone, and anything that doesn't decode is taken as it is
+    fn parse_monitor_output(content: &[u8]) -> String {
+        if content.starts_with(b"O") && content.len() > 1 {
+            // Strip the 'O' prefix, decode run-length encoding, then decode hex
+            let hex_content = &content[1..];
 
-            // qXfer responses (m<data> or l<data>)
-            content if content.starts_with(b"m") => {
-                let data_part = &content[1..];
-                let looks_like_thread = Self::looks_like_thread_info(data_part);
+            // Decode run-length encoding first (handles '*' markers)
+            let run_length_decoded = Self::decode_run_length(hex_content);
 
-                // This could be either thread info or qXfer data
-                // Try to parse as thread info first, then fall back to qXfer
-                if looks_like_thread {
-                    Self::parse_thread_info(content, false)
-                } else {
-                    // Parse as qXfer data
-                    Ok(GdbResponse::QXferData {
-                        data: content[1..].to_vec(),
-                        is_final: false,
-                    })
-                }
-            }
-            content if content.starts_with(b"l") => {
-                // This could be end of thread info or final qXfer data
-                if content.len() == 1 {
-                    // Just 'l' - end of thread info
-                    Ok(GdbResponse::ThreadInfo {
-                        threads: vec![],
-                        more_data: false,
-                    })
-                } else {
-                    // 'l' followed by data - final qXfer chunk
-                    Ok(GdbResponse::QXferData {
-                        data: content[1..].to_vec(),
-                        is_final: true,
-                    })
+            if Self::is_hex_data(&run_length_decoded) {
+                match Self::decode_hex(&run_length_decoded) {
+                    Ok(decoded_bytes) => String::from_utf8_lossy(&decoded_bytes).to_string(),
+                    Err(_e) => String::from_utf8_lossy(content).to_string(),
                 }
+            } else {
+                String::from_utf8_lossy(content).to_string()
             }
-            // Handle raw thread info responses that might not be properly formatted
-            content if content.len() == 2 && Self::is_hex_data(content) => {
-                // This might be a malformed thread info response, treat as end of thread list
-                Ok(GdbResponse::ThreadInfo {
-                    threads: vec![],
-                    more_data: false,
-                })
-            }
-
-            // qSupported response
-            _content
-                if content_str.contains("PacketSize")
-                    || content_str.contains("qRelocInsn")
-                    || content_str.contains("swbreak") =>
-            {
-                Self::parse_supported_response(content_str)
-            }
-
-            // Monitor command responses (typically for qRcmd)
-            content if packet.is_monitor_command() => {
-                // Monitor responses often come in the format "O<hex-encoded-text>"
-                // where 'O' indicates console output
-                let output = if content.starts_with(b"O") && content.len() > 1 {
-                    // Strip the 'O' prefix, decode run-length encoding, then decode hex
-                    let hex_content = &content[1..];
-
-                    // Decode run-length encoding first (handles '*' markers)
-                    let run_length_decoded = Self::decode_run_length(hex_content);
-
-                    if Self::is_hex_data(&run_length_decoded) {
-                        match Self::decode_hex(&run_length_decoded) {
-                            Ok(decoded_bytes) => {
-                                String::from_utf8_lossy(&decoded_bytes).to_string()
-                            }
-                            Err(_e) => String::from_utf8_lossy(content).to_string(),
-                        }
-                    } else {
-                        String::from_utf8_lossy(content).to_string()
-                    }
-                } else if Self::is_hex_data_or_run_length(content) {
-                    // Decode run-length encoding first, then try hex decode
-                    let run_length_decoded = Self::decode_run_length(content);
-                    match Self::decode_hex(&run_length_decoded) {
-                        Ok(decoded_bytes) => String::from_utf8_lossy(&decoded_bytes).to_string(),
-                        Err(e) => {
-                            log::error!("error is {e:?} when decoding hex data");
-                            String::from_utf8_lossy(content).to_string()
-                        }
-                    }
-                } else {
+        } else if Self::is_hex_data_or_run_length(content) {
+            // Decode run-length encoding first, then try hex decode
+            let run_length_decoded = Self::decode_run_length(content);
+            match Self::decode_hex(&run_length_decoded) {
+                Ok(decoded_bytes) => String::from_utf8_lossy(&decoded_bytes).to_string(),
+                Err(e) => {
+                    log::error!("error is {e:?} when decoding hex data");
                     String::from_utf8_lossy(content).to_string()
-                };
-                Ok(GdbResponse::MonitorOutput { output })
-            }
-
-            // Hex-encoded data (register or memory reads) - always try run-length decoding first
-            content if Self::is_hex_data_or_run_length(content) => {
-                // Always decode run-length encoding first, then hex
-                let run_length_decoded = Self::decode_run_length(content);
-                let data = Self::decode_hex(&run_length_decoded)?;
-
-                log::trace!("Decoded run-length + hex data: {} bytes", data.len());
-
-                // Use packet type to determine response classification
-                if packet.is_register_read() {
-                    log::trace!(
-                        "Classified as RegisterData based on packet type (length={})",
-                        data.len()
-                    );
-                    Ok(GdbResponse::RegisterData { data })
-                } else if packet.is_memory_read() {
-                    log::trace!(
-                        "Classified as MemoryData based on packet type (length={})",
-                        data.len()
-                    );
-                    Ok(GdbResponse::MemoryData { data })
-                } else {
-                    // Fallback: use heuristic for unknown packet types
-                    if data.len() >= 128 && data.len() % 4 == 0 {
-                        log::trace!(
-                            "Heuristically classified as RegisterData (length={}, divisible by 4)",
-                            data.len()
-                        );
-                        Ok(GdbResponse::RegisterData { data })
-                    } else {
-                        log::debug!(
-                            "Classified as Raw data (unknown packet type, length={})",
-                            data.len()
-                        );
-                        Ok(GdbResponse::Raw { data })
-                    }
                 }
             }
+        } else {
+            String::from_utf8_lossy(content).to_string()
+        }
+    }
 
-            // Default: return as raw data
-            _ => {
-                log::trace!("Classified as Raw data (no specific pattern matched)");
-                Ok(GdbResponse::Raw {
-                    data: content.to_vec(),
-                })
-            }
+    /// Hex-encoded data, run lengths decoded first
+    fn decode_hex_data(content: &[u8]) -> Result<Vec<u8>, ParseError> {
+        let run_length_decoded = Self::decode_run_length(content);
+        let data = Self::decode_hex(&run_length_decoded)?;
+        log::trace!("Decoded run-length + hex data: {} bytes", data.len());
+        Ok(data)
+    }
+
+    /// Hex data in reply to something that isn't a register or memory read: registers if it's
+    /// long enough to be the whole file, raw data otherwise
+    fn parse_unknown_hex(content: &[u8]) -> Result<Self, ParseError> {
+        let data = Self::decode_hex_data(content)?;
+        if data.len() >= 128 && data.len() % 4 == 0 {
+            log::trace!(
+                "Heuristically classified as RegisterData (length={}, divisible by 4)",
+                data.len()
+            );
+            Ok(GdbResponse::RegisterData { data })
+        } else {
+            log::debug!(
+                "Classified as Raw data (unknown packet type, length={})",
+                data.len()
+            );
+            Ok(GdbResponse::Raw { data })
         }
-        .map(|response| {
-            log::trace!("Final parsed response: {response}");
-            response
-        })
     }
 
-    /// Parse stop reply packets (S, T, W, or X packets)
+    /// Parse stop reply packets (S, T, W, or X packets). `W` and `X` end the process, so they
+    /// come back as [`GdbResponse::Exited`] and [`GdbResponse::TerminatedWithSignal`]
     fn parse_stop_reply(content: &[u8]) -> Result<Self, ParseError> {
         if content.len() < 3 {
             return Err(ParseError::InvalidFormat("stop reply packet too short"));
@@ -390,22 +351,77 @@ impl GdbResponse {
         let value = u8::from_str_radix(value_str, 16).map_err(|_| ParseError::InvalidHex)?;
 
         // Determine the reason based on packet type
-        let reason = match packet_type {
+        let mut reason = match packet_type {
             b'S' | b'T' => StopReason::Signal(value),
-            b'W' => StopReason::ProcessExit { code: value },
-            b'X' => StopReason::SignalTermination(value),
+            // anything after the code, like `;process:pid`, says which process, and there's one
+            b'W' => return Ok(GdbResponse::Exited { code: value }),
+            b'X' => return Ok(GdbResponse::TerminatedWithSignal { signal: value }),
             _ => return Err(ParseError::InvalidFormat("unknown stop reply packet type")),
         };
 
-        // For now, we'll parse just the basic signal/code
-        // TODO: Parse additional stop reply information (thread ID, registers, process:pid extensions, etc.)
+        let mut thread_id = None;
+        let mut regs = Vec::new();
+        if packet_type == b'T' {
+            let info = String::from_utf8_lossy(&content[3..]);
+            for field in info.split(';').filter(|field| !field.is_empty()) {
+                let Some((key, value)) = field.split_once(':') else {
+                    log::warn!("Skipping malformed stop reply field '{field}'");
+                    continue;
+                };
+                match key {
+                    "thread" => match Self::parse_thread_id(value) {
+                        Some(id) => thread_id = Some(id),
+                        None => log::warn!("Skipping malformed thread id '{value}'"),
+                    },
+                    "swbreak" | "hwbreak" => reason = StopReason::Breakpoint,
+                    "watch" | "rwatch" | "awatch" => match u32::from_str_radix(value, 16) {
+                        Ok(addr) => reason = StopReason::Watchpoint { addr },
+                        Err(_) => log::warn!("Skipping malformed {key} address '{value}'"),
+                    },
+                    // `NN:value`, a register number in hex
+                    key if Self::is_hex_data(key.as_bytes()) => {
+                        match (
+                            u8::from_str_radix(key, 16),
+                            Self::decode_hex(&Self::decode_run_length(value.as_bytes())),
+                        ) {
+                            (Ok(reg), Ok(bytes)) if !bytes.is_empty() => regs.push((reg, bytes)),
+                            _ => log::warn!("Skipping malformed register value '{field}'"),
+                        }
+                    }
+                    // core, replaylog and the like
+                    _ => log::trace!("Ignoring stop reply field '{field}'"),
+                }
+            }
+        }
+
         Ok(GdbResponse::StopReply {
             signal: value,
-            thread_id: None,
+            thread_id,
             reason,
+            regs,
         })
     }
 
+    /// A thread id as stop replies, thread lists and `qC` give it: hex, `0` for any thread, `-1`
+    /// for all of them, and `p<pid>.<tid>` with the multiprocess extensions
+    fn parse_thread_id(text: &str) -> Option<ThreadId> {
+        let id = |text: &str| u32::from_str_radix(text, 16).ok();
+        match text {
+            "0" => Some(ThreadId::Any),
+            "-1" => Some(ThreadId::All),
+            _ => match text.strip_prefix('p') {
+                Some(process) => {
+                    let (pid, tid) = process.split_once('.')?;
+                    Some(ThreadId::Process {
+                        pid: id(pid)?,
+                        tid: id(tid)?,
+                    })
+                }
+                None => id(text).map(ThreadId::Specific),
+            },
+        }
+    }
+
     /// Parse thread info responses (mXX,YY,ZZ...)
     fn parse_thread_info(content: &[u8], more_data: bool) -> Result<Self, ParseError> {
         if content.len() < 2 || content[0] != b'm' {
@@ -415,22 +431,23 @@ impl GdbResponse {
         let thread_list_str = str::from_utf8(&content[1..])
             .map_err(|_| ParseError::InvalidFormat("invalid thread info -- its not a string"))?;
 
-        let mut threads = Vec::new();
-
-        for thread_str in thread_list_str.split(',') {
-            if thread_str == "0" {
-                threads.push(ThreadId::Any);
-            } else if thread_str == "-1" {
-                threads.push(ThreadId::All);
-            } else if let Ok(tid) = thread_str.parse::<u32>() {
-                threads.push(ThreadId::Specific(tid));
-            }
-            // TODO: Handle process.thread format
-        }
+        let threads = thread_list_str
+            .split(',')
+            .filter_map(Self::parse_thread_id)
+            .collect();
 
         Ok(GdbResponse::ThreadInfo { threads, more_data })
     }
 
+    /// `QC<tid>`
+    fn parse_current_thread(content: &[u8]) -> Result<Self, ParseError> {
+        str::from_utf8(&content[2..])
+            .ok()
+            .and_then(Self::parse_thread_id)
+            .map(|thread| GdbResponse::CurrentThread { thread })
+            .ok_or(ParseError::InvalidFormat("invalid thread id in qC reply"))
+    }
+
     /// Parse qSupported response
     fn parse_supported_response(content: &str) -> Result<Self, ParseError> {
         let features: Vec<String> = content.split(';').map(|s| s.to_string()).collect();
@@ -500,10 +517,10 @@ impl GdbResponse {
             Err(_) => return false,
         };
 
-        // Thread info should be comma-separated hex numbers or special values
+        // Thread info should be comma-separated thread ids
         content_str
             .split(',')
-            .all(|part| part == "0" || part == "-1" || part.chars().all(|c| c.is_ascii_hexdigit()))
+            .all(|part| Self::parse_thread_id(part).is_some())
     }
 
     /// Decode run-length encoded data from GDB
@@ -569,6 +586,221 @@ impl GdbResponse {
     }
 }
 
+/// What a [`Rule`] needs the reply to have been for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PacketKind {
+    Any,
+    /// `qRcmd`
+    MonitorCommand,
+    /// `g`
+    RegisterRead,
+    /// `m`
+    MemoryRead,
+}
+
+impl PacketKind {
+    fn matches(self, packet: &Packet) -> bool {
+        match self {
+            PacketKind::Any => true,
+            PacketKind::MonitorCommand => packet.is_monitor_command(),
+            PacketKind::RegisterRead => packet.is_register_read(),
+            PacketKind::MemoryRead => packet.is_memory_read(),
+        }
+    }
+}
+
+/// One way of reading a reply: taken when the packet it answers is `packet` and the content
+/// passes `content`
+struct Rule {
+    /// Logged when it matches, so a misparse can be traced to the rule that made it
+    name: &'static str,
+    packet: PacketKind,
+    content: fn(&[u8]) -> bool,
+    build: fn(&[u8], &Packet) -> Result<GdbResponse, ParseError>,
+}
+
+/// Whether `content` is one of the strings only a qSupported reply has
+fn looks_like_supported(content: &[u8]) -> bool {
+    let content_str = str::from_utf8(content).unwrap_or(""); // Allow non-UTF8 for binary data
+    content_str.contains("PacketSize")
+        || content_str.contains("qRelocInsn")
+        || content_str.contains("swbreak")
+}
+
+/// How replies are classified, first match wins. Order matters: `E01` and `W00` are hex too,
+/// and `m`/`l` replies are thread lists or qXfer chunks depending on what follows
+const RULES: &[Rule] = &[
+    Rule {
+        name: "empty",
+        packet: PacketKind::Any,
+        content: <[u8]>::is_empty,
+        build: |_, _| Ok(GdbResponse::Empty),
+    },
+    Rule {
+        name: "ack",
+        packet: PacketKind::Any,
+        content: |content| content == b"+",
+        build: |_, _| Ok(GdbResponse::Ack),
+    },
+    Rule {
+        name: "nack",
+        packet: PacketKind::Any,
+        content: |content| content == b"-",
+        build: |_, _| Ok(GdbResponse::Nack),
+    },
+    Rule {
+        name: "ok",
+        packet: PacketKind::Any,
+        content: |content| content == b"OK",
+        build: |_, _| Ok(GdbResponse::Ok),
+    },
+    Rule {
+        name: "error",
+        packet: PacketKind::Any,
+        content: |content| content.len() >= 3 && content[0] == b'E',
+        build: |content, _| GdbResponse::parse_error(content),
+    },
+    Rule {
+        name: "exited",
+        packet: PacketKind::Any,
+        content: |content| content.len() >= 3 && content[0] == b'W',
+        build: |content, _| GdbResponse::parse_stop_reply(content),
+    },
+    Rule {
+        name: "terminated_with_signal",
+        packet: PacketKind::Any,
+        content: |content| content.len() >= 3 && content[0] == b'X',
+        build: |content, _| GdbResponse::parse_stop_reply(content),
+    },
+    Rule {
+        name: "stop_reply",
+        packet: PacketKind::Any,
+        content: |content| content.len() >= 3 && matches!(content[0], b'S' | b'T'),
+        build: |content, _| GdbResponse::parse_stop_reply(content),
+    },
+    Rule {
+        name: "current_thread",
+        packet: PacketKind::Any,
+        content: |content| content.starts_with(b"QC"),
+        build: |content, _| GdbResponse::parse_current_thread(content),
+    },
+    Rule {
+        name: "thread_info",
+        packet: PacketKind::Any,
+        content: |content| {
+            content.starts_with(b"m") && GdbResponse::looks_like_thread_info(&content[1..])
+        },
+        build: |content, _| GdbResponse::parse_thread_info(content, false),
+    },
+    Rule {
+        name: "qxfer_chunk",
+        packet: PacketKind::Any,
+        content: |content| content.starts_with(b"m"),
+        build: |content, _| {
+            Ok(GdbResponse::QXferData {
+                data: content[1..].to_vec(),
+                is_final: false,
+            })
+        },
+    },
+    Rule {
+        name: "thread_info_end",
+        packet: PacketKind::Any,
+        content: |content| content == b"l",
+        build: |_, _| {
+            Ok(GdbResponse::ThreadInfo {
+                threads: vec![],
+                more_data: false,
+            })
+        },
+    },
+    Rule {
+        name: "qxfer_final",
+        packet: PacketKind::Any,
+        content: |content| content.starts_with(b"l"),
+        build: |content, _| {
+            Ok(GdbResponse::QXferData {
+                data: content[1..].to_vec(),
+                is_final: true,
+            })
+        },
+    },
+    // a malformed thread info reply, taken as the end of the thread list
+    Rule {
+        name: "two_hex_digits",
+        packet: PacketKind::Any,
+        content: |content| content.len() == 2 && GdbResponse::is_hex_data(content),
+        build: |_, _| {
+            Ok(GdbResponse::ThreadInfo {
+                threads: vec![],
+                more_data: false,
+            })
+        },
+    },
+    Rule {
+        name: "supported",
+        packet: PacketKind::Any,
+        content: looks_like_supported,
+        build: |content, _| {
+            GdbResponse::parse_supported_response(str::from_utf8(content).unwrap_or(""))
+        },
+    },
+    Rule {
+        name: "monitor_output",
+        packet: PacketKind::MonitorCommand,
+        content: |_| true,
+        build: |content, _| {
+            Ok(GdbResponse::MonitorOutput {
+                output: GdbResponse::parse_monitor_output(content),
+            })
+        },
+    },
+    Rule {
+        name: "register_data",
+        packet: PacketKind::RegisterRead,
+        content: GdbResponse::is_hex_data_or_run_length,
+        build: |content, _| {
+            Ok(GdbResponse::RegisterData {
+                data: GdbResponse::decode_hex_data(content)?,
+            })
+        },
+    },
+    Rule {
+        name: "memory_data",
+        packet: PacketKind::MemoryRead,
+        content: GdbResponse::is_hex_data_or_run_length,
+        build: |content, _| {
+            Ok(GdbResponse::MemoryData {
+                data: GdbResponse::decode_hex_data(content)?,
+            })
+        },
+    },
+    Rule {
+        name: "hex_data",
+        packet: PacketKind::Any,
+        content: GdbResponse::is_hex_data_or_run_length,
+        build: |content, _| GdbResponse::parse_unknown_hex(content),
+    },
+    Rule {
+        name: "raw",
+        packet: PacketKind::Any,
+        content: |_| true,
+        build: |content, _| {
+            Ok(GdbResponse::Raw {
+                data: content.to_vec(),
+            })
+        },
+    },
+];
+
+/// The first of [`RULES`] that takes `content` in reply to `packet`. The last takes anything
+fn matching_rule(content: &[u8], packet: &Packet) -> &'static Rule {
+    RULES
+        .iter()
+        .find(|rule| rule.packet.matches(packet) && (rule.content)(content))
+        .expect("the raw rule takes any reply")
+}
+
 impl fmt::Display for GdbResponse {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -576,17 +808,24 @@ impl fmt::Display for GdbResponse {
             GdbResponse::Nack => write!(f, "NACK"),
             GdbResponse::Ok => write!(f, "OK"),
             GdbResponse::Empty => write!(f, "Empty"),
+            GdbResponse::Unsupported => write!(f, "Unsupported"),
             GdbResponse::Error { code } => write!(f, "Error(0x{code:02x})"),
             GdbResponse::StopReply {
                 signal,
                 thread_id,
                 reason,
+                regs,
             } => {
                 write!(
                     f,
-                    "Stop(signal=0x{signal:02x}, thread={thread_id:?}, reason={reason:?})"
+                    "Stop(signal=0x{signal:02x}, thread={thread_id:?}, reason={reason:?}, {} regs)",
+                    regs.len()
                 )
             }
+            GdbResponse::Exited { code } => write!(f, "Exited(code=0x{code:02x})"),
+            GdbResponse::TerminatedWithSignal { signal } => {
+                write!(f, "Terminated(signal=0x{signal:02x})")
+            }
             GdbResponse::MemoryData { data } => {
                 write!(
                     f,
@@ -606,6 +845,7 @@ impl fmt::Display for GdbResponse {
             GdbResponse::ThreadInfo { threads, more_data } => {
                 write!(f, "Threads({} threads, more={})", threads.len(), more_data)
             }
+            GdbResponse::CurrentThread { thread } => write!(f, "CurrentThread({thread})"),
             GdbResponse::Supported { features } => {
                 write!(f, "Supported({} features)", features.len())
             }
@@ -640,6 +880,7 @@ impl fmt::Display for GdbResponse {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::commands::{Base, GdbCommand, Resume};
 
     pub fn test_parse(data: &[u8]) -> Result<GdbResponse, ParseError> {
         let rv = RawGdbResponse::find_packet_data(data)?;
@@ -653,6 +894,42 @@ mod tests {
         GdbResponse::parse_packet(rv, packet).unwrap()
     }
 
+    /// `payload` framed as a packet, with its checksum
+    fn frame(payload: &[u8]) -> Vec<u8> {
+        let checksum = payload.iter().fold(0u8, |acc, &b| acc.wrapping_add(b));
+        let mut packet = vec![b'$'];
+        packet.extend_from_slice(payload);
+        packet.extend_from_slice(format!("#{checksum:02x}").as_bytes());
+        packet
+    }
+
+    #[test]
+    fn test_entire_packet_len_is_wire_len() {
+        crate::init_test_logger();
+        // run lengths and an escaped '#' make the decoded payload differ in length from the wire
+        for payload in [&b"0* 1*!"[..], b"ab}\x03cd", b"00*%11"] {
+            let mut wire = frame(payload);
+            let first_len = wire.len();
+            wire.extend_from_slice(&frame(b"OK"));
+            wire.push(b'+');
+
+            let first = RawGdbResponse::find_packet_data(&wire).unwrap();
+            assert_eq!(first.as_slice(), payload);
+            assert_eq!(first.entire_packet_len(), first_len);
+
+            let rest = &wire[first.entire_packet_len()..];
+            let second = RawGdbResponse::find_packet_data(rest).unwrap();
+            assert_eq!(second.entire_packet_len(), 6);
+            assert_eq!(
+                GdbResponse::parse_packet(second, &Packet::default()).unwrap(),
+                GdbResponse::Ok
+            );
+
+            let ack = RawGdbResponse::find_packet_data(&rest[6..]).unwrap();
+            assert_eq!(ack.entire_packet_len(), 1);
+        }
+    }
+
     #[test]
     fn test_parse_ack() {
         crate::init_test_logger();
@@ -669,6 +946,61 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_empty_reply_by_command() {
+        crate::init_test_logger();
+        let base = |base| Packet::Command(GdbCommand::Base(base));
+        let reply = |packet: &Packet| {
+            GdbResponse::parse_reply(RawGdbResponse::find_packet_data(b"$#00").unwrap(), packet)
+                .unwrap()
+        };
+
+        // queries: the stub doesn't do them
+        for packet in [
+            base(Base::QSupported),
+            base(Base::QAttached),
+            base(Base::QfThreadInfo),
+            base(Base::QStartNoAckMode),
+            base(Base::QXferExecFile {
+                offset: 0,
+                length: 100,
+            }),
+            base(Base::QRcmd {
+                command: "time_idx".to_string(),
+            }),
+            base(Base::Z0 { addr: 0x100 }),
+            base(Base::Z0Remove { addr: 0x100 }),
+            base(Base::VKill),
+        ] {
+            assert_eq!(reply(&packet), GdbResponse::Unsupported, "{packet:?}");
+        }
+
+        // resumes and memory: nothing came back, which the caller makes sense of
+        for packet in [
+            Packet::Command(GdbCommand::Resume(Resume::Step)),
+            Packet::Command(GdbCommand::Resume(Resume::Continue)),
+            base(Base::LowerM { addr: 0, length: 0 }),
+            base(Base::LowerG),
+            base(Base::UpperM {
+                addr: 0,
+                data: vec![],
+            }),
+            Packet::Ack,
+        ] {
+            assert_eq!(reply(&packet), GdbResponse::Empty, "{packet:?}");
+        }
+
+        // a query that did answer is left alone
+        assert_eq!(
+            GdbResponse::parse_reply(
+                RawGdbResponse::find_packet_data(b"$OK#9a").unwrap(),
+                &base(Base::Z0 { addr: 0x100 })
+            )
+            .unwrap(),
+            GdbResponse::Ok
+        );
+    }
+
     #[test]
     fn test_parse_ok() {
         crate::init_test_logger();
@@ -784,12 +1116,7 @@ mod tests {
         let packet = b"$W00#b7";
         let response = test_parse(packet).expect("Failed to parse W00 packet");
 
-        if let GdbResponse::StopReply { signal, reason, .. } = response {
-            assert_eq!(signal, 0x00);
-            assert_eq!(reason, StopReason::ProcessExit { code: 0x00 });
-        } else {
-            panic!("Expected StopReply with ProcessExit, got: {:?}", response);
-        }
+        assert_eq!(response, GdbResponse::Exited { code: 0x00 });
     }
 
     #[test]
@@ -800,14 +1127,149 @@ mod tests {
         let packet = b"$X11#ba";
         let response = test_parse(packet).expect("Failed to parse X11 packet");
 
-        if let GdbResponse::StopReply { signal, reason, .. } = response {
-            assert_eq!(signal, 0x11);
-            assert_eq!(reason, StopReason::SignalTermination(0x11));
-        } else {
-            panic!("Expected StopReply with SignalTermination, got: {:?}", response);
+        assert_eq!(response, GdbResponse::TerminatedWithSignal { signal: 0x11 });
+    }
+
+    #[test]
+    fn test_parse_w_packet_with_process() {
+        crate::init_test_logger();
+        // multiprocess stubs say which process exited
+        assert_eq!(
+            test_parse(&frame(b"W02;process:3e1b")).unwrap(),
+            GdbResponse::Exited { code: 0x02 }
+        );
+        assert!(matches!(
+            test_parse(&frame(b"Wzz")),
+            Err(ParseError::InvalidHex)
+        ));
+    }
+
+    /// The stop reply in `payload`, framed and parsed
+    fn stop_reply(payload: &[u8]) -> (Option<ThreadId>, StopReason, Vec<(u8, Vec<u8>)>) {
+        match test_parse(&frame(payload)).unwrap() {
+            GdbResponse::StopReply {
+                thread_id,
+                reason,
+                regs,
+                ..
+            } => (thread_id, reason, regs),
+            other => panic!("Expected StopReply, got: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_t_packet_from_gdbserver() {
+        crate::init_test_logger();
+        let (thread_id, reason, regs) = stop_reply(
+            b"T0506:0000000000000000;07:e0e6ffffff7f0000;10:b6a6a0f7ff7f0000;\
+              thread:p3e1b.3e1b;core:3;",
+        );
+        assert_eq!(
+            thread_id,
+            Some(ThreadId::Process {
+                pid: 0x3e1b,
+                tid: 0x3e1b
+            })
+        );
+        assert_eq!(reason, StopReason::Signal(0x05));
+        assert_eq!(regs.len(), 3);
+        assert_eq!(regs[0], (0x06, vec![0; 8]));
+        assert_eq!(
+            regs[2],
+            (0x10, vec![0xb6, 0xa6, 0xa0, 0xf7, 0xff, 0x7f, 0x00, 0x00])
+        );
+    }
+
+    #[test]
+    fn test_parse_t_packet_from_dang() {
+        crate::init_test_logger();
+        assert_eq!(
+            stop_reply(b"T05thread:01;swbreak:;"),
+            (Some(ThreadId::Specific(1)), StopReason::Breakpoint, vec![])
+        );
+        // thread ids are hex
+        assert_eq!(
+            stop_reply(b"T05thread:1a;").0,
+            Some(ThreadId::Specific(0x1a))
+        );
+        assert_eq!(stop_reply(b"T05thread:-1;").0, Some(ThreadId::All));
+        // no thread id at all
+        assert_eq!(
+            stop_reply(b"T05hwbreak:;20:a4001000;"),
+            (
+                None,
+                StopReason::Breakpoint,
+                vec![(0x20, vec![0xa4, 0x00, 0x10, 0x00])]
+            )
+        );
+        // fields jpdb has no use for leave the signal as the reason
+        assert_eq!(
+            stop_reply(b"T05replaylog:begin;").1,
+            StopReason::Signal(0x05)
+        );
+    }
+
+    #[test]
+    fn test_thread_ids_round_trip() {
+        crate::init_test_logger();
+        for (text, thread) in [
+            ("0", ThreadId::Any),
+            ("-1", ThreadId::All),
+            ("1a", ThreadId::Specific(0x1a)),
+            (
+                "p3e1b.2",
+                ThreadId::Process {
+                    pid: 0x3e1b,
+                    tid: 2,
+                },
+            ),
+        ] {
+            assert_eq!(thread.to_string(), text);
+            assert_eq!(GdbResponse::parse_thread_id(text), Some(thread.clone()));
+            assert_eq!(
+                test_parse(&frame(format!("QC{text}").as_bytes())).unwrap(),
+                GdbResponse::CurrentThread { thread }
+            );
+        }
+        assert!(test_parse(&frame(b"QCzz")).is_err());
+
+        // thread lists are hex too, and can be multiprocess
+        assert_eq!(
+            test_parse(&frame(b"m01,1a,p2.3")).unwrap(),
+            GdbResponse::ThreadInfo {
+                threads: vec![
+                    ThreadId::Specific(1),
+                    ThreadId::Specific(0x1a),
+                    ThreadId::Process { pid: 2, tid: 3 },
+                ],
+                more_data: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_t_packet_watchpoints() {
+        crate::init_test_logger();
+        for kind in ["watch", "rwatch", "awatch"] {
+            let payload = format!("T05{kind}:20001000;thread:01;");
+            assert_eq!(
+                stop_reply(payload.as_bytes()).1,
+                StopReason::Watchpoint { addr: 0x20001000 },
+                "{kind}"
+            );
         }
     }
 
+    #[test]
+    fn test_parse_t_packet_skips_malformed_fields() {
+        crate::init_test_logger();
+        let (thread_id, reason, regs) =
+            stop_reply(b"T05nocolon;20:xyz;100:00;21:0102;thread:q;watch:zz;");
+        assert_eq!(thread_id, None);
+        assert_eq!(reason, StopReason::Signal(0x05));
+        assert_eq!(regs, vec![(0x21, vec![0x01, 0x02])]);
+    }
+
     #[test]
     fn test_parse_w_packet_nonzero_exit() {
         crate::init_test_logger();
@@ -816,12 +1278,7 @@ mod tests {
         let packet = b"$W01#b8";
         let response = test_parse(packet).expect("Failed to parse W01 packet");
 
-        if let GdbResponse::StopReply { signal, reason, .. } = response {
-            assert_eq!(signal, 0x01);
-            assert_eq!(reason, StopReason::ProcessExit { code: 0x01 });
-        } else {
-            panic!("Expected StopReply with ProcessExit, got: {:?}", response);
-        }
+        assert_eq!(response, GdbResponse::Exited { code: 0x01 });
     }
 
     #[test]
@@ -832,11 +1289,290 @@ mod tests {
         let packet = b"$X0b#ea";
         let response = test_parse(packet).expect("Failed to parse X0b packet");
 
-        if let GdbResponse::StopReply { signal, reason, .. } = response {
-            assert_eq!(signal, 0x0b);
-            assert_eq!(reason, StopReason::SignalTermination(0x0b));
-        } else {
-            panic!("Expected StopReply with SignalTermination, got: {:?}", response);
+        assert_eq!(response, GdbResponse::TerminatedWithSignal { signal: 0x0b });
+    }
+
+    fn base(base: Base) -> Packet {
+        Packet::Command(GdbCommand::Base(base))
+    }
+
+    /// A reply each rule takes, the packet it answers and what it parses to, in [`RULES`] order
+    fn rule_samples() -> Vec<(&'static str, &'static [u8], Packet, GdbResponse)> {
+        let qrcmd = || {
+            base(Base::QRcmd {
+                command: "time_idx".to_string(),
+            })
+        };
+        vec![
+            ("empty", b"", Packet::Ack, GdbResponse::Empty),
+            ("ack", b"+", Packet::Ack, GdbResponse::Ack),
+            ("nack", b"-", Packet::Ack, GdbResponse::Nack),
+            ("ok", b"OK", base(Base::Z0 { addr: 0x100 }), GdbResponse::Ok),
+            (
+                "error",
+                b"E0a",
+                base(Base::LowerG),
+                GdbResponse::Error { code: 0x0a },
+            ),
+            (
+                "exited",
+                b"W00",
+                base(Base::QuestionMark),
+                GdbResponse::Exited { code: 0x00 },
+            ),
+            (
+                "terminated_with_signal",
+                b"X0b",
+                base(Base::QuestionMark),
+                GdbResponse::TerminatedWithSignal { signal: 0x0b },
+            ),
+            (
+                "stop_reply",
+                b"T05thread:01;",
+                base(Base::QuestionMark),
+                GdbResponse::StopReply {
+                    signal: 0x05,
+                    thread_id: Some(ThreadId::Specific(1)),
+                    reason: StopReason::Signal(0x05),
+                    regs: vec![],
+                },
+            ),
+            (
+                "current_thread",
+                b"QC1",
+                base(Base::QC),
+                GdbResponse::CurrentThread {
+                    thread: ThreadId::Specific(1),
+                },
+            ),
+            (
+                "thread_info",
+                b"m1,2",
+                base(Base::QfThreadInfo),
+                GdbResponse::ThreadInfo {
+                    threads: vec![ThreadId::Specific(1), ThreadId::Specific(2)],
+                    more_data: false,
+                },
+            ),
+            (
+                "qxfer_chunk",
+                b"m/bin/app",
+                base(Base::QXferExecFile {
+                    offset: 0,
+                    length: 100,
+                }),
+                GdbResponse::QXferData {
+                    data: b"/bin/app".to_vec(),
+                    is_final: false,
+                },
+            ),
+            (
+                "thread_info_end",
+                b"l",
+                base(Base::QsThreadInfo),
+                GdbResponse::ThreadInfo {
+                    threads: vec![],
+                    more_data: false,
+                },
+            ),
+            (
+                "qxfer_final",
+                b"l/bin/app",
+                base(Base::QXferExecFile {
+                    offset: 0,
+                    length: 100,
+                }),
+                GdbResponse::QXferData {
+                    data: b"/bin/app".to_vec(),
+                    is_final: true,
+                },
+            ),
+            (
+                "two_hex_digits",
+                b"01",
+                base(Base::QfThreadInfo),
+                GdbResponse::ThreadInfo {
+                    threads: vec![],
+                    more_data: false,
+                },
+            ),
+            (
+                "supported",
+                b"PacketSize=1000;swbreak+",
+                base(Base::QSupported),
+                GdbResponse::Supported {
+                    features: vec!["PacketSize=1000".to_string(), "swbreak+".to_string()],
+                },
+            ),
+            (
+                "monitor_output",
+                b"O6869",
+                qrcmd(),
+                GdbResponse::MonitorOutput {
+                    output: "hi".to_string(),
+                },
+            ),
+            (
+                "register_data",
+                b"deadbeef",
+                base(Base::LowerG),
+                GdbResponse::RegisterData {
+                    data: vec![0xde, 0xad, 0xbe, 0xef],
+                },
+            ),
+            (
+                "memory_data",
+                b"0* ",
+                base(Base::LowerM {
+                    addr: 0x100,
+                    length: 2,
+                }),
+                GdbResponse::MemoryData {
+                    data: vec![0x00, 0x00],
+                },
+            ),
+            (
+                "hex_data",
+                b"cafe",
+                Packet::Ack,
+                GdbResponse::Raw {
+                    data: vec![0xca, 0xfe],
+                },
+            ),
+            (
+                "raw",
+                b"xyz",
+                Packet::Ack,
+                GdbResponse::Raw {
+                    data: b"xyz".to_vec(),
+                },
+            ),
+        ]
+    }
+
+    /// `content` framed and parsed as the reply to `packet`
+    fn parse_framed(content: &[u8], packet: &Packet) -> Result<GdbResponse, ParseError> {
+        let raw = RawGdbResponse::find_packet_data(&frame(content))?;
+        GdbResponse::parse_packet(raw, packet)
+    }
+
+    #[test]
+    fn test_rules() {
+        crate::init_test_logger();
+        let samples = rule_samples();
+        assert_eq!(
+            samples.iter().map(|(name, ..)| *name).collect::<Vec<_>>(),
+            RULES.iter().map(|rule| rule.name).collect::<Vec<_>>(),
+            "every rule needs a sample, in order"
+        );
+        for (name, content, packet, expected) in samples {
+            assert_eq!(matching_rule(content, &packet).name, name, "{content:?}");
+            assert_eq!(
+                parse_framed(content, &packet).unwrap(),
+                expected,
+                "rule {name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rules_depend_on_the_packet() {
+        crate::init_test_logger();
+        // the same hex, answering different packets
+        assert_eq!(matching_rule(b"deadbeef", &Packet::Ack).name, "hex_data");
+        assert_eq!(
+            matching_rule(b"deadbeef", &base(Base::LowerM { addr: 0, length: 4 })).name,
+            "memory_data"
+        );
+        let qrcmd = base(Base::QRcmd {
+            command: "time_idx".to_string(),
+        });
+        assert_eq!(matching_rule(b"deadbeef", &qrcmd).name, "monitor_output");
+        // a stop reply is one whatever it answers
+        assert_eq!(matching_rule(b"S05", &qrcmd).name, "stop_reply");
+
+        // a whole register file's worth of hex reads as registers without a `g` to go on
+        let registers = "00".repeat(132);
+        assert_eq!(
+            parse_framed(registers.as_bytes(), &Packet::Ack).unwrap(),
+            GdbResponse::RegisterData { data: vec![0; 132] }
+        );
+        assert!(matches!(
+            parse_framed(b"Ezz", &Packet::Ack),
+            Err(ParseError::InvalidHex)
+        ));
+    }
+
+    /// Name of `response`'s variant. No wildcard, so a new variant has to be added here, and to
+    /// [`VARIANTS`] or [`NOT_PARSED`]
+    fn variant(response: &GdbResponse) -> &'static str {
+        match response {
+            GdbResponse::Ack => "Ack",
+            GdbResponse::Nack => "Nack",
+            GdbResponse::Ok => "Ok",
+            GdbResponse::Empty => "Empty",
+            GdbResponse::Unsupported => "Unsupported",
+            GdbResponse::Error { .. } => "Error",
+            GdbResponse::StopReply { .. } => "StopReply",
+            GdbResponse::Exited { .. } => "Exited",
+            GdbResponse::TerminatedWithSignal { .. } => "TerminatedWithSignal",
+            GdbResponse::MemoryData { .. } => "MemoryData",
+            GdbResponse::RegisterData { .. } => "RegisterData",
+            GdbResponse::ThreadInfo { .. } => "ThreadInfo",
+            GdbResponse::CurrentThread { .. } => "CurrentThread",
+            GdbResponse::Supported { .. } => "Supported",
+            GdbResponse::QXferData { .. } => "QXferData",
+            GdbResponse::BinaryData { .. } => "BinaryData",
+            GdbResponse::MonitorOutput { .. } => "MonitorOutput",
+            GdbResponse::Raw { .. } => "Raw",
+        }
+    }
+
+    const VARIANTS: &[&str] = &[
+        "Ack",
+        "Nack",
+        "Ok",
+        "Empty",
+        "Unsupported",
+        "Error",
+        "StopReply",
+        "Exited",
+        "TerminatedWithSignal",
+        "MemoryData",
+        "RegisterData",
+        "ThreadInfo",
+        "CurrentThread",
+        "Supported",
+        "QXferData",
+        "MonitorOutput",
+        "Raw",
+    ];
+
+    /// Variants no rule makes, kept for code outside the crate that builds or matches them
+    const NOT_PARSED: &[&str] = &["BinaryData"];
+
+    #[test]
+    fn test_every_variant_has_a_rule() {
+        crate::init_test_logger();
+        let mut made: Vec<&str> = rule_samples()
+            .iter()
+            .map(|(_, _, _, expected)| variant(expected))
+            .collect();
+        // the empty rule's reply, as parse_reply reads it for a query
+        let unsupported = GdbResponse::parse_reply(
+            RawGdbResponse::find_packet_data(b"$#00").unwrap(),
+            &base(Base::QSupported),
+        )
+        .unwrap();
+        made.push(variant(&unsupported));
+        for name in VARIANTS {
+            assert!(made.contains(name), "no rule makes a {name}");
+        }
+        for name in NOT_PARSED {
+            assert!(
+                !made.contains(name),
+                "a rule makes a {name}, move it to VARIANTS"
+            );
         }
     }
 }
@@ -0,0 +1,385 @@
+//! The handshake [`Client::initialize_gdb_session`] runs, as a state machine that can be
+//! stepped one stage at a time, stopped halfway, or told to skip or relax a stage.
+//!
+//! ```no_run
+//! # use shucks::{Client, session::{Session, Stage}};
+//! # let mut client = Client::new_with_port(9001);
+//! let mut session = Session::default().skip(Stage::ThreadEnumeration);
+//! while session.advance(&mut client)? != Stage::Ready {}
+//! println!("{:?}", session.report());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::error::Error;
+use std::fmt;
+
+use crate::commands::{Base, GdbCommand};
+use crate::response::{GdbResponse, ThreadId};
+use crate::{Client, Packet};
+
+/// One step of the handshake, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    /// `QStartNoAckMode`
+    NoAckNegotiation,
+    /// `qSupported`, which has to advertise a `PacketSize`
+    FeatureExchange,
+    /// `qfThreadInfo` then `qsThreadInfo`, and `qC` for the selected thread
+    ThreadEnumeration,
+    /// `?`, which has to come back with a stop reply
+    InitialStop,
+    /// `g`, checked against a [`RegisterCheck`]
+    RegisterSanity,
+    /// Nothing left to run
+    Ready,
+}
+
+impl Stage {
+    /// The stage that runs after this one
+    pub fn after(self) -> Stage {
+        match self {
+            Stage::NoAckNegotiation => Stage::FeatureExchange,
+            Stage::FeatureExchange => Stage::ThreadEnumeration,
+            Stage::ThreadEnumeration => Stage::InitialStop,
+            Stage::InitialStop => Stage::RegisterSanity,
+            Stage::RegisterSanity | Stage::Ready => Stage::Ready,
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Stage::NoAckNegotiation => "no-ack negotiation",
+            Stage::FeatureExchange => "feature exchange",
+            Stage::ThreadEnumeration => "thread enumeration",
+            Stage::InitialStop => "initial stop",
+            Stage::RegisterSanity => "register sanity",
+            Stage::Ready => "ready",
+        };
+        f.write_str(name)
+    }
+}
+
+/// What the `g` reply has to look like: at least `min_len` bytes, in whole registers of `align`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterCheck {
+    pub min_len: usize,
+    pub align: usize,
+}
+
+impl RegisterCheck {
+    /// 32 GPRs and the PC, 4 bytes each
+    pub const RV32: RegisterCheck = RegisterCheck {
+        min_len: 33 * 4,
+        align: 4,
+    };
+    /// 32 GPRs and the PC, 8 bytes each
+    pub const RV64: RegisterCheck = RegisterCheck {
+        min_len: 33 * 8,
+        align: 8,
+    };
+
+    fn check(&self, len: usize) -> Result<(), String> {
+        if len < self.min_len || len % self.align != 0 {
+            return Err(format!(
+                "Unexpected register data length (got {len}, expected >= {} and multiple of {})",
+                self.min_len, self.align
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for RegisterCheck {
+    fn default() -> Self {
+        RegisterCheck::RV32
+    }
+}
+
+/// What each stage found, filled in as they run. Stages that haven't run, or were skipped, leave
+/// their fields empty
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InitReport {
+    /// Whether the stub acked `QStartNoAckMode` with a `+` ahead of its `OK`
+    pub no_ack_acked: Option<bool>,
+    /// Everything `qSupported` advertised
+    pub features: Vec<String>,
+    /// Threads from `qfThreadInfo` and `qsThreadInfo` together
+    pub threads: Option<Vec<ThreadId>>,
+    /// The thread `qC` named, or the first listed for a stub that doesn't answer it
+    pub current_thread: Option<ThreadId>,
+    /// Signal of the stop reply to `?`
+    pub stop_signal: Option<u8>,
+    /// The `W` or `X` reply to `?` when the program had already exited
+    pub exit: Option<GdbResponse>,
+    /// Length of the `g` reply, in bytes
+    pub register_bytes: Option<usize>,
+    /// Stages passed over without sending anything
+    pub skipped: Vec<Stage>,
+}
+
+/// The handshake in progress. [`Session::default`] runs every stage with the RV32 register check
+#[derive(Debug, Clone)]
+pub struct Session {
+    stage: Stage,
+    skip: Vec<Stage>,
+    register_check: RegisterCheck,
+    report: InitReport,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            stage: Stage::NoAckNegotiation,
+            skip: Vec::new(),
+            register_check: RegisterCheck::default(),
+            report: InitReport::default(),
+        }
+    }
+}
+
+impl Session {
+    /// Pass over `stage`, e.g. thread enumeration for a stub that doesn't do it. Skipping
+    /// [`Stage::Ready`] does nothing
+    pub fn skip(mut self, stage: Stage) -> Self {
+        self.skip.push(stage);
+        self
+    }
+
+    /// Check the `g` reply against `check` instead of [`RegisterCheck::RV32`]
+    pub fn register_check(mut self, check: RegisterCheck) -> Self {
+        self.register_check = check;
+        self
+    }
+
+    /// The stage the next [`Session::advance`] runs
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    pub fn report(&self) -> &InitReport {
+        &self.report
+    }
+
+    pub fn into_report(self) -> InitReport {
+        self.report
+    }
+
+    /// Run the current stage against `client` and move on, returning the stage now due. Once
+    /// [`Stage::Ready`] is reached this does nothing. A failed stage stays current
+    pub fn advance(&mut self, client: &mut Client) -> Result<Stage, Box<dyn Error>> {
+        let stage = self.stage;
+        if stage == Stage::Ready {
+            return Ok(stage);
+        }
+        if self.skip.contains(&stage) {
+            log::trace!("Skipping {stage}");
+            self.report.skipped.push(stage);
+        } else {
+            self.run_stage(stage, client)
+                .map_err(|e| format!("{stage}: {e}"))?;
+        }
+        self.stage = stage.after();
+        if self.stage == Stage::Ready {
+            log::info!("GDB initialization sequence complete!");
+        }
+        Ok(self.stage)
+    }
+
+    /// Advance until [`Stage::Ready`]
+    pub fn run(&mut self, client: &mut Client) -> Result<(), Box<dyn Error>> {
+        while self.advance(client)? != Stage::Ready {}
+        Ok(())
+    }
+
+    fn run_stage(&mut self, stage: Stage, client: &mut Client) -> Result<(), Box<dyn Error>> {
+        let send = |client: &mut Client, command: Base| {
+            client.send_command_parsed(Packet::Command(GdbCommand::Base(command)))
+        };
+        match stage {
+            // QStartNoAckMode must return OK per RSP
+            Stage::NoAckNegotiation => match send(client, Base::QStartNoAckMode)? {
+                GdbResponse::Ack => {
+                    log::trace!("QStartNoAckMode acknowledged with an ack");
+                    let resp = client.pop_response()?;
+                    if resp != GdbResponse::Ok {
+                        return Err(format!("Expected Ok for QStartNoAckMode, got: {resp}").into());
+                    }
+                    self.report.no_ack_acked = Some(true);
+                    client.set_no_ack(true);
+                }
+                GdbResponse::Ok => {
+                    log::trace!("QStartNoAckMode acknowledged with an ok");
+                    self.report.no_ack_acked = Some(false);
+                    client.set_no_ack(true);
+                }
+                other => {
+                    return Err(format!("Expected Ack for QStartNoAckMode, got: {other}").into());
+                }
+            },
+            // qSupported should return a feature list; require PacketSize (commonly provided)
+            Stage::FeatureExchange => match send(client, Base::QSupported)? {
+                GdbResponse::Supported { features } => {
+                    if !features.iter().any(|f| f.starts_with("PacketSize=")) {
+                        return Err(format!(
+                            "qSupported missing PacketSize in features: {features:?}"
+                        )
+                        .into());
+                    }
+                    log::trace!("qSupported features: {features:?}");
+                    client.set_stub_features(features.clone());
+                    self.report.features = features;
+                }
+                other => {
+                    return Err(format!("Expected qSupported feature list, got: {other}").into());
+                }
+            },
+            // qfThreadInfo must return thread list (may be empty) or 'm...' chunk, then
+            // qsThreadInfo should continue the list or return end
+            Stage::ThreadEnumeration => {
+                let mut all = Vec::new();
+                for (name, command) in [
+                    ("qfThreadInfo", Base::QfThreadInfo),
+                    ("qsThreadInfo", Base::QsThreadInfo),
+                ] {
+                    match send(client, command)? {
+                        GdbResponse::ThreadInfo { threads, .. } => {
+                            log::trace!("{name} threads: {threads:?}");
+                            all.extend(threads);
+                        }
+                        other => {
+                            return Err(
+                                format!("Expected thread info for {name}, got: {other}").into()
+                            );
+                        }
+                    }
+                }
+                // qC names the selected thread, without it the stub uses the first
+                let current = match send(client, Base::QC)? {
+                    GdbResponse::CurrentThread { thread } => Some(thread),
+                    GdbResponse::Unsupported => all.first().cloned(),
+                    other => {
+                        return Err(format!("Expected current thread for qC, got: {other}").into());
+                    }
+                };
+                log::trace!("qC current thread: {current:?}");
+                if let Some(thread) = &current {
+                    client.set_current_thread(thread.clone());
+                }
+                self.report.threads = Some(all);
+                self.report.current_thread = current;
+            }
+            // '?' must return a stop reply (Sxx or Txx), or Wxx or Xxx for a program that's gone
+            Stage::InitialStop => match send(client, Base::QuestionMark)? {
+                GdbResponse::StopReply { signal, .. } => {
+                    log::trace!("Got stop reply with signal 0x{signal:02x}");
+                    self.report.stop_signal = Some(signal);
+                }
+                exit @ (GdbResponse::Exited { .. } | GdbResponse::TerminatedWithSignal { .. }) => {
+                    client.note_exit(&exit);
+                    // there are no registers left to read
+                    self.skip.push(Stage::RegisterSanity);
+                    self.report.exit = Some(exit);
+                }
+                other => {
+                    return Err(format!("Expected stop reply for '?', got: {other}").into());
+                }
+            },
+            Stage::RegisterSanity => match send(client, Base::LowerG)? {
+                GdbResponse::RegisterData { data } => {
+                    self.register_check.check(data.len())?;
+                    log::trace!("Register read length OK: {} bytes", data.len());
+                    self.report.register_bytes = Some(data.len());
+                }
+                other => {
+                    return Err(
+                        format!("Expected RegisterData for 'g' (LowerG), got: {other}").into(),
+                    );
+                }
+            },
+            Stage::Ready => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::test_utils::{create_test_listener, start_dang_instance};
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    fn connected_client() -> (Client, std::thread::JoinHandle<()>) {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+        let client = Client::new_with_port(port);
+        sleep(Duration::from_millis(200));
+        (client, handle)
+    }
+
+    #[test]
+    fn test_advance_through_every_stage() {
+        let (mut client, handle) = connected_client();
+        let mut session = Session::default();
+
+        let mut seen = vec![session.stage()];
+        while *seen.last().unwrap() != Stage::Ready {
+            seen.push(session.advance(&mut client).unwrap());
+        }
+        assert_eq!(
+            seen,
+            [
+                Stage::NoAckNegotiation,
+                Stage::FeatureExchange,
+                Stage::ThreadEnumeration,
+                Stage::InitialStop,
+                Stage::RegisterSanity,
+                Stage::Ready,
+            ]
+        );
+        // nothing left to do
+        assert_eq!(session.advance(&mut client).unwrap(), Stage::Ready);
+
+        let report = session.into_report();
+        assert!(report.no_ack_acked.is_some());
+        assert_eq!(report.features, client.stub_features());
+        assert!(client.stub_packet_size().is_some());
+        assert_eq!(report.threads, Some(vec![ThreadId::Specific(1)]));
+        assert_eq!(report.current_thread, Some(ThreadId::Specific(1)));
+        assert_eq!(client.current_thread(), &ThreadId::Specific(1));
+        assert!(report.stop_signal.is_some());
+        assert!(report.register_bytes.unwrap() >= RegisterCheck::RV32.min_len);
+        assert!(report.skipped.is_empty());
+
+        // the handshake left the connection usable
+        assert!(client.get_time_idx().is_ok());
+        drop(handle);
+    }
+
+    #[test]
+    fn test_skipped_stage_still_reaches_ready() {
+        let (mut client, handle) = connected_client();
+        let mut session = Session::default().skip(Stage::ThreadEnumeration);
+        session.run(&mut client).unwrap();
+
+        assert_eq!(session.stage(), Stage::Ready);
+        let report = session.report();
+        assert_eq!(report.skipped, [Stage::ThreadEnumeration]);
+        assert_eq!(report.threads, None);
+        assert_eq!(report.current_thread, None);
+        assert!(report.stop_signal.is_some());
+        drop(handle);
+    }
+
+    #[test]
+    fn test_register_check() {
+        assert!(RegisterCheck::RV32.check(33 * 4).is_ok());
+        assert!(RegisterCheck::RV32.check(33 * 4 + 2).is_err());
+        assert!(RegisterCheck::RV64.check(33 * 4).is_err());
+        assert!(RegisterCheck::RV64.check(33 * 8).is_ok());
+    }
+}
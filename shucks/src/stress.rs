@@ -0,0 +1,712 @@
+//! Randomized protocol stress against any GDB stub, `shucks --port N stress`.
+//!
+//! A seeded run of register reads, memory reads inside a given range, steps, continues and
+//! monitor commands, with the odd read sent twice back to back, the way GDB retransmits after a
+//! timeout, and the odd packet sent with a wrong checksum, which the stub has to drop (or nak in
+//! ack mode) without losing its place. Every reply is checked against what was asked, and after
+//! every exchange a canary `?` has to come back as a stop reply. The same seed plans the same
+//! run, so a failure can be replayed with `--seed`.
+//!
+//! The last few exchanges are kept, every packet as it went over the wire, and come with the
+//! failure so it's clear what the stub was answering when it went wrong
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use crate::{
+    commands::{Base, GdbCommand, Resume},
+    golden::{console_output, render},
+    Client, Packet,
+};
+
+/// Exchanges kept for the failure dump unless the config says otherwise
+pub const DEFAULT_HISTORY: usize = 16;
+
+/// Longest memory read asked for, in bytes
+const MAX_READ: u32 = 64;
+
+/// splitmix64. Small, and the same everywhere, so a seed plans the same run on any machine
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`, `n` has to be above zero
+    fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    fn percent(&mut self, percent: u32) -> bool {
+        self.below(100) < u64::from(percent)
+    }
+}
+
+/// What one exchange asks the stub
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    ReadRegisters,
+    ReadMemory { addr: u32, length: u32 },
+    Step,
+    Continue,
+    Monitor(String),
+}
+
+impl Action {
+    fn packet(&self) -> Packet {
+        let base = |base| Packet::Command(GdbCommand::Base(base));
+        match self {
+            Action::ReadRegisters => base(Base::LowerG),
+            Action::ReadMemory { addr, length } => base(Base::LowerM {
+                addr: *addr,
+                length: *length,
+            }),
+            Action::Step => Packet::Command(GdbCommand::Resume(Resume::Step)),
+            Action::Continue => Packet::Command(GdbCommand::Resume(Resume::Continue)),
+            Action::Monitor(command) => base(Base::QRcmd {
+                command: command.clone(),
+            }),
+        }
+    }
+
+    fn is_read(&self) -> bool {
+        matches!(self, Action::ReadRegisters | Action::ReadMemory { .. })
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::ReadRegisters => write!(f, "read registers"),
+            Action::ReadMemory { addr, length } => write!(f, "read {length} bytes at 0x{addr:x}"),
+            Action::Step => write!(f, "step"),
+            Action::Continue => write!(f, "continue"),
+            Action::Monitor(command) => write!(f, "monitor {command}"),
+        }
+    }
+}
+
+/// How an action's packet goes over the wire
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Normal,
+    /// Sent twice back to back, both copies have to be answered the same. Only reads are
+    Retransmitted,
+    /// Sent with a wrong checksum, the stub mustn't act on it or answer it
+    BadChecksum,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedExchange {
+    pub action: Action,
+    pub delivery: Delivery,
+}
+
+impl fmt::Display for PlannedExchange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.delivery {
+            Delivery::Normal => write!(f, "{}", self.action),
+            Delivery::Retransmitted => write!(f, "{} (retransmitted)", self.action),
+            Delivery::BadChecksum => write!(f, "{} (bad checksum)", self.action),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StressConfig {
+    pub seed: u64,
+    pub iterations: usize,
+    /// Where memory reads land, `(start, size)`. No memory reads without one
+    pub mem_range: Option<(u32, u32)>,
+    /// Monitor commands to pick from, best kept to ones that don't change the target
+    pub monitor_cmds: Vec<String>,
+    /// Whether to continue as well as step. On a target that runs to its end, the first
+    /// continue is usually the last exchange
+    pub continues: bool,
+    /// How long a step or continue gets to stop
+    pub resume_timeout: Duration,
+    pub retransmit_percent: u32,
+    pub bad_checksum_percent: u32,
+    /// Exchanges kept for the failure dump
+    pub history: usize,
+}
+
+impl Default for StressConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            iterations: 200,
+            mem_range: None,
+            monitor_cmds: Vec::new(),
+            continues: true,
+            resume_timeout: Duration::from_secs(10),
+            retransmit_percent: 5,
+            bad_checksum_percent: 2,
+            history: DEFAULT_HISTORY,
+        }
+    }
+}
+
+/// The kinds of action a run picks between, before the details are filled in
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Registers,
+    Memory,
+    Step,
+    Continue,
+    Monitor,
+}
+
+/// The run `config` asks for. Only the seed and the config go into it, never anything the stub
+/// says, so the same seed against a different stub asks the same things
+pub fn plan(config: &StressConfig) -> Vec<PlannedExchange> {
+    // registers and memory most of the time, resumes and monitor commands now and then
+    let mut weighted = vec![(3, Kind::Registers), (2, Kind::Step)];
+    if config.mem_range.is_some() {
+        weighted.push((3, Kind::Memory));
+    }
+    if config.continues {
+        weighted.push((1, Kind::Continue));
+    }
+    if !config.monitor_cmds.is_empty() {
+        weighted.push((1, Kind::Monitor));
+    }
+    let total: u64 = weighted.iter().map(|(weight, _)| weight).sum();
+
+    let mut rng = Rng::new(config.seed);
+    (0..config.iterations)
+        .map(|_| {
+            let mut pick = rng.below(total);
+            let mut kind = Kind::Registers;
+            for &(weight, candidate) in &weighted {
+                if pick < weight {
+                    kind = candidate;
+                    break;
+                }
+                pick -= weight;
+            }
+            let action = match (kind, config.mem_range) {
+                (Kind::Memory, Some((start, size))) => {
+                    let offset = rng.below(u64::from(size)) as u32;
+                    let room = (size - offset).min(MAX_READ);
+                    Action::ReadMemory {
+                        addr: start + offset,
+                        length: 1 + rng.below(u64::from(room)) as u32,
+                    }
+                }
+                (Kind::Step, _) => Action::Step,
+                (Kind::Continue, _) => Action::Continue,
+                (Kind::Monitor, _) => {
+                    let which = rng.below(config.monitor_cmds.len() as u64) as usize;
+                    Action::Monitor(config.monitor_cmds[which].clone())
+                }
+                (Kind::Registers | Kind::Memory, _) => Action::ReadRegisters,
+            };
+            let delivery = if rng.percent(config.bad_checksum_percent) {
+                Delivery::BadChecksum
+            } else if action.is_read() && rng.percent(config.retransmit_percent) {
+                Delivery::Retransmitted
+            } else {
+                Delivery::Normal
+            };
+            PlannedExchange { action, delivery }
+        })
+        .collect()
+}
+
+/// Parse `--mem-range`, `<addr>:<size>` in hex
+pub fn parse_mem_range(value: &str) -> Result<(u32, u32), String> {
+    let parse_hex = |v: &str| u32::from_str_radix(v.trim_start_matches("0x"), 16);
+    let err = || format!("expected <addr>:<size> in hex, got '{value}'");
+    let (start, size) = value.split_once(':').ok_or_else(err)?;
+    let start = parse_hex(start).map_err(|_| err())?;
+    let size = parse_hex(size).map_err(|_| err())?;
+    if size == 0 || start.checked_add(size - 1).is_none() {
+        return Err(format!("memory range '{value}' is empty or wraps around"));
+    }
+    Ok((start, size))
+}
+
+/// One exchange as it went over the wire, the canary after it included
+#[derive(Debug, Clone)]
+pub struct Exchange {
+    pub iteration: usize,
+    pub label: String,
+    /// `->` and `<-` lines in the order they happened
+    pub lines: Vec<String>,
+}
+
+/// The last few exchanges
+#[derive(Debug)]
+pub struct Capture {
+    limit: usize,
+    exchanges: VecDeque<Exchange>,
+}
+
+impl Capture {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit: limit.max(1),
+            exchanges: VecDeque::new(),
+        }
+    }
+
+    fn start(&mut self, iteration: usize, label: String) {
+        if self.exchanges.len() == self.limit {
+            self.exchanges.pop_front();
+        }
+        self.exchanges.push_back(Exchange {
+            iteration,
+            label,
+            lines: Vec::new(),
+        });
+    }
+
+    fn line(&mut self, line: String) {
+        if let Some(exchange) = self.exchanges.back_mut() {
+            exchange.lines.push(line);
+        }
+    }
+
+    pub fn exchanges(&self) -> impl Iterator<Item = &Exchange> {
+        self.exchanges.iter()
+    }
+
+    /// The kept exchanges, oldest first, each under its iteration and what it was
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        for exchange in &self.exchanges {
+            out.push_str(&format!("#{} {}\n", exchange.iteration, exchange.label));
+            for line in &exchange.lines {
+                out.push_str(&format!("  {line}\n"));
+            }
+        }
+        out
+    }
+}
+
+/// What went wrong, as far as the client can tell from its side of the connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// Nothing, or nothing complete, came back in time
+    Timeout,
+    /// The connection went away under us
+    Disconnected,
+    /// An answer that doesn't fit what was asked
+    BadReply,
+    /// `?` stopped coming back as a stop reply
+    Canary,
+}
+
+impl FailureKind {
+    fn of_io(e: &io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => FailureKind::Timeout,
+            _ => FailureKind::Disconnected,
+        }
+    }
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            FailureKind::Timeout => "timeout",
+            FailureKind::Disconnected => "disconnected",
+            FailureKind::BadReply => "bad reply",
+            FailureKind::Canary => "canary failed",
+        };
+        f.write_str(kind)
+    }
+}
+
+#[derive(Debug)]
+pub struct StressFailure {
+    pub seed: u64,
+    pub iteration: usize,
+    pub kind: FailureKind,
+    pub message: String,
+    /// [`Capture::dump`] at the point of failure
+    pub history: String,
+}
+
+impl fmt::Display for StressFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "seed {} failed at iteration {}: {}: {}",
+            self.seed, self.iteration, self.kind, self.message
+        )
+    }
+}
+
+impl std::error::Error for StressFailure {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StressReport {
+    pub seed: u64,
+    /// Exchanges that went through, canary and all
+    pub exchanges: usize,
+    /// The stop reply that ended the run early, the target having exited
+    pub exited: Option<String>,
+}
+
+type Failed = (FailureKind, String);
+
+fn io_failed(e: io::Error) -> Failed {
+    (FailureKind::of_io(&e), e.to_string())
+}
+
+fn bad_reply(action: &Action, reply: &str) -> Failed {
+    (
+        FailureKind::BadReply,
+        format!("unexpected reply to {action}: {reply:?}"),
+    )
+}
+
+/// `$payload#cs` with the checksum one off
+fn corrupt_checksum(wire: &[u8]) -> Vec<u8> {
+    let mut wire = wire.to_vec();
+    let digits = wire.len().saturating_sub(2);
+    let checksum = std::str::from_utf8(&wire[digits..])
+        .ok()
+        .and_then(|cs| u8::from_str_radix(cs, 16).ok())
+        .unwrap_or(0);
+    wire.truncate(digits);
+    wire.extend_from_slice(format!("{:02x}", checksum.wrapping_add(1)).as_bytes());
+    wire
+}
+
+fn is_hex(payload: &str) -> bool {
+    !payload.is_empty() && payload.len() % 2 == 0 && payload.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// `Exx`, the RSP's error reply
+fn is_error(payload: &str) -> bool {
+    payload.len() == 3 && payload.starts_with('E') && is_hex(&payload[1..])
+}
+
+fn is_stop_reply(payload: &str) -> bool {
+    matches!(payload.as_bytes(), [b'S' | b'T' | b'W' | b'X', hi, lo, ..]
+        if hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit())
+}
+
+struct Runner<'a> {
+    client: &'a mut Client,
+    capture: Capture,
+    scratch: [u8; 4096],
+}
+
+impl Runner<'_> {
+    fn wire(&mut self, packet: &Packet) -> Result<Vec<u8>, Failed> {
+        let wire = packet
+            .to_finished_packet(&mut self.scratch)
+            .map_err(io_failed)?;
+        Ok(wire.0.to_vec())
+    }
+
+    fn send(&mut self, wire: &[u8]) -> Result<(), Failed> {
+        self.capture
+            .line(format!("-> {}", String::from_utf8_lossy(wire)));
+        self.client.send_raw(wire).map_err(io_failed)
+    }
+
+    /// The next packet from the stub, acks and naks included
+    fn receive(&mut self) -> Result<String, Failed> {
+        let raw = self.client.pop_raw_response().map_err(io_failed)?;
+        let payload = String::from_utf8_lossy(raw.as_slice()).into_owned();
+        let line = match payload.as_str() {
+            "+" | "-" => format!("<- {payload}"),
+            _ => render("<-", &payload),
+        };
+        self.capture.line(line);
+        Ok(payload)
+    }
+
+    /// The next packet that isn't an ack
+    fn reply(&mut self) -> Result<String, Failed> {
+        loop {
+            let payload = self.receive()?;
+            if payload != "+" {
+                return Ok(payload);
+            }
+        }
+    }
+
+    /// Read and check the answer to `action`. A stop reply saying the target exited comes back
+    /// as `Some`
+    fn answer(
+        &mut self,
+        action: &Action,
+        config: &StressConfig,
+    ) -> Result<(String, Option<String>), Failed> {
+        match action {
+            Action::ReadRegisters => {
+                let reply = self.reply()?;
+                // `xx` is a register the stub can't read
+                let registers = reply.len() % 2 == 0
+                    && !reply.is_empty()
+                    && reply.chars().all(|c| c.is_ascii_hexdigit() || c == 'x');
+                if !registers {
+                    return Err(bad_reply(action, &reply));
+                }
+                Ok((reply, None))
+            }
+            Action::ReadMemory { length, .. } => {
+                let reply = self.reply()?;
+                // a stub may stop short of what was asked, never past it
+                let fits = is_hex(&reply) && reply.len() <= 2 * *length as usize;
+                if !fits && !is_error(&reply) {
+                    return Err(bad_reply(action, &reply));
+                }
+                Ok((reply, None))
+            }
+            Action::Step | Action::Continue => {
+                let previous = self.client.reply_timeout();
+                self.client.set_reply_timeout(config.resume_timeout);
+                let reply = self.reply();
+                self.client.set_reply_timeout(previous);
+                let reply = reply?;
+                if !is_stop_reply(&reply) {
+                    return Err(bad_reply(action, &reply));
+                }
+                let exited = matches!(reply.as_bytes()[0], b'W' | b'X').then(|| reply.clone());
+                Ok((reply, exited))
+            }
+            Action::Monitor(_) => {
+                let mut output = String::new();
+                loop {
+                    let reply = self.reply()?;
+                    if let Some(text) = console_output(&reply) {
+                        output.push_str(&text);
+                        continue;
+                    }
+                    // empty is a stub without the command, which is an answer too
+                    if reply == "OK" || reply.is_empty() || is_error(&reply) {
+                        output.push_str(&reply);
+                        return Ok((output, None));
+                    }
+                    return Err(bad_reply(action, &reply));
+                }
+            }
+        }
+    }
+
+    /// `?` has to come back as a stop reply. After a bad checksum a stub in ack mode naks it
+    /// first, one in no-ack mode doesn't answer it at all, so that nak is the only thing allowed
+    /// ahead of the reply
+    fn canary(&mut self, after_bad_checksum: bool) -> Result<(), Failed> {
+        let wire = self.wire(&Packet::Command(GdbCommand::Base(Base::QuestionMark)))?;
+        self.send(&wire)?;
+        let canary = |(kind, message): Failed| {
+            let kind = match kind {
+                FailureKind::BadReply => FailureKind::Canary,
+                kind => kind,
+            };
+            (kind, format!("canary `?`: {message}"))
+        };
+        let mut reply = self.reply().map_err(canary)?;
+        if after_bad_checksum && reply == "-" {
+            reply = self.reply().map_err(canary)?;
+        }
+        if !is_stop_reply(&reply) {
+            return Err((
+                FailureKind::Canary,
+                format!("canary `?` answered {reply:?}, not a stop reply"),
+            ));
+        }
+        Ok(())
+    }
+
+    /// One planned exchange and the canary after it. `Some` if the target exited, which ends
+    /// the run without a canary, there being nothing left to ask about
+    fn exchange(
+        &mut self,
+        planned: &PlannedExchange,
+        config: &StressConfig,
+    ) -> Result<Option<String>, Failed> {
+        let wire = self.wire(&planned.action.packet())?;
+        match planned.delivery {
+            Delivery::Normal => {
+                self.send(&wire)?;
+                if let (_, Some(exited)) = self.answer(&planned.action, config)? {
+                    return Ok(Some(exited));
+                }
+                self.canary(false)?;
+            }
+            Delivery::Retransmitted => {
+                self.send(&[wire.as_slice(), wire.as_slice()].concat())?;
+                let (first, _) = self.answer(&planned.action, config)?;
+                let (second, _) = self.answer(&planned.action, config)?;
+                if first != second {
+                    return Err((
+                        FailureKind::BadReply,
+                        format!(
+                            "retransmitted {} answered differently: {first:?} then {second:?}",
+                            planned.action
+                        ),
+                    ));
+                }
+                self.canary(false)?;
+            }
+            Delivery::BadChecksum => {
+                self.send(&corrupt_checksum(&wire))?;
+                self.canary(true)?;
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Run [`plan`] for `config` against `client`, which should already have been through
+/// [`Client::initialize_gdb_session`]. Stops at the first failure, or early if the target exits
+pub fn run_stress(
+    client: &mut Client,
+    config: &StressConfig,
+) -> Result<StressReport, StressFailure> {
+    let mut runner = Runner {
+        client,
+        capture: Capture::new(config.history),
+        scratch: [0; 4096],
+    };
+    let mut report = StressReport {
+        seed: config.seed,
+        exchanges: 0,
+        exited: None,
+    };
+    for (iteration, planned) in plan(config).iter().enumerate() {
+        runner.capture.start(iteration, planned.to_string());
+        match runner.exchange(planned, config) {
+            Ok(exited) => {
+                report.exchanges += 1;
+                if exited.is_some() {
+                    report.exited = exited;
+                    break;
+                }
+            }
+            Err((kind, message)) => {
+                return Err(StressFailure {
+                    seed: config.seed,
+                    iteration,
+                    kind,
+                    message,
+                    history: runner.capture.dump(),
+                });
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::test_utils::*;
+    use std::thread::sleep;
+
+    fn config(seed: u64) -> StressConfig {
+        StressConfig {
+            seed,
+            mem_range: Some((0x100000, 0x1000)),
+            monitor_cmds: vec!["time_idx".to_string()],
+            ..StressConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_plan_is_reproducible() {
+        assert_eq!(plan(&config(7)), plan(&config(7)));
+        assert_ne!(plan(&config(7)), plan(&config(8)));
+
+        let planned = plan(&config(7));
+        assert_eq!(planned.len(), 200);
+        for exchange in &planned {
+            if let Action::ReadMemory { addr, length } = exchange.action {
+                assert!((1..=MAX_READ).contains(&length));
+                assert!(addr >= 0x100000 && addr + length <= 0x101000);
+            }
+            if exchange.delivery == Delivery::Retransmitted {
+                assert!(exchange.action.is_read());
+            }
+        }
+        // with 200 of them, every kind turns up
+        for kind in [Action::Step, Action::Continue, Action::ReadRegisters] {
+            assert!(planned.iter().any(|exchange| exchange.action == kind));
+        }
+        assert!(planned
+            .iter()
+            .any(|exchange| matches!(exchange.action, Action::Monitor(_))));
+
+        // nothing asked for that wasn't configured
+        let bare = StressConfig {
+            continues: false,
+            ..StressConfig::default()
+        };
+        assert!(plan(&bare)
+            .iter()
+            .all(|exchange| matches!(exchange.action, Action::ReadRegisters | Action::Step)));
+    }
+
+    #[test]
+    fn test_corrupt_checksum() {
+        assert_eq!(corrupt_checksum(b"$g#67"), b"$g#68");
+        assert_eq!(corrupt_checksum(b"$?#ff"), b"$?#00");
+    }
+
+    #[test]
+    fn test_parse_mem_range() {
+        assert_eq!(parse_mem_range("0x100000:1000"), Ok((0x100000, 0x1000)));
+        assert!(parse_mem_range("100000").is_err());
+        assert!(parse_mem_range("100000:0").is_err());
+        assert!(parse_mem_range("ffffffff:2").is_err());
+    }
+
+    #[test]
+    fn test_capture_keeps_the_last_few() {
+        let mut capture = Capture::new(2);
+        for iteration in 0..3 {
+            capture.start(iteration, "step".to_string());
+            capture.line("-> $s#73".to_string());
+        }
+        let kept: Vec<usize> = capture.exchanges().map(|e| e.iteration).collect();
+        assert_eq!(kept, [1, 2]);
+        assert_eq!(capture.dump(), "#1 step\n  -> $s#73\n#2 step\n  -> $s#73\n");
+    }
+
+    /// A short run against dang. Bad checksums are left out: gdbstub drops the connection on
+    /// one rather than ignoring the packet, which the CLI run does turn up
+    #[test]
+    fn test_stress_dang_smoke() {
+        crate::init_test_logger();
+        let (listener, port) = create_test_listener();
+        let _server_handle = start_dang_instance(listener);
+        sleep(Duration::from_millis(1000));
+
+        let mut client = Client::new_with_port(port);
+        client.initialize_gdb_session().unwrap();
+        let config = StressConfig {
+            iterations: 40,
+            continues: false,
+            bad_checksum_percent: 0,
+            retransmit_percent: 20,
+            ..config(0x5eed)
+        };
+        let report = run_stress(&mut client, &config).unwrap_or_else(|failure| {
+            panic!("{failure}\n{}", failure.history);
+        });
+        assert_eq!(report.seed, 0x5eed);
+        assert_eq!(report.exchanges, 40);
+        assert_eq!(report.exited, None);
+    }
+}
@@ -0,0 +1,147 @@
+//! Bounded cache of formatted symbol names, shared by everything that labels an address
+
+use std::collections::{HashMap, VecDeque};
+
+/// How many addresses [`SymbolCache`] remembers by default. The working set is the PC, the next
+/// few instructions and the breakpoints, so this is plenty
+pub const SYMBOL_CACHE_CAPACITY: usize = 256;
+
+/// Hit/miss counters and current size of a [`SymbolCache`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub size: usize,
+}
+
+impl std::fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} hits, {} misses, {} entries",
+            self.hits, self.misses, self.size
+        )
+    }
+}
+
+/// Least recently used cache from address to its formatted symbol
+#[derive(Debug)]
+pub struct SymbolCache {
+    entries: HashMap<u32, String>,
+    /// Least recently used first
+    order: VecDeque<u32>,
+    capacity: usize,
+    hits: u64,
+    misses: u64,
+}
+
+impl Default for SymbolCache {
+    fn default() -> Self {
+        Self::with_capacity(SYMBOL_CACHE_CAPACITY)
+    }
+}
+
+impl SymbolCache {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            capacity: capacity.max(1),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// The cached string for `addr`, or `compute`'s result, which is then cached
+    pub fn get_or_insert_with(&mut self, addr: u32, compute: impl FnOnce() -> String) -> String {
+        if let Some(hit) = self.entries.get(&addr) {
+            self.hits += 1;
+            let hit = hit.clone();
+            self.touch(addr);
+            return hit;
+        }
+
+        self.misses += 1;
+        let value = compute();
+        if self.entries.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(addr, value.clone());
+        self.order.push_back(addr);
+        value
+    }
+
+    fn touch(&mut self, addr: u32) {
+        if let Some(pos) = self.order.iter().position(|&a| a == addr) {
+            self.order.remove(pos);
+        }
+        self.order.push_back(addr);
+    }
+
+    /// Forget everything, the symbols belong to an ELF that's no longer loaded
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            size: self.entries.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hits_skip_compute() {
+        let mut cache = SymbolCache::default();
+        assert_eq!(cache.get_or_insert_with(0x10, || "a".to_string()), "a");
+        assert_eq!(
+            cache.get_or_insert_with(0x10, || panic!("should be cached")),
+            "a"
+        );
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 1,
+                size: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_bounded_evicts_least_recent() {
+        let mut cache = SymbolCache::with_capacity(2);
+        cache.get_or_insert_with(1, || "one".to_string());
+        cache.get_or_insert_with(2, || "two".to_string());
+        // using 1 again makes 2 the oldest
+        cache.get_or_insert_with(1, || unreachable!());
+        cache.get_or_insert_with(3, || "three".to_string());
+        assert_eq!(cache.stats().size, 2);
+
+        let mut recomputed = false;
+        cache.get_or_insert_with(2, || {
+            recomputed = true;
+            "two".to_string()
+        });
+        assert!(recomputed);
+        cache.get_or_insert_with(3, || unreachable!());
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut cache = SymbolCache::default();
+        cache.get_or_insert_with(1, || "one".to_string());
+        cache.clear();
+        assert_eq!(cache.stats().size, 0);
+        assert_eq!(cache.get_or_insert_with(1, || "new".to_string()), "new");
+    }
+}
@@ -1,31 +1,300 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use nucleo_matcher::{
     pattern::{AtomKind, CaseMatching, Normalization, Pattern},
     Config, Matcher, Utf32Str,
 };
 use wellen::{
-    simple::{read as waveread, Waveform},
-    Time, TimeTableIdx, Var, WellenError,
+    simple::{read_with_options as waveread, Waveform},
+    SignalRef, Time, TimeTableIdx, Var,
 };
 
-use dang::waveloader::WellenSignalExt;
+use dang::wave_watch::WaveFingerprint;
+use dang::waveloader::{self, WaveLoadOptions, WellenSignalExt};
 
+/// Indices of the `names` fuzzy matching `query`, best match first and ties in name order.
+/// Shared with [`RemoteVarIndex`](crate::remote_vars::RemoteVarIndex) so both rank alike
+pub(crate) fn rank_matches<'a>(
+    matcher: &mut Matcher,
+    query: &str,
+    names: impl Iterator<Item = &'a str>,
+) -> Vec<usize> {
+    // Use the pattern API for proper normalization and matching
+    let pattern = Pattern::new(
+        query,
+        CaseMatching::Ignore,
+        Normalization::Smart,
+        AtomKind::Fuzzy,
+    );
+
+    let mut name_buf = Vec::new();
+    let mut scored: Vec<(u32, usize, &str)> = names
+        .enumerate()
+        .filter_map(|(idx, name)| {
+            let haystack = Utf32Str::new(name, &mut name_buf);
+            pattern
+                .score(haystack, matcher)
+                .map(|score| (score, idx, name))
+        })
+        .collect();
+
+    scored.sort_by(|(score_a, _, name_a), (score_b, _, name_b)| {
+        score_b.cmp(score_a).then_with(|| name_a.cmp(name_b))
+    });
+    scored.into_iter().map(|(_, idx, _)| idx).collect()
+}
+
+/// Why a selected signal has no value to show
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueError {
+    /// The waveform has no data for the signal
+    NotLoaded,
+    /// The cursor is before the signal's first change
+    Undriven,
+    /// The value isn't a bit vector, e.g. a real or string signal
+    Conversion(String),
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueError::NotLoaded => write!(f, "<not loaded>"),
+            ValueError::Undriven => write!(f, "<undriven>"),
+            ValueError::Conversion(reason) => write!(f, "<unconvertible: {reason}>"),
+        }
+    }
+}
+
+/// A selected signal's value, in its radix or by name
+pub type SignalValueResult = Result<String, ValueError>;
+
+/// How often a selected signal changes, see [`WaveformTracker::signal_stats`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalStats {
+    /// Changes over the whole trace. The value a signal starts with counts as its first
+    pub total: usize,
+    /// Changes in the window asked for, `total` without one
+    pub in_window: usize,
+    /// Time indices of the first and last change, `None` for a signal that never has a value
+    pub first: Option<TimeTableIdx>,
+    pub last: Option<TimeTableIdx>,
+}
+
+impl SignalStats {
+    /// Stuck at whatever it started at, or never driven at all
+    pub fn is_constant(&self) -> bool {
+        self.total <= 1
+    }
+}
+
+/// How many of the sorted `indices` are in `lo..=hi`
+fn count_between(indices: &[TimeTableIdx], lo: TimeTableIdx, hi: TimeTableIdx) -> usize {
+    if lo > hi {
+        return 0;
+    }
+    let before = indices.partition_point(|&idx| idx < lo);
+    let through = indices.partition_point(|&idx| idx <= hi);
+    through - before
+}
+
+/// Whether any of the sorted `indices` is after `from` and at or before `to`
+fn changed_between(indices: &[TimeTableIdx], from: TimeTableIdx, to: TimeTableIdx) -> bool {
+    let after = indices.partition_point(|&idx| idx <= from);
+    indices.get(after).is_some_and(|&idx| idx <= to)
+}
+
+/// A selected signal, with everything the hierarchy knows about it resolved when it was selected
 pub struct TrackerVar {
-    var: Var,
+    signal: SignalRef,
+    name: String,
+    /// Declared width in bits, `None` for reals and strings
+    width: Option<u32>,
+    /// Names for its values, see [`WaveformTracker::set_value_names`]
+    value_names: Option<(HashMap<u64, String>, NameFormat)>,
+    /// The radix values without a name are shown in
+    format: FormattingType,
+}
+
+/// How a value with a name is shown
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NameFormat {
+    /// `FLUSH (0x3)`
+    #[default]
+    NameAndValue,
+    /// `FLUSH`
+    Name,
+}
+
+/// The radix a selected signal's values are shown in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FormattingType {
+    #[default]
+    Hex,
+    Decimal,
+    Binary,
+    /// Two's complement over the signal's width
+    SignedDecimal,
+}
+
+impl FormattingType {
+    /// How `format` spells it: `hex`, `dec`, `bin` or `sdec`
+    pub fn name(self) -> &'static str {
+        match self {
+            FormattingType::Hex => "hex",
+            FormattingType::Decimal => "dec",
+            FormattingType::Binary => "bin",
+            FormattingType::SignedDecimal => "sdec",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            FormattingType::Hex,
+            FormattingType::Decimal,
+            FormattingType::Binary,
+            FormattingType::SignedDecimal,
+        ]
+        .into_iter()
+        .find(|format| format.name() == name)
+    }
+}
+
+impl fmt::Display for FormattingType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// `hex` with its name from `names`. A value without one keeps its hex and gets a `?`, one
+/// with x or z bits, or too wide to be a key, is left as it is
+fn named_value(hex: String, names: &HashMap<u64, String>, format: NameFormat) -> String {
+    let Ok(value) = u64::from_str_radix(&hex.replace('_', ""), 16) else {
+        return hex;
+    };
+    match (names.get(&value), format) {
+        (Some(name), NameFormat::NameAndValue) => format!("{name} (0x{value:x})"),
+        (Some(name), NameFormat::Name) => name.clone(),
+        (None, _) => format!("{hex} ?"),
+    }
+}
+
+/// The keys of `names` a signal `width` bits wide can never hold, in order
+pub fn out_of_range_keys(names: &HashMap<u64, String>, width: Option<u32>) -> Vec<u64> {
+    let Some(width) = width.filter(|&width| width < 64) else {
+        return Vec::new();
+    };
+    let mut keys: Vec<u64> = names
+        .keys()
+        .copied()
+        .filter(|key| key >> width != 0)
+        .collect();
+    keys.sort_unstable();
+    keys
+}
+
+/// Change indices either side of the cursor [`WaveformTracker::prefetch`] warms up unless told
+/// otherwise
+pub const DEFAULT_PREFETCH_SPAN: usize = 2;
+
+/// The `span` change indices closest before `idx` and the `span` closest after it, among the
+/// changes of every one of `signals`, in order. `idx` itself isn't one of them
+fn neighbor_indices<'a>(
+    signals: impl Iterator<Item = &'a [TimeTableIdx]>,
+    idx: TimeTableIdx,
+    span: usize,
+) -> Vec<TimeTableIdx> {
+    let (mut before, mut after) = (Vec::new(), Vec::new());
+    for indices in signals {
+        let at = indices.partition_point(|&i| i < idx);
+        before.extend_from_slice(&indices[at.saturating_sub(span)..at]);
+        let past = indices.partition_point(|&i| i <= idx);
+        after.extend(indices[past..].iter().take(span));
+    }
+    before.sort_unstable();
+    before.dedup();
+    after.sort_unstable();
+    after.dedup();
+    let mut neighbors = before.split_off(before.len().saturating_sub(span));
+    neighbors.extend(after.into_iter().take(span));
+    neighbors
+}
+
+/// How much work the tracker has done, so refreshes can be checked to stay cheap
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TrackerCounts {
+    /// Names resolved through the hierarchy
+    pub hierarchy_lookups: u64,
+    /// Passes formatting the value of every selected signal
+    pub value_passes: u64,
+    /// Calls into wellen to load signal data, each one a pass over the waveform body
+    pub load_calls: u64,
+    /// Warm-ups run around the cursor, see [`WaveformTracker::prefetch`]
+    pub prefetch_runs: u64,
+    /// Value passes done ahead of time by those warm-ups
+    pub prefetched_passes: u64,
+    /// Values asked for that a warm-up already had
+    pub prefetch_hits: u64,
+    /// Values asked for that had to be worked out then and there
+    pub prefetch_misses: u64,
+    /// Time spent working values out when asked, warm-ups not included
+    pub fetch_time: Duration,
+}
+
+impl fmt::Display for TrackerCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} value passes ({} warmed up ahead), {} prefetch hits, {} misses, {:?} fetching",
+            self.value_passes,
+            self.prefetched_passes,
+            self.prefetch_hits,
+            self.prefetch_misses,
+            self.fetch_time
+        )
+    }
 }
 
 pub struct WaveformTracker {
     waveform: Waveform,
+    path: PathBuf,
+    load_options: WaveLoadOptions,
+    /// The file as it was when it was read, `None` if it couldn't be stat'd
+    wave_file: Option<WaveFingerprint>,
     selected_var_order: Vec<TrackerVar>,
     // Cached data for efficient fuzzy matching
     cached_vars: Vec<(Var, String)>,
     matcher: Matcher,
+    /// The last value pass and the time index it was for
+    value_cache: Option<(TimeTableIdx, Vec<SignalValueResult>)>,
+    counts: TrackerCounts,
+    /// Change indices either side of the cursor to warm up, `None` for no prefetching
+    prefetch_span: Option<usize>,
+    /// The index the last value pass was for, until a warm-up has been run around it
+    prefetch_pending: Option<TimeTableIdx>,
+    /// Values worked out ahead of time around the cursor, by time index
+    prefetched: HashMap<TimeTableIdx, Vec<SignalValueResult>>,
+    /// Who's holding each loaded signal: one for being selected, one per preload. Data is
+    /// dropped when the count gets back to zero
+    load_refs: HashMap<SignalRef, usize>,
 }
 
 impl WaveformTracker {
-    pub fn new(waveform_path: PathBuf) -> Result<Self, WellenError> {
-        let waveform = waveread(waveform_path)?;
+    pub fn new(waveform_path: PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::new_with_options(waveform_path, &WaveLoadOptions::default())
+    }
+
+    /// [`WaveformTracker::new`], loading the waveform the way dang's `--wave-load-threads` and
+    /// `--wave-keep-scopes-with-empty-name` say
+    pub fn new_with_options(
+        waveform_path: PathBuf,
+        options: &WaveLoadOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let started = Instant::now();
+        let wave_file = WaveFingerprint::of(&waveform_path);
+        let waveform = options.run(|| waveread(&waveform_path, &options.wellen()))??;
 
         // Pre-compute all variable names for efficient fuzzy matching
         let h = waveform.hierarchy();
@@ -36,87 +305,443 @@ impl WaveformTracker {
             cached_vars.push((var.clone(), name));
         }
 
+        log::info!(
+            "{}",
+            waveloader::load_summary(
+                &waveform_path,
+                started.elapsed(),
+                cached_vars.len(),
+                waveform.time_table().len(),
+                options
+            )
+        );
+
         // Create reusable matcher instance
         let matcher = Matcher::new(Config::DEFAULT);
 
         Ok(Self {
             waveform,
+            path: waveform_path,
+            load_options: *options,
+            wave_file,
             selected_var_order: Vec::new(),
             cached_vars,
             matcher,
+            value_cache: None,
+            counts: TrackerCounts::default(),
+            prefetch_span: Some(DEFAULT_PREFETCH_SPAN),
+            prefetch_pending: None,
+            prefetched: HashMap::new(),
+            load_refs: HashMap::new(),
         })
     }
 
-    pub fn fuzzy_match_var(&mut self, query: &str) -> Vec<(Var, String)> {
-        // Use the pattern API for proper normalization and matching
-        let pattern = Pattern::new(
-            query,
-            CaseMatching::Ignore,
-            Normalization::Smart,
-            AtomKind::Fuzzy,
-        );
+    /// The waveform file this was read from
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
 
-        let mut scored: Vec<(u32, usize)> = self
-            .cached_vars
-            .iter()
-            .enumerate()
-            .filter_map(|(idx, (_, name))| {
-                let mut name_buf = Vec::new();
-                let haystack = Utf32Str::new(name, &mut name_buf);
-                pattern
-                    .score(haystack, &mut self.matcher)
-                    .map(|score| (score, idx))
-            })
-            .collect();
+    /// Whether the waveform file has been rewritten since it was read, see
+    /// [`WaveFingerprint::is_stale`]
+    pub fn is_stale(&self) -> bool {
+        self.wave_file
+            .as_ref()
+            .is_some_and(|wave_file| wave_file.is_stale())
+    }
 
-        scored.sort_by(|(sa, idx_a), (sb, idx_b)| {
-            sb.cmp(sa).then_with(|| {
-                let name_a = &self.cached_vars[*idx_a].1;
-                let name_b = &self.cached_vars[*idx_b].1;
-                name_a.cmp(name_b)
-            })
-        });
+    /// Read the waveform file again, the way it was read the first time, and select the same
+    /// signals by name, keeping their value names and radix. Returns the names of the selected signals
+    /// the new waveform doesn't have, which are dropped. On an error nothing changes
+    pub fn reload(&mut self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut reloaded = Self::new_with_options(self.path.clone(), &self.load_options)?;
+        reloaded.prefetch_span = self.prefetch_span;
 
-        scored
+        let mut missing = Vec::new();
+        let mut kept = Vec::new();
+        for old in &self.selected_var_order {
+            match reloaded
+                .cached_vars
+                .iter()
+                .find(|(_, name)| *name == old.name)
+            {
+                Some((var, _)) => kept.push((var.clone(), old.value_names.clone(), old.format)),
+                None => missing.push(old.name.clone()),
+            }
+        }
+        reloaded.select_signals(kept.iter().map(|(var, ..)| var.clone()).collect());
+        for (new, (_, value_names, format)) in reloaded.selected_var_order.iter_mut().zip(kept) {
+            new.value_names = value_names;
+            new.format = format;
+        }
+        *self = reloaded;
+        Ok(missing)
+    }
+
+    /// The var with the full name `name`, exactly
+    pub fn find_var(&self, name: &str) -> Option<Var> {
+        self.cached_vars
+            .iter()
+            .find(|(_, var_name)| var_name == name)
+            .map(|(var, _)| var.clone())
+    }
+
+    pub fn fuzzy_match_var(&mut self, query: &str) -> Vec<(Var, String)> {
+        let names = self.cached_vars.iter().map(|(_, name)| name.as_str());
+        rank_matches(&mut self.matcher, query, names)
             .into_iter()
-            .map(|(_, idx)| self.cached_vars[idx].clone())
+            .map(|idx| self.cached_vars[idx].clone())
             .collect()
     }
 
     pub fn select_signal(&mut self, var: Var) {
-        self.waveform.load_signals(&[var.signal_ref()]);
-        self.selected_var_order.push(TrackerVar { var });
+        self.select_signals(vec![var]);
     }
 
-    pub fn get_current_time(&self, timetableidx: TimeTableIdx) -> Time {
+    /// Select every var in `vars` that isn't already selected, loading all of their data in one
+    /// pass. Vars are told apart by name, aliases of one signal can each be selected. Returns
+    /// how many were newly selected
+    pub fn select_signals(&mut self, vars: Vec<Var>) -> usize {
+        let mut new_vars: Vec<TrackerVar> = Vec::new();
+        for var in vars {
+            self.counts.hierarchy_lookups += 1;
+            let name = var.full_name(self.waveform.hierarchy());
+            let known = |v: &TrackerVar| v.name == name;
+            if !self.selected_var_order.iter().any(known) && !new_vars.iter().any(known) {
+                new_vars.push(TrackerVar {
+                    signal: var.signal_ref(),
+                    name,
+                    width: var.length(),
+                    value_names: None,
+                    format: FormattingType::Hex,
+                });
+            }
+        }
+        let signals: Vec<SignalRef> = new_vars.iter().map(|v| v.signal).collect();
+        self.acquire(&signals);
+
+        if signals
+            .iter()
+            .any(|&signal| self.waveform.get_signal(signal).is_none())
+        {
+            log::warn!("No data loaded for a selected signal, it will show as not loaded");
+        }
+        let added = new_vars.len();
+        if added > 0 {
+            self.selected_var_order.extend(new_vars);
+            self.invalidate_values();
+        }
+        added
+    }
+
+    /// Stop showing the selected signal with the full name `name`, letting go of its data.
+    /// False if no selected signal has that name
+    pub fn deselect_signal(&mut self, name: &str) -> bool {
+        let Some(pos) = self.selected_var_order.iter().position(|v| v.name == name) else {
+            return false;
+        };
+        let var = self.selected_var_order.remove(pos);
+        self.release(&[var.signal]);
+        self.invalidate_values();
+        true
+    }
+
+    /// Deselect every signal. Returns how many there were
+    pub fn clear_signals(&mut self) -> usize {
+        let signals: Vec<SignalRef> = self
+            .selected_var_order
+            .drain(..)
+            .map(|var| var.signal)
+            .collect();
+        self.release(&signals);
+        self.invalidate_values();
+        signals.len()
+    }
+
+    /// Load the data for `vars` without selecting them, e.g. to preview values. Each preload
+    /// has to be matched by a [`WaveformTracker::discard_preload`]
+    pub fn preload(&mut self, vars: &[Var]) {
+        let signals: Vec<SignalRef> = vars.iter().map(|var| var.signal_ref()).collect();
+        self.acquire(&signals);
+    }
+
+    /// Let go of a [`WaveformTracker::preload`]. Data stays loaded while anything else,
+    /// including a selection, still holds it
+    pub fn discard_preload(&mut self, vars: &[Var]) {
+        let signals: Vec<SignalRef> = vars.iter().map(|var| var.signal_ref()).collect();
+        self.release(&signals);
+    }
+
+    /// Drop a reference on each of `signals`, unloading the ones nothing holds any more in a
+    /// single call
+    fn release(&mut self, signals: &[SignalRef]) {
+        let mut unused = Vec::new();
+        for &signal in signals {
+            let Some(refs) = self.load_refs.get_mut(&signal) else {
+                log::warn!("Letting go of a signal that isn't loaded");
+                continue;
+            };
+            *refs -= 1;
+            if *refs == 0 {
+                self.load_refs.remove(&signal);
+                unused.push(signal);
+            }
+        }
+        if !unused.is_empty() {
+            self.waveform.unload_signals(&unused);
+        }
+    }
+
+    /// Take a reference on each of `signals`, loading the ones nothing held yet in a single call
+    fn acquire(&mut self, signals: &[SignalRef]) {
+        let mut to_load = Vec::new();
+        for &signal in signals {
+            let refs = self.load_refs.entry(signal).or_insert(0);
+            *refs += 1;
+            if *refs == 1 && !to_load.contains(&signal) {
+                to_load.push(signal);
+            }
+        }
+        if !to_load.is_empty() {
+            self.counts.load_calls += 1;
+            self.waveform.load_signals(&to_load);
+        }
+    }
+
+    /// How many holders `var`'s data has, 0 if it isn't loaded
+    pub fn load_refs(&self, var: &Var) -> usize {
+        self.load_refs.get(&var.signal_ref()).copied().unwrap_or(0)
+    }
+
+    /// The simulation time at `timetableidx`, in [`WaveformTracker::get_scale_factor`]'s unit.
+    /// `None` past the end of the time table
+    pub fn get_current_time(&self, timetableidx: TimeTableIdx) -> Option<Time> {
         self.waveform
             .time_table()
             .get(timetableidx as usize)
             .copied()
-            .unwrap_or(0)
     }
 
-    pub fn get_scale_factor(&self, _var: Var) -> &'static str {
-        "ps"
+    /// How many time indices the waveform has
+    pub fn time_table_len(&self) -> usize {
+        self.waveform.time_table().len()
+    }
+
+    /// The last time index at or before `time`, `None` if `time` is before the trace starts
+    pub fn index_at_time(&self, time: Time) -> Option<TimeTableIdx> {
+        let after = self.waveform.time_table().partition_point(|&t| t <= time);
+        after.checked_sub(1).map(|idx| idx as TimeTableIdx)
+    }
+
+    /// The waveform's timescale, `ps` or `10ns`, `None` if its header doesn't give one
+    pub fn get_scale_factor(&self) -> Option<String> {
+        self.waveform
+            .hierarchy()
+            .timescale()
+            .and_then(|timescale| waveloader::timescale_unit(&timescale))
     }
 
-    pub fn get_values(&self, idx: TimeTableIdx) -> Vec<String> {
+    /// The value of every selected signal at `idx`, in selection order. Asking for the same
+    /// index again reuses the last pass, and one a warm-up got to first is taken from there
+    pub fn get_values(&mut self, idx: TimeTableIdx) -> Vec<SignalValueResult> {
+        if let Some((cached_idx, values)) = &self.value_cache {
+            if *cached_idx == idx {
+                return values.clone();
+            }
+        }
+        let values = match self.prefetched.get(&idx) {
+            Some(values) => {
+                self.counts.prefetch_hits += 1;
+                values.clone()
+            }
+            None => {
+                self.counts.prefetch_misses += 1;
+                let start = Instant::now();
+                let values = self.value_pass(idx);
+                self.counts.fetch_time += start.elapsed();
+                values
+            }
+        };
+        self.value_cache = Some((idx, values.clone()));
+        self.prefetch_pending = Some(idx);
+        values
+    }
+
+    fn value_pass(&mut self, idx: TimeTableIdx) -> Vec<SignalValueResult> {
+        self.counts.value_passes += 1;
         self.selected_var_order
             .iter()
-            .map(|v| self.waveform.get_signal(v.var.signal_ref()))
-            .map(|s| s.map(|sig| sig.get_val(idx)))
-            .map(|s| s.map(|val| val.to_bit_string().map(bitstring_to_hex)))
-            .flatten()
-            .map(|v| v.unwrap_or("Could not get value".to_string()))
+            .map(|v| self.value_of(v, idx))
             .collect()
     }
 
+    /// Work out the values at the change indices around where the last value pass was, so
+    /// moving to one of them is a lookup. Meant for when nothing else is going on: it does
+    /// nothing unless a value pass has been asked for since the last warm-up, or with
+    /// prefetching off. Returns how many indices it worked out
+    pub fn prefetch(&mut self) -> usize {
+        let (Some(span), Some(idx)) = (self.prefetch_span, self.prefetch_pending.take()) else {
+            return 0;
+        };
+        let signals = self
+            .selected_var_order
+            .iter()
+            .filter_map(|var| self.waveform.get_signal(var.signal))
+            .map(|signal| signal.time_indices());
+        let neighbors = neighbor_indices(signals, idx, span);
+
+        // only ever the values around the cursor, anything further away goes
+        self.prefetched.retain(|idx, _| neighbors.contains(idx));
+        self.counts.prefetch_runs += 1;
+        let mut worked_out = 0;
+        for neighbor in neighbors {
+            if !self.prefetched.contains_key(&neighbor) {
+                let values = self.value_pass(neighbor);
+                self.prefetched.insert(neighbor, values);
+                self.counts.prefetched_passes += 1;
+                worked_out += 1;
+            }
+        }
+        worked_out
+    }
+
+    /// Warm up `span` change indices either side of the cursor, `None` to stop prefetching
+    pub fn set_prefetch_span(&mut self, span: Option<usize>) {
+        self.prefetch_span = span;
+        if span.is_none() {
+            self.prefetch_pending = None;
+            self.prefetched.clear();
+        }
+    }
+
+    pub fn prefetch_span(&self) -> Option<usize> {
+        self.prefetch_span
+    }
+
+    /// Forget every value worked out so far, they no longer match what's selected or how it's
+    /// shown
+    fn invalidate_values(&mut self) {
+        self.value_cache = None;
+        self.prefetched.clear();
+    }
+
+    fn value_of(&self, var: &TrackerVar, idx: TimeTableIdx) -> SignalValueResult {
+        let signal = self
+            .waveform
+            .get_signal(var.signal)
+            .ok_or(ValueError::NotLoaded)?;
+        let value = signal.try_get_val(idx).ok_or(ValueError::Undriven)?;
+        let bits = value
+            .to_bit_string()
+            .ok_or_else(|| ValueError::Conversion(format!("{value:?}")))?;
+        Ok(match &var.value_names {
+            Some((names, format)) => {
+                named_value(bitstring_to_hex(&bits, var.width), names, *format)
+            }
+            None => format_bits(&bits, var.width, var.format),
+        })
+    }
+
+    /// Show the `var_index`th selected signal's values in `format`. Values with a name from
+    /// `sigenum` keep showing it. False if there's no such signal
+    pub fn set_format(&mut self, var_index: usize, format: FormattingType) -> bool {
+        let Some(var) = self.selected_var_order.get_mut(var_index) else {
+            return false;
+        };
+        if var.format != format {
+            var.format = format;
+            self.invalidate_values();
+        }
+        true
+    }
+
+    /// The radix of every selected signal, in selection order
+    pub fn get_formats(&self) -> Vec<FormattingType> {
+        self.selected_var_order.iter().map(|v| v.format).collect()
+    }
+
+    /// Show the `var_index`th selected signal's values by name from `names`. Returns the keys
+    /// it's too narrow to ever hold, `None` if there's no such signal
+    pub fn set_value_names(
+        &mut self,
+        var_index: usize,
+        names: HashMap<u64, String>,
+        format: NameFormat,
+    ) -> Option<Vec<u64>> {
+        let var = self.selected_var_order.get_mut(var_index)?;
+        let unreachable = out_of_range_keys(&names, var.width);
+        var.value_names = Some((names, format));
+        self.invalidate_values();
+        Some(unreachable)
+    }
+
+    /// Go back to plain values, in its radix, for the `var_index`th selected signal. False if it
+    /// had no names
+    pub fn clear_value_names(&mut self, var_index: usize) -> bool {
+        let cleared = self
+            .selected_var_order
+            .get_mut(var_index)
+            .and_then(|var| var.value_names.take())
+            .is_some();
+        if cleared {
+            self.invalidate_values();
+        }
+        cleared
+    }
+
+    /// Declared width of the `var_index`th selected signal, `None` for reals and strings
+    pub fn signal_width(&self, var_index: usize) -> Option<u32> {
+        self.selected_var_order.get(var_index)?.width
+    }
+
     pub fn get_signal_names(&self) -> Vec<String> {
         self.selected_var_order
             .iter()
-            .map(|v| v.var.full_name(self.waveform.hierarchy()))
+            .map(|v| v.name.clone())
             .collect()
     }
+
+    pub fn counts(&self) -> TrackerCounts {
+        self.counts
+    }
+
+    /// Change counts for the `var_index`th selected signal, over the whole trace and between
+    /// the two ends of `window`, both included and in either order. `None` if there's no such
+    /// signal or its data isn't loaded
+    pub fn signal_stats(
+        &self,
+        var_index: usize,
+        window: Option<(TimeTableIdx, TimeTableIdx)>,
+    ) -> Option<SignalStats> {
+        let var = self.selected_var_order.get(var_index)?;
+        let indices = self.waveform.get_signal(var.signal)?.time_indices();
+        let in_window = match window {
+            Some((a, b)) => count_between(indices, a.min(b), a.max(b)),
+            None => indices.len(),
+        };
+        Some(SignalStats {
+            total: indices.len(),
+            in_window,
+            first: indices.first().copied(),
+            last: indices.last().copied(),
+        })
+    }
+
+    /// Whether the `var_index`th selected signal changes after `from_idx` and at or before
+    /// `to_idx`. A change at `from_idx` is the value execution was already looking at, one at
+    /// `to_idx` the value it stopped on, so a signal that toggles and comes back in between
+    /// still counts. False for an empty or backwards range, or a signal that isn't selected or
+    /// loaded
+    pub fn changed_in_range(
+        &self,
+        var_index: usize,
+        from_idx: TimeTableIdx,
+        to_idx: TimeTableIdx,
+    ) -> bool {
+        self.selected_var_order
+            .get(var_index)
+            .and_then(|var| self.waveform.get_signal(var.signal))
+            .is_some_and(|signal| changed_between(signal.time_indices(), from_idx, to_idx))
+    }
 }
 
 #[cfg(test)]
@@ -149,6 +774,9 @@ mod tests {
 
         // Basic validation - ensure we get some matches
         assert!(!top_matches.is_empty(), "Should find matches for 'TOP'");
+        let (_, name) = &top_matches[0];
+        assert!(tracker.find_var(name).is_some());
+        assert!(tracker.find_var("TOP").is_none());
         assert!(!tt_matches.is_empty(), "Should find matches for 'TT'");
 
         // Verify that TOP matches contain signals with "top" somewhere in the name
@@ -180,22 +808,712 @@ mod tests {
         println!("Found {} matches for 'top'", top_lower_matches.len());
         println!("Found {} matches for 't'", t_matches.len());
     }
+
+    #[test]
+    fn test_value_error_rendering() {
+        assert_eq!(ValueError::NotLoaded.to_string(), "<not loaded>");
+        assert_eq!(ValueError::Undriven.to_string(), "<undriven>");
+        assert_eq!(
+            ValueError::Conversion("Real(1.5)".to_string()).to_string(),
+            "<unconvertible: Real(1.5)>"
+        );
+    }
+
+    #[test]
+    fn test_lookups_per_selection_not_refresh() {
+        let fst_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../test_data/ibex/sim.fst");
+        let mut tracker = WaveformTracker::new(fst_path).unwrap();
+        let vars: Vec<Var> = tracker
+            .fuzzy_match_var("pc_id")
+            .into_iter()
+            .take(3)
+            .map(|(var, _)| var)
+            .collect();
+        assert_eq!(vars.len(), 3);
+        for var in vars {
+            tracker.select_signal(var);
+        }
+        assert_eq!(tracker.counts().hierarchy_lookups, 3);
+
+        for refresh in 0..100u32 {
+            let values = tracker.get_values(refresh % 10 + 100);
+            assert_eq!(values.len(), 3);
+            assert_eq!(tracker.get_signal_names().len(), 3);
+        }
+        assert_eq!(tracker.counts().hierarchy_lookups, 3);
+        // a pass per index change, every refresh here moves the cursor
+        assert_eq!(tracker.counts().value_passes, 100);
+
+        // a refresh at the same index formats nothing
+        tracker.get_values(105);
+        tracker.get_values(105);
+        assert_eq!(tracker.counts().value_passes, 101);
+    }
+
+    fn ibex_tracker() -> WaveformTracker {
+        let fst_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../test_data/ibex/sim.fst");
+        WaveformTracker::new(fst_path).unwrap()
+    }
+
+    fn matching_vars(tracker: &mut WaveformTracker, query: &str, count: usize) -> Vec<Var> {
+        let vars: Vec<Var> = tracker
+            .fuzzy_match_var(query)
+            .into_iter()
+            .take(count)
+            .map(|(var, _)| var)
+            .collect();
+        assert_eq!(vars.len(), count);
+        vars
+    }
+
+    #[test]
+    fn test_index_at_time() {
+        let tracker = ibex_tracker();
+        let last = tracker.time_table_len() as TimeTableIdx - 1;
+        let time = tracker.get_current_time(10).unwrap();
+        assert_eq!(tracker.index_at_time(time), Some(10));
+        assert_eq!(tracker.index_at_time(Time::MAX), Some(last));
+    }
+
+    #[test]
+    fn test_current_time_past_the_end() {
+        let tracker = ibex_tracker();
+        let len = tracker.time_table_len() as TimeTableIdx;
+        assert!(tracker.get_current_time(len - 1).is_some());
+        assert_eq!(tracker.get_current_time(len), None);
+        assert_eq!(tracker.get_current_time(TimeTableIdx::MAX), None);
+    }
+
+    #[test]
+    fn test_scale_factor_from_the_header() {
+        let tracker = ibex_tracker();
+        let timescale = tracker.waveform.hierarchy().timescale().unwrap();
+        assert_eq!(
+            tracker.get_scale_factor(),
+            waveloader::timescale_unit(&timescale)
+        );
+        assert!(tracker.get_scale_factor().is_some());
+    }
+
+    #[test]
+    fn test_batch_select_loads_once() {
+        let mut tracker = ibex_tracker();
+        let vars = matching_vars(&mut tracker, "u_ibex_core", 10);
+
+        assert_eq!(tracker.select_signals(vars.clone()), 10);
+        assert_eq!(tracker.counts().load_calls, 1);
+        assert_eq!(tracker.get_signal_names().len(), 10);
+
+        // already selected, nothing to load or add
+        assert_eq!(tracker.select_signals(vars.clone()), 0);
+        tracker.select_signal(vars[0].clone());
+        assert_eq!(tracker.counts().load_calls, 1);
+        assert_eq!(tracker.get_signal_names().len(), 10);
+    }
+
+    #[test]
+    fn test_count_between() {
+        let indices = [3, 10, 11, 12, 40];
+        assert_eq!(count_between(&indices, 0, 100), 5);
+        assert_eq!(count_between(&indices, 10, 12), 3);
+        assert_eq!(count_between(&indices, 11, 11), 1);
+        assert_eq!(count_between(&indices, 13, 39), 0);
+        // a one index window without a change, and an upside down one
+        assert_eq!(count_between(&indices, 5, 5), 0);
+        assert_eq!(count_between(&indices, 12, 10), 0);
+        // before the first change and after the last
+        assert_eq!(count_between(&indices, 0, 2), 0);
+        assert_eq!(count_between(&indices, 41, 1000), 0);
+        assert_eq!(count_between(&[], 0, 1000), 0);
+    }
+
+    #[test]
+    fn test_changed_between() {
+        let indices = [3, 10, 11, 40];
+        // a change at the start is where execution already was, one at the end is where it
+        // stopped
+        assert!(!changed_between(&indices, 10, 10));
+        assert!(changed_between(&indices, 9, 10));
+        assert!(!changed_between(&indices, 11, 39));
+        assert!(changed_between(&indices, 11, 40));
+        assert!(changed_between(&indices, 0, 1000));
+        // an empty or backwards range, and past the last change
+        assert!(!changed_between(&indices, 12, 4));
+        assert!(!changed_between(&indices, 40, 1000));
+        assert!(!changed_between(&[], 0, 1000));
+    }
+
+    #[test]
+    fn test_changed_in_range() {
+        let mut tracker = ibex_tracker();
+        let vars = matching_vars(&mut tracker, "u_ibex_core", 10);
+        tracker.select_signals(vars);
+
+        let busiest = (0..10)
+            .max_by_key(|&i| tracker.signal_stats(i, None).unwrap().total)
+            .unwrap();
+        let indices = tracker
+            .waveform
+            .get_signal(tracker.selected_var_order[busiest].signal)
+            .unwrap()
+            .time_indices()
+            .to_vec();
+        let (a, b) = (indices[1], indices[2]);
+        assert!(tracker.changed_in_range(busiest, a, b));
+        assert!(!tracker.changed_in_range(busiest, a, a));
+        if b > a + 1 {
+            assert!(!tracker.changed_in_range(busiest, a, b - 1));
+        }
+        assert!(!tracker.changed_in_range(10, 0, b));
+    }
+
+    #[test]
+    fn test_neighbor_indices() {
+        let a: &[TimeTableIdx] = &[0, 4, 8, 12];
+        let b: &[TimeTableIdx] = &[2, 8, 20];
+        let neighbors = |idx, span| neighbor_indices([a, b].into_iter(), idx, span);
+        assert_eq!(neighbors(8, 2), [2, 4, 12, 20]);
+        assert_eq!(neighbors(7, 1), [4, 8]);
+        // the start of the trace has nothing before it, the end nothing after
+        assert_eq!(neighbors(0, 2), [2, 4]);
+        assert_eq!(neighbors(20, 2), [8, 12]);
+        assert_eq!(neighbors(100, 3), [8, 12, 20]);
+        assert!(neighbors(8, 0).is_empty());
+        assert!(neighbor_indices(std::iter::empty(), 8, 2).is_empty());
+    }
+
+    #[test]
+    fn test_prefetch_off_does_nothing() {
+        let mut tracker = ibex_tracker();
+        let vars = matching_vars(&mut tracker, "u_ibex_core", 3);
+        tracker.select_signals(vars);
+        tracker.set_prefetch_span(None);
+        for idx in 100..110 {
+            tracker.get_values(idx);
+            assert_eq!(tracker.prefetch(), 0);
+        }
+        let counts = tracker.counts();
+        assert_eq!(counts.prefetch_runs, 0);
+        assert_eq!(counts.prefetched_passes, 0);
+        assert_eq!(counts.prefetch_hits, 0);
+        assert_eq!(counts.value_passes, 10);
+    }
+
+    #[test]
+    fn test_prefetch_warms_neighbors() {
+        let mut tracker = ibex_tracker();
+        let vars = matching_vars(&mut tracker, "u_ibex_core", 3);
+        tracker.select_signals(vars);
+        // nothing asked for yet, nothing to warm up around
+        assert_eq!(tracker.prefetch(), 0);
+
+        let idx = 100;
+        tracker.get_values(idx);
+        let warmed = tracker.prefetch();
+        assert!(warmed > 0 && warmed <= 2 * DEFAULT_PREFETCH_SPAN);
+        // nothing new asked for since
+        assert_eq!(tracker.prefetch(), 0);
+
+        let signals = tracker
+            .selected_var_order
+            .iter()
+            .filter_map(|var| tracker.waveform.get_signal(var.signal))
+            .map(|signal| signal.time_indices());
+        let next = neighbor_indices(signals, idx, 1)
+            .into_iter()
+            .find(|&i| i > idx)
+            .unwrap();
+        let passes = tracker.counts().value_passes;
+        let warm = tracker.get_values(next);
+        assert_eq!(tracker.counts().prefetch_hits, 1);
+        assert_eq!(tracker.counts().value_passes, passes);
+
+        // the same values as working them out then and there
+        tracker.set_prefetch_span(None);
+        tracker.get_values(idx);
+        assert_eq!(tracker.get_values(next), warm);
+        assert_eq!(tracker.counts().prefetch_hits, 1);
+    }
+
+    fn ctrl_names() -> HashMap<u64, String> {
+        HashMap::from([
+            (0, "RESET".to_string()),
+            (3, "FLUSH".to_string()),
+            (16, "WAIT_SLEEP".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_named_value() {
+        let names = ctrl_names();
+        let both = NameFormat::NameAndValue;
+        assert_eq!(named_value("3".to_string(), &names, both), "FLUSH (0x3)");
+        assert_eq!(
+            named_value("0000_0010".to_string(), &names, both),
+            "WAIT_SLEEP (0x10)"
+        );
+        assert_eq!(
+            named_value("03".to_string(), &names, NameFormat::Name),
+            "FLUSH"
+        );
+        // no name, the hex stays and gets marked
+        assert_eq!(named_value("5".to_string(), &names, both), "5 ?");
+        // x and z bits aren't any key
+        assert_eq!(named_value("x".to_string(), &names, both), "x");
+        assert_eq!(named_value("Z3".to_string(), &names, both), "Z3");
+        // nor is anything past 64 bits
+        let wide = "1_00000000_00000003".to_string();
+        assert_eq!(named_value(wide.clone(), &names, both), wide);
+    }
+
+    #[test]
+    fn test_out_of_range_keys() {
+        let names = ctrl_names();
+        assert_eq!(out_of_range_keys(&names, Some(4)), [16]);
+        assert_eq!(out_of_range_keys(&names, Some(1)), [3, 16]);
+        assert!(out_of_range_keys(&names, Some(5)).is_empty());
+        assert!(out_of_range_keys(&names, Some(64)).is_empty());
+        assert!(out_of_range_keys(&names, None).is_empty());
+    }
+
+    #[test]
+    fn test_value_names() {
+        let mut tracker = ibex_tracker();
+        let vars = matching_vars(&mut tracker, "u_ibex_core", 10);
+        tracker.select_signals(vars);
+        let idx = tracker.time_table_len() as TimeTableIdx - 1;
+        let values = tracker.get_values(idx);
+        // the first signal with a plain value at the end of the trace
+        let (named, hex, value) = values
+            .iter()
+            .enumerate()
+            .find_map(|(i, hex)| {
+                let hex = hex.as_ref().ok()?;
+                let value = u64::from_str_radix(&hex.replace('_', ""), 16).ok()?;
+                Some((i, hex.clone(), value))
+            })
+            .unwrap();
+
+        let names = HashMap::from([(value, "HERE".to_string())]);
+        assert!(tracker
+            .set_value_names(named, names, NameFormat::NameAndValue)
+            .is_some());
+        let renamed = tracker.get_values(idx);
+        assert_eq!(renamed[named], Ok(format!("HERE (0x{value:x})")));
+        // only the signal the names were given to
+        for (i, (before, after)) in values.iter().zip(&renamed).enumerate() {
+            if i != named {
+                assert_eq!(before, after);
+            }
+        }
+
+        assert!(tracker.clear_value_names(named));
+        assert_eq!(tracker.get_values(idx)[named], Ok(hex));
+        assert!(!tracker.clear_value_names(named));
+        assert!(tracker
+            .set_value_names(10, HashMap::new(), NameFormat::Name)
+            .is_none());
+    }
+
+    /// A copy of the ibex waveform of its own, to rewrite
+    fn copied_wave(name: &str) -> PathBuf {
+        let fst_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../test_data/ibex/sim.fst");
+        let copy = std::env::temp_dir().join(format!("shucks_{}_{name}", std::process::id()));
+        std::fs::copy(fst_path, &copy).unwrap();
+        copy
+    }
+
+    #[test]
+    fn test_reload_keeps_the_selection() {
+        let path = copied_wave("reload.fst");
+        let mut tracker = WaveformTracker::new(path.clone()).unwrap();
+        let vars = matching_vars(&mut tracker, "u_ibex_core", 4);
+        tracker.select_signals(vars);
+        let names = HashMap::from([(0, "ZERO".to_string())]);
+        tracker.set_value_names(1, names, NameFormat::Name).unwrap();
+        let selected = tracker.get_signal_names();
+        let idx = tracker.time_table_len() as TimeTableIdx - 1;
+        let values = tracker.get_values(idx);
+        assert!(!tracker.is_stale());
+
+        // the simulation reran and wrote it again
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        assert!(tracker.is_stale());
+
+        assert_eq!(tracker.reload().unwrap(), Vec::<String>::new());
+        assert!(!tracker.is_stale());
+        assert_eq!(tracker.get_signal_names(), selected);
+        assert_eq!(tracker.get_values(idx), values);
+        assert!(tracker.clear_value_names(1));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_reload_drops_a_signal_that_went_away() {
+        let path = copied_wave("vanished.fst");
+        let mut tracker = WaveformTracker::new(path.clone()).unwrap();
+        let vars = matching_vars(&mut tracker, "u_ibex_core", 2);
+        tracker.select_signals(vars);
+        let first = &tracker.selected_var_order[0];
+        let gone = TrackerVar {
+            signal: first.signal,
+            name: "TOP.renamed_in_the_new_build".to_string(),
+            width: first.width,
+            value_names: None,
+            format: FormattingType::Hex,
+        };
+        tracker.selected_var_order.insert(1, gone);
+        let kept = [
+            tracker.selected_var_order[0].name.clone(),
+            tracker.selected_var_order[2].name.clone(),
+        ];
+
+        assert_eq!(tracker.reload().unwrap(), ["TOP.renamed_in_the_new_build"]);
+        assert_eq!(tracker.get_signal_names(), kept);
+
+        // nothing to read it from, and nothing changes
+        std::fs::remove_file(&path).unwrap();
+        assert!(tracker.is_stale());
+        assert!(tracker.reload().is_err());
+        assert_eq!(tracker.get_signal_names(), kept);
+    }
+
+    #[test]
+    fn test_signal_stats() {
+        let mut tracker = ibex_tracker();
+        let vars = matching_vars(&mut tracker, "u_ibex_core", 10);
+        tracker.select_signals(vars);
+        let last = tracker.time_table_len() as TimeTableIdx - 1;
+
+        let stats: Vec<SignalStats> = (0..10)
+            .map(|i| tracker.signal_stats(i, None).unwrap())
+            .collect();
+        for s in &stats {
+            assert!(s.total <= tracker.time_table_len());
+            assert_eq!(s.in_window, s.total);
+            assert!(s.first <= s.last);
+            assert!(s.last.unwrap_or(0) <= last);
+        }
+        // the core runs a program, something in it toggles
+        assert!(stats.iter().any(|s| s.total > 100), "{stats:?}");
+
+        let busiest = (0..10).max_by_key(|&i| stats[i].total).unwrap();
+        let whole = tracker.signal_stats(busiest, Some((last, 0))).unwrap();
+        assert_eq!(whole.in_window, whole.total);
+        let first = stats[busiest].first.unwrap();
+        let half = tracker
+            .signal_stats(busiest, Some((first, last / 2)))
+            .unwrap();
+        assert!(half.in_window > 0 && half.in_window < whole.total);
+        let before = tracker.signal_stats(busiest, Some((0, first.saturating_sub(1))));
+        assert!(first == 0 || before.unwrap().in_window == 0);
+
+        assert_eq!(tracker.signal_stats(10, None), None);
+    }
+
+    #[test]
+    fn test_wide_values_keep_their_width() {
+        let bits = |width: usize, value: &str| format!("{value:0>width$}");
+        assert_eq!(bitstring_to_hex("1", Some(1)), "1");
+        assert_eq!(bitstring_to_hex("0", Some(1)), "0");
+        assert_eq!(bitstring_to_hex(&bits(32, "10011"), Some(32)), "00000013");
+        assert_eq!(bitstring_to_hex(&bits(33, "1"), Some(33)), "0_00000001");
+        assert_eq!(
+            bitstring_to_hex(&format!("1{}", bits(32, "")), Some(33)),
+            "1_00000000"
+        );
+        assert_eq!(
+            bitstring_to_hex(&"1".repeat(64), Some(64)),
+            "ffffffff_ffffffff"
+        );
+        assert_eq!(
+            bitstring_to_hex(&bits(64, "1"), Some(64)),
+            "00000000_00000001"
+        );
+        assert_eq!(
+            bitstring_to_hex(&format!("1{}", bits(64, "")), Some(65)),
+            "1_00000000_00000000"
+        );
+        assert_eq!(
+            bitstring_to_hex(&bits(128, "1010"), Some(128)),
+            "00000000_00000000_00000000_0000000a"
+        );
+        // the declared width wins over however long the string is
+        assert_eq!(bitstring_to_hex("101", Some(32)), "00000005");
+        assert_eq!(
+            bitstring_to_hex(&format!("1{}", bits(32, "1")), Some(32)),
+            "00000001"
+        );
+        // without a width the string's own length is all there is
+        assert_eq!(bitstring_to_hex("10011", None), "13");
+    }
+
+    #[test]
+    fn test_x_and_z_digits() {
+        assert_eq!(bitstring_to_hex("x", Some(1)), "x");
+        assert_eq!(bitstring_to_hex("z", Some(1)), "z");
+        assert_eq!(bitstring_to_hex(&"x".repeat(32), Some(32)), "xxxxxxxx");
+        // part of a digit unknown
+        assert_eq!(bitstring_to_hex("0000000x", Some(8)), "0X");
+        assert_eq!(bitstring_to_hex("zzzz0000", Some(8)), "z0");
+        assert_eq!(bitstring_to_hex("zz01", Some(4)), "Z");
+        assert_eq!(bitstring_to_hex("zx01", Some(4)), "X");
+        // x extends to the declared width, past 64 bits too
+        assert_eq!(bitstring_to_hex("x", Some(8)), "xx");
+        assert_eq!(
+            bitstring_to_hex(&format!("{}1", "z".repeat(127)), Some(128)),
+            "zzzzzzzz_zzzzzzzz_zzzzzzzz_zzzzzzzZ"
+        );
+        assert_eq!(
+            bitstring_to_hex(&format!("x{}", "0".repeat(64)), Some(65)),
+            "x_00000000_00000000"
+        );
+    }
+
+    #[test]
+    fn test_radix_formatting() {
+        use FormattingType::*;
+        let show = |bits: &str, width: u32, format| format_bits(bits, Some(width), format);
+        assert_eq!(show("11111110", 8, Hex), "fe");
+        assert_eq!(show("11111110", 8, Decimal), "254");
+        assert_eq!(show("11111110", 8, SignedDecimal), "-2");
+        assert_eq!(show("11111110", 8, Binary), "1111_1110");
+        assert_eq!(show("10000000", 8, SignedDecimal), "-128");
+        assert_eq!(show("01111111", 8, SignedDecimal), "127");
+        // the sign bit is the declared width's, not the string's
+        assert_eq!(show("1", 32, SignedDecimal), "1");
+        assert_eq!(show("111", 2, SignedDecimal), "-1");
+        assert_eq!(show("101", 6, Binary), "00_0101");
+        assert_eq!(show(&"1".repeat(128), 128, SignedDecimal), "-1");
+        assert_eq!(show(&"1".repeat(128), 128, Decimal), u128::MAX.to_string());
+        assert_eq!(
+            show(&format!("1{}", "0".repeat(127)), 128, SignedDecimal),
+            i128::MIN.to_string()
+        );
+        // x and z bits have no number, too wide for one stays hex
+        assert_eq!(show("0000000x", 8, Decimal), "0000_000x");
+        assert_eq!(show("z", 4, SignedDecimal), "zzzz");
+        assert_eq!(
+            show(&"1".repeat(129), 129, Decimal),
+            "1_ffffffff_ffffffff_ffffffff_ffffffff"
+        );
+
+        for format in [Hex, Decimal, Binary, SignedDecimal] {
+            assert_eq!(FormattingType::from_name(format.name()), Some(format));
+        }
+        assert_eq!(FormattingType::from_name("oct"), None);
+    }
+
+    #[test]
+    fn test_switching_radix_of_a_selected_signal() {
+        let mut tracker = ibex_tracker();
+        let vars = matching_vars(&mut tracker, "u_ibex_core", 10);
+        tracker.select_signals(vars);
+        let idx = tracker.time_table_len() as TimeTableIdx - 1;
+        let values = tracker.get_values(idx);
+        let (i, value) = values
+            .iter()
+            .enumerate()
+            .find_map(|(i, hex)| {
+                let value = u64::from_str_radix(&hex.as_ref().ok()?.replace('_', ""), 16).ok()?;
+                Some((i, value))
+            })
+            .unwrap();
+        assert_eq!(tracker.get_formats(), vec![FormattingType::Hex; 10]);
+
+        assert!(tracker.set_format(i, FormattingType::Decimal));
+        let decimal = tracker.get_values(idx);
+        assert_eq!(decimal[i], Ok(value.to_string()));
+        assert_eq!(tracker.get_formats()[i], FormattingType::Decimal);
+        // only the signal it was set for
+        for (j, (before, after)) in values.iter().zip(&decimal).enumerate() {
+            if j != i {
+                assert_eq!(before, after);
+            }
+        }
+
+        assert!(tracker.set_format(i, FormattingType::Binary));
+        let binary = tracker.get_values(idx)[i].clone().unwrap();
+        assert_eq!(u64::from_str_radix(&binary.replace('_', ""), 2), Ok(value));
+        assert!(tracker.set_format(i, FormattingType::Hex));
+        assert_eq!(tracker.get_values(idx), values);
+        assert!(!tracker.set_format(10, FormattingType::Decimal));
+    }
+
+    #[test]
+    fn test_preload_refcounts() {
+        let mut tracker = ibex_tracker();
+        let kept = matching_vars(&mut tracker, "pc_id", 1).remove(0);
+        let (dropped, _) = tracker
+            .fuzzy_match_var("u_ibex_core")
+            .into_iter()
+            .find(|(var, _)| var.signal_ref() != kept.signal_ref())
+            .unwrap();
+
+        // preview then select: the selection keeps the data after the preview goes
+        tracker.preload(std::slice::from_ref(&kept));
+        assert_eq!(tracker.load_refs(&kept), 1);
+        tracker.select_signal(kept.clone());
+        assert_eq!(tracker.load_refs(&kept), 2);
+        assert_eq!(tracker.counts().load_calls, 1);
+        tracker.discard_preload(std::slice::from_ref(&kept));
+        assert_eq!(tracker.load_refs(&kept), 1);
+        assert!(tracker.waveform.get_signal(kept.signal_ref()).is_some());
+
+        // preview then cancel: nothing holds the data any more
+        tracker.preload(std::slice::from_ref(&dropped));
+        assert_eq!(tracker.load_refs(&dropped), 1);
+        assert_eq!(tracker.counts().load_calls, 2);
+        tracker.discard_preload(std::slice::from_ref(&dropped));
+        assert_eq!(tracker.load_refs(&dropped), 0);
+        assert!(tracker.waveform.get_signal(dropped.signal_ref()).is_none());
+    }
+
+    #[test]
+    fn test_deselect_the_middle_signal() {
+        let mut tracker = ibex_tracker();
+        let vars = matching_vars(&mut tracker, "u_ibex_core", 3);
+        tracker.select_signals(vars.clone());
+        let names = tracker.get_signal_names();
+        let idx = tracker.time_table_len() as TimeTableIdx - 1;
+        let values = tracker.get_values(idx);
+
+        assert!(tracker.deselect_signal(&names[1]));
+        assert_eq!(
+            tracker.get_signal_names(),
+            [names[0].clone(), names[2].clone()]
+        );
+        assert_eq!(
+            tracker.get_values(idx),
+            [values[0].clone(), values[2].clone()]
+        );
+        // its data goes unless one of the others is an alias of it
+        let shared = |i: usize| vars[i].signal_ref() == vars[1].signal_ref();
+        if !shared(0) && !shared(2) {
+            assert_eq!(tracker.load_refs(&vars[1]), 0);
+        }
+        assert!(!tracker.deselect_signal(&names[1]));
+        assert!(!tracker.deselect_signal("TOP.no_such_signal"));
+
+        // selecting it again puts it at the end
+        tracker.select_signal(vars[1].clone());
+        assert_eq!(
+            tracker.get_signal_names(),
+            [names[0].clone(), names[2].clone(), names[1].clone()]
+        );
+        assert_eq!(
+            tracker.get_values(idx),
+            [values[0].clone(), values[2].clone(), values[1].clone()]
+        );
+
+        assert_eq!(tracker.clear_signals(), 3);
+        assert!(tracker.get_signal_names().is_empty());
+        assert!(tracker.get_values(idx).is_empty());
+        for var in &vars {
+            assert_eq!(tracker.load_refs(var), 0);
+        }
+        assert_eq!(tracker.clear_signals(), 0);
+    }
+}
+
+/// Hex digits between `_` separators
+const HEX_GROUP: usize = 8;
+
+/// Binary digits between `_` separators, one hex digit's worth
+const BIN_GROUP: usize = 4;
+
+/// `bitstring` brought to `width` bits, extended or cut down on the left
+fn sized_bits(bitstring: &str, width: Option<u32>) -> Vec<u8> {
+    let bits = bitstring.as_bytes();
+    let width = width.map_or(bits.len(), |width| width as usize).max(1);
+    // x and z extend to the left like they do in Verilog, anything else with zeros
+    let fill = match bits.first() {
+        Some(bit @ (b'x' | b'X' | b'z' | b'Z')) => bit.to_ascii_lowercase(),
+        _ => b'0',
+    };
+    std::iter::repeat(fill)
+        .take(width.saturating_sub(bits.len()))
+        .chain(bits[bits.len().saturating_sub(width)..].iter().copied())
+        .collect()
+}
+
+/// `digits` with `_` between every `group` of them, counting from the right
+fn grouped(digits: impl ExactSizeIterator<Item = char>, group: usize) -> String {
+    let len = digits.len();
+    let mut out = String::with_capacity(len + len / group);
+    for (i, digit) in digits.enumerate() {
+        if i > 0 && (len - i) % group == 0 {
+            out.push('_');
+        }
+        out.push(digit);
+    }
+    out
 }
 
-fn bitstring_to_hex<S: AsRef<str>>(bitstring: S) -> String {
-    let bitstring = bitstring.as_ref();
-    // Check if the bitstring contains 'x' or 'z' values
-    if bitstring.contains('x')
-        || bitstring.contains('z')
-        || bitstring.contains('X')
-        || bitstring.contains('Z')
-    {
-        return bitstring.to_string();
+/// `bitstring` shown in `format`, at `width` bits like [`bitstring_to_hex`]. Binary is the bits
+/// themselves with `_` every 4. The decimals need every bit to be 0 or 1 and the value to fit
+/// in 128 bits: one with x or z bits is shown in binary instead, one that's too wide in hex
+fn format_bits(bitstring: &str, width: Option<u32>, format: FormattingType) -> String {
+    let bits = sized_bits(bitstring, width);
+    let binary = || grouped(bits.iter().map(|&bit| bit as char), BIN_GROUP);
+    let signed = match format {
+        FormattingType::Hex => return bitstring_to_hex(bitstring, width),
+        FormattingType::Binary => return binary(),
+        FormattingType::Decimal => false,
+        FormattingType::SignedDecimal => true,
+    };
+    if bits.iter().any(|bit| !matches!(bit, b'0' | b'1')) {
+        return binary();
+    }
+    if bits.len() > 128 {
+        return bitstring_to_hex(bitstring, width);
     }
+    let value = bits
+        .iter()
+        .fold(0u128, |value, &bit| (value << 1) | u128::from(bit == b'1'));
+    match signed && bits[0] == b'1' {
+        // the top bit's weight is negative, and 2^width - value is the magnitude
+        true => format!(
+            "-{}",
+            (!value).wrapping_add(1) & (u128::MAX >> (128 - bits.len()))
+        ),
+        false => value.to_string(),
+    }
+}
 
-    // Convert binary string to hexadecimal
-    match u64::from_str_radix(bitstring, 2) {
-        Ok(decimal) => format!("{:x}", decimal),
-        Err(_) => bitstring.to_string(), // Return original if conversion fails
+/// `bitstring`, most significant bit first, as hex. Digits are made straight from 4 bits at a
+/// time, so there's no limit on the width. It's brought to `width` bits first, extended or cut
+/// down on the left, which keeps leading zeros up to the declared width, and `_` goes between
+/// every 8 digits. A digit is `x` or `z` when all its bits are, `X` or `Z` when only some are
+fn bitstring_to_hex(bitstring: &str, width: Option<u32>) -> String {
+    let bits = sized_bits(bitstring, width);
+    grouped(bits.rchunks(4).rev().map(hex_digit), HEX_GROUP)
+}
+
+/// The hex digit for up to 4 bits, most significant first. Bits that aren't 0, 1 or z count
+/// as x
+fn hex_digit(bits: &[u8]) -> char {
+    let mut value = 0;
+    let (mut unknown, mut high_z) = (0, 0);
+    for &bit in bits {
+        value <<= 1;
+        match bit {
+            b'0' => {}
+            b'1' => value |= 1,
+            b'z' | b'Z' => high_z += 1,
+            _ => unknown += 1,
+        }
+    }
+    match (unknown, high_z) {
+        (0, 0) => char::from_digit(value, 16).unwrap_or('?'),
+        (0, n) if n == bits.len() => 'z',
+        (n, 0) if n == bits.len() => 'x',
+        (0, _) => 'Z',
+        _ => 'X',
     }
 }
@@ -0,0 +1,16 @@
+from pywellen import Waveform, Signal
+from typing import Dict
+
+
+def get_gdb_signals(wave: Waveform, args: Dict[str, str]) -> Dict[str, Signal]:
+    prefix = args["prefix"]
+    pc = wave.get_signal_from_path(f"{prefix}.u_ibex_core.wb_stage_i.pc_wb_o")
+    gprs = {
+        f"x{i}": wave.get_signal_from_path(
+            f"{prefix}.gen_regfile_ff.register_file_i.rf_reg.[{i}]"
+        ).sliced(0, 31)
+        for i in range(32)
+    }
+
+    rv = {"pc": pc, **gprs}
+    return rv
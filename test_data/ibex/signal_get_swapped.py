@@ -0,0 +1,22 @@
+from pywellen import Waveform, Signal
+from typing import Dict
+
+# signal_get.py with the paths for x0 and x5 swapped, for checking that dang notices
+
+REGFILE = "TOP.ibex_simple_system.u_top.u_ibex_top.gen_regfile_ff.register_file_i.rf_reg"
+SWAPPED = {0: 5, 5: 0}
+
+
+def get_gdb_signals(wave: Waveform) -> Dict[str, Signal]:
+    pc = wave.get_signal_from_path(
+        "TOP.ibex_simple_system.u_top.u_ibex_top.u_ibex_core.wb_stage_i.pc_wb_o"
+    )
+    gprs = {
+        f"x{i}": wave.get_signal_from_path(
+            f"{REGFILE}.[{SWAPPED.get(i, i)}]"
+        ).sliced(0, 31)
+        for i in range(32)
+    }
+
+    rv = {"pc": pc, **gprs}
+    return rv